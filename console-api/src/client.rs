@@ -0,0 +1,346 @@
+//! A high-level client for the instrument service.
+//!
+//! [`InstrumentClient`] only exposes the raw streams of updates, each of which
+//! contains just what changed since the one before it. [`ConsoleClient`]
+//! merges those updates into a [`Snapshot`] of everything the target has
+//! reported, which is a better starting point for building a custom UI.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use console_api::client::ConsoleClient;
+//!
+//! # async fn run() -> Result<(), console_api::client::Error> {
+//! let mut client = ConsoleClient::connect("http://127.0.0.1:6669").await?;
+//! loop {
+//!     let snapshot = client.next_snapshot().await?;
+//!     let running = snapshot
+//!         .tasks()
+//!         .values()
+//!         .filter(|task| task.stats.dropped_time().is_none())
+//!         .count();
+//!     println!("{} tasks running", running);
+//! }
+//! # }
+//! ```
+use std::{collections::HashMap, error, fmt, time::SystemTime};
+
+use tonic::{
+    transport::{Channel, Endpoint},
+    Streaming,
+};
+
+use crate::{
+    async_ops,
+    convenience::system_time,
+    instrument::{
+        instrument_client::InstrumentClient, InstrumentRequest, PauseRequest, ResumeRequest,
+        TaskDetailsRequest, Update,
+    },
+    resources, tasks, Id, MetaId, Metadata,
+};
+
+/// A connection to a target's instrument service, which keeps a [`Snapshot`]
+/// of its state up to date.
+#[derive(Debug)]
+pub struct ConsoleClient {
+    client: InstrumentClient<Channel>,
+    updates: Streaming<Update>,
+    /// The ID the target assigned to `updates`, which pause and resume
+    /// requests must refer to.
+    stream_id: u64,
+    snapshot: Snapshot,
+}
+
+/// Everything a target has reported so far, merged from its updates.
+#[derive(Clone, Debug, Default)]
+pub struct Snapshot {
+    now: Option<SystemTime>,
+    tasks: HashMap<u64, TaskEntry>,
+    resources: HashMap<u64, ResourceEntry>,
+    async_ops: HashMap<u64, AsyncOpEntry>,
+    metadata: HashMap<u64, Metadata>,
+    dropped_events: u64,
+}
+
+/// A task, resource or async op, along with its latest stats.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry<T, S> {
+    /// What the target reported when the entry was created.
+    pub info: T,
+    /// The most recently reported stats.
+    pub stats: S,
+}
+
+/// A task and its latest stats.
+pub type TaskEntry = Entry<tasks::Task, tasks::Stats>;
+/// A resource and its latest stats.
+pub type ResourceEntry = Entry<resources::Resource, resources::Stats>;
+/// An async op and its latest stats.
+pub type AsyncOpEntry = Entry<async_ops::AsyncOp, async_ops::Stats>;
+
+/// Errors returned by a [`ConsoleClient`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Connecting to the target failed.
+    Transport(tonic::transport::Error),
+    /// The target returned an error.
+    Status(tonic::Status),
+    /// The target closed the update stream.
+    Closed,
+}
+
+// === impl ConsoleClient ===
+
+impl ConsoleClient {
+    /// Connects to the target at `dst`, and waits for its first update.
+    pub async fn connect<D>(dst: D) -> Result<Self, Error>
+    where
+        D: TryInto<Endpoint>,
+        D::Error: Into<Box<dyn error::Error + Send + Sync + 'static>>,
+    {
+        let channel = Endpoint::new(dst)?.connect().await?;
+        Self::with_channel(channel).await
+    }
+
+    /// Starts watching updates over an already connected `channel`, and waits
+    /// for the first update.
+    ///
+    /// This can be used to connect with a custom connector, such as over a
+    /// Unix domain socket.
+    pub async fn with_channel(channel: Channel) -> Result<Self, Error> {
+        let mut client = InstrumentClient::new(channel);
+        let mut updates = client
+            .watch_updates(tonic::Request::new(InstrumentRequest::default()))
+            .await?
+            .into_inner();
+        // The first update with any state contains everything the target
+        // currently knows about, so the snapshot is complete as soon as it is
+        // applied. It may be preceded by updates carrying only metadata.
+        let mut snapshot = Snapshot::default();
+        let stream_id = loop {
+            let update = updates.message().await?.ok_or(Error::Closed)?;
+            let stream_id = update.stream_id;
+            let has_state = update.task_update.is_some()
+                || update.resource_update.is_some()
+                || update.async_op_update.is_some();
+            snapshot.apply(update);
+            if has_state {
+                break stream_id;
+            }
+        };
+        Ok(Self {
+            client,
+            updates,
+            stream_id,
+            snapshot,
+        })
+    }
+
+    /// Returns the current snapshot.
+    pub fn snapshot(&self) -> &Snapshot {
+        &self.snapshot
+    }
+
+    /// Waits for the next update, and returns the snapshot with it applied.
+    pub async fn next_snapshot(&mut self) -> Result<&Snapshot, Error> {
+        let update = self.updates.message().await?.ok_or(Error::Closed)?;
+        self.snapshot.apply(update);
+        Ok(&self.snapshot)
+    }
+
+    /// Asks the target to stop sending updates until [`resume`] is called.
+    ///
+    /// [`resume`]: Self::resume
+    pub async fn pause(&mut self) -> Result<(), Error> {
+        let request = tonic::Request::new(PauseRequest {
+            stream_id: self.stream_id,
+        });
+        self.client.pause(request).await?;
+        Ok(())
+    }
+
+    /// Asks the target to send updates again after [`pause`] was called.
+    ///
+    /// [`pause`]: Self::pause
+    pub async fn resume(&mut self) -> Result<(), Error> {
+        let request = tonic::Request::new(ResumeRequest {
+            stream_id: self.stream_id,
+        });
+        self.client.resume(request).await?;
+        Ok(())
+    }
+
+    /// Returns a stream of details, such as poll time histograms, for the
+    /// task with the given ID.
+    pub async fn watch_task_details(
+        &mut self,
+        id: impl Into<Id>,
+    ) -> Result<Streaming<tasks::TaskDetails>, Error> {
+        let request = tonic::Request::new(TaskDetailsRequest {
+            id: Some(id.into()),
+            histograms: None,
+        });
+        Ok(self.client.watch_task_details(request).await?.into_inner())
+    }
+
+    /// Returns the underlying client, for making requests which this type
+    /// doesn't wrap.
+    pub fn instrument_client(&mut self) -> &mut InstrumentClient<Channel> {
+        &mut self.client
+    }
+}
+
+// === impl Snapshot ===
+
+impl Snapshot {
+    /// Merges an update from the target into the snapshot.
+    ///
+    /// Entries which have been dropped are kept, so that their final stats
+    /// can still be shown, until [`clear_dropped`] is called.
+    ///
+    /// [`clear_dropped`]: Self::clear_dropped
+    pub fn apply(&mut self, update: Update) {
+        if let Some(now) = update.now.as_ref().and_then(system_time) {
+            self.now = Some(now);
+        }
+
+        if let Some(new_metadata) = update.new_metadata {
+            for new in new_metadata.metadata {
+                if let (Some(id), Some(metadata)) = (new.id, new.metadata) {
+                    self.metadata.insert(id.id, metadata);
+                }
+            }
+        }
+
+        if let Some(update) = update.task_update {
+            self.dropped_events += update.dropped_events;
+            merge(
+                &mut self.tasks,
+                update.new_tasks,
+                |task| task.id,
+                update.stats_update,
+            );
+        }
+
+        if let Some(update) = update.resource_update {
+            self.dropped_events += update.dropped_events;
+            merge(
+                &mut self.resources,
+                update.new_resources,
+                |resource| resource.id,
+                update.stats_update,
+            );
+        }
+
+        if let Some(update) = update.async_op_update {
+            self.dropped_events += update.dropped_events;
+            merge(
+                &mut self.async_ops,
+                update.new_async_ops,
+                |async_op| async_op.id,
+                update.stats_update,
+            );
+        }
+    }
+
+    /// Forgets the tasks, resources and async ops which have been dropped.
+    pub fn clear_dropped(&mut self) {
+        self.tasks
+            .retain(|_, task| task.stats.dropped_time().is_none());
+        self.resources
+            .retain(|_, resource| resource.stats.dropped_time().is_none());
+        self.async_ops
+            .retain(|_, async_op| async_op.stats.dropped_time().is_none());
+    }
+
+    /// Returns the time of the most recent update, as reported by the target.
+    pub fn now(&self) -> Option<SystemTime> {
+        self.now
+    }
+
+    /// Returns the tasks, by ID.
+    pub fn tasks(&self) -> &HashMap<u64, TaskEntry> {
+        &self.tasks
+    }
+
+    /// Returns the resources, by ID.
+    pub fn resources(&self) -> &HashMap<u64, ResourceEntry> {
+        &self.resources
+    }
+
+    /// Returns the async ops, by ID.
+    pub fn async_ops(&self) -> &HashMap<u64, AsyncOpEntry> {
+        &self.async_ops
+    }
+
+    /// Returns the metadata with the given ID, such as that of a task's span.
+    pub fn metadata(&self, id: &MetaId) -> Option<&Metadata> {
+        self.metadata.get(&id.id)
+    }
+
+    /// Returns the total number of events the target dropped because its
+    /// event buffer was full, which means the snapshot may be inaccurate.
+    pub fn dropped_events(&self) -> u64 {
+        self.dropped_events
+    }
+}
+
+/// Adds `new` entries to `entries`, then updates the stats of each entry in
+/// `stats`.
+///
+/// New entries are always sent along with their first stats, so stats for an
+/// ID which isn't known are ignored.
+fn merge<T, S: Default>(
+    entries: &mut HashMap<u64, Entry<T, S>>,
+    new: Vec<T>,
+    id: impl Fn(&T) -> Option<Id>,
+    mut stats: HashMap<u64, S>,
+) {
+    for info in new {
+        let Some(Id { id }) = id(&info) else {
+            continue;
+        };
+        let stats = stats.remove(&id).unwrap_or_default();
+        entries.insert(id, Entry { info, stats });
+    }
+    for (id, stats) in stats {
+        if let Some(entry) = entries.get_mut(&id) {
+            entry.stats = stats;
+        }
+    }
+}
+
+// === impl Error ===
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Transport(_) => f.write_str("failed to connect to the target"),
+            Error::Status(status) => write!(f, "the target returned an error: {}", status),
+            Error::Closed => f.write_str("the target closed the update stream"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Transport(error) => Some(error),
+            Error::Status(_) | Error::Closed => None,
+        }
+    }
+}
+
+impl From<tonic::transport::Error> for Error {
+    fn from(error: tonic::transport::Error) -> Self {
+        Error::Transport(error)
+    }
+}
+
+impl From<tonic::Status> for Error {
+    fn from(status: tonic::Status) -> Self {
+        Error::Status(status)
+    }
+}
@@ -0,0 +1,456 @@
+//! Helpers for working with the wire format from Rust.
+//!
+//! The generated message types mirror the protobuf definitions exactly, so
+//! timestamps and durations are [`prost_types`] values, and most fields are
+//! optional. This module adds accessors which return [`std::time`] types
+//! instead, and builders for the messages a client or a test is most likely
+//! to construct by hand.
+//!
+//! # Examples
+//!
+//! ```
+//! use console_api::{convenience::TaskStatsBuilder, tasks};
+//! use std::time::{Duration, SystemTime};
+//!
+//! let created = SystemTime::now();
+//! let stats: tasks::Stats = TaskStatsBuilder::new(created)
+//!     .polls(3)
+//!     .busy_time(Duration::from_millis(5))
+//!     .build();
+//!
+//! assert_eq!(stats.created_time(), Some(created));
+//! assert_eq!(stats.busy_duration(), Some(Duration::from_millis(5)));
+//! assert_eq!(stats.dropped_time(), None);
+//! ```
+use std::{
+    error, fmt,
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    async_ops, field, instrument, resources, tasks, Field, Id, Location, MetaId, PollStats,
+    RegisterMetadata, SpanId,
+};
+
+/// Converts a protobuf timestamp into a [`SystemTime`].
+///
+/// Returns `None` if the timestamp is outside of the range a `SystemTime`
+/// can represent on this platform.
+pub fn system_time(timestamp: &prost_types::Timestamp) -> Option<SystemTime> {
+    SystemTime::try_from(*timestamp).ok()
+}
+
+/// Converts a protobuf duration into a [`Duration`].
+///
+/// Returns `None` if the duration is negative.
+pub fn duration(duration: &prost_types::Duration) -> Option<Duration> {
+    Duration::try_from(*duration).ok()
+}
+
+/// Converts a [`Duration`] into a protobuf duration, saturating at the
+/// largest duration protobuf can represent.
+pub fn pb_duration(duration: Duration) -> prost_types::Duration {
+    prost_types::Duration::try_from(duration).unwrap_or(prost_types::Duration {
+        seconds: i64::MAX,
+        nanos: 999_999_999,
+    })
+}
+
+// === IDs ===
+
+impl Id {
+    /// Returns an ID with the given value.
+    pub const fn new(id: u64) -> Self {
+        Id { id }
+    }
+
+    /// Returns the ID's value.
+    pub const fn as_u64(&self) -> u64 {
+        self.id
+    }
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.id, f)
+    }
+}
+
+impl SpanId {
+    /// Returns the span ID's value.
+    pub const fn as_u64(&self) -> u64 {
+        self.id
+    }
+}
+
+/// The error returned when converting an [`Id`] of zero into a
+/// [`tracing_core::span::Id`], which must be non-zero.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ZeroIdError(());
+
+impl fmt::Display for ZeroIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("span IDs must be non-zero")
+    }
+}
+
+impl error::Error for ZeroIdError {}
+
+impl TryFrom<Id> for tracing_core::span::Id {
+    type Error = ZeroIdError;
+
+    fn try_from(id: Id) -> Result<Self, Self::Error> {
+        std::num::NonZeroU64::new(id.id)
+            .map(tracing_core::span::Id::from_non_zero_u64)
+            .ok_or(ZeroIdError(()))
+    }
+}
+
+// === Accessors ===
+
+impl PollStats {
+    /// Returns when the first poll started.
+    pub fn first_poll_time(&self) -> Option<SystemTime> {
+        self.first_poll.as_ref().and_then(system_time)
+    }
+
+    /// Returns when the most recent poll started.
+    pub fn last_poll_started_time(&self) -> Option<SystemTime> {
+        self.last_poll_started.as_ref().and_then(system_time)
+    }
+
+    /// Returns when the most recent poll ended.
+    pub fn last_poll_ended_time(&self) -> Option<SystemTime> {
+        self.last_poll_ended.as_ref().and_then(system_time)
+    }
+
+    /// Returns the total time spent being polled.
+    pub fn busy_duration(&self) -> Option<Duration> {
+        self.busy_time.as_ref().and_then(duration)
+    }
+}
+
+impl tasks::Stats {
+    /// Returns when the task was spawned.
+    pub fn created_time(&self) -> Option<SystemTime> {
+        self.created_at.as_ref().and_then(system_time)
+    }
+
+    /// Returns when the task was dropped, if it has completed.
+    pub fn dropped_time(&self) -> Option<SystemTime> {
+        self.dropped_at.as_ref().and_then(system_time)
+    }
+
+    /// Returns when the task was last woken.
+    pub fn last_wake_time(&self) -> Option<SystemTime> {
+        self.last_wake.as_ref().and_then(system_time)
+    }
+
+    /// Returns the number of times the task has been polled.
+    pub fn polls(&self) -> u64 {
+        self.poll_stats.as_ref().map_or(0, |stats| stats.polls)
+    }
+
+    /// Returns the total time the task has spent being polled.
+    pub fn busy_duration(&self) -> Option<Duration> {
+        self.poll_stats.as_ref()?.busy_duration()
+    }
+
+    /// Returns the total time the task has spent waiting to be polled after
+    /// being woken.
+    pub fn scheduled_duration(&self) -> Option<Duration> {
+        self.scheduled_time.as_ref().and_then(duration)
+    }
+
+    /// Returns the time spent instrumenting the task, if the subscriber
+    /// measures it.
+    pub fn overhead_duration(&self) -> Option<Duration> {
+        self.instrumentation_overhead.as_ref().and_then(duration)
+    }
+
+    /// Returns how long the task has existed for, as of `now`, or until it
+    /// was dropped.
+    pub fn total_duration(&self, now: SystemTime) -> Option<Duration> {
+        let end = self.dropped_time().unwrap_or(now);
+        end.duration_since(self.created_time()?).ok()
+    }
+}
+
+impl resources::Stats {
+    /// Returns when the resource was created.
+    pub fn created_time(&self) -> Option<SystemTime> {
+        self.created_at.as_ref().and_then(system_time)
+    }
+
+    /// Returns when the resource was dropped, if it has been.
+    pub fn dropped_time(&self) -> Option<SystemTime> {
+        self.dropped_at.as_ref().and_then(system_time)
+    }
+}
+
+impl async_ops::Stats {
+    /// Returns when the async op was created.
+    pub fn created_time(&self) -> Option<SystemTime> {
+        self.created_at.as_ref().and_then(system_time)
+    }
+
+    /// Returns when the async op was dropped, if it has been.
+    pub fn dropped_time(&self) -> Option<SystemTime> {
+        self.dropped_at.as_ref().and_then(system_time)
+    }
+
+    /// Returns the total time the async op has spent being polled.
+    pub fn busy_duration(&self) -> Option<Duration> {
+        self.poll_stats.as_ref()?.busy_duration()
+    }
+}
+
+impl instrument::Update {
+    /// Returns the time at which the update was sent.
+    pub fn now_time(&self) -> Option<SystemTime> {
+        self.now.as_ref().and_then(system_time)
+    }
+}
+
+impl tasks::TaskDetails {
+    /// Returns the time at which the details were sent.
+    pub fn now_time(&self) -> Option<SystemTime> {
+        self.now.as_ref().and_then(system_time)
+    }
+}
+
+// === Builders ===
+
+/// Builds a [`tasks::Task`].
+#[derive(Clone, Debug)]
+pub struct TaskBuilder {
+    task: tasks::Task,
+}
+
+impl TaskBuilder {
+    /// Returns a builder for a task with the given ID and metadata.
+    pub fn new(id: impl Into<Id>, metadata: MetaId) -> Self {
+        Self {
+            task: tasks::Task {
+                id: Some(id.into()),
+                metadata: Some(metadata),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Sets the kind of task.
+    pub fn kind(mut self, kind: tasks::task::Kind) -> Self {
+        self.task.set_kind(kind);
+        self
+    }
+
+    /// Adds a field to the task, such as its name.
+    pub fn field(mut self, name: &str, value: impl Into<field::Value>) -> Self {
+        self.task.fields.push(Field {
+            metadata_id: self.task.metadata,
+            name: Some(name.into()),
+            value: Some(value.into()),
+        });
+        self
+    }
+
+    /// Sets the location the task was spawned at.
+    pub fn location(mut self, location: impl Into<Location>) -> Self {
+        self.task.location = Some(location.into());
+        self
+    }
+
+    /// Adds a span which the task was spawned within.
+    pub fn parent(mut self, parent: impl Into<SpanId>) -> Self {
+        self.task.parents.push(parent.into());
+        self
+    }
+
+    /// Sets the runtime the task was spawned on.
+    pub fn runtime(mut self, runtime: impl Into<String>) -> Self {
+        self.task.runtime = Some(runtime.into());
+        self
+    }
+
+    /// Returns the task.
+    pub fn build(self) -> tasks::Task {
+        self.task
+    }
+}
+
+/// Builds a task's [`tasks::Stats`].
+#[derive(Clone, Debug)]
+pub struct TaskStatsBuilder {
+    stats: tasks::Stats,
+}
+
+impl TaskStatsBuilder {
+    /// Returns a builder for the stats of a task spawned at `created_at`,
+    /// which has not yet been polled or woken.
+    pub fn new(created_at: SystemTime) -> Self {
+        Self {
+            stats: tasks::Stats {
+                created_at: Some(created_at.into()),
+                poll_stats: Some(PollStats::default()),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Sets when the task was dropped.
+    pub fn dropped_at(mut self, dropped_at: SystemTime) -> Self {
+        self.stats.dropped_at = Some(dropped_at.into());
+        self
+    }
+
+    /// Sets the number of times the task was woken, and when it was last
+    /// woken.
+    pub fn wakes(mut self, wakes: u64, last_wake: SystemTime) -> Self {
+        self.stats.wakes = wakes;
+        self.stats.last_wake = Some(last_wake.into());
+        self
+    }
+
+    /// Sets the number of times the task woke itself.
+    pub fn self_wakes(mut self, self_wakes: u64) -> Self {
+        self.stats.self_wakes = self_wakes;
+        self
+    }
+
+    /// Sets the number of times the task's waker was cloned and dropped.
+    pub fn wakers(mut self, clones: u64, drops: u64) -> Self {
+        self.stats.waker_clones = clones;
+        self.stats.waker_drops = drops;
+        self
+    }
+
+    /// Sets the number of times the task was polled.
+    pub fn polls(mut self, polls: u64) -> Self {
+        self.poll_stats().polls = polls;
+        self
+    }
+
+    /// Sets when the task was first polled.
+    pub fn first_poll(mut self, at: SystemTime) -> Self {
+        self.poll_stats().first_poll = Some(at.into());
+        self
+    }
+
+    /// Sets when the task's most recent poll started and, if it is not
+    /// currently being polled, ended.
+    pub fn last_poll(mut self, started: SystemTime, ended: Option<SystemTime>) -> Self {
+        let poll_stats = self.poll_stats();
+        poll_stats.last_poll_started = Some(started.into());
+        poll_stats.last_poll_ended = ended.map(Into::into);
+        self
+    }
+
+    /// Sets the total time the task spent being polled.
+    pub fn busy_time(mut self, busy_time: Duration) -> Self {
+        self.poll_stats().busy_time = Some(pb_duration(busy_time));
+        self
+    }
+
+    /// Sets the total time the task spent waiting to be polled after being
+    /// woken.
+    pub fn scheduled_time(mut self, scheduled_time: Duration) -> Self {
+        self.stats.scheduled_time = Some(pb_duration(scheduled_time));
+        self
+    }
+
+    /// Returns the stats.
+    pub fn build(self) -> tasks::Stats {
+        self.stats
+    }
+
+    fn poll_stats(&mut self) -> &mut PollStats {
+        self.stats.poll_stats.get_or_insert_with(Default::default)
+    }
+}
+
+/// Builds an [`instrument::Update`].
+#[derive(Clone, Debug)]
+pub struct UpdateBuilder {
+    update: instrument::Update,
+}
+
+impl UpdateBuilder {
+    /// Returns a builder for an update sent at `now`, which contains no
+    /// changes.
+    pub fn new(now: SystemTime) -> Self {
+        Self {
+            update: instrument::Update {
+                now: Some(now.into()),
+                task_update: Some(Default::default()),
+                resource_update: Some(Default::default()),
+                async_op_update: Some(Default::default()),
+                new_metadata: Some(Default::default()),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Adds a newly spawned task, and its stats.
+    pub fn new_task(mut self, task: tasks::Task, stats: tasks::Stats) -> Self {
+        let id = task.id.as_ref().map_or(0, Id::as_u64);
+        let update = self.update.task_update.get_or_insert_with(Default::default);
+        update.new_tasks.push(task);
+        update.stats_update.insert(id, stats);
+        self
+    }
+
+    /// Adds the updated stats of a task.
+    pub fn task_stats(mut self, id: impl Into<Id>, stats: tasks::Stats) -> Self {
+        self.update
+            .task_update
+            .get_or_insert_with(Default::default)
+            .stats_update
+            .insert(id.into().id, stats);
+        self
+    }
+
+    /// Adds a newly created resource, and its stats.
+    pub fn new_resource(mut self, resource: resources::Resource, stats: resources::Stats) -> Self {
+        let id = resource.id.as_ref().map_or(0, Id::as_u64);
+        let update = self
+            .update
+            .resource_update
+            .get_or_insert_with(Default::default);
+        update.new_resources.push(resource);
+        update.stats_update.insert(id, stats);
+        self
+    }
+
+    /// Adds the updated stats of a resource.
+    pub fn resource_stats(mut self, id: impl Into<Id>, stats: resources::Stats) -> Self {
+        self.update
+            .resource_update
+            .get_or_insert_with(Default::default)
+            .stats_update
+            .insert(id.into().id, stats);
+        self
+    }
+
+    /// Adds metadata which tasks, resources and async ops in this or later
+    /// updates refer to.
+    pub fn metadata(mut self, metadata: impl Into<crate::register_metadata::NewMetadata>) -> Self {
+        self.update
+            .new_metadata
+            .get_or_insert_with(RegisterMetadata::default)
+            .metadata
+            .push(metadata.into());
+        self
+    }
+
+    /// Sets the update's sequence number.
+    pub fn sequence(mut self, sequence: u64) -> Self {
+        self.update.sequence = sequence;
+        self
+    }
+
+    /// Returns the update.
+    pub fn build(self) -> instrument::Update {
+        self.update
+    }
+}
@@ -3,6 +3,8 @@
 ///
 /// This includes a list of any new async ops, and updates to the associated statistics
 /// for any async ops that have changed since the last update.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct AsyncOpUpdate {
     /// A list of new async operations that were created since the last `AsyncOpUpdate`
@@ -32,6 +34,8 @@ pub struct AsyncOpUpdate {
 /// An async operation is an operation that is associated with a resource
 /// This could, for example, be a read or write on a TCP stream, or a receive operation on
 /// a channel.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct AsyncOp {
     /// The async op's ID.
@@ -66,13 +70,17 @@ pub struct AsyncOp {
     pub resource_id: ::core::option::Option<super::common::Id>,
 }
 /// Statistics associated with a given async operation.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Stats {
     /// Timestamp of when the async op has been created.
     #[prost(message, optional, tag = "1")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::timestamp"))]
     pub created_at: ::core::option::Option<::prost_types::Timestamp>,
     /// Timestamp of when the async op was dropped.
     #[prost(message, optional, tag = "2")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::timestamp"))]
     pub dropped_at: ::core::option::Option<::prost_types::Timestamp>,
     /// The Id of the task that is awaiting on this op.
     #[prost(message, optional, tag = "4")]
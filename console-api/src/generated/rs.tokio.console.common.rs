@@ -1,5 +1,7 @@
 // This file is @generated by prost-build.
 /// Unique identifier for each task.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
 pub struct Id {
     /// The unique identifier's concrete value.
@@ -7,6 +9,8 @@ pub struct Id {
     pub id: u64,
 }
 /// A Rust source code location.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Location {
     /// The file path
@@ -23,6 +27,8 @@ pub struct Location {
     pub column: ::core::option::Option<u32>,
 }
 /// Unique identifier for metadata.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
 pub struct MetaId {
     /// The unique identifier's concrete value.
@@ -30,6 +36,8 @@ pub struct MetaId {
     pub id: u64,
 }
 /// Unique identifier for spans.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
 pub struct SpanId {
     /// The unique identifier's concrete value.
@@ -37,6 +45,8 @@ pub struct SpanId {
     pub id: u64,
 }
 /// A message representing a key-value pair of data associated with a `Span`
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Field {
     /// Metadata for the task span that the field came from.
@@ -58,6 +68,7 @@ pub mod field {
     ///
     /// This is either represented as a string, or as an index into a `Metadata`'s
     /// array of field name strings.
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Name {
         /// The string representation of the name.
@@ -69,6 +80,7 @@ pub mod field {
         NameIdx(u64),
     }
     /// The value of the key-value pair.
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum Value {
         /// A value serialized to a string using `fmt::Debug`.
@@ -91,6 +103,8 @@ pub mod field {
 /// Represents a period of time in which a program was executing in a particular context.
 ///
 /// Corresponds to `Span` in the `tracing` crate.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Span {
     /// An Id that uniquely identifies it in relation to other spans.
@@ -106,9 +120,12 @@ pub struct Span {
     pub fields: ::prost::alloc::vec::Vec<Field>,
     /// Timestamp for the span.
     #[prost(message, optional, tag = "4")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::timestamp"))]
     pub at: ::core::option::Option<::prost_types::Timestamp>,
 }
 /// Any new metadata that was registered since the last update.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct RegisterMetadata {
     /// The new metadata that was registered since the last update.
@@ -118,6 +135,8 @@ pub struct RegisterMetadata {
 /// Nested message and enum types in `RegisterMetadata`.
 pub mod register_metadata {
     /// One metadata element registered since the last update.
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "serde", serde(default))]
     #[derive(Clone, PartialEq, ::prost::Message)]
     pub struct NewMetadata {
         /// Unique identifier for `metadata`.
@@ -129,6 +148,8 @@ pub mod register_metadata {
     }
 }
 /// Metadata associated with a span or event.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Metadata {
     /// The name of the span or event.
@@ -158,6 +179,7 @@ pub struct Metadata {
 /// Nested message and enum types in `Metadata`.
 pub mod metadata {
     /// Indicates whether metadata is associated with a span or with an event.
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     #[derive(
         Clone,
         Copy,
@@ -199,6 +221,7 @@ pub mod metadata {
     /// Describes the level of verbosity of a span or event.
     ///
     /// Corresponds to `Level` in the `tracing` crate.
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     #[derive(
         Clone,
         Copy,
@@ -262,6 +285,8 @@ pub mod metadata {
 /// Contains stats about objects that can be polled. Currently these can be:
 /// - tasks that have been spawned
 /// - async operations on resources that are performed within the context of a task
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
 pub struct PollStats {
     /// The total number of times this object has been polled.
@@ -274,6 +299,7 @@ pub struct PollStats {
     /// Subtracting this timestamp from `created_at` can be used to calculate the
     /// time to first poll for this object, a measurement of executor latency.
     #[prost(message, optional, tag = "3")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::timestamp"))]
     pub first_poll: ::core::option::Option<::prost_types::Timestamp>,
     /// The timestamp of the most recent time this objects's poll method was invoked.
     ///
@@ -283,6 +309,7 @@ pub struct PollStats {
     /// equal to the `first_poll` timestamp.
     ///
     #[prost(message, optional, tag = "4")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::timestamp"))]
     pub last_poll_started: ::core::option::Option<::prost_types::Timestamp>,
     /// The timestamp of the most recent time this objects's poll method finished execution.
     ///
@@ -291,6 +318,7 @@ pub struct PollStats {
     /// If the object does not exist anymore, then this is the time the final invocation of
     /// its poll method has completed.
     #[prost(message, optional, tag = "5")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::timestamp"))]
     pub last_poll_ended: ::core::option::Option<::prost_types::Timestamp>,
     /// The total duration this object was being *actively polled*, summed across
     /// all polls.
@@ -301,7 +329,26 @@ pub struct PollStats {
     /// has spent *waiting* to be polled (including the `scheduled_time` value
     /// from `TaskStats`, if this is a task).
     #[prost(message, optional, tag = "6")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
     pub busy_time: ::core::option::Option<::prost_types::Duration>,
+    /// The portion of `busy_time` spent in polls that returned `Poll::Ready`.
+    ///
+    /// For async operations, whether a poll returned `Ready` is reported by the
+    /// instrumentation of the resource being operated on. For tasks, only the
+    /// final poll of a task that has been dropped is counted as `Ready`.
+    ///
+    /// If this is `None`, the instrumented application does not record busy
+    /// time by poll outcome.
+    #[prost(message, optional, tag = "7")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
+    pub ready_time: ::core::option::Option<::prost_types::Duration>,
+    /// The portion of `busy_time` spent in polls that returned `Poll::Pending`.
+    ///
+    /// A task or async operation whose busy time is dominated by polls that
+    /// return `Pending` may be woken more often than necessary.
+    #[prost(message, optional, tag = "8")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
+    pub pending_time: ::core::option::Option<::prost_types::Duration>,
 }
 /// State attributes of an entity. These are dependent on the type of the entity.
 ///
@@ -310,6 +357,8 @@ pub struct PollStats {
 /// indicating how many permits they are trying to acquire vs how many are acquired.
 /// These values may change over time. Therefore, they live in the runtime stats rather
 /// than the static data describing the entity.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Attribute {
     /// The key-value pair for the attribute
@@ -2,25 +2,149 @@
 /// InstrumentRequest requests the stream of updates
 /// to observe the async runtime state over time.
 ///
-/// TODO: In the future allow for the request to specify
-/// only the data that the caller cares about (i.e. only
-/// tasks but no resources)
+/// Clients which only care about one kind of data (i.e. only
+/// tasks but no resources) can use `WatchTasks`, `WatchResources`
+/// or `WatchAsyncOps` instead.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
-pub struct InstrumentRequest {}
+pub struct InstrumentRequest {
+    /// How often the client would like to be sent updates.
+    ///
+    /// If this is not set, updates are sent at the aggregator's own publish
+    /// interval. Otherwise, the aggregator publishes updates often enough for
+    /// its most frequently updated stream, and combines the updates published
+    /// between those sent on slower streams. Intervals shorter than 100ms are
+    /// rounded up to 100ms.
+    #[prost(message, optional, tag = "1")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
+    pub update_interval: ::core::option::Option<::prost_types::Duration>,
+}
+/// TasksRequest requests the stream of updates describing tasks.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct TasksRequest {}
+/// ResourcesRequest requests the stream of updates describing resources.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct ResourcesRequest {}
+/// AsyncOpsRequest requests the stream of updates describing async
+/// operations.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct AsyncOpsRequest {}
 /// TaskDetailsRequest requests the stream of updates about
 /// the specific task identified in the request.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
 pub struct TaskDetailsRequest {
     /// Identifies the task for which details were requested.
     #[prost(message, optional, tag = "1")]
     pub id: ::core::option::Option<super::common::Id>,
+    /// Which of the task's histograms to include in the stream's updates.
+    ///
+    /// Histograms are the largest part of a `TaskDetails` update, so a client
+    /// can ask for only the ones it's showing. If this isn't set, every
+    /// histogram is included.
+    #[prost(message, optional, tag = "2")]
+    pub histograms: ::core::option::Option<HistogramSelection>,
+}
+/// Selects which of a task's histograms a `TaskDetails` stream includes.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct HistogramSelection {
+    /// Whether to include the histogram of all of the task's poll durations.
+    #[prost(bool, tag = "1")]
+    pub poll_times: bool,
+    /// Whether to include the histogram of the task's scheduled durations.
+    #[prost(bool, tag = "2")]
+    pub scheduled_times: bool,
+    /// Whether to include the histogram of the task's poll durations since the
+    /// previous update.
+    #[prost(bool, tag = "3")]
+    pub recent_poll_times: bool,
 }
 /// PauseRequest requests the stream of updates to pause.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
-pub struct PauseRequest {}
+pub struct PauseRequest {
+    /// The ID of the update stream to pause, as reported in the first `Update`
+    /// sent on that stream.
+    ///
+    /// Pausing one stream does not affect any other clients watching the same
+    /// process. If this is 0, every stream is paused.
+    #[prost(uint64, tag = "1")]
+    pub stream_id: u64,
+}
 /// ResumeRequest requests the stream of updates to resume after a pause.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
-pub struct ResumeRequest {}
+pub struct ResumeRequest {
+    /// The ID of the update stream to resume, as reported in the first `Update`
+    /// sent on that stream.
+    ///
+    /// The first update sent after a stream is resumed contains the complete
+    /// current state, rather than only what changed while it was paused. If
+    /// this is 0, every stream is resumed.
+    #[prost(uint64, tag = "1")]
+    pub stream_id: u64,
+}
+/// DebugSelfRequest requests metrics describing the instrumentation itself.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct DebugSelfRequest {}
+/// SnapshotRequest requests a snapshot of the complete current state.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct SnapshotRequest {}
+/// Metrics describing the memory footprint of the instrumentation itself,
+/// to help diagnose its overhead.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct SelfMetrics {
+    /// The number of tasks whose data is currently held, including completed
+    /// tasks that are still being retained.
+    #[prost(uint64, tag = "1")]
+    pub tasks: u64,
+    /// The number of resources whose data is currently held.
+    #[prost(uint64, tag = "2")]
+    pub resources: u64,
+    /// The number of async operations whose data is currently held.
+    #[prost(uint64, tag = "3")]
+    pub async_ops: u64,
+    /// The number of span and event metadata registered so far.
+    #[prost(uint64, tag = "4")]
+    pub metadata: u64,
+    /// The number of poll ops buffered until the next update.
+    #[prost(uint64, tag = "5")]
+    pub poll_ops: u64,
+    /// The approximate number of bytes allocated for task poll and scheduled
+    /// duration histograms.
+    #[prost(uint64, tag = "6")]
+    pub histogram_bytes: u64,
+    /// The number of events waiting in the event buffer to be aggregated.
+    #[prost(uint64, tag = "7")]
+    pub event_buffer_len: u64,
+    /// The maximum number of events the event buffer can hold.
+    #[prost(uint64, tag = "8")]
+    pub event_buffer_capacity: u64,
+    /// The number of clients currently streaming updates.
+    #[prost(uint64, tag = "9")]
+    pub update_watchers: u64,
+    /// The number of clients currently streaming task details.
+    #[prost(uint64, tag = "10")]
+    pub details_watchers: u64,
+}
 /// Update carries all information regarding tasks, resources, async operations
 /// and resource operations in one message. There are a couple of reasons to combine all
 /// of these into a single message:
@@ -29,6 +153,8 @@ pub struct ResumeRequest {}
 /// - we can have all the new_metadata in one place
 /// - things such as async ops and resource ops do not make sense
 ///    on their own as they have relations to tasks and resources
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Update {
     /// The system time when this update was recorded.
@@ -36,6 +162,7 @@ pub struct Update {
     /// This is the timestamp any durations in the included `Stats` were
     /// calculated relative to.
     #[prost(message, optional, tag = "1")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::timestamp"))]
     pub now: ::core::option::Option<::prost_types::Timestamp>,
     /// Task state update.
     #[prost(message, optional, tag = "2")]
@@ -47,25 +174,170 @@ pub struct Update {
     #[prost(message, optional, tag = "4")]
     pub async_op_update: ::core::option::Option<super::async_ops::AsyncOpUpdate>,
     /// Any new span metadata that was registered since the last update.
+    ///
+    /// When a stream starts or is resumed, all of the metadata registered so
+    /// far is sent. If there is a lot of it, it may be split across several
+    /// updates which carry nothing but metadata, sent before the update with
+    /// the stream's initial state.
     #[prost(message, optional, tag = "5")]
     pub new_metadata: ::core::option::Option<super::common::RegisterMetadata>,
+    /// Aggregate statistics describing the runtime as a whole, measured over
+    /// the interval since the previous update.
+    #[prost(message, optional, tag = "6")]
+    pub runtime_stats: ::core::option::Option<RuntimeStats>,
+    /// Identifies the `WatchUpdates` stream this update was sent on, so that
+    /// the client can pause and resume it.
+    ///
+    /// This is only set in the updates which start a stream, and in those sent
+    /// when the stream is resumed. It is 0 in all other updates.
+    #[prost(uint64, tag = "7")]
+    pub stream_id: u64,
+    /// The number of this update on its stream.
+    ///
+    /// This increases by one with each update sent on a stream, starting at
+    /// 1. Updates which contain the complete current state, such as the first
+    /// update on a stream, carry the number of the last update sent on the
+    /// stream before them, which is 0 for the first update. A client which
+    /// sees the number increase by more than one between consecutive updates
+    /// has missed the updates in between. It is 0 if the aggregator doesn't
+    /// number updates.
+    #[prost(uint64, tag = "8")]
+    pub sequence: u64,
+    /// Statistics describing the health of the subscriber itself, measured
+    /// over the interval since the previous update.
+    ///
+    /// These help to explain gaps in the data, such as events dropped because
+    /// the aggregator couldn't keep up with them.
+    #[prost(message, optional, tag = "9")]
+    pub subscriber_stats: ::core::option::Option<SubscriberStats>,
+    /// Whether the client has recently fallen behind on this stream.
+    ///
+    /// When the client doesn't read its updates as fast as they're published,
+    /// the stream's buffer fills up. Rather than disconnecting the client
+    /// straight away, the subscriber keeps what it couldn't send for the next
+    /// update, and leaves the bulkier parts out of the stream's updates for a
+    /// while, such as new poll ops and the history of resource attributes. This
+    /// is set in the updates they're left out of. If the client keeps falling
+    /// behind, it's disconnected.
+    #[prost(bool, tag = "10")]
+    pub lagging: bool,
+    /// Whether some of the changes since the previous update were left out of
+    /// this one, so that it stays small enough for the client to decode.
+    ///
+    /// New poll ops and the history of resource attributes are dropped first.
+    /// If the update is still too large, the stats of tasks, resources, and
+    /// async ops which aren't new are left out, and then the newest of those
+    /// which are, along with their stats. Everything but the dropped poll ops
+    /// and attribute histories is sent in the stream's following updates.
+    #[prost(bool, tag = "11")]
+    pub truncated: bool,
+}
+/// Statistics describing the health of the subscriber, measured over the
+/// interval between two consecutive updates.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct SubscriberStats {
+    /// The most events that were waiting in the event buffer when the
+    /// aggregator drained it during the interval.
+    #[prost(uint64, tag = "1")]
+    pub max_event_buffer_len: u64,
+    /// The maximum number of events the event buffer can hold.
+    #[prost(uint64, tag = "2")]
+    pub event_buffer_capacity: u64,
+    /// The number of times the event buffer filled up enough that the
+    /// aggregator was asked to drain it before the next update was due.
+    #[prost(uint64, tag = "3")]
+    pub flushes: u64,
+    /// The number of events of each kind which were dropped during the
+    /// interval, because the event buffer was full.
+    #[prost(message, optional, tag = "4")]
+    pub dropped_events: ::core::option::Option<DroppedEvents>,
+    /// The longest a single pass of the aggregator's loop took during the
+    /// interval, including draining the event buffer and publishing updates.
+    #[prost(message, optional, tag = "5")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
+    pub max_aggregation_time: ::core::option::Option<::prost_types::Duration>,
+}
+/// The number of events of each kind which were dropped because the event
+/// buffer was full.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct DroppedEvents {
+    /// Registrations of new span and event metadata.
+    #[prost(uint64, tag = "1")]
+    pub metadata: u64,
+    /// Spawned tasks.
+    #[prost(uint64, tag = "2")]
+    pub spawns: u64,
+    /// Created resources.
+    #[prost(uint64, tag = "3")]
+    pub resources: u64,
+    /// Created async ops.
+    #[prost(uint64, tag = "4")]
+    pub async_ops: u64,
+    /// Polls of async ops by tasks. This also counts the poll ops left out of
+    /// updates to keep them small while the aggregator was falling behind.
+    #[prost(uint64, tag = "5")]
+    pub poll_ops: u64,
+}
+/// Aggregate statistics for the instrumented runtime, measured over the
+/// interval between two consecutive updates.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct RuntimeStats {
+    /// The total time spent polling tasks during the interval, summed across
+    /// all tasks.
+    #[prost(message, optional, tag = "1")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
+    pub busy_time: ::core::option::Option<::prost_types::Duration>,
+    /// The length of the interval over which `busy_time` was measured.
+    #[prost(message, optional, tag = "2")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
+    pub interval: ::core::option::Option<::prost_types::Duration>,
+    /// The number of worker threads available to poll tasks.
+    ///
+    /// The saturation of the runtime can be estimated as
+    /// `busy_time / (interval * worker_threads)`.
+    #[prost(uint64, tag = "3")]
+    pub worker_threads: u64,
 }
 /// StateRequest requests the current state of the aggregator.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
-pub struct StateRequest {}
-/// State carries the current state of the aggregator.
+pub struct StateRequest {
+    /// The ID of the update stream whose state should be reported, as reported
+    /// in the first `Update` sent on that stream.
+    ///
+    /// If this is 0, the aggregator is reported as paused only while every
+    /// update stream is paused.
+    #[prost(uint64, tag = "1")]
+    pub stream_id: u64,
+}
+/// State carries the current state of the aggregator, as seen by one update
+/// stream.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
 pub struct State {
     #[prost(enumeration = "Temporality", tag = "1")]
     pub temporality: i32,
 }
 /// `PauseResponse` is the value returned after a pause request.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
 pub struct PauseResponse {}
 /// `ResumeResponse` is the value returned after a resume request.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
 pub struct ResumeResponse {}
 /// The time "state" of the aggregator.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum Temporality {
@@ -216,6 +488,105 @@ pub mod instrument_client {
                 );
             self.inner.server_streaming(req, path, codec).await
         }
+        /// Produces a stream of updates which only describe tasks.
+        ///
+        /// The `resource_update` and `async_op_update` fields of these updates are
+        /// never set.
+        pub async fn watch_tasks(
+            &mut self,
+            request: impl tonic::IntoRequest<super::TasksRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::Update>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/rs.tokio.console.instrument.Instrument/WatchTasks",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "rs.tokio.console.instrument.Instrument",
+                        "WatchTasks",
+                    ),
+                );
+            self.inner.server_streaming(req, path, codec).await
+        }
+        /// Produces a stream of updates which only describe resources.
+        ///
+        /// The `task_update` and `async_op_update` fields of these updates are
+        /// never set.
+        pub async fn watch_resources(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ResourcesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::Update>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/rs.tokio.console.instrument.Instrument/WatchResources",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "rs.tokio.console.instrument.Instrument",
+                        "WatchResources",
+                    ),
+                );
+            self.inner.server_streaming(req, path, codec).await
+        }
+        /// Produces a stream of updates which only describe async operations.
+        ///
+        /// The `task_update` and `resource_update` fields of these updates are
+        /// never set.
+        pub async fn watch_async_ops(
+            &mut self,
+            request: impl tonic::IntoRequest<super::AsyncOpsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::Update>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/rs.tokio.console.instrument.Instrument/WatchAsyncOps",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "rs.tokio.console.instrument.Instrument",
+                        "WatchAsyncOps",
+                    ),
+                );
+            self.inner.server_streaming(req, path, codec).await
+        }
         /// Produces a stream of updates describing the activity of a specific task.
         pub async fn watch_task_details(
             &mut self,
@@ -324,6 +695,69 @@ pub mod instrument_client {
                 );
             self.inner.unary(req, path, codec).await
         }
+        /// Reports how much memory the instrumentation itself is using.
+        pub async fn debug_self(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DebugSelfRequest>,
+        ) -> std::result::Result<tonic::Response<super::SelfMetrics>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/rs.tokio.console.instrument.Instrument/DebugSelf",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "rs.tokio.console.instrument.Instrument",
+                        "DebugSelf",
+                    ),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        /// Produces a consistent snapshot of the complete current state, and then
+        /// ends.
+        ///
+        /// The aggregator stops processing events while it assembles the snapshot,
+        /// so every task, resource and async operation it retains is captured as of
+        /// the same instant, however large the state is. Unlike the first update of
+        /// a `WatchUpdates` stream, retention is never reduced to make the state
+        /// fit in a single message: the snapshot is instead split across as many
+        /// updates as it needs, which all carry the same `now` timestamp. Applying
+        /// them in order gives the complete state.
+        pub async fn snapshot(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SnapshotRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::Update>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/rs.tokio.console.instrument.Instrument/Snapshot",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("rs.tokio.console.instrument.Instrument", "Snapshot"),
+                );
+            self.inner.server_streaming(req, path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -353,6 +787,54 @@ pub mod instrument_server {
             tonic::Response<Self::WatchUpdatesStream>,
             tonic::Status,
         >;
+        /// Server streaming response type for the WatchTasks method.
+        type WatchTasksStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::Update, tonic::Status>,
+            >
+            + std::marker::Send
+            + 'static;
+        /// Produces a stream of updates which only describe tasks.
+        ///
+        /// The `resource_update` and `async_op_update` fields of these updates are
+        /// never set.
+        async fn watch_tasks(
+            &self,
+            request: tonic::Request<super::TasksRequest>,
+        ) -> std::result::Result<tonic::Response<Self::WatchTasksStream>, tonic::Status>;
+        /// Server streaming response type for the WatchResources method.
+        type WatchResourcesStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::Update, tonic::Status>,
+            >
+            + std::marker::Send
+            + 'static;
+        /// Produces a stream of updates which only describe resources.
+        ///
+        /// The `task_update` and `async_op_update` fields of these updates are
+        /// never set.
+        async fn watch_resources(
+            &self,
+            request: tonic::Request<super::ResourcesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::WatchResourcesStream>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the WatchAsyncOps method.
+        type WatchAsyncOpsStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::Update, tonic::Status>,
+            >
+            + std::marker::Send
+            + 'static;
+        /// Produces a stream of updates which only describe async operations.
+        ///
+        /// The `task_update` and `resource_update` fields of these updates are
+        /// never set.
+        async fn watch_async_ops(
+            &self,
+            request: tonic::Request<super::AsyncOpsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::WatchAsyncOpsStream>,
+            tonic::Status,
+        >;
         /// Server streaming response type for the WatchTaskDetails method.
         type WatchTaskDetailsStream: tonic::codegen::tokio_stream::Stream<
                 Item = std::result::Result<
@@ -391,6 +873,31 @@ pub mod instrument_server {
             &self,
             request: tonic::Request<super::ResumeRequest>,
         ) -> std::result::Result<tonic::Response<super::ResumeResponse>, tonic::Status>;
+        /// Reports how much memory the instrumentation itself is using.
+        async fn debug_self(
+            &self,
+            request: tonic::Request<super::DebugSelfRequest>,
+        ) -> std::result::Result<tonic::Response<super::SelfMetrics>, tonic::Status>;
+        /// Server streaming response type for the Snapshot method.
+        type SnapshotStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::Update, tonic::Status>,
+            >
+            + std::marker::Send
+            + 'static;
+        /// Produces a consistent snapshot of the complete current state, and then
+        /// ends.
+        ///
+        /// The aggregator stops processing events while it assembles the snapshot,
+        /// so every task, resource and async operation it retains is captured as of
+        /// the same instant, however large the state is. Unlike the first update of
+        /// a `WatchUpdates` stream, retention is never reduced to make the state
+        /// fit in a single message: the snapshot is instead split across as many
+        /// updates as it needs, which all carry the same `now` timestamp. Applying
+        /// them in order gives the complete state.
+        async fn snapshot(
+            &self,
+            request: tonic::Request<super::SnapshotRequest>,
+        ) -> std::result::Result<tonic::Response<Self::SnapshotStream>, tonic::Status>;
     }
     /// `InstrumentServer<T>` implements `Instrument` as a service.
     #[derive(Debug)]
@@ -515,6 +1022,144 @@ pub mod instrument_server {
                     };
                     Box::pin(fut)
                 }
+                "/rs.tokio.console.instrument.Instrument/WatchTasks" => {
+                    #[allow(non_camel_case_types)]
+                    struct WatchTasksSvc<T: Instrument>(pub Arc<T>);
+                    impl<
+                        T: Instrument,
+                    > tonic::server::ServerStreamingService<super::TasksRequest>
+                    for WatchTasksSvc<T> {
+                        type Response = super::Update;
+                        type ResponseStream = T::WatchTasksStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::TasksRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Instrument>::watch_tasks(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = WatchTasksSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rs.tokio.console.instrument.Instrument/WatchResources" => {
+                    #[allow(non_camel_case_types)]
+                    struct WatchResourcesSvc<T: Instrument>(pub Arc<T>);
+                    impl<
+                        T: Instrument,
+                    > tonic::server::ServerStreamingService<super::ResourcesRequest>
+                    for WatchResourcesSvc<T> {
+                        type Response = super::Update;
+                        type ResponseStream = T::WatchResourcesStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ResourcesRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Instrument>::watch_resources(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = WatchResourcesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rs.tokio.console.instrument.Instrument/WatchAsyncOps" => {
+                    #[allow(non_camel_case_types)]
+                    struct WatchAsyncOpsSvc<T: Instrument>(pub Arc<T>);
+                    impl<
+                        T: Instrument,
+                    > tonic::server::ServerStreamingService<super::AsyncOpsRequest>
+                    for WatchAsyncOpsSvc<T> {
+                        type Response = super::Update;
+                        type ResponseStream = T::WatchAsyncOpsStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::AsyncOpsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Instrument>::watch_async_ops(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = WatchAsyncOpsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/rs.tokio.console.instrument.Instrument/WatchTaskDetails" => {
                     #[allow(non_camel_case_types)]
                     struct WatchTaskDetailsSvc<T: Instrument>(pub Arc<T>);
@@ -693,6 +1338,97 @@ pub mod instrument_server {
                     };
                     Box::pin(fut)
                 }
+                "/rs.tokio.console.instrument.Instrument/DebugSelf" => {
+                    #[allow(non_camel_case_types)]
+                    struct DebugSelfSvc<T: Instrument>(pub Arc<T>);
+                    impl<
+                        T: Instrument,
+                    > tonic::server::UnaryService<super::DebugSelfRequest>
+                    for DebugSelfSvc<T> {
+                        type Response = super::SelfMetrics;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DebugSelfRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Instrument>::debug_self(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = DebugSelfSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rs.tokio.console.instrument.Instrument/Snapshot" => {
+                    #[allow(non_camel_case_types)]
+                    struct SnapshotSvc<T: Instrument>(pub Arc<T>);
+                    impl<
+                        T: Instrument,
+                    > tonic::server::ServerStreamingService<super::SnapshotRequest>
+                    for SnapshotSvc<T> {
+                        type Response = super::Update;
+                        type ResponseStream = T::SnapshotStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SnapshotRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Instrument>::snapshot(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = SnapshotSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         let mut response = http::Response::new(empty_body());
@@ -0,0 +1,23 @@
+// This file is @generated by prost-build.
+/// A recording of the updates a console received from an instrumented
+/// application over a session.
+///
+/// Recordings are written as the bytes `tokio-console-recording`, then a single
+/// version byte (currently 1), followed by a sequence of length-delimited
+/// `RecordedUpdate` messages, in the order they were received.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RecordedUpdate {
+    /// The time at which the console received the update.
+    ///
+    /// This is used to play back the recording at the pace it was recorded,
+    /// and may differ from the `now` timestamp of the update, which was
+    /// taken by the instrumented application.
+    #[prost(message, optional, tag = "1")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::timestamp"))]
+    pub received_at: ::core::option::Option<::prost_types::Timestamp>,
+    /// The update itself.
+    #[prost(message, optional, tag = "2")]
+    pub update: ::core::option::Option<super::instrument::Update>,
+}
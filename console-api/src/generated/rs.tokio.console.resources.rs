@@ -6,6 +6,8 @@
 /// - any new resources that were created since the last update
 /// - the current stats for any resource whose stats changed since the last update
 /// - any new poll ops that have been invoked on a resource
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ResourceUpdate {
     /// A list of new resources that were created since the last `ResourceUpdate` was
@@ -32,6 +34,8 @@ pub struct ResourceUpdate {
     pub dropped_events: u64,
 }
 /// Static data recorded when a new resource is created.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Resource {
     /// The resources's ID.
@@ -66,6 +70,8 @@ pub struct Resource {
 /// Nested message and enum types in `Resource`.
 pub mod resource {
     /// The kind of resource (e.g. timer, mutex).
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "serde", serde(default))]
     #[derive(Clone, PartialEq, ::prost::Message)]
     pub struct Kind {
         /// Every resource is either a known kind or an other (unknown) kind.
@@ -75,6 +81,7 @@ pub mod resource {
     /// Nested message and enum types in `Kind`.
     pub mod kind {
         /// `Known` collects the kinds of resources that are known in this version of the API.
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
         #[derive(
             Clone,
             Copy,
@@ -110,6 +117,7 @@ pub mod resource {
             }
         }
         /// Every resource is either a known kind or an other (unknown) kind.
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
         #[derive(Clone, PartialEq, ::prost::Oneof)]
         pub enum Kind {
             /// `known` signals that this kind of resource is known to the console API.
@@ -122,13 +130,17 @@ pub mod resource {
     }
 }
 /// Task runtime stats of a resource.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Stats {
     /// Timestamp of when the resource was created.
     #[prost(message, optional, tag = "1")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::timestamp"))]
     pub created_at: ::core::option::Option<::prost_types::Timestamp>,
     /// Timestamp of when the resource was dropped.
     #[prost(message, optional, tag = "2")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::timestamp"))]
     pub dropped_at: ::core::option::Option<::prost_types::Timestamp>,
     /// State attributes of the resource. These are dependent on the type of the resource.
     /// For example, a timer resource will have a duration while a semaphore resource may
@@ -137,9 +149,41 @@ pub struct Stats {
     /// static data describing the resource.
     #[prost(message, repeated, tag = "3")]
     pub attributes: ::prost::alloc::vec::Vec<super::common::Attribute>,
+    /// The task which most recently had one of the resource's poll ops return
+    /// ready, such as by acquiring a lock or a semaphore permit.
+    ///
+    /// Together with the resource's attributes, this can tell which task is
+    /// holding a lock. It is not set if no poll op of the resource has
+    /// returned ready yet.
+    #[prost(message, optional, tag = "4")]
+    pub acquired_by: ::core::option::Option<super::common::Id>,
+    /// Recent values of the resource's attributes, oldest first.
+    ///
+    /// Only `attributes` is kept up to date by default, so values which are
+    /// overwritten between updates are never seen. If the subscriber is
+    /// configured to keep a history of attribute values, this holds a bounded
+    /// number of the most recent values of each attribute, each with the time
+    /// it was set.
+    #[prost(message, repeated, tag = "5")]
+    pub attribute_history: ::prost::alloc::vec::Vec<AttributeSample>,
+}
+/// The value of one of a resource's attributes at a point in time.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AttributeSample {
+    /// When the attribute was set to this value.
+    #[prost(message, optional, tag = "1")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::timestamp"))]
+    pub at: ::core::option::Option<::prost_types::Timestamp>,
+    /// The attribute's name and value.
+    #[prost(message, optional, tag = "2")]
+    pub attribute: ::core::option::Option<super::common::Attribute>,
 }
 /// A `PollOp` describes each poll operation that completes within the async
 /// application.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct PollOp {
     /// The numeric ID of the op's `Metadata`.
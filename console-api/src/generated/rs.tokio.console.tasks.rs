@@ -5,6 +5,8 @@
 /// update. This includes:
 /// - any new tasks that were spawned since the last update
 /// - the current stats for any task whose stats changed since the last update
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct TaskUpdate {
     /// A list of new tasks that were spawned since the last `TaskUpdate` was
@@ -36,6 +38,8 @@ pub struct TaskUpdate {
     pub dropped_events: u64,
 }
 /// A task details update
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct TaskDetails {
     /// The task's ID which the details belong to.
@@ -43,6 +47,7 @@ pub struct TaskDetails {
     pub task_id: ::core::option::Option<super::common::Id>,
     /// The timestamp for when the update to the task took place.
     #[prost(message, optional, tag = "2")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::timestamp"))]
     pub now: ::core::option::Option<::prost_types::Timestamp>,
     /// A histogram of task scheduled durations.
     ///
@@ -50,6 +55,40 @@ pub struct TaskDetails {
     /// woken and when it is next polled.
     #[prost(message, optional, tag = "5")]
     pub scheduled_times_histogram: ::core::option::Option<DurationHistogram>,
+    /// A histogram of the durations of the task's polls that completed since
+    /// the previous `TaskDetails` update was sent.
+    ///
+    /// Unlike `poll_times_histogram`, which is cumulative over the task's
+    /// entire lifetime, this reflects only the most recent publish interval.
+    /// It is not set in the first update sent to a new subscriber.
+    #[prost(message, optional, tag = "6")]
+    pub recent_poll_times_histogram: ::core::option::Option<DurationHistogram>,
+    /// The locations the task has most often been woken from, most frequent
+    /// first.
+    ///
+    /// Only a limited number of the most frequent locations are included.
+    /// Wakes are only counted here if the runtime records where they happened
+    /// (as `loc.*` fields) in its waker events, so this may be empty.
+    #[prost(message, repeated, tag = "7")]
+    pub wake_sites: ::prost::alloc::vec::Vec<WakeSite>,
+    /// The number of times the task was woken, and woke itself, in each of the
+    /// most recent publish intervals, oldest first.
+    ///
+    /// This shows whether a task's self-wakes are constant or come in bursts.
+    #[prost(message, repeated, tag = "8")]
+    pub wake_history: ::prost::alloc::vec::Vec<WakeInterval>,
+    /// The number of the task's polls performed by each thread it was polled
+    /// on, most polls first.
+    ///
+    /// This shows whether a task is spread across a runtime's worker threads
+    /// or always polled by the same one.
+    #[prost(message, repeated, tag = "9")]
+    pub thread_polls: ::prost::alloc::vec::Vec<ThreadPolls>,
+    /// The async ops the task is currently waiting on.
+    ///
+    /// This is empty if the task is not waiting on any instrumented async op.
+    #[prost(message, repeated, tag = "10")]
+    pub waiting_on: ::prost::alloc::vec::Vec<WaitingOn>,
     /// A histogram of task poll durations.
     ///
     /// This is either:
@@ -67,6 +106,7 @@ pub mod task_details {
     /// - the raw binary representation of a HdrHistogram.rs `Histogram`
     ///    serialized to binary in the V2 format (legacy)
     /// - a binary histogram plus details on outliers (current)
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum PollTimesHistogram {
         /// HdrHistogram.rs `Histogram` serialized to binary in the V2 format
@@ -77,7 +117,77 @@ pub mod task_details {
         Histogram(super::DurationHistogram),
     }
 }
+/// An async op which a task is currently waiting on.
+///
+/// A task is waiting on an async op if the last time the task polled it, it
+/// returned pending, and it has not been dropped since.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WaitingOn {
+    /// The ID of the async op.
+    #[prost(message, optional, tag = "1")]
+    pub async_op_id: ::core::option::Option<super::common::Id>,
+    /// The ID of the resource the async op is performed on.
+    #[prost(message, optional, tag = "2")]
+    pub resource_id: ::core::option::Option<super::common::Id>,
+    /// The name of the async op's most recent poll op (e.g. `poll_elapsed`).
+    #[prost(string, tag = "3")]
+    pub op_name: ::prost::alloc::string::String,
+    /// When the async op first returned pending to the task.
+    #[prost(message, optional, tag = "4")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::timestamp"))]
+    pub since: ::core::option::Option<::prost_types::Timestamp>,
+}
+/// The number of a task's polls performed by a single thread.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ThreadPolls {
+    /// An ID assigned by the subscriber to each thread that polls tasks.
+    ///
+    /// This is not the operating system's thread ID. It is needed to tell
+    /// threads apart, as a runtime's worker threads usually share a name.
+    #[prost(uint64, tag = "1")]
+    pub thread_id: u64,
+    /// The name of the thread, if it has one.
+    #[prost(string, optional, tag = "2")]
+    pub thread_name: ::core::option::Option<::prost::alloc::string::String>,
+    /// The number of the task's polls performed by the thread.
+    #[prost(uint64, tag = "3")]
+    pub polls: u64,
+}
+/// Wake counts for a task over a single publish interval.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct WakeInterval {
+    /// The number of times the task was woken.
+    #[prost(uint64, tag = "1")]
+    pub wakes: u64,
+    /// The number of those wakes where the task woke itself.
+    #[prost(uint64, tag = "2")]
+    pub self_wakes: u64,
+}
+/// A location from which a task was woken.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WakeSite {
+    /// The location of the wake.
+    ///
+    /// This is taken from the `loc.file`, `loc.line`, and `loc.col` fields of
+    /// the waker event if it has them, and otherwise from the event's
+    /// callsite.
+    #[prost(message, optional, tag = "1")]
+    pub location: ::core::option::Option<super::common::Location>,
+    /// The number of times the task was woken from this location.
+    #[prost(uint64, tag = "2")]
+    pub wakes: u64,
+}
 /// Data recorded when a new task is spawned.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Task {
     /// The task's ID.
@@ -118,10 +228,50 @@ pub struct Task {
     /// The location in code where the task was spawned.
     #[prost(message, optional, tag = "6")]
     pub location: ::core::option::Option<super::common::Location>,
+    /// A label identifying the runtime this task was spawned on.
+    ///
+    /// This is only present if the instrumented application runs more than one
+    /// runtime and has labeled them, or if the runtime could be inferred from
+    /// the name of the thread the task was spawned from.
+    #[prost(string, optional, tag = "7")]
+    pub runtime: ::core::option::Option<::prost::alloc::string::String>,
+    /// The group this task belongs to.
+    ///
+    /// Applications assign tasks to groups by spawning them within a span that
+    /// has a `console.group` field, such as
+    /// `tracing::info_span!("ingest", console.group = "ingest")`. Tasks spawned
+    /// by a grouped task are in the same group. If this is not set, the task
+    /// is not in any group.
+    #[prost(string, optional, tag = "8")]
+    pub group: ::core::option::Option<::prost::alloc::string::String>,
+    /// The size of the task's future, in bytes.
+    ///
+    /// This is reported by the runtime when the task is spawned, or estimated
+    /// by the application if the runtime does not report it. If this is not
+    /// set, the size is unknown.
+    #[prost(uint64, optional, tag = "9")]
+    pub size_bytes: ::core::option::Option<u64>,
+    /// The size of the task's future before the runtime boxed it, in bytes.
+    ///
+    /// Runtimes may move futures which are too large to the heap before
+    /// spawning them. This is only set if the runtime reported that the
+    /// future was boxed, in which case `size_bytes` is the size of the box.
+    #[prost(uint64, optional, tag = "10")]
+    pub original_size_bytes: ::core::option::Option<u64>,
+    /// The stack of the code which spawned the task, innermost frame first.
+    ///
+    /// This is only captured if the application opted in, and may be missing
+    /// for some tasks, as capturing backtraces is rate limited. Each frame is a
+    /// human-readable function name, followed by its source location if it is
+    /// known, such as `app::server::accept at src/server.rs:42`. Frames in the
+    /// runtime, the standard library and the instrumentation are omitted.
+    #[prost(string, repeated, tag = "11")]
+    pub spawn_backtrace: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
 /// Nested message and enum types in `Task`.
 pub mod task {
     /// The category of task this task belongs to.
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     #[derive(
         Clone,
         Copy,
@@ -164,13 +314,17 @@ pub mod task {
     }
 }
 /// Task performance statistics.
-#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Stats {
     /// Timestamp of when the task was spawned.
     #[prost(message, optional, tag = "1")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::timestamp"))]
     pub created_at: ::core::option::Option<::prost_types::Timestamp>,
     /// Timestamp of when the task was dropped.
     #[prost(message, optional, tag = "2")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::timestamp"))]
     pub dropped_at: ::core::option::Option<::prost_types::Timestamp>,
     /// The total number of times this task has been woken over its lifetime.
     #[prost(uint64, tag = "3")]
@@ -185,6 +339,7 @@ pub struct Stats {
     ///
     /// If this is `None`, the task has not yet been woken.
     #[prost(message, optional, tag = "6")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::timestamp"))]
     pub last_wake: ::core::option::Option<::prost_types::Timestamp>,
     /// Contains task poll statistics.
     #[prost(message, optional, tag = "7")]
@@ -202,8 +357,102 @@ pub struct Stats {
     /// amount of time it spent unable to progress because it was waiting on
     /// some resource.
     #[prost(message, optional, tag = "9")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
     pub scheduled_time: ::core::option::Option<::prost_types::Duration>,
+    /// An estimate of the total time the instrumentation spent handling this
+    /// task's spans and waker events.
+    ///
+    /// The subscriber only measures a sample of its callbacks, so this is
+    /// extrapolated from the sampled callbacks. If it is not set, overhead
+    /// sampling is disabled, or none of the task's callbacks have been
+    /// sampled yet.
+    #[prost(message, optional, tag = "10")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_time::duration"))]
+    pub instrumentation_overhead: ::core::option::Option<::prost_types::Duration>,
+    /// Whether the task was dropped before its future completed, such as
+    /// because it was aborted (by `JoinHandle::abort`) or the runtime shut
+    /// down.
+    ///
+    /// This is only set once the task has been dropped (when `dropped_at` is
+    /// set).
+    #[prost(bool, tag = "11")]
+    pub cancelled: bool,
+    /// The async ops the task is currently waiting on.
+    ///
+    /// This is the same as `TaskDetails.waiting_on`, but is sent for every
+    /// task whose stats changed, so that clients can see what all tasks are
+    /// blocked on. As it is only sent with the rest of the task's stats, it
+    /// may be out of date for tasks which have not been polled since.
+    #[prost(message, repeated, tag = "12")]
+    pub waiting_on: ::prost::alloc::vec::Vec<WaitingOn>,
+    /// The name of the innermost span of application code the task is in.
+    ///
+    /// This is only set while the task is being polled, and only if the
+    /// instrumentation was configured to track the application's spans. Spans
+    /// created by the runtime are not included.
+    #[prost(string, optional, tag = "14")]
+    pub current_span: ::core::option::Option<::prost::alloc::string::String>,
+    /// How the task terminated, if it has.
+    ///
+    /// This is only set once the task has been dropped (when `dropped_at` is
+    /// set), and is `NOT_TERMINATED` until then.
+    #[prost(enumeration = "stats::TerminatedBy", tag = "15")]
+    pub terminated_by: i32,
+}
+/// Nested message and enum types in `Stats`.
+pub mod stats {
+    /// How a task terminated.
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+        PartialOrd,
+        Ord,
+        ::prost::Enumeration
+    )]
+    #[repr(i32)]
+    pub enum TerminatedBy {
+        /// The task hasn't terminated yet.
+        NotTerminated = 0,
+        /// The task's future completed.
+        Completed = 1,
+        /// The task panicked while it was being polled or dropped.
+        Panicked = 2,
+        /// The task was dropped before its future completed, such as because
+        /// it was aborted (by `JoinHandle::abort`) or the runtime shut down.
+        Aborted = 3,
+    }
+    impl TerminatedBy {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                Self::NotTerminated => "NOT_TERMINATED",
+                Self::Completed => "COMPLETED",
+                Self::Panicked => "PANICKED",
+                Self::Aborted => "ABORTED",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "NOT_TERMINATED" => Some(Self::NotTerminated),
+                "COMPLETED" => Some(Self::Completed),
+                "PANICKED" => Some(Self::Panicked),
+                "ABORTED" => Some(Self::Aborted),
+                _ => None,
+            }
+        }
+    }
 }
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct DurationHistogram {
     /// HdrHistogram.rs `Histogram` serialized to binary in the V2 format
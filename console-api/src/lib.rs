@@ -2,12 +2,22 @@
 
 /// Represents the operations performed by an async runtime.
 pub mod async_ops;
+/// A high-level client which merges a target's updates into snapshots.
+#[cfg(feature = "client")]
+pub mod client;
 /// Represents unique id's and Rust source locations.
 mod common;
+/// Accessors returning `std::time` types, and builders for common messages.
+#[cfg(feature = "convenience")]
+pub mod convenience;
 /// Represents interactions between the console-subscriber and a console client observing it.
 pub mod instrument;
+/// Represents recordings of the updates a console received over a session.
+pub mod recording;
 /// Represents updates to the resources in an async runtime.
 pub mod resources;
+#[cfg(feature = "serde")]
+mod serde_time;
 /// Represents updates to the tasks in an async runtime.
 pub mod tasks;
 /// Represents events on the tracing subsystem: thread registration and span activities.
@@ -0,0 +1,38 @@
+//! Serializes the `google.protobuf.Timestamp` and `google.protobuf.Duration`
+//! fields of messages as strings, the way the [protobuf JSON mapping] does,
+//! since `prost-types` doesn't implement serde's traits for them.
+//!
+//! [protobuf JSON mapping]: https://protobuf.dev/programming-guides/proto3/#json
+
+macro_rules! string_module {
+    ($name:ident, $ty:ty) => {
+        pub(crate) mod $name {
+            use serde::{de, Deserialize, Deserializer, Serializer};
+
+            pub(crate) fn serialize<S>(
+                value: &Option<$ty>,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                match value {
+                    Some(value) => serializer.collect_str(value),
+                    None => serializer.serialize_none(),
+                }
+            }
+
+            pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<Option<$ty>, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                Option::<std::borrow::Cow<'de, str>>::deserialize(deserializer)?
+                    .map(|value| value.parse().map_err(de::Error::custom))
+                    .transpose()
+            }
+        }
+    };
+}
+
+string_module!(timestamp, prost_types::Timestamp);
+string_module!(duration, prost_types::Duration);
@@ -1,5 +1,68 @@
 use std::{fs, path::PathBuf, process::Command};
 
+/// Every `google.protobuf.Timestamp` and `google.protobuf.Duration` field,
+/// with the module in `serde_time` which serializes it.
+const TIME_FIELDS: &[(&str, &str)] = &[
+    (".rs.tokio.console.async_ops.Stats.created_at", "timestamp"),
+    (".rs.tokio.console.async_ops.Stats.dropped_at", "timestamp"),
+    (".rs.tokio.console.common.Span.at", "timestamp"),
+    (".rs.tokio.console.common.PollStats.first_poll", "timestamp"),
+    (
+        ".rs.tokio.console.common.PollStats.last_poll_started",
+        "timestamp",
+    ),
+    (
+        ".rs.tokio.console.common.PollStats.last_poll_ended",
+        "timestamp",
+    ),
+    (".rs.tokio.console.common.PollStats.busy_time", "duration"),
+    (".rs.tokio.console.common.PollStats.ready_time", "duration"),
+    (
+        ".rs.tokio.console.common.PollStats.pending_time",
+        "duration",
+    ),
+    (
+        ".rs.tokio.console.instrument.InstrumentRequest.update_interval",
+        "duration",
+    ),
+    (".rs.tokio.console.instrument.Update.now", "timestamp"),
+    (
+        ".rs.tokio.console.instrument.SubscriberStats.max_aggregation_time",
+        "duration",
+    ),
+    (
+        ".rs.tokio.console.instrument.RuntimeStats.busy_time",
+        "duration",
+    ),
+    (
+        ".rs.tokio.console.instrument.RuntimeStats.interval",
+        "duration",
+    ),
+    (
+        ".rs.tokio.console.recording.RecordedUpdate.received_at",
+        "timestamp",
+    ),
+    (
+        ".rs.tokio.console.resources.AttributeSample.at",
+        "timestamp",
+    ),
+    (".rs.tokio.console.resources.Stats.created_at", "timestamp"),
+    (".rs.tokio.console.resources.Stats.dropped_at", "timestamp"),
+    (".rs.tokio.console.tasks.TaskDetails.now", "timestamp"),
+    (".rs.tokio.console.tasks.WaitingOn.since", "timestamp"),
+    (".rs.tokio.console.tasks.Stats.created_at", "timestamp"),
+    (".rs.tokio.console.tasks.Stats.dropped_at", "timestamp"),
+    (".rs.tokio.console.tasks.Stats.last_wake", "timestamp"),
+    (".rs.tokio.console.tasks.Stats.scheduled_time", "duration"),
+    (
+        ".rs.tokio.console.tasks.Stats.instrumentation_overhead",
+        "duration",
+    ),
+    (".rs.tokio.console.trace.TraceEvent.Enter.at", "timestamp"),
+    (".rs.tokio.console.trace.TraceEvent.Exit.at", "timestamp"),
+    (".rs.tokio.console.trace.TraceEvent.Close.at", "timestamp"),
+];
+
 #[test]
 fn bootstrap() {
     let root_dir = PathBuf::from(std::env!("CARGO_MANIFEST_DIR"));
@@ -31,14 +94,37 @@ fn bootstrap() {
 
     let out_dir = root_dir.join("src").join("generated");
 
-    if let Err(error) = tonic_build::configure()
+    let mut builder = tonic_build::configure()
         .build_client(true)
         .build_server(true)
         .emit_rerun_if_changed(false)
         .protoc_arg("--experimental_allow_proto3_optional")
         .out_dir(&out_dir)
-        .compile_protos(&proto_files[..], &[proto_dir])
-    {
+        .type_attribute(
+            ".rs.tokio.console",
+            "#[cfg_attr(feature = \"serde\", derive(serde::Serialize, serde::Deserialize))]",
+        )
+        // Fields missing from a serialized message take their default value,
+        // as they do in the protobuf encoding, so that newer fields don't
+        // break reading older messages.
+        .message_attribute(
+            ".rs.tokio.console",
+            "#[cfg_attr(feature = \"serde\", serde(default))]",
+        );
+    // `prost-types` doesn't implement serde's traits for the well-known
+    // types, so these fields are serialized as strings, the way the protobuf
+    // JSON mapping does.
+    for (field, module) in TIME_FIELDS {
+        builder = builder.field_attribute(
+            field,
+            format!(
+                "#[cfg_attr(feature = \"serde\", serde(with = \"crate::serde_time::{}\"))]",
+                module
+            ),
+        );
+    }
+
+    if let Err(error) = builder.compile_protos(&proto_files[..], &[proto_dir]) {
         panic!("failed to compile `console-api` protobuf: {}", error);
     }
 
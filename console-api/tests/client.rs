@@ -0,0 +1,45 @@
+#![cfg(feature = "client")]
+
+use console_api::{
+    client::Snapshot,
+    convenience::{TaskBuilder, TaskStatsBuilder, UpdateBuilder},
+    MetaId,
+};
+use std::time::{Duration, SystemTime};
+
+#[test]
+fn merges_updates() {
+    let start = SystemTime::now();
+    let mut snapshot = Snapshot::default();
+    snapshot.apply(
+        UpdateBuilder::new(start)
+            .new_task(
+                TaskBuilder::new(1, MetaId { id: 1 }).build(),
+                TaskStatsBuilder::new(start).build(),
+            )
+            .new_task(
+                TaskBuilder::new(2, MetaId { id: 1 }).build(),
+                TaskStatsBuilder::new(start).build(),
+            )
+            .build(),
+    );
+    assert_eq!(snapshot.tasks().len(), 2);
+
+    // Later updates only carry the stats which changed.
+    let later = start + Duration::from_secs(1);
+    snapshot.apply(
+        UpdateBuilder::new(later)
+            .task_stats(1, TaskStatsBuilder::new(start).polls(5).build())
+            .task_stats(2, TaskStatsBuilder::new(start).dropped_at(later).build())
+            // Stats for tasks which were never reported are ignored.
+            .task_stats(3, TaskStatsBuilder::new(start).build())
+            .build(),
+    );
+    assert_eq!(snapshot.now(), Some(later));
+    assert_eq!(snapshot.tasks().len(), 2);
+    assert_eq!(snapshot.tasks()[&1].stats.polls(), 5);
+    assert_eq!(snapshot.tasks()[&2].stats.dropped_time(), Some(later));
+
+    snapshot.clear_dropped();
+    assert_eq!(snapshot.tasks().keys().collect::<Vec<_>>(), vec![&1]);
+}
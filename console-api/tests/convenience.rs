@@ -0,0 +1,50 @@
+#![cfg(feature = "convenience")]
+
+use console_api::{
+    convenience::{TaskBuilder, TaskStatsBuilder, UpdateBuilder},
+    tasks, Id, MetaId,
+};
+use std::time::{Duration, SystemTime};
+
+#[test]
+fn builds_update() {
+    let now = SystemTime::now();
+    let created = now - Duration::from_secs(2);
+    let task = TaskBuilder::new(1, MetaId { id: 7 })
+        .kind(tasks::task::Kind::Blocking)
+        .field("task.name", "worker")
+        .build();
+    let stats = TaskStatsBuilder::new(created)
+        .polls(2)
+        .busy_time(Duration::from_millis(30))
+        .scheduled_time(Duration::from_millis(5))
+        .build();
+    let update = UpdateBuilder::new(now)
+        .new_task(task, stats)
+        .sequence(4)
+        .build();
+
+    assert_eq!(update.now_time(), Some(now));
+    assert_eq!(update.sequence, 4);
+    let task_update = update.task_update.expect("update should have tasks");
+    let task = &task_update.new_tasks[0];
+    assert_eq!(task.id, Some(Id::new(1)));
+    assert_eq!(task.kind(), tasks::task::Kind::Blocking);
+    assert_eq!(task.fields[0].to_string(), "task.name=worker");
+
+    let stats = &task_update.stats_update[&1];
+    assert_eq!(stats.polls(), 2);
+    assert_eq!(stats.busy_duration(), Some(Duration::from_millis(30)));
+    assert_eq!(stats.scheduled_duration(), Some(Duration::from_millis(5)));
+    assert_eq!(stats.total_duration(now), Some(Duration::from_secs(2)));
+}
+
+#[test]
+fn converts_ids() {
+    assert_eq!(Id::new(5).to_string(), "5");
+    assert_eq!(
+        tracing_core::span::Id::try_from(Id::new(5)).map(|id| id.into_u64()),
+        Ok(5)
+    );
+    assert!(tracing_core::span::Id::try_from(Id::new(0)).is_err());
+}
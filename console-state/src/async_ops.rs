@@ -1,53 +1,70 @@
 use crate::{
     intern::{self, InternedStr},
-    state::{
-        pb_duration,
-        resources::Resource,
-        store::{self, Id, Store},
-        tasks::Task,
-        Attribute, Field, Metadata, Visibility,
-    },
-    view,
+    pb_duration,
+    resources::Resource,
+    retention::PausedTime,
+    store::{self, Id, Store},
+    tasks::Task,
+    Attribute, Field, Metadata, Visibility,
 };
 use console_api as proto;
-use ratatui::text::Span;
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     convert::{TryFrom, TryInto},
     rc::{Rc, Weak},
     time::{Duration, SystemTime},
 };
 
 #[derive(Default, Debug)]
-pub(crate) struct AsyncOpsState {
+pub struct AsyncOpsState {
     async_ops: Store<AsyncOp>,
     dropped_events: u64,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Default)]
 #[repr(usize)]
-pub(crate) enum SortBy {
+pub enum SortBy {
+    #[default]
     Aid = 0,
     Task = 1,
     Source = 2,
     Total = 3,
     Busy = 4,
-    Idle = 5,
-    Polls = 6,
+    Pending = 5,
+    Idle = 6,
+    Polls = 7,
 }
 
 #[derive(Debug)]
-pub(crate) struct AsyncOp {
+pub struct AsyncOp {
     id: Id<AsyncOp>,
     parent_id: InternedStr,
     resource_id: Id<Resource>,
     meta_id: u64,
     source: InternedStr,
     stats: AsyncOpStats,
+    /// Poll counts as of each recent stats update, oldest first.
+    poll_history: VecDeque<PollSample>,
+    /// Changes to the async op's attributes, oldest first.
+    attribute_history: VecDeque<AttributeChange>,
+}
+
+/// The async op's poll count as of a stats update.
+#[derive(Debug, Copy, Clone)]
+pub struct PollSample {
+    pub at: SystemTime,
+    pub polls: u64,
+}
+
+/// The async op's attributes after a stats update changed them.
+#[derive(Debug, Clone)]
+pub struct AttributeChange {
+    pub at: SystemTime,
+    pub attributes: Vec<Attribute>,
 }
 
-pub(crate) type AsyncOpRef = store::Ref<AsyncOp>;
+pub type AsyncOpRef = store::Ref<AsyncOp>;
 
 #[derive(Debug)]
 struct AsyncOpStats {
@@ -56,37 +73,29 @@ struct AsyncOpStats {
 
     polls: u64,
     busy: Duration,
+    /// Time spent in polls that returned `Pending`, if the subscriber records
+    /// it.
+    pending: Option<Duration>,
     last_poll_started: Option<SystemTime>,
     last_poll_ended: Option<SystemTime>,
     idle: Option<Duration>,
     total: Option<Duration>,
     task_id: Option<Id<Task>>,
     task_id_str: InternedStr,
-    formatted_attributes: Vec<Vec<Span<'static>>>,
-}
-
-impl Default for SortBy {
-    fn default() -> Self {
-        Self::Aid
-    }
+    attributes: Vec<Attribute>,
 }
 
 impl SortBy {
     pub fn sort(&self, now: SystemTime, ops: &mut [Weak<RefCell<AsyncOp>>]) {
         match self {
-            Self::Aid => ops.sort_unstable_by_key(|ao| ao.upgrade().map(|a| a.borrow().id)),
-            Self::Task => ops.sort_unstable_by_key(|ao| ao.upgrade().map(|a| a.borrow().task_id())),
-            Self::Source => {
-                ops.sort_unstable_by_key(|ao| ao.upgrade().map(|a| a.borrow().source.clone()))
-            }
-            Self::Total => {
-                ops.sort_unstable_by_key(|ao| ao.upgrade().map(|a| a.borrow().total(now)))
-            }
-            Self::Busy => ops.sort_unstable_by_key(|ao| ao.upgrade().map(|a| a.borrow().busy(now))),
-            Self::Idle => ops.sort_unstable_by_key(|ao| ao.upgrade().map(|a| a.borrow().idle(now))),
-            Self::Polls => {
-                ops.sort_unstable_by_key(|ao| ao.upgrade().map(|a| a.borrow().stats.polls))
-            }
+            Self::Aid => ops.sort_by_key(|ao| ao.upgrade().map(|a| a.borrow().id)),
+            Self::Task => ops.sort_by_key(|ao| ao.upgrade().map(|a| a.borrow().task_id())),
+            Self::Source => ops.sort_by_key(|ao| ao.upgrade().map(|a| a.borrow().source.clone())),
+            Self::Total => ops.sort_by_key(|ao| ao.upgrade().map(|a| a.borrow().total(now))),
+            Self::Busy => ops.sort_by_key(|ao| ao.upgrade().map(|a| a.borrow().busy(now))),
+            Self::Pending => ops.sort_by_key(|ao| ao.upgrade().map(|a| a.borrow().stats.pending)),
+            Self::Idle => ops.sort_by_key(|ao| ao.upgrade().map(|a| a.borrow().idle(now))),
+            Self::Polls => ops.sort_by_key(|ao| ao.upgrade().map(|a| a.borrow().stats.polls)),
         }
     }
 }
@@ -100,6 +109,7 @@ impl TryFrom<usize> for SortBy {
             idx if idx == Self::Source as usize => Ok(Self::Source),
             idx if idx == Self::Total as usize => Ok(Self::Total),
             idx if idx == Self::Busy as usize => Ok(Self::Busy),
+            idx if idx == Self::Pending as usize => Ok(Self::Pending),
             idx if idx == Self::Idle as usize => Ok(Self::Idle),
             idx if idx == Self::Polls as usize => Ok(Self::Polls),
             _ => Err(()),
@@ -107,37 +117,32 @@ impl TryFrom<usize> for SortBy {
     }
 }
 
-impl view::SortBy for SortBy {
-    fn as_column(&self) -> usize {
-        *self as usize
-    }
-}
-
 impl AsyncOpsState {
     /// Returns any new async ops for a resource that were added since the last async ops update.
-    pub(crate) fn take_new_async_ops(&mut self) -> impl Iterator<Item = AsyncOpRef> + '_ {
+    pub fn take_new_async_ops(&mut self) -> impl Iterator<Item = AsyncOpRef> + '_ {
         self.async_ops.take_new_items()
     }
 
     /// Returns all async ops.
-    pub(crate) fn async_ops(&self) -> impl Iterator<Item = AsyncOpRef> + '_ {
+    pub fn async_ops(&self) -> impl Iterator<Item = AsyncOpRef> + '_ {
         self.async_ops.values().map(Rc::downgrade)
     }
 
     // Clippy warns us that having too many arguments is bad style. In this case, however
     // it does not make much sense to group any of them.
     #[allow(clippy::too_many_arguments)]
-    pub(crate) fn update_async_ops(
+    pub fn update_async_ops(
         &mut self,
-        styles: &view::Styles,
         strings: &mut intern::Strings,
         metas: &HashMap<u64, Metadata>,
         update: proto::async_ops::AsyncOpUpdate,
         resource_ids: &mut store::Ids<Resource>,
         task_ids: &mut store::Ids<Task>,
         visibility: Visibility,
+        now: Option<SystemTime>,
     ) {
         let mut stats_update = update.stats_update;
+        let seen_at = now.unwrap_or_else(SystemTime::now);
 
         self.async_ops
             .insert_with(visibility, update.new_async_ops, |ids, async_op| {
@@ -166,7 +171,6 @@ impl AsyncOpsState {
                 let stats = AsyncOpStats::from_proto(
                     stats_update.remove(&span_id)?,
                     meta,
-                    styles,
                     strings,
                     task_ids,
                 );
@@ -180,28 +184,37 @@ impl AsyncOpsState {
 
                 let source = strings.string(async_op.source);
 
-                let async_op = AsyncOp {
+                let mut async_op = AsyncOp {
                     id,
                     parent_id,
                     resource_id,
                     meta_id,
                     source,
                     stats,
+                    poll_history: VecDeque::new(),
+                    attribute_history: VecDeque::new(),
                 };
+                async_op.record_history(seen_at);
                 Some((id, async_op))
             });
 
         for (stats, mut async_op) in self.async_ops.updated(stats_update) {
             if let Some(meta) = metas.get(&async_op.meta_id) {
                 tracing::trace!(?async_op, ?stats, "processing stats update for");
-                async_op.stats = AsyncOpStats::from_proto(stats, meta, styles, strings, task_ids);
+                async_op.stats = AsyncOpStats::from_proto(stats, meta, strings, task_ids);
+                async_op.record_history(seen_at);
             }
         }
 
         self.dropped_events += update.dropped_events;
     }
 
-    pub(crate) fn retain_active(&mut self, now: SystemTime, retain_for: Duration) {
+    pub fn retain_active(
+        &mut self,
+        paused_time: &PausedTime,
+        now: SystemTime,
+        retain_for: Duration,
+    ) {
         self.async_ops.retain(|_, async_op| {
             let async_op = async_op.borrow();
 
@@ -209,51 +222,80 @@ impl AsyncOpsState {
                 .stats
                 .dropped_at
                 .map(|d| {
-                    let dropped_for = now.duration_since(d).unwrap_or_default();
+                    let dropped_for = paused_time.unpaused_since(d, now);
                     retain_for > dropped_for
                 })
                 .unwrap_or(true)
         })
     }
 
-    pub(crate) fn dropped_events(&self) -> u64 {
+    pub fn dropped_events(&self) -> u64 {
         self.dropped_events
     }
 }
 
 impl AsyncOp {
-    pub(crate) fn id(&self) -> Id<AsyncOp> {
+    /// The maximum number of samples kept in each of the async op's
+    /// histories.
+    const MAX_HISTORY: usize = 120;
+
+    /// Records the async op's current stats in its histories.
+    fn record_history(&mut self, at: SystemTime) {
+        self.poll_history.push_back(PollSample {
+            at,
+            polls: self.stats.polls,
+        });
+        if self.poll_history.len() > Self::MAX_HISTORY {
+            self.poll_history.pop_front();
+        }
+
+        let changed = self
+            .attribute_history
+            .back()
+            .map_or(true, |last| last.attributes != self.stats.attributes);
+        if changed && !self.stats.attributes.is_empty() {
+            self.attribute_history.push_back(AttributeChange {
+                at,
+                attributes: self.stats.attributes.clone(),
+            });
+            if self.attribute_history.len() > Self::MAX_HISTORY {
+                self.attribute_history.pop_front();
+            }
+        }
+    }
+
+    pub fn id(&self) -> Id<AsyncOp> {
         self.id
     }
 
-    pub(crate) fn parent_id(&self) -> &str {
+    pub fn parent_id(&self) -> &str {
         &self.parent_id
     }
 
-    pub(crate) fn resource_id(&self) -> Id<Resource> {
+    pub fn resource_id(&self) -> Id<Resource> {
         self.resource_id
     }
 
-    pub(crate) fn task_id(&self) -> Option<Id<Task>> {
+    pub fn task_id(&self) -> Option<Id<Task>> {
         self.stats.task_id
     }
 
-    pub(crate) fn task_id_str(&self) -> &str {
+    pub fn task_id_str(&self) -> &str {
         &self.stats.task_id_str
     }
 
-    pub(crate) fn source(&self) -> &str {
+    pub fn source(&self) -> &str {
         &self.source
     }
 
-    pub(crate) fn total(&self, since: SystemTime) -> Duration {
+    pub fn total(&self, since: SystemTime) -> Duration {
         self.stats
             .total
             .or_else(|| since.duration_since(self.stats.created_at).ok())
             .unwrap_or_default()
     }
 
-    pub(crate) fn busy(&self, since: SystemTime) -> Duration {
+    pub fn busy(&self, since: SystemTime) -> Duration {
         if let (Some(last_poll_started), None) =
             (self.stats.last_poll_started, self.stats.last_poll_ended)
         {
@@ -263,23 +305,56 @@ impl AsyncOp {
         self.stats.busy
     }
 
-    pub(crate) fn idle(&self, since: SystemTime) -> Duration {
+    /// Returns the time spent in completed polls that returned `Pending`.
+    pub fn pending(&self) -> Option<Duration> {
+        self.stats.pending
+    }
+
+    pub fn idle(&self, since: SystemTime) -> Duration {
         self.stats
             .idle
             .or_else(|| self.total(since).checked_sub(self.busy(since)))
             .unwrap_or_default()
     }
 
-    pub(crate) fn total_polls(&self) -> u64 {
+    pub fn total_polls(&self) -> u64 {
         self.stats.polls
     }
 
-    pub(crate) fn dropped(&self) -> bool {
+    pub fn dropped(&self) -> bool {
         self.stats.total.is_some()
     }
 
-    pub(crate) fn formatted_attributes(&self) -> &[Vec<Span<'static>>] {
-        &self.stats.formatted_attributes
+    /// Returns the async op's attributes, sorted by name.
+    pub fn attributes(&self) -> &[Attribute] {
+        &self.stats.attributes
+    }
+
+    pub fn created_at(&self) -> SystemTime {
+        self.stats.created_at
+    }
+
+    pub fn dropped_at(&self) -> Option<SystemTime> {
+        self.stats.dropped_at
+    }
+
+    pub fn last_poll_started(&self) -> Option<SystemTime> {
+        self.stats.last_poll_started
+    }
+
+    pub fn last_poll_ended(&self) -> Option<SystemTime> {
+        self.stats.last_poll_ended
+    }
+
+    /// Returns the async op's poll count as of each recent stats update,
+    /// oldest first.
+    pub fn poll_history(&self) -> impl ExactSizeIterator<Item = &PollSample> + '_ {
+        self.poll_history.iter()
+    }
+
+    /// Returns each recent change to the async op's attributes, oldest first.
+    pub fn attribute_history(&self) -> impl DoubleEndedIterator<Item = &AttributeChange> + '_ {
+        self.attribute_history.iter()
     }
 }
 
@@ -287,7 +362,6 @@ impl AsyncOpStats {
     fn from_proto(
         pb: proto::async_ops::Stats,
         meta: &Metadata,
-        styles: &view::Styles,
         strings: &mut intern::Strings,
         task_ids: &mut store::Ids<Task>,
     ) -> Self {
@@ -317,8 +391,9 @@ impl AsyncOpStats {
 
         let poll_stats = pb.poll_stats.expect("task should have poll stats");
         let busy = poll_stats.busy_time.map(pb_duration).unwrap_or_default();
+        let pending = poll_stats.pending_time.map(pb_duration);
         let idle = total.map(|total| total.checked_sub(busy).unwrap_or_default());
-        let formatted_attributes = Attribute::make_formatted(styles, &mut attributes);
+        attributes.sort_unstable();
         let task_id = pb.task_id.map(|id| task_ids.id_for(id.id));
         let task_id_str = strings.string(
             task_id
@@ -332,12 +407,13 @@ impl AsyncOpStats {
             task_id,
             task_id_str,
             busy,
+            pending,
             last_poll_started: poll_stats.last_poll_started.map(|v| v.try_into().unwrap()),
             last_poll_ended: poll_stats.last_poll_ended.map(|v| v.try_into().unwrap()),
             polls: poll_stats.polls,
             created_at,
             dropped_at,
-            formatted_attributes,
+            attributes,
         }
     }
 }
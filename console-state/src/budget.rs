@@ -0,0 +1,296 @@
+use crate::histogram::DurationHistogram;
+use hdrhistogram::Histogram;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr, time::Duration};
+
+/// A latency budget for the tasks with a given name, such as
+/// `poll p99 < 10ms`.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct LatencyBudget {
+    metric: BudgetMetric,
+    percentile: f64,
+    limit: Duration,
+}
+
+/// The task durations a [`LatencyBudget`] applies to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BudgetMetric {
+    /// The time spent in each poll of the task.
+    Poll,
+    /// The time the task spent scheduled before each poll.
+    Scheduled,
+}
+
+/// Whether a task is within its latency budget.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BudgetStatus {
+    /// The percentile is well within the budget.
+    Met,
+    /// The percentile is within the budget, but over
+    /// [`BudgetTracker::NEAR_FRACTION`] of it.
+    Near,
+    /// The percentile is over the budget.
+    Exceeded,
+}
+
+/// Tracks a task's durations to check them against its latency budget.
+///
+/// The console only receives a task's poll and scheduled time histograms
+/// while its details are being viewed, so the tracker estimates them from
+/// the task's stats updates: each poll since the previous update is recorded
+/// with the mean duration of those polls. While the task's details are being
+/// viewed, the exact histogram is used instead.
+///
+/// The estimate only captures how the means vary between updates, so it
+/// understates the higher percentiles. Statuses read from it are marked as
+/// [estimated], and shouldn't be used to flag a task.
+///
+/// [estimated]: BudgetTracker::is_estimated
+#[derive(Debug)]
+pub struct BudgetTracker {
+    budget: LatencyBudget,
+    estimated: Histogram<u64>,
+    exact: Option<DurationHistogram>,
+}
+
+// === impl LatencyBudget ===
+
+impl LatencyBudget {
+    pub fn metric(&self) -> BudgetMetric {
+        self.metric
+    }
+
+    pub fn percentile(&self) -> f64 {
+        self.percentile
+    }
+}
+
+impl FromStr for LatencyBudget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid latency budget {s:?}, expected a budget like \"poll p99 < 10ms\" or \
+                \"scheduled p50 < 1ms\""
+            )
+        };
+        let mut parts = s.split_whitespace();
+        let metric = match parts.next() {
+            Some("poll") => BudgetMetric::Poll,
+            Some("scheduled") => BudgetMetric::Scheduled,
+            _ => return Err(invalid()),
+        };
+        let percentile = parts
+            .next()
+            .and_then(|p| p.strip_prefix('p'))
+            .and_then(|p| p.parse::<f64>().ok())
+            .filter(|p| *p > 0.0 && *p <= 100.0)
+            .ok_or_else(invalid)?;
+        if parts.next() != Some("<") {
+            return Err(invalid());
+        }
+        let limit = parts.collect::<Vec<_>>().join(" ");
+        let limit = humantime::parse_duration(&limit).map_err(|_| invalid())?;
+        Ok(Self {
+            metric,
+            percentile,
+            limit,
+        })
+    }
+}
+
+impl TryFrom<String> for LatencyBudget {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<LatencyBudget> for String {
+    fn from(budget: LatencyBudget) -> Self {
+        budget.to_string()
+    }
+}
+
+impl fmt::Display for LatencyBudget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} p{} < {}",
+            self.metric,
+            self.percentile,
+            humantime::format_duration(self.limit)
+        )
+    }
+}
+
+// === impl BudgetMetric ===
+
+impl fmt::Display for BudgetMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BudgetMetric::Poll => f.write_str("poll"),
+            BudgetMetric::Scheduled => f.write_str("scheduled"),
+        }
+    }
+}
+
+// === impl BudgetTracker ===
+
+impl BudgetTracker {
+    /// The fraction of the budget above which a task is considered close to
+    /// exceeding it.
+    pub const NEAR_FRACTION: f64 = 0.8;
+
+    pub fn new(budget: LatencyBudget) -> Self {
+        Self {
+            budget,
+            estimated: Histogram::new(2).expect("2 significant figures is valid"),
+            exact: None,
+        }
+    }
+
+    pub fn budget(&self) -> &LatencyBudget {
+        &self.budget
+    }
+
+    /// Records `polls` polls which took `total` time between them, for the
+    /// budget's metric.
+    pub fn record(&mut self, polls: u64, total: Duration) {
+        if polls == 0 {
+            return;
+        }
+        let mean = total.as_nanos() / polls as u128;
+        let mean = u64::try_from(mean).unwrap_or(u64::MAX);
+        if let Err(error) = self.estimated.record_n(mean, polls) {
+            tracing::debug!(%error, mean, polls, "failed to record latency budget sample");
+        }
+    }
+
+    /// Replaces the estimated histogram with the task's exact histogram for
+    /// the budget's metric, or goes back to the estimate if it is `None`.
+    pub fn set_exact(&mut self, histogram: Option<DurationHistogram>) {
+        self.exact = histogram;
+    }
+
+    /// Returns whether [`current`] and [`status`] are estimated from the mean
+    /// durations between updates, rather than read from the task's exact
+    /// histogram.
+    ///
+    /// [`current`]: BudgetTracker::current
+    /// [`status`]: BudgetTracker::status
+    pub fn is_estimated(&self) -> bool {
+        !self
+            .exact
+            .as_ref()
+            .is_some_and(|exact| !exact.histogram.is_empty())
+    }
+
+    /// Returns the budget's percentile of the task's durations, if any have
+    /// been recorded.
+    pub fn current(&self) -> Option<Duration> {
+        match &self.exact {
+            Some(exact) if !exact.histogram.is_empty() => {
+                Some(exact.percentile(self.budget.percentile))
+            }
+            _ if !self.estimated.is_empty() => Some(Duration::from_nanos(
+                self.estimated.value_at_percentile(self.budget.percentile),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the task is within its budget, if any durations have
+    /// been recorded.
+    pub fn status(&self) -> Option<BudgetStatus> {
+        let current = self.current()?;
+        let status = if current > self.budget.limit {
+            BudgetStatus::Exceeded
+        } else if current.as_secs_f64() > self.budget.limit.as_secs_f64() * Self::NEAR_FRACTION {
+            BudgetStatus::Near
+        } else {
+            BudgetStatus::Met
+        };
+        Some(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_budgets() {
+        let budget = "poll p99 < 10ms".parse::<LatencyBudget>().unwrap();
+        assert_eq!(budget.metric(), BudgetMetric::Poll);
+        assert_eq!(budget.percentile(), 99.0);
+        assert_eq!(budget.limit, Duration::from_millis(10));
+        assert_eq!(budget.to_string(), "poll p99 < 10ms");
+
+        let budget = "scheduled p99.9 < 1s 500ms"
+            .parse::<LatencyBudget>()
+            .unwrap();
+        assert_eq!(budget.metric(), BudgetMetric::Scheduled);
+        assert_eq!(budget.percentile(), 99.9);
+        assert_eq!(budget.limit, Duration::from_millis(1500));
+
+        for invalid in [
+            "",
+            "busy p99 < 10ms",
+            "poll 99 < 10ms",
+            "poll p0 < 10ms",
+            "poll p101 < 10ms",
+            "poll p99 > 10ms",
+            "poll p99 < soon",
+        ] {
+            assert!(
+                invalid.parse::<LatencyBudget>().is_err(),
+                "{invalid:?} should not parse"
+            );
+        }
+    }
+
+    #[test]
+    fn status_from_estimate() {
+        let budget = "poll p50 < 10ms".parse().unwrap();
+        let mut tracker = BudgetTracker::new(budget);
+        assert_eq!(tracker.status(), None);
+
+        tracker.record(10, Duration::from_millis(20));
+        assert_eq!(tracker.status(), Some(BudgetStatus::Met));
+
+        tracker.record(20, Duration::from_millis(180));
+        assert_eq!(tracker.status(), Some(BudgetStatus::Near));
+
+        tracker.record(40, Duration::from_millis(800));
+        assert_eq!(tracker.status(), Some(BudgetStatus::Exceeded));
+        assert!(tracker.is_estimated());
+    }
+
+    #[test]
+    fn exact_status_is_not_estimated() {
+        let budget = "poll p99 < 10ms".parse().unwrap();
+        let mut tracker = BudgetTracker::new(budget);
+        // Every poll took 5ms on average, but the slowest took 50ms.
+        tracker.record(100, Duration::from_millis(500));
+        assert_eq!(tracker.status(), Some(BudgetStatus::Met));
+        assert!(tracker.is_estimated());
+
+        let mut histogram = Histogram::new(2).unwrap();
+        histogram.record_n(1_000_000, 98).unwrap();
+        histogram.record_n(50_000_000, 2).unwrap();
+        tracker.set_exact(Some(DurationHistogram {
+            histogram,
+            high_outliers: 0,
+            highest_outlier: None,
+        }));
+        assert_eq!(tracker.status(), Some(BudgetStatus::Exceeded));
+        assert!(!tracker.is_estimated());
+
+        tracker.set_exact(None);
+        assert!(tracker.is_estimated());
+    }
+}
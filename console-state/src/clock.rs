@@ -0,0 +1,31 @@
+//! The target's clock, as estimated by the console.
+use std::{
+    sync::atomic::{AtomicI64, Ordering},
+    time::{Duration, SystemTime},
+};
+
+/// The estimated offset of the target's clock, in nanoseconds, for
+/// [`target_now`].
+static TARGET_CLOCK_OFFSET: AtomicI64 = AtomicI64::new(0);
+
+/// Sets how far the target's clock is estimated to be ahead of this
+/// process's, in seconds. This is negative if the target's clock is behind.
+pub fn set_target_offset(offset: f64) {
+    TARGET_CLOCK_OFFSET.store((offset * 1e9) as i64, Ordering::Relaxed);
+}
+
+/// Returns the current time by the target's clock, as estimated from the
+/// skew between it and this process's clock.
+///
+/// This should be used instead of [`SystemTime::now`] when comparing against
+/// times reported by the target.
+pub fn target_now() -> SystemTime {
+    let offset = TARGET_CLOCK_OFFSET.load(Ordering::Relaxed);
+    let now = SystemTime::now();
+    let skew = Duration::from_nanos(offset.unsigned_abs());
+    if offset >= 0 {
+        now + skew
+    } else {
+        now.checked_sub(skew).unwrap_or(now)
+    }
+}
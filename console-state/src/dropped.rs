@@ -0,0 +1,67 @@
+use std::collections::VecDeque;
+
+use console_api as proto;
+
+/// A history of how many events the target reported dropping in each update,
+/// because its event buffer was full.
+#[derive(Debug, Default)]
+pub struct DroppedEvents {
+    /// Dropped event counts for the most recent updates, oldest first.
+    samples: VecDeque<DroppedSample>,
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct DroppedSample {
+    pub tasks: u64,
+    pub resources: u64,
+    pub async_ops: u64,
+}
+
+impl DroppedEvents {
+    /// The maximum number of updates to keep counts for.
+    const MAX_SAMPLES: usize = 120;
+
+    /// Records the dropped event counts reported by `update`, returning them.
+    pub fn record(&mut self, update: &proto::instrument::Update) -> DroppedSample {
+        let sample = DroppedSample {
+            tasks: update
+                .task_update
+                .as_ref()
+                .map_or(0, |update| update.dropped_events),
+            resources: update
+                .resource_update
+                .as_ref()
+                .map_or(0, |update| update.dropped_events),
+            async_ops: update
+                .async_op_update
+                .as_ref()
+                .map_or(0, |update| update.dropped_events),
+        };
+        self.samples.push_back(sample);
+        while self.samples.len() > Self::MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        sample
+    }
+
+    /// Returns the dropped event counts for each recorded update, oldest
+    /// first.
+    pub fn samples(&self) -> impl ExactSizeIterator<Item = &DroppedSample> + '_ {
+        self.samples.iter()
+    }
+
+    /// Returns the number of recorded updates in which any events were
+    /// dropped.
+    pub fn updates_with_drops(&self) -> usize {
+        self.samples
+            .iter()
+            .filter(|sample| sample.total() > 0)
+            .count()
+    }
+}
+
+impl DroppedSample {
+    pub fn total(&self) -> u64 {
+        self.tasks + self.resources + self.async_ops
+    }
+}
@@ -0,0 +1,141 @@
+use std::{collections::VecDeque, time::Duration};
+
+use console_api as proto;
+
+/// A history of the health the target's subscriber reported in each update,
+/// which helps to explain gaps in the data it sent.
+#[derive(Debug, Default)]
+pub struct SubscriberHealth {
+    /// The subscriber's stats for the most recent updates, oldest first.
+    samples: VecDeque<HealthSample>,
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct HealthSample {
+    /// The most events that were waiting in the event buffer.
+    pub max_buffered: u64,
+    /// The number of events the event buffer can hold.
+    pub capacity: u64,
+    /// The number of times the buffer was drained early because it was
+    /// filling up.
+    pub flushes: u64,
+    /// The number of events of each kind which were dropped.
+    pub dropped: proto::instrument::DroppedEvents,
+    /// The longest a single pass of the aggregator took.
+    pub max_aggregation_time: Duration,
+}
+
+impl SubscriberHealth {
+    /// The maximum number of updates to keep stats for.
+    const MAX_SAMPLES: usize = 120;
+
+    /// Records the subscriber's stats reported by an update.
+    pub fn record(&mut self, stats: proto::instrument::SubscriberStats) {
+        let sample = HealthSample {
+            max_buffered: stats.max_event_buffer_len,
+            capacity: stats.event_buffer_capacity,
+            flushes: stats.flushes,
+            dropped: stats.dropped_events.unwrap_or_default(),
+            max_aggregation_time: stats
+                .max_aggregation_time
+                .and_then(|time| time.try_into().ok())
+                .unwrap_or_default(),
+        };
+        self.samples.push_back(sample);
+        while self.samples.len() > Self::MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Returns the stats for each recorded update, oldest first.
+    pub fn samples(&self) -> impl ExactSizeIterator<Item = &HealthSample> + '_ {
+        self.samples.iter()
+    }
+
+    /// Returns the stats reported by the most recent update, if the target
+    /// reports them.
+    pub fn latest(&self) -> Option<&HealthSample> {
+        self.samples.back()
+    }
+
+    /// Returns the fullest the event buffer was in any recorded update, as a
+    /// percentage of its capacity.
+    pub fn peak_buffer_percent(&self) -> f64 {
+        self.samples
+            .iter()
+            .map(HealthSample::buffer_percent)
+            .fold(0.0, f64::max)
+    }
+
+    /// Returns the total number of early flushes in the recorded updates.
+    pub fn flushes(&self) -> u64 {
+        self.samples.iter().map(|sample| sample.flushes).sum()
+    }
+
+    /// Returns the total number of events of each kind dropped in the
+    /// recorded updates.
+    pub fn dropped(&self) -> proto::instrument::DroppedEvents {
+        self.samples.iter().fold(
+            proto::instrument::DroppedEvents::default(),
+            |total, sample| proto::instrument::DroppedEvents {
+                metadata: total.metadata + sample.dropped.metadata,
+                spawns: total.spawns + sample.dropped.spawns,
+                resources: total.resources + sample.dropped.resources,
+                async_ops: total.async_ops + sample.dropped.async_ops,
+                poll_ops: total.poll_ops + sample.dropped.poll_ops,
+            },
+        )
+    }
+
+    /// Returns the longest single pass of the aggregator in the recorded
+    /// updates.
+    pub fn max_aggregation_time(&self) -> Duration {
+        self.samples
+            .iter()
+            .map(|sample| sample.max_aggregation_time)
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+impl HealthSample {
+    /// Returns the fullest the event buffer was, as a percentage of its
+    /// capacity.
+    pub fn buffer_percent(&self) -> f64 {
+        if self.capacity == 0 {
+            return 0.0;
+        }
+        self.max_buffered as f64 / self.capacity as f64 * 100.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn totals_across_updates() {
+        let mut health = SubscriberHealth::default();
+        assert!(health.latest().is_none());
+
+        let stats =
+            |max_event_buffer_len, flushes, spawns, millis| proto::instrument::SubscriberStats {
+                max_event_buffer_len,
+                event_buffer_capacity: 200,
+                flushes,
+                dropped_events: Some(proto::instrument::DroppedEvents {
+                    spawns,
+                    ..Default::default()
+                }),
+                max_aggregation_time: Duration::from_millis(millis).try_into().ok(),
+            };
+        health.record(stats(150, 2, 3, 4));
+        health.record(stats(20, 1, 0, 9));
+
+        assert_eq!(health.peak_buffer_percent(), 75.0);
+        assert_eq!(health.latest().unwrap().buffer_percent(), 10.0);
+        assert_eq!(health.flushes(), 3);
+        assert_eq!(health.dropped().spawns, 3);
+        assert_eq!(health.max_aggregation_time(), Duration::from_millis(9));
+    }
+}
@@ -2,17 +2,15 @@ use console_api::tasks as proto;
 use hdrhistogram::Histogram;
 use std::{io::Cursor, time::Duration};
 
-#[derive(Debug)]
-pub(crate) struct DurationHistogram {
-    pub(crate) histogram: Histogram<u64>,
-    pub(crate) high_outliers: u64,
-    pub(crate) highest_outlier: Option<Duration>,
+#[derive(Clone, Debug)]
+pub struct DurationHistogram {
+    pub histogram: Histogram<u64>,
+    pub high_outliers: u64,
+    pub highest_outlier: Option<Duration>,
 }
 
 impl DurationHistogram {
-    pub(crate) fn from_poll_durations(
-        proto: &proto::task_details::PollTimesHistogram,
-    ) -> Option<Self> {
+    pub fn from_poll_durations(proto: &proto::task_details::PollTimesHistogram) -> Option<Self> {
         match proto {
             proto::task_details::PollTimesHistogram::Histogram(hist) => Self::from_proto(hist),
             proto::task_details::PollTimesHistogram::LegacyHistogram(bytes) => {
@@ -21,6 +19,11 @@ impl DurationHistogram {
         }
     }
 
+    /// Returns the duration at the given percentile, between 0 and 100.
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        Duration::from_nanos(self.histogram.value_at_percentile(percentile))
+    }
+
     fn from_proto_legacy(bytes: &[u8]) -> Option<Self> {
         let histogram = deserialize_histogram(bytes)?;
         Some(Self {
@@ -30,7 +33,7 @@ impl DurationHistogram {
         })
     }
 
-    pub(crate) fn from_proto(proto: &proto::DurationHistogram) -> Option<Self> {
+    pub fn from_proto(proto: &proto::DurationHistogram) -> Option<Self> {
         let histogram = deserialize_histogram(&proto.raw_histogram[..])?;
         Some(Self {
             histogram,
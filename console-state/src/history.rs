@@ -0,0 +1,274 @@
+//! A bounded history of the updates received from the instrumented
+//! application, so that the console can step back through time while paused.
+//!
+//! Updates only contain what changed since the previous update, so to display
+//! the state as of an earlier update, the [`State`](super::State) is rebuilt by
+//! replaying every update up to that point. Updates that fall out of the
+//! memory budget are merged into a single base update, rather than discarded.
+use console_api as proto;
+use prost::Message;
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, Default)]
+pub struct History {
+    /// All the updates older than those in `updates`, merged together.
+    base: Option<proto::instrument::Update>,
+    updates: VecDeque<proto::instrument::Update>,
+    /// The encoded size of the updates in `updates`.
+    bytes: usize,
+    /// The maximum encoded size of the updates in `updates`. If this is 0,
+    /// no history is kept.
+    budget: usize,
+    /// If the console is displaying an earlier update, the index of that
+    /// update in `updates`.
+    cursor: Option<usize>,
+}
+
+impl History {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            ..Self::default()
+        }
+    }
+
+    /// Records a new update, merging the oldest updates into the base if the
+    /// memory budget is exceeded.
+    pub fn push(&mut self, update: &proto::instrument::Update) {
+        if self.budget == 0 {
+            return;
+        }
+
+        self.bytes += update.encoded_len();
+        self.updates.push_back(update.clone());
+        while self.bytes > self.budget && self.updates.len() > 1 {
+            let oldest = self.updates.pop_front().expect("updates can't be empty");
+            self.bytes -= oldest.encoded_len();
+            self.cursor = self.cursor.map(|cursor| cursor.saturating_sub(1));
+            match self.base {
+                Some(ref mut base) => merge(base, oldest),
+                None => self.base = Some(oldest),
+            }
+        }
+    }
+
+    /// Moves the cursor one update back, returning `false` if there is no
+    /// earlier update to display.
+    pub fn step_back(&mut self) -> bool {
+        let latest = match self.updates.len().checked_sub(1) {
+            Some(latest) => latest,
+            None => return false,
+        };
+        match self.cursor.unwrap_or(latest).checked_sub(1) {
+            Some(cursor) => {
+                self.cursor = Some(cursor);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves the cursor one update forward, returning `false` if the latest
+    /// update is already displayed.
+    pub fn step_forward(&mut self) -> bool {
+        let Some(cursor) = self.cursor else {
+            return false;
+        };
+        let cursor = cursor + 1;
+        self.cursor = (cursor + 1 < self.updates.len()).then_some(cursor);
+        true
+    }
+
+    /// Moves the cursor to the latest update, returning `false` if it was
+    /// already there.
+    pub fn step_to_latest(&mut self) -> bool {
+        self.cursor.take().is_some()
+    }
+
+    /// Returns how many updates back from the latest one the cursor is, or
+    /// `None` if the latest update is displayed.
+    pub fn steps_back(&self) -> Option<usize> {
+        self.cursor.map(|cursor| self.updates.len() - 1 - cursor)
+    }
+
+    /// Returns every update up to and including the one at the cursor, in the
+    /// order they should be replayed.
+    pub fn replay(&self) -> impl Iterator<Item = &proto::instrument::Update> + '_ {
+        let end = self.cursor.map_or(self.updates.len(), |cursor| cursor + 1);
+        self.base.iter().chain(self.updates.range(..end))
+    }
+}
+
+/// Merges `next` into `base`, so that replaying `base` is equivalent to
+/// replaying the original `base` followed by `next`.
+///
+/// Tasks, resources, and async ops that were dropped by the time of `next` are
+/// removed from the merged update, so that the base update doesn't grow
+/// without bound.
+fn merge(base: &mut proto::instrument::Update, next: proto::instrument::Update) {
+    base.now = next.now.or(base.now);
+    base.runtime_stats = next.runtime_stats.or(base.runtime_stats);
+
+    if let Some(next) = next.new_metadata {
+        base.new_metadata
+            .get_or_insert_with(Default::default)
+            .metadata
+            .extend(next.metadata);
+    }
+
+    if let Some(next) = next.task_update {
+        let base = base.task_update.get_or_insert_with(Default::default);
+        base.new_tasks.extend(next.new_tasks);
+        base.stats_update.extend(next.stats_update);
+        base.dropped_events += next.dropped_events;
+        retain_live(
+            &mut base.new_tasks,
+            &mut base.stats_update,
+            |task| task.id,
+            |stats| stats.dropped_at,
+        );
+    }
+
+    if let Some(next) = next.resource_update {
+        let base = base.resource_update.get_or_insert_with(Default::default);
+        base.new_resources.extend(next.new_resources);
+        base.stats_update.extend(next.stats_update);
+        base.new_poll_ops.extend(next.new_poll_ops);
+        base.dropped_events += next.dropped_events;
+        retain_live(
+            &mut base.new_resources,
+            &mut base.stats_update,
+            |resource| resource.id,
+            |stats| stats.dropped_at,
+        );
+    }
+
+    if let Some(next) = next.async_op_update {
+        let base = base.async_op_update.get_or_insert_with(Default::default);
+        base.new_async_ops.extend(next.new_async_ops);
+        base.stats_update.extend(next.stats_update);
+        base.dropped_events += next.dropped_events;
+        retain_live(
+            &mut base.new_async_ops,
+            &mut base.stats_update,
+            |async_op| async_op.id,
+            |stats| stats.dropped_at,
+        );
+    }
+}
+
+/// Removes the items that have been dropped, along with their stats.
+fn retain_live<T, S>(
+    items: &mut Vec<T>,
+    stats: &mut HashMap<u64, S>,
+    id: impl Fn(&T) -> Option<proto::Id>,
+    dropped_at: impl Fn(&S) -> Option<prost_types::Timestamp>,
+) {
+    stats.retain(|_, stats| dropped_at(stats).is_none());
+    items.retain(|item| id(item).is_some_and(|id| stats.contains_key(&id.id)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(now: i64, new_tasks: &[u64], dropped: &[u64]) -> proto::instrument::Update {
+        let stats = new_tasks
+            .iter()
+            .map(|&id| (id, proto::tasks::Stats::default()))
+            .chain(dropped.iter().map(|&id| {
+                let stats = proto::tasks::Stats {
+                    dropped_at: Some(prost_types::Timestamp::default()),
+                    ..Default::default()
+                };
+                (id, stats)
+            }))
+            .collect();
+        proto::instrument::Update {
+            now: Some(prost_types::Timestamp {
+                seconds: now,
+                nanos: 0,
+            }),
+            task_update: Some(proto::tasks::TaskUpdate {
+                new_tasks: new_tasks
+                    .iter()
+                    .map(|&id| proto::tasks::Task {
+                        id: Some(id.into()),
+                        ..Default::default()
+                    })
+                    .collect(),
+                stats_update: stats,
+                dropped_events: 0,
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn replayed_times(history: &History) -> Vec<i64> {
+        history
+            .replay()
+            .map(|update| update.now.unwrap().seconds)
+            .collect()
+    }
+
+    #[test]
+    fn steps_back_and_forward() {
+        let mut history = History::new(usize::MAX);
+        for now in 0..3 {
+            history.push(&update(now, &[], &[]));
+        }
+        assert_eq!(history.steps_back(), None);
+        assert_eq!(replayed_times(&history), [0, 1, 2]);
+
+        assert!(history.step_back());
+        assert!(history.step_back());
+        assert!(!history.step_back());
+        assert_eq!(history.steps_back(), Some(2));
+        assert_eq!(replayed_times(&history), [0]);
+
+        assert!(history.step_forward());
+        assert_eq!(replayed_times(&history), [0, 1]);
+        assert!(history.step_forward());
+        assert_eq!(history.steps_back(), None);
+        assert!(!history.step_forward());
+    }
+
+    #[test]
+    fn merges_updates_over_budget() {
+        let first = update(0, &[1, 2], &[]);
+        let second = update(1, &[3], &[1]);
+        let third = update(2, &[], &[]);
+        let budget = second.encoded_len() + third.encoded_len();
+        let mut history = History::new(budget);
+        history.push(&first);
+        history.push(&second);
+        history.push(&third);
+        history.push(&update(3, &[], &[]));
+
+        // The first two updates are merged into the base, and task 1 was
+        // dropped, so it is pruned.
+        assert_eq!(replayed_times(&history), [1, 2, 3]);
+        let base = history
+            .replay()
+            .next()
+            .unwrap()
+            .task_update
+            .as_ref()
+            .unwrap();
+        let mut ids = base
+            .new_tasks
+            .iter()
+            .map(|task| task.id.unwrap().id)
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        assert_eq!(ids, [2, 3]);
+    }
+
+    #[test]
+    fn disabled_without_budget() {
+        let mut history = History::new(0);
+        history.push(&update(0, &[1], &[]));
+        assert_eq!(history.replay().count(), 0);
+        assert!(!history.step_back());
+    }
+}
@@ -13,15 +13,15 @@ use std::{
 /// simple and doesn't involve any unsafe code. We could almost certainly
 /// replace it with something faster if it becomes a bottleneck.
 #[derive(Debug, Default)]
-pub(crate) struct Strings {
+pub struct Strings {
     strings: HashSet<InternedStr>,
 }
 
 #[derive(Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
-pub(crate) struct InternedStr(Rc<String>);
+pub struct InternedStr(Rc<String>);
 
 impl Strings {
-    pub(crate) fn string_ref<Q>(&mut self, string: &Q) -> InternedStr
+    pub fn string_ref<Q>(&mut self, string: &Q) -> InternedStr
     where
         InternedStr: Borrow<Q>,
         Q: Hash + Eq + ToOwned<Owned = String> + ?Sized,
@@ -33,7 +33,7 @@ impl Strings {
         self.insert(string.to_owned())
     }
 
-    pub(crate) fn string(&mut self, string: String) -> InternedStr {
+    pub fn string(&mut self, string: String) -> InternedStr {
         if let Some(s) = self.strings.get(&string) {
             return s.clone();
         }
@@ -48,7 +48,7 @@ impl Strings {
     }
 
     /// Drop any interned strings that are not currently referenced.
-    pub(crate) fn retain_referenced(&mut self) {
+    pub fn retain_referenced(&mut self) {
         const FOUR_KILOBYTES: usize = 4 * 1024;
 
         let len0 = self.strings.len();
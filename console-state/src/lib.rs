@@ -0,0 +1,1629 @@
+#![doc = include_str!("../README.md")]
+use self::{
+    async_ops::AsyncOpsState,
+    budget::{BudgetMetric, LatencyBudget},
+    dropped::DroppedEvents,
+    health::SubscriberHealth,
+    history::History,
+    resources::ResourcesState,
+    retention::PausedTime,
+    runtime::RuntimeState,
+};
+use crate::{intern::InternedStr, warnings::Linter};
+use console_api as proto;
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::HashMap,
+    convert::{TryFrom, TryInto},
+    fmt, mem,
+    rc::Rc,
+    time::{Duration, SystemTime},
+};
+use tasks::{Details, Task, TasksState};
+
+pub mod async_ops;
+pub mod budget;
+pub mod clock;
+pub mod dropped;
+pub mod health;
+pub mod histogram;
+pub mod history;
+pub mod intern;
+pub mod resources;
+pub mod retention;
+pub mod runtime;
+pub mod stalls;
+pub mod store;
+pub mod tasks;
+pub mod timers;
+pub mod util;
+pub mod warnings;
+
+pub use self::store::Id;
+
+pub type DetailsRef = Rc<RefCell<Option<Details>>>;
+
+#[derive(Default, Debug)]
+pub struct State {
+    metas: HashMap<u64, Metadata>,
+    last_updated_at: Option<SystemTime>,
+    temporality: Temporality,
+    tasks_state: TasksState,
+    resources_state: ResourcesState,
+    async_ops_state: AsyncOpsState,
+    runtime_state: RuntimeState,
+    current_task_details: DetailsRef,
+    retention: Retention,
+    /// When the console was paused, which doesn't count towards `retention`.
+    paused_time: PausedTime,
+    /// How long tasks must be idle for to be considered stalled.
+    stall_threshold: Option<Duration>,
+    strings: intern::Strings,
+    history: History,
+    session: SessionStats,
+    dropped_events: DroppedEvents,
+    subscriber_health: SubscriberHealth,
+    /// The sequence number of the last update received, if the target
+    /// numbers its updates.
+    last_sequence: Option<u64>,
+    /// When updates were most recently missed, and how many have been missed
+    /// since the warning about them was last shown.
+    missed_updates: Option<(SystemTime, u64)>,
+    /// Whether the target flagged the latest update as lagging, leaving
+    /// details out of it because the console fell behind.
+    lagging: bool,
+    /// Whether the target truncated the latest update to keep it small
+    /// enough to decode, leaving some changes for later updates.
+    truncated: bool,
+}
+
+/// Totals observed since the console started, across reconnections.
+///
+/// Unlike the rest of the state, these are not rebuilt when stepping through
+/// the history.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SessionStats {
+    /// The number of updates received.
+    pub updates: u64,
+    /// The number of task spawns observed.
+    pub tasks_spawned: u64,
+    /// The number of task drops observed.
+    pub tasks_dropped: u64,
+    /// The number of events the target reported dropping because its event
+    /// buffer was full.
+    pub dropped_events: u64,
+    /// The number of updates the target published which were never received,
+    /// for example because the console fell behind and was disconnected.
+    pub missed_updates: u64,
+}
+
+/// How long to keep each kind of item after it has been dropped. `None` keeps
+/// them forever.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Retention {
+    pub tasks: Option<Duration>,
+    pub resources: Option<Duration>,
+    pub async_ops: Option<Duration>,
+}
+
+impl Retention {
+    /// Returns the longest retention period, or `None` if nothing is
+    /// retained for a limited time.
+    fn longest(&self) -> Option<Duration> {
+        [self.tasks, self.resources, self.async_ops]
+            .into_iter()
+            .flatten()
+            .max()
+    }
+}
+
+pub enum Visibility {
+    Show,
+    Hide,
+}
+
+/// The list a frontend is displaying when an update arrives.
+///
+/// The items an update adds are kept as new items until the frontend takes
+/// them. The list being displayed takes its new items as it's drawn, so any
+/// it hasn't taken yet are stale; the other lists' new items are kept until
+/// they're next displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayedList {
+    Tasks,
+    Resources,
+    /// The async ops of a single resource.
+    AsyncOps,
+}
+
+/// The file and line a task was spawned at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: u32,
+}
+
+#[derive(Debug)]
+pub struct Metadata {
+    field_names: Vec<InternedStr>,
+    target: InternedStr,
+    id: u64,
+    //TODO: add more metadata as needed
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Field {
+    pub name: InternedStr,
+    pub value: FieldValue,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum FieldValue {
+    Bool(bool),
+    Str(String),
+    U64(u64),
+    I64(i64),
+    Debug(String),
+}
+
+#[derive(Debug, Default)]
+pub enum Temporality {
+    Unpausing,
+    #[default]
+    Live,
+    Pausing,
+    Paused,
+}
+
+impl From<proto::instrument::Temporality> for Temporality {
+    fn from(pb: proto::instrument::Temporality) -> Self {
+        match pb {
+            proto::instrument::Temporality::Live => Self::Live,
+            proto::instrument::Temporality::Paused => Self::Paused,
+        }
+    }
+}
+
+/// A field of a resource or async op, which may have a unit.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Attribute {
+    pub field: Field,
+    pub unit: Option<String>,
+}
+
+impl State {
+    /// How long the warning about missed updates is shown for.
+    const MISSED_UPDATES_WARNING: Duration = Duration::from_secs(30);
+
+    pub fn with_retention(mut self, retention: Retention) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    pub fn with_stall_threshold(mut self, threshold: Duration) -> Self {
+        self.stall_threshold = Some(threshold);
+        self
+    }
+
+    /// Keeps up to `budget` bytes of previous updates, to step back through
+    /// while paused.
+    pub fn with_history_budget(mut self, budget: usize) -> Self {
+        self.history = History::new(budget);
+        self
+    }
+
+    /// Checks the tasks with each name against its latency budget.
+    pub fn with_latency_budgets(
+        mut self,
+        budgets: impl IntoIterator<Item = (String, LatencyBudget)>,
+    ) -> Self {
+        self.tasks_state.latency_budgets.extend(budgets);
+        self
+    }
+
+    pub fn with_task_linters(mut self, linters: impl IntoIterator<Item = Linter<Task>>) -> Self {
+        self.tasks_state.linters.extend(linters);
+        self
+    }
+
+    /// Adds task linters which start out disabled, and can be enabled while
+    /// the console is running.
+    pub fn with_disabled_task_linters(
+        mut self,
+        linters: impl IntoIterator<Item = Linter<Task>>,
+    ) -> Self {
+        self.tasks_state.add_disabled_linters(linters);
+        self
+    }
+
+    pub fn last_updated_at(&self) -> Option<SystemTime> {
+        self.last_updated_at
+    }
+
+    pub fn update(&mut self, displayed: Option<DisplayedList>, update: proto::instrument::Update) {
+        // If we were looking at an earlier update, catch up before applying
+        // the new one.
+        if self.history.step_to_latest() {
+            self.replay();
+        }
+        self.history.push(&update);
+
+        self.session.updates += 1;
+        self.session.dropped_events += self.dropped_events.record(&update).total();
+        if let Some(stats) = update.subscriber_stats {
+            self.subscriber_health.record(stats);
+        }
+        let missed = self.record_sequence(update.sequence);
+        self.lagging = update.lagging;
+        self.truncated = update.truncated;
+        let (spawned, dropped) = self.apply(displayed, update);
+        if let Some(now) = self.last_updated_at {
+            self.paused_time.update(now, self.is_paused());
+        }
+        self.session.tasks_spawned += spawned;
+        self.session.tasks_dropped += dropped;
+
+        if missed > 0 {
+            self.session.missed_updates += missed;
+            let recent = self.recent_missed_updates().unwrap_or(0);
+            if let Some(now) = self.last_updated_at {
+                self.missed_updates = Some((now, recent + missed));
+            }
+        }
+    }
+
+    /// Records the sequence number of an update, returning how many updates
+    /// were missed since the previous one.
+    fn record_sequence(&mut self, sequence: u64) -> u64 {
+        // Targets which don't number their updates send 0.
+        if sequence == 0 {
+            return 0;
+        }
+        let missed = match self.last_sequence {
+            Some(last) if sequence > last => sequence - last - 1,
+            // If the number went backwards, the target has restarted.
+            _ => 0,
+        };
+        self.last_sequence = Some(sequence);
+        missed
+    }
+
+    /// Returns `true` if the target is leaving details out of its updates,
+    /// such as new poll ops, because the console fell behind.
+    pub fn is_lagging(&self) -> bool {
+        self.lagging
+    }
+
+    /// Returns `true` if the latest update was too large to send whole, so
+    /// some changes were left for later updates.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Returns how many updates were missed recently, if any were.
+    pub fn recent_missed_updates(&self) -> Option<u64> {
+        let (at, missed) = self.missed_updates?;
+        let since = self.last_updated_at?.duration_since(at).unwrap_or_default();
+        (since < Self::MISSED_UPDATES_WARNING).then_some(missed)
+    }
+
+    /// Applies an update to the state, returning the number of tasks it
+    /// spawned and dropped.
+    fn apply(
+        &mut self,
+        displayed: Option<DisplayedList>,
+        update: proto::instrument::Update,
+    ) -> (u64, u64) {
+        // Updates which start or resume a stream carry the complete state, so
+        // every task the target already had is new to them. So is the first
+        // update applied, for targets which don't identify their streams.
+        let is_full_state = update.stream_id != 0 || self.last_updated_at.is_none();
+        if let Some(now) = update.now.map(|v| v.try_into().unwrap()) {
+            self.last_updated_at = Some(now);
+        }
+
+        let strings = &mut self.strings;
+        if let Some(new_metadata) = update.new_metadata {
+            let metas = new_metadata.metadata.into_iter().filter_map(|meta| {
+                let id = meta.id?.id;
+                let metadata = meta.metadata?;
+                Some((id, Metadata::from_proto(metadata, id, strings)))
+            });
+            self.metas.extend(metas);
+        }
+
+        let mut task_counts = (0, 0);
+        if let Some(tasks_update) = update.task_update {
+            let visibility = if displayed == Some(DisplayedList::Tasks) {
+                Visibility::Show
+            } else {
+                Visibility::Hide
+            };
+            task_counts = self.tasks_state.update_tasks(
+                &mut self.strings,
+                &self.metas,
+                tasks_update,
+                visibility,
+                self.last_updated_at,
+                is_full_state,
+            );
+        }
+
+        if let Some(resources_update) = update.resource_update {
+            let visibility = if displayed == Some(DisplayedList::Resources) {
+                Visibility::Show
+            } else {
+                Visibility::Hide
+            };
+            let io_polls = self.resources_state.update_resources(
+                &mut self.strings,
+                &self.metas,
+                resources_update,
+                visibility,
+                self.last_updated_at,
+            );
+            self.tasks_state.record_io_polls(io_polls);
+        }
+
+        if let Some(async_ops_update) = update.async_op_update {
+            let visibility = if displayed == Some(DisplayedList::AsyncOps) {
+                Visibility::Show
+            } else {
+                Visibility::Hide
+            };
+            self.async_ops_state.update_async_ops(
+                &mut self.strings,
+                &self.metas,
+                async_ops_update,
+                self.resources_state.ids_mut(),
+                self.tasks_state.ids_mut(),
+                visibility,
+                self.last_updated_at,
+            )
+        }
+
+        if let Some(runtime_stats) = update.runtime_stats {
+            self.runtime_state.update_runtime(runtime_stats);
+        }
+
+        task_counts
+    }
+
+    /// Shows the state as of the update before the one currently displayed,
+    /// returning `false` if there is no earlier update in the history.
+    pub fn step_back(&mut self) -> bool {
+        let stepped = self.history.step_back();
+        if stepped {
+            self.replay();
+        }
+        stepped
+    }
+
+    /// Shows the state as of the update after the one currently displayed,
+    /// returning `false` if the latest update is already displayed.
+    pub fn step_forward(&mut self) -> bool {
+        let stepped = self.history.step_forward();
+        if stepped {
+            self.replay();
+        }
+        stepped
+    }
+
+    /// Shows the state as of the latest update.
+    pub fn step_to_latest(&mut self) {
+        if self.history.step_to_latest() {
+            self.replay();
+        }
+    }
+
+    /// Returns how many updates before the latest one the displayed state is,
+    /// or `None` if it is up to date.
+    pub fn steps_back(&self) -> Option<usize> {
+        self.history.steps_back()
+    }
+
+    /// Rebuilds the state from scratch by replaying the history up to the
+    /// update currently selected.
+    fn replay(&mut self) {
+        self.metas.clear();
+        self.last_updated_at = None;
+        self.tasks_state.clear();
+        self.resources_state = ResourcesState::default();
+        self.async_ops_state = AsyncOpsState::default();
+        self.runtime_state = RuntimeState::default();
+
+        let history = mem::take(&mut self.history);
+        for update in history.replay() {
+            self.apply(None, update.clone());
+        }
+        self.history = history;
+
+        // Evict anything that would have been evicted by the time of the
+        // selected update, as `retain_active` does while live.
+        self.retain_dropped();
+    }
+
+    pub fn retain_active(&mut self) {
+        if self.is_paused() {
+            if let Some(now) = self.last_updated_at {
+                self.paused_time.pause(now);
+            }
+            return;
+        }
+
+        self.retain_dropped();
+        if let (Some(now), Some(longest)) = (self.last_updated_at, self.retention.longest()) {
+            self.paused_time.prune(now, longest);
+        }
+
+        // After dropping idle tasks & resources, prune any interned strings
+        // that are no longer referenced.
+        self.strings.retain_referenced();
+    }
+
+    /// Evicts the items that were dropped longer ago than their kind's
+    /// retention period, not counting the time the console was paused.
+    fn retain_dropped(&mut self) {
+        let Some(now) = self.last_updated_at else {
+            return;
+        };
+        let paused_time = &self.paused_time;
+        if let Some(retain_for) = self.retention.tasks {
+            self.tasks_state.retain_active(paused_time, now, retain_for);
+        }
+        if let Some(retain_for) = self.retention.resources {
+            self.resources_state
+                .retain_active(paused_time, now, retain_for);
+        }
+        if let Some(retain_for) = self.retention.async_ops {
+            self.async_ops_state
+                .retain_active(paused_time, now, retain_for);
+        }
+    }
+
+    pub fn session_stats(&self) -> &SessionStats {
+        &self.session
+    }
+
+    /// Returns the sets of stalled tasks which are waiting on each other.
+    pub fn stalls(&self) -> Vec<stalls::Stall> {
+        let Some(now) = self.last_updated_at else {
+            return Vec::new();
+        };
+        stalls::find(
+            &self.tasks_state,
+            &self.resources_state,
+            now,
+            self.stall_threshold(),
+        )
+    }
+
+    /// Returns the timers which are due to fire, soonest first.
+    pub fn upcoming_wakes(&self) -> Vec<timers::UpcomingWake> {
+        let Some(now) = self.last_updated_at else {
+            return Vec::new();
+        };
+        timers::upcoming(
+            &self.tasks_state,
+            &self.resources_state,
+            &self.async_ops_state,
+            now,
+        )
+    }
+
+    pub fn stall_threshold(&self) -> Duration {
+        self.stall_threshold.unwrap_or(stalls::DEFAULT_THRESHOLD)
+    }
+
+    pub fn dropped_events(&self) -> &DroppedEvents {
+        &self.dropped_events
+    }
+
+    pub fn subscriber_health(&self) -> &SubscriberHealth {
+        &self.subscriber_health
+    }
+
+    pub fn task_details_ref(&self) -> DetailsRef {
+        self.current_task_details.clone()
+    }
+
+    pub fn tasks_state(&mut self) -> &TasksState {
+        &self.tasks_state
+    }
+
+    pub fn tasks_state_mut(&mut self) -> &mut TasksState {
+        &mut self.tasks_state
+    }
+
+    pub fn resources_state(&mut self) -> &ResourcesState {
+        &self.resources_state
+    }
+
+    pub fn resources_state_mut(&mut self) -> &mut ResourcesState {
+        &mut self.resources_state
+    }
+
+    pub fn async_ops_state(&self) -> &AsyncOpsState {
+        &self.async_ops_state
+    }
+
+    pub fn async_ops_state_mut(&mut self) -> &mut AsyncOpsState {
+        &mut self.async_ops_state
+    }
+
+    pub fn runtime_state(&self) -> &RuntimeState {
+        &self.runtime_state
+    }
+
+    pub fn update_task_details(&mut self, update: proto::tasks::TaskDetails) {
+        if let Some(id) = update.task_id {
+            let details = Details {
+                span_id: id.id,
+                poll_times_histogram: update
+                    .poll_times_histogram
+                    .as_ref()
+                    .and_then(histogram::DurationHistogram::from_poll_durations),
+                recent_poll_times_histogram: update
+                    .recent_poll_times_histogram
+                    .as_ref()
+                    .and_then(histogram::DurationHistogram::from_proto),
+                scheduled_times_histogram: update
+                    .scheduled_times_histogram
+                    .as_ref()
+                    .and_then(histogram::DurationHistogram::from_proto),
+                wake_sites: update
+                    .wake_sites
+                    .into_iter()
+                    .map(|site| (format_location(site.location), site.wakes))
+                    .collect(),
+                wake_history: update
+                    .wake_history
+                    .into_iter()
+                    .map(|interval| (interval.wakes, interval.self_wakes))
+                    .collect(),
+                thread_polls: update
+                    .thread_polls
+                    .into_iter()
+                    .map(|thread| {
+                        let name = match thread.thread_name {
+                            Some(name) => format!("{} #{}", name, thread.thread_id),
+                            None => format!("#{}", thread.thread_id),
+                        };
+                        (name, thread.polls)
+                    })
+                    .collect(),
+                waiting_on: update
+                    .waiting_on
+                    .into_iter()
+                    .filter_map(|waiting| {
+                        Some(tasks::WaitingOn {
+                            resource_span_id: waiting.resource_id?.id,
+                            op_name: waiting.op_name,
+                            since: waiting.since.and_then(|since| since.try_into().ok()),
+                        })
+                    })
+                    .collect(),
+            };
+
+            if let Some(task) = self.tasks_state.task_by_span(id.id) {
+                let mut task = task.borrow_mut();
+                if let Some(histogram) = &details.scheduled_times_histogram {
+                    task.set_scheduled_histogram(histogram);
+                }
+                if let Some(tracker) = task.latency_budget_mut() {
+                    let exact = match tracker.budget().metric() {
+                        BudgetMetric::Poll => details.poll_times_histogram.clone(),
+                        BudgetMetric::Scheduled => details.scheduled_times_histogram.clone(),
+                    };
+                    tracker.set_exact(exact);
+                }
+            }
+
+            *self.current_task_details.borrow_mut() = Some(details);
+        }
+    }
+
+    pub fn unset_task_details(&mut self) {
+        if let Some(details) = self.current_task_details.borrow_mut().take() {
+            // Go back to estimating the task's durations now that its exact
+            // histograms will no longer be updated.
+            if let Some(task) = self.tasks_state.task_by_span(details.span_id) {
+                if let Some(tracker) = task.borrow_mut().latency_budget_mut() {
+                    tracker.set_exact(None);
+                }
+            }
+        }
+    }
+
+    // temporality methods
+    pub fn temporality(&self) -> &Temporality {
+        &self.temporality
+    }
+
+    pub fn start_unpausing(&mut self) {
+        self.temporality = Temporality::Unpausing;
+        // The updates published while paused were skipped on purpose.
+        self.last_sequence = None;
+    }
+
+    pub fn start_pausing(&mut self) {
+        self.temporality = Temporality::Pausing;
+    }
+
+    /// Applies a state update from the target, which is authoritative over
+    /// any temporality inferred from the user's input.
+    pub fn update_state(&mut self, state: proto::instrument::State) {
+        match proto::instrument::Temporality::try_from(state.temporality) {
+            Ok(temporality) => self.temporality = temporality.into(),
+            // A newer target may report states this console doesn't know about.
+            Err(_) => tracing::warn!(
+                temporality = state.temporality,
+                "unknown temporality in state update"
+            ),
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        matches!(self.temporality, Temporality::Paused | Temporality::Pausing)
+    }
+}
+
+impl Metadata {
+    fn from_proto(pb: proto::Metadata, id: u64, strings: &mut intern::Strings) -> Self {
+        Self {
+            field_names: pb
+                .field_names
+                .into_iter()
+                .map(|n| strings.string(n))
+                .collect(),
+            target: strings.string(pb.target),
+            id,
+        }
+    }
+}
+
+// === impl Field ===
+
+impl Field {
+    const SPAWN_LOCATION: &'static str = "spawn.location";
+    const KIND: &'static str = "kind";
+    const NAME: &'static str = "task.name";
+    const TASK_ID: &'static str = "task.id";
+    const SIZE_BYTES: &'static str = "size.bytes";
+    const ORIGINAL_SIZE_BYTES: &'static str = "original_size.bytes";
+
+    /// Creates a new Field with a pre-interned `name` and a `FieldValue`.
+    fn new(name: InternedStr, value: FieldValue) -> Self {
+        Field { name, value }
+    }
+
+    /// Converts a wire-format `Field` into an internal `Field` representation,
+    /// using the provided `Metadata` for the task span that the field came
+    /// from.
+    ///
+    /// If the field is invalid or it has a string value which is empty, this
+    /// returns `None`.
+    fn from_proto(
+        proto::Field {
+            name,
+            metadata_id,
+            value,
+        }: proto::Field,
+        meta: &Metadata,
+        strings: &mut intern::Strings,
+    ) -> Option<Self> {
+        use proto::field::Name;
+        let name = match name? {
+            Name::StrName(n) => strings.string(n),
+            Name::NameIdx(idx) => {
+                let meta_id = metadata_id.map(|m| m.id);
+                if meta_id != Some(meta.id) {
+                    tracing::warn!(
+                        task.meta_id = meta.id,
+                        field.meta.id = ?meta_id,
+                        field.name_index = idx,
+                        ?meta,
+                        "skipping malformed field name (metadata id mismatch)"
+                    );
+                    debug_assert_eq!(
+                        meta_id,
+                        Some(meta.id),
+                        "malformed field name: metadata ID mismatch! (name idx={}; metadata={:#?})",
+                        idx,
+                        meta,
+                    );
+                    return None;
+                }
+                match meta.field_names.get(idx as usize).cloned() {
+                    Some(name) => name,
+                    None => {
+                        tracing::warn!(
+                            task.meta_id = meta.id,
+                            field.meta.id = ?meta_id,
+                            field.name_index = idx,
+                            ?meta,
+                            "missing field name for index"
+                        );
+                        return None;
+                    }
+                }
+            }
+        };
+
+        debug_assert!(
+            value.is_some(),
+            "missing field value for field `{:?}` (metadata={:#?})",
+            name,
+            meta,
+        );
+        let mut value = FieldValue::from(value?)
+            // if the value is an empty string, just skip it.
+            .ensure_nonempty()?;
+
+        if &*name == Field::SPAWN_LOCATION {
+            value = value.truncate_registry_path();
+        }
+
+        Some(Self { name, value })
+    }
+}
+
+impl Ord for Field {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (&*self.name, &*other.name) {
+            // the `NAME` field should always come first
+            (Field::NAME, Field::NAME) => Ordering::Equal,
+            (Field::NAME, _) => Ordering::Less,
+            (_, Field::NAME) => Ordering::Greater,
+
+            // the `SPAWN_LOCATION` field should always come last (it's long)
+            (Field::SPAWN_LOCATION, Field::SPAWN_LOCATION) => Ordering::Equal,
+            (Field::SPAWN_LOCATION, _) => Ordering::Greater,
+            (_, Field::SPAWN_LOCATION) => Ordering::Less,
+            (this, that) => this.cmp(that),
+        }
+    }
+}
+
+impl PartialOrd for Field {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// === impl FieldValue ===
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldValue::Bool(v) => fmt::Display::fmt(v, f)?,
+            FieldValue::Str(v) => fmt::Display::fmt(v, f)?,
+            FieldValue::U64(v) => fmt::Display::fmt(v, f)?,
+            FieldValue::Debug(v) => fmt::Display::fmt(v, f)?,
+            FieldValue::I64(v) => fmt::Display::fmt(v, f)?,
+        }
+
+        Ok(())
+    }
+}
+
+impl FieldValue {
+    /// Truncates paths including `.cargo/registry`.
+    fn truncate_registry_path(self) -> Self {
+        match self {
+            FieldValue::Str(s) | FieldValue::Debug(s) => {
+                FieldValue::Debug(truncate_registry_path(s))
+            }
+
+            f => f,
+        }
+    }
+
+    /// If `self` is an empty string, returns `None`. Otherwise, returns `Some(self)`.
+    fn ensure_nonempty(self) -> Option<Self> {
+        match self {
+            FieldValue::Debug(s) | FieldValue::Str(s) if s.is_empty() => None,
+            val => Some(val),
+        }
+    }
+}
+
+impl From<proto::field::Value> for FieldValue {
+    fn from(pb: proto::field::Value) -> Self {
+        match pb {
+            proto::field::Value::BoolVal(v) => Self::Bool(v),
+            proto::field::Value::StrVal(v) => Self::Str(v),
+            proto::field::Value::I64Val(v) => Self::I64(v),
+            proto::field::Value::U64Val(v) => Self::U64(v),
+            proto::field::Value::DebugVal(v) => Self::Debug(v),
+        }
+    }
+}
+
+impl Ord for Attribute {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.field
+            .cmp(&other.field)
+            // TODO(eliza): *maybe* this should compare so that larger units are
+            // greater than smaller units (e.g. `ms` > `us`), rather than
+            // alphabetically?
+            // but, meh...
+            .then_with(|| self.unit.cmp(&other.unit))
+    }
+}
+
+impl PartialOrd for Attribute {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// A naive way to determine if a path is a Windows path.
+// If the path has a drive letter and more backslashes than forward slashes, it's a Windows path.
+fn is_windows_path(path: &str) -> bool {
+    use once_cell::sync::OnceCell;
+    use regex::Regex;
+
+    static REGEX: OnceCell<Regex> = OnceCell::new();
+    let regex = REGEX.get_or_init(|| Regex::new(r"^[a-zA-Z]:\\").expect("failed to compile regex"));
+    let has_drive_letter = regex.is_match(path);
+    let slash_count = path.chars().filter(|&c| c == '/').count();
+    let backslash_count = path.chars().filter(|&c| c == '\\').count();
+    has_drive_letter && backslash_count > slash_count
+}
+
+fn truncate_registry_path(s: String) -> String {
+    use once_cell::sync::OnceCell;
+    use regex::Regex;
+    use std::borrow::Cow;
+
+    static REGEX: OnceCell<Regex> = OnceCell::new();
+    let regex = REGEX.get_or_init(|| {
+        Regex::new(r".*[/\\]\.cargo[/\\](registry[/\\]src[/\\][^/\\]*[/\\]|git[/\\]checkouts[/\\])")
+            .expect("failed to compile regex")
+    });
+
+    let rep = if is_windows_path(&s) {
+        "<cargo>\\"
+    } else {
+        "<cargo>/"
+    };
+
+    match regex.replace(&s, rep) {
+        Cow::Owned(s) => s,
+        // String was not modified, return the original.
+        Cow::Borrowed(_) => s,
+    }
+}
+
+fn format_location(loc: Option<proto::Location>) -> String {
+    loc.map(|mut l| {
+        if let Some(file) = l.file.take() {
+            let truncated = truncate_registry_path(file);
+            l.file = Some(truncated);
+        }
+        l.to_string()
+    })
+    .unwrap_or_else(|| "<unknown location>".to_string())
+}
+
+fn pb_duration(dur: prost_types::Duration) -> Duration {
+    let secs = u64::try_from(dur.seconds).expect("duration should not be negative!");
+    let nanos = u64::try_from(dur.nanos).expect("duration should not be negative!");
+    Duration::from_secs(secs) + Duration::from_nanos(nanos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tasks::{RateWindow, SpawnHistory},
+        warnings,
+    };
+
+    #[test]
+    fn counts_missed_updates() {
+        let mut state = State::default();
+        // Targets which don't number updates never miss any.
+        assert_eq!(state.record_sequence(0), 0);
+        assert_eq!(state.record_sequence(0), 0);
+
+        assert_eq!(state.record_sequence(5), 0);
+        assert_eq!(state.record_sequence(6), 0);
+        // A full update carries the number of the previous update.
+        assert_eq!(state.record_sequence(6), 0);
+        assert_eq!(state.record_sequence(10), 3);
+
+        // The target restarted.
+        assert_eq!(state.record_sequence(1), 0);
+        assert_eq!(state.record_sequence(2), 0);
+
+        state.start_unpausing();
+        assert_eq!(state.record_sequence(20), 0);
+    }
+
+    #[test]
+    fn counts_io_polls_for_tasks() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let meta = |id, target: &str| proto::register_metadata::NewMetadata {
+            id: Some(proto::MetaId { id }),
+            metadata: Some(proto::Metadata {
+                name: "runtime.resource".to_string(),
+                target: target.to_string(),
+                ..Default::default()
+            }),
+        };
+        let resource = |id, meta_id| proto::resources::Resource {
+            id: Some(proto::Id { id }),
+            metadata: Some(proto::MetaId { id: meta_id }),
+            kind: Some(proto::resources::resource::Kind {
+                kind: Some(proto::resources::resource::kind::Kind::Other(
+                    "Other".to_string(),
+                )),
+            }),
+            concrete_type: "Resource".to_string(),
+            ..Default::default()
+        };
+        let resource_stats = proto::resources::Stats {
+            created_at: Some(now.into()),
+            ..Default::default()
+        };
+        let poll_op = |resource_id, is_ready| proto::resources::PollOp {
+            resource_id: Some(proto::Id { id: resource_id }),
+            task_id: Some(proto::Id { id: 1 }),
+            is_ready,
+            ..Default::default()
+        };
+
+        let mut state = State::default();
+        state.update(
+            None,
+            proto::instrument::Update {
+                now: Some(now.into()),
+                new_metadata: Some(proto::RegisterMetadata {
+                    metadata: vec![
+                        meta(1, "app"),
+                        meta(2, "tokio::net::tcp"),
+                        meta(3, "tokio::sync::mutex"),
+                    ],
+                }),
+                task_update: Some(proto::tasks::TaskUpdate {
+                    new_tasks: vec![proto::tasks::Task {
+                        id: Some(proto::Id { id: 1 }),
+                        metadata: Some(proto::MetaId { id: 1 }),
+                        ..Default::default()
+                    }],
+                    stats_update: [(
+                        1,
+                        proto::tasks::Stats {
+                            created_at: Some(now.into()),
+                            poll_stats: Some(Default::default()),
+                            ..Default::default()
+                        },
+                    )]
+                    .into(),
+                    ..Default::default()
+                }),
+                resource_update: Some(proto::resources::ResourceUpdate {
+                    new_resources: vec![resource(2, 2), resource(3, 3)],
+                    stats_update: [(2, resource_stats.clone()), (3, resource_stats)].into(),
+                    // Only the polls of the socket are I/O polls.
+                    new_poll_ops: vec![poll_op(2, false), poll_op(2, true), poll_op(3, false)],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+
+        let task = state
+            .tasks_state()
+            .task_by_span(1)
+            .expect("task should be added");
+        let io_polls = task.borrow().io_polls();
+        assert_eq!(io_polls.polls(), 2);
+        assert_eq!(io_polls.ready(), 1);
+    }
+
+    #[test]
+    fn keeps_watched_and_pinned_tasks_after_they_end() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let task_update = |id, dropped_at: Option<SystemTime>| proto::tasks::TaskUpdate {
+            new_tasks: vec![proto::tasks::Task {
+                id: Some(proto::Id { id }),
+                metadata: Some(proto::MetaId { id: 1 }),
+                ..Default::default()
+            }],
+            stats_update: [(
+                id,
+                proto::tasks::Stats {
+                    created_at: Some(now.into()),
+                    dropped_at: dropped_at.map(Into::into),
+                    poll_stats: Some(Default::default()),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let update = |now: SystemTime, task_update| proto::instrument::Update {
+            now: Some(now.into()),
+            new_metadata: Some(proto::RegisterMetadata {
+                metadata: vec![proto::register_metadata::NewMetadata {
+                    id: Some(proto::MetaId { id: 1 }),
+                    metadata: Some(proto::Metadata {
+                        name: "runtime.spawn".to_string(),
+                        ..Default::default()
+                    }),
+                }],
+            }),
+            task_update: Some(task_update),
+            ..Default::default()
+        };
+
+        let mut state = State::default().with_retention(Retention {
+            tasks: Some(Duration::from_secs(1)),
+            ..Default::default()
+        });
+        state.update(None, update(now, task_update(1, None)));
+        state.update(None, update(now, task_update(2, None)));
+        state.update(None, update(now, task_update(3, None)));
+        let id = |state: &mut State, span_id| {
+            state
+                .tasks_state()
+                .task_by_span(span_id)
+                .map(|task| task.borrow().id())
+        };
+        let watched = id(&mut state, 1).expect("task should be added");
+        assert!(state.tasks_state_mut().toggle_watched(watched));
+        let pinned = id(&mut state, 3).expect("task should be added");
+        assert!(state.tasks_state_mut().toggle_pinned(pinned));
+
+        // All the tasks are dropped.
+        let later = now + Duration::from_secs(10);
+        let mut ended = task_update(1, Some(later));
+        ended.new_tasks.clear();
+        for id in [2, 3] {
+            ended
+                .stats_update
+                .extend(task_update(id, Some(later)).stats_update);
+        }
+        state.update(None, update(later, ended));
+        assert_eq!(state.tasks_state_mut().take_ended_watched(), [watched]);
+        assert!(state.tasks_state_mut().take_ended_watched().is_empty());
+
+        // Only the watched and pinned tasks are kept once the retention period
+        // has passed.
+        let much_later = later + Duration::from_secs(10);
+        state.update(None, update(much_later, Default::default()));
+        state.retain_active();
+        assert_eq!(id(&mut state, 1), Some(watched));
+        assert_eq!(id(&mut state, 2), None);
+        assert_eq!(id(&mut state, 3), Some(pinned));
+    }
+
+    #[test]
+    fn keeps_pinned_tasks_when_stepping_through_history() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let update = |id| proto::instrument::Update {
+            now: Some(now.into()),
+            new_metadata: Some(proto::RegisterMetadata {
+                metadata: vec![proto::register_metadata::NewMetadata {
+                    id: Some(proto::MetaId { id: 1 }),
+                    metadata: Some(proto::Metadata {
+                        name: "runtime.spawn".to_string(),
+                        ..Default::default()
+                    }),
+                }],
+            }),
+            task_update: Some(proto::tasks::TaskUpdate {
+                new_tasks: vec![proto::tasks::Task {
+                    id: Some(proto::Id { id }),
+                    metadata: Some(proto::MetaId { id: 1 }),
+                    ..Default::default()
+                }],
+                stats_update: [(
+                    id,
+                    proto::tasks::Stats {
+                        created_at: Some(now.into()),
+                        poll_stats: Some(Default::default()),
+                        ..Default::default()
+                    },
+                )]
+                .into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut state = State::default().with_history_budget(usize::MAX);
+        state.update(None, update(1));
+        state.update(None, update(2));
+        let pinned = |state: &mut State, span_id| {
+            state
+                .tasks_state()
+                .task_by_span(span_id)
+                .map(|task| task.borrow().is_pinned())
+        };
+        let id = state
+            .tasks_state()
+            .task_by_span(1)
+            .map(|task| task.borrow().id())
+            .expect("task should be added");
+        assert!(state.tasks_state_mut().toggle_pinned(id));
+
+        // Stepping back replays the history from scratch.
+        assert!(state.step_back());
+        assert_eq!(pinned(&mut state, 1), Some(true));
+        assert_eq!(pinned(&mut state, 2), None);
+
+        assert!(state.step_forward());
+        assert_eq!(pinned(&mut state, 1), Some(true));
+        assert_eq!(pinned(&mut state, 2), Some(false));
+    }
+
+    #[test]
+    fn keeps_task_ids_across_reconnects() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        // After reconnecting, the target sends every task again, which may
+        // have a different span ID by then.
+        let update = |tasks: &[(u64, u64)]| proto::instrument::Update {
+            now: Some(now.into()),
+            new_metadata: Some(proto::RegisterMetadata {
+                metadata: vec![proto::register_metadata::NewMetadata {
+                    id: Some(proto::MetaId { id: 1 }),
+                    metadata: Some(proto::Metadata {
+                        name: "runtime.spawn".to_string(),
+                        ..Default::default()
+                    }),
+                }],
+            }),
+            task_update: Some(proto::tasks::TaskUpdate {
+                new_tasks: tasks
+                    .iter()
+                    .map(|&(span_id, task_id)| proto::tasks::Task {
+                        id: Some(proto::Id { id: span_id }),
+                        metadata: Some(proto::MetaId { id: 1 }),
+                        fields: vec![proto::Field {
+                            name: Some(proto::field::Name::StrName("task.id".to_string())),
+                            value: Some(proto::field::Value::U64Val(task_id)),
+                            metadata_id: Some(proto::MetaId { id: 1 }),
+                        }],
+                        ..Default::default()
+                    })
+                    .collect(),
+                stats_update: tasks
+                    .iter()
+                    .map(|&(span_id, _)| {
+                        let stats = proto::tasks::Stats {
+                            created_at: Some(now.into()),
+                            poll_stats: Some(Default::default()),
+                            ..Default::default()
+                        };
+                        (span_id, stats)
+                    })
+                    .collect(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let task = |state: &mut State, span_id| {
+            state
+                .tasks_state()
+                .task_by_span(span_id)
+                .cloned()
+                .expect("task should be added")
+        };
+
+        let mut state = State::default();
+        state.update(None, update(&[(1, 7), (2, 8)]));
+        assert_eq!(state.tasks_state_mut().take_new_tasks().count(), 2);
+        let selected = task(&mut state, 1);
+        let watched = selected.borrow().id();
+        assert!(state.tasks_state_mut().toggle_watched(watched));
+        let pinned = task(&mut state, 2).borrow().id();
+        assert!(state.tasks_state_mut().toggle_pinned(pinned));
+
+        state.update(None, update(&[(3, 7), (4, 8), (1, 9)]));
+        // Only the task with a task ID that wasn't seen before is new, even
+        // though it reuses a span ID.
+        assert_eq!(state.tasks_state_mut().take_new_tasks().count(), 1);
+        assert_ne!(task(&mut state, 1).borrow().id(), watched);
+        assert!(Rc::ptr_eq(&task(&mut state, 3), &selected));
+        assert_eq!(selected.borrow().id(), watched);
+        assert!(state.tasks_state().watched().contains(&watched));
+        let repinned = task(&mut state, 4);
+        assert_eq!(repinned.borrow().id(), pinned);
+        assert!(repinned.borrow().is_pinned());
+    }
+
+    #[test]
+    fn spawn_history_skips_full_state_updates() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let update = |now: SystemTime, stream_id, span_ids: &[u64]| proto::instrument::Update {
+            now: Some(now.into()),
+            new_metadata: Some(proto::RegisterMetadata {
+                metadata: vec![proto::register_metadata::NewMetadata {
+                    id: Some(proto::MetaId { id: 1 }),
+                    metadata: Some(proto::Metadata {
+                        name: "runtime.spawn".to_string(),
+                        ..Default::default()
+                    }),
+                }],
+            }),
+            task_update: Some(proto::tasks::TaskUpdate {
+                new_tasks: span_ids
+                    .iter()
+                    .map(|&id| proto::tasks::Task {
+                        id: Some(proto::Id { id }),
+                        metadata: Some(proto::MetaId { id: 1 }),
+                        ..Default::default()
+                    })
+                    .collect(),
+                stats_update: span_ids
+                    .iter()
+                    .map(|&id| {
+                        let stats = proto::tasks::Stats {
+                            created_at: Some(start.into()),
+                            poll_stats: Some(Default::default()),
+                            ..Default::default()
+                        };
+                        (id, stats)
+                    })
+                    .collect(),
+                ..Default::default()
+            }),
+            stream_id,
+            ..Default::default()
+        };
+        let spawned = |state: &mut State| {
+            state
+                .tasks_state()
+                .spawn_history()
+                .spawned()
+                .collect::<Vec<_>>()
+        };
+
+        // The tasks which already existed when the console connected weren't
+        // spawned since.
+        let mut state = State::default();
+        state.update(None, update(start, 1, &[1, 2, 3]));
+        assert!(spawned(&mut state).is_empty());
+        let later = start + SpawnHistory::BUCKET_WIDTH;
+        state.update(None, update(later, 0, &[4]));
+        assert_eq!(spawned(&mut state), [1]);
+
+        // Nor were those sent again after reconnecting.
+        let much_later = later + SpawnHistory::BUCKET_WIDTH;
+        state.update(None, update(much_later, 2, &[1, 2, 3, 4, 5]));
+        assert_eq!(spawned(&mut state), [1]);
+
+        // Nor those in the first update from a target which doesn't identify
+        // its streams.
+        let mut state = State::default();
+        state.update(None, update(start, 0, &[1, 2, 3]));
+        assert!(spawned(&mut state).is_empty());
+    }
+
+    #[test]
+    fn computes_runtime_shares_over_windows() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let stats = |busy_ms| proto::tasks::Stats {
+            created_at: Some(start.into()),
+            poll_stats: Some(proto::PollStats {
+                busy_time: Some(
+                    Duration::from_millis(busy_ms)
+                        .try_into()
+                        .expect("duration fits"),
+                ),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        // Tasks are only sent as new in the first update.
+        let update = |secs, busy_ms: [u64; 2]| proto::instrument::Update {
+            now: Some((start + Duration::from_secs(secs)).into()),
+            new_metadata: Some(proto::RegisterMetadata {
+                metadata: vec![proto::register_metadata::NewMetadata {
+                    id: Some(proto::MetaId { id: 1 }),
+                    metadata: Some(proto::Metadata {
+                        name: "runtime.spawn".to_string(),
+                        ..Default::default()
+                    }),
+                }],
+            }),
+            task_update: Some(proto::tasks::TaskUpdate {
+                new_tasks: if secs == 0 {
+                    [1, 2]
+                        .map(|id| proto::tasks::Task {
+                            id: Some(proto::Id { id }),
+                            metadata: Some(proto::MetaId { id: 1 }),
+                            ..Default::default()
+                        })
+                        .into()
+                } else {
+                    Vec::new()
+                },
+                stats_update: [(1, stats(busy_ms[0])), (2, stats(busy_ms[1]))].into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let shares = |state: &mut State, window| {
+            [1, 2].map(|span_id| {
+                state
+                    .tasks_state()
+                    .task_by_span(span_id)
+                    .expect("task should be added")
+                    .borrow()
+                    .runtime_share(window)
+            })
+        };
+
+        let mut state = State::default();
+        state.update(None, update(0, [0, 0]));
+        state.update(None, update(5, [300, 100]));
+        assert_eq!(
+            state.tasks_state().busy_total(RateWindow::TenSeconds),
+            Duration::from_millis(400)
+        );
+        assert_eq!(shares(&mut state, RateWindow::TenSeconds), [75.0, 25.0]);
+
+        // Only the second task is busy in the next update, so it has all of
+        // the busy time in the shorter window.
+        state.update(None, update(20, [300, 300]));
+        assert_eq!(shares(&mut state, RateWindow::TenSeconds), [0.0, 100.0]);
+        assert_eq!(shares(&mut state, RateWindow::OneMinute), [50.0, 50.0]);
+
+        let now = start + Duration::from_secs(20);
+        let tasks = state.tasks_state();
+        let task = tasks.task_by_span(1).expect("task should be added");
+        assert_eq!(task.borrow().busy_percent(now), 1.5);
+    }
+
+    #[test]
+    fn merges_resent_attribute_history() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let at = |secs| start + Duration::from_secs(secs);
+        let sample = |secs, idle| proto::resources::AttributeSample {
+            at: Some(at(secs).into()),
+            attribute: Some(proto::Attribute {
+                field: Some(proto::Field {
+                    name: Some(proto::field::Name::StrName("idle".to_string())),
+                    value: Some(proto::field::Value::U64Val(idle)),
+                    metadata_id: None,
+                }),
+                unit: None,
+            }),
+        };
+        let update = |now, history: Vec<_>| proto::instrument::Update {
+            now: Some(at(now).into()),
+            new_metadata: Some(proto::RegisterMetadata {
+                metadata: vec![proto::register_metadata::NewMetadata {
+                    id: Some(proto::MetaId { id: 1 }),
+                    metadata: Some(proto::Metadata {
+                        name: "runtime.resource".to_string(),
+                        ..Default::default()
+                    }),
+                }],
+            }),
+            resource_update: Some(proto::resources::ResourceUpdate {
+                new_resources: vec![proto::resources::Resource {
+                    id: Some(proto::Id { id: 1 }),
+                    metadata: Some(proto::MetaId { id: 1 }),
+                    kind: Some(proto::resources::resource::Kind {
+                        kind: Some(proto::resources::resource::kind::Kind::Other(
+                            "Sync".to_string(),
+                        )),
+                    }),
+                    concrete_type: "Pool".to_string(),
+                    ..Default::default()
+                }],
+                stats_update: [(
+                    1,
+                    proto::resources::Stats {
+                        created_at: Some(start.into()),
+                        attribute_history: history,
+                        ..Default::default()
+                    },
+                )]
+                .into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut state = State::default();
+        state.update(None, update(1, vec![sample(0, 4), sample(1, 3)]));
+        // The subscriber sends the values it still has again, along with the
+        // new ones.
+        let mut resent = update(2, vec![sample(1, 3), sample(2, 1)]);
+        if let Some(resources) = resent.resource_update.as_mut() {
+            resources.new_resources.clear();
+        }
+        state.update(None, resent);
+
+        let resource = state
+            .resources_state()
+            .resource_by_span(1)
+            .expect("resource should be added")
+            .clone();
+        let resource = resource.borrow();
+        let history = resource
+            .attribute_history()
+            .iter()
+            .map(|(name, samples)| {
+                let values = samples
+                    .iter()
+                    .map(|sample| sample.value)
+                    .collect::<Vec<_>>();
+                (name.to_string(), values)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(history, [("idle".to_string(), vec![4, 3, 1])]);
+    }
+
+    #[test]
+    fn toggling_linters_relints_tasks() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let update = proto::instrument::Update {
+            now: Some(now.into()),
+            new_metadata: Some(proto::RegisterMetadata {
+                metadata: vec![proto::register_metadata::NewMetadata {
+                    id: Some(proto::MetaId { id: 1 }),
+                    metadata: Some(proto::Metadata {
+                        name: "runtime.spawn".to_string(),
+                        ..Default::default()
+                    }),
+                }],
+            }),
+            task_update: Some(proto::tasks::TaskUpdate {
+                new_tasks: vec![proto::tasks::Task {
+                    id: Some(proto::Id { id: 1 }),
+                    metadata: Some(proto::MetaId { id: 1 }),
+                    ..Default::default()
+                }],
+                stats_update: [(
+                    1,
+                    proto::tasks::Stats {
+                        created_at: Some(now.into()),
+                        terminated_by: proto::tasks::stats::TerminatedBy::Panicked as i32,
+                        dropped_at: Some(now.into()),
+                        poll_stats: Some(Default::default()),
+                        ..Default::default()
+                    },
+                )]
+                .into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut state = State::default()
+            .with_task_linters([Linter::new(warnings::LostWaker)])
+            .with_disabled_task_linters([Linter::new(warnings::Panicked)]);
+        state.update(None, update);
+        let summary = "tasks have panicked";
+        assert_eq!(state.tasks_state().warnings().count(), 0);
+
+        assert_eq!(state.tasks_state_mut().toggle_linter(summary), Some(true));
+        let warnings = state.tasks_state().warnings().collect::<Vec<_>>();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].summary(), summary);
+        assert_eq!(warnings[0].count(), 1);
+
+        assert_eq!(state.tasks_state_mut().toggle_linter(summary), Some(false));
+        assert_eq!(state.tasks_state().warnings().count(), 0);
+        assert_eq!(state.tasks_state().disabled_linters()[0].count(), 0);
+        assert_eq!(state.tasks_state_mut().toggle_linter("no such lint"), None);
+    }
+
+    // This test should be run on all platforms. The console can display instrumentation data
+    // from different console subscribers that may be running on different operating systems.
+    // For instance, the console could be running on Windows, while the application is running on Linux.
+    // Therefore, it's important to ensure that paths, which can differ between operating systems,
+    // are displayed correctly in the console.
+    #[test]
+    fn test_format_location_linux() {
+        // Linux style paths.
+        let location1 = proto::Location {
+            file: Some(
+                "/home/user/.cargo/registry/src/github.com-1ecc6299db9ec823/tokio-1.0.1/src/lib.rs"
+                    .to_string(),
+            ),
+            ..Default::default()
+        };
+        let location2 = proto::Location {
+            file: Some("/home/user/.cargo/git/checkouts/tokio-1.0.1/src/lib.rs".to_string()),
+            ..Default::default()
+        };
+        let location3 = proto::Location {
+            file: Some("/home/user/projects/tokio-1.0.1/src/lib.rs".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            format_location(Some(location1)),
+            "<cargo>/tokio-1.0.1/src/lib.rs"
+        );
+        assert_eq!(
+            format_location(Some(location2)),
+            "<cargo>/tokio-1.0.1/src/lib.rs"
+        );
+        assert_eq!(
+            format_location(Some(location3)),
+            "/home/user/projects/tokio-1.0.1/src/lib.rs"
+        );
+
+        assert_eq!(format_location(None), "<unknown location>");
+    }
+
+    #[test]
+    fn test_format_location_macos() {
+        // macOS style paths.
+        let location1 = proto::Location {
+            file: Some("/Users/user/.cargo/registry/src/github.com-1ecc6299db9ec823/tokio-1.0.1/src/lib.rs".to_string()),
+            ..Default::default()
+        };
+        let location2 = proto::Location {
+            file: Some("/Users/user/.cargo/git/checkouts/tokio-1.0.1/src/lib.rs".to_string()),
+            ..Default::default()
+        };
+        let location3 = proto::Location {
+            file: Some("/Users/user/projects/tokio-1.0.1/src/lib.rs".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            format_location(Some(location1)),
+            "<cargo>/tokio-1.0.1/src/lib.rs"
+        );
+        assert_eq!(
+            format_location(Some(location2)),
+            "<cargo>/tokio-1.0.1/src/lib.rs"
+        );
+        assert_eq!(
+            format_location(Some(location3)),
+            "/Users/user/projects/tokio-1.0.1/src/lib.rs"
+        );
+    }
+
+    #[test]
+    fn test_format_location_windows() {
+        // Windows style paths.
+        let location1 = proto::Location {
+            file: Some(
+                "C:\\Users\\user\\.cargo\\registry\\src\\github.com-1ecc6299db9ec823\\tokio-1.0.1\\src\\lib.rs"
+                    .to_string(),
+            ),
+            ..Default::default()
+        };
+
+        let location2 = proto::Location {
+            file: Some(
+                "C:\\Users\\user\\.cargo\\git\\checkouts\\tokio-1.0.1\\src\\lib.rs".to_string(),
+            ),
+            ..Default::default()
+        };
+
+        let location3 = proto::Location {
+            file: Some("C:\\Users\\user\\projects\\tokio-1.0.1\\src\\lib.rs".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            format_location(Some(location1)),
+            "<cargo>\\tokio-1.0.1\\src\\lib.rs"
+        );
+
+        assert_eq!(
+            format_location(Some(location2)),
+            "<cargo>\\tokio-1.0.1\\src\\lib.rs"
+        );
+
+        assert_eq!(
+            format_location(Some(location3)),
+            "C:\\Users\\user\\projects\\tokio-1.0.1\\src\\lib.rs"
+        );
+    }
+}
@@ -0,0 +1,881 @@
+use crate::intern::{self, InternedStr};
+use crate::{
+    format_location,
+    retention::PausedTime,
+    store::{self, Id, SpanId, Store},
+    Attribute, Field, FieldValue, Metadata, Visibility,
+};
+use console_api as proto;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, VecDeque},
+    convert::{TryFrom, TryInto},
+    mem,
+    rc::{Rc, Weak},
+    time::{Duration, SystemTime},
+};
+
+#[derive(Default, Debug)]
+pub struct ResourcesState {
+    resources: Store<Resource>,
+    /// The resources grouped by their kind and concrete type.
+    groups: HashMap<(InternedStr, InternedStr), Rc<RefCell<ResourceGroup>>>,
+    /// Groups which have been added since the last time `take_new_groups`
+    /// was called.
+    new_groups: Vec<ResourceGroupRef>,
+    dropped_events: u64,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum TypeVisibility {
+    Public,
+    Internal,
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(usize)]
+pub enum SortBy {
+    #[default]
+    Id = 0,
+    ParentId = 1,
+    Kind = 2,
+    Total = 3,
+    Target = 4,
+    ConcreteType = 5,
+    Visibility = 6,
+    Ready = 7,
+    PollsPerReady = 8,
+    Location = 9,
+    Attributes = 10,
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(usize)]
+pub enum GroupSortBy {
+    #[default]
+    Kind = 0,
+    ConcreteType = 1,
+    Live = 2,
+    Dropped = 3,
+    CreatedRate = 4,
+    DroppedRate = 5,
+    Ready = 6,
+    PollsPerReady = 7,
+}
+
+#[derive(Debug)]
+pub struct Resource {
+    /// The resource's pretty (console-generated, sequential) ID.
+    ///
+    /// This is NOT the `tracing::span::Id` for the resource's `tracing` span on the
+    /// remote.
+    id: Id<Resource>,
+    /// The `tracing::span::Id` on the remote process for this resource's span.
+    ///
+    /// This is used when requesting a resource details stream.
+    span_id: SpanId,
+    id_str: InternedStr,
+    parent: InternedStr,
+    parent_id: InternedStr,
+    /// The ID of the resource's parent resource, if it has one.
+    parent_resource: Option<Id<Resource>>,
+    meta_id: u64,
+    kind: InternedStr,
+    stats: ResourceStats,
+    target: InternedStr,
+    concrete_type: InternedStr,
+    location: String,
+    visibility: TypeVisibility,
+    /// The resource statistics before they were last updated, used to
+    /// highlight resources that changed.
+    prev_stats: Option<ResourceStats>,
+    /// The time of the last update that changed the resource statistics.
+    stats_updated_at: Option<SystemTime>,
+    /// How many times the resource's poll ops returned ready and pending.
+    poll_counts: PollCounts,
+    /// Recent values of the resource's numeric attributes, oldest first, if
+    /// the subscriber keeps their history.
+    attribute_history: BTreeMap<InternedStr, VecDeque<AttributeSample>>,
+}
+
+/// The value of a numeric attribute at a point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttributeSample {
+    pub at: SystemTime,
+    pub value: u64,
+}
+
+/// Counts how many times poll ops returned ready and pending.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PollCounts {
+    ready: u64,
+    pending: u64,
+}
+
+pub type ResourceRef = store::Ref<Resource>;
+
+/// All of the resources with the same kind and concrete type, such as the
+/// `Sync` resources of type `Semaphore`.
+#[derive(Debug)]
+pub struct ResourceGroup {
+    kind: InternedStr,
+    concrete_type: InternedStr,
+    resources: Vec<ResourceRef>,
+    /// The number of resources in the group which have been created.
+    created: u64,
+    /// The number of resources in the group which have been dropped.
+    dropped: u64,
+    /// Recent values of `created` and `dropped`, used to calculate the rates
+    /// at which resources are created and dropped.
+    samples: VecDeque<(SystemTime, u64, u64)>,
+    /// The poll op results of all of the resources in the group, including
+    /// those which are no longer retained.
+    poll_counts: PollCounts,
+}
+
+pub type ResourceGroupRef = Weak<RefCell<ResourceGroup>>;
+
+#[derive(Debug)]
+struct ResourceStats {
+    created_at: SystemTime,
+    dropped_at: Option<SystemTime>,
+    total: Option<Duration>,
+    attributes: Vec<Attribute>,
+    /// The attributes with unsigned integer values, which are summed up for
+    /// each [`ResourceGroup`].
+    numeric_attributes: Vec<(InternedStr, u64, Option<String>)>,
+    /// Whether the resource's attributes say it is locked.
+    locked: bool,
+    /// The span ID of the task which last acquired the resource.
+    acquired_by: Option<SpanId>,
+    /// The recent values of numeric attributes sent with these stats, oldest
+    /// first.
+    attribute_history: Vec<(InternedStr, AttributeSample)>,
+}
+
+impl SortBy {
+    pub fn sort(&self, now: SystemTime, resources: &mut [ResourceRef]) {
+        match self {
+            Self::Id => resources.sort_by_key(|resource| resource.upgrade().map(|r| r.borrow().id)),
+            Self::ParentId => resources
+                .sort_by_key(|resource| resource.upgrade().map(|r| r.borrow().parent_id.clone())),
+            Self::Kind => resources
+                .sort_by_key(|resource| resource.upgrade().map(|r| r.borrow().kind.clone())),
+            Self::Total => {
+                resources.sort_by_key(|resource| resource.upgrade().map(|r| r.borrow().total(now)))
+            }
+            Self::Target => resources
+                .sort_by_key(|resource| resource.upgrade().map(|r| r.borrow().target.clone())),
+            Self::ConcreteType => resources.sort_by_key(|resource| {
+                resource.upgrade().map(|r| r.borrow().concrete_type.clone())
+            }),
+            Self::Visibility => {
+                resources.sort_by_key(|resource| resource.upgrade().map(|r| r.borrow().visibility))
+            }
+            Self::Ready => resources.sort_by_key(|resource| {
+                resource
+                    .upgrade()
+                    .and_then(|r| r.borrow().poll_counts.ready_ratio().map(thousandths))
+            }),
+            Self::PollsPerReady => resources.sort_by_key(|resource| {
+                resource
+                    .upgrade()
+                    .and_then(|r| r.borrow().poll_counts.polls_per_ready().map(thousandths))
+            }),
+            Self::Location => resources
+                .sort_by_key(|resource| resource.upgrade().map(|r| r.borrow().location.clone())),
+            Self::Attributes => resources.sort_by_key(|resource| {
+                resource.upgrade().and_then(|r| {
+                    // FIXME - we are taking only the key of the first attribute as sorting key here.
+                    // Instead, attributes should probably be parsed and sorted according to their actual values.
+                    //
+                    // See https://github.com/tokio-rs/console/issues/496
+                    r.borrow()
+                        .attributes()
+                        .first()
+                        .map(|attr| attr.field.name.clone())
+                })
+            }),
+        }
+    }
+}
+
+impl TryFrom<usize> for SortBy {
+    type Error = ();
+    fn try_from(idx: usize) -> Result<Self, Self::Error> {
+        match idx {
+            idx if idx == Self::Id as usize => Ok(Self::Id),
+            idx if idx == Self::ParentId as usize => Ok(Self::ParentId),
+            idx if idx == Self::Kind as usize => Ok(Self::Kind),
+            idx if idx == Self::Total as usize => Ok(Self::Total),
+            idx if idx == Self::Target as usize => Ok(Self::Target),
+            idx if idx == Self::ConcreteType as usize => Ok(Self::ConcreteType),
+            idx if idx == Self::Visibility as usize => Ok(Self::Visibility),
+            idx if idx == Self::Ready as usize => Ok(Self::Ready),
+            idx if idx == Self::PollsPerReady as usize => Ok(Self::PollsPerReady),
+            idx if idx == Self::Location as usize => Ok(Self::Location),
+            idx if idx == Self::Attributes as usize => Ok(Self::Attributes),
+            _ => Err(()),
+        }
+    }
+}
+
+impl GroupSortBy {
+    pub fn sort(&self, groups: &mut [ResourceGroupRef]) {
+        match self {
+            Self::Kind => groups.sort_by_key(|group| {
+                group
+                    .upgrade()
+                    .map(|g| (g.borrow().kind.clone(), g.borrow().concrete_type.clone()))
+            }),
+            Self::ConcreteType => groups
+                .sort_by_key(|group| group.upgrade().map(|g| g.borrow().concrete_type.clone())),
+            Self::Live => groups.sort_by_key(|group| group.upgrade().map(|g| g.borrow().live())),
+            Self::Dropped => {
+                groups.sort_by_key(|group| group.upgrade().map(|g| g.borrow().dropped))
+            }
+            Self::CreatedRate => groups.sort_by_key(|group| {
+                group
+                    .upgrade()
+                    .map(|g| thousandths(g.borrow().created_rate()))
+            }),
+            Self::DroppedRate => groups.sort_by_key(|group| {
+                group
+                    .upgrade()
+                    .map(|g| thousandths(g.borrow().dropped_rate()))
+            }),
+            Self::Ready => groups.sort_by_key(|group| {
+                group
+                    .upgrade()
+                    .and_then(|g| g.borrow().poll_counts.ready_ratio().map(thousandths))
+            }),
+            Self::PollsPerReady => groups.sort_by_key(|group| {
+                group
+                    .upgrade()
+                    .and_then(|g| g.borrow().poll_counts.polls_per_ready().map(thousandths))
+            }),
+        }
+    }
+}
+
+/// Converts a ratio or rate to a sort key, since `f64` is not `Ord`.
+fn thousandths(value: f64) -> u64 {
+    (value * 1000.0) as u64
+}
+
+impl TryFrom<usize> for GroupSortBy {
+    type Error = ();
+    fn try_from(idx: usize) -> Result<Self, Self::Error> {
+        match idx {
+            idx if idx == Self::Kind as usize => Ok(Self::Kind),
+            idx if idx == Self::ConcreteType as usize => Ok(Self::ConcreteType),
+            idx if idx == Self::Live as usize => Ok(Self::Live),
+            idx if idx == Self::Dropped as usize => Ok(Self::Dropped),
+            idx if idx == Self::CreatedRate as usize => Ok(Self::CreatedRate),
+            idx if idx == Self::DroppedRate as usize => Ok(Self::DroppedRate),
+            idx if idx == Self::Ready as usize => Ok(Self::Ready),
+            idx if idx == Self::PollsPerReady as usize => Ok(Self::PollsPerReady),
+            _ => Err(()),
+        }
+    }
+}
+
+impl ResourcesState {
+    pub fn take_new_resources(&mut self) -> impl Iterator<Item = ResourceRef> + '_ {
+        self.resources.take_new_items()
+    }
+
+    pub fn ids_mut(&mut self) -> &mut store::Ids<Resource> {
+        self.resources.ids_mut()
+    }
+
+    /// Returns all of the resources.
+    pub fn resources(&self) -> impl Iterator<Item = &store::Stored<Resource>> {
+        self.resources.values()
+    }
+
+    /// Returns the resource with the given `tracing` span ID, if it is
+    /// present.
+    pub fn resource_by_span(&self, span_id: SpanId) -> Option<&store::Stored<Resource>> {
+        self.resources.get_by_span(span_id)
+    }
+
+    /// Returns an iterator over the resource groups which have been added
+    /// since the last time this was called.
+    pub fn take_new_groups(&mut self) -> impl Iterator<Item = ResourceGroupRef> + '_ {
+        self.new_groups.drain(..)
+    }
+
+    /// Applies a resource update, returning the span ID of the task and the
+    /// readiness of each poll op on an I/O resource, so that the tasks'
+    /// I/O polls can be counted.
+    pub fn update_resources(
+        &mut self,
+        strings: &mut intern::Strings,
+        metas: &HashMap<u64, Metadata>,
+        update: proto::resources::ResourceUpdate,
+        visibility: Visibility,
+        now: Option<SystemTime>,
+    ) -> Vec<(SpanId, bool)> {
+        let parents: HashMap<Id<Resource>, ResourceRef> = update
+            .new_resources
+            .iter()
+            .filter_map(|resource| {
+                let parent_id = resource.parent_resource_id?.id;
+                let parent = self.resources.get_by_span(parent_id)?;
+                Some((parent.borrow().id, Rc::downgrade(parent)))
+            })
+            .collect();
+
+        let mut stats_update = update.stats_update;
+        let mut new_resources = Vec::new();
+        self.resources
+            .insert_with(visibility, update.new_resources, |ids, resource| {
+                let span_id = match resource.id.as_ref() {
+                    Some(id) => id.id,
+                    None => {
+                        tracing::warn!(?resource, "skipping resource with no id");
+                        return None;
+                    }
+                };
+
+                let meta_id = match resource.metadata.as_ref() {
+                    Some(id) => id.id,
+                    None => {
+                        tracing::warn!(?resource, "resource has no metadata id skipping");
+                        return None;
+                    }
+                };
+                let meta = match metas.get(&meta_id) {
+                    Some(meta) => meta,
+                    None => {
+                        tracing::warn!(?resource, meta_id, "no metadata for resource, skipping");
+                        return None;
+                    }
+                };
+                let kind = match kind_from_proto(resource.kind?, strings) {
+                    Ok(kind) => kind,
+                    Err(err) => {
+                        tracing::warn!(%err, "resource kind cannot be parsed");
+                        return None;
+                    }
+                };
+
+                let stats =
+                    ResourceStats::from_proto(stats_update.remove(&span_id)?, meta, strings);
+
+                let id = ids.id_for(span_id);
+                let parent_id = resource.parent_resource_id.map(|id| ids.id_for(id.id));
+
+                let parent = strings.string(match parent_id {
+                    Some(id) => parents
+                        .get(&id)
+                        .and_then(|r| r.upgrade())
+                        .map(|r| {
+                            let r = r.borrow();
+                            format!("{} ({}::{})", r.id(), r.target(), r.concrete_type())
+                        })
+                        .unwrap_or_else(|| id.to_string()),
+                    None => "n/a".to_string(),
+                });
+
+                let parent_resource = parent_id;
+                let parent_id = strings.string(
+                    parent_id
+                        .as_ref()
+                        .map(Id::<Resource>::to_string)
+                        .unwrap_or_else(|| "n/a".to_string()),
+                );
+
+                let location = format_location(resource.location);
+                let visibility = if resource.is_internal {
+                    TypeVisibility::Internal
+                } else {
+                    TypeVisibility::Public
+                };
+
+                let mut resource = Resource {
+                    id,
+                    span_id,
+                    id_str: strings.string(id.to_string()),
+                    parent,
+                    parent_id,
+                    parent_resource,
+                    kind,
+                    stats,
+                    target: meta.target.clone(),
+                    concrete_type: strings.string(resource.concrete_type),
+                    meta_id,
+                    location,
+                    visibility,
+                    prev_stats: None,
+                    stats_updated_at: now,
+                    poll_counts: PollCounts::default(),
+                    attribute_history: BTreeMap::new(),
+                };
+                resource.record_attribute_history();
+                new_resources.push(id);
+                Some((id, resource))
+            });
+
+        self.dropped_events += update.dropped_events;
+
+        for id in new_resources {
+            if let Some(resource) = self.resources.get(id) {
+                let group = group_for(&mut self.groups, &mut self.new_groups, &resource.borrow());
+                let mut group = group.borrow_mut();
+                group.created += 1;
+                if resource.borrow().dropped() {
+                    group.dropped += 1;
+                }
+                group.resources.push(Rc::downgrade(resource));
+            }
+        }
+
+        let mut newly_dropped = Vec::new();
+        for (stats, mut resource) in self.resources.updated(stats_update) {
+            if let Some(meta) = metas.get(&resource.meta_id) {
+                tracing::trace!(?resource, ?stats, "processing stats update for");
+                let was_dropped = resource.dropped();
+                let stats = ResourceStats::from_proto(stats, meta, strings);
+                resource.prev_stats = Some(mem::replace(&mut resource.stats, stats));
+                resource.stats_updated_at = now;
+                resource.record_attribute_history();
+                if !was_dropped && resource.dropped() {
+                    newly_dropped.push((resource.kind.clone(), resource.concrete_type.clone()));
+                }
+            }
+        }
+        for key in newly_dropped {
+            if let Some(group) = self.groups.get(&key) {
+                group.borrow_mut().dropped += 1;
+            }
+        }
+
+        let mut io_polls = Vec::new();
+        for poll_op in update.new_poll_ops {
+            let Some(resource) = poll_op
+                .resource_id
+                .and_then(|id| self.resources.get_by_span(id.id))
+            else {
+                continue;
+            };
+            let mut resource = resource.borrow_mut();
+            resource.poll_counts.record(poll_op.is_ready);
+            if let Some(task_id) = poll_op.task_id.filter(|_| resource.is_io()) {
+                io_polls.push((task_id.id, poll_op.is_ready));
+            }
+            let key = (resource.kind.clone(), resource.concrete_type.clone());
+            if let Some(group) = self.groups.get(&key) {
+                group.borrow_mut().poll_counts.record(poll_op.is_ready);
+            }
+        }
+
+        for group in self.groups.values() {
+            let mut group = group.borrow_mut();
+            group.resources.retain(|r| r.upgrade().is_some());
+            if let Some(now) = now {
+                group.sample(now);
+            }
+        }
+
+        io_polls
+    }
+
+    pub fn retain_active(
+        &mut self,
+        paused_time: &PausedTime,
+        now: SystemTime,
+        retain_for: Duration,
+    ) {
+        self.resources.retain(|_, resource| {
+            let resource = resource.borrow();
+
+            resource
+                .stats
+                .dropped_at
+                .map(|d| {
+                    let dropped_for = paused_time.unpaused_since(d, now);
+                    retain_for > dropped_for
+                })
+                .unwrap_or(true)
+        })
+    }
+
+    pub fn dropped_events(&self) -> u64 {
+        self.dropped_events
+    }
+}
+
+/// Returns the group for `resource`, adding a new one if there isn't one yet.
+fn group_for<'a>(
+    groups: &'a mut HashMap<(InternedStr, InternedStr), Rc<RefCell<ResourceGroup>>>,
+    new_groups: &mut Vec<ResourceGroupRef>,
+    resource: &Resource,
+) -> &'a Rc<RefCell<ResourceGroup>> {
+    groups
+        .entry((resource.kind.clone(), resource.concrete_type.clone()))
+        .or_insert_with(|| {
+            let group = Rc::new(RefCell::new(ResourceGroup {
+                kind: resource.kind.clone(),
+                concrete_type: resource.concrete_type.clone(),
+                resources: Vec::new(),
+                created: 0,
+                dropped: 0,
+                samples: VecDeque::new(),
+                poll_counts: PollCounts::default(),
+            }));
+            new_groups.push(Rc::downgrade(&group));
+            group
+        })
+}
+
+impl ResourceGroup {
+    /// How far back the created and dropped rates are calculated over.
+    pub const RATE_WINDOW: Duration = Duration::from_secs(10);
+
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn concrete_type(&self) -> &str {
+        &self.concrete_type
+    }
+
+    /// Returns the number of resources in this group which have not been
+    /// dropped.
+    pub fn live(&self) -> usize {
+        self.live_resources().count()
+    }
+
+    /// Returns the number of resources in this group which have been dropped,
+    /// including those which are no longer retained.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Returns the number of resources created per second, over the last
+    /// [`Self::RATE_WINDOW`].
+    pub fn created_rate(&self) -> f64 {
+        self.rate(|&(_, created, _)| created)
+    }
+
+    /// Returns the number of resources dropped per second, over the last
+    /// [`Self::RATE_WINDOW`].
+    pub fn dropped_rate(&self) -> f64 {
+        self.rate(|&(_, _, dropped)| dropped)
+    }
+
+    /// Returns the sum of each numeric attribute over the resources in this
+    /// group which have not been dropped, along with the attribute's unit.
+    pub fn attribute_totals(&self) -> BTreeMap<InternedStr, (u64, Option<String>)> {
+        let mut totals = BTreeMap::new();
+        for resource in self.live_resources() {
+            let resource = resource.borrow();
+            for (name, value, unit) in &resource.stats.numeric_attributes {
+                let total = totals
+                    .entry(name.clone())
+                    .or_insert_with(|| (0u64, unit.clone()));
+                total.0 = total.0.saturating_add(*value);
+            }
+        }
+        totals
+    }
+
+    pub fn poll_counts(&self) -> PollCounts {
+        self.poll_counts
+    }
+
+    fn live_resources(&self) -> impl Iterator<Item = Rc<RefCell<Resource>>> + '_ {
+        self.resources
+            .iter()
+            .filter_map(Weak::upgrade)
+            .filter(|r| !r.borrow().dropped())
+    }
+
+    fn sample(&mut self, now: SystemTime) {
+        self.samples.push_back((now, self.created, self.dropped));
+        while let Some(&(oldest, _, _)) = self.samples.front() {
+            if now.duration_since(oldest).unwrap_or_default() <= Self::RATE_WINDOW {
+                break;
+            }
+            self.samples.pop_front();
+        }
+    }
+
+    fn rate(&self, count: impl Fn(&(SystemTime, u64, u64)) -> u64) -> f64 {
+        let (Some(oldest), Some(newest)) = (self.samples.front(), self.samples.back()) else {
+            return 0.0;
+        };
+        let elapsed = newest.0.duration_since(oldest.0).unwrap_or_default();
+        if elapsed.is_zero() {
+            return 0.0;
+        }
+        (count(newest) - count(oldest)) as f64 / elapsed.as_secs_f64()
+    }
+}
+
+impl Resource {
+    /// The number of values of each attribute to keep.
+    const MAX_ATTRIBUTE_HISTORY: usize = 120;
+
+    pub fn id(&self) -> Id<Resource> {
+        self.id
+    }
+
+    pub fn span_id(&self) -> u64 {
+        self.span_id
+    }
+
+    pub fn id_str(&self) -> &str {
+        &self.id_str
+    }
+
+    pub fn parent(&self) -> &str {
+        &self.parent
+    }
+
+    pub fn parent_id(&self) -> &str {
+        &self.parent_id
+    }
+
+    /// Returns the ID of the resource's parent resource, if it has one.
+    pub fn parent_resource(&self) -> Option<Id<Resource>> {
+        self.parent_resource
+    }
+
+    pub fn type_visibility(&self) -> TypeVisibility {
+        self.visibility
+    }
+
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn concrete_type(&self) -> &str {
+        &self.concrete_type
+    }
+
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    /// Returns `true` if the resource is an I/O resource, such as a socket or
+    /// a file, whose poll ops wait for I/O readiness.
+    ///
+    /// Runtimes mark I/O resources with the `io` kind, or by instrumenting
+    /// them in their `io` or `net` modules.
+    pub fn is_io(&self) -> bool {
+        self.kind.eq_ignore_ascii_case("io")
+            || self
+                .target
+                .split("::")
+                .any(|module| module == "io" || module == "net")
+    }
+
+    /// Returns `true` if the resource is a timer, such as Tokio's `Sleep`.
+    pub fn is_timer(&self) -> bool {
+        self.kind.eq_ignore_ascii_case("timer")
+    }
+
+    /// Returns the resource's attributes, sorted by name.
+    pub fn attributes(&self) -> &[Attribute] {
+        &self.stats.attributes
+    }
+
+    pub fn total(&self, since: SystemTime) -> Duration {
+        self.stats.total.unwrap_or_else(|| {
+            since
+                .duration_since(self.stats.created_at)
+                .unwrap_or_default()
+        })
+    }
+
+    /// Returns the span ID of the task holding the resource, if it is locked
+    /// and it is known which task last acquired it.
+    pub fn held_by(&self) -> Option<SpanId> {
+        if self.dropped() || !self.stats.locked {
+            return None;
+        }
+        self.stats.acquired_by
+    }
+
+    pub fn dropped(&self) -> bool {
+        self.stats.total.is_some()
+    }
+
+    pub fn created_at(&self) -> SystemTime {
+        self.stats.created_at
+    }
+
+    /// Returns `true` if the update received at `update_time` significantly
+    /// changed this resource: its attributes changed, or it was dropped.
+    pub fn changed_in_update(&self, update_time: SystemTime) -> bool {
+        if self.stats_updated_at != Some(update_time) {
+            return false;
+        }
+        self.prev_stats.as_ref().is_some_and(|prev| {
+            prev.attributes != self.stats.attributes || prev.total != self.stats.total
+        })
+    }
+
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+
+    pub fn poll_counts(&self) -> PollCounts {
+        self.poll_counts
+    }
+
+    /// Returns the recent values of each of the resource's numeric
+    /// attributes, oldest first.
+    ///
+    /// This is empty unless the subscriber is configured to keep the history
+    /// of attribute values.
+    pub fn attribute_history(&self) -> &BTreeMap<InternedStr, VecDeque<AttributeSample>> {
+        &self.attribute_history
+    }
+
+    /// Adds the attribute values sent with the latest stats to the history.
+    ///
+    /// The subscriber sends all of the values it still has each time, so
+    /// those which were already seen are skipped.
+    fn record_attribute_history(&mut self) {
+        for (name, sample) in &self.stats.attribute_history {
+            let history = self.attribute_history.entry(name.clone()).or_default();
+            if history.back().is_some_and(|last| last.at >= sample.at) {
+                continue;
+            }
+            if history.len() == Self::MAX_ATTRIBUTE_HISTORY {
+                history.pop_front();
+            }
+            history.push_back(*sample);
+        }
+    }
+}
+
+impl PollCounts {
+    pub(crate) fn record(&mut self, is_ready: bool) {
+        if is_ready {
+            self.ready += 1;
+        } else {
+            self.pending += 1;
+        }
+    }
+
+    /// Returns the number of polls.
+    pub fn polls(&self) -> u64 {
+        self.ready + self.pending
+    }
+
+    /// Returns the number of polls which returned ready.
+    pub fn ready(&self) -> u64 {
+        self.ready
+    }
+
+    /// Returns the fraction of polls which returned ready, if there have been
+    /// any polls.
+    pub fn ready_ratio(&self) -> Option<f64> {
+        let polls = self.ready + self.pending;
+        (polls > 0).then(|| self.ready as f64 / polls as f64)
+    }
+
+    /// Returns the average number of polls it took to return ready, if any
+    /// have returned ready.
+    pub fn polls_per_ready(&self) -> Option<f64> {
+        (self.ready > 0).then(|| (self.ready + self.pending) as f64 / self.ready as f64)
+    }
+}
+
+impl ResourceStats {
+    fn from_proto(
+        pb: proto::resources::Stats,
+        meta: &Metadata,
+        strings: &mut intern::Strings,
+    ) -> Self {
+        let mut pb = pb;
+        let mut attributes = pb
+            .attributes
+            .drain(..)
+            .filter_map(|pb| {
+                let field = pb.field?;
+                let field = Field::from_proto(field, meta, strings)?;
+                Some(Attribute {
+                    field,
+                    unit: pb.unit,
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut attribute_history = pb
+            .attribute_history
+            .drain(..)
+            .filter_map(|pb| {
+                let at = pb.at?.try_into().ok()?;
+                let field = Field::from_proto(pb.attribute?.field?, meta, strings)?;
+                match field.value {
+                    FieldValue::U64(value) => Some((field.name, AttributeSample { at, value })),
+                    _ => None,
+                }
+            })
+            .collect::<Vec<_>>();
+        attribute_history.sort_by_key(|(_, sample)| sample.at);
+        let numeric_attributes = attributes
+            .iter()
+            .filter_map(|attr| match attr.field.value {
+                FieldValue::U64(value) => Some((attr.field.name.clone(), value, attr.unit.clone())),
+                _ => None,
+            })
+            .collect();
+        let locked = attributes.iter().any(|attr| {
+            matches!(
+                (&*attr.field.name, &attr.field.value),
+                ("locked" | "write_locked", FieldValue::Bool(true))
+                    | ("permits", FieldValue::U64(0))
+            )
+        });
+
+        attributes.sort_unstable();
+        let created_at = pb
+            .created_at
+            .expect("resource span was never created")
+            .try_into()
+            .unwrap();
+        let dropped_at: Option<SystemTime> = pb.dropped_at.map(|v| v.try_into().unwrap());
+        let total = dropped_at.map(|d| d.duration_since(created_at).unwrap_or_default());
+
+        Self {
+            created_at,
+            dropped_at,
+            total,
+            attributes,
+            numeric_attributes,
+            locked,
+            acquired_by: pb.acquired_by.map(|id| id.id),
+            attribute_history,
+        }
+    }
+}
+
+fn kind_from_proto(
+    pb: proto::resources::resource::Kind,
+    strings: &mut intern::Strings,
+) -> Result<InternedStr, String> {
+    use proto::resources::resource::kind::Kind::Known as PbKnown;
+    use proto::resources::resource::kind::Kind::Other as PBOther;
+    use proto::resources::resource::kind::Known::Timer as PbTimer;
+
+    match pb.kind.expect("a resource should have a kind field") {
+        PbKnown(known) if known == (PbTimer as i32) => Ok(strings.string("Timer".to_string())),
+        PbKnown(known) => Err(format!("failed to parse known kind from {}", known)),
+        PBOther(other) => Ok(strings.string(other)),
+    }
+}
+
+impl TypeVisibility {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Internal => "internal",
+            Self::Public => "public",
+        }
+    }
+}
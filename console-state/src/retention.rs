@@ -0,0 +1,112 @@
+//! Measures how long dropped items have been retained for, without counting
+//! the time the console was paused.
+//!
+//! Times are the target's, as reported in its updates, so a pause is counted
+//! from the last update received before pausing until the first update
+//! received after resuming.
+use std::{
+    collections::VecDeque,
+    time::{Duration, SystemTime},
+};
+
+/// The periods during which the console was paused.
+#[derive(Debug, Default)]
+pub struct PausedTime {
+    /// Completed pauses, as their start and end, oldest first.
+    pauses: VecDeque<(SystemTime, SystemTime)>,
+    /// The time of the last update received before pausing, if the console
+    /// has been paused since.
+    paused_after: Option<SystemTime>,
+}
+
+impl PausedTime {
+    /// Records that the console is paused, after the update received at
+    /// `last_update`.
+    pub fn pause(&mut self, last_update: SystemTime) {
+        self.paused_after.get_or_insert(last_update);
+    }
+
+    /// Records that an update was received at `now`, ending the current pause
+    /// unless the console is still `paused`.
+    pub fn update(&mut self, now: SystemTime, paused: bool) {
+        if paused {
+            // Updates may still arrive until the target confirms the pause.
+            self.paused_after = Some(now);
+        } else if let Some(start) = self.paused_after.take() {
+            self.pauses.push_back((start, now));
+        }
+    }
+
+    /// Returns how long it has been since `since`, as of `now`, not counting
+    /// any time the console was paused.
+    pub fn unpaused_since(&self, since: SystemTime, now: SystemTime) -> Duration {
+        let elapsed = now.duration_since(since).unwrap_or_default();
+        self.pauses.iter().fold(elapsed, |elapsed, &(start, end)| {
+            let paused = end
+                .min(now)
+                .duration_since(start.max(since))
+                .unwrap_or_default();
+            elapsed.saturating_sub(paused)
+        })
+    }
+
+    /// Forgets pauses which ended so long ago that anything dropped before
+    /// them has exceeded `retention` anyway.
+    pub fn prune(&mut self, now: SystemTime, retention: Duration) {
+        while let Some(&(_, end)) = self.pauses.front() {
+            if self.unpaused_since(end, now) <= retention {
+                break;
+            }
+            self.pauses.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pauses_stop_the_retention_clock() {
+        let start = SystemTime::UNIX_EPOCH;
+        let at = |secs| start + Duration::from_secs(secs);
+        let retention = Duration::from_secs(10);
+        let mut paused = PausedTime::default();
+
+        // Dropped at 5s, then paused after the update at 8s, for a minute.
+        paused.update(at(8), false);
+        paused.pause(at(8));
+        paused.update(at(68), false);
+        assert_eq!(paused.unpaused_since(at(5), at(68)), Duration::from_secs(3));
+        // The retention clock runs again after resuming.
+        assert_eq!(
+            paused.unpaused_since(at(5), at(75)),
+            Duration::from_secs(10)
+        );
+        assert_eq!(
+            paused.unpaused_since(at(5), at(76)),
+            Duration::from_secs(11)
+        );
+        // Items dropped after resuming aren't affected by the pause.
+        assert_eq!(
+            paused.unpaused_since(at(70), at(76)),
+            Duration::from_secs(6)
+        );
+
+        // The pause no longer matters once everything dropped before it would
+        // have been evicted anyway.
+        paused.prune(at(78), retention);
+        assert_eq!(paused.pauses.len(), 1);
+        paused.prune(at(79), retention);
+        assert!(paused.pauses.is_empty());
+
+        // Updates received while pausing move the start of the pause.
+        paused.pause(at(80));
+        paused.update(at(81), true);
+        paused.update(at(91), false);
+        assert_eq!(
+            paused.unpaused_since(at(76), at(91)),
+            Duration::from_secs(5)
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use crate::pb_duration;
+use console_api as proto;
+use std::collections::VecDeque;
+
+/// Aggregate statistics describing the instrumented runtime as a whole.
+#[derive(Debug, Default)]
+pub struct RuntimeState {
+    /// Recent runtime saturation estimates, oldest first.
+    ///
+    /// Each sample is the fraction (from 0.0 to 1.0) of the runtime's worker
+    /// time that was spent polling tasks over a single update interval.
+    saturation: VecDeque<f64>,
+}
+
+impl RuntimeState {
+    /// The maximum number of saturation samples to keep.
+    const MAX_SATURATION_SAMPLES: usize = 120;
+
+    pub fn update_runtime(&mut self, update: proto::instrument::RuntimeStats) {
+        let (Some(busy_time), Some(interval)) = (update.busy_time, update.interval) else {
+            return;
+        };
+
+        let capacity = pb_duration(interval).as_secs_f64() * update.worker_threads as f64;
+        if capacity <= 0.0 {
+            return;
+        }
+
+        // A poll is only counted once it has completed, so polls that span
+        // several intervals may push a single sample above 100%.
+        let saturation = (pb_duration(busy_time).as_secs_f64() / capacity).clamp(0.0, 1.0);
+        if self.saturation.len() == Self::MAX_SATURATION_SAMPLES {
+            self.saturation.pop_front();
+        }
+        self.saturation.push_back(saturation);
+    }
+
+    /// Returns the most recent saturation estimate, if one has been received.
+    pub fn saturation(&self) -> Option<f64> {
+        self.saturation.back().copied()
+    }
+
+    /// Returns the most recent `n` saturation estimates, oldest first.
+    pub fn recent_saturation(&self, n: usize) -> impl Iterator<Item = f64> + '_ {
+        self.saturation
+            .iter()
+            .skip(self.saturation.len().saturating_sub(n))
+            .copied()
+    }
+}
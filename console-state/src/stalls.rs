@@ -0,0 +1,221 @@
+//! Finds probable deadlocks: sets of tasks which have been idle for a while,
+//! and are each waiting on a resource held by another task in the set.
+//!
+//! A resource is considered held by the task which last acquired it, for as
+//! long as its attributes say it is locked (such as a `Mutex` with
+//! `locked=true`, or a `Semaphore` with no permits left).
+use super::{
+    resources::ResourcesState,
+    store::SpanId,
+    tasks::{TaskState, TasksState},
+};
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime},
+};
+
+/// How long tasks must have been idle for before they are considered
+/// stalled, unless configured otherwise.
+pub const DEFAULT_THRESHOLD: Duration = Duration::from_secs(10);
+
+/// A set of stalled tasks which are waiting on each other.
+#[derive(Debug)]
+pub struct Stall {
+    /// Each task in the set, with what it is waiting on.
+    pub links: Vec<StallLink>,
+}
+
+/// A stalled task, and the resource it is waiting on.
+#[derive(Debug)]
+pub struct StallLink {
+    /// The task's ID and name.
+    pub task: String,
+    pub idle_for: Duration,
+    /// The kind, type and ID of the resource the task is waiting on.
+    pub resource: String,
+    /// The ID and name of the task holding the resource.
+    pub held_by: String,
+}
+
+/// Returns the sets of tasks which have been idle for at least `threshold`,
+/// and are waiting on resources held by each other.
+pub fn find(
+    tasks: &TasksState,
+    resources: &ResourcesState,
+    now: SystemTime,
+    threshold: Duration,
+) -> Vec<Stall> {
+    // The stalled tasks, and how long each has been idle.
+    let stalled = tasks
+        .tasks()
+        .filter_map(|task| {
+            let task = task.borrow();
+            if task.state() != TaskState::Idle {
+                return None;
+            }
+            let idle_for = task.idle_for(now)?;
+            (idle_for >= threshold).then(|| (task.span_id(), idle_for))
+        })
+        .collect::<HashMap<_, _>>();
+
+    // An edge from each stalled task to the stalled tasks holding the
+    // resources it waits on, labelled with the resource.
+    let mut edges: HashMap<SpanId, Vec<(SpanId, SpanId)>> = HashMap::new();
+    for &task_id in stalled.keys() {
+        let Some(task) = tasks.task_by_span(task_id) else {
+            continue;
+        };
+        for &resource_id in task.borrow().waiting_on() {
+            let holder = resources
+                .resource_by_span(resource_id)
+                .and_then(|resource| resource.borrow().held_by());
+            if let Some(holder) = holder.filter(|holder| stalled.contains_key(holder)) {
+                edges
+                    .entry(task_id)
+                    .or_default()
+                    .push((holder, resource_id));
+            }
+        }
+    }
+
+    let describe_task = |span_id: SpanId| {
+        tasks
+            .task_by_span(span_id)
+            .map(|task| format!("task {}", task.borrow().short_desc()))
+            .unwrap_or_else(|| "unknown task".to_owned())
+    };
+    cycles(&edges)
+        .into_iter()
+        .map(|cycle| Stall {
+            links: cycle
+                .into_iter()
+                .map(|(task_id, holder, resource_id)| StallLink {
+                    task: describe_task(task_id),
+                    idle_for: stalled[&task_id],
+                    resource: resources
+                        .resource_by_span(resource_id)
+                        .map(|resource| {
+                            let resource = resource.borrow();
+                            format!(
+                                "{} {} (resource {})",
+                                resource.kind(),
+                                resource.concrete_type(),
+                                resource.id_str()
+                            )
+                        })
+                        .unwrap_or_else(|| "unknown resource".to_owned()),
+                    held_by: describe_task(holder),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Returns the strongly connected components of the graph which contain a
+/// cycle, as each node in the component with its first edge to another node
+/// in the same component.
+///
+/// `edges` maps each node to the nodes it has edges to, and a label for each
+/// edge.
+fn cycles<L: Copy>(edges: &HashMap<SpanId, Vec<(SpanId, L)>>) -> Vec<Vec<(SpanId, SpanId, L)>> {
+    // Tarjan's algorithm.
+    struct Search<'a, L> {
+        edges: &'a HashMap<SpanId, Vec<(SpanId, L)>>,
+        next_index: usize,
+        // The index and lowest reachable index of each visited node.
+        indices: HashMap<SpanId, (usize, usize)>,
+        stack: Vec<SpanId>,
+        components: Vec<Vec<SpanId>>,
+    }
+
+    impl<L> Search<'_, L> {
+        fn visit(&mut self, node: SpanId) {
+            let index = self.next_index;
+            self.next_index += 1;
+            self.indices.insert(node, (index, index));
+            self.stack.push(node);
+
+            for &(next, _) in self.edges.get(&node).into_iter().flatten() {
+                let low = match self.indices.get(&next) {
+                    None => {
+                        self.visit(next);
+                        self.indices[&next].1
+                    }
+                    Some(&(next_index, _)) if self.stack.contains(&next) => next_index,
+                    Some(_) => continue,
+                };
+                let entry = self.indices.get_mut(&node).expect("node was visited");
+                entry.1 = entry.1.min(low);
+            }
+
+            if self.indices[&node] == (index, index) {
+                let start = self
+                    .stack
+                    .iter()
+                    .position(|&n| n == node)
+                    .expect("node is on the stack");
+                self.components.push(self.stack.split_off(start));
+            }
+        }
+    }
+
+    let mut search = Search {
+        edges,
+        next_index: 0,
+        indices: HashMap::new(),
+        stack: Vec::new(),
+        components: Vec::new(),
+    };
+    let mut nodes = edges.keys().copied().collect::<Vec<_>>();
+    // Visit in a stable order, so the same cycles are listed the same way.
+    nodes.sort_unstable();
+    for node in nodes {
+        if !search.indices.contains_key(&node) {
+            search.visit(node);
+        }
+    }
+
+    search
+        .components
+        .into_iter()
+        .filter_map(|mut component| {
+            component.sort_unstable();
+            let links = component
+                .iter()
+                .filter_map(|&node| {
+                    edges
+                        .get(&node)?
+                        .iter()
+                        .find(|(next, _)| component.binary_search(next).is_ok())
+                        .map(|&(next, label)| (node, next, label))
+                })
+                .collect::<Vec<_>>();
+            // A single node is only a cycle if it has an edge to itself.
+            (links.len() == component.len()).then_some(links)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_cycles() {
+        let edges = HashMap::from([
+            // 1 and 2 wait on each other.
+            (1, vec![(2, 'a')]),
+            (2, vec![(1, 'b'), (3, 'c')]),
+            // 3 waits on 4, which isn't waiting on anything.
+            (3, vec![(4, 'd')]),
+            // 5 waits on itself.
+            (5, vec![(5, 'e')]),
+        ]);
+        let mut found = cycles(&edges);
+        found.sort();
+        assert_eq!(
+            found,
+            vec![vec![(1, 2, 'a'), (2, 1, 'b')], vec![(5, 5, 'e')]]
+        );
+    }
+}
@@ -15,29 +15,32 @@ use super::Visibility;
 /// Stores a set of items which are associated with a [`SpanId`] and a rewritten
 /// sequential [`Id`].
 #[derive(Debug)]
-pub(crate) struct Store<T> {
+pub struct Store<T> {
     ids: Ids<T>,
     store: HashMap<Id<T>, Stored<T>>,
     new_items: Vec<Ref<T>>,
 }
 
-pub(crate) type Ref<T> = Weak<RefCell<T>>;
-pub(crate) type Stored<T> = Rc<RefCell<T>>;
-pub(crate) type SpanId = u64;
+pub type Ref<T> = Weak<RefCell<T>>;
+pub type Stored<T> = Rc<RefCell<T>>;
+pub type SpanId = u64;
 
 /// A rewritten sequential ID.
 ///
 /// This is distinct from the remote server's span ID, which may be reused and
 /// is not sequential.
-pub(crate) struct Id<T> {
+pub struct Id<T> {
     id: u64,
     _ty: PhantomData<fn(T)>,
 }
 
 /// Stores the rewritten sequential IDs of items in a [`Store`].
-pub(crate) struct Ids<T> {
+pub struct Ids<T> {
     next: u64,
     map: HashMap<u64, Id<T>>,
+    /// The IDs of items which have a key that identifies them more reliably
+    /// than their span ID, such as a Tokio task ID.
+    keys: HashMap<u64, Id<T>>,
 }
 
 // === impl Store ===
@@ -60,6 +63,11 @@ impl<T> Store<T> {
     /// `U`-typed item to a `T`-typed item and an [`Id`] for that item, inserts
     /// the `T`-typed items into the store along with their IDs.
     ///
+    /// If an item with the same [`Id`] is already stored, such as when the
+    /// console reconnects and the target sends every item again, it's
+    /// replaced in place, so that anything holding on to it sees the new item
+    /// rather than it being added again.
+    ///
     /// This function has an admittedly somewhat complex signature. It would be
     /// nicer if this could just be an `iter::Extend` implementation, but that
     /// makes borrowing the set of [`Ids`] in the closure that's mapped over the
@@ -72,15 +80,19 @@ impl<T> Store<T> {
         mut f: impl FnMut(&mut Ids<T>, U) -> Option<(Id<T>, T)>,
     ) {
         self.set_visibility(visibility);
-        let items = items
-            .into_iter()
-            .filter_map(|item| f(&mut self.ids, item))
-            .map(|(id, item)| {
-                let item = Rc::new(RefCell::new(item));
-                self.new_items.push(Rc::downgrade(&item));
-                (id, item)
-            });
-        self.store.extend(items);
+        for item in items {
+            let Some((id, item)) = f(&mut self.ids, item) else {
+                continue;
+            };
+            match self.store.entry(id) {
+                Entry::Occupied(entry) => *entry.get().borrow_mut() = item,
+                Entry::Vacant(entry) => {
+                    let item = Rc::new(RefCell::new(item));
+                    self.new_items.push(Rc::downgrade(&item));
+                    entry.insert(item);
+                }
+            }
+        }
     }
 
     pub fn updated<'store, U, I>(
@@ -151,20 +163,40 @@ impl<'store, T> IntoIterator for &'store Store<T> {
 // === impl Ids ===
 
 impl<T> Ids<T> {
-    pub(crate) fn id_for(&mut self, span_id: SpanId) -> Id<T> {
+    pub fn id_for(&mut self, span_id: SpanId) -> Id<T> {
         match self.map.entry(span_id) {
             Entry::Occupied(entry) => *entry.get(),
-            Entry::Vacant(entry) => {
-                let id = Id {
-                    id: self.next,
-                    _ty: PhantomData,
-                };
-                entry.insert(id);
-                self.next = self.next.wrapping_add(1);
-                id
-            }
+            Entry::Vacant(entry) => *entry.insert(Self::next_id(&mut self.next)),
         }
     }
+
+    /// Returns the ID of the item with `span_id`, or the item identified by
+    /// `key` if it has one.
+    ///
+    /// Span IDs may be reused once a span is closed, so an item with a key
+    /// keeps the same ID as long as its key does, even if it's sent with a
+    /// different span ID, and never takes over the ID of an item which had
+    /// the same span ID before it.
+    pub fn id_for_key(&mut self, span_id: SpanId, key: Option<u64>) -> Id<T> {
+        let Some(key) = key else {
+            return self.id_for(span_id);
+        };
+        let id = *self
+            .keys
+            .entry(key)
+            .or_insert_with(|| Self::next_id(&mut self.next));
+        self.map.insert(span_id, id);
+        id
+    }
+
+    fn next_id(next: &mut u64) -> Id<T> {
+        let id = Id {
+            id: *next,
+            _ty: PhantomData,
+        };
+        *next = next.wrapping_add(1);
+        id
+    }
 }
 
 impl<T> Default for Ids<T> {
@@ -172,6 +204,7 @@ impl<T> Default for Ids<T> {
         Self {
             next: 1,
             map: Default::default(),
+            keys: Default::default(),
         }
     }
 }
@@ -181,6 +214,7 @@ impl<T> fmt::Debug for Ids<T> {
         f.debug_struct("Ids")
             .field("next", &self.next)
             .field("map", &self.map)
+            .field("keys", &self.keys)
             .field("type", &format_args!("{}", any::type_name::<T>()))
             .finish()
     }
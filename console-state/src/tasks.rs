@@ -0,0 +1,1791 @@
+use crate::{
+    budget::{BudgetMetric, BudgetStatus, BudgetTracker, LatencyBudget},
+    format_location,
+    histogram::DurationHistogram,
+    intern::{self, InternedStr},
+    pb_duration,
+    resources::PollCounts,
+    retention::PausedTime,
+    store::{self, Id, SpanId, Store},
+    util::Percentage,
+    warnings::{Lint, Linter, WarningHistory},
+    Field, FieldValue, Metadata, SourceLocation, Visibility,
+};
+use console_api as proto;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    convert::{TryFrom, TryInto},
+    mem,
+    rc::{Rc, Weak},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Default, Debug)]
+pub struct TasksState {
+    tasks: Store<Task>,
+    pending_lint: HashSet<Id<Task>>,
+    pub linters: Vec<Linter<Task>>,
+    /// Linters which were configured but have been disabled, so they aren't
+    /// checked until they're enabled again.
+    disabled_linters: Vec<Linter<Task>>,
+    warning_history: WarningHistory,
+    /// The estimated instrumentation overhead of every task seen, including
+    /// evicted tasks, summed by spawn location.
+    overhead_by_location: HashMap<String, Duration>,
+    dropped_events: u64,
+    spawn_history: SpawnHistory,
+    /// Latency budgets for tasks, by task name.
+    pub latency_budgets: BTreeMap<String, LatencyBudget>,
+    /// The tasks being watched, which are kept after they end regardless of
+    /// retention.
+    watched: HashSet<Id<Task>>,
+    /// Watched tasks which have ended since they were last taken.
+    ended_watched: Vec<Id<Task>>,
+    /// The tasks the user pinned, which are kept after they end regardless
+    /// of retention.
+    pinned: HashSet<Id<Task>>,
+    /// How long all tasks were busy for over each window, as of the latest
+    /// update.
+    busy_totals: ByWindow<Duration>,
+}
+
+/// A time-bucketed history of how many tasks were spawned and dropped.
+#[derive(Debug, Default)]
+pub struct SpawnHistory {
+    /// Buckets of spawn and drop counts, oldest first.
+    buckets: VecDeque<SpawnBucket>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct SpawnBucket {
+    /// The index of this bucket, in units of [`SpawnHistory::BUCKET_WIDTH`]
+    /// since the Unix epoch.
+    index: u64,
+    spawned: u64,
+    dropped: u64,
+}
+
+/// Tracks how a task's live waker count changes across stats updates, to
+/// detect wakers which are stored and never dropped.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WakerTrend {
+    /// The live waker count in the most recent update.
+    live: u64,
+    /// The number of consecutive updates in which the live waker count grew.
+    growing_for: u32,
+    /// The live waker count before it started growing.
+    grown_from: u64,
+}
+
+/// The changes in a task's poll and wake counts over recent updates, from
+/// which its rates over a sliding [`RateWindow`] are computed.
+#[derive(Debug, Default, Clone)]
+pub struct RateHistory {
+    /// The counts added by each update, oldest first.
+    deltas: VecDeque<RateDelta>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct RateDelta {
+    /// The time of the update.
+    at: SystemTime,
+    polls: u64,
+    wakes: u64,
+    busy: Duration,
+}
+
+/// The window over which poll and wake rates are computed.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum RateWindow {
+    OneSecond,
+    #[default]
+    TenSeconds,
+    OneMinute,
+}
+
+/// A value for each [`RateWindow`].
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct ByWindow<T> {
+    one_second: T,
+    ten_seconds: T,
+    one_minute: T,
+}
+
+#[derive(Debug, Default)]
+pub struct Details {
+    pub span_id: SpanId,
+    pub poll_times_histogram: Option<DurationHistogram>,
+    /// Poll times recorded since the previous details update.
+    pub recent_poll_times_histogram: Option<DurationHistogram>,
+    pub scheduled_times_histogram: Option<DurationHistogram>,
+    /// The locations the task was most often woken from, and how many times
+    /// it was woken from each, most frequent first.
+    pub wake_sites: Vec<(String, u64)>,
+    /// The number of wakes and self-wakes in each recent publish interval,
+    /// oldest first.
+    pub wake_history: Vec<(u64, u64)>,
+    /// The threads the task was polled on, and how many of its polls each
+    /// performed, most polls first.
+    pub thread_polls: Vec<(String, u64)>,
+    /// The async ops the task is currently waiting on.
+    pub waiting_on: Vec<WaitingOn>,
+}
+
+/// An async op which a task is currently waiting on.
+#[derive(Debug)]
+pub struct WaitingOn {
+    /// The span ID of the resource the async op is performed on.
+    pub resource_span_id: SpanId,
+    pub op_name: String,
+    /// When the async op first returned pending to the task.
+    pub since: Option<SystemTime>,
+}
+
+/// Aggregate statistics for all the tasks in a group.
+#[derive(Debug)]
+pub struct GroupStats {
+    pub name: InternedStr,
+    pub tasks: usize,
+    pub running: usize,
+    pub idle: usize,
+    pub busy: Duration,
+    pub polls: u64,
+}
+
+/// The `kind` of tasks that run on the runtime's blocking pool.
+const BLOCKING_POOL_KIND: &str = "blocking";
+
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(usize)]
+pub enum SortBy {
+    Pinned = 0,
+    Warns = 1,
+    Tid = 2,
+    State = 3,
+    Name = 4,
+    #[default]
+    Total = 5,
+    Busy = 6,
+    Scheduled = 7,
+    Idle = 8,
+    Polls = 9,
+    Kind = 10,
+    Group = 11,
+    Runtime = 12,
+    Location = 13,
+    LastPoll = 14,
+    LastWake = 15,
+    PollRate = 16,
+    WakeRate = 17,
+    IoPolls = 18,
+    IoReady = 19,
+    BusyPercent = 20,
+    RuntimeShare = 21,
+}
+
+/// Which value is shown and sorted by for each task's scheduled time.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum ScheduledMetric {
+    /// The total time the task has spent waiting to be polled after being
+    /// woken.
+    #[default]
+    Total,
+    /// The mean time the task waited to be polled after being woken.
+    Mean,
+    /// The 95th percentile of the time the task waited to be polled, once its
+    /// scheduled times histogram has been received.
+    P95,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum TaskState {
+    Completed,
+    /// The task was aborted before it completed.
+    Cancelled,
+    /// The task panicked.
+    Panicked,
+    Idle,
+    Running,
+    Scheduled,
+}
+
+pub type TaskRef = store::Ref<Task>;
+
+/// The Id for a Tokio task.
+///
+/// This should be equivalent to [`tokio::task::Id`], which can't be
+/// used because it's not possible to construct outside the `tokio`
+/// crate.
+///
+/// Within the context of `tokio-console`, we don't depend on it
+/// being the same as Tokio's own type, as the task id is recorded
+/// as a `u64` in tracing and then sent via the wire protocol as such.
+///
+/// [`tokio::task::Id`]: https://docs.rs/tokio/latest/tokio/task/struct.Id.html
+pub type TaskId = u64;
+
+#[derive(Debug)]
+pub struct Task {
+    /// The task's pretty (console-generated, sequential) task ID.
+    ///
+    /// This is NOT the `tracing::span::Id` for the task's tracing span on the
+    /// remote.
+    id: Id<Task>,
+    /// The `tokio::task::Id` in the remote tokio runtime.
+    task_id: Option<TaskId>,
+    /// The `tracing::span::Id` on the remote process for this task's span.
+    ///
+    /// This is used when requesting a task details stream.
+    span_id: SpanId,
+    /// A cached string representation of the Id for display purposes.
+    id_str: String,
+    /// A precomputed short description string used in the async ops table
+    short_desc: InternedStr,
+    /// Fields that don't have their own column, sorted by name
+    fields: Vec<Field>,
+    /// The task statistics that are updated over the lifetime of the task
+    stats: TaskStats,
+    /// The target of the span representing the task
+    target: InternedStr,
+    /// The name of the task (when `tokio::task::Builder` is used)
+    name: Option<InternedStr>,
+    /// Currently active warnings for this task.
+    warnings: Vec<Linter<Task>>,
+    /// The source file and line number the task was spawned from
+    location: String,
+    /// The untruncated file and line the task was spawned from, if known,
+    /// for previewing its source.
+    source_location: Option<SourceLocation>,
+    /// The frames of the stack the task was spawned from, innermost first, if
+    /// the subscriber captured them.
+    spawn_backtrace: Vec<String>,
+    /// The kind of task, currently one of task, blocking, block_on, local
+    kind: InternedStr,
+    /// The label of the runtime the task was spawned on, if known
+    runtime: Option<InternedStr>,
+    /// The group the application assigned the task to, if any
+    group: Option<InternedStr>,
+    /// The size of the future driving the task
+    size_bytes: Option<usize>,
+    /// The original size of the future (before runtime auto-boxing)
+    original_size_bytes: Option<usize>,
+    /// The task statistics before they were last updated, used to highlight
+    /// tasks that changed.
+    prev_stats: Option<TaskStats>,
+    /// The time of the last update that changed the task statistics.
+    stats_updated_at: Option<SystemTime>,
+    /// The task's latency budget, if one is configured for its name.
+    latency_budget: Option<BudgetTracker>,
+    /// How the task's live waker count has changed across updates.
+    waker_trend: WakerTrend,
+    /// The 95th percentile of the task's scheduled times, as of the last
+    /// time its details were received.
+    scheduled_p95: Option<Duration>,
+    /// Recent changes in the task's poll and wake counts.
+    rates: RateHistory,
+    /// The share of all tasks' busy time over each window that this task was
+    /// busy for, as of the latest update.
+    runtime_share: ByWindow<f64>,
+    /// How many times the task polled I/O resources, and how many of those
+    /// polls found them ready.
+    io_polls: PollCounts,
+    /// Whether the user pinned the task, so that it's kept after it ends
+    /// regardless of retention.
+    pinned: bool,
+}
+
+#[derive(Debug)]
+struct TaskStats {
+    polls: u64,
+    created_at: SystemTime,
+    dropped_at: Option<SystemTime>,
+    busy: Duration,
+    /// Busy time split into polls that returned `Ready` and `Pending`, if the
+    /// subscriber records it.
+    busy_by_outcome: Option<(Duration, Duration)>,
+    scheduled: Duration,
+    first_poll: Option<SystemTime>,
+    last_poll_started: Option<SystemTime>,
+    last_poll_ended: Option<SystemTime>,
+    idle: Option<Duration>,
+    total: Option<Duration>,
+
+    // === waker stats ===
+    /// Total number of times the task has been woken over its lifetime.
+    wakes: u64,
+    /// Total number of times the task's waker has been cloned
+    waker_clones: u64,
+
+    /// Total number of times the task's waker has been dropped.
+    waker_drops: u64,
+
+    /// The timestamp of when the task was last woken.
+    last_wake: Option<SystemTime>,
+    /// Total number of times the task has woken itself.
+    self_wakes: u64,
+
+    /// Estimated time spent instrumenting the task, if the subscriber samples
+    /// its overhead.
+    overhead: Option<Duration>,
+
+    /// Whether the task was aborted before it completed.
+    cancelled: bool,
+
+    /// Whether the task panicked.
+    panicked: bool,
+
+    /// The innermost span of application code the task is in, if it's being
+    /// polled and the target tracks user spans.
+    current_span: Option<String>,
+
+    /// The span IDs of the resources the task is currently waiting on.
+    waiting_on: Vec<SpanId>,
+}
+
+impl TaskStats {
+    fn state(&self) -> TaskState {
+        if self.total.is_some() {
+            if self.panicked {
+                return TaskState::Panicked;
+            }
+            if self.cancelled {
+                return TaskState::Cancelled;
+            }
+            return TaskState::Completed;
+        }
+
+        if self.last_poll_started > self.last_poll_ended {
+            return TaskState::Running;
+        }
+
+        if self.last_wake > self.last_poll_started {
+            return TaskState::Scheduled;
+        }
+
+        TaskState::Idle
+    }
+}
+
+impl TasksState {
+    /// Returns any new tasks that were added since the last task update.
+    pub fn take_new_tasks(&mut self) -> impl Iterator<Item = TaskRef> + '_ {
+        self.tasks.take_new_items()
+    }
+
+    /// Counts poll ops on I/O resources towards the tasks which made them,
+    /// given the task's span ID and whether the resource was ready.
+    pub fn record_io_polls(&mut self, polls: impl IntoIterator<Item = (SpanId, bool)>) {
+        for (span_id, is_ready) in polls {
+            if let Some(task) = self.tasks.get_by_span(span_id) {
+                task.borrow_mut().io_polls.record(is_ready);
+            }
+        }
+    }
+
+    /// Starts watching the task `id`, or stops watching it if it's already
+    /// watched, returning whether it's now watched.
+    pub fn toggle_watched(&mut self, id: Id<Task>) -> bool {
+        if self.watched.remove(&id) {
+            false
+        } else {
+            self.watched.insert(id)
+        }
+    }
+
+    /// Pins the task `id`, or unpins it if it's already pinned, returning
+    /// whether it's now pinned.
+    pub fn toggle_pinned(&mut self, id: Id<Task>) -> bool {
+        let Some(task) = self.tasks.get(id) else {
+            return false;
+        };
+        let pinned = if self.pinned.remove(&id) {
+            false
+        } else {
+            self.pinned.insert(id)
+        };
+        task.borrow_mut().pinned = pinned;
+        pinned
+    }
+
+    /// Returns the tasks being watched.
+    pub fn watched(&self) -> &HashSet<Id<Task>> {
+        &self.watched
+    }
+
+    /// Returns the watched tasks which have completed or been dropped since
+    /// this was last called.
+    pub fn take_ended_watched(&mut self) -> Vec<Id<Task>> {
+        mem::take(&mut self.ended_watched)
+    }
+
+    pub fn ids_mut(&mut self) -> &mut store::Ids<Task> {
+        self.tasks.ids_mut()
+    }
+
+    /// Returns the task with the given `tracing` span ID, if it is present.
+    pub fn task_by_span(&self, span_id: SpanId) -> Option<&store::Stored<Task>> {
+        self.tasks.get_by_span(span_id)
+    }
+
+    /// Applies a task update, returning the number of tasks it spawned and
+    /// dropped.
+    ///
+    /// If `is_full_state` is set, the update carries the complete state
+    /// rather than only what changed, so the tasks it adds aren't counted
+    /// towards the [`SpawnHistory`].
+    pub fn update_tasks(
+        &mut self,
+        strings: &mut intern::Strings,
+        metas: &HashMap<u64, Metadata>,
+        update: proto::tasks::TaskUpdate,
+        visibility: Visibility,
+        now: Option<SystemTime>,
+        is_full_state: bool,
+    ) -> (u64, u64) {
+        let mut stats_update = update.stats_update;
+        let linters = &self.linters;
+        let warning_history = &mut self.warning_history;
+        let overhead_by_location = &mut self.overhead_by_location;
+        let latency_budgets = &self.latency_budgets;
+        let seen_at = now.unwrap_or_else(SystemTime::now);
+
+        // Gathers the tasks that need to be linted again on the next update cycle
+        let mut next_pending_lint = HashSet::new();
+
+        // Counts of tasks spawned and dropped in this update
+        let mut spawned = 0;
+        let mut dropped = 0;
+
+        let pinned = &self.pinned;
+
+        self.tasks
+            .insert_with(visibility, update.new_tasks, |ids, mut task| {
+                let span_id = match task.id.as_ref() {
+                    Some(id) => id.id,
+                    None => {
+                        tracing::warn!(?task, "task has no id, skipping");
+                        return None;
+                    }
+                };
+
+                let meta_id = match task.metadata.as_ref() {
+                    Some(id) => id.id,
+                    None => {
+                        tracing::warn!(?task, "task has no metadata id, skipping");
+                        return None;
+                    }
+                };
+                let meta = match metas.get(&meta_id) {
+                    Some(meta) => meta,
+                    None => {
+                        tracing::warn!(?task, meta_id, "no metadata for task, skipping");
+                        return None;
+                    }
+                };
+                let mut name = None;
+                let mut task_id = None;
+                let mut kind = strings.string(String::new());
+                let mut size_bytes = None;
+                let mut original_size_bytes = None;
+                let target_field = Field::new(
+                    strings.string_ref("target"),
+                    FieldValue::Str(meta.target.to_string()),
+                );
+                let mut fields = task
+                    .fields
+                    .drain(..)
+                    .filter_map(|pb| {
+                        let field = Field::from_proto(pb, meta, strings)?;
+                        // the `task.name` field gets its own column, if it's present.
+                        match &*field.name {
+                            Field::NAME => {
+                                name = Some(strings.string(field.value.to_string()));
+                                None
+                            }
+                            Field::TASK_ID => {
+                                task_id = match field.value {
+                                    FieldValue::U64(id) => Some(id as TaskId),
+                                    _ => None,
+                                };
+                                None
+                            }
+                            Field::KIND => {
+                                kind = strings.string(field.value.to_string());
+                                None
+                            }
+                            Field::SIZE_BYTES => {
+                                size_bytes = match field.value {
+                                    FieldValue::U64(size_bytes) => Some(size_bytes as usize),
+                                    _ => None,
+                                };
+                                // Include size in the other fields
+                                Some(field)
+                            }
+                            Field::ORIGINAL_SIZE_BYTES => {
+                                original_size_bytes = match field.value {
+                                    FieldValue::U64(original_size_bytes) => {
+                                        Some(original_size_bytes as usize)
+                                    }
+                                    _ => None,
+                                };
+                                // Include size in the other fields
+                                Some(field)
+                            }
+                            _ => Some(field),
+                        }
+                    })
+                    // We wish to include the target in the fields as we won't give it a dedicated column.
+                    .chain([target_field])
+                    .collect::<Vec<_>>();
+
+                fields.sort_unstable();
+
+                // Subscribers which report sizes explicitly may also have
+                // estimated them, so prefer those over the fields recorded by
+                // the runtime. Older subscribers only send the fields.
+                let size_bytes = task.size_bytes.map(|size| size as usize).or(size_bytes);
+                let original_size_bytes = task
+                    .original_size_bytes
+                    .map(|size| size as usize)
+                    .or(original_size_bytes);
+
+                // Older versions of Tokio don't record a `kind` field for
+                // tasks on the blocking pool, but the subscriber still knows
+                // they are blocking tasks.
+                if kind.is_empty() && task.kind() == proto::tasks::task::Kind::Blocking {
+                    kind = strings.string(BLOCKING_POOL_KIND.to_owned());
+                }
+
+                let stats = stats_update.remove(&span_id)?.into();
+                let source_location = task.location.as_ref().and_then(|location| {
+                    Some(SourceLocation {
+                        file: location.file.clone()?,
+                        line: location.line?,
+                    })
+                });
+                let location = format_location(task.location);
+                let spawn_backtrace = task.spawn_backtrace;
+                let runtime = task.runtime.map(|runtime| strings.string(runtime));
+                let group = task.group.map(|group| strings.string(group));
+
+                // remap the server's ID to a pretty, sequential task ID,
+                // which stays the same across reconnects if the task has a
+                // Tokio task ID
+                let id = ids.id_for_key(span_id, task_id);
+
+                let short_desc = strings.string(match (task_id, name.as_ref()) {
+                    (Some(task_id), Some(name)) => format!("{task_id} ({name})"),
+                    (Some(task_id), None) => task_id.to_string(),
+                    (None, Some(name)) => name.as_ref().to_owned(),
+                    (None, None) => "".to_owned(),
+                });
+
+                let latency_budget = name
+                    .as_ref()
+                    .and_then(|name| latency_budgets.get(name.as_ref() as &str))
+                    .map(|budget| BudgetTracker::new(*budget));
+
+                let mut task = Task {
+                    name,
+                    id,
+                    task_id,
+                    span_id,
+                    id_str: task_id.map(|id| id.to_string()).unwrap_or_default(),
+                    short_desc,
+                    fields,
+                    stats,
+                    target: meta.target.clone(),
+                    warnings: Vec::new(),
+                    location,
+                    source_location,
+                    spawn_backtrace,
+                    kind,
+                    runtime,
+                    group,
+                    size_bytes,
+                    original_size_bytes,
+                    prev_stats: None,
+                    stats_updated_at: now,
+                    latency_budget,
+                    waker_trend: WakerTrend::default(),
+                    scheduled_p95: None,
+                    rates: RateHistory::default(),
+                    runtime_share: ByWindow::default(),
+                    io_polls: PollCounts::default(),
+                    pinned: pinned.contains(&id),
+                };
+                task.waker_trend.record(task.waker_count());
+                task.record_rates(now);
+                task.record_budget_sample();
+                if let TaskLintResult::RequiresRecheck = task.lint(linters) {
+                    next_pending_lint.insert(task.id);
+                }
+                warning_history.record(&task, seen_at);
+                if let Some(overhead) = task.overhead() {
+                    *overhead_by_location
+                        .entry(task.location.clone())
+                        .or_default() += overhead;
+                }
+                spawned += 1;
+                if task.stats.dropped_at.is_some() {
+                    dropped += 1;
+                }
+                Some((id, task))
+            });
+
+        for (stats, mut task) in self.tasks.updated(stats_update) {
+            tracing::trace!(?task, ?stats, "processing stats update for");
+            let was_dropped = task.stats.dropped_at.is_some();
+            let had_ended = task.has_ended();
+            task.prev_stats = Some(mem::replace(&mut task.stats, stats.into()));
+            task.stats_updated_at = now;
+            task.record_budget_sample();
+            task.record_rates(now);
+            let waker_count = task.waker_count();
+            task.waker_trend.record(waker_count);
+            if let Some(overhead) = task.overhead() {
+                let prev = task.prev_stats.as_ref().and_then(|stats| stats.overhead);
+                *overhead_by_location
+                    .entry(task.location.clone())
+                    .or_default() += overhead.saturating_sub(prev.unwrap_or_default());
+            }
+            if !was_dropped && task.stats.dropped_at.is_some() {
+                dropped += 1;
+            }
+            if !had_ended && task.has_ended() && self.watched.contains(&task.id) {
+                self.ended_watched.push(task.id);
+            }
+            match task.lint(linters) {
+                TaskLintResult::RequiresRecheck => next_pending_lint.insert(task.id),
+                // Avoid linting this task again this cycle
+                _ => self.pending_lint.remove(&task.id),
+            };
+            warning_history.record(&task, seen_at);
+        }
+
+        for id in &self.pending_lint {
+            if let Some(task) = self.tasks.get(*id) {
+                let mut task = task.borrow_mut();
+                if let TaskLintResult::RequiresRecheck = task.lint(linters) {
+                    next_pending_lint.insert(*id);
+                }
+                warning_history.record(&task, seen_at);
+            }
+        }
+        self.pending_lint = next_pending_lint;
+
+        if let Some(now) = now {
+            // Tasks which already existed before the full state was sent
+            // would otherwise show up as a spike of spawns.
+            if !is_full_state {
+                self.spawn_history.record(now, spawned, dropped);
+            }
+            self.update_runtime_shares(now);
+        }
+
+        self.dropped_events += update.dropped_events;
+        (spawned, dropped)
+    }
+
+    /// Sums the busy time of all tasks over each window before `now`, and
+    /// updates each task's share of it.
+    fn update_runtime_shares(&mut self, now: SystemTime) {
+        let busy_totals = ByWindow::from_fn(|window| {
+            self.tasks
+                .values()
+                .map(|task| task.borrow().busy_in(window, now))
+                .sum::<Duration>()
+        });
+        for task in self.tasks.values() {
+            let mut task = task.borrow_mut();
+            task.runtime_share = ByWindow::from_fn(|window| {
+                let total = *busy_totals.get(window);
+                if total.is_zero() {
+                    0.0
+                } else {
+                    task.busy_in(window, now).as_nanos() as f64 * 100.0 / total.as_nanos() as f64
+                }
+            });
+        }
+        self.busy_totals = busy_totals;
+    }
+
+    /// Returns how long all tasks were busy for over the `window` before the
+    /// latest update.
+    pub fn busy_total(&self, window: RateWindow) -> Duration {
+        *self.busy_totals.get(window)
+    }
+
+    /// Removes all tasks, keeping the configured linters, the warning
+    /// history, and which tasks are watched or pinned.
+    pub fn clear(&mut self) {
+        *self = Self {
+            linters: mem::take(&mut self.linters),
+            disabled_linters: mem::take(&mut self.disabled_linters),
+            warning_history: mem::take(&mut self.warning_history),
+            latency_budgets: mem::take(&mut self.latency_budgets),
+            watched: mem::take(&mut self.watched),
+            pinned: mem::take(&mut self.pinned),
+            ..Self::default()
+        };
+    }
+
+    pub fn retain_active(
+        &mut self,
+        paused_time: &PausedTime,
+        now: SystemTime,
+        retain_for: Duration,
+    ) {
+        let (watched, pinned) = (&self.watched, &self.pinned);
+        self.tasks.retain(|_, task| {
+            let task = task.borrow();
+            if pinned.contains(&task.id) || watched.contains(&task.id) {
+                return true;
+            }
+
+            task.stats
+                .dropped_at
+                .map(|d| {
+                    let dropped_for = paused_time.unpaused_since(d, now);
+                    retain_for > dropped_for
+                })
+                .unwrap_or(true)
+        })
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Linter<Task>> {
+        self.linters.iter().filter(|linter| linter.count() > 0)
+    }
+
+    /// Returns the configured linters which are currently disabled.
+    pub fn disabled_linters(&self) -> &[Linter<Task>] {
+        &self.disabled_linters
+    }
+
+    /// Adds linters which are configured, but disabled until they're toggled
+    /// on with [`TasksState::toggle_linter`].
+    pub fn add_disabled_linters(&mut self, linters: impl IntoIterator<Item = Linter<Task>>) {
+        self.disabled_linters.extend(linters);
+    }
+
+    /// Disables the linter with the given summary if it's enabled, or enables
+    /// it if it's disabled, then lints every task again so that the warnings
+    /// reflect the change straight away.
+    ///
+    /// Returns whether the linter is now enabled, or `None` if no linter has
+    /// that summary.
+    pub fn toggle_linter(&mut self, summary: &str) -> Option<bool> {
+        let find = |linters: &[Linter<Task>]| {
+            linters
+                .iter()
+                .position(|linter| linter.summary() == summary)
+        };
+        let enabled = if let Some(idx) = find(&self.linters) {
+            let linter = self.linters.remove(idx);
+            self.disabled_linters.push(linter);
+            false
+        } else {
+            let idx = find(&self.disabled_linters)?;
+            let linter = self.disabled_linters.remove(idx);
+            self.linters.push(linter);
+            true
+        };
+
+        let mut pending_lint = HashSet::new();
+        for (&id, task) in self.tasks.iter() {
+            if let TaskLintResult::RequiresRecheck = task.borrow_mut().lint(&self.linters) {
+                pending_lint.insert(id);
+            }
+        }
+        self.pending_lint = pending_lint;
+        Some(enabled)
+    }
+
+    /// Returns the warnings detected over the whole session, including for
+    /// tasks that have since been evicted.
+    pub fn warning_history(&self) -> &WarningHistory {
+        &self.warning_history
+    }
+
+    /// Returns the estimated instrumentation overhead of all the tasks spawned
+    /// at `location` this session, if the subscriber samples its overhead.
+    pub fn location_overhead(&self, location: &str) -> Option<Duration> {
+        self.overhead_by_location.get(location).copied()
+    }
+
+    /// Returns aggregate statistics for each group that currently has tasks,
+    /// ordered by name.
+    pub fn groups(&self, now: SystemTime) -> Vec<GroupStats> {
+        let mut groups: Vec<GroupStats> = Vec::new();
+        for task in self.tasks.values() {
+            let task = task.borrow();
+            let Some(name) = task.group.as_ref() else {
+                continue;
+            };
+            let idx = match groups.binary_search_by(|group| group.name.cmp(name)) {
+                Ok(idx) => idx,
+                Err(idx) => {
+                    groups.insert(
+                        idx,
+                        GroupStats {
+                            name: name.clone(),
+                            tasks: 0,
+                            running: 0,
+                            idle: 0,
+                            busy: Duration::ZERO,
+                            polls: 0,
+                        },
+                    );
+                    idx
+                }
+            };
+            let group = &mut groups[idx];
+            group.tasks += 1;
+            match task.state() {
+                TaskState::Running => group.running += 1,
+                TaskState::Idle => group.idle += 1,
+                _ => {}
+            }
+            group.busy += task.busy(now);
+            group.polls += task.total_polls();
+        }
+        groups
+    }
+
+    pub fn tasks(&self) -> impl Iterator<Item = &store::Stored<Task>> {
+        self.tasks.values()
+    }
+
+    pub fn task(&self, id: Id<Task>) -> Option<TaskRef> {
+        self.tasks.get(id).map(Rc::downgrade)
+    }
+
+    pub fn dropped_events(&self) -> u64 {
+        self.dropped_events
+    }
+
+    pub fn spawn_history(&self) -> &SpawnHistory {
+        &self.spawn_history
+    }
+}
+
+impl SpawnHistory {
+    /// The length of time covered by each bucket.
+    pub const BUCKET_WIDTH: Duration = Duration::from_secs(5);
+
+    /// The maximum number of buckets to keep (five minutes of history).
+    const MAX_BUCKETS: usize = 60;
+
+    fn record(&mut self, now: SystemTime, spawned: u64, dropped: u64) {
+        let index = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+            / Self::BUCKET_WIDTH.as_secs();
+
+        match self.buckets.back_mut() {
+            Some(last) if last.index >= index => {
+                // Updates may arrive slightly out of order across a bucket
+                // boundary; count them towards the latest bucket.
+                last.spawned += spawned;
+                last.dropped += dropped;
+                return;
+            }
+            Some(last) => {
+                // Fill in any intervals in which no updates were received, so
+                // that every bucket covers the same length of time.
+                let gap = (index - last.index - 1).min(Self::MAX_BUCKETS as u64);
+                let first_missing = index - gap;
+                self.buckets
+                    .extend((first_missing..index).map(|index| SpawnBucket {
+                        index,
+                        spawned: 0,
+                        dropped: 0,
+                    }));
+            }
+            None => {}
+        }
+
+        self.buckets.push_back(SpawnBucket {
+            index,
+            spawned,
+            dropped,
+        });
+        while self.buckets.len() > Self::MAX_BUCKETS {
+            self.buckets.pop_front();
+        }
+    }
+
+    /// Returns the number of tasks spawned in each bucket, oldest first.
+    pub fn spawned(&self) -> impl Iterator<Item = u64> + '_ {
+        self.buckets.iter().map(|bucket| bucket.spawned)
+    }
+
+    /// Returns the number of tasks dropped in each bucket, oldest first.
+    pub fn dropped(&self) -> impl Iterator<Item = u64> + '_ {
+        self.buckets.iter().map(|bucket| bucket.dropped)
+    }
+}
+
+impl Details {
+    pub fn span_id(&self) -> SpanId {
+        self.span_id
+    }
+
+    pub fn poll_times_histogram(&self) -> Option<&DurationHistogram> {
+        self.poll_times_histogram.as_ref()
+    }
+
+    pub fn recent_poll_times_histogram(&self) -> Option<&DurationHistogram> {
+        self.recent_poll_times_histogram.as_ref()
+    }
+
+    pub fn scheduled_times_histogram(&self) -> Option<&DurationHistogram> {
+        self.scheduled_times_histogram.as_ref()
+    }
+
+    pub fn wake_sites(&self) -> &[(String, u64)] {
+        &self.wake_sites
+    }
+
+    pub fn wake_history(&self) -> &[(u64, u64)] {
+        &self.wake_history
+    }
+
+    pub fn thread_polls(&self) -> &[(String, u64)] {
+        &self.thread_polls
+    }
+
+    pub fn waiting_on(&self) -> &[WaitingOn] {
+        &self.waiting_on
+    }
+}
+
+impl Task {
+    pub fn id(&self) -> Id<Task> {
+        self.id
+    }
+
+    pub fn span_id(&self) -> SpanId {
+        self.span_id
+    }
+
+    pub fn id_str(&self) -> &str {
+        &self.id_str
+    }
+
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn group(&self) -> Option<&InternedStr> {
+        self.group.as_ref()
+    }
+
+    pub fn runtime(&self) -> Option<&str> {
+        self.runtime.as_ref().map(AsRef::as_ref)
+    }
+
+    pub fn short_desc(&self) -> &str {
+        &self.short_desc
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(AsRef::as_ref)
+    }
+
+    /// Returns the task's fields which aren't shown in their own columns,
+    /// sorted by name.
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Returns `true` if this task is currently being polled.
+    pub fn is_running(&self) -> bool {
+        self.stats.last_poll_started > self.stats.last_poll_ended
+    }
+
+    pub fn is_blocking(&self) -> bool {
+        matches!(self.kind.as_ref(), "block_on" | BLOCKING_POOL_KIND)
+    }
+
+    /// Returns `true` if this task runs on the runtime's blocking pool, such
+    /// as a task spawned with `spawn_blocking`.
+    pub fn is_blocking_pool(&self) -> bool {
+        &*self.kind == BLOCKING_POOL_KIND
+    }
+
+    /// Returns how long a blocking pool task waited for a thread before it was
+    /// first polled, or `None` if this isn't a blocking pool task.
+    pub fn queued(&self, since: SystemTime) -> Option<Duration> {
+        if !self.is_blocking_pool() {
+            return None;
+        }
+        let started = self
+            .stats
+            .first_poll
+            .or(self.stats.dropped_at)
+            .unwrap_or(since);
+        Some(
+            started
+                .duration_since(self.stats.created_at)
+                .unwrap_or_default(),
+        )
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.stats.total.is_some()
+    }
+
+    /// Returns `true` if the task has completed, or was dropped without
+    /// completing.
+    pub fn has_ended(&self) -> bool {
+        self.is_completed() || self.stats.dropped_at.is_some()
+    }
+
+    pub fn state(&self) -> TaskState {
+        self.stats.state()
+    }
+
+    /// Returns `true` if the update received at `update_time` significantly
+    /// changed this task: it was polled, or its state changed.
+    pub fn changed_in_update(&self, update_time: SystemTime) -> bool {
+        if self.stats_updated_at != Some(update_time) {
+            return false;
+        }
+        self.prev_stats.as_ref().is_some_and(|prev| {
+            prev.polls != self.stats.polls || prev.state() != self.stats.state()
+        })
+    }
+
+    pub fn total(&self, since: SystemTime) -> Duration {
+        self.stats
+            .total
+            .or_else(|| since.duration_since(self.stats.created_at).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn busy(&self, since: SystemTime) -> Duration {
+        if let Some(started) = self.stats.last_poll_started {
+            if self.stats.last_poll_started > self.stats.last_poll_ended {
+                // in this case the task is being polled at the moment
+                let current_time_in_poll = since.duration_since(started).unwrap_or_default();
+                return self.stats.busy + current_time_in_poll;
+            }
+        }
+        self.stats.busy
+    }
+
+    /// Returns the time spent in completed polls that returned `Ready` and
+    /// `Pending`, respectively.
+    pub fn busy_by_outcome(&self) -> Option<(Duration, Duration)> {
+        self.stats.busy_by_outcome
+    }
+
+    pub fn overhead(&self) -> Option<Duration> {
+        self.stats.overhead
+    }
+
+    pub fn scheduled(&self, since: SystemTime) -> Duration {
+        if let Some(wake) = self.stats.last_wake {
+            if self.stats.last_wake > self.stats.last_poll_started {
+                // In this case the task is scheduled, but has not yet been polled
+                let current_time_since_wake = since.duration_since(wake).unwrap_or_default();
+                return self.stats.scheduled + current_time_since_wake;
+            }
+        }
+        self.stats.scheduled
+    }
+
+    /// Returns the task's scheduled time as the given metric, or `None` if
+    /// it isn't known yet.
+    pub fn scheduled_by(&self, metric: ScheduledMetric, now: SystemTime) -> Option<Duration> {
+        match metric {
+            ScheduledMetric::Total => Some(self.scheduled(now)),
+            // Every poll, including the first, follows a wait to be scheduled.
+            ScheduledMetric::Mean => u32::try_from(self.stats.polls)
+                .ok()
+                .filter(|&polls| polls > 0)
+                .map(|polls| self.stats.scheduled / polls),
+            ScheduledMetric::P95 => self.scheduled_p95,
+        }
+    }
+
+    /// Records the task's scheduled times histogram from its details.
+    pub fn set_scheduled_histogram(&mut self, histogram: &DurationHistogram) {
+        self.scheduled_p95 = Some(histogram.percentile(95.0));
+    }
+
+    pub fn idle(&self, since: SystemTime) -> Duration {
+        self.stats
+            .idle
+            .or_else(|| {
+                self.total(since)
+                    .checked_sub(self.busy(since) + self.scheduled(since))
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the total number of times the task has been polled.
+    pub fn total_polls(&self) -> u64 {
+        self.stats.polls
+    }
+
+    /// Returns whether the user pinned the task.
+    pub fn is_pinned(&self) -> bool {
+        self.pinned
+    }
+
+    /// Returns how many times the task polled I/O resources, and how many of
+    /// those polls found them ready.
+    ///
+    /// Comparing these with the task's polls shows whether it is mostly
+    /// waiting on I/O or on other tasks and timers.
+    pub fn io_polls(&self) -> PollCounts {
+        self.io_polls
+    }
+
+    /// Returns the elapsed time since the task was last woken, relative to
+    /// given `now` timestamp.
+    ///
+    /// Returns `None` if the task has never been woken, or if it was last woken
+    /// more recently than `now` (which *shouldn't* happen as long as `now` is the
+    /// timestamp of the last stats update...)
+    pub fn since_wake(&self, now: SystemTime) -> Option<Duration> {
+        now.duration_since(self.last_wake()?).ok()
+    }
+
+    /// Returns when the task last started or finished being polled, or `None`
+    /// if it has never been polled.
+    pub fn last_polled(&self) -> Option<SystemTime> {
+        self.stats.last_poll_started.max(self.stats.last_poll_ended)
+    }
+
+    /// Returns how long it has been since the task was last polled, or since
+    /// it was spawned if it was never polled.
+    pub fn idle_for(&self, now: SystemTime) -> Option<Duration> {
+        let since = self.stats.last_poll_ended.unwrap_or(self.stats.created_at);
+        now.duration_since(since).ok()
+    }
+
+    /// Returns the span IDs of the resources the task is currently waiting
+    /// on.
+    pub fn waiting_on(&self) -> &[SpanId] {
+        &self.stats.waiting_on
+    }
+
+    pub fn last_wake(&self) -> Option<SystemTime> {
+        self.stats.last_wake
+    }
+
+    /// Returns the current number of wakers for this task.
+    pub fn waker_count(&self) -> u64 {
+        self.waker_clones().saturating_sub(self.waker_drops())
+    }
+
+    /// Returns the total number of times this task's waker has been cloned.
+    pub fn waker_clones(&self) -> u64 {
+        self.stats.waker_clones
+    }
+
+    /// Returns the total number of times this task's waker has been dropped.
+    pub fn waker_drops(&self) -> u64 {
+        self.stats.waker_drops
+    }
+
+    /// Returns the total number of times this task has been woken.
+    pub fn wakes(&self) -> u64 {
+        self.stats.wakes
+    }
+
+    /// Returns the total number of times this task has woken itself.
+    pub fn self_wakes(&self) -> u64 {
+        self.stats.self_wakes
+    }
+
+    /// Returns the name of the innermost span of application code the task
+    /// is in, if the target reports it.
+    pub fn current_span(&self) -> Option<&str> {
+        self.stats.current_span.as_deref()
+    }
+
+    /// Returns how the task's live waker count has changed across updates.
+    pub fn waker_trend(&self) -> &WakerTrend {
+        &self.waker_trend
+    }
+
+    /// Returns the percentage of this task's total wakeups that were self-wakes.
+    pub fn self_wake_percent(&self) -> u64 {
+        self.self_wakes().percent_of(self.wakes())
+    }
+
+    /// Returns whether this task has signaled via its waker to run again.
+    ///
+    /// Once the task has been polled, this is changed back to false.
+    pub fn is_awakened(&self) -> bool {
+        // Before the first poll, the task is waiting on the executor to run it
+        // for the first time.
+        self.total_polls() == 0 || self.last_wake() > self.stats.last_poll_started
+    }
+
+    pub fn warnings(&self) -> &[Linter<Task>] {
+        &self.warnings[..]
+    }
+
+    /// Returns the tracker for this task's latency budget, if it has one.
+    pub fn latency_budget(&self) -> Option<&BudgetTracker> {
+        self.latency_budget.as_ref()
+    }
+
+    pub fn latency_budget_mut(&mut self) -> Option<&mut BudgetTracker> {
+        self.latency_budget.as_mut()
+    }
+
+    /// Returns whether this task is within its latency budget, if it has one
+    /// and it has been polled, along with whether that's [estimated].
+    ///
+    /// [estimated]: BudgetTracker::is_estimated
+    pub fn budget_status(&self) -> Option<(BudgetStatus, bool)> {
+        let tracker = self.latency_budget.as_ref()?;
+        Some((tracker.status()?, tracker.is_estimated()))
+    }
+
+    /// Records the polls since the previous stats update in the task's latency
+    /// budget tracker, if it has one.
+    /// Returns how many times per second the task was polled, on average,
+    /// over the `window` before `now`.
+    pub fn poll_rate(&self, window: RateWindow, now: SystemTime) -> f64 {
+        self.rates
+            .per_sec(window, now, self.stats.created_at, |delta| delta.polls)
+    }
+
+    /// Returns how many times per second the task was woken, on average,
+    /// over the `window` before `now`.
+    pub fn wake_rate(&self, window: RateWindow, now: SystemTime) -> f64 {
+        self.rates
+            .per_sec(window, now, self.stats.created_at, |delta| delta.wakes)
+    }
+
+    /// Returns how long the task was busy for over the `window` before
+    /// `now`, as of the updates received by then.
+    pub fn busy_in(&self, window: RateWindow, now: SystemTime) -> Duration {
+        self.rates.recent(window, now).map(|delta| delta.busy).sum()
+    }
+
+    /// Returns the percentage of its lifetime that the task spent being
+    /// polled.
+    pub fn busy_percent(&self, now: SystemTime) -> f64 {
+        let total = self.total(now);
+        if total.is_zero() {
+            return 0.0;
+        }
+        (self.busy(now).as_secs_f64() / total.as_secs_f64() * 100.0).min(100.0)
+    }
+
+    /// Returns the percentage of all tasks' busy time over the `window`
+    /// before the latest update which this task was busy for.
+    pub fn runtime_share(&self, window: RateWindow) -> f64 {
+        *self.runtime_share.get(window)
+    }
+
+    /// Records how much the task's poll and wake counts and busy time changed
+    /// in the update at `now`.
+    fn record_rates(&mut self, now: Option<SystemTime>) {
+        let Some(now) = now else {
+            return;
+        };
+        let (prev_polls, prev_wakes, prev_busy) = self
+            .prev_stats
+            .as_ref()
+            .map(|prev| (prev.polls, prev.wakes, prev.busy))
+            .unwrap_or_default();
+        self.rates.record(RateDelta {
+            at: now,
+            polls: self.stats.polls.saturating_sub(prev_polls),
+            wakes: self.stats.wakes.saturating_sub(prev_wakes),
+            busy: self.stats.busy.saturating_sub(prev_busy),
+        });
+    }
+
+    fn record_budget_sample(&mut self) {
+        let Some(tracker) = self.latency_budget.as_mut() else {
+            return;
+        };
+        let (prev_polls, prev_busy, prev_scheduled) = self
+            .prev_stats
+            .as_ref()
+            .map(|prev| (prev.polls, prev.busy, prev.scheduled))
+            .unwrap_or_default();
+        let polls = self.stats.polls.saturating_sub(prev_polls);
+        let total = match tracker.budget().metric() {
+            BudgetMetric::Poll => self.stats.busy.saturating_sub(prev_busy),
+            BudgetMetric::Scheduled => self.stats.scheduled.saturating_sub(prev_scheduled),
+        };
+        tracker.record(polls, total);
+    }
+
+    fn lint(&mut self, linters: &[Linter<Task>]) -> TaskLintResult {
+        self.warnings.clear();
+        let mut recheck = false;
+        for lint in linters {
+            tracing::debug!(?lint, task = ?self, "checking...");
+            match lint.check(self) {
+                Lint::Warning(warning) => {
+                    tracing::info!(?warning, task = ?self, "found a warning!");
+                    self.warnings.push(warning);
+                }
+                Lint::Ok => {}
+                Lint::Recheck => recheck = true,
+            }
+        }
+        if recheck {
+            TaskLintResult::RequiresRecheck
+        } else {
+            TaskLintResult::Linted
+        }
+    }
+
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+
+    pub fn source_location(&self) -> Option<&SourceLocation> {
+        self.source_location.as_ref()
+    }
+
+    pub fn spawn_backtrace(&self) -> &[String] {
+        &self.spawn_backtrace
+    }
+
+    pub fn created_at(&self) -> SystemTime {
+        self.stats.created_at
+    }
+
+    pub fn size_bytes(&self) -> Option<usize> {
+        self.size_bytes
+    }
+
+    pub fn original_size_bytes(&self) -> Option<usize> {
+        self.original_size_bytes
+    }
+}
+
+// === impl WakerTrend ===
+
+impl WakerTrend {
+    /// Records the live waker count from a stats update.
+    fn record(&mut self, live: u64) {
+        if live > self.live {
+            if self.growing_for == 0 {
+                self.grown_from = self.live;
+            }
+            self.growing_for += 1;
+        } else {
+            self.growing_for = 0;
+        }
+        self.live = live;
+    }
+
+    /// The number of consecutive updates in which the live waker count grew.
+    pub fn growing_for(&self) -> u32 {
+        self.growing_for
+    }
+
+    /// How much the live waker count has grown over those updates.
+    pub fn growth(&self) -> u64 {
+        if self.growing_for == 0 {
+            return 0;
+        }
+        self.live.saturating_sub(self.grown_from)
+    }
+
+    pub fn live(&self) -> u64 {
+        self.live
+    }
+}
+
+// === impl RateHistory ===
+
+impl RateHistory {
+    fn record(&mut self, delta: RateDelta) {
+        let oldest = delta.at.checked_sub(RateWindow::LONGEST);
+        self.deltas.push_back(delta);
+        while self
+            .deltas
+            .front()
+            .zip(oldest)
+            .is_some_and(|(delta, oldest)| delta.at <= oldest)
+        {
+            self.deltas.pop_front();
+        }
+    }
+
+    /// Returns the per-second rate of the count selected by `count` over the
+    /// `window` before `now`, for a task created at `created_at`.
+    fn per_sec(
+        &self,
+        window: RateWindow,
+        now: SystemTime,
+        created_at: SystemTime,
+        count: impl Fn(&RateDelta) -> u64,
+    ) -> f64 {
+        let total: u64 = self.recent(window, now).map(count).sum();
+        let window = window.duration();
+        // Tasks younger than the window had less time to be polled or woken.
+        let elapsed = now
+            .duration_since(created_at)
+            .unwrap_or_default()
+            .min(window);
+        if elapsed.is_zero() {
+            return 0.0;
+        }
+        total as f64 / elapsed.as_secs_f64()
+    }
+
+    /// Returns the updates in the `window` before `now`, newest first.
+    fn recent(&self, window: RateWindow, now: SystemTime) -> impl Iterator<Item = &RateDelta> {
+        let start = now.checked_sub(window.duration());
+        self.deltas
+            .iter()
+            .rev()
+            .take_while(move |delta| start.map_or(true, |start| delta.at > start))
+    }
+}
+
+// === impl ByWindow ===
+
+impl<T> ByWindow<T> {
+    fn from_fn(mut f: impl FnMut(RateWindow) -> T) -> Self {
+        Self {
+            one_second: f(RateWindow::OneSecond),
+            ten_seconds: f(RateWindow::TenSeconds),
+            one_minute: f(RateWindow::OneMinute),
+        }
+    }
+
+    pub fn get(&self, window: RateWindow) -> &T {
+        match window {
+            RateWindow::OneSecond => &self.one_second,
+            RateWindow::TenSeconds => &self.ten_seconds,
+            RateWindow::OneMinute => &self.one_minute,
+        }
+    }
+}
+
+// === impl RateWindow ===
+
+impl RateWindow {
+    /// The longest window, which bounds how far back rates are kept.
+    const LONGEST: Duration = Duration::from_secs(60);
+
+    /// Returns the next window to switch to.
+    pub fn next(self) -> Self {
+        match self {
+            Self::OneSecond => Self::TenSeconds,
+            Self::TenSeconds => Self::OneMinute,
+            Self::OneMinute => Self::OneSecond,
+        }
+    }
+
+    pub fn duration(self) -> Duration {
+        match self {
+            Self::OneSecond => Duration::from_secs(1),
+            Self::TenSeconds => Duration::from_secs(10),
+            Self::OneMinute => Self::LONGEST,
+        }
+    }
+
+    /// Returns the headers of the poll and wake rate columns when rates are
+    /// computed over this window.
+    pub fn headers(self) -> (&'static str, &'static str) {
+        match self {
+            Self::OneSecond => ("Polls/s 1s", "Wakes/s 1s"),
+            Self::TenSeconds => ("Polls/s 10s", "Wakes/s 10s"),
+            Self::OneMinute => ("Polls/s 1m", "Wakes/s 1m"),
+        }
+    }
+
+    /// Returns the header of the runtime share column when shares are
+    /// computed over this window.
+    pub fn share_header(self) -> &'static str {
+        match self {
+            Self::OneSecond => "Share 1s",
+            Self::TenSeconds => "Share 10s",
+            Self::OneMinute => "Share 1m",
+        }
+    }
+}
+
+enum TaskLintResult {
+    Linted,
+    RequiresRecheck,
+}
+
+impl From<proto::tasks::Stats> for TaskStats {
+    fn from(pb: proto::tasks::Stats) -> Self {
+        let created_at = pb
+            .created_at
+            .expect("task span was never created")
+            .try_into()
+            .unwrap();
+
+        let dropped_at: Option<SystemTime> = pb.dropped_at.map(|v| v.try_into().unwrap());
+        let total = dropped_at.map(|d| d.duration_since(created_at).unwrap_or_default());
+
+        let poll_stats = pb.poll_stats.expect("task should have poll stats");
+        let busy = poll_stats.busy_time.map(pb_duration).unwrap_or_default();
+        let busy_by_outcome = poll_stats
+            .ready_time
+            .zip(poll_stats.pending_time)
+            .map(|(ready, pending)| (pb_duration(ready), pb_duration(pending)));
+        let scheduled = pb.scheduled_time.map(pb_duration).unwrap_or_default();
+        let idle = total.map(|total| total.checked_sub(busy + scheduled).unwrap_or_default());
+        Self {
+            total,
+            idle,
+            scheduled,
+            busy,
+            busy_by_outcome,
+            last_wake: pb.last_wake.map(|v| v.try_into().unwrap()),
+            first_poll: poll_stats.first_poll.map(|v| v.try_into().unwrap()),
+            last_poll_started: poll_stats.last_poll_started.map(|v| v.try_into().unwrap()),
+            last_poll_ended: poll_stats.last_poll_ended.map(|v| v.try_into().unwrap()),
+            polls: poll_stats.polls,
+            created_at,
+            dropped_at,
+            wakes: pb.wakes,
+            waker_clones: pb.waker_clones,
+            waker_drops: pb.waker_drops,
+            self_wakes: pb.self_wakes,
+            overhead: pb.instrumentation_overhead.map(pb_duration),
+            cancelled: pb.cancelled,
+            panicked: pb.terminated_by() == proto::tasks::stats::TerminatedBy::Panicked,
+            current_span: pb.current_span,
+            waiting_on: pb
+                .waiting_on
+                .into_iter()
+                .filter_map(|waiting| Some(waiting.resource_id?.id))
+                .collect(),
+        }
+    }
+}
+
+impl SortBy {
+    /// Sorts `tasks`, keeping tasks which compare equal in the same order.
+    ///
+    /// The tasks are still in the order they were sorted into for the
+    /// previous frame, and a stable sort takes close to linear time for
+    /// input which is already nearly sorted, so re-sorting every frame stays
+    /// cheap even with a very large number of tasks.
+    ///
+    /// Scheduled times are compared as the given `sched` metric, and rates
+    /// over the given `window`.
+    pub fn sort(
+        &self,
+        now: SystemTime,
+        sched: ScheduledMetric,
+        window: RateWindow,
+        tasks: &mut [Weak<RefCell<Task>>],
+    ) {
+        // Rates are compared in thousandths, as floats aren't `Ord`.
+        let millis = |rate: f64| (rate * 1000.0) as u64;
+        match self {
+            Self::Pinned => tasks.sort_by_key(|task| task.upgrade().map(|t| t.borrow().pinned)),
+            Self::Tid => tasks.sort_by_key(|task| task.upgrade().map(|t| t.borrow().task_id)),
+            Self::Name => tasks.sort_by_key(|task| task.upgrade().map(|t| t.borrow().name.clone())),
+            Self::State => tasks.sort_by_key(|task| task.upgrade().map(|t| t.borrow().state())),
+            Self::Warns => {
+                tasks.sort_by_key(|task| task.upgrade().map(|t| t.borrow().warnings().len()))
+            }
+            Self::Total => tasks.sort_by_key(|task| task.upgrade().map(|t| t.borrow().total(now))),
+            Self::Idle => tasks.sort_by_key(|task| task.upgrade().map(|t| t.borrow().idle(now))),
+            Self::Scheduled => tasks
+                .sort_by_key(|task| task.upgrade().map(|t| t.borrow().scheduled_by(sched, now))),
+            Self::Busy => tasks.sort_by_key(|task| task.upgrade().map(|t| t.borrow().busy(now))),
+            Self::Polls => tasks.sort_by_key(|task| task.upgrade().map(|t| t.borrow().stats.polls)),
+            Self::Kind => tasks.sort_by_key(|task| task.upgrade().map(|t| t.borrow().kind.clone())),
+            Self::Group => {
+                tasks.sort_by_key(|task| task.upgrade().map(|t| t.borrow().group.clone()))
+            }
+            Self::Runtime => {
+                tasks.sort_by_key(|task| task.upgrade().map(|t| t.borrow().runtime.clone()))
+            }
+            Self::Location => {
+                tasks.sort_by_key(|task| task.upgrade().map(|t| t.borrow().location.clone()))
+            }
+            Self::LastPoll => {
+                tasks.sort_by_key(|task| task.upgrade().map(|t| t.borrow().last_polled()))
+            }
+            Self::LastWake => {
+                tasks.sort_by_key(|task| task.upgrade().map(|t| t.borrow().last_wake()))
+            }
+            Self::PollRate => tasks.sort_by_key(|task| {
+                task.upgrade()
+                    .map(|t| millis(t.borrow().poll_rate(window, now)))
+            }),
+            Self::WakeRate => tasks.sort_by_key(|task| {
+                task.upgrade()
+                    .map(|t| millis(t.borrow().wake_rate(window, now)))
+            }),
+            Self::IoPolls => {
+                tasks.sort_by_key(|task| task.upgrade().map(|t| t.borrow().io_polls.polls()))
+            }
+            Self::IoReady => {
+                tasks.sort_by_key(|task| task.upgrade().map(|t| t.borrow().io_polls.ready()))
+            }
+            Self::BusyPercent => tasks
+                .sort_by_key(|task| task.upgrade().map(|t| millis(t.borrow().busy_percent(now)))),
+            Self::RuntimeShare => tasks.sort_by_key(|task| {
+                task.upgrade()
+                    .map(|t| millis(t.borrow().runtime_share(window)))
+            }),
+        }
+    }
+}
+
+impl TryFrom<usize> for SortBy {
+    type Error = ();
+    fn try_from(idx: usize) -> Result<Self, Self::Error> {
+        match idx {
+            idx if idx == Self::Pinned as usize => Ok(Self::Pinned),
+            idx if idx == Self::Tid as usize => Ok(Self::Tid),
+            idx if idx == Self::State as usize => Ok(Self::State),
+            idx if idx == Self::Warns as usize => Ok(Self::Warns),
+            idx if idx == Self::Name as usize => Ok(Self::Name),
+            idx if idx == Self::Total as usize => Ok(Self::Total),
+            idx if idx == Self::Busy as usize => Ok(Self::Busy),
+            idx if idx == Self::Scheduled as usize => Ok(Self::Scheduled),
+            idx if idx == Self::Idle as usize => Ok(Self::Idle),
+            idx if idx == Self::Polls as usize => Ok(Self::Polls),
+            idx if idx == Self::Kind as usize => Ok(Self::Kind),
+            idx if idx == Self::Group as usize => Ok(Self::Group),
+            idx if idx == Self::Runtime as usize => Ok(Self::Runtime),
+            idx if idx == Self::Location as usize => Ok(Self::Location),
+            idx if idx == Self::LastPoll as usize => Ok(Self::LastPoll),
+            idx if idx == Self::LastWake as usize => Ok(Self::LastWake),
+            idx if idx == Self::PollRate as usize => Ok(Self::PollRate),
+            idx if idx == Self::WakeRate as usize => Ok(Self::WakeRate),
+            idx if idx == Self::IoPolls as usize => Ok(Self::IoPolls),
+            idx if idx == Self::IoReady as usize => Ok(Self::IoReady),
+            idx if idx == Self::BusyPercent as usize => Ok(Self::BusyPercent),
+            idx if idx == Self::RuntimeShare as usize => Ok(Self::RuntimeShare),
+            _ => Err(()),
+        }
+    }
+}
+
+impl ScheduledMetric {
+    /// Returns the next metric to switch to.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Total => Self::Mean,
+            Self::Mean => Self::P95,
+            Self::P95 => Self::Total,
+        }
+    }
+
+    /// Returns the header of the scheduled time column when showing this
+    /// metric.
+    pub fn header(self) -> &'static str {
+        match self {
+            Self::Total => "Sched",
+            Self::Mean => "Sched Avg",
+            Self::P95 => "Sched p95",
+        }
+    }
+}
+
+impl TaskState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Scheduled => "scheduled",
+            Self::Idle => "idle",
+            Self::Completed => "completed",
+            Self::Cancelled => "cancelled",
+            Self::Panicked => "panicked",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waker_trend_tracks_growth() {
+        let mut trend = WakerTrend::default();
+        for live in [1, 0, 1] {
+            trend.record(live);
+        }
+        assert_eq!(trend.growing_for(), 1);
+        assert_eq!(trend.growth(), 1);
+
+        for live in [5, 20, 40] {
+            trend.record(live);
+        }
+        assert_eq!(trend.growing_for(), 4);
+        assert_eq!(trend.growth(), 40);
+        assert_eq!(trend.live(), 40);
+
+        // Any update where the count doesn't grow ends the streak.
+        trend.record(40);
+        assert_eq!(trend.growing_for(), 0);
+        assert_eq!(trend.growth(), 0);
+
+        trend.record(41);
+        assert_eq!(trend.growing_for(), 1);
+        assert_eq!(trend.growth(), 1);
+    }
+
+    #[test]
+    fn rates_over_windows() {
+        let created_at = UNIX_EPOCH + Duration::from_secs(1_000);
+        let mut rates = RateHistory::default();
+        // A task polled 10 times a second for two minutes, woken half as
+        // often.
+        for secs in 1..=120 {
+            rates.record(RateDelta {
+                at: created_at + Duration::from_secs(secs),
+                polls: 10,
+                wakes: 5,
+                busy: Duration::ZERO,
+            });
+        }
+        let now = created_at + Duration::from_secs(120);
+        let polls = |window| rates.per_sec(window, now, created_at, |delta| delta.polls);
+        assert_eq!(polls(RateWindow::OneSecond), 10.0);
+        assert_eq!(polls(RateWindow::TenSeconds), 10.0);
+        assert_eq!(polls(RateWindow::OneMinute), 10.0);
+        assert_eq!(
+            rates.per_sec(RateWindow::OneMinute, now, created_at, |delta| delta.wakes),
+            5.0
+        );
+        // Only the longest window's worth of updates is kept.
+        assert_eq!(rates.deltas.len(), 60);
+
+        // Once the task stops being polled, the shorter windows notice first.
+        let later = now + Duration::from_secs(5);
+        let polls = |window| rates.per_sec(window, later, created_at, |delta| delta.polls);
+        assert_eq!(polls(RateWindow::OneSecond), 0.0);
+        assert_eq!(polls(RateWindow::TenSeconds), 5.0);
+
+        // A task younger than the window is rated over its lifetime.
+        let mut young = RateHistory::default();
+        young.record(RateDelta {
+            at: created_at + Duration::from_secs(2),
+            polls: 20,
+            wakes: 0,
+            busy: Duration::ZERO,
+        });
+        let rate = young.per_sec(
+            RateWindow::OneMinute,
+            created_at + Duration::from_secs(2),
+            created_at,
+            |delta| delta.polls,
+        );
+        assert_eq!(rate, 10.0);
+    }
+}
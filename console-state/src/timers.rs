@@ -0,0 +1,268 @@
+//! Finds the timers which are due to fire, such as Tokio's `Sleep`, which
+//! `Interval` and `timeout` also use.
+//!
+//! Tokio sets a `Sleep`'s `duration` attribute to how long it's set for
+//! whenever it's created or reset. It's due to fire that long after the
+//! attribute was set, which is when the attribute was last recorded, if the
+//! subscriber keeps attribute history, or otherwise no earlier than when the
+//! timer's latest async op was created, as each reset starts a new one.
+use super::{
+    async_ops::AsyncOpsState,
+    resources::{Resource, ResourcesState},
+    store::Id,
+    tasks::{Task, TasksState},
+    FieldValue,
+};
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime},
+};
+
+/// The attribute which timers record how long they're set for in.
+const DURATION: &str = "duration";
+
+/// How long a timer resource is set for, and when it was set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timer {
+    pub duration: Duration,
+    pub set_at: SystemTime,
+}
+
+/// A timer which hasn't fired yet.
+#[derive(Debug)]
+pub struct UpcomingWake {
+    pub deadline: SystemTime,
+    /// How long until the deadline.
+    pub due_in: Duration,
+    /// The timer's type and ID.
+    pub timer: String,
+    /// Where the timer was created.
+    pub location: String,
+    /// The ID and name of the task which last polled the timer, if it's been
+    /// polled.
+    pub task: Option<String>,
+}
+
+impl Timer {
+    /// Interprets `resource`'s attributes, if it's a timer which records how
+    /// long it's set for.
+    ///
+    /// `last_reset` is when the timer's latest async op was created, if it
+    /// has any.
+    pub fn from_resource(resource: &Resource, last_reset: Option<SystemTime>) -> Option<Self> {
+        if !resource.is_timer() {
+            return None;
+        }
+        let attribute = resource
+            .attributes()
+            .iter()
+            .find(|attr| &*attr.field.name == DURATION)?;
+        let FieldValue::U64(value) = attribute.field.value else {
+            return None;
+        };
+        let duration = match attribute.unit.as_deref() {
+            // Tokio records durations in milliseconds.
+            Some("ms") | None => Duration::from_millis(value),
+            Some("s") => Duration::from_secs(value),
+            Some("us") => Duration::from_micros(value),
+            Some("ns") => Duration::from_nanos(value),
+            Some(_) => return None,
+        };
+        let recorded_at = resource
+            .attribute_history()
+            .get(DURATION)
+            .and_then(|history| history.back())
+            .map(|sample| sample.at);
+        let set_at = [Some(resource.created_at()), last_reset, recorded_at]
+            .into_iter()
+            .flatten()
+            .max()?;
+        Some(Self { duration, set_at })
+    }
+
+    pub fn deadline(&self) -> SystemTime {
+        self.set_at + self.duration
+    }
+}
+
+/// Returns the timers which haven't been dropped and are due to fire after
+/// `now`, soonest first.
+pub fn upcoming(
+    tasks: &TasksState,
+    resources: &ResourcesState,
+    async_ops: &AsyncOpsState,
+    now: SystemTime,
+) -> Vec<UpcomingWake> {
+    // The latest async op of each resource, which tells when it was last
+    // reset and which task is waiting on it.
+    let mut latest_ops: HashMap<Id<Resource>, (SystemTime, Option<Id<Task>>)> = HashMap::new();
+    for op in async_ops.async_ops() {
+        let Some(op) = op.upgrade() else {
+            continue;
+        };
+        let op = op.borrow();
+        let latest = latest_ops
+            .entry(op.resource_id())
+            .or_insert((op.created_at(), op.task_id()));
+        if op.created_at() >= latest.0 {
+            *latest = (op.created_at(), op.task_id().or(latest.1));
+        }
+    }
+
+    let mut wakes = resources
+        .resources()
+        .filter_map(|resource| {
+            let resource = resource.borrow();
+            if resource.dropped() {
+                return None;
+            }
+            let latest_op = latest_ops.get(&resource.id());
+            let timer = Timer::from_resource(&resource, latest_op.map(|&(at, _)| at))?;
+            let deadline = timer.deadline();
+            let due_in = deadline.duration_since(now).ok()?;
+            let task = latest_op
+                .and_then(|&(_, task)| tasks.task(task?)?.upgrade())
+                .map(|task| format!("task {}", task.borrow().short_desc()));
+            Some(UpcomingWake {
+                deadline,
+                due_in,
+                timer: format!(
+                    "{} (resource {})",
+                    resource.concrete_type(),
+                    resource.id_str()
+                ),
+                location: resource.location().to_owned(),
+                task,
+            })
+        })
+        .collect::<Vec<_>>();
+    wakes.sort_by_key(|wake| wake.deadline);
+    wakes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::State;
+    use console_api as proto;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn lists_upcoming_wakes_soonest_first() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let meta = |id, name: &str| proto::register_metadata::NewMetadata {
+            id: Some(proto::MetaId { id }),
+            metadata: Some(proto::Metadata {
+                name: name.to_string(),
+                ..Default::default()
+            }),
+        };
+        let sleep = |id, kind| proto::resources::Resource {
+            id: Some(proto::Id { id }),
+            metadata: Some(proto::MetaId { id: 2 }),
+            kind: Some(proto::resources::resource::Kind { kind: Some(kind) }),
+            concrete_type: "Sleep".to_string(),
+            ..Default::default()
+        };
+        let timer = proto::resources::resource::kind::Kind::Known(
+            proto::resources::resource::kind::Known::Timer as i32,
+        );
+        let other = proto::resources::resource::kind::Kind::Other("Sync".to_string());
+        let stats = |created_secs_ago, duration_ms| proto::resources::Stats {
+            created_at: Some((now - Duration::from_secs(created_secs_ago)).into()),
+            attributes: vec![proto::Attribute {
+                field: Some(proto::Field {
+                    name: Some(proto::field::Name::StrName("duration".to_string())),
+                    value: Some(proto::field::Value::U64Val(duration_ms)),
+                    metadata_id: Some(proto::MetaId { id: 2 }),
+                }),
+                unit: Some("ms".to_string()),
+            }],
+            ..Default::default()
+        };
+
+        let mut state = State::default();
+        state.update(
+            None,
+            proto::instrument::Update {
+                now: Some(now.into()),
+                new_metadata: Some(proto::RegisterMetadata {
+                    metadata: vec![
+                        meta(1, "runtime.spawn"),
+                        meta(2, "runtime.resource"),
+                        meta(3, "runtime.resource.async_op"),
+                    ],
+                }),
+                task_update: Some(proto::tasks::TaskUpdate {
+                    new_tasks: vec![proto::tasks::Task {
+                        id: Some(proto::Id { id: 1 }),
+                        metadata: Some(proto::MetaId { id: 1 }),
+                        ..Default::default()
+                    }],
+                    stats_update: [(
+                        1,
+                        proto::tasks::Stats {
+                            created_at: Some(now.into()),
+                            poll_stats: Some(Default::default()),
+                            ..Default::default()
+                        },
+                    )]
+                    .into(),
+                    ..Default::default()
+                }),
+                resource_update: Some(proto::resources::ResourceUpdate {
+                    new_resources: vec![
+                        sleep(2, timer.clone()),
+                        sleep(3, timer.clone()),
+                        sleep(4, timer),
+                        sleep(5, other),
+                    ],
+                    stats_update: [
+                        // Reset by the async op below, 2.5s before it's due.
+                        (2, stats(10, 3_000)),
+                        (3, stats(1, 5_000)),
+                        // Already fired.
+                        (4, stats(2, 500)),
+                        // Not a timer.
+                        (5, stats(1, 1_000)),
+                    ]
+                    .into(),
+                    ..Default::default()
+                }),
+                async_op_update: Some(proto::async_ops::AsyncOpUpdate {
+                    new_async_ops: vec![proto::async_ops::AsyncOp {
+                        id: Some(proto::Id { id: 6 }),
+                        metadata: Some(proto::MetaId { id: 3 }),
+                        source: "Sleep::reset".to_string(),
+                        resource_id: Some(proto::Id { id: 2 }),
+                        ..Default::default()
+                    }],
+                    stats_update: [(
+                        6,
+                        proto::async_ops::Stats {
+                            created_at: Some((now - Duration::from_millis(500)).into()),
+                            task_id: Some(proto::Id { id: 1 }),
+                            poll_stats: Some(Default::default()),
+                            ..Default::default()
+                        },
+                    )]
+                    .into(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+
+        let wakes = state
+            .upcoming_wakes()
+            .into_iter()
+            .map(|wake| (wake.due_in, wake.task.is_some()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            wakes,
+            [
+                (Duration::from_millis(2_500), true),
+                (Duration::from_secs(4), false),
+            ]
+        );
+    }
+}
@@ -1,4 +1,4 @@
-pub(crate) trait Percentage {
+pub trait Percentage {
     // Using an extension trait for this is maybe a bit excessive, but making it
     // a method has the nice advantage of making it *really* obvious which is
     // the total and which is the amount.
@@ -23,7 +23,7 @@ impl Percentage for f64 {
     }
 }
 
-pub(crate) fn percentage(total: f64, amount: f64) -> f64 {
+pub fn percentage(total: f64, amount: f64) -> f64 {
     debug_assert!(
         total >= amount,
         "assertion failed: total >= amount; total={}, amount={}",
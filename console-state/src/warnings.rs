@@ -0,0 +1,615 @@
+use crate::{
+    budget::BudgetStatus,
+    clock,
+    store::SpanId,
+    tasks::{Task, TaskState},
+};
+use std::{
+    cmp::{self, Reverse},
+    collections::{BTreeMap, HashSet},
+    fmt::Debug,
+    mem,
+    rc::Rc,
+    time::{Duration, SystemTime},
+};
+
+/// A warning for a particular type of monitored entity (e.g. task or resource).
+///
+/// This trait implements the logic for detecting a particular warning, and
+/// generating a warning message describing it. The [`Linter`] type wraps an
+/// instance of this trait to track active instances of the warning.
+pub trait Warn<T>: Debug {
+    /// Returns if the warning applies to `val`.
+    fn check(&self, val: &T) -> Warning;
+
+    /// Formats a description of the warning detected for a *specific* `val`.
+    ///
+    /// This may include dynamically formatted content specific to `val`, such
+    /// as the specific numeric value that was over the line for detecting the
+    /// warning.
+    ///
+    /// This should be a complete sentence describing the warning. For example,
+    /// for the [`SelfWakePercent`] warning, this returns a string like:
+    ///
+    /// > "This task has woken itself for more than 50% of its total wakeups (86%)"
+    fn format(&self, val: &T) -> String;
+
+    /// Returns a string summarizing the warning *in general*, suitable for
+    /// displaying in a list of all detected warnings.
+    ///
+    /// The list entry will begin with a count of the number of monitored
+    /// entities for which the warning was detected. Therefore, this should be a
+    /// sentence fragment suitable to follow a count. For example, for the
+    /// [`SelfWakePercent`] warning, this method will return a string like
+    ///
+    /// > "tasks have woken themselves more than 50% of the time"
+    ///
+    /// so that the warnings list can read
+    ///
+    /// > "45 tasks have woken themselves more than 50% of the time"
+    ///
+    /// If the warning is configurable (for example, [`SelfWakePercent`] allows
+    /// customizing the percentage used to detect the warning), this string may
+    /// be formatted dynamically for the *linter*, but not for the individual
+    /// instances of the lint that were detected.
+    //
+    // TODO(eliza): it would be nice if we had separate plural and singular
+    // versions of this, like "56 tasks have..." vs "1 task has...".
+    fn summary(&self) -> &str;
+}
+
+/// A result for a warning check
+pub enum Warning {
+    /// No warning for this entity.
+    Ok,
+
+    /// A warning has been detected for this entity.
+    Warn,
+
+    /// The warning should be rechecked as the conditions to allow for checking
+    /// are not satisfied yet
+    Recheck,
+}
+
+#[derive(Debug)]
+pub struct Linter<T>(Rc<dyn Warn<T>>);
+
+impl<T> Linter<T> {
+    pub fn new<W>(warning: W) -> Self
+    where
+        W: Warn<T> + 'static,
+    {
+        Self(Rc::new(warning))
+    }
+
+    /// Checks if the warning applies to a particular entity
+    pub fn check(&self, val: &T) -> Lint<T> {
+        match self.0.check(val) {
+            Warning::Ok => Lint::Ok,
+            Warning::Warn => Lint::Warning(Self(self.0.clone())),
+            Warning::Recheck => Lint::Recheck,
+        }
+    }
+
+    /// Returns the number of monitored entities that currently have this warning.
+    pub fn count(&self) -> usize {
+        Rc::strong_count(&self.0) - 1
+    }
+
+    pub fn format(&self, val: &T) -> String {
+        debug_assert!(
+            matches!(self.0.check(val), Warning::Warn),
+            "tried to format a warning for a {} that did not have that warning!",
+            std::any::type_name::<T>()
+        );
+        self.0.format(val)
+    }
+
+    pub fn summary(&self) -> &str {
+        self.0.summary()
+    }
+}
+
+/// The warnings detected over the whole console session.
+///
+/// [`Linter::count`] only counts the tasks that have a warning right now, so it
+/// drops when tasks are evicted or the connection to the target is lost. This
+/// instead keeps a record for each warning and spawn location, which counts
+/// every task it was detected for once, however many times it is linted. Only
+/// the most recently recorded tasks are kept to tell whether a task was already
+/// counted, so the record's size is bounded however long the session lasts.
+#[derive(Debug, Default)]
+pub struct WarningHistory {
+    /// Records, by warning summary and then by spawn location.
+    records: BTreeMap<String, BTreeMap<String, WarningRecord>>,
+}
+
+#[derive(Debug)]
+pub struct WarningRecord {
+    /// The number of distinct tasks this warning was detected for.
+    count: usize,
+    /// The tasks this warning was most recently detected for, so that a task
+    /// which is linted again isn't counted again. Span IDs may be reused once
+    /// a span closes, so the task's creation time is included as well.
+    ///
+    /// Only the most recently recorded tasks are kept, so that the record
+    /// doesn't grow for as long as the session lasts. Once `recent` is full,
+    /// it replaces `older`, and a task found in `older` is moved back.
+    recent: HashSet<(SpanId, SystemTime)>,
+    older: HashSet<(SpanId, SystemTime)>,
+    first_seen: SystemTime,
+    last_seen: SystemTime,
+}
+
+/// The most tasks each [`WarningRecord`] keeps in each of its generations of
+/// recently recorded tasks.
+///
+/// A task is only counted twice if more tasks than this are recorded with the
+/// same warning and spawn location between two times it's linted.
+const MAX_RECENT_TASKS: usize = 1024;
+
+/// A result for a linter check
+pub enum Lint<T> {
+    /// No warning applies to the entity
+    Ok,
+
+    /// The cloned instance of `Self` should be held by the entity that
+    /// generated the warning, so that it can be formatted. Holding the clone of
+    /// `Self` will increment the warning count for that entity.
+    Warning(Linter<T>),
+
+    /// The lint should be rechecked as the conditions to allow for checking are
+    /// not satisfied yet
+    Recheck,
+}
+
+// === impl WarningHistory ===
+
+impl WarningHistory {
+    /// Records the warnings `task` currently has, as seen at `seen_at`.
+    pub fn record(&mut self, task: &Task, seen_at: SystemTime) {
+        for warning in task.warnings() {
+            let locations = match self.records.get_mut(warning.summary()) {
+                Some(locations) => locations,
+                None => self
+                    .records
+                    .entry(warning.summary().to_owned())
+                    .or_default(),
+            };
+            let record = match locations.get_mut(task.location()) {
+                Some(record) => record,
+                None => locations
+                    .entry(task.location().to_owned())
+                    .or_insert_with(|| WarningRecord::new(seen_at)),
+            };
+            record.record_task((task.span_id(), task.created_at()));
+            record.first_seen = cmp::min(record.first_seen, seen_at);
+            record.last_seen = cmp::max(record.last_seen, seen_at);
+        }
+    }
+
+    /// Returns the summary, spawn location, and record of every warning seen
+    /// so far, most recently seen first.
+    pub fn records(&self) -> Vec<(&str, &str, &WarningRecord)> {
+        let mut records = self
+            .records
+            .iter()
+            .flat_map(|(summary, locations)| {
+                locations
+                    .iter()
+                    .map(move |(location, record)| (summary.as_str(), location.as_str(), record))
+            })
+            .collect::<Vec<_>>();
+        records.sort_by_key(|&(_, _, record)| Reverse(record.last_seen));
+        records
+    }
+}
+
+// === impl WarningRecord ===
+
+impl WarningRecord {
+    fn new(seen_at: SystemTime) -> Self {
+        Self {
+            count: 0,
+            recent: HashSet::new(),
+            older: HashSet::new(),
+            first_seen: seen_at,
+            last_seen: seen_at,
+        }
+    }
+
+    /// Counts the task identified by `key`, unless it was recorded recently.
+    fn record_task(&mut self, key: (SpanId, SystemTime)) {
+        if self.recent.contains(&key) {
+            return;
+        }
+        if !self.older.remove(&key) {
+            self.count += 1;
+        }
+        if self.recent.len() >= MAX_RECENT_TASKS {
+            self.older = mem::take(&mut self.recent);
+        }
+        self.recent.insert(key);
+    }
+
+    /// Returns the number of distinct tasks this warning was detected for.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn first_seen(&self) -> SystemTime {
+        self.first_seen
+    }
+
+    pub fn last_seen(&self) -> SystemTime {
+        self.last_seen
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SelfWakePercent {
+    min_percent: u64,
+    description: String,
+}
+
+impl SelfWakePercent {
+    pub const DEFAULT_PERCENT: u64 = 50;
+    pub fn new(min_percent: u64) -> Self {
+        Self {
+            min_percent,
+            description: format!(
+                "tasks have woken themselves over {}% of the time",
+                min_percent
+            ),
+        }
+    }
+}
+
+impl Default for SelfWakePercent {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_PERCENT)
+    }
+}
+
+impl Warn<Task> for SelfWakePercent {
+    fn summary(&self) -> &str {
+        self.description.as_str()
+    }
+
+    fn check(&self, task: &Task) -> Warning {
+        // Don't fire warning for tasks that are not async
+        if task.is_blocking() {
+            return Warning::Ok;
+        }
+        let self_wakes = task.self_wake_percent();
+        if self_wakes > self.min_percent {
+            Warning::Warn
+        } else {
+            Warning::Ok
+        }
+    }
+
+    fn format(&self, task: &Task) -> String {
+        let self_wakes = task.self_wake_percent();
+        format!(
+            "This task has woken itself for more than {}% of its total wakeups ({}%)",
+            self.min_percent, self_wakes
+        )
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LostWaker;
+
+impl Warn<Task> for LostWaker {
+    fn summary(&self) -> &str {
+        "tasks have lost their wakers"
+    }
+
+    fn check(&self, task: &Task) -> Warning {
+        // Don't fire warning for tasks that are not async
+        if task.is_blocking() {
+            return Warning::Ok;
+        }
+        if !task.is_completed()
+            && task.waker_count() == 0
+            && !task.is_running()
+            && !task.is_awakened()
+        {
+            Warning::Warn
+        } else {
+            Warning::Ok
+        }
+    }
+
+    fn format(&self, _: &Task) -> String {
+        "This task has lost its waker, and will never be woken again.".into()
+    }
+}
+
+/// Warning for if a task has never yielded
+#[derive(Clone, Debug)]
+pub struct NeverYielded {
+    min_duration: Duration,
+    description: String,
+}
+
+impl NeverYielded {
+    pub const DEFAULT_DURATION: Duration = Duration::from_secs(1);
+    pub fn new(min_duration: Duration) -> Self {
+        Self {
+            min_duration,
+            description: format!(
+                "tasks have never yielded (threshold {}ms)",
+                min_duration.as_millis()
+            ),
+        }
+    }
+}
+
+impl Default for NeverYielded {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_DURATION)
+    }
+}
+
+impl Warn<Task> for NeverYielded {
+    fn summary(&self) -> &str {
+        self.description.as_str()
+    }
+
+    fn check(&self, task: &Task) -> Warning {
+        // Don't fire warning for tasks that are not async
+        if task.is_blocking() {
+            return Warning::Ok;
+        }
+        // Don't fire warning for tasks that are waiting to run
+        if task.state() != TaskState::Running {
+            return Warning::Ok;
+        }
+
+        if task.total_polls() > 1 {
+            return Warning::Ok;
+        }
+
+        // Avoid short-lived task false positives
+        if task.busy(clock::target_now()) >= self.min_duration {
+            Warning::Warn
+        } else {
+            Warning::Recheck
+        }
+    }
+
+    fn format(&self, task: &Task) -> String {
+        format!(
+            "This task has never yielded ({:?})",
+            task.busy(clock::target_now()),
+        )
+    }
+}
+
+/// Warning for if a task's driving future was auto-boxed by the runtime
+#[derive(Clone, Debug, Default)]
+pub struct AutoBoxedFuture;
+
+impl Warn<Task> for AutoBoxedFuture {
+    fn summary(&self) -> &str {
+        "tasks have been boxed by the runtime due to their size"
+    }
+
+    fn check(&self, task: &Task) -> Warning {
+        let (Some(size_bytes), Some(original_size_bytes)) =
+            (task.size_bytes(), task.original_size_bytes())
+        else {
+            return Warning::Ok;
+        };
+
+        if original_size_bytes != size_bytes {
+            Warning::Warn
+        } else {
+            Warning::Ok
+        }
+    }
+
+    fn format(&self, task: &Task) -> String {
+        let original_size = task
+            .original_size_bytes()
+            .expect("warning should not trigger if original size is None");
+        let boxed_size = task
+            .size_bytes()
+            .expect("warning should not trigger if size is None");
+        format!(
+            "This task's future was auto-boxed by the runtime when spawning, due to its size (originally \
+            {original_size} bytes, boxed size {boxed_size} bytes)",
+        )
+    }
+}
+
+/// Warning for if a task's driving future if large
+#[derive(Clone, Debug)]
+pub struct LargeFuture {
+    min_size: usize,
+    description: String,
+}
+impl LargeFuture {
+    pub const DEFAULT_MIN_SIZE_BYTES: usize = 1024;
+    pub fn new(min_size: usize) -> Self {
+        Self {
+            min_size,
+            description: format!("tasks are {} bytes or larger", min_size),
+        }
+    }
+}
+
+impl Default for LargeFuture {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MIN_SIZE_BYTES)
+    }
+}
+
+impl Warn<Task> for LargeFuture {
+    fn summary(&self) -> &str {
+        self.description.as_str()
+    }
+
+    fn check(&self, task: &Task) -> Warning {
+        // Don't fire warning for tasks that are not async
+        if task.is_blocking() {
+            return Warning::Ok;
+        }
+
+        if let Some(size_bytes) = task.size_bytes() {
+            if size_bytes >= self.min_size {
+                return Warning::Warn;
+            }
+        }
+        Warning::Ok
+    }
+
+    fn format(&self, task: &Task) -> String {
+        format!(
+            "This task occupies a large amount of stack space ({} bytes)",
+            task.size_bytes()
+                .expect("warning should not trigger if size is None"),
+        )
+    }
+}
+
+/// Warning for if a task has exceeded its configured latency budget
+#[derive(Clone, Debug, Default)]
+pub struct LatencyBudgetExceeded;
+
+impl Warn<Task> for LatencyBudgetExceeded {
+    fn summary(&self) -> &str {
+        "tasks have exceeded their latency budgets"
+    }
+
+    fn check(&self, task: &Task) -> Warning {
+        // Estimated percentiles aren't reliable enough to warn about.
+        match task.budget_status() {
+            Some((BudgetStatus::Exceeded, false)) => Warning::Warn,
+            _ => Warning::Ok,
+        }
+    }
+
+    fn format(&self, task: &Task) -> String {
+        let tracker = task
+            .latency_budget()
+            .expect("warning should not trigger if the task has no budget");
+        let budget = tracker.budget();
+        let current = tracker
+            .current()
+            .expect("warning should not trigger if no durations were recorded");
+        format!(
+            "This task has exceeded its latency budget of `{budget}` ({} p{} is {current:?})",
+            budget.metric(),
+            budget.percentile(),
+        )
+    }
+}
+
+/// Warning for if a task panicked.
+#[derive(Clone, Debug, Default)]
+pub struct Panicked;
+
+impl Warn<Task> for Panicked {
+    fn summary(&self) -> &str {
+        "tasks have panicked"
+    }
+
+    fn check(&self, task: &Task) -> Warning {
+        if task.state() == TaskState::Panicked {
+            Warning::Warn
+        } else {
+            Warning::Ok
+        }
+    }
+
+    fn format(&self, _: &Task) -> String {
+        "This task panicked".to_string()
+    }
+}
+
+/// Warning for if a task's live waker count keeps growing, which suggests
+/// that its wakers are being stored and never dropped.
+#[derive(Clone, Debug)]
+pub struct WakerLeak {
+    min_updates: u32,
+    min_growth: u64,
+    description: String,
+}
+
+impl WakerLeak {
+    /// The default number of consecutive updates the live waker count must
+    /// grow for.
+    pub const DEFAULT_MIN_UPDATES: u32 = 5;
+    /// The default number of wakers the live waker count must grow by.
+    pub const DEFAULT_MIN_GROWTH: u64 = 100;
+
+    pub fn new(min_updates: u32, min_growth: u64) -> Self {
+        Self {
+            min_updates,
+            min_growth,
+            description: format!(
+                "tasks have gained over {} live wakers in {} or more consecutive updates",
+                min_growth, min_updates
+            ),
+        }
+    }
+}
+
+impl Default for WakerLeak {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MIN_UPDATES, Self::DEFAULT_MIN_GROWTH)
+    }
+}
+
+impl Warn<Task> for WakerLeak {
+    fn summary(&self) -> &str {
+        self.description.as_str()
+    }
+
+    fn check(&self, task: &Task) -> Warning {
+        let trend = task.waker_trend();
+        if trend.growing_for() >= self.min_updates && trend.growth() > self.min_growth {
+            Warning::Warn
+        } else {
+            Warning::Ok
+        }
+    }
+
+    fn format(&self, task: &Task) -> String {
+        let trend = task.waker_trend();
+        format!(
+            "This task's live waker count has grown by {} over the last {} updates, to {}; \
+            its wakers may be stored and never dropped",
+            trend.growth(),
+            trend.growing_for(),
+            trend.live(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warning_record_counts_tasks_once_and_stays_bounded() {
+        let start = SystemTime::UNIX_EPOCH;
+        let mut record = WarningRecord::new(start);
+        let key = |i: u64| (i, start + Duration::from_secs(i));
+
+        for i in 0..MAX_RECENT_TASKS as u64 * 3 {
+            record.record_task(key(i));
+            // A task which keeps being linted is only counted once.
+            record.record_task(key(0));
+        }
+        assert_eq!(record.count(), MAX_RECENT_TASKS * 3);
+        assert!(record.recent.len() + record.older.len() <= MAX_RECENT_TASKS * 2);
+
+        // A task which was recorded recently isn't counted again.
+        record.record_task(key(MAX_RECENT_TASKS as u64 * 3 - 1));
+        assert_eq!(record.count(), MAX_RECENT_TASKS * 3);
+    }
+}
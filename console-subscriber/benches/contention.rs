@@ -0,0 +1,91 @@
+//! Measures how the [`ConsoleLayer`] scales when many threads record task
+//! spawns at the same time, with a single event buffer shard compared to one
+//! shard per CPU core.
+//!
+//! Run with:
+//!
+//! ```shell
+//! cargo bench -p console-subscriber --bench contention
+//! ```
+use console_subscriber::ConsoleLayer;
+use std::{
+    sync::{Arc, Barrier},
+    thread,
+    time::{Duration, Instant},
+};
+use tracing_subscriber::prelude::*;
+
+const ITERATIONS_PER_THREAD: usize = 20_000;
+
+fn main() {
+    let threads = thread::available_parallelism()
+        .map(usize::from)
+        .unwrap_or(1)
+        .max(2);
+
+    let single = run(threads, 1);
+    let sharded = run(threads, threads);
+    println!(
+        "contention ({} threads): 1 shard: {:?}/iter, {} shards: {:?}/iter ({:.2}x)",
+        threads,
+        single,
+        threads,
+        sharded,
+        single.as_secs_f64() / sharded.as_secs_f64(),
+    );
+}
+
+/// Spawns `threads` threads which each record task spawns as fast as they
+/// can, and returns the mean time taken per spawn.
+fn run(threads: usize, shards: usize) -> Duration {
+    let iterations = threads * ITERATIONS_PER_THREAD;
+    // Make the event buffer large enough that no spawn events are dropped, so
+    // that every iteration does the same amount of work.
+    let (layer, _server) = ConsoleLayer::builder()
+        .event_buffer_capacity(iterations * 2)
+        .event_buffer_shards(shards)
+        .build();
+    let dispatch = tracing::Dispatch::new(tracing_subscriber::registry().with(layer));
+
+    // Register the callsite before measuring.
+    tracing::dispatcher::with_default(&dispatch, || spawn_span(0));
+
+    let barrier = Arc::new(Barrier::new(threads + 1));
+    let handles = (0..threads)
+        .map(|thread| {
+            let dispatch = dispatch.clone();
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                tracing::dispatcher::with_default(&dispatch, || {
+                    barrier.wait();
+                    for i in 0..ITERATIONS_PER_THREAD {
+                        spawn_span((thread * ITERATIONS_PER_THREAD + i) as u64);
+                    }
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+
+    barrier.wait();
+    let start = Instant::now();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    start.elapsed() / iterations as u32
+}
+
+/// Creates a span with the same shape as the one Tokio creates for each
+/// spawned task.
+fn spawn_span(id: u64) {
+    let _span = tracing::trace_span!(
+        target: "tokio::task",
+        "runtime.spawn",
+        kind = %"task",
+        task.name = %"bench-task",
+        task.id = id,
+        size.bytes = 64u64,
+        loc.file = file!(),
+        loc.line = line!(),
+        loc.col = column!(),
+    );
+}
@@ -0,0 +1,101 @@
+//! Measures the cost of recording task spawns in the [`ConsoleLayer`].
+//!
+//! This reports both the time taken and the number of heap allocations made
+//! per spawned task, as the allocation count is much less noisy than the
+//! timing on a busy machine. The benchmark fails if spawning a task makes
+//! more allocations than it should.
+//!
+//! Run with:
+//!
+//! ```shell
+//! cargo bench -p console-subscriber --bench spawn
+//! ```
+use console_subscriber::ConsoleLayer;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+use tracing_subscriber::prelude::*;
+
+const ITERATIONS: usize = 100_000;
+
+/// The most heap allocations recording a spawned task may make.
+///
+/// The values of fields which are the same for every task spawned from a
+/// callsite, such as `kind` and `loc.file`, are shared rather than copied,
+/// and so aren't counted here.
+const MAX_ALLOCATIONS_PER_SPAWN: usize = 5;
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn main() {
+    // Make the event buffer large enough that no spawn events are dropped, so
+    // that every iteration does the same amount of work.
+    let (layer, _server) = ConsoleLayer::builder()
+        .event_buffer_capacity(ITERATIONS * 2)
+        .build();
+    let subscriber = tracing_subscriber::registry().with(layer);
+
+    tracing::subscriber::with_default(subscriber, || {
+        // Register the callsite before measuring.
+        spawn_span(0);
+
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+        let start = Instant::now();
+        for i in 0..ITERATIONS {
+            spawn_span(i as u64);
+        }
+        let elapsed = start.elapsed();
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+
+        println!(
+            "spawn: {:?}/iter, {:.2} allocations/iter ({} iterations)",
+            elapsed / ITERATIONS as u32,
+            allocations as f64 / ITERATIONS as f64,
+            ITERATIONS,
+        );
+        assert!(
+            allocations / ITERATIONS <= MAX_ALLOCATIONS_PER_SPAWN,
+            "recording a spawn should make at most {} allocations",
+            MAX_ALLOCATIONS_PER_SPAWN,
+        );
+    });
+}
+
+/// Creates a span with the same shape as the one Tokio creates for each
+/// spawned task.
+fn spawn_span(id: u64) {
+    let _span = tracing::trace_span!(
+        target: "tokio::task",
+        "runtime.spawn",
+        kind = %"task",
+        task.name = %"bench-task",
+        task.id = id,
+        size.bytes = 64u64,
+        loc.file = file!(),
+        loc.line = line!(),
+        loc.col = column!(),
+    );
+}
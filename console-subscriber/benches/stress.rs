@@ -0,0 +1,367 @@
+//! Stresses the [`ConsoleLayer`] and its aggregator with the volume of
+//! instrumentation a large application produces: hundreds of thousands of
+//! short tasks, heavy waker traffic, and churning resources, recorded from
+//! several threads at once.
+//!
+//! Each workload is run three times, to measure:
+//!
+//! - **overhead**: the mean time the layer takes to record each span and
+//!   event, with an event buffer large enough that nothing is dropped and no
+//!   aggregator running.
+//! - **drain**: how many buffered events per second the aggregator
+//!   aggregates while it's kept busy, once it's started with part of the
+//!   workload waiting in the event buffer.
+//! - **dropped**: the share of buffered events which are dropped because the
+//!   event buffer is full, with the aggregator running alongside the
+//!   workload. The aggregator's clock only moves between batches of the
+//!   workload, so during a batch it only drains the buffer when the layer
+//!   asks it to, as it would during a burst shorter than the publish
+//!   interval. The longest pass of the aggregator's loop is reported too.
+//!
+//! The workloads are recorded in batches, so that the memory held for
+//! tasks which haven't been aggregated yet stays bounded.
+//!
+//! Results are printed one line per workload. To keep a baseline to compare
+//! changes against, set `CONSOLE_STRESS_JSON` to a path to also write them
+//! there as JSON.
+//!
+//! Run with:
+//!
+//! ```shell
+//! cargo bench -p console-subscriber --bench stress
+//! ```
+use console_api::instrument::DroppedEvents;
+use console_subscriber::{test_support::AggregatorHarness, ConsoleLayer};
+use futures::StreamExt;
+use serde::Serialize;
+use std::{
+    ops::Range,
+    sync::{Arc, Barrier},
+    thread,
+    time::{Duration, Instant},
+};
+use tracing_subscriber::prelude::*;
+
+const PUBLISH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The most threads to record from, so that machines with many cores don't
+/// run much larger workloads than others.
+const MAX_THREADS: usize = 8;
+
+/// How many units of a workload are recorded at a time.
+///
+/// Every task holds its poll and scheduled time histograms, tens of kilobytes
+/// of them, from when it's spawned until the aggregator drops it. Recording a
+/// whole workload before aggregating any of it would take gigabytes of
+/// memory.
+const BATCH: u64 = 5_000;
+
+/// The event buffer capacity used when measuring drop rates.
+///
+/// This is smaller than the default for the same reason as [`BATCH`], as
+/// each buffered spawn holds the new task's histograms.
+const DROPS_EVENT_BUFFER_CAPACITY: usize = 16 * 1024;
+
+/// How many times each task in the `wakers` workload is woken.
+const WAKES_PER_TASK: u64 = 100;
+
+/// A burst of instrumentation, made up of a number of identical units which
+/// are recorded in batches, each split between the recording threads.
+struct Workload {
+    name: &'static str,
+    units: u64,
+    /// The number of spans and events the layer records for each unit,
+    /// counting each span's creation, enters, exits, and close separately.
+    events_per_unit: u64,
+    /// The number of those which are sent to the event buffer.
+    buffered_per_unit: u64,
+    /// Records the given units of the workload.
+    run: fn(Range<u64>),
+}
+
+static WORKLOADS: [Workload; 3] = [
+    Workload {
+        name: "tasks",
+        units: 200_000,
+        events_per_unit: 7,
+        buffered_per_unit: 1,
+        run: short_tasks,
+    },
+    Workload {
+        name: "wakers",
+        units: 2_000,
+        events_per_unit: 2 + WAKES_PER_TASK * 4,
+        buffered_per_unit: 1,
+        run: woken_tasks,
+    },
+    Workload {
+        name: "resources",
+        units: 100_000,
+        events_per_unit: 10,
+        buffered_per_unit: 3,
+        run: resource_churn,
+    },
+];
+
+/// The results for one workload.
+#[derive(Debug, Serialize)]
+struct Results {
+    workload: &'static str,
+    threads: usize,
+    events: u64,
+    buffered_events: u64,
+    overhead_ns_per_event: f64,
+    drained_events_per_sec: f64,
+    dropped_events: u64,
+    dropped_percent: f64,
+    max_aggregation_time_us: u64,
+}
+
+fn main() {
+    let threads = thread::available_parallelism()
+        .map(usize::from)
+        .unwrap_or(1)
+        .clamp(2, MAX_THREADS);
+
+    let mut results = Vec::new();
+    for workload in &WORKLOADS {
+        let result = run(workload, threads);
+        println!(
+            "stress {} ({} events, {} threads): {:.0}ns/event, drained {:.0} events/s, \
+             dropped {} of {} buffered events ({:.2}%), max aggregation pass {:?}",
+            result.workload,
+            result.events,
+            result.threads,
+            result.overhead_ns_per_event,
+            result.drained_events_per_sec,
+            result.dropped_events,
+            result.buffered_events,
+            result.dropped_percent,
+            Duration::from_micros(result.max_aggregation_time_us),
+        );
+        results.push(result);
+    }
+
+    if let Some(path) = std::env::var_os("CONSOLE_STRESS_JSON") {
+        let json = serde_json::to_string_pretty(&results).expect("results should serialize");
+        std::fs::write(&path, json).expect("results should be written");
+        println!("wrote results to {}", path.to_string_lossy());
+    }
+}
+
+fn run(workload: &'static Workload, threads: usize) -> Results {
+    // Each batch is split evenly between the threads, leaving out any
+    // remainder.
+    let batch = BATCH.min(workload.units) / threads as u64 * threads as u64;
+    let batches = (0..workload.units / batch)
+        .map(|i| i * batch..(i + 1) * batch)
+        .collect::<Vec<_>>();
+    let units = batch * batches.len() as u64;
+    let events = units * workload.events_per_unit;
+    let buffered = units * workload.buffered_per_unit;
+    // Leave room for metadata registrations on top of a batch's events, and
+    // for the layer asking for a flush before the buffer is completely full.
+    let capacity = (batch * workload.buffered_per_unit) as usize * 2 + 1024;
+    let runtime = runtime();
+
+    // Recording overhead, without an aggregator competing for the CPU. Each
+    // batch gets a new layer, so that the last one's buffered events are
+    // freed.
+    let mut overhead = Duration::ZERO;
+    for batch in &batches {
+        let (layer, _server) = ConsoleLayer::builder()
+            .event_buffer_capacity(capacity)
+            .build();
+        let dispatch = tracing::Dispatch::new(tracing_subscriber::registry().with(layer));
+        overhead += record(&dispatch, workload, batch.clone(), threads);
+    }
+
+    // Aggregator drain throughput, starting the aggregator once a whole batch
+    // is waiting in the event buffer.
+    let mut drain = Duration::ZERO;
+    for batch in &batches {
+        let (layer, mut harness) =
+            AggregatorHarness::new(ConsoleLayer::builder().event_buffer_capacity(capacity));
+        let dispatch = tracing::Dispatch::new(tracing_subscriber::registry().with(layer));
+        record(&dispatch, workload, batch.clone(), threads);
+        drain += runtime.block_on(async {
+            let start = Instant::now();
+            harness.spawn();
+            while harness.self_metrics().await.event_buffer_len > 0 {}
+            start.elapsed()
+        });
+    }
+
+    // Drop rates, with the aggregator draining the event buffer while the
+    // batches are recorded one after the other. Publishing after each batch
+    // lets completed tasks be dropped, as no retention is kept.
+    let (layer, mut harness) = AggregatorHarness::new(
+        ConsoleLayer::builder()
+            .event_buffer_capacity(DROPS_EVENT_BUFFER_CAPACITY)
+            .publish_interval(PUBLISH_INTERVAL)
+            .retention(Duration::ZERO),
+    );
+    let dispatch = tracing::Dispatch::new(tracing_subscriber::registry().with(layer));
+    let (dropped, max_aggregation_time) = runtime.block_on(async {
+        harness.spawn();
+        let mut updates = Box::pin(harness.watch_updates().await);
+        updates.next().await;
+
+        let mut dropped = 0;
+        let mut max_aggregation_time = Duration::ZERO;
+        for batch in batches {
+            // The layer must outlive the recording, as the aggregator stops
+            // once it's dropped.
+            let recording = dispatch.clone();
+            tokio::task::spawn_blocking(move || record(&recording, workload, batch, threads))
+                .await
+                .expect("recording should complete");
+            harness.advance(PUBLISH_INTERVAL).await;
+            let stats = updates
+                .next()
+                .await
+                .expect("the stream should not end")
+                .expect("the update should not be an error")
+                .subscriber_stats
+                .expect("published updates include subscriber stats");
+            dropped += stats.dropped_events.as_ref().map_or(0, dropped_total);
+            if let Some(time) = stats
+                .max_aggregation_time
+                .and_then(|time| Duration::try_from(time).ok())
+            {
+                max_aggregation_time = max_aggregation_time.max(time);
+            }
+        }
+        (dropped, max_aggregation_time)
+    });
+
+    Results {
+        workload: workload.name,
+        threads,
+        events,
+        buffered_events: buffered,
+        overhead_ns_per_event: overhead.as_nanos() as f64 / events as f64,
+        drained_events_per_sec: buffered as f64 / drain.as_secs_f64(),
+        dropped_events: dropped,
+        dropped_percent: dropped as f64 / buffered as f64 * 100.0,
+        max_aggregation_time_us: max_aggregation_time.as_micros() as u64,
+    }
+}
+
+/// Records `units` of `workload` from `threads` threads at once, each with an
+/// equal share of the units, and returns how long it took.
+fn record(
+    dispatch: &tracing::Dispatch,
+    workload: &Workload,
+    units: Range<u64>,
+    threads: usize,
+) -> Duration {
+    let per_thread = (units.end - units.start) / threads as u64;
+    let barrier = Arc::new(Barrier::new(threads + 1));
+    let handles = (0..threads as u64)
+        .map(|thread| {
+            let dispatch = dispatch.clone();
+            let barrier = barrier.clone();
+            let run = workload.run;
+            let start = units.start + thread * per_thread;
+            thread::spawn(move || {
+                tracing::dispatcher::with_default(&dispatch, || {
+                    barrier.wait();
+                    run(start..start + per_thread);
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+
+    barrier.wait();
+    let start = Instant::now();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    start.elapsed()
+}
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .enable_all()
+        .build()
+        .expect("runtime should build")
+}
+
+fn dropped_total(dropped: &DroppedEvents) -> u64 {
+    dropped.spawns + dropped.resources + dropped.async_ops + dropped.poll_ops
+}
+
+/// Tasks which are polled twice, waking themselves in between, and then
+/// complete.
+fn short_tasks(units: Range<u64>) {
+    for id in units {
+        let span = spawn_span(id);
+        span.in_scope(|| wake(&span, "waker.wake_by_ref"));
+        span.in_scope(|| {});
+    }
+}
+
+/// Tasks which are woken many times by other threads' wakers, and polled
+/// after each wake.
+fn woken_tasks(units: Range<u64>) {
+    for id in units {
+        let span = spawn_span(id);
+        for _ in 0..WAKES_PER_TASK {
+            wake(&span, "waker.clone");
+            wake(&span, "waker.wake");
+            span.in_scope(|| {});
+        }
+    }
+}
+
+/// Resources which are created, have an attribute updated, and are polled
+/// once by an async op before being dropped, all from a single task.
+fn resource_churn(units: Range<u64>) {
+    let task = spawn_span(units.start);
+    let _task = task.enter();
+    for _ in units {
+        let resource =
+            console_subscriber::instrument_resource!(concrete_type = "bench::Pool", kind = "Sync",);
+        let _resource = resource.enter();
+        tracing::trace!(
+            target: "runtime::resource::state_update",
+            idle = 1,
+            idle.op = "override",
+        );
+        let op = console_subscriber::instrument_async_op!(source = "Pool::checkout");
+        op.in_scope(|| {
+            tracing::trace!(
+                target: "runtime::resource::poll_op",
+                op_name = "poll_checkout",
+                is_ready = true,
+            );
+        });
+    }
+}
+
+/// Emits a waker event for the task with `span`, as Tokio does when one of
+/// its wakers is used.
+fn wake(span: &tracing::Span, op: &'static str) {
+    let Some(id) = span.id() else {
+        return;
+    };
+    tracing::trace!(target: "tokio::task::waker", op, task.id = id.into_u64());
+}
+
+/// Creates a span with the same shape as the one Tokio creates for each
+/// spawned task.
+fn spawn_span(id: u64) -> tracing::Span {
+    tracing::trace_span!(
+        target: "tokio::task",
+        "runtime.spawn",
+        kind = %"task",
+        task.name = %"bench-task",
+        task.id = id,
+        size.bytes = 64u64,
+        loc.file = file!(),
+        loc.line = line!(),
+        loc.col = column!(),
+    )
+}
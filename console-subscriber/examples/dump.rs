@@ -13,7 +13,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     eprintln!("CONNECTING: {}", target);
     let mut client = InstrumentClient::connect(target).await?;
 
-    let request = tonic::Request::new(InstrumentRequest {});
+    let request = tonic::Request::new(InstrumentRequest::default());
     let mut stream = client.watch_updates(request).await?.into_inner();
 
     let mut i: usize = 0;
@@ -0,0 +1,106 @@
+//! Stretches the publish interval while the aggregator is falling behind, and
+//! restores it once the load drops.
+
+use std::{ops::RangeInclusive, sync::atomic::Ordering::*, time::Duration};
+
+use super::Aggregator;
+use crate::EventKind;
+
+/// How many times the event buffer must near capacity between two publish
+/// ticks before the publish interval is stretched.
+const PRESSURE_THRESHOLD: usize = 2;
+
+/// The maximum number of resource poll ops sent in a single update while the
+/// publish interval is stretched. Older poll ops beyond this are counted as
+/// dropped poll op events.
+const MAX_POLL_OPS_UNDER_LOAD: usize = 1024;
+
+/// State for adjusting the publish interval under load.
+pub(super) struct AdaptivePublish {
+    /// The publish interval used when the aggregator is keeping up.
+    normal: Duration,
+    /// The longest the publish interval may be stretched to.
+    max: Duration,
+    /// How many times the event buffer neared capacity since the last
+    /// publish tick.
+    pressure: usize,
+}
+
+impl AdaptivePublish {
+    pub(super) fn new(publish_interval: Duration, bounds: RangeInclusive<Duration>) -> Self {
+        let (min, max) = bounds.into_inner();
+        let max = max.max(min);
+        Self {
+            normal: publish_interval.clamp(min, max),
+            max,
+            pressure: 0,
+        }
+    }
+
+    /// Returns the interval to publish at when the aggregator is keeping up.
+    pub(super) fn normal(&self) -> Duration {
+        self.normal
+    }
+
+    /// Records that the event buffer neared capacity.
+    pub(super) fn record_pressure(&mut self) {
+        self.pressure += 1;
+    }
+
+    /// Returns the publish interval to use after `current`, based on the
+    /// pressure recorded since the last tick, and starts counting again.
+    fn next_interval(&mut self, current: Duration) -> Duration {
+        let pressure = std::mem::take(&mut self.pressure);
+        if pressure >= PRESSURE_THRESHOLD {
+            current
+                .checked_mul(2)
+                .map_or(self.max, |doubled| doubled.min(self.max))
+        } else if pressure == 0 {
+            (current / 2).max(self.normal)
+        } else {
+            current
+        }
+    }
+
+    fn is_stretched(&self, current: Duration) -> bool {
+        current > self.normal
+    }
+}
+
+impl Aggregator {
+    /// Adjusts the publish interval to the load since the last publish tick.
+    ///
+    /// Returns the new interval, if it changed.
+    pub(super) fn adapt_publish_interval(&mut self) -> Option<Duration> {
+        let adaptive = self.adaptive_publish.as_mut()?;
+        let next = adaptive.next_interval(self.publish_interval);
+        if next == self.publish_interval {
+            return None;
+        }
+
+        tracing::debug!(
+            from = ?self.publish_interval,
+            to = ?next,
+            "adjusting publish interval",
+        );
+        self.publish_interval = next;
+        Some(next)
+    }
+
+    /// While the publish interval is stretched, drops the oldest poll ops
+    /// beyond the per-update maximum, so that updates stay small.
+    pub(super) fn shed_poll_ops(&mut self) {
+        let Some(ref adaptive) = self.adaptive_publish else {
+            return;
+        };
+        if !adaptive.is_stretched(self.publish_interval) {
+            return;
+        }
+
+        let excess = self.poll_ops.len().saturating_sub(MAX_POLL_OPS_UNDER_LOAD);
+        if excess > 0 {
+            self.poll_ops.drain(..excess);
+            self.shared.dropped_by_kind[EventKind::PollOp as usize].fetch_add(excess, Release);
+        }
+    }
+}
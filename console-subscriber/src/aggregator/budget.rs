@@ -0,0 +1,196 @@
+//! Keeping updates small enough for clients to decode.
+//!
+//! Clients refuse messages larger than a few megabytes, so a pathological
+//! burst of activity could otherwise produce an update that the client can't
+//! read at all. Instead, an update that would be too large is truncated
+//! before it's sent, and flagged as such. What's left out of it, apart from
+//! the parts a lagging client also does without, is sent with the stream's
+//! next update, so the client catches up once the burst is over.
+
+use std::collections::{HashMap, HashSet};
+
+use console_api as proto;
+use prost::Message;
+
+use super::lagging::slim;
+
+/// A generous estimate of how many bytes an entity or its stats take up in an
+/// update on top of their encoded message: the field's tag and the message's
+/// length, plus the key, for stats.
+const ENTRY_OVERHEAD: usize = 16;
+
+/// What's deferred to make an update fit, in the order it's deferred in.
+#[derive(Clone, Copy)]
+enum Defer {
+    /// The stats of tasks, resources, and async ops which aren't new in the
+    /// update, so the client can already show them.
+    KnownStats,
+    /// New tasks, resources, and async ops, along with their stats, newest
+    /// first.
+    NewEntities,
+}
+
+/// Something which has stats in an update.
+trait Entity: Message + Sized {
+    fn id(&self) -> Option<u64>;
+}
+
+/// Truncates `update` so that it's encoded in fewer than `max_size` bytes, if
+/// it's any larger.
+///
+/// Returns `None` if the update already fits. Otherwise, this returns the
+/// changes left out of `update` which should be sent later. New poll ops and
+/// resource attribute histories are dropped rather than deferred.
+pub(super) fn truncate(
+    update: &mut proto::instrument::Update,
+    max_size: usize,
+) -> Option<proto::instrument::Update> {
+    if update.encoded_len() < max_size {
+        return None;
+    }
+    slim(update);
+
+    let mut deferred = proto::instrument::Update::default();
+    for defer in [Defer::KnownStats, Defer::NewEntities] {
+        loop {
+            let size = update.encoded_len();
+            if size < max_size {
+                return Some(pruned(deferred));
+            }
+            // The sizes of what's deferred are only estimated, so this may
+            // take more than one pass.
+            let mut excess = size - max_size + 1;
+            let mut moved = false;
+            if let Some(ops) = update.async_op_update.as_mut() {
+                let deferred = deferred
+                    .async_op_update
+                    .get_or_insert_with(Default::default);
+                moved |= defer.apply(
+                    (&mut ops.new_async_ops, &mut ops.stats_update),
+                    (&mut deferred.new_async_ops, &mut deferred.stats_update),
+                    &mut excess,
+                );
+            }
+            if let Some(resources) = update.resource_update.as_mut() {
+                let deferred = deferred
+                    .resource_update
+                    .get_or_insert_with(Default::default);
+                moved |= defer.apply(
+                    (&mut resources.new_resources, &mut resources.stats_update),
+                    (&mut deferred.new_resources, &mut deferred.stats_update),
+                    &mut excess,
+                );
+            }
+            if let Some(tasks) = update.task_update.as_mut() {
+                let deferred = deferred.task_update.get_or_insert_with(Default::default);
+                moved |= defer.apply(
+                    (&mut tasks.new_tasks, &mut tasks.stats_update),
+                    (&mut deferred.new_tasks, &mut deferred.stats_update),
+                    &mut excess,
+                );
+            }
+            if !moved {
+                break;
+            }
+        }
+    }
+
+    // Only the metadata and the runtime's and subscriber's stats are left,
+    // which can't be deferred.
+    tracing::warn!(
+        message_size = update.encoded_len(),
+        max_message_size = max_size,
+        "update is too large to send, even with everything possible left out"
+    );
+    Some(pruned(deferred))
+}
+
+impl Defer {
+    /// Moves what this defers from an update's new entities and stats to
+    /// the deferred ones, until roughly `excess` bytes have been moved.
+    ///
+    /// Returns `false` if there was nothing to move.
+    fn apply<E: Entity, S: Message>(
+        self,
+        (new, stats): (&mut Vec<E>, &mut HashMap<u64, S>),
+        (deferred_new, deferred_stats): (&mut Vec<E>, &mut HashMap<u64, S>),
+        excess: &mut usize,
+    ) -> bool {
+        let mut moved = false;
+        match self {
+            Defer::KnownStats => {
+                let new_ids = new.iter().filter_map(Entity::id).collect::<HashSet<_>>();
+                let known = stats
+                    .keys()
+                    .copied()
+                    .filter(|id| !new_ids.contains(id))
+                    .collect::<Vec<_>>();
+                for id in known {
+                    if *excess == 0 {
+                        break;
+                    }
+                    if let Some(entry) = stats.remove(&id) {
+                        *excess = excess.saturating_sub(entry.encoded_len() + ENTRY_OVERHEAD);
+                        deferred_stats.insert(id, entry);
+                        moved = true;
+                    }
+                }
+            }
+            Defer::NewEntities => {
+                let mut split = new.len();
+                while split > 0 && *excess > 0 {
+                    split -= 1;
+                    let entity = &new[split];
+                    let stats_len = entity
+                        .id()
+                        .and_then(|id| stats.get(&id))
+                        .map_or(0, |entry| entry.encoded_len() + ENTRY_OVERHEAD);
+                    *excess =
+                        excess.saturating_sub(entity.encoded_len() + ENTRY_OVERHEAD + stats_len);
+                    moved = true;
+                }
+                let newest = new.split_off(split);
+                for id in newest.iter().filter_map(Entity::id) {
+                    if let Some(entry) = stats.remove(&id) {
+                        deferred_stats.insert(id, entry);
+                    }
+                }
+                // Keep the deferred entities in the order they were created.
+                deferred_new.splice(0..0, newest);
+            }
+        }
+        moved
+    }
+}
+
+/// Returns `update` without the parts that are empty.
+fn pruned(mut update: proto::instrument::Update) -> proto::instrument::Update {
+    update.task_update = update
+        .task_update
+        .filter(|tasks| !tasks.new_tasks.is_empty() || !tasks.stats_update.is_empty());
+    update.resource_update = update.resource_update.filter(|resources| {
+        !resources.new_resources.is_empty() || !resources.stats_update.is_empty()
+    });
+    update.async_op_update = update
+        .async_op_update
+        .filter(|ops| !ops.new_async_ops.is_empty() || !ops.stats_update.is_empty());
+    update
+}
+
+impl Entity for proto::tasks::Task {
+    fn id(&self) -> Option<u64> {
+        self.id.map(|id| id.id)
+    }
+}
+
+impl Entity for proto::resources::Resource {
+    fn id(&self) -> Option<u64> {
+        self.id.map(|id| id.id)
+    }
+}
+
+impl Entity for proto::async_ops::AsyncOp {
+    fn id(&self) -> Option<u64> {
+        self.id.map(|id| id.id)
+    }
+}
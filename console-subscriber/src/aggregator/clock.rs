@@ -0,0 +1,154 @@
+//! The aggregator's source of the current time.
+//!
+//! The layer and aggregator normally read the system's monotonic clock, and
+//! the aggregator publishes on a Tokio interval. Tests can instead give them a
+//! [`ManualClock`], which only moves forward when it is advanced, so that
+//! publish ticks and the expiry of retained data happen exactly when the test
+//! says they should.
+
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "test-support")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "test-support")]
+use tokio::sync::Notify;
+
+/// Where the layer and aggregator get the current time from.
+#[derive(Clone, Debug, Default)]
+pub(crate) enum Clock {
+    /// The system's monotonic clock.
+    #[default]
+    System,
+    /// A clock that is moved forward by a test.
+    #[cfg(feature = "test-support")]
+    Manual(ManualClock),
+}
+
+/// Ticks at a fixed period, as measured by a [`Clock`].
+pub(crate) enum Ticker {
+    System(tokio::time::Interval),
+    #[cfg(feature = "test-support")]
+    Manual {
+        clock: ManualClock,
+        next: Instant,
+        period: Duration,
+    },
+}
+
+/// A clock which only moves forward when it's [advanced], for driving the
+/// console subscriber from tests.
+///
+/// Everything the layer records happens at the time the clock reads when it's
+/// recorded, however long it really took, and the aggregator only publishes
+/// when the clock is advanced.
+///
+/// [advanced]: ManualClock::advance
+#[cfg(feature = "test-support")]
+#[derive(Clone, Debug)]
+pub struct ManualClock {
+    inner: Arc<ManualClockInner>,
+}
+
+#[cfg(feature = "test-support")]
+#[derive(Debug)]
+struct ManualClockInner {
+    /// The time the clock read when it was created.
+    start: Instant,
+    /// How far the clock has been advanced since it was created.
+    elapsed: Mutex<Duration>,
+    advanced: Notify,
+}
+
+// === impl Clock ===
+
+impl Clock {
+    pub(crate) fn now(&self) -> Instant {
+        match self {
+            Self::System => Instant::now(),
+            #[cfg(feature = "test-support")]
+            Self::Manual(clock) => clock.now(),
+        }
+    }
+
+    /// Returns a ticker which first ticks at `start`, then every `period`.
+    pub(crate) fn interval_at(&self, start: Instant, period: Duration) -> Ticker {
+        match self {
+            Self::System => Ticker::System(tokio::time::interval_at(start.into(), period)),
+            #[cfg(feature = "test-support")]
+            Self::Manual(clock) => Ticker::Manual {
+                clock: clock.clone(),
+                next: start,
+                period,
+            },
+        }
+    }
+}
+
+// === impl Ticker ===
+
+impl Ticker {
+    /// Waits until the next tick.
+    pub(crate) async fn tick(&mut self) {
+        match self {
+            Self::System(interval) => {
+                interval.tick().await;
+            }
+            #[cfg(feature = "test-support")]
+            Self::Manual {
+                clock,
+                next,
+                period,
+            } => loop {
+                // Register for the notification before reading the time, so
+                // that an advance in between isn't missed.
+                let advanced = clock.inner.advanced.notified();
+                let now = clock.now();
+                if now >= *next {
+                    // However far the clock was advanced, it only ticks once.
+                    *next = now + *period;
+                    return;
+                }
+                advanced.await;
+            },
+        }
+    }
+}
+
+// === impl ManualClock ===
+
+#[cfg(feature = "test-support")]
+impl ManualClock {
+    /// Returns a new clock, which reads the time it was created at until it's
+    /// advanced.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(ManualClockInner {
+                start: Instant::now(),
+                elapsed: Mutex::new(Duration::ZERO),
+                advanced: Notify::new(),
+            }),
+        }
+    }
+
+    /// Moves the clock forward by `by`.
+    pub fn advance(&self, by: Duration) {
+        *self.inner.elapsed.lock().unwrap() += by;
+        self.inner.advanced.notify_waiters();
+    }
+
+    /// Returns how far the clock has been advanced since it was created.
+    pub fn elapsed(&self) -> Duration {
+        *self.inner.elapsed.lock().unwrap()
+    }
+
+    fn now(&self) -> Instant {
+        self.inner.start + self.elapsed()
+    }
+}
+
+#[cfg(feature = "test-support")]
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
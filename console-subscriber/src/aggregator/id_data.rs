@@ -1,4 +1,4 @@
-use super::{shrink::ShrinkMap, Id, ToProto};
+use super::{retention::PausedTime, shrink::ShrinkMap, Id, ToProto};
 use crate::stats::{DroppedAt, TimeAnchor, Unsent};
 use std::collections::HashMap;
 use std::time::{Duration, Instant};
@@ -42,6 +42,10 @@ impl<T: Unsent> IdData<T> {
         self.data.iter()
     }
 
+    pub(crate) fn len(&self) -> usize {
+        self.data.len()
+    }
+
     pub(crate) fn get(&self, id: &Id) -> Option<&T> {
         self.data.get(id)
     }
@@ -86,6 +90,7 @@ impl<T: Unsent> IdData<T> {
     pub(crate) fn drop_closed<R: DroppedAt + Unsent>(
         &mut self,
         stats: &mut IdData<R>,
+        paused_time: &PausedTime,
         now: Instant,
         retention: Duration,
         has_watchers: bool,
@@ -102,11 +107,14 @@ impl<T: Unsent> IdData<T> {
 
         stats.data.retain_and_shrink(|id, stats| {
             if let Some(dropped_at) = stats.dropped_at() {
-                let dropped_for = now.checked_duration_since(dropped_at).unwrap_or_default();
+                // Time spent with every stream paused doesn't count, so that
+                // resuming doesn't evict what was dropped before pausing.
+                let dropped_for = paused_time.unpaused_since(dropped_at, now, retention);
                 let dirty = stats.is_unsent();
                 let should_retain =
-                        // if there are any clients watching, retain all dirty tasks regardless of age
-                        (dirty && has_watchers)
+                        // if there are any clients watching, retain all dirty tasks regardless of age,
+                        // unless they're all paused, in which case nothing is sent until they resume
+                        (dirty && has_watchers && !paused_time.is_paused())
                         || dropped_for <= retention;
                 tracing::trace!(
                     stats.id = ?id,
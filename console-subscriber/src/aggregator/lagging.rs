@@ -0,0 +1,117 @@
+//! Streams whose clients don't keep up.
+//!
+//! When a client doesn't read its updates as fast as they're published, its
+//! stream's buffer fills up and the aggregator can't send it any more.
+//! Rather than disconnecting the client straight away, the stream is marked
+//! as lagging: the update that couldn't be sent is kept to be merged into the
+//! next one, and for a while the stream's updates leave out their bulkier,
+//! non-essential parts and are flagged as lagging, so the client can tell the
+//! user. The client is only disconnected once too many updates in a row
+//! couldn't be sent.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use console_api as proto;
+
+use super::{budget, UpdateWatcher};
+use crate::unshare;
+use prost::Message;
+
+/// How long a stream's updates are slimmed down for after one couldn't be
+/// sent.
+const LAGGING_FOR: Duration = Duration::from_secs(10);
+
+impl UpdateWatcher {
+    /// Sends `update` on the stream, numbered as the stream's next update.
+    ///
+    /// If the update would be `max_size` bytes or larger, it's truncated, and
+    /// what's left out of it is kept for the stream's next update. The update
+    /// is only copied if it's changed for this stream.
+    ///
+    /// Returns `false` if the stream should be dropped, because the client
+    /// went away or `lag_limit` updates in a row couldn't be sent to it.
+    pub(super) fn send(
+        &mut self,
+        update: Arc<proto::instrument::Update>,
+        now: Instant,
+        lag_limit: u32,
+        max_size: usize,
+    ) -> bool {
+        let lagging = self.is_lagging(now);
+        let mut deferred = None;
+        let update =
+            if lagging || update.lagging || update.truncated || update.encoded_len() >= max_size {
+                let mut update = unshare(update);
+                update.lagging = lagging;
+                if update.lagging {
+                    slim(&mut update);
+                }
+                deferred = budget::truncate(&mut update, max_size);
+                update.truncated = deferred.is_some();
+                if update.truncated {
+                    tracing::debug!(
+                        stream_id = self.stream_id,
+                        max_size,
+                        "update is too large, leaving some of it for the next one"
+                    );
+                }
+                Arc::new(update)
+            } else {
+                update
+            };
+
+        if self.watch.send_shared(update.clone(), self.sequence + 1) {
+            self.sequence += 1;
+            self.failed_sends = 0;
+            if let Some(deferred) = deferred {
+                self.queue(deferred);
+            }
+            return true;
+        }
+        if self.watch.0.is_closed() {
+            return false;
+        }
+
+        self.failed_sends += 1;
+        if self.failed_sends >= lag_limit {
+            tracing::warn!(
+                stream_id = self.stream_id,
+                failed_sends = self.failed_sends,
+                "client isn't keeping up with updates, disconnecting it"
+            );
+            return false;
+        }
+        tracing::debug!(
+            stream_id = self.stream_id,
+            failed_sends = self.failed_sends,
+            "client is lagging behind, slimming down its updates"
+        );
+        self.lagging_until = Some(now + LAGGING_FOR);
+        self.queue(unshare(update));
+        if let Some(deferred) = deferred {
+            self.queue(deferred);
+        }
+        true
+    }
+
+    fn is_lagging(&self, now: Instant) -> bool {
+        self.lagging_until.is_some_and(|until| now < until)
+    }
+}
+
+/// Removes the parts of `update` that a lagging client can do without.
+///
+/// These are either recorded again in later updates, or only feed views
+/// which can cope with gaps, so leaving them out doesn't leave the client's
+/// state inconsistent.
+pub(super) fn slim(update: &mut proto::instrument::Update) {
+    if let Some(resources) = update.resource_update.as_mut() {
+        resources.new_poll_ops.clear();
+        for stats in resources.stats_update.values_mut() {
+            stats.attribute_history.clear();
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! Metrics describing the aggregator's own memory footprint and health, so
+//! that users can diagnose the overhead of the instrumentation in production.
+
+use std::{
+    mem,
+    sync::atomic::Ordering::AcqRel,
+    time::{Duration, Instant},
+};
+
+use console_api as proto;
+
+use super::Aggregator;
+use crate::EventKind;
+
+/// How often the aggregator logs its own metrics at the `DEBUG` level.
+const SELF_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+impl Aggregator {
+    /// Returns the aggregator's current metrics.
+    pub(super) fn self_metrics(&self) -> proto::instrument::SelfMetrics {
+        let histogram_bytes = self
+            .task_stats
+            .all()
+            .map(|(_, stats)| stats.histogram_bytes())
+            .sum::<usize>();
+        // If the layer has been dropped, there's nothing left to buffer.
+        let event_buffer_len = self.events_tx.len();
+
+        proto::instrument::SelfMetrics {
+            tasks: self.tasks.len() as u64,
+            resources: self.resources.len() as u64,
+            async_ops: self.async_ops.len() as u64,
+            metadata: self.all_metadata.len() as u64,
+            poll_ops: self.poll_ops.len() as u64,
+            histogram_bytes: histogram_bytes as u64,
+            event_buffer_len: event_buffer_len as u64,
+            event_buffer_capacity: self.event_buffer_capacity as u64,
+            update_watchers: self.watchers.len() as u64,
+            details_watchers: self.details_watchers.values().map(Vec::len).sum::<usize>() as u64,
+        }
+    }
+
+    /// Returns the subscriber's health since the last update was published,
+    /// and starts measuring it afresh.
+    pub(super) fn subscriber_stats(&mut self) -> proto::instrument::SubscriberStats {
+        let dropped =
+            |kind: EventKind| self.shared.dropped_by_kind[kind as usize].swap(0, AcqRel) as u64;
+        let dropped_events = proto::instrument::DroppedEvents {
+            metadata: dropped(EventKind::Metadata),
+            spawns: dropped(EventKind::Spawn),
+            resources: dropped(EventKind::Resource),
+            async_ops: dropped(EventKind::AsyncResourceOp),
+            poll_ops: dropped(EventKind::PollOp),
+        };
+        proto::instrument::SubscriberStats {
+            max_event_buffer_len: mem::take(&mut self.max_buffered_events) as u64,
+            event_buffer_capacity: self.event_buffer_capacity as u64,
+            flushes: mem::take(&mut self.flushes),
+            dropped_events: Some(dropped_events),
+            max_aggregation_time: mem::take(&mut self.max_pass_time).try_into().ok(),
+        }
+    }
+
+    /// Logs the aggregator's metrics, if it hasn't done so recently.
+    pub(super) fn maybe_report_self(&mut self, now: Instant) {
+        if now.saturating_duration_since(self.last_self_report) < SELF_REPORT_INTERVAL {
+            return;
+        }
+        self.last_self_report = now;
+
+        let metrics = self.self_metrics();
+        tracing::debug!(
+            metrics.tasks,
+            metrics.resources,
+            metrics.async_ops,
+            metrics.metadata,
+            metrics.poll_ops,
+            metrics.histogram_bytes,
+            metrics.event_buffer_len,
+            metrics.event_buffer_capacity,
+            metrics.update_watchers,
+            metrics.details_watchers,
+            "console subscriber self-report",
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use std::{
     sync::{
-        atomic::{AtomicBool, Ordering::*},
+        atomic::{AtomicBool, AtomicUsize, Ordering::*},
         Arc,
     },
     time::{Duration, Instant},
@@ -12,20 +12,49 @@ use proto::resources::resource;
 use tokio::sync::{mpsc, Notify};
 use tracing_core::{span::Id, Metadata};
 
-use super::{Command, Event, Shared, Watch};
+use super::{unshare, Command, Event, Shared, Watch};
 use crate::{
+    shards,
+    shutdown::Shutdown,
+    spawn_backtrace,
     stats::{self, Unsent},
+    visitors::{TaskFields, TaskLocation},
     ToProto, WatchRequest,
 };
 
+mod adaptive;
+mod budget;
+mod clock;
 mod id_data;
+mod lagging;
+mod metrics;
+#[cfg(feature = "otel")]
+mod otel;
+use self::adaptive::AdaptivePublish;
+pub(crate) use self::clock::Clock;
+#[cfg(feature = "test-support")]
+pub use self::clock::ManualClock;
+mod retention;
 mod shrink;
+mod snapshot;
+mod throttle;
+mod waiting;
 use self::id_data::{IdData, Include};
+use self::retention::PausedTime;
 use self::shrink::{ShrinkMap, ShrinkVec};
+pub(crate) use self::throttle::MIN_UPDATE_INTERVAL;
+use self::waiting::WaitingOn;
 
 /// Should match tonic's (private) codec::DEFAULT_MAX_RECV_MESSAGE_SIZE
 const MAX_MESSAGE_SIZE: usize = 4 * 1024 * 1024;
 
+/// The most metadata sent in one update to a new or resumed stream.
+///
+/// Streams are sent all of the metadata registered so far when they start,
+/// which for targets with many callsites is split across several updates, so
+/// that it is never all converted to protobuf at once.
+const METADATA_CHUNK_LEN: usize = 1024;
+
 /// Aggregates instrumentation traces and prepares state for the instrument
 /// server.
 ///
@@ -34,39 +63,78 @@ const MAX_MESSAGE_SIZE: usize = 4 * 1024 * 1024;
 /// client.
 pub struct Aggregator {
     /// Channel of incoming events emitted by `TaskLayer`s.
-    events: mpsc::Receiver<Event>,
+    events: shards::Receiver<Event>,
+
+    /// The sending side of `events`, used to measure how many events are
+    /// waiting to be aggregated.
+    ///
+    /// This is a weak sender, so that the channel still closes when the
+    /// layer is dropped.
+    events_tx: shards::WeakSender<Event>,
+
+    /// The maximum number of events that `events` can hold.
+    event_buffer_capacity: usize,
+
+    /// The number of updates in a row a stream may fail to be sent before
+    /// it's dropped.
+    client_lag_limit: u32,
 
     /// New incoming RPCs.
     rpcs: mpsc::Receiver<Command>,
 
     /// The interval at which new data updates are pushed to clients.
+    ///
+    /// If adaptive publishing is enabled, this is the current interval.
     publish_interval: Duration,
 
+    /// Adjusts `publish_interval` under load, if enabled.
+    adaptive_publish: Option<AdaptivePublish>,
+
     /// How long to keep task data after a task has completed.
     retention: Duration,
 
+    /// The largest update that may be sent to a stream.
+    ///
+    /// This is [`MAX_MESSAGE_SIZE`], except in tests.
+    pub(crate) max_message_size: usize,
+
+    /// Where the current time is read from.
+    clock: Clock,
+
+    /// When every update stream was paused, which doesn't count towards
+    /// `retention`.
+    paused_time: PausedTime,
+
     /// Shared state, including a `Notify` that triggers a flush when the event
     /// buffer is approaching capacity.
     shared: Arc<Shared>,
 
+    /// Signals that the aggregator should publish a final update and stop.
+    shutdown: Arc<Shutdown>,
+
     /// Currently active RPCs streaming state events.
-    state_watchers: ShrinkVec<Watch<proto::instrument::State>>,
+    state_watchers: ShrinkVec<StateWatcher>,
 
     /// Currently active RPCs streaming task events.
-    watchers: ShrinkVec<Watch<proto::instrument::Update>>,
+    watchers: ShrinkVec<UpdateWatcher>,
+
+    /// The ID assigned to the most recently started update stream.
+    last_stream_id: u64,
 
     /// Currently active RPCs streaming task details events, by task ID.
-    details_watchers: ShrinkMap<Id, Vec<Watch<proto::tasks::TaskDetails>>>,
+    details_watchers: ShrinkMap<Id, Vec<DetailsWatcher>>,
 
     /// *All* metadata for task spans and user-defined spans that we care about.
     ///
-    /// This is sent to new clients as part of the initial state.
-    all_metadata: ShrinkVec<proto::register_metadata::NewMetadata>,
+    /// This is sent to new clients as part of the initial state. Callsite
+    /// metadata is `'static`, so only references to it are kept, and it is
+    /// converted to protobuf as it is sent.
+    all_metadata: Vec<&'static Metadata<'static>>,
 
     /// *New* metadata that was registered since the last state update.
     ///
     /// This is emptied on every state update.
-    new_metadata: Vec<proto::register_metadata::NewMetadata>,
+    new_metadata: Vec<&'static Metadata<'static>>,
 
     /// Map of task IDs to task static data.
     tasks: IdData<Task>,
@@ -91,12 +159,100 @@ pub struct Aggregator {
     /// This is emptied on every state update.
     poll_ops: Vec<proto::resources::PollOp>,
 
-    /// The time "state" of the aggregator, such as paused or live.
-    temporality: proto::instrument::Temporality,
+    /// The async ops each task is currently waiting on.
+    waiting_on: WaitingOn,
 
     /// Used to anchor monotonic timestamps to a base `SystemTime`, to produce a
     /// timestamp that can be sent over the wire.
     base_time: stats::TimeAnchor,
+
+    /// The number of worker threads the instrumented runtime is assumed to
+    /// have, used to estimate runtime saturation.
+    worker_threads: usize,
+
+    /// When runtime stats were last published.
+    ///
+    /// The busy time reported in each update is measured since this instant.
+    runtime_stats_since: Instant,
+
+    /// When the aggregator last logged its own metrics.
+    last_self_report: Instant,
+
+    /// The most events found waiting in the event buffer when draining it,
+    /// since the last update was published.
+    max_buffered_events: usize,
+
+    /// The number of times the layer asked for the event buffer to be
+    /// drained, since the last update was published.
+    flushes: u64,
+
+    /// The longest a single pass of the aggregator's loop has taken, since
+    /// the last update was published.
+    max_pass_time: Duration,
+
+    /// Instruments for exporting stats to OpenTelemetry, if enabled.
+    #[cfg(feature = "otel")]
+    otel: Option<otel::OtelMetrics>,
+}
+
+/// An update stream, along with its own time "state".
+///
+/// Each client pauses and resumes its own stream, so that one console pausing
+/// doesn't stop updates to any others watching the same process.
+struct UpdateWatcher {
+    stream_id: u64,
+    temporality: proto::instrument::Temporality,
+    /// The kinds of data this stream is sent.
+    domains: Domains,
+    /// How often the client asked to be sent updates, if it chose.
+    interval: Option<Duration>,
+    /// When the stream is next due an update.
+    next_update: Instant,
+    /// The updates published since the stream was last sent one, merged.
+    pending: Option<proto::instrument::Update>,
+    /// The number of updates sent on the stream.
+    sequence: u64,
+    /// The number of updates in a row that couldn't be sent because the
+    /// stream's buffer was full.
+    failed_sends: u32,
+    /// Until when the stream's updates are slimmed down, after the client
+    /// last fell behind.
+    lagging_until: Option<Instant>,
+    watch: Watch<StreamUpdate>,
+}
+
+/// An update sent on an update stream.
+///
+/// Streams are numbered separately, so the update itself is shared between
+/// every stream it's sent to, and only numbered as a stream takes it.
+pub struct StreamUpdate {
+    update: Arc<proto::instrument::Update>,
+    sequence: u64,
+}
+
+/// The kinds of data an update stream describes.
+///
+/// Streams started with `WatchUpdates` describe everything, while the
+/// `WatchTasks`, `WatchResources` and `WatchAsyncOps` streams only describe
+/// one kind. The aggregator only assembles the parts of an update which at
+/// least one stream needs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Domains {
+    tasks: bool,
+    resources: bool,
+    async_ops: bool,
+}
+
+/// A task details stream, along with the histograms its client asked for.
+struct DetailsWatcher {
+    histograms: proto::instrument::HistogramSelection,
+    watch: Watch<proto::tasks::TaskDetails>,
+}
+
+/// A state stream, along with the ID of the update stream it reports on.
+struct StateWatcher {
+    stream_id: u64,
+    watch: Watch<proto::instrument::State>,
 }
 
 #[derive(Debug, Default)]
@@ -122,8 +278,14 @@ struct Task {
     id: Id,
     is_dirty: AtomicBool,
     metadata: &'static Metadata<'static>,
-    fields: Vec<proto::Field>,
-    location: Option<proto::Location>,
+    fields: TaskFields,
+    location: Option<TaskLocation>,
+    kind: proto::tasks::task::Kind,
+    runtime: Option<Arc<str>>,
+    group: Option<String>,
+    size_bytes: Option<u64>,
+    original_size_bytes: Option<u64>,
+    spawn_backtrace: Vec<String>,
 }
 
 struct AsyncOp {
@@ -137,21 +299,38 @@ struct AsyncOp {
 
 impl Aggregator {
     pub(crate) fn new(
-        events: mpsc::Receiver<Event>,
+        events: shards::Receiver<Event>,
+        events_tx: shards::WeakSender<Event>,
         rpcs: mpsc::Receiver<Command>,
         builder: &crate::Builder,
         shared: Arc<crate::Shared>,
+        shutdown: Arc<Shutdown>,
         base_time: stats::TimeAnchor,
     ) -> Self {
+        let adaptive_publish = builder
+            .adaptive_publish
+            .clone()
+            .map(|bounds| AdaptivePublish::new(builder.publish_interval, bounds));
         Self {
             shared,
+            shutdown,
             rpcs,
-            publish_interval: builder.publish_interval,
+            publish_interval: adaptive_publish
+                .as_ref()
+                .map_or(builder.publish_interval, AdaptivePublish::normal),
+            adaptive_publish,
             retention: builder.retention,
+            max_message_size: MAX_MESSAGE_SIZE,
+            clock: builder.clock.clone(),
+            paused_time: PausedTime::default(),
             events,
+            events_tx,
+            event_buffer_capacity: builder.event_buffer_capacity,
+            client_lag_limit: builder.client_lag_limit,
             watchers: Default::default(),
             details_watchers: Default::default(),
             state_watchers: Default::default(),
+            last_stream_id: 0,
             all_metadata: Default::default(),
             new_metadata: Default::default(),
             tasks: IdData::default(),
@@ -161,8 +340,20 @@ impl Aggregator {
             async_ops: IdData::default(),
             async_op_stats: IdData::default(),
             poll_ops: Default::default(),
-            temporality: proto::instrument::Temporality::Live,
+            waiting_on: WaitingOn::default(),
             base_time,
+            worker_threads: builder.worker_threads.unwrap_or_else(|| {
+                std::thread::available_parallelism()
+                    .map(usize::from)
+                    .unwrap_or(1)
+            }),
+            runtime_stats_since: builder.clock.now(),
+            last_self_report: builder.clock.now(),
+            max_buffered_events: 0,
+            flushes: 0,
+            max_pass_time: Duration::ZERO,
+            #[cfg(feature = "otel")]
+            otel: builder.otel_meter.as_ref().map(otel::OtelMetrics::new),
         }
     }
 
@@ -171,41 +362,65 @@ impl Aggregator {
     /// This method will start the aggregator loop and should run as long as
     /// the instrument server is running. If the instrument server stops,
     /// this future can be aborted.
+    ///
+    /// When the server's [`ShutdownHandle`] is triggered, the aggregator
+    /// publishes a final update to its clients and this future completes.
+    ///
+    /// [`ShutdownHandle`]: crate::ShutdownHandle
     pub async fn run(mut self) {
-        let mut publish = tokio::time::interval(self.publish_interval);
+        let mut tick = self.tick_interval();
+        let mut publish = self.clock.interval_at(self.clock.now(), tick);
+        let shutdown = self.shutdown.clone();
         loop {
+            let mut ticked = false;
+            let mut shutting_down = false;
             let should_send = tokio::select! {
+                // the server is shutting down, send everything that's left
+                _ = shutdown.requested() => {
+                    tracing::debug!("shutdown requested; publishing final update");
+                    shutting_down = true;
+                    true
+                }
+
                 // if the flush interval elapses, flush data to the client
                 _ = publish.tick() => {
-                    match self.temporality {
-                        proto::instrument::Temporality::Live => true,
-                        proto::instrument::Temporality::Paused => false,
-                    }
+                    ticked = true;
+                    self.watchers.iter().any(UpdateWatcher::is_live)
                 }
 
                 // triggered when the event buffer is approaching capacity
                 _ = self.shared.flush.should_flush.notified() => {
                     tracing::debug!("approaching capacity; draining buffer");
+                    self.flushes += 1;
+                    if let Some(ref mut adaptive) = self.adaptive_publish {
+                        adaptive.record_pressure();
+                    }
                     false
                 }
 
                 // a new command from a client
                 cmd = self.rpcs.recv() => {
                     match cmd {
-                        Some(Command::Instrument(subscription)) => {
-                            self.add_instrument_subscription(subscription);
+                        Some(Command::Instrument { watch, domains, interval }) => {
+                            self.add_instrument_subscription(watch, domains, interval);
                         },
                         Some(Command::WatchTaskDetail(watch_request)) => {
                             self.add_task_detail_subscription(watch_request);
                         },
-                        Some(Command::WatchState(subscription)) => {
-                            self.add_state_subscription(subscription);
+                        Some(Command::WatchState { stream_id, watch }) => {
+                            self.add_state_subscription(stream_id, watch);
+                        }
+                        Some(Command::Pause(stream_id)) => {
+                            self.pause(stream_id);
+                        }
+                        Some(Command::Resume(stream_id)) => {
+                            self.resume(stream_id);
                         }
-                        Some(Command::Pause) => {
-                            self.temporality = proto::instrument::Temporality::Paused;
+                        Some(Command::DebugSelf(response)) => {
+                            let _ = response.send(self.self_metrics());
                         }
-                        Some(Command::Resume) => {
-                            self.temporality = proto::instrument::Temporality::Live;
+                        Some(Command::Snapshot(response)) => {
+                            let _ = response.send(self.snapshot());
                         }
                         None => {
                             tracing::debug!("rpc channel closed, terminating");
@@ -217,6 +432,18 @@ impl Aggregator {
                 }
             };
 
+            let pass_started = Instant::now();
+            if ticked {
+                self.adapt_publish_interval();
+            }
+            // The publish interval, or the streams' intervals, may have
+            // changed.
+            let next_tick = self.tick_interval();
+            if next_tick != tick {
+                tick = next_tick;
+                publish = self.clock.interval_at(self.clock.now() + tick, tick);
+            }
+
             // drain and aggregate buffered events.
             //
             // Note: we *don't* want to actually await the call to `recv` --- we
@@ -227,7 +454,8 @@ impl Aggregator {
             // channel is almost full.
             let mut drained = false;
             let mut counts = EventCounts::new();
-            while let Some(event) = recv_now_or_never(&mut self.events) {
+            self.max_buffered_events = self.max_buffered_events.max(self.events_tx.len());
+            while let Some(event) = self.events.recv_now_or_never() {
                 match event {
                     Some(event) => {
                         counts.update(&event);
@@ -252,6 +480,17 @@ impl Aggregator {
                 "event channel drain loop",
             );
 
+            if ticked {
+                // Each publish interval ends an interval of the tasks' wake
+                // histories, whether or not anyone is watching, so that the
+                // history is there once they are.
+                for (_, stats) in self.task_stats.all() {
+                    stats.end_wake_interval();
+                }
+                #[cfg(feature = "otel")]
+                self.export_otel();
+            }
+
             if !self.state_watchers.is_empty() {
                 self.publish_state();
             }
@@ -259,50 +498,165 @@ impl Aggregator {
             // flush data to clients, if there are any currently subscribed
             // watchers and we should send a new update.
             if !self.watchers.is_empty() && should_send {
-                self.publish();
+                self.publish(tick, shutting_down);
             }
             self.cleanup_closed();
+            self.maybe_report_self(self.clock.now());
             if drained {
                 self.shared.flush.has_flushed();
             }
+            self.max_pass_time = self.max_pass_time.max(pass_started.elapsed());
+            if shutting_down {
+                return;
+            }
         }
     }
 
     fn cleanup_closed(&mut self) {
         // drop all closed have that has completed *and* whose final data has already
         // been sent off.
-        let now = Instant::now();
-        let has_watchers = !self.watchers.is_empty();
-        self.tasks
-            .drop_closed(&mut self.task_stats, now, self.retention, has_watchers);
-        self.resources
-            .drop_closed(&mut self.resource_stats, now, self.retention, has_watchers);
-        self.async_ops
-            .drop_closed(&mut self.async_op_stats, now, self.retention, has_watchers);
-        if !has_watchers {
+        let now = self.clock.now();
+        self.update_paused_time(now);
+        let watched = self.watched_domains();
+        let paused_time = &self.paused_time;
+        self.tasks.drop_closed(
+            &mut self.task_stats,
+            paused_time,
+            now,
+            self.retention,
+            watched.tasks,
+        );
+        self.resources.drop_closed(
+            &mut self.resource_stats,
+            paused_time,
+            now,
+            self.retention,
+            watched.resources,
+        );
+        self.async_ops.drop_closed(
+            &mut self.async_op_stats,
+            paused_time,
+            now,
+            self.retention,
+            watched.async_ops,
+        );
+        self.paused_time.prune(now, self.retention);
+        if !watched.resources {
             self.poll_ops.clear();
         }
+        self.waiting_on
+            .retain_live(&self.task_stats, &self.resource_stats, &self.async_op_stats);
+    }
+
+    /// Records whether every update stream is paused, so that the time they
+    /// are all paused for doesn't count towards retention.
+    fn update_paused_time(&mut self, now: Instant) {
+        let paused =
+            !self.watchers.is_empty() && self.watchers.iter().all(|watcher| !watcher.is_live());
+        self.paused_time.set_paused(paused, now);
+    }
+
+    /// Returns the kinds of data that at least one update stream needs.
+    fn watched_domains(&self) -> Domains {
+        self.watchers
+            .iter()
+            .fold(Domains::NONE, |domains, watcher| {
+                domains.union(watcher.domains)
+            })
     }
 
     /// Add the task subscription to the watchers after sending the first update
-    fn add_instrument_subscription(&mut self, subscription: Watch<proto::instrument::Update>) {
-        tracing::debug!("new instrument subscription");
-        let now = Instant::now();
+    fn add_instrument_subscription(
+        &mut self,
+        subscription: Watch<StreamUpdate>,
+        domains: Domains,
+        interval: Option<Duration>,
+    ) {
+        self.last_stream_id += 1;
+        let stream_id = self.last_stream_id;
+        tracing::debug!(
+            stream_id,
+            ?domains,
+            ?interval,
+            "new instrument subscription"
+        );
+
+        match self.full_update(stream_id, domains) {
+            // Send the initial state
+            Some(update) => {
+                let sent = self
+                    .leading_metadata(stream_id, 0)
+                    .all(|update| subscription.send_update(update));
+                if !sent || !subscription.send_update(update) {
+                    // If sending the initial update fails, the subscription is already dead,
+                    // so don't add it to `watchers`.
+                    return;
+                }
+            }
+            // User will only get updates.
+            None => tracing::error!(
+                min_retention = ?self.publish_interval,
+                "Message too big. Start with smaller retention.",
+            ),
+        }
 
-        let update = loop {
+        self.watchers.push(UpdateWatcher {
+            stream_id,
+            temporality: proto::instrument::Temporality::Live,
+            domains,
+            interval,
+            next_update: self.clock.now() + interval.unwrap_or(self.publish_interval),
+            pending: None,
+            sequence: 0,
+            failed_sends: 0,
+            lagging_until: None,
+            watch: subscription,
+        });
+        self.update_tasks_watched();
+    }
+
+    /// Tells the layer whether any stream needs tasks, so that it only
+    /// records all of the fields of new tasks while one does.
+    fn update_tasks_watched(&self) {
+        self.shared
+            .tasks_watched
+            .store(self.watched_domains().tasks, Release);
+    }
+
+    /// Returns an update containing the complete current state of the given
+    /// `domains`, to be sent on the stream with the given ID.
+    ///
+    /// If the update would be too large to send, retention is reduced until
+    /// it fits; if it still doesn't fit at the minimum retention, this
+    /// returns `None`.
+    fn full_update(
+        &mut self,
+        stream_id: u64,
+        domains: Domains,
+    ) -> Option<proto::instrument::Update> {
+        let now = self.clock.now();
+        loop {
             let update = proto::instrument::Update {
-                task_update: Some(self.task_update(Include::All)),
-                resource_update: Some(self.resource_update(Include::All)),
-                async_op_update: Some(self.async_op_update(Include::All)),
+                task_update: domains.tasks.then(|| self.task_update(Include::All)),
+                resource_update: domains
+                    .resources
+                    .then(|| self.resource_update(Include::All)),
+                async_op_update: domains
+                    .async_ops
+                    .then(|| self.async_op_update(Include::All)),
                 now: Some(self.base_time.to_timestamp(now)),
-                new_metadata: Some(proto::RegisterMetadata {
-                    metadata: (*self.all_metadata).clone(),
-                }),
+                new_metadata: Some(register_metadata(self.trailing_metadata())),
+                runtime_stats: None,
+                subscriber_stats: None,
+                stream_id,
+                sequence: 0,
+                lagging: false,
+                truncated: false,
             };
             let message_size = update.encoded_len();
-            if message_size < MAX_MESSAGE_SIZE {
+            if message_size < self.max_message_size {
                 // normal case
-                break Some(update);
+                return Some(update);
             }
             // If the grpc message is bigger than tokio-console will accept, throw away the oldest
             // inactive data and try again
@@ -311,49 +665,129 @@ impl Aggregator {
             tracing::debug!(
                 retention = ?self.retention,
                 message_size,
-                max_message_size = MAX_MESSAGE_SIZE,
+                max_message_size = self.max_message_size,
                 "Message too big, reduced retention",
             );
 
             if self.retention <= self.publish_interval {
                 self.retention = self.publish_interval;
-                break None;
+                return None;
             }
-        };
+        }
+    }
 
-        match update {
-            // Send the initial state
-            Some(update) => {
-                if !subscription.update(&update) {
-                    // If sending the initial update fails, the subscription is already dead,
-                    // so don't add it to `watchers`.
-                    return;
-                }
+    /// Returns the updates which carry the metadata that doesn't fit in a
+    /// stream's first update, to be sent on the stream with the given ID and
+    /// number of updates sent so far before it.
+    fn leading_metadata(
+        &self,
+        stream_id: u64,
+        sequence: u64,
+    ) -> impl Iterator<Item = proto::instrument::Update> + '_ {
+        let leading =
+            &self.all_metadata[..self.all_metadata.len() - self.trailing_metadata().len()];
+        let now = Some(self.base_time.to_timestamp(self.clock.now()));
+        leading
+            .chunks(METADATA_CHUNK_LEN)
+            .map(move |chunk| proto::instrument::Update {
+                now,
+                new_metadata: Some(register_metadata(chunk)),
+                stream_id,
+                sequence,
+                ..Default::default()
+            })
+    }
+
+    /// Returns the metadata sent in a stream's first update, along with its
+    /// initial state.
+    fn trailing_metadata(&self) -> &[&'static Metadata<'static>] {
+        let split = self.all_metadata.len().saturating_sub(1) / METADATA_CHUNK_LEN;
+        &self.all_metadata[split * METADATA_CHUNK_LEN..]
+    }
+
+    /// Pauses the update stream with the given ID, or every update stream if
+    /// the ID is 0.
+    fn pause(&mut self, stream_id: u64) {
+        tracing::debug!(stream_id, "pausing update stream");
+        for watcher in self.watchers.iter_mut() {
+            if watcher.is(stream_id) {
+                watcher.temporality = proto::instrument::Temporality::Paused;
+                // Resumed streams are sent the complete state instead.
+                watcher.pending = None;
             }
-            // User will only get updates.
-            None => tracing::error!(
-                min_retention = ?self.publish_interval,
-                "Message too big. Start with smaller retention.",
-            ),
         }
+        self.update_paused_time(self.clock.now());
+    }
 
-        self.watchers.push(subscription);
+    /// Resumes the update stream with the given ID, or every update stream if
+    /// the ID is 0.
+    ///
+    /// The changes published while a stream was paused were never sent on
+    /// it, so resumed streams are sent the complete current state.
+    fn resume(&mut self, stream_id: u64) {
+        tracing::debug!(stream_id, "resuming update stream");
+        let resumed: Vec<usize> = self
+            .watchers
+            .iter()
+            .enumerate()
+            .filter(|(_, watcher)| watcher.is(stream_id) && !watcher.is_live())
+            .map(|(idx, _)| idx)
+            .collect();
+        if resumed.is_empty() {
+            return;
+        }
+
+        let domains = resumed.iter().fold(Domains::NONE, |domains, &idx| {
+            domains.union(self.watchers[idx].domains)
+        });
+        let update = self.full_update(0, domains);
+        let now = self.clock.now();
+        for idx in resumed {
+            let watcher = &mut self.watchers[idx];
+            watcher.temporality = proto::instrument::Temporality::Live;
+            watcher.sent(now, self.publish_interval);
+            if let Some(ref update) = update {
+                let watcher = &self.watchers[idx];
+                let mut update = watcher.domains.restrict(update);
+                update.stream_id = watcher.stream_id;
+                update.sequence = watcher.sequence;
+                // If this fails, the watcher will be dropped on the next
+                // publish.
+                let sent = self
+                    .leading_metadata(watcher.stream_id, watcher.sequence)
+                    .all(|update| watcher.watch.send_update(update));
+                if sent {
+                    watcher.watch.send_update(update);
+                }
+            }
+        }
+        self.update_paused_time(self.clock.now());
     }
 
     fn task_update(&mut self, include: Include) -> proto::tasks::TaskUpdate {
+        let mut stats_update = self.task_stats.as_proto(include, &self.base_time);
+        self.waiting_on
+            .add_to_task_stats(&mut stats_update, &self.base_time);
         proto::tasks::TaskUpdate {
             new_tasks: self.tasks.as_proto_list(include, &self.base_time),
-            stats_update: self.task_stats.as_proto(include, &self.base_time),
-            dropped_events: self.shared.dropped_tasks.swap(0, AcqRel) as u64,
+            stats_update,
+            dropped_events: take_dropped(&self.shared.dropped_tasks, include),
         }
     }
 
     fn resource_update(&mut self, include: Include) -> proto::resources::ResourceUpdate {
+        let mut stats_update = self.resource_stats.as_proto(include, &self.base_time);
+        self.waiting_on.add_to_resource_stats(&mut stats_update);
         proto::resources::ResourceUpdate {
             new_resources: self.resources.as_proto_list(include, &self.base_time),
-            stats_update: self.resource_stats.as_proto(include, &self.base_time),
-            new_poll_ops: std::mem::take(&mut self.poll_ops),
-            dropped_events: self.shared.dropped_resources.swap(0, AcqRel) as u64,
+            stats_update,
+            // Poll ops are only sent once, in the next update published to
+            // every live stream, so a full update doesn't take them.
+            new_poll_ops: match include {
+                Include::UpdatedOnly => std::mem::take(&mut self.poll_ops),
+                Include::All => Vec::new(),
+            },
+            dropped_events: take_dropped(&self.shared.dropped_resources, include),
         }
     }
 
@@ -361,7 +795,20 @@ impl Aggregator {
         proto::async_ops::AsyncOpUpdate {
             new_async_ops: self.async_ops.as_proto_list(include, &self.base_time),
             stats_update: self.async_op_stats.as_proto(include, &self.base_time),
-            dropped_events: self.shared.dropped_async_ops.swap(0, AcqRel) as u64,
+            dropped_events: take_dropped(&self.shared.dropped_async_ops, include),
+        }
+    }
+
+    /// Returns the runtime stats accumulated since they were last published,
+    /// and starts a new measurement interval.
+    fn runtime_stats(&mut self, now: Instant) -> proto::instrument::RuntimeStats {
+        let busy_time = Duration::from_nanos(self.shared.busy_nanos.swap(0, AcqRel));
+        let interval = now.saturating_duration_since(self.runtime_stats_since);
+        self.runtime_stats_since = now;
+        proto::instrument::RuntimeStats {
+            busy_time: busy_time.try_into().ok(),
+            interval: interval.try_into().ok(),
+            worker_threads: self.worker_threads as u64,
         }
     }
 
@@ -375,84 +822,217 @@ impl Aggregator {
             id,
             stream_sender,
             buffer,
+            histograms,
         } = watch_request;
         tracing::debug!(id = ?id, "new task details subscription");
         if let Some(stats) = self.task_stats.get(&id) {
             let (tx, rx) = mpsc::channel(buffer);
             let subscription = Watch(tx);
-            let now = Some(self.base_time.to_timestamp(Instant::now()));
+            let now = Some(self.base_time.to_timestamp(self.clock.now()));
             // Send back the stream receiver.
             // Then send the initial state --- if this fails, the subscription is already dead.
             if stream_sender.send(rx).is_ok()
-                && subscription.update(&proto::tasks::TaskDetails {
+                && subscription.update(&Arc::new(proto::tasks::TaskDetails {
                     task_id: Some(id.clone().into()),
                     now,
-                    poll_times_histogram: Some(stats.poll_duration_histogram()),
-                    scheduled_times_histogram: Some(stats.scheduled_duration_histogram()),
-                })
+                    poll_times_histogram: histograms
+                        .poll_times
+                        .then(|| stats.poll_duration_histogram()),
+                    scheduled_times_histogram: histograms
+                        .scheduled_times
+                        .then(|| stats.scheduled_duration_histogram()),
+                    recent_poll_times_histogram: None,
+                    wake_sites: stats.top_wake_sites(),
+                    wake_history: stats.wake_history(),
+                    thread_polls: stats.poll_threads(),
+                    waiting_on: self.waiting_on.to_proto(&id, &self.base_time),
+                }))
             {
+                // Recording the recent poll times starts a new interval, so
+                // that the first one this subscriber sees doesn't span the
+                // task's whole life so far. If another subscriber already
+                // wants them, the interval it's in is left alone.
+                if histograms.recent_poll_times {
+                    stats.record_recent_poll_durations(true);
+                }
                 self.details_watchers
                     .entry(id.clone())
                     .or_default()
-                    .push(subscription);
+                    .push(DetailsWatcher {
+                        histograms,
+                        watch: subscription,
+                    });
             }
         }
         // If the task is not found, drop `stream_sender` which will result in a not found error
     }
 
     /// Add a state subscription to the watchers.
-    fn add_state_subscription(&mut self, subscription: Watch<proto::instrument::State>) {
-        self.state_watchers.push(subscription);
+    fn add_state_subscription(
+        &mut self,
+        stream_id: u64,
+        subscription: Watch<proto::instrument::State>,
+    ) {
+        self.state_watchers.push(StateWatcher {
+            stream_id,
+            watch: subscription,
+        });
     }
 
     /// Publish the current state to all active state watchers.
     fn publish_state(&mut self) {
-        let state = proto::instrument::State {
-            temporality: self.temporality.into(),
-        };
-        self.state_watchers
-            .retain_and_shrink(|watch| watch.update(&state));
+        let watchers = &self.watchers;
+        self.state_watchers.retain_and_shrink(|state_watcher| {
+            let state = proto::instrument::State {
+                temporality: temporality_of(watchers, state_watcher.stream_id).into(),
+            };
+            state_watcher.watch.update(&Arc::new(state))
+        });
     }
 
     /// Publish the current state to all active watchers.
     ///
-    /// This drops any watchers which have closed the RPC, or whose update
-    /// channel has filled up.
-    fn publish(&mut self) {
+    /// Streams which aren't due an update at this `tick` keep it, merged
+    /// with the others they've missed, until they are, unless `flush` is
+    /// set. This drops any watchers which have closed the RPC, or whose
+    /// update channel has been full for too many updates in a row.
+    fn publish(&mut self, tick: Duration, flush: bool) {
+        self.shed_poll_ops();
         let new_metadata = if !self.new_metadata.is_empty() {
-            Some(proto::RegisterMetadata {
-                metadata: std::mem::take(&mut self.new_metadata),
-            })
+            Some(register_metadata(&std::mem::take(&mut self.new_metadata)))
         } else {
             None
         };
-        let task_update = Some(self.task_update(Include::UpdatedOnly));
-        let resource_update = Some(self.resource_update(Include::UpdatedOnly));
-        let async_op_update = Some(self.async_op_update(Include::UpdatedOnly));
+        // Only assemble the parts of the update that a live stream will be
+        // sent. Anything left out stays unsent until a stream needs it.
+        let domains = self
+            .watchers
+            .iter()
+            .filter(|watcher| watcher.is_live())
+            .fold(Domains::NONE, |domains, watcher| {
+                domains.union(watcher.domains)
+            });
+        let task_update = domains
+            .tasks
+            .then(|| self.task_update(Include::UpdatedOnly));
+        let resource_update = domains
+            .resources
+            .then(|| self.resource_update(Include::UpdatedOnly));
+        let async_op_update = domains
+            .async_ops
+            .then(|| self.async_op_update(Include::UpdatedOnly));
+        let now = self.clock.now();
+        let runtime_stats = Some(self.runtime_stats(now));
+        let subscriber_stats = Some(self.subscriber_stats());
 
         let update = proto::instrument::Update {
-            now: Some(self.base_time.to_timestamp(Instant::now())),
+            now: Some(self.base_time.to_timestamp(now)),
             new_metadata,
             task_update,
             resource_update,
             async_op_update,
+            runtime_stats,
+            subscriber_stats,
+            stream_id: 0,
+            sequence: 0,
+            lagging: false,
+            truncated: false,
         };
 
+        // Streams which describe everything assembled share the update as it
+        // is. The others share a copy with the parts they don't need removed,
+        // which is made once for all the streams describing the same domains.
+        let update = Arc::new(update);
+        let mut restricted = vec![(domains, update)];
+        let publish_interval = self.publish_interval;
+        let lag_limit = self.client_lag_limit;
+        let max_message_size = self.max_message_size;
         self.watchers
-            .retain_and_shrink(|watch: &Watch<proto::instrument::Update>| watch.update(&update));
+            .retain_and_shrink(|watcher: &mut UpdateWatcher| {
+                if !watcher.is_live() {
+                    // Paused streams are skipped, but still dropped once the
+                    // client goes away.
+                    return !watcher.watch.0.is_closed();
+                }
+                let update = match restricted
+                    .iter()
+                    .find(|(domains, _)| *domains == watcher.domains)
+                {
+                    Some((_, update)) => update.clone(),
+                    None => {
+                        let update = Arc::new(watcher.domains.restrict(&restricted[0].1));
+                        restricted.push((watcher.domains, update.clone()));
+                        update
+                    }
+                };
+                if !flush && !watcher.is_due(now, tick) {
+                    watcher.queue(unshare(update));
+                    return !watcher.watch.0.is_closed();
+                }
+                let update = watcher.with_pending(update);
+                watcher.sent(now, publish_interval);
+                watcher.send(update, now, lag_limit, max_message_size)
+            });
+        // Let the last stream to take each update have it without copying it.
+        drop(restricted);
+        self.update_tasks_watched();
 
         let stats = &self.task_stats;
         // Assuming there are much fewer task details subscribers than there are
         // stats updates, iterate over `details_watchers` and compact the map.
         self.details_watchers.retain_and_shrink(|id, watchers| {
             if let Some(task_stats) = stats.get(id) {
-                let details = proto::tasks::TaskDetails {
+                // Only the histograms some stream asked for are built, and
+                // each stream is sent the ones it asked for. The recent poll
+                // times are taken once, and every stream is sent the same
+                // interval.
+                let histograms = watchers.iter().fold(
+                    proto::instrument::HistogramSelection::default(),
+                    |all, watcher| proto::instrument::HistogramSelection {
+                        poll_times: all.poll_times || watcher.histograms.poll_times,
+                        scheduled_times: all.scheduled_times || watcher.histograms.scheduled_times,
+                        recent_poll_times: all.recent_poll_times
+                            || watcher.histograms.recent_poll_times,
+                    },
+                );
+                let details = Arc::new(proto::tasks::TaskDetails {
                     task_id: Some(id.clone().into()),
-                    now: Some(self.base_time.to_timestamp(Instant::now())),
-                    poll_times_histogram: Some(task_stats.poll_duration_histogram()),
-                    scheduled_times_histogram: Some(task_stats.scheduled_duration_histogram()),
-                };
-                watchers.retain(|watch| watch.update(&details));
+                    now: Some(self.base_time.to_timestamp(self.clock.now())),
+                    poll_times_histogram: histograms
+                        .poll_times
+                        .then(|| task_stats.poll_duration_histogram()),
+                    scheduled_times_histogram: histograms
+                        .scheduled_times
+                        .then(|| task_stats.scheduled_duration_histogram()),
+                    recent_poll_times_histogram: task_stats.take_recent_poll_duration_histogram(),
+                    wake_sites: task_stats.top_wake_sites(),
+                    wake_history: task_stats.wake_history(),
+                    thread_polls: task_stats.poll_threads(),
+                    waiting_on: self.waiting_on.to_proto(id, &self.base_time),
+                });
+                watchers.retain(|watcher| {
+                    if watcher.histograms == histograms {
+                        return watcher.watch.update(&details);
+                    }
+                    let selected = &watcher.histograms;
+                    let mut details = proto::tasks::TaskDetails::clone(&details);
+                    if !selected.poll_times {
+                        details.poll_times_histogram = None;
+                    }
+                    if !selected.scheduled_times {
+                        details.scheduled_times_histogram = None;
+                    }
+                    if !selected.recent_poll_times {
+                        details.recent_poll_times_histogram = None;
+                    }
+                    watcher.watch.update(&Arc::new(details))
+                });
+                if !watchers
+                    .iter()
+                    .any(|watcher| watcher.histograms.recent_poll_times)
+                {
+                    task_stats.record_recent_poll_durations(false);
+                }
                 !watchers.is_empty()
             } else {
                 false
@@ -465,8 +1045,8 @@ impl Aggregator {
         // do state update
         match event {
             Event::Metadata(meta) => {
-                self.all_metadata.push(meta.into());
-                self.new_metadata.push(meta.into());
+                self.all_metadata.push(meta);
+                self.new_metadata.push(meta);
             }
 
             Event::Spawn {
@@ -475,6 +1055,12 @@ impl Aggregator {
                 stats,
                 fields,
                 location,
+                kind,
+                runtime,
+                group,
+                size_bytes,
+                original_size_bytes,
+                backtrace,
             } => {
                 self.tasks.insert(
                     id.clone(),
@@ -484,6 +1070,14 @@ impl Aggregator {
                         metadata,
                         fields,
                         location,
+                        kind,
+                        runtime,
+                        group,
+                        size_bytes,
+                        original_size_bytes,
+                        spawn_backtrace: backtrace
+                            .map(spawn_backtrace::resolve)
+                            .unwrap_or_default(),
                         // TODO: parents
                     },
                 );
@@ -526,8 +1120,17 @@ impl Aggregator {
                 task_id,
                 is_ready,
             } => {
+                self.waiting_on.poll_op(
+                    &task_id,
+                    &async_op_id,
+                    &resource_id,
+                    &op_name,
+                    is_ready,
+                    self.clock.now(),
+                );
+
                 // CLI doesn't show historical poll ops, so don't save them if no-one is watching
-                if self.watchers.is_empty() {
+                if !self.watched_domains().resources {
                     return;
                 }
                 let poll_op = proto::resources::PollOp {
@@ -568,13 +1171,21 @@ impl Aggregator {
     }
 }
 
-fn recv_now_or_never<T>(receiver: &mut mpsc::Receiver<T>) -> Option<Option<T>> {
-    let waker = futures_task::noop_waker();
-    let mut cx = std::task::Context::from_waker(&waker);
+/// Takes the number of events counted in `dropped` since the last update
+/// published to every live stream.
+///
+/// A full update is only sent to one stream, so it leaves the count for the
+/// others' next update.
+fn take_dropped(dropped: &AtomicUsize, include: Include) -> u64 {
+    match include {
+        Include::UpdatedOnly => dropped.swap(0, AcqRel) as u64,
+        Include::All => 0,
+    }
+}
 
-    match receiver.poll_recv(&mut cx) {
-        std::task::Poll::Ready(opt) => Some(opt),
-        std::task::Poll::Pending => None,
+fn register_metadata(metadata: &[&'static Metadata<'static>]) -> proto::RegisterMetadata {
+    proto::RegisterMetadata {
+        metadata: metadata.iter().map(|&meta| meta.into()).collect(),
     }
 }
 
@@ -638,8 +1249,100 @@ impl Flush {
     }
 }
 
-impl<T: Clone> Watch<T> {
-    fn update(&self, update: &T) -> bool {
+/// Returns the time "state" of the update stream with the given ID.
+///
+/// For ID 0, this is `Paused` only while every update stream is paused.
+fn temporality_of(watchers: &[UpdateWatcher], stream_id: u64) -> proto::instrument::Temporality {
+    let mut streams = watchers.iter().filter(|watcher| watcher.is(stream_id));
+    let paused = match streams.next() {
+        Some(first) => !first.is_live() && streams.all(|watcher| !watcher.is_live()),
+        None => false,
+    };
+    if paused {
+        proto::instrument::Temporality::Paused
+    } else {
+        proto::instrument::Temporality::Live
+    }
+}
+
+// === impl Domains ===
+
+impl Domains {
+    pub(crate) const ALL: Self = Self {
+        tasks: true,
+        resources: true,
+        async_ops: true,
+    };
+    pub(crate) const TASKS: Self = Self {
+        tasks: true,
+        ..Self::NONE
+    };
+    pub(crate) const RESOURCES: Self = Self {
+        resources: true,
+        ..Self::NONE
+    };
+    pub(crate) const ASYNC_OPS: Self = Self {
+        async_ops: true,
+        ..Self::NONE
+    };
+    const NONE: Self = Self {
+        tasks: false,
+        resources: false,
+        async_ops: false,
+    };
+
+    fn union(self, other: Self) -> Self {
+        Self {
+            tasks: self.tasks || other.tasks,
+            resources: self.resources || other.resources,
+            async_ops: self.async_ops || other.async_ops,
+        }
+    }
+
+    /// Returns a copy of `update` without the parts outside these domains.
+    fn restrict(self, update: &proto::instrument::Update) -> proto::instrument::Update {
+        proto::instrument::Update {
+            now: update.now,
+            task_update: if self.tasks {
+                update.task_update.clone()
+            } else {
+                None
+            },
+            resource_update: if self.resources {
+                update.resource_update.clone()
+            } else {
+                None
+            },
+            async_op_update: if self.async_ops {
+                update.async_op_update.clone()
+            } else {
+                None
+            },
+            new_metadata: update.new_metadata.clone(),
+            runtime_stats: update.runtime_stats,
+            subscriber_stats: update.subscriber_stats,
+            stream_id: update.stream_id,
+            sequence: update.sequence,
+            lagging: update.lagging,
+            truncated: update.truncated,
+        }
+    }
+}
+
+impl UpdateWatcher {
+    /// Returns `true` if this watcher is selected by `stream_id`, where 0
+    /// selects every watcher.
+    fn is(&self, stream_id: u64) -> bool {
+        stream_id == 0 || self.stream_id == stream_id
+    }
+
+    fn is_live(&self) -> bool {
+        self.temporality == proto::instrument::Temporality::Live
+    }
+}
+
+impl<T> Watch<T> {
+    fn update(&self, update: &Arc<T>) -> bool {
         if let Ok(reserve) = self.0.try_reserve() {
             reserve.send(Ok(update.clone()));
             true
@@ -649,18 +1352,49 @@ impl<T: Clone> Watch<T> {
     }
 }
 
+impl StreamUpdate {
+    /// Takes the update, numbered for the stream it was sent on.
+    pub(crate) fn take(self: Arc<Self>) -> proto::instrument::Update {
+        let (update, sequence) = match Arc::try_unwrap(self) {
+            Ok(this) => (this.update, this.sequence),
+            Err(this) => (this.update.clone(), this.sequence),
+        };
+        let mut update = unshare(update);
+        update.sequence = sequence;
+        update
+    }
+}
+
+impl Watch<StreamUpdate> {
+    /// Sends `update` on an update stream, numbered as it already is.
+    fn send_update(&self, update: proto::instrument::Update) -> bool {
+        let sequence = update.sequence;
+        self.send_shared(Arc::new(update), sequence)
+    }
+
+    /// Sends an update which may also be sent on other streams, numbered as
+    /// this stream's update `sequence`.
+    fn send_shared(&self, update: Arc<proto::instrument::Update>, sequence: u64) -> bool {
+        self.update(&Arc::new(StreamUpdate { update, sequence }))
+    }
+}
+
 impl ToProto for Task {
     type Output = proto::tasks::Task;
 
     fn to_proto(&self, _: &stats::TimeAnchor) -> Self::Output {
         proto::tasks::Task {
             id: Some(self.id.clone().into()),
-            // TODO: more kinds of tasks...
-            kind: proto::tasks::task::Kind::Spawn as i32,
+            kind: self.kind as i32,
             metadata: Some(self.metadata.into()),
             parents: Vec::new(), // TODO: implement parents nicely
-            fields: self.fields.clone(),
-            location: self.location.clone(),
+            fields: self.fields.to_proto(self.metadata),
+            location: self.location.as_ref().map(TaskLocation::to_proto),
+            runtime: self.runtime.as_deref().map(String::from),
+            group: self.group.clone(),
+            size_bytes: self.size_bytes,
+            original_size_bytes: self.original_size_bytes,
+            spawn_backtrace: self.spawn_backtrace.clone(),
         }
     }
 }
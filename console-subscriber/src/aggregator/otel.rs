@@ -0,0 +1,132 @@
+//! Exports the aggregator's stats as OpenTelemetry metrics, so that they can
+//! be routed into an existing observability stack.
+
+use std::{collections::HashMap, time::Duration};
+
+use opentelemetry::metrics::{Counter, Gauge, Meter};
+use tracing_core::span::Id;
+
+use super::Aggregator;
+use crate::{stats::DroppedAt, ToProto};
+
+/// The instruments the aggregator's stats are exported to.
+pub(super) struct OtelMetrics {
+    tasks: Gauge<u64>,
+    resources: Gauge<u64>,
+    async_ops: Gauge<u64>,
+    polls: Counter<u64>,
+    busy_time: Counter<f64>,
+    wakes: Counter<u64>,
+    self_wakes: Counter<u64>,
+    /// The totals last exported for each task, so that only what changed
+    /// since then is added to the counters.
+    exported: HashMap<Id, TaskTotals>,
+}
+
+#[derive(Copy, Clone, Default)]
+struct TaskTotals {
+    polls: u64,
+    busy_time: Duration,
+    wakes: u64,
+    self_wakes: u64,
+}
+
+impl OtelMetrics {
+    pub(super) fn new(meter: &Meter) -> Self {
+        Self {
+            tasks: meter
+                .u64_gauge("tokio.console.tasks.live")
+                .with_description("The number of live tasks")
+                .init(),
+            resources: meter
+                .u64_gauge("tokio.console.resources.live")
+                .with_description("The number of live resources")
+                .init(),
+            async_ops: meter
+                .u64_gauge("tokio.console.async_ops.live")
+                .with_description("The number of live async operations")
+                .init(),
+            polls: meter
+                .u64_counter("tokio.console.task.polls")
+                .with_description("The number of times tasks were polled")
+                .init(),
+            busy_time: meter
+                .f64_counter("tokio.console.task.busy_time")
+                .with_description("The time tasks spent being polled")
+                .with_unit("s")
+                .init(),
+            wakes: meter
+                .u64_counter("tokio.console.task.wakes")
+                .with_description("The number of times tasks were woken")
+                .init(),
+            self_wakes: meter
+                .u64_counter("tokio.console.task.self_wakes")
+                .with_description("The number of times tasks woke themselves")
+                .init(),
+            exported: HashMap::new(),
+        }
+    }
+}
+
+impl Aggregator {
+    /// Records the current stats to the OpenTelemetry instruments, if
+    /// exporting them is enabled.
+    pub(super) fn export_otel(&mut self) {
+        let Some(otel) = self.otel.as_mut() else {
+            return;
+        };
+
+        let live = |dropped: Option<_>| dropped.is_none();
+        otel.tasks.record(
+            self.task_stats
+                .all()
+                .filter(|(_, stats)| live(stats.dropped_at()))
+                .count() as u64,
+            &[],
+        );
+        otel.resources.record(
+            self.resource_stats
+                .all()
+                .filter(|(_, stats)| live(stats.dropped_at()))
+                .count() as u64,
+            &[],
+        );
+        otel.async_ops.record(
+            self.async_op_stats
+                .all()
+                .filter(|(_, stats)| live(stats.dropped_at()))
+                .count() as u64,
+            &[],
+        );
+
+        let mut totals = TaskTotals::default();
+        let mut exported = HashMap::with_capacity(otel.exported.len());
+        for (id, stats) in self.task_stats.all() {
+            let stats = stats.to_proto(&self.base_time);
+            let poll_stats = stats.poll_stats.unwrap_or_default();
+            let current = TaskTotals {
+                polls: poll_stats.polls,
+                busy_time: poll_stats
+                    .busy_time
+                    .and_then(|busy| Duration::try_from(busy).ok())
+                    .unwrap_or_default(),
+                wakes: stats.wakes,
+                self_wakes: stats.self_wakes,
+            };
+            let prev = otel.exported.get(id).copied().unwrap_or_default();
+            totals.polls += current.polls.saturating_sub(prev.polls);
+            totals.busy_time += current.busy_time.saturating_sub(prev.busy_time);
+            totals.wakes += current.wakes.saturating_sub(prev.wakes);
+            totals.self_wakes += current.self_wakes.saturating_sub(prev.self_wakes);
+            exported.insert(id.clone(), current);
+        }
+        // Tasks which have since been dropped from the aggregator are
+        // forgotten, as their final counts were already exported.
+        otel.exported = exported;
+
+        otel.polls.add(totals.polls, &[]);
+        otel.busy_time.add(totals.busy_time.as_secs_f64(), &[]);
+        otel.wakes.add(totals.wakes, &[]);
+        otel.self_wakes.add(totals.self_wakes, &[]);
+    }
+}
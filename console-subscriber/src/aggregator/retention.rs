@@ -0,0 +1,74 @@
+//! Measures how long closed entities have been retained for, without counting
+//! the time every update stream was paused.
+//!
+//! A paused console still shows the entities it knew about when it paused, so
+//! if retention kept running while it was paused, resuming could evict
+//! entities the console had never been sent the final state of.
+//!
+//! Only up to one retention period of paused time is discounted, though, so
+//! that a console which stays paused doesn't make the aggregator keep every
+//! closed entity for as long as it does.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// The periods during which every update stream was paused.
+#[derive(Debug, Default)]
+pub(super) struct PausedTime {
+    /// Completed pauses, as their start and end, oldest first.
+    pauses: VecDeque<(Instant, Instant)>,
+    /// When the current pause started, if every stream is paused now.
+    paused_since: Option<Instant>,
+}
+
+impl PausedTime {
+    /// Records whether every update stream is paused as of `now`.
+    pub(super) fn set_paused(&mut self, paused: bool, now: Instant) {
+        match (paused, self.paused_since) {
+            (true, None) => self.paused_since = Some(now),
+            (false, Some(since)) => {
+                self.paused_since = None;
+                self.pauses.push_back((since, now));
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns whether every update stream is paused.
+    pub(super) fn is_paused(&self) -> bool {
+        self.paused_since.is_some()
+    }
+
+    /// Returns how long it has been since `since`, as of `now`, not counting
+    /// up to `retention` of the time during which every stream was paused.
+    pub(super) fn unpaused_since(
+        &self,
+        since: Instant,
+        now: Instant,
+        retention: Duration,
+    ) -> Duration {
+        let current = self.paused_since.map(|start| (start, now));
+        let paused = self
+            .pauses
+            .iter()
+            .copied()
+            .chain(current)
+            .map(|(start, end)| end.min(now).saturating_duration_since(start.max(since)))
+            .sum::<Duration>();
+        now.saturating_duration_since(since)
+            .saturating_sub(paused.min(retention))
+    }
+
+    /// Forgets pauses which ended so long ago that anything dropped before
+    /// them has exceeded `retention` anyway.
+    pub(super) fn prune(&mut self, now: Instant, retention: Duration) {
+        while let Some(&(_, end)) = self.pauses.front() {
+            if self.unpaused_since(end, now, retention) <= retention {
+                break;
+            }
+            self.pauses.pop_front();
+        }
+    }
+}
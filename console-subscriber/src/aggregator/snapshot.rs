@@ -0,0 +1,144 @@
+//! Assembles snapshots of the complete current state for the `Snapshot` RPC.
+
+use std::{collections::HashMap, mem};
+
+use console_api as proto;
+use prost::Message;
+
+use super::{register_metadata, Aggregator, Include, MAX_MESSAGE_SIZE, METADATA_CHUNK_LEN};
+
+/// An allowance for the bytes each task, resource, async op, or set of stats
+/// adds to an update beyond its own encoded length, such as its field tag,
+/// length prefix, and map key.
+const FIELD_OVERHEAD: usize = 16;
+
+/// Returns the list of items, and the map of their stats, in an update that
+/// items of one kind are added to.
+type Lists<T, S> = fn(&mut proto::instrument::Update) -> (&mut Vec<T>, &mut HashMap<u64, S>);
+
+impl Aggregator {
+    /// Returns the complete current state, split into as many updates as it
+    /// takes for each of them to be small enough to send.
+    ///
+    /// The updates start with all of the metadata registered so far, and all
+    /// carry the same timestamp. Unlike [`Aggregator::full_update`], this never
+    /// reduces retention, and it doesn't take the pending poll ops or dropped
+    /// event counts from the next published update.
+    pub(super) fn snapshot(&mut self) -> Vec<proto::instrument::Update> {
+        let now = Some(self.base_time.to_timestamp(self.clock.now()));
+        let mut chunks = Chunks::new(now);
+        for metadata in self.all_metadata.chunks(METADATA_CHUNK_LEN) {
+            chunks.current.new_metadata = Some(register_metadata(metadata));
+            chunks.finish();
+        }
+
+        let mut task_stats = self.task_stats.as_proto(Include::All, &self.base_time);
+        self.waiting_on
+            .add_to_task_stats(&mut task_stats, &self.base_time);
+        chunks.add_all(
+            self.tasks.as_proto_list(Include::All, &self.base_time),
+            task_stats,
+            |task| task.id.as_ref().map(|id| id.id),
+            |update| {
+                let tasks = update.task_update.get_or_insert_with(Default::default);
+                (&mut tasks.new_tasks, &mut tasks.stats_update)
+            },
+        );
+
+        let mut resource_stats = self.resource_stats.as_proto(Include::All, &self.base_time);
+        self.waiting_on.add_to_resource_stats(&mut resource_stats);
+        chunks.add_all(
+            self.resources.as_proto_list(Include::All, &self.base_time),
+            resource_stats,
+            |resource| resource.id.as_ref().map(|id| id.id),
+            |update| {
+                let resources = update.resource_update.get_or_insert_with(Default::default);
+                (&mut resources.new_resources, &mut resources.stats_update)
+            },
+        );
+
+        chunks.add_all(
+            self.async_ops.as_proto_list(Include::All, &self.base_time),
+            self.async_op_stats.as_proto(Include::All, &self.base_time),
+            |async_op| async_op.id.as_ref().map(|id| id.id),
+            |update| {
+                let async_ops = update.async_op_update.get_or_insert_with(Default::default);
+                (&mut async_ops.new_async_ops, &mut async_ops.stats_update)
+            },
+        );
+
+        chunks.finish();
+        chunks.updates
+    }
+}
+
+/// Splits a snapshot into updates no larger than [`MAX_MESSAGE_SIZE`].
+struct Chunks {
+    now: Option<prost_types::Timestamp>,
+    /// The updates which are complete.
+    updates: Vec<proto::instrument::Update>,
+    /// The update being filled.
+    current: proto::instrument::Update,
+    /// The approximate encoded length of `current`.
+    len: usize,
+}
+
+impl Chunks {
+    fn new(now: Option<prost_types::Timestamp>) -> Self {
+        Self {
+            now,
+            updates: Vec::new(),
+            current: proto::instrument::Update {
+                now,
+                ..Default::default()
+            },
+            len: 0,
+        }
+    }
+
+    /// Adds each of `items`, along with its stats, and then any stats left
+    /// over, to the updates' lists returned by `lists`.
+    fn add_all<T: Message, S: Message>(
+        &mut self,
+        items: Vec<T>,
+        mut stats: HashMap<u64, S>,
+        id: fn(&T) -> Option<u64>,
+        lists: Lists<T, S>,
+    ) {
+        for item in items {
+            let item_stats = id(&item).and_then(|id| Some((id, stats.remove(&id)?)));
+            let len = item.encoded_len()
+                + item_stats
+                    .as_ref()
+                    .map_or(0, |(_, stats)| stats.encoded_len() + FIELD_OVERHEAD);
+            let (items, stats) = lists(self.reserve(len));
+            items.push(item);
+            stats.extend(item_stats);
+        }
+        for (id, item_stats) in stats {
+            let (_, stats) = lists(self.reserve(item_stats.encoded_len()));
+            stats.insert(id, item_stats);
+        }
+    }
+
+    /// Returns the update to add `len` more bytes to, starting a new one if
+    /// the current update is too full.
+    fn reserve(&mut self, len: usize) -> &mut proto::instrument::Update {
+        let len = len + FIELD_OVERHEAD;
+        if self.len > 0 && self.len + len > MAX_MESSAGE_SIZE {
+            self.finish();
+        }
+        self.len += len;
+        &mut self.current
+    }
+
+    /// Completes the current update, and starts a new one.
+    fn finish(&mut self) {
+        let next = proto::instrument::Update {
+            now: self.now,
+            ..Default::default()
+        };
+        self.updates.push(mem::replace(&mut self.current, next));
+        self.len = 0;
+    }
+}
@@ -0,0 +1,172 @@
+//! Per-stream update intervals.
+//!
+//! Clients may ask for updates more or less often than the aggregator's own
+//! publish interval. The aggregator publishes often enough for the stream
+//! which wants updates most often, and the updates published between those
+//! sent on a slower stream are combined into one.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use console_api as proto;
+
+use super::{Aggregator, UpdateWatcher};
+use crate::unshare;
+
+/// The shortest interval a client may ask to be sent updates at.
+pub(crate) const MIN_UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+
+impl Aggregator {
+    /// Returns how often updates need to be published: the publish interval,
+    /// or the shortest interval a stream asked for, if that's shorter.
+    pub(super) fn tick_interval(&self) -> Duration {
+        self.watchers
+            .iter()
+            .filter_map(|watcher| watcher.interval)
+            .fold(self.publish_interval, Duration::min)
+    }
+}
+
+impl UpdateWatcher {
+    /// Returns `true` if the stream should be sent an update now, when
+    /// updates are published every `tick`.
+    ///
+    /// Ticks don't land exactly on the times streams are due, so a stream is
+    /// sent an update on the tick closest to when it's due.
+    pub(super) fn is_due(&self, now: Instant, tick: Duration) -> bool {
+        self.next_update <= now + tick / 2
+    }
+
+    /// Records that the stream was sent an update at `now`.
+    pub(super) fn sent(&mut self, now: Instant, publish_interval: Duration) {
+        self.next_update = now + self.interval.unwrap_or(publish_interval);
+    }
+
+    /// Adds a published update to those the stream has yet to be sent.
+    pub(super) fn queue(&mut self, update: proto::instrument::Update) {
+        match self.pending {
+            Some(ref mut pending) => merge(pending, update),
+            None => self.pending = Some(update),
+        }
+    }
+
+    /// Returns the update to send the stream now: `update`, merged into any
+    /// the stream has yet to be sent.
+    ///
+    /// `update` may be shared with other streams, and is only copied if
+    /// there's something to merge it into.
+    pub(super) fn with_pending(
+        &mut self,
+        update: Arc<proto::instrument::Update>,
+    ) -> Arc<proto::instrument::Update> {
+        match self.pending.take() {
+            Some(mut pending) => {
+                merge(&mut pending, unshare(update));
+                Arc::new(pending)
+            }
+            None => update,
+        }
+    }
+}
+
+/// Merges `next` into `base`, so that applying `base` is equivalent to
+/// applying the original `base` followed by `next`.
+///
+/// Unlike the console's history, dropped tasks, resources, and async ops are
+/// kept, as the client still needs to be told that they were dropped.
+fn merge(base: &mut proto::instrument::Update, next: proto::instrument::Update) {
+    base.now = next.now.or(base.now);
+    base.sequence = next.sequence;
+
+    if let Some(next) = next.new_metadata {
+        base.new_metadata
+            .get_or_insert_with(Default::default)
+            .metadata
+            .extend(next.metadata);
+    }
+
+    if let Some(next) = next.task_update {
+        let base = base.task_update.get_or_insert_with(Default::default);
+        base.new_tasks.extend(next.new_tasks);
+        base.stats_update.extend(next.stats_update);
+        base.dropped_events += next.dropped_events;
+    }
+
+    if let Some(next) = next.resource_update {
+        let base = base.resource_update.get_or_insert_with(Default::default);
+        base.new_resources.extend(next.new_resources);
+        base.stats_update.extend(next.stats_update);
+        base.new_poll_ops.extend(next.new_poll_ops);
+        base.dropped_events += next.dropped_events;
+    }
+
+    if let Some(next) = next.async_op_update {
+        let base = base.async_op_update.get_or_insert_with(Default::default);
+        base.new_async_ops.extend(next.new_async_ops);
+        base.stats_update.extend(next.stats_update);
+        base.dropped_events += next.dropped_events;
+    }
+
+    // Runtime and subscriber stats describe the interval since the previous
+    // update, so they're combined to describe the whole of the merged
+    // updates' intervals.
+    base.runtime_stats = match (base.runtime_stats, next.runtime_stats) {
+        (Some(base), Some(next)) => Some(proto::instrument::RuntimeStats {
+            busy_time: add_durations(base.busy_time, next.busy_time),
+            interval: add_durations(base.interval, next.interval),
+            worker_threads: next.worker_threads,
+        }),
+        (base, next) => next.or(base),
+    };
+    base.subscriber_stats = match (base.subscriber_stats, next.subscriber_stats) {
+        (Some(base), Some(next)) => Some(merge_subscriber_stats(base, next)),
+        (base, next) => next.or(base),
+    };
+}
+
+fn merge_subscriber_stats(
+    base: proto::instrument::SubscriberStats,
+    next: proto::instrument::SubscriberStats,
+) -> proto::instrument::SubscriberStats {
+    let dropped_events = match (base.dropped_events, next.dropped_events) {
+        (Some(base), Some(next)) => Some(proto::instrument::DroppedEvents {
+            metadata: base.metadata + next.metadata,
+            spawns: base.spawns + next.spawns,
+            resources: base.resources + next.resources,
+            async_ops: base.async_ops + next.async_ops,
+            poll_ops: base.poll_ops + next.poll_ops,
+        }),
+        (base, next) => next.or(base),
+    };
+    let max_aggregation_time = match (base.max_aggregation_time, next.max_aggregation_time) {
+        (Some(base), Some(next)) => Some(if to_std(base) >= to_std(next) {
+            base
+        } else {
+            next
+        }),
+        (base, next) => next.or(base),
+    };
+    proto::instrument::SubscriberStats {
+        max_event_buffer_len: base.max_event_buffer_len.max(next.max_event_buffer_len),
+        event_buffer_capacity: next.event_buffer_capacity,
+        flushes: base.flushes + next.flushes,
+        dropped_events,
+        max_aggregation_time,
+    }
+}
+
+fn add_durations(
+    a: Option<prost_types::Duration>,
+    b: Option<prost_types::Duration>,
+) -> Option<prost_types::Duration> {
+    match (a, b) {
+        (Some(a), Some(b)) => (to_std(a) + to_std(b)).try_into().ok(),
+        (a, b) => b.or(a),
+    }
+}
+
+fn to_std(duration: prost_types::Duration) -> Duration {
+    duration.try_into().unwrap_or_default()
+}
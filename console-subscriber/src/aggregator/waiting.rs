@@ -0,0 +1,151 @@
+//! Tracks which async ops each task is currently waiting on, and which task
+//! last acquired each resource, so that clients can tell what a task is
+//! blocked on, and by whom.
+
+use std::{collections::HashMap, sync::Arc, time::Instant};
+
+use console_api as proto;
+use tracing_core::span::Id;
+
+use super::id_data::IdData;
+use crate::stats::{self, DroppedAt, TimeAnchor};
+
+/// The async ops each task last polled as pending, by task ID.
+///
+/// An edge is added when a poll op in a task returns pending, and removed
+/// when a later poll op of the same async op returns ready, or when the async
+/// op or the task is dropped.
+#[derive(Debug, Default)]
+pub(super) struct WaitingOn {
+    edges: HashMap<Id, Vec<Edge>>,
+    /// The task which most recently had a poll op of each resource return
+    /// ready, by resource ID.
+    acquired_by: HashMap<Id, Id>,
+}
+
+#[derive(Debug)]
+struct Edge {
+    async_op_id: Id,
+    resource_id: Id,
+    /// The name of the most recent poll op of the async op.
+    op_name: String,
+    /// When the async op first returned pending.
+    since: Instant,
+}
+
+impl WaitingOn {
+    /// Records that `task_id` polled `async_op_id` on `resource_id`.
+    pub(super) fn poll_op(
+        &mut self,
+        task_id: &Id,
+        async_op_id: &Id,
+        resource_id: &Id,
+        op_name: &str,
+        is_ready: bool,
+        at: Instant,
+    ) {
+        if is_ready {
+            self.acquired_by
+                .insert(resource_id.clone(), task_id.clone());
+            if let Some(edges) = self.edges.get_mut(task_id) {
+                edges.retain(|edge| edge.async_op_id != *async_op_id);
+                if edges.is_empty() {
+                    self.edges.remove(task_id);
+                }
+            }
+            return;
+        }
+
+        let edges = self.edges.entry(task_id.clone()).or_default();
+        match edges
+            .iter_mut()
+            .find(|edge| edge.async_op_id == *async_op_id)
+        {
+            Some(edge) => {
+                edge.resource_id = resource_id.clone();
+                if edge.op_name != op_name {
+                    edge.op_name = op_name.to_owned();
+                }
+            }
+            None => edges.push(Edge {
+                async_op_id: async_op_id.clone(),
+                resource_id: resource_id.clone(),
+                op_name: op_name.to_owned(),
+                since: at,
+            }),
+        }
+    }
+
+    /// Removes the edges of tasks which have completed, and to async ops
+    /// which have been dropped, along with the acquirers of dropped
+    /// resources.
+    pub(super) fn retain_live(
+        &mut self,
+        tasks: &IdData<Arc<stats::TaskStats>>,
+        resources: &IdData<Arc<stats::ResourceStats>>,
+        async_ops: &IdData<Arc<stats::AsyncOpStats>>,
+    ) {
+        self.acquired_by.retain(|resource_id, _| {
+            resources
+                .get(resource_id)
+                .is_some_and(|stats| stats.dropped_at().is_none())
+        });
+        self.edges.retain(|task_id, edges| {
+            let task_live = tasks
+                .get(task_id)
+                .is_some_and(|stats| stats.dropped_at().is_none());
+            if !task_live {
+                return false;
+            }
+            edges.retain(|edge| {
+                async_ops
+                    .get(&edge.async_op_id)
+                    .is_some_and(|stats| stats.dropped_at().is_none())
+            });
+            !edges.is_empty()
+        });
+    }
+
+    /// Sets what each task in a task update is currently waiting on.
+    pub(super) fn add_to_task_stats(
+        &self,
+        stats: &mut HashMap<u64, proto::tasks::Stats>,
+        base_time: &TimeAnchor,
+    ) {
+        for (&id, stats) in stats.iter_mut() {
+            stats.waiting_on = self.to_proto(&Id::from_u64(id), base_time);
+        }
+    }
+
+    /// Sets the task which last acquired each resource in a resource update.
+    pub(super) fn add_to_resource_stats(&self, stats: &mut HashMap<u64, proto::resources::Stats>) {
+        for (&id, stats) in stats.iter_mut() {
+            stats.acquired_by = self
+                .acquired_by
+                .get(&Id::from_u64(id))
+                .map(|task_id| task_id.clone().into());
+        }
+    }
+
+    /// Returns what `task_id` is currently waiting on.
+    pub(super) fn to_proto(
+        &self,
+        task_id: &Id,
+        base_time: &TimeAnchor,
+    ) -> Vec<proto::tasks::WaitingOn> {
+        self.edges
+            .get(task_id)
+            .map(|edges| {
+                edges
+                    .iter()
+                    .map(|edge| proto::tasks::WaitingOn {
+                        async_op_id: Some(edge.async_op_id.clone().into()),
+                        resource_id: Some(edge.resource_id.clone().into()),
+                        op_name: edge.op_name.clone(),
+                        since: Some(base_time.to_timestamp(edge.since)),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
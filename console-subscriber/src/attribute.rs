@@ -1,10 +1,24 @@
 use console_api as proto;
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Instant,
+};
 use tracing::span::Id;
 
 #[derive(Debug, Default)]
 pub(crate) struct Attributes {
-    attributes: HashMap<FieldKey, proto::Attribute>,
+    attributes: HashMap<FieldKey, Attribute>,
+    /// The number of past values to keep for each attribute, or 0 if their
+    /// history isn't kept.
+    history_len: usize,
+}
+
+#[derive(Debug)]
+struct Attribute {
+    current: proto::Attribute,
+    /// The attribute's most recent values and when they were set, oldest
+    /// first, including the current value.
+    history: VecDeque<(Instant, proto::Attribute)>,
 }
 
 #[derive(Debug, Clone)]
@@ -33,11 +47,25 @@ struct FieldKey {
 // === impl Attributes ===
 
 impl Attributes {
+    /// Returns attributes which keep the last `history_len` values of each
+    /// attribute.
+    pub(crate) fn with_history(history_len: usize) -> Self {
+        Self {
+            attributes: HashMap::new(),
+            history_len,
+        }
+    }
+
     pub(crate) fn values(&self) -> impl Iterator<Item = &proto::Attribute> {
-        self.attributes.values()
+        self.attributes.values().map(|attr| &attr.current)
     }
 
-    pub(crate) fn update(&mut self, id: &Id, update: &Update) {
+    /// Returns the recent values of every attribute, in no particular order.
+    pub(crate) fn history(&self) -> impl Iterator<Item = &(Instant, proto::Attribute)> {
+        self.attributes.values().flat_map(|attr| &attr.history)
+    }
+
+    pub(crate) fn update(&mut self, id: &Id, update: &Update, at: Instant) {
         let field_name = match update.field.name.as_ref() {
             Some(name) => name.clone(),
             None => {
@@ -51,10 +79,21 @@ impl Attributes {
             field_name,
         };
 
-        self.attributes
+        let attr = self
+            .attributes
             .entry(key)
-            .and_modify(|attr| update_attribute(attr, update))
-            .or_insert_with(|| update.clone().into());
+            .and_modify(|attr| update_attribute(&mut attr.current, update))
+            .or_insert_with(|| Attribute {
+                current: update.clone().into(),
+                history: VecDeque::new(),
+            });
+
+        if self.history_len > 0 {
+            if attr.history.len() == self.history_len {
+                attr.history.pop_front();
+            }
+            attr.history.push_back((at, attr.current.clone()));
+        }
     }
 }
 
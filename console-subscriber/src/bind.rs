@@ -0,0 +1,144 @@
+use std::{
+    error, fmt, io,
+    net::SocketAddr,
+    ops::RangeInclusive,
+    sync::{Mutex, PoisonError},
+};
+use tokio::net::TcpListener;
+
+/// The address the most recently started server is listening on.
+static LOCAL_ADDR: Mutex<Option<SocketAddr>> = Mutex::new(None);
+
+/// An error which prevented a [`Server`] from listening on its address.
+///
+/// This is returned (boxed) by [`Server::serve`] and the other `serve_with`
+/// methods, and can be recovered with [`downcast_ref`].
+///
+/// [`Server`]: crate::Server
+/// [`Server::serve`]: crate::Server::serve
+/// [`downcast_ref`]: https://doc.rust-lang.org/std/error/trait.Error.html#method.downcast_ref
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum BindError {
+    /// The address was already in use, as was every port in the fallback
+    /// [port range], if there is one.
+    ///
+    /// This usually means that another instrumented process is already
+    /// running, or that this process started more than one server.
+    ///
+    /// [port range]: crate::Builder::port_range
+    AddrInUse {
+        /// The address the server was configured to listen on.
+        addr: SocketAddr,
+        /// The ports that were tried after `addr`.
+        port_range: Option<RangeInclusive<u16>>,
+    },
+    /// The address couldn't be listened on for another reason.
+    Io {
+        /// The address the server tried to listen on.
+        addr: SocketAddr,
+        /// The error from binding the address.
+        source: io::Error,
+    },
+}
+
+/// Listens on `addr`, or if it's in use, on the first free port in
+/// `port_range`.
+pub(crate) fn bind_tcp(
+    addr: SocketAddr,
+    port_range: Option<&RangeInclusive<u16>>,
+) -> Result<TcpListener, BindError> {
+    let fallbacks = port_range
+        .into_iter()
+        .flat_map(RangeInclusive::clone)
+        .filter(|&port| port != addr.port())
+        .map(|port| SocketAddr::new(addr.ip(), port));
+    for candidate in std::iter::once(addr).chain(fallbacks) {
+        match listen(candidate) {
+            Ok(listener) => {
+                let local_addr = listener.local_addr().unwrap_or(candidate);
+                if candidate == addr {
+                    tracing::info!(addr = %local_addr, "console server listening");
+                } else {
+                    tracing::warn!(
+                        configured = %addr,
+                        addr = %local_addr,
+                        "console server address was in use, listening on a fallback port"
+                    );
+                }
+                *LOCAL_ADDR.lock().unwrap_or_else(PoisonError::into_inner) = Some(local_addr);
+                return Ok(listener);
+            }
+            Err(error) if error.kind() == io::ErrorKind::AddrInUse => {
+                tracing::debug!(addr = %candidate, "console server address in use");
+            }
+            Err(source) => {
+                return Err(BindError::Io {
+                    addr: candidate,
+                    source,
+                })
+            }
+        }
+    }
+    Err(BindError::AddrInUse {
+        addr,
+        port_range: port_range.cloned(),
+    })
+}
+
+/// Returns the address the most recently started server is listening on.
+pub(crate) fn local_addr() -> Option<SocketAddr> {
+    *LOCAL_ADDR.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn listen(addr: SocketAddr) -> io::Result<TcpListener> {
+    let listener = std::net::TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+    TcpListener::from_std(listener)
+}
+
+// === impl BindError ===
+
+impl fmt::Display for BindError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindError::AddrInUse {
+                addr,
+                port_range: None,
+            } => write!(
+                f,
+                "the console server address {} is already in use; is another \
+                 instrumented process running? Set TOKIO_CONSOLE_BIND to a \
+                 free address, or use `Builder::port_range` to fall back on \
+                 other ports",
+                addr
+            ),
+            BindError::AddrInUse {
+                addr,
+                port_range: Some(ports),
+            } => write!(
+                f,
+                "the console server address {}, and ports {}-{} on the same \
+                 host, are all already in use; set TOKIO_CONSOLE_BIND to a \
+                 free address, or widen the port range",
+                addr,
+                ports.start(),
+                ports.end()
+            ),
+            BindError::Io { addr, source } => write!(
+                f,
+                "the console server couldn't listen on {}: {}",
+                addr, source
+            ),
+        }
+    }
+}
+
+impl error::Error for BindError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            BindError::AddrInUse { .. } => None,
+            BindError::Io { source, .. } => Some(source),
+        }
+    }
+}
@@ -1,8 +1,9 @@
-use super::{ConsoleLayer, Server};
+use super::{BindError, ConsoleLayer, Server, TriggerPolicy};
 #[cfg(unix)]
 use std::path::Path;
 use std::{
     net::{IpAddr, SocketAddr, SocketAddrV4, SocketAddrV6, ToSocketAddrs},
+    ops::RangeInclusive,
     path::PathBuf,
     thread,
     time::Duration,
@@ -23,28 +24,52 @@ pub struct Builder {
     /// the aggregator task.
     pub(super) event_buffer_capacity: usize,
 
+    /// The number of shards the channel of events is split into, or `None`
+    /// to use one per CPU core.
+    pub(super) event_buffer_shards: Option<usize>,
+
     /// The maximum number of updates to buffer per-client before the client is
     /// dropped.
     pub(super) client_buffer_capacity: usize,
 
+    /// The number of updates in a row a client may fail to be sent before it's
+    /// disconnected.
+    pub(crate) client_lag_limit: u32,
+
     /// The interval between publishing updates to clients.
     pub(crate) publish_interval: Duration,
 
+    /// The range the publish interval may be adjusted within when the
+    /// aggregator falls behind, or `None` if it is never adjusted.
+    pub(crate) adaptive_publish: Option<RangeInclusive<Duration>>,
+
     /// How long to retain data for completed events.
     pub(crate) retention: Duration,
 
     /// The address on which to serve the RPC server.
     pub(super) server_addr: ServerAddr,
 
+    /// Ports to try in turn if the server's TCP address is in use.
+    pub(super) port_range: Option<RangeInclusive<u16>>,
+
     /// If and where to save a recording of the events.
     pub(super) recording_path: Option<PathBuf>,
 
+    /// If set, only record events after an anomaly.
+    pub(super) record_on: Option<TriggerPolicy>,
+
     /// The filter environment variable to use for `tracing` events.
     pub(super) filter_env_var: String,
 
     /// Whether to trace events coming from the subscriber thread
     self_trace: bool,
 
+    /// The number of worker threads used by the instrumented runtime, used to
+    /// estimate how saturated the runtime is.
+    ///
+    /// If this is `None`, the number of available CPU cores is used.
+    pub(crate) worker_threads: Option<usize>,
+
     /// The maximum value for the task poll duration histogram.
     ///
     /// Any polls exceeding this duration will be clamped to this value. Higher
@@ -57,26 +82,90 @@ pub struct Builder {
     /// value. Higher values will result in more memory usage.
     pub(super) scheduled_duration_max: Duration,
 
+    /// How many of the layer's callbacks to measure, for estimating the
+    /// instrumentation's overhead. One in every `overhead_sample_rate`
+    /// callbacks is measured, and 0 disables overhead sampling.
+    pub(super) overhead_sample_rate: usize,
+
+    /// Estimates the size of tasks whose size the runtime does not report.
+    pub(super) task_memory_estimator: Option<TaskMemoryEstimator>,
+
+    /// Whether to capture a backtrace where each task is spawned.
+    pub(super) capture_spawn_backtraces: bool,
+
+    /// Whether to record the innermost user span each task is in.
+    pub(super) track_user_spans: bool,
+
+    /// The number of past values of each resource attribute to keep.
+    pub(super) attribute_history: usize,
+
     /// Whether to enable the grpc-web support.
     #[cfg(feature = "grpc-web")]
     enable_grpc_web: bool,
+
+    /// Whether to serve the web UI.
+    #[cfg(feature = "web-ui")]
+    enable_web_ui: bool,
+
+    /// The meter to export task and resource stats to, if any.
+    #[cfg(feature = "otel")]
+    pub(crate) otel_meter: Option<opentelemetry::metrics::Meter>,
+
+    /// Where the aggregator reads the current time from.
+    pub(crate) clock: crate::aggregator::Clock,
+}
+
+/// A function which estimates the size of a task's future, in bytes.
+///
+/// See [`Builder::with_task_memory_estimator`].
+pub type TaskMemoryEstimator = fn(&TaskMemoryInfo<'_>) -> Option<u64>;
+
+/// What is known about a newly spawned task, for estimating its size with a
+/// [`TaskMemoryEstimator`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct TaskMemoryInfo<'a> {
+    /// The task's name, if it has one.
+    pub name: Option<&'a str>,
+    /// The file the task was spawned in, if it is known.
+    pub file: Option<&'a str>,
+    /// The line the task was spawned on, if it is known.
+    pub line: Option<u32>,
+    /// Whether the task was spawned on the blocking pool.
+    pub is_blocking: bool,
 }
 
 impl Default for Builder {
     fn default() -> Self {
         Self {
             event_buffer_capacity: ConsoleLayer::DEFAULT_EVENT_BUFFER_CAPACITY,
+            event_buffer_shards: None,
             client_buffer_capacity: ConsoleLayer::DEFAULT_CLIENT_BUFFER_CAPACITY,
+            client_lag_limit: ConsoleLayer::DEFAULT_CLIENT_LAG_LIMIT,
             publish_interval: ConsoleLayer::DEFAULT_PUBLISH_INTERVAL,
+            adaptive_publish: None,
             retention: ConsoleLayer::DEFAULT_RETENTION,
             poll_duration_max: ConsoleLayer::DEFAULT_POLL_DURATION_MAX,
             scheduled_duration_max: ConsoleLayer::DEFAULT_SCHEDULED_DURATION_MAX,
+            overhead_sample_rate: 0,
             server_addr: ServerAddr::Tcp(SocketAddr::new(Server::DEFAULT_IP, Server::DEFAULT_PORT)),
+            port_range: None,
             recording_path: None,
+            record_on: None,
             filter_env_var: "RUST_LOG".to_string(),
             self_trace: false,
+            worker_threads: None,
+            task_memory_estimator: None,
+            capture_spawn_backtraces: false,
+            track_user_spans: false,
+            attribute_history: 0,
             #[cfg(feature = "grpc-web")]
             enable_grpc_web: false,
+            #[cfg(feature = "web-ui")]
+            enable_web_ui: false,
+            #[cfg(feature = "otel")]
+            otel_meter: None,
+            clock: Default::default(),
         }
     }
 }
@@ -95,6 +184,29 @@ impl Builder {
         }
     }
 
+    /// Sets the number of shards the channel of events sent from subscriber
+    /// layers to the aggregator task is split into.
+    ///
+    /// Each thread sends its events on one shard, so that threads which
+    /// record events at the same time contend less with each other. The
+    /// [event buffer capacity] is divided evenly between the shards, so a
+    /// thread recording a burst of events may fill its shard and have events
+    /// dropped while the others still have room.
+    ///
+    /// By default, there is one shard per CPU core available to the process.
+    /// Methods like [`init`][`crate::init`] and [`spawn`][`crate::spawn`]
+    /// will take the value from the `TOKIO_CONSOLE_BUFFER_SHARDS`
+    /// [environment variable] before falling back on that default.
+    ///
+    /// [event buffer capacity]: Builder::event_buffer_capacity
+    /// [environment variable]: `Builder::with_default_env`
+    pub fn event_buffer_shards(self, event_buffer_shards: usize) -> Self {
+        Self {
+            event_buffer_shards: Some(event_buffer_shards),
+            ..self
+        }
+    }
+
     /// Sets the maximum capacity of updates to buffer for each subscribed
     /// client, if that client is not reading from the RPC stream.
     ///
@@ -108,6 +220,23 @@ impl Builder {
         }
     }
 
+    /// Sets how many updates in a row a client may fail to be sent, because
+    /// its buffer is full, before it's disconnected.
+    ///
+    /// A client which doesn't keep up is first marked as lagging: whatever it
+    /// couldn't be sent is kept for its next update, and its updates leave
+    /// out new poll ops and resource attribute histories for a while, so that
+    /// it has a chance to catch up. Setting this to 1 disconnects clients as
+    /// soon as their buffer fills up.
+    ///
+    /// By default, this is [`ConsoleLayer::DEFAULT_CLIENT_LAG_LIMIT`].
+    pub fn client_lag_limit(self, client_lag_limit: u32) -> Self {
+        Self {
+            client_lag_limit: client_lag_limit.max(1),
+            ..self
+        }
+    }
+
     /// Sets how frequently updates are published to clients.
     ///
     /// A shorter duration will allow clients to update more frequently, but may
@@ -126,6 +255,37 @@ impl Builder {
         }
     }
 
+    /// Allows the publish interval to be adjusted within `bounds` when the
+    /// aggregator falls behind.
+    ///
+    /// If the event buffer repeatedly nears capacity between two updates, the
+    /// publish interval is doubled, up to the end of `bounds`, and the
+    /// resource poll ops sent in each update are capped, so that less time is
+    /// spent preparing updates. Once an interval passes without the buffer
+    /// nearing capacity, the publish interval is halved again, back down to
+    /// the [publish interval], clamped to `bounds`.
+    ///
+    /// By default, the publish interval is never adjusted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// let builder = console_subscriber::ConsoleLayer::builder()
+    ///     .publish_interval(Duration::from_secs(1))
+    ///     // publish at most every 10 seconds when under load
+    ///     .adaptive_publish(Duration::from_secs(1)..=Duration::from_secs(10));
+    /// ```
+    ///
+    /// [publish interval]: Builder::publish_interval
+    pub fn adaptive_publish(self, bounds: RangeInclusive<Duration>) -> Self {
+        Self {
+            adaptive_publish: Some(bounds),
+            ..self
+        }
+    }
+
     /// Sets how long data is retained for completed tasks.
     ///
     /// A longer duration will allow more historical data to be replayed by
@@ -190,6 +350,37 @@ impl Builder {
         }
     }
 
+    /// Sets a range of ports to fall back on if the [server address] is
+    /// already in use, such as when another instrumented process is running
+    /// on the same host.
+    ///
+    /// If the configured address can't be listened on because it's in use,
+    /// the server tries each port in `ports`, on the same IP address, in
+    /// turn. The address it ends up listening on is logged, and returned by
+    /// [`ConsoleLayer::server_addr`]; the console must be pointed at it.
+    ///
+    /// By default, there is no fallback, and the server fails to start if its
+    /// address is in use. Methods like [`init`][`crate::init`] and
+    /// [`spawn`][`crate::spawn`] will take the range from the
+    /// `TOKIO_CONSOLE_PORT_RANGE` [environment variable], formatted as
+    /// `START-END`, before falling back on that default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use console_subscriber::Builder;
+    /// let builder = Builder::default().port_range(6670..=6679);
+    /// ```
+    ///
+    /// [server address]: Builder::server_addr
+    /// [environment variable]: `Builder::with_default_env`
+    pub fn port_range(self, ports: RangeInclusive<u16>) -> Self {
+        Self {
+            port_range: Some(ports),
+            ..self
+        }
+    }
+
     /// Sets the path to record the events to the file system.
     ///
     /// By default, this is initially `None`. Methods like
@@ -205,6 +396,23 @@ impl Builder {
         }
     }
 
+    /// Only records events to the [recording path] for a while after an
+    /// anomaly described by `policy` occurs, rather than for the whole run of
+    /// the application.
+    ///
+    /// This keeps recordings small enough to leave enabled in production. It
+    /// has no effect unless a [recording path] is set.
+    ///
+    /// By default, all events are recorded.
+    ///
+    /// [recording path]: Builder::recording_path
+    pub fn record_on(self, policy: TriggerPolicy) -> Self {
+        Self {
+            record_on: Some(policy),
+            ..self
+        }
+    }
+
     /// Sets the environment variable used to configure which `tracing` events
     /// are logged to stdout.
     ///
@@ -265,6 +473,145 @@ impl Builder {
         }
     }
 
+    /// Sets how often the time spent in the [`ConsoleLayer`]'s callbacks is
+    /// measured, to estimate the overhead of instrumenting each task.
+    ///
+    /// One in every `sample_rate` callbacks is timed, and the measured time is
+    /// scaled up by the sample rate and attributed to the task the callback
+    /// was handling. The console adds these estimates up per spawn location,
+    /// so they can be used to find which tasks' instrumentation costs the most.
+    ///
+    /// By default, this is 0, which disables overhead sampling. Methods like
+    /// [`init`][`crate::init`] and [`spawn`][`crate::spawn`] will take the
+    /// value from the `TOKIO_CONSOLE_OVERHEAD_SAMPLE_RATE` [environment
+    /// variable] before falling back on that default.
+    ///
+    /// [environment variable]: `Builder::with_default_env`
+    pub fn overhead_sample_rate(self, sample_rate: usize) -> Self {
+        Self {
+            overhead_sample_rate: sample_rate,
+            ..self
+        }
+    }
+
+    /// Sets the number of worker threads used by the instrumented runtime.
+    ///
+    /// This is used to estimate the runtime's saturation: the fraction of the
+    /// available worker time that was spent polling tasks. It does not change
+    /// the number of worker threads the runtime actually uses.
+    ///
+    /// By default, this is the number of CPU cores available to the process,
+    /// which matches the default for Tokio's multi-threaded runtime. Methods
+    /// like [`init`][`crate::init`] and [`spawn`][`crate::spawn`] will take
+    /// the value from the `TOKIO_WORKER_THREADS` [environment variable] before
+    /// falling back on that default.
+    ///
+    /// [environment variable]: `Builder::with_default_env`
+    pub fn worker_threads(self, worker_threads: usize) -> Self {
+        Self {
+            worker_threads: Some(worker_threads),
+            ..self
+        }
+    }
+
+    /// Sets a function which estimates the size of tasks' futures.
+    ///
+    /// Tokio reports the size of each task's future when it is spawned, which
+    /// the console uses to warn about large futures and futures which were
+    /// boxed because they were too large. Runtimes which don't report sizes
+    /// can provide an estimate with this hook instead, for example from a
+    /// table of the sizes of the application's tasks by name.
+    ///
+    /// The estimator is only called for tasks whose size was not reported by
+    /// the runtime. If it returns `None`, the task's size is unknown.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use console_subscriber::TaskMemoryInfo;
+    ///
+    /// fn estimate(task: &TaskMemoryInfo<'_>) -> Option<u64> {
+    ///     match task.name? {
+    ///         "connection" => Some(16 * 1024),
+    ///         _ => None,
+    ///     }
+    /// }
+    ///
+    /// let builder = console_subscriber::ConsoleLayer::builder()
+    ///     .with_task_memory_estimator(estimate);
+    /// ```
+    pub fn with_task_memory_estimator(self, estimator: TaskMemoryEstimator) -> Self {
+        Self {
+            task_memory_estimator: Some(estimator),
+            ..self
+        }
+    }
+
+    /// Sets whether to capture a backtrace where each task is spawned.
+    ///
+    /// The console shows the backtrace in the task's details, which helps
+    /// to find out what a task is when its spawn location is inside a
+    /// library that spawns tasks on the application's behalf. Frames in the
+    /// runtime, the standard library and the instrumentation itself are left
+    /// out, and only the innermost 16 of the rest are kept.
+    ///
+    /// Capturing backtraces slows down spawning tasks, so at most 100 are
+    /// captured each second, and tasks spawned beyond that have none. The
+    /// backtraces are resolved to function names and source locations by the
+    /// aggregator, rather than when tasks are spawned. Source locations are
+    /// only available if the application was built with debug info.
+    ///
+    /// By default, this is `false`.
+    pub fn capture_spawn_backtraces(self, capture_spawn_backtraces: bool) -> Self {
+        Self {
+            capture_spawn_backtraces,
+            ..self
+        }
+    }
+
+    /// Sets whether to record which of the application's own spans each task
+    /// is in while it's being polled.
+    ///
+    /// When this is enabled, task stats include the name of the innermost
+    /// span entered inside the task which isn't part of the runtime, and the
+    /// console shows it while the task is running. This helps to find out
+    /// what part of a long-running task is busy, if its code is instrumented
+    /// with spans.
+    ///
+    /// Every span the application enters is then seen by the console layer,
+    /// so this adds a little overhead to entering and exiting spans. When
+    /// the layer is built with [`spawn`] or [`init`], its filter is widened
+    /// to let the application's spans through; layers added to a subscriber
+    /// by hand must not filter them out.
+    ///
+    /// By default, this is `false`.
+    ///
+    /// [`spawn`]: Builder::spawn
+    /// [`init`]: Builder::init
+    pub fn track_user_spans(self, track_user_spans: bool) -> Self {
+        Self {
+            track_user_spans,
+            ..self
+        }
+    }
+
+    /// Sets the number of past values to keep of each resource attribute,
+    /// such as a semaphore's available permits.
+    ///
+    /// Updates only include an attribute's latest value, so a value which is
+    /// overwritten before the next update is never seen by the console. When
+    /// this is set, updates also include each attribute's last `len` values
+    /// and the times they were set, which the console plots in the resource
+    /// view.
+    ///
+    /// By default, this is 0, and no history is kept.
+    pub fn attribute_history(self, len: usize) -> Self {
+        Self {
+            attribute_history: len,
+            ..self
+        }
+    }
+
     /// Sets whether tasks, resources, and async ops from the console
     /// subscriber thread are recorded.
     ///
@@ -296,6 +643,55 @@ impl Builder {
         }
     }
 
+    /// Sets whether to serve the web UI.
+    ///
+    /// By default, this is `false`. If enabled, the console subscriber will
+    /// serve a web UI showing the application's tasks and resources at `/`,
+    /// along with the JSON gateway, on the same port as the gRPC service.
+    /// As with gRPC-Web, some browsers refuse to connect to the default port,
+    /// 6669, so consider serving the console on another port.
+    ///
+    /// See [`serve_with_web_ui`] for details.
+    ///
+    /// [`serve_with_web_ui`]: crate::Server::serve_with_web_ui
+    #[cfg(feature = "web-ui")]
+    pub fn enable_web_ui(self, enable_web_ui: bool) -> Self {
+        Self {
+            enable_web_ui,
+            ..self
+        }
+    }
+
+    /// Sets an OpenTelemetry [`Meter`] to export task and resource stats to.
+    ///
+    /// Once per publish interval, the aggregator records the number of live
+    /// tasks, resources and async ops, and the polls, busy time, wakes and
+    /// self-wakes of all tasks, to instruments created from this meter. The
+    /// console's own server runs as usual, so the same data can be routed into
+    /// an existing observability stack while still being served to the
+    /// console.
+    ///
+    /// By default, stats are not exported.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let meter = opentelemetry::global::meter("tokio-console");
+    /// let (layer, server) = console_subscriber::ConsoleLayer::builder()
+    ///     .with_otel_meter(meter)
+    ///     .build();
+    /// # drop((layer, server));
+    /// ```
+    ///
+    /// [`Meter`]: opentelemetry::metrics::Meter
+    #[cfg(feature = "otel")]
+    pub fn with_otel_meter(self, meter: opentelemetry::metrics::Meter) -> Self {
+        Self {
+            otel_meter: Some(meter),
+            ..self
+        }
+    }
+
     /// Completes the builder, returning a [`ConsoleLayer`] and [`Server`] task.
     pub fn build(self) -> (ConsoleLayer, Server) {
         ConsoleLayer::build(self)
@@ -303,12 +699,16 @@ impl Builder {
 
     /// Configures this builder from a standard set of environment variables:
     ///
-    /// | **Environment Variable**         | **Purpose**                                                  | **Default Value** |
-    /// |----------------------------------|--------------------------------------------------------------|-------------------|
-    /// | `TOKIO_CONSOLE_RETENTION`        | The duration of seconds to accumulate completed tracing data | 3600s (1h)        |
-    /// | `TOKIO_CONSOLE_BIND`             | a HOST:PORT description, such as `localhost:1234`            | `127.0.0.1:6669`  |
-    /// | `TOKIO_CONSOLE_PUBLISH_INTERVAL` | The duration to wait between sending updates to the console  | 1000ms (1s)       |
-    /// | `TOKIO_CONSOLE_RECORD_PATH`      | The file path to save a recording                            | None              |
+    /// | **Environment Variable**             | **Purpose**                                                  | **Default Value** |
+    /// |--------------------------------------|--------------------------------------------------------------|-------------------|
+    /// | `TOKIO_CONSOLE_RETENTION`            | The duration of seconds to accumulate completed tracing data | 3600s (1h)        |
+    /// | `TOKIO_CONSOLE_BIND`                 | a HOST:PORT description, such as `localhost:1234`            | `127.0.0.1:6669`  |
+    /// | `TOKIO_CONSOLE_PORT_RANGE`           | Ports to fall back on if the address is in use, as START-END | None              |
+    /// | `TOKIO_CONSOLE_PUBLISH_INTERVAL`     | The duration to wait between sending updates to the console  | 1000ms (1s)       |
+    /// | `TOKIO_CONSOLE_RECORD_PATH`          | The file path to save a recording                            | None              |
+    /// | `TOKIO_WORKER_THREADS`               | The number of worker threads used by the runtime             | Number of cores   |
+    /// | `TOKIO_CONSOLE_OVERHEAD_SAMPLE_RATE` | Measure one in this many callbacks to estimate overhead      | 0 (disabled)      |
+    /// | `TOKIO_CONSOLE_BUFFER_SHARDS`        | The number of shards the event buffer is split into          | Number of cores   |
     pub fn with_default_env(mut self) -> Self {
         if let Some(retention) = duration_from_env("TOKIO_CONSOLE_RETENTION") {
             self.retention = retention;
@@ -325,6 +725,15 @@ impl Builder {
             );
         }
 
+        if let Ok(range) = std::env::var("TOKIO_CONSOLE_PORT_RANGE") {
+            self.port_range = Some(port_range_from_str(&range).unwrap_or_else(|| {
+                panic!(
+                    "TOKIO_CONSOLE_PORT_RANGE must be formatted as START-END, such as 6670-6679, not {:?}",
+                    range
+                )
+            }));
+        }
+
         if let Some(interval) = duration_from_env("TOKIO_CONSOLE_PUBLISH_INTERVAL") {
             self.publish_interval = interval;
         }
@@ -337,6 +746,18 @@ impl Builder {
             self.event_buffer_capacity = capacity;
         }
 
+        if let Some(shards) = usize_from_env("TOKIO_CONSOLE_BUFFER_SHARDS") {
+            self.event_buffer_shards = Some(shards);
+        }
+
+        if let Some(worker_threads) = usize_from_env("TOKIO_WORKER_THREADS") {
+            self.worker_threads = Some(worker_threads);
+        }
+
+        if let Some(sample_rate) = usize_from_env("TOKIO_CONSOLE_OVERHEAD_SAMPLE_RATE") {
+            self.overhead_sample_rate = sample_rate;
+        }
+
         self
     }
 
@@ -374,13 +795,17 @@ impl Builder {
     /// use cases. If you need to tune these parameters, several environmental
     /// configuration variables are available:
     ///
-    /// | **Environment Variable**            | **Purpose**                                                               | **Default Value** |
-    /// |-------------------------------------|---------------------------------------------------------------------------|-------------------|
-    /// | `TOKIO_CONSOLE_RETENTION`           | The number of seconds to accumulate completed tracing data                | 3600s (1h)        |
-    /// | `TOKIO_CONSOLE_BIND`                | A HOST:PORT description, such as `localhost:1234`                         | `127.0.0.1:6669`  |
-    /// | `TOKIO_CONSOLE_PUBLISH_INTERVAL`    | The number of milliseconds to wait between sending updates to the console | 1000ms (1s)       |
-    /// | `TOKIO_CONSOLE_RECORD_PATH`         | The file path to save a recording                                         | None              |
-    /// | `RUST_LOG`                          | Configures what events are logged events. See [`Targets`] for details.    | "error"           |
+    /// | **Environment Variable**             | **Purpose**                                                               | **Default Value** |
+    /// |--------------------------------------|---------------------------------------------------------------------------|-------------------|
+    /// | `TOKIO_CONSOLE_RETENTION`            | The number of seconds to accumulate completed tracing data                | 3600s (1h)        |
+    /// | `TOKIO_CONSOLE_BIND`                 | A HOST:PORT description, such as `localhost:1234`                         | `127.0.0.1:6669`  |
+    /// | `TOKIO_CONSOLE_PORT_RANGE`           | Ports to fall back on if the address is in use, such as `6670-6679`       | None              |
+    /// | `TOKIO_CONSOLE_PUBLISH_INTERVAL`     | The number of milliseconds to wait between sending updates to the console | 1000ms (1s)       |
+    /// | `TOKIO_CONSOLE_RECORD_PATH`          | The file path to save a recording                                         | None              |
+    /// | `TOKIO_WORKER_THREADS`               | The number of worker threads used by the runtime                          | Number of cores   |
+    /// | `TOKIO_CONSOLE_OVERHEAD_SAMPLE_RATE` | Measure one in this many callbacks to estimate overhead                   | 0 (disabled)      |
+    /// | `TOKIO_CONSOLE_BUFFER_SHARDS`        | The number of shards the event buffer is split into                       | Number of cores   |
+    /// | `RUST_LOG`                           | Configures what events are logged events. See [`Targets`] for details.    | "error"           |
     ///
     /// If the "env-filter" crate feature flag is enabled, the `RUST_LOG`
     /// environment variable will be parsed using the [`EnvFilter`] type from
@@ -500,7 +925,8 @@ impl Builder {
     where
         S: Subscriber + for<'a> LookupSpan<'a>,
     {
-        fn console_filter(meta: &tracing::Metadata<'_>) -> bool {
+        let track_user_spans = self.track_user_spans;
+        let console_filter = move |meta: &tracing::Metadata<'_>| {
             // events will have *targets* beginning with "runtime"
             if meta.is_event() {
                 return meta.target().starts_with("runtime") || meta.target().starts_with("tokio");
@@ -508,17 +934,21 @@ impl Builder {
 
             // spans will have *names* beginning with "runtime". for backwards
             // compatibility with older Tokio versions, enable anything with the `tokio`
-            // target as well.
-            meta.name().starts_with("runtime.") || meta.target().starts_with("tokio")
-        }
+            // target as well. the application's own spans are only needed
+            // to track which of them tasks are in.
+            track_user_spans
+                || meta.name().starts_with("runtime.")
+                || meta.target().starts_with("tokio")
+        };
 
         let self_trace = self.self_trace;
         #[cfg(feature = "grpc-web")]
         let enable_grpc_web = self.enable_grpc_web;
+        #[cfg(feature = "web-ui")]
+        let enable_web_ui = self.enable_web_ui;
 
         let (layer, server) = self.build();
-        let filter =
-            FilterFn::new(console_filter as for<'r, 's> fn(&'r tracing::Metadata<'s>) -> bool);
+        let filter = FilterFn::new(console_filter);
         let layer = layer.with_filter(filter);
 
         thread::Builder::new()
@@ -535,21 +965,33 @@ impl Builder {
                     .enable_time()
                     .build()
                     .expect("console subscriber runtime initialization failed");
-                runtime.block_on(async move {
+                let result = runtime.block_on(async move {
+                    #[cfg(feature = "web-ui")]
+                    if enable_web_ui {
+                        return server
+                            .serve_with_web_ui(tonic::transport::Server::builder())
+                            .await;
+                    }
+
                     #[cfg(feature = "grpc-web")]
                     if enable_grpc_web {
-                        server
+                        return server
                             .serve_with_grpc_web(tonic::transport::Server::builder())
-                            .await
-                            .expect("console subscriber server failed");
-                        return;
+                            .await;
                     }
 
-                    server
-                        .serve()
-                        .await
-                        .expect("console subscriber server failed")
+                    server.serve().await
                 });
+                if let Err(error) = result {
+                    // The application can carry on without the console, so
+                    // explain why it can't connect rather than panicking.
+                    // This thread's `tracing` events are usually discarded,
+                    // so this is printed instead.
+                    match error.downcast_ref::<BindError>() {
+                        Some(error) => eprintln!("console subscriber not started: {}", error),
+                        None => panic!("console subscriber server failed: {}", error),
+                    }
+                }
             })
             .expect("console subscriber could not spawn thread");
 
@@ -770,6 +1212,13 @@ fn duration_from_env(var_name: &str) -> Option<Duration> {
     }
 }
 
+fn port_range_from_str(range: &str) -> Option<RangeInclusive<u16>> {
+    let (start, end) = range.split_once('-')?;
+    let start = start.trim().parse().ok()?;
+    let end = end.trim().parse().ok()?;
+    (start <= end).then_some(start..=end)
+}
+
 fn usize_from_env(var_name: &str) -> Option<usize> {
     let var = std::env::var(var_name).ok()?;
     match var.parse::<usize>() {
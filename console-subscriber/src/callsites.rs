@@ -41,6 +41,10 @@ impl<const MAX_CALLSITES: usize> Callsites<MAX_CALLSITES> {
         }
     }
 
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len.load(Ordering::Acquire) == 0
+    }
+
     pub(crate) fn contains(&self, callsite: &'static Metadata<'static>) -> bool {
         let mut start = 0;
         let mut len = self.len.load(Ordering::Acquire);
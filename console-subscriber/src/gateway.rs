@@ -0,0 +1,510 @@
+//! A read-only JSON view of the aggregator's state, for clients that don't
+//! speak gRPC.
+//!
+//! Each request takes a fresh snapshot by subscribing to the aggregator just
+//! like a gRPC client would, and converts the initial update (which always
+//! contains the complete state) into JSON.
+use crate::{
+    aggregator::{Domains, StreamUpdate},
+    Command, Watch, WatchRequest,
+};
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use console_api as proto;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, SystemTime},
+};
+use tokio::sync::{mpsc, oneshot};
+use tracing_core::span;
+
+#[derive(Clone)]
+struct Gateway {
+    subscribe: mpsc::Sender<Command>,
+    client_buffer: usize,
+}
+
+#[derive(Serialize)]
+struct Snapshot {
+    now: Option<String>,
+    tasks: Vec<Task>,
+    resources: Vec<Resource>,
+    async_ops: Vec<AsyncOp>,
+}
+
+#[derive(Serialize)]
+struct Task {
+    id: u64,
+    kind: &'static str,
+    target: Option<String>,
+    location: Option<String>,
+    runtime: Option<String>,
+    group: Option<String>,
+    fields: BTreeMap<String, serde_json::Value>,
+    created_at: Option<String>,
+    dropped_at: Option<String>,
+    polls: u64,
+    busy_ns: u64,
+    ready_ns: u64,
+    pending_ns: u64,
+    scheduled_ns: u64,
+    wakes: u64,
+    self_wakes: u64,
+    waker_count: u64,
+    overhead_ns: u64,
+    cancelled: bool,
+    terminated_by: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+struct Resource {
+    id: u64,
+    kind: String,
+    concrete_type: String,
+    target: Option<String>,
+    location: Option<String>,
+    parent_id: Option<u64>,
+    is_internal: bool,
+    created_at: Option<String>,
+    dropped_at: Option<String>,
+    attributes: BTreeMap<String, Attribute>,
+}
+
+#[derive(Serialize)]
+struct AsyncOp {
+    id: u64,
+    source: String,
+    resource_id: Option<u64>,
+    parent_id: Option<u64>,
+    task_id: Option<u64>,
+    created_at: Option<String>,
+    dropped_at: Option<String>,
+    polls: u64,
+    busy_ns: u64,
+    ready_ns: u64,
+    pending_ns: u64,
+    attributes: BTreeMap<String, Attribute>,
+}
+
+/// A task's poll and scheduled time histograms.
+#[derive(Serialize)]
+struct Histograms {
+    poll_times: Option<Histogram>,
+    scheduled_times: Option<Histogram>,
+}
+
+#[derive(Serialize)]
+struct Histogram {
+    count: u64,
+    min_ns: u64,
+    max_ns: u64,
+    p50_ns: u64,
+    p90_ns: u64,
+    p99_ns: u64,
+    /// The number of durations in each of [`Histogram::BUCKETS`] equally wide
+    /// buckets from `min_ns` to `max_ns`.
+    buckets: Vec<u64>,
+    /// The number of durations too long to be recorded in the histogram.
+    high_outliers: u64,
+}
+
+#[derive(Serialize)]
+struct Attribute {
+    value: serde_json::Value,
+    unit: Option<String>,
+}
+
+/// Field names and targets of all the metadata registered so far.
+struct Metadata(HashMap<u64, proto::Metadata>);
+
+/// Returns a router serving the JSON gateway's endpoints.
+pub(crate) fn router(subscribe: mpsc::Sender<Command>, client_buffer: usize) -> Router {
+    Router::new()
+        .route("/json/state", get(state))
+        .route("/json/tasks", get(tasks))
+        .route("/json/resources", get(resources))
+        .route("/json/async_ops", get(async_ops))
+        .route("/json/tasks/:id/histograms", get(histograms))
+        .with_state(Gateway {
+            subscribe,
+            client_buffer,
+        })
+}
+
+async fn state(State(gateway): State<Gateway>) -> Result<impl IntoResponse, StatusCode> {
+    json(&gateway.snapshot().await?)
+}
+
+async fn tasks(State(gateway): State<Gateway>) -> Result<impl IntoResponse, StatusCode> {
+    json(&gateway.snapshot().await?.tasks)
+}
+
+async fn resources(State(gateway): State<Gateway>) -> Result<impl IntoResponse, StatusCode> {
+    json(&gateway.snapshot().await?.resources)
+}
+
+async fn async_ops(State(gateway): State<Gateway>) -> Result<impl IntoResponse, StatusCode> {
+    json(&gateway.snapshot().await?.async_ops)
+}
+
+async fn histograms(
+    State(gateway): State<Gateway>,
+    Path(id): Path<u64>,
+) -> Result<impl IntoResponse, StatusCode> {
+    json(&gateway.histograms(id).await?)
+}
+
+fn json(value: &impl Serialize) -> Result<impl IntoResponse, StatusCode> {
+    let body = serde_json::to_vec(value).map_err(|error| {
+        tracing::warn!(%error, "failed to serialize JSON response");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(([(header::CONTENT_TYPE, "application/json")], body))
+}
+
+// === impl Gateway ===
+
+impl Gateway {
+    async fn snapshot(&self) -> Result<Snapshot, StatusCode> {
+        let permit = self
+            .subscribe
+            .reserve()
+            .await
+            .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+        let (tx, mut rx) = mpsc::channel(self.client_buffer);
+        permit.send(Command::Instrument {
+            watch: Watch(tx),
+            domains: Domains::ALL,
+            interval: None,
+        });
+        // The first update sent to a new subscription with any state contains
+        // the complete state, and may be preceded by updates carrying only
+        // metadata. Dropping the receiver afterwards unsubscribes us again.
+        let mut metadata = Vec::new();
+        let mut update = loop {
+            let update = rx
+                .recv()
+                .await
+                .and_then(Result::ok)
+                .map(StreamUpdate::take)
+                .ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+            if update.task_update.is_some() {
+                break update;
+            }
+            metadata.extend(update.new_metadata.into_iter().flat_map(|new| new.metadata));
+        };
+        let new_metadata = update.new_metadata.get_or_insert_with(Default::default);
+        metadata.append(&mut new_metadata.metadata);
+        new_metadata.metadata = metadata;
+        Ok(Snapshot::from_update(update))
+    }
+
+    /// Returns the current histograms of the task with the span ID `id`.
+    async fn histograms(&self, id: u64) -> Result<Histograms, StatusCode> {
+        let id = std::num::NonZeroU64::new(id)
+            .map(span::Id::from_non_zero_u64)
+            .ok_or(StatusCode::NOT_FOUND)?;
+        let permit = self
+            .subscribe
+            .reserve()
+            .await
+            .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+        let (stream_sender, stream_recv) = oneshot::channel();
+        permit.send(Command::WatchTaskDetail(WatchRequest {
+            id,
+            stream_sender,
+            buffer: self.client_buffer,
+            histograms: proto::instrument::HistogramSelection {
+                poll_times: true,
+                scheduled_times: true,
+                recent_poll_times: false,
+            },
+        }));
+        // The aggregator drops the sender if there's no such task, and
+        // otherwise sends the task's details as soon as the watch starts.
+        let mut rx = stream_recv.await.map_err(|_| StatusCode::NOT_FOUND)?;
+        let details = rx
+            .recv()
+            .await
+            .and_then(Result::ok)
+            .ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+        let poll_times = match &details.poll_times_histogram {
+            Some(proto::tasks::task_details::PollTimesHistogram::Histogram(histogram)) => {
+                Histogram::from_proto(histogram)
+            }
+            _ => None,
+        };
+        Ok(Histograms {
+            poll_times,
+            scheduled_times: details
+                .scheduled_times_histogram
+                .as_ref()
+                .and_then(Histogram::from_proto),
+        })
+    }
+}
+
+// === impl Snapshot ===
+
+impl Snapshot {
+    fn from_update(update: proto::instrument::Update) -> Self {
+        let metadata = Metadata(
+            update
+                .new_metadata
+                .into_iter()
+                .flat_map(|new_metadata| new_metadata.metadata)
+                .filter_map(|new| Some((new.id?.id, new.metadata?)))
+                .collect(),
+        );
+
+        let tasks = update.task_update.map_or_else(Vec::new, |update| {
+            let mut stats = update.stats_update;
+            update
+                .new_tasks
+                .into_iter()
+                .filter_map(|task| {
+                    let id = task.id?.id;
+                    Some(Task::new(task, stats.remove(&id), &metadata))
+                })
+                .collect()
+        });
+        let resources = update.resource_update.map_or_else(Vec::new, |update| {
+            let mut stats = update.stats_update;
+            update
+                .new_resources
+                .into_iter()
+                .filter_map(|resource| {
+                    let id = resource.id?.id;
+                    Some(Resource::new(resource, stats.remove(&id), &metadata))
+                })
+                .collect()
+        });
+        let async_ops = update.async_op_update.map_or_else(Vec::new, |update| {
+            let mut stats = update.stats_update;
+            update
+                .new_async_ops
+                .into_iter()
+                .filter_map(|async_op| {
+                    let id = async_op.id?.id;
+                    Some(AsyncOp::new(async_op, stats.remove(&id), &metadata))
+                })
+                .collect()
+        });
+
+        Self {
+            now: update.now.and_then(timestamp),
+            tasks,
+            resources,
+            async_ops,
+        }
+    }
+}
+
+// === impl Task ===
+
+impl Task {
+    fn new(
+        task: proto::tasks::Task,
+        stats: Option<proto::tasks::Stats>,
+        metadata: &Metadata,
+    ) -> Self {
+        let stats = stats.unwrap_or_default();
+        let poll_stats = stats.poll_stats.unwrap_or_default();
+        let kind = match proto::tasks::task::Kind::try_from(task.kind) {
+            Ok(proto::tasks::task::Kind::Blocking) => "blocking",
+            _ => "spawn",
+        };
+        let terminated_by = match proto::tasks::stats::TerminatedBy::try_from(stats.terminated_by) {
+            Ok(proto::tasks::stats::TerminatedBy::Completed) => Some("completed"),
+            Ok(proto::tasks::stats::TerminatedBy::Panicked) => Some("panicked"),
+            Ok(proto::tasks::stats::TerminatedBy::Aborted) => Some("aborted"),
+            Ok(proto::tasks::stats::TerminatedBy::NotTerminated) | Err(_) => None,
+        };
+        Self {
+            id: task.id.map_or(0, |id| id.id),
+            kind,
+            target: metadata.target(task.metadata),
+            location: task.location.as_ref().and_then(location),
+            runtime: task.runtime,
+            group: task.group,
+            fields: task
+                .fields
+                .into_iter()
+                .filter_map(|field| metadata.field(field))
+                .collect(),
+            created_at: stats.created_at.and_then(timestamp),
+            dropped_at: stats.dropped_at.and_then(timestamp),
+            polls: poll_stats.polls,
+            busy_ns: nanos(poll_stats.busy_time),
+            ready_ns: nanos(poll_stats.ready_time),
+            pending_ns: nanos(poll_stats.pending_time),
+            scheduled_ns: nanos(stats.scheduled_time),
+            wakes: stats.wakes,
+            self_wakes: stats.self_wakes,
+            waker_count: stats.waker_clones.saturating_sub(stats.waker_drops),
+            overhead_ns: nanos(stats.instrumentation_overhead),
+            cancelled: stats.cancelled,
+            terminated_by,
+        }
+    }
+}
+
+// === impl Resource ===
+
+impl Resource {
+    fn new(
+        resource: proto::resources::Resource,
+        stats: Option<proto::resources::Stats>,
+        metadata: &Metadata,
+    ) -> Self {
+        use proto::resources::resource::kind::{Kind, Known};
+
+        let stats = stats.unwrap_or_default();
+        let kind = match resource.kind.and_then(|kind| kind.kind) {
+            Some(Kind::Known(known)) if known == Known::Timer as i32 => "Timer".to_string(),
+            Some(Kind::Other(other)) => other,
+            _ => "Unknown".to_string(),
+        };
+        Self {
+            id: resource.id.map_or(0, |id| id.id),
+            kind,
+            concrete_type: resource.concrete_type,
+            target: metadata.target(resource.metadata),
+            location: resource.location.as_ref().and_then(location),
+            parent_id: resource.parent_resource_id.map(|id| id.id),
+            is_internal: resource.is_internal,
+            created_at: stats.created_at.and_then(timestamp),
+            dropped_at: stats.dropped_at.and_then(timestamp),
+            attributes: metadata.attributes(stats.attributes),
+        }
+    }
+}
+
+// === impl AsyncOp ===
+
+impl AsyncOp {
+    fn new(
+        async_op: proto::async_ops::AsyncOp,
+        stats: Option<proto::async_ops::Stats>,
+        metadata: &Metadata,
+    ) -> Self {
+        let stats = stats.unwrap_or_default();
+        let poll_stats = stats.poll_stats.unwrap_or_default();
+        Self {
+            id: async_op.id.map_or(0, |id| id.id),
+            source: async_op.source,
+            resource_id: async_op.resource_id.map(|id| id.id),
+            parent_id: async_op.parent_async_op_id.map(|id| id.id),
+            task_id: stats.task_id.map(|id| id.id),
+            created_at: stats.created_at.and_then(timestamp),
+            dropped_at: stats.dropped_at.and_then(timestamp),
+            polls: poll_stats.polls,
+            busy_ns: nanos(poll_stats.busy_time),
+            ready_ns: nanos(poll_stats.ready_time),
+            pending_ns: nanos(poll_stats.pending_time),
+            attributes: metadata.attributes(stats.attributes),
+        }
+    }
+}
+
+// === impl Histogram ===
+
+impl Histogram {
+    const BUCKETS: u64 = 32;
+
+    fn from_proto(durations: &proto::tasks::DurationHistogram) -> Option<Self> {
+        let histogram: hdrhistogram::Histogram<u64> =
+            hdrhistogram::serialization::Deserializer::new()
+                .deserialize(&mut &durations.raw_histogram[..])
+                .map_err(|error| tracing::warn!(%error, "failed to deserialize histogram"))
+                .ok()?;
+        let (min, max) = (histogram.min(), histogram.max());
+        let mut buckets = vec![0; Self::BUCKETS as usize];
+        if !histogram.is_empty() {
+            let width = ((max - min) / Self::BUCKETS).max(1);
+            for value in histogram.iter_recorded() {
+                let bucket =
+                    (value.value_iterated_to().saturating_sub(min) / width).min(Self::BUCKETS - 1);
+                buckets[bucket as usize] += value.count_at_value();
+            }
+        }
+        Some(Self {
+            count: histogram.len(),
+            min_ns: min,
+            max_ns: max,
+            p50_ns: histogram.value_at_quantile(0.5),
+            p90_ns: histogram.value_at_quantile(0.9),
+            p99_ns: histogram.value_at_quantile(0.99),
+            buckets,
+            high_outliers: durations.high_outliers,
+        })
+    }
+}
+
+// === impl Metadata ===
+
+impl Metadata {
+    fn target(&self, id: Option<proto::MetaId>) -> Option<String> {
+        self.0.get(&id?.id).map(|meta| meta.target.clone())
+    }
+
+    fn field(&self, field: proto::Field) -> Option<(String, serde_json::Value)> {
+        use proto::field::{Name, Value};
+
+        let name = match field.name? {
+            Name::StrName(name) => name,
+            Name::NameIdx(idx) => self
+                .0
+                .get(&field.metadata_id?.id)?
+                .field_names
+                .get(idx as usize)?
+                .clone(),
+        };
+        let value = match field.value? {
+            Value::DebugVal(value) | Value::StrVal(value) => value.into(),
+            Value::U64Val(value) => value.into(),
+            Value::I64Val(value) => value.into(),
+            Value::BoolVal(value) => value.into(),
+        };
+        Some((name, value))
+    }
+
+    fn attributes(&self, attributes: Vec<proto::Attribute>) -> BTreeMap<String, Attribute> {
+        attributes
+            .into_iter()
+            .filter_map(|attribute| {
+                let (name, value) = self.field(attribute.field?)?;
+                let unit = attribute.unit;
+                Some((name, Attribute { value, unit }))
+            })
+            .collect()
+    }
+}
+
+fn timestamp(timestamp: prost_types::Timestamp) -> Option<String> {
+    let time = SystemTime::try_from(timestamp).ok()?;
+    Some(humantime::format_rfc3339_nanos(time).to_string())
+}
+
+fn nanos(duration: Option<prost_types::Duration>) -> u64 {
+    duration
+        .and_then(|duration| Duration::try_from(duration).ok())
+        .map_or(0, |duration| duration.as_nanos() as u64)
+}
+
+fn location(location: &proto::Location) -> Option<String> {
+    let mut formatted = location.file.clone()?;
+    if let Some(line) = location.line {
+        formatted.push_str(&format!(":{}", line));
+        if let Some(column) = location.column {
+            formatted.push_str(&format!(":{}", column));
+        }
+    }
+    Some(formatted)
+}
@@ -0,0 +1,85 @@
+//! Shares the string values of span fields which are usually the same for
+//! every span created at a callsite.
+//!
+//! Fields such as a spawned task's `kind` and `loc.file` are recorded with the
+//! same value each time a callsite's span is created. Rather than allocating a
+//! new string for every span, the value last recorded for each field is kept,
+//! and shared for as long as the field keeps being recorded with that value.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{self, Write},
+    mem,
+    sync::Arc,
+};
+use tracing_core::field::Field;
+
+/// The most fields whose last value is kept on each thread.
+///
+/// Callsites are static, so this is only reached by an application with a
+/// very large number of them. The values are then all dropped, and kept again
+/// as fields are recorded.
+const MAX_FIELDS: usize = 1024;
+
+thread_local! {
+    static VALUES: RefCell<Values> = RefCell::new(Values::default());
+}
+
+#[derive(Default)]
+struct Values {
+    by_field: HashMap<Field, Arc<str>>,
+    /// Reused to format `Debug` values before they're looked up.
+    scratch: String,
+}
+
+/// Returns `value`, as recorded for `field`, as a shared string.
+pub(crate) fn str_value(field: &Field, value: &str) -> Arc<str> {
+    VALUES
+        .try_with(|values| {
+            let mut values = values.try_borrow_mut().ok()?;
+            Some(values.get(field, value))
+        })
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| Arc::from(value))
+}
+
+/// Returns `value`, as recorded for `field`, formatted with `Debug`, as a
+/// shared string.
+pub(crate) fn debug_value(field: &Field, value: &dyn fmt::Debug) -> Arc<str> {
+    // The value isn't formatted while the values are borrowed, in case its
+    // `Debug` implementation creates spans of its own.
+    let mut scratch = VALUES
+        .try_with(|values| {
+            values
+                .try_borrow_mut()
+                .map(|mut values| mem::take(&mut values.scratch))
+                .unwrap_or_default()
+        })
+        .unwrap_or_default();
+    scratch.clear();
+    let _ = write!(scratch, "{:?}", value);
+    let value = str_value(field, &scratch);
+    let _ = VALUES.try_with(|values| {
+        if let Ok(mut values) = values.try_borrow_mut() {
+            values.scratch = scratch;
+        }
+    });
+    value
+}
+
+impl Values {
+    fn get(&mut self, field: &Field, value: &str) -> Arc<str> {
+        if let Some(last) = self.by_field.get(field) {
+            if &**last == value {
+                return last.clone();
+            }
+        } else if self.by_field.len() >= MAX_FIELDS {
+            self.by_field.clear();
+        }
+        let value = Arc::<str>::from(value);
+        self.by_field.insert(field.clone(), value.clone());
+        value
+    }
+}
@@ -1,23 +1,25 @@
 #![doc = include_str!("../README.md")]
 use console_api as proto;
 use proto::{instrument::instrument_server::InstrumentServer, resources::resource};
-use serde::Serialize;
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     fmt,
-    net::{IpAddr, Ipv4Addr},
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    ops::RangeInclusive,
     sync::{
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
         Arc,
     },
     time::{Duration, Instant},
 };
 use thread_local::ThreadLocal;
+use tokio::net::TcpListener;
 #[cfg(unix)]
 use tokio::net::UnixListener;
 use tokio::sync::{mpsc, oneshot};
 #[cfg(unix)]
 use tokio_stream::wrappers::UnixListenerStream;
+use tonic::transport::server::TcpIncoming;
 use tracing_core::{
     span::{self, Id},
     subscriber::{self, Subscriber},
@@ -31,24 +33,51 @@ use tracing_subscriber::{
 
 mod aggregator;
 mod attribute;
+mod bind;
 mod builder;
 mod callsites;
-mod record;
+#[cfg(feature = "json-gateway")]
+mod gateway;
+mod intern;
+mod macros;
+pub mod record;
+mod runtime;
+mod shards;
+mod shutdown;
+mod spawn_backtrace;
 mod stack;
 mod stats;
 pub(crate) mod sync;
+#[cfg(feature = "test-support")]
+pub mod test_support;
 mod visitors;
+#[cfg(feature = "web-ui")]
+mod web_ui;
 
 pub use aggregator::Aggregator;
-pub use builder::{Builder, ServerAddr};
+pub use bind::BindError;
+pub use builder::{Builder, ServerAddr, TaskMemoryEstimator, TaskMemoryInfo};
 use callsites::Callsites;
-use record::Recorder;
+pub use record::TriggerPolicy;
+use record::{Recorder, WakeOp};
+use shutdown::Shutdown;
+pub use shutdown::ShutdownHandle;
 use stack::SpanStack;
-use visitors::{AsyncOpVisitor, ResourceVisitor, ResourceVisitorResult, TaskVisitor, WakerVisitor};
+use visitors::{
+    AsyncOpVisitor, GroupVisitor, ResourceVisitor, ResourceVisitorResult, TaskVisitor, WakerVisitor,
+};
 
 pub use builder::{init, spawn};
 
-use crate::visitors::{PollOpVisitor, StateUpdateVisitor};
+use crate::visitors::{
+    PollOpVisitor, StateUpdateVisitor, TaskFields, TaskLocation, TaskVisitorResult,
+};
+
+#[doc(hidden)]
+pub mod __private {
+    //! Re-exports used by this crate's macros. Not public API.
+    pub use tracing;
+}
 
 /// A [`ConsoleLayer`] is a [`tracing_subscriber::Layer`] that records [`tracing`]
 /// spans and events emitted by the async runtime.
@@ -62,7 +91,16 @@ use crate::visitors::{PollOpVisitor, StateUpdateVisitor};
 /// [`tracing`]: https://docs.rs/tracing
 pub struct ConsoleLayer {
     current_spans: ThreadLocal<RefCell<SpanStack>>,
-    tx: mpsc::Sender<Event>,
+    /// The task span most recently exited on each thread, if nothing else has
+    /// been recorded on the thread since. A task whose span is entered again
+    /// straight away is having its completed future dropped.
+    last_exited: ThreadLocal<Cell<Option<span::Id>>>,
+    /// Whether each thread is unwinding from a panic, and if so, the task
+    /// that was executing on it when the unwind started. Only that task is
+    /// blamed for the panic: other tasks can be polled or dropped while a
+    /// thread unwinds, such as when a runtime is dropped during a panic.
+    unwinding_from: ThreadLocal<Cell<Option<Option<span::Id>>>>,
+    tx: shards::Sender<Event>,
     shared: Arc<Shared>,
     /// When the channel capacity goes under this number, a flush in the aggregator
     /// will be triggered.
@@ -82,6 +120,10 @@ pub struct ConsoleLayer {
     /// they might all have their own sets of waker ops.
     waker_callsites: Callsites<16>,
 
+    /// Set of callsites for spans with a `console.group` field, which assigns
+    /// the tasks spawned within them to a group.
+    group_callsites: Callsites<32>,
+
     /// Set of callsites for spans representing resources
     ///
     /// TODO: Take some time to determine more reasonable numbers
@@ -113,7 +155,7 @@ pub struct ConsoleLayer {
     async_op_state_update_callsites: Callsites<32>,
 
     /// A sink to record all events to a file.
-    recorder: Option<Recorder>,
+    recorder: Option<Arc<Recorder>>,
 
     /// Used to anchor monotonic timestamps to a base `SystemTime`, to produce a
     /// timestamp that can be sent over the wire or recorded to JSON.
@@ -128,6 +170,30 @@ pub struct ConsoleLayer {
     ///
     /// By default, this is one second.
     max_scheduled_duration_nanos: u64,
+
+    /// One in every `overhead_sample_rate` task callbacks is timed, to
+    /// estimate the overhead of instrumenting each task. If this is 0,
+    /// callbacks are never timed.
+    overhead_sample_rate: usize,
+
+    /// Per-thread counts of task callbacks, used to decide which callbacks to
+    /// time.
+    overhead_samples: ThreadLocal<Cell<usize>>,
+
+    /// Estimates the size of tasks whose size the runtime does not report.
+    task_memory_estimator: Option<TaskMemoryEstimator>,
+
+    /// Captures backtraces where tasks are spawned, if enabled.
+    spawn_backtraces: Option<spawn_backtrace::SpawnBacktraces>,
+
+    /// Whether to record the innermost user span each task is in.
+    track_user_spans: bool,
+
+    /// The number of past values of each resource attribute to keep.
+    attribute_history: usize,
+
+    /// Where the times that events happen at are read from.
+    clock: aggregator::Clock,
 }
 
 /// A gRPC [`Server`] that implements the [`tokio-console` wire format][wire].
@@ -144,8 +210,12 @@ pub struct ConsoleLayer {
 pub struct Server {
     subscribe: mpsc::Sender<Command>,
     addr: ServerAddr,
+    /// Ports to try if `addr` is in use.
+    port_range: Option<RangeInclusive<u16>>,
     aggregator: Option<Aggregator>,
     client_buffer: usize,
+    shutdown: Arc<Shutdown>,
+    recorder: Option<Arc<Recorder>>,
 }
 
 pub(crate) trait ToProto {
@@ -171,22 +241,91 @@ struct Shared {
     /// A counter of how many resource events were dropped because the event buffer
     /// was at capacity.
     dropped_resources: AtomicUsize,
+
+    /// Counters of how many events of each [`EventKind`] were dropped because
+    /// the event buffer was at capacity, since the aggregator last published
+    /// them. Poll ops the aggregator sheds while it's under load are counted
+    /// here too.
+    dropped_by_kind: [AtomicUsize; EventKind::COUNT],
+
+    /// The total time, in nanoseconds, spent polling tasks since the
+    /// aggregator last published runtime stats.
+    busy_nanos: AtomicU64,
+
+    /// Whether any client is watching tasks, set by the aggregator.
+    ///
+    /// While this is unset, the layer only records the fields needed to
+    /// identify newly spawned tasks, rather than all of their fields.
+    tasks_watched: AtomicBool,
 }
 
-struct Watch<T>(mpsc::Sender<Result<T, tonic::Status>>);
+/// The sending side of a stream of messages to a client.
+///
+/// Messages are shared between every client they are sent to, and only
+/// cloned when a client's stream takes one which is still shared, so that a
+/// message isn't copied for each client while the aggregator is publishing.
+struct Watch<T>(mpsc::Sender<Result<Arc<T>, tonic::Status>>);
+
+/// The receiving side of a stream of messages sent on a [`Watch`].
+type WatchStream<T> = tokio_stream::adapters::Map<
+    tokio_stream::wrappers::ReceiverStream<Result<Arc<T>, tonic::Status>>,
+    fn(Result<Arc<T>, tonic::Status>) -> Result<T, tonic::Status>,
+>;
+
+/// The receiving side of an update stream.
+type UpdateStream = tokio_stream::adapters::Map<
+    tokio_stream::wrappers::ReceiverStream<Result<Arc<aggregator::StreamUpdate>, tonic::Status>>,
+    fn(
+        Result<Arc<aggregator::StreamUpdate>, tonic::Status>,
+    ) -> Result<proto::instrument::Update, tonic::Status>,
+>;
+
+/// The group that tasks spawned within a span belong to, stored in the
+/// extensions of spans with a `console.group` field, and of grouped tasks.
+struct TaskGroup(String);
 
 enum Command {
-    Instrument(Watch<proto::instrument::Update>),
+    Instrument {
+        watch: Watch<aggregator::StreamUpdate>,
+        domains: aggregator::Domains,
+        /// How often the client asked to be sent updates, if it chose.
+        interval: Option<Duration>,
+    },
     WatchTaskDetail(WatchRequest<proto::tasks::TaskDetails>),
-    WatchState(Watch<proto::instrument::State>),
-    Pause,
-    Resume,
+    WatchState {
+        stream_id: u64,
+        watch: Watch<proto::instrument::State>,
+    },
+    Pause(u64),
+    Resume(u64),
+    DebugSelf(oneshot::Sender<proto::instrument::SelfMetrics>),
+    Snapshot(oneshot::Sender<Vec<proto::instrument::Update>>),
 }
 
+/// The histograms sent to task details streams which don't select any.
+const ALL_HISTOGRAMS: proto::instrument::HistogramSelection =
+    proto::instrument::HistogramSelection {
+        poll_times: true,
+        scheduled_times: true,
+        recent_poll_times: true,
+    };
+
 struct WatchRequest<T> {
     id: Id,
-    stream_sender: oneshot::Sender<mpsc::Receiver<Result<T, tonic::Status>>>,
+    stream_sender: oneshot::Sender<mpsc::Receiver<Result<Arc<T>, tonic::Status>>>,
     buffer: usize,
+    /// The histograms to include in the stream's updates.
+    histograms: proto::instrument::HistogramSelection,
+}
+
+/// The kinds of [`Event`], for counting how many of each were dropped.
+#[derive(Clone, Copy, Debug)]
+enum EventKind {
+    Metadata,
+    Spawn,
+    Resource,
+    PollOp,
+    AsyncResourceOp,
 }
 
 #[derive(Debug)]
@@ -196,8 +335,14 @@ enum Event {
         id: span::Id,
         metadata: &'static Metadata<'static>,
         stats: Arc<stats::TaskStats>,
-        fields: Vec<proto::Field>,
-        location: Option<proto::Location>,
+        fields: TaskFields,
+        location: Option<TaskLocation>,
+        kind: proto::tasks::task::Kind,
+        runtime: Option<Arc<str>>,
+        group: Option<String>,
+        size_bytes: Option<u64>,
+        original_size_bytes: Option<u64>,
+        backtrace: Option<backtrace::Backtrace>,
     },
     Resource {
         id: span::Id,
@@ -228,14 +373,6 @@ enum Event {
     },
 }
 
-#[derive(Clone, Debug, Copy, Serialize)]
-enum WakeOp {
-    Wake { self_wake: bool },
-    WakeByRef { self_wake: bool },
-    Clone,
-    Drop,
-}
-
 impl ConsoleLayer {
     /// Returns a `ConsoleLayer` built with the default settings.
     ///
@@ -247,6 +384,20 @@ impl ConsoleLayer {
         Self::builder().build()
     }
 
+    /// Returns the TCP address the console [`Server`] is listening on, once it
+    /// has started.
+    ///
+    /// This may differ from the configured [server address] if that was in
+    /// use and a [port range] was given to fall back on. If more than one
+    /// server has been started, this is the address of the most recent one.
+    /// Servers listening on a Unix domain socket aren't reported.
+    ///
+    /// [server address]: Builder::server_addr
+    /// [port range]: Builder::port_range
+    pub fn server_addr() -> Option<SocketAddr> {
+        bind::local_addr()
+    }
+
     /// Returns a [`Builder`] for configuring a `ConsoleLayer`.
     ///
     /// Note that the returned builder does *not* include values provided via
@@ -256,6 +407,47 @@ impl ConsoleLayer {
         Builder::default()
     }
 
+    /// Labels the runtime that the current thread belongs to.
+    ///
+    /// When an application runs more than one runtime, this makes it possible
+    /// to tell which runtime each task was spawned on: tasks spawned from this
+    /// thread will be reported to the console with the given runtime label.
+    ///
+    /// This should be called on each of a runtime's threads, which is most
+    /// easily done using [`on_thread_start`] when building the runtime. If the
+    /// runtime is driven by [`Runtime::block_on`], this should also be called
+    /// on the thread that calls `block_on`.
+    ///
+    /// If no label has been registered for a thread, the thread's name is used
+    /// as the runtime label instead. Tokio names the worker threads of every
+    /// runtime `tokio-runtime-worker` unless it's told otherwise, so the
+    /// tasks of runtimes which are neither registered here nor given their own
+    /// [`thread_name`] all have the same label, and can't be told apart.
+    ///
+    /// The label is that of the thread which spawns a task, not of the runtime
+    /// the task is spawned onto, so a task spawned through another runtime's
+    /// [`Handle`], or from a thread outside any runtime, is labelled with the
+    /// spawning thread's runtime.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use console_subscriber::ConsoleLayer;
+    ///
+    /// let blocking_pool = tokio::runtime::Builder::new_multi_thread()
+    ///     .on_thread_start(|| ConsoleLayer::register_runtime("blocking-pool"))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    ///
+    /// [`on_thread_start`]: tokio::runtime::Builder::on_thread_start
+    /// [`thread_name`]: tokio::runtime::Builder::thread_name
+    /// [`Handle`]: tokio::runtime::Handle
+    /// [`Runtime::block_on`]: tokio::runtime::Runtime::block_on
+    pub fn register_runtime(label: impl Into<Arc<str>>) {
+        runtime::register(label.into());
+    }
+
     fn build(config: Builder) -> (Self, Server) {
         // The `cfg` value *appears* to be a constant to clippy, but it changes
         // depending on the build-time configuration...
@@ -269,6 +461,7 @@ impl ConsoleLayer {
         tracing::debug!(
             config.event_buffer_capacity,
             config.client_buffer_capacity,
+            config.client_lag_limit,
             ?config.publish_interval,
             ?config.retention,
             ?config.server_addr,
@@ -280,30 +473,52 @@ impl ConsoleLayer {
             "configured console subscriber"
         );
 
-        let (tx, events) = mpsc::channel(config.event_buffer_capacity);
+        let shards = config.event_buffer_shards.unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(usize::from)
+                .unwrap_or(1)
+        });
+        let shard_capacity = config.event_buffer_capacity.div_ceil(shards.max(1));
+        let (tx, events) = shards::channel(shards, shard_capacity);
         let (subscribe, rpcs) = mpsc::channel(256);
         let shared = Arc::new(Shared::default());
-        let aggregator = Aggregator::new(events, rpcs, &config, shared.clone(), base_time.clone());
-        // Conservatively, start to trigger a flush when half the channel is full.
+        let shutdown = Arc::new(Shutdown::default());
+        let aggregator = Aggregator::new(
+            events,
+            tx.downgrade(),
+            rpcs,
+            &config,
+            shared.clone(),
+            shutdown.clone(),
+            base_time.clone(),
+        );
+        // Conservatively, start to trigger a flush when half of a shard is full.
         // This tries to reduce the chance of losing events to a full channel.
-        let flush_under_capacity = config.event_buffer_capacity / 2;
+        let flush_under_capacity = shard_capacity / 2;
         let recorder = config
             .recording_path
             .as_ref()
-            .map(|path| Recorder::new(path).expect("creating recorder"));
+            .map(|path| Recorder::new(path, config.record_on.clone()).expect("creating recorder"))
+            .map(Arc::new);
         let server = Server {
             aggregator: Some(aggregator),
             addr: config.server_addr,
+            port_range: config.port_range,
             subscribe,
             client_buffer: config.client_buffer_capacity,
+            shutdown,
+            recorder: recorder.clone(),
         };
         let layer = Self {
             current_spans: ThreadLocal::new(),
+            last_exited: ThreadLocal::new(),
+            unwinding_from: ThreadLocal::new(),
             tx,
             shared,
             flush_under_capacity,
             spawn_callsites: Callsites::default(),
             waker_callsites: Callsites::default(),
+            group_callsites: Callsites::default(),
             resource_callsites: Callsites::default(),
             async_op_callsites: Callsites::default(),
             async_op_poll_callsites: Callsites::default(),
@@ -314,6 +529,15 @@ impl ConsoleLayer {
             base_time,
             max_poll_duration_nanos: config.poll_duration_max.as_nanos() as u64,
             max_scheduled_duration_nanos: config.scheduled_duration_max.as_nanos() as u64,
+            overhead_sample_rate: config.overhead_sample_rate,
+            overhead_samples: ThreadLocal::new(),
+            task_memory_estimator: config.task_memory_estimator,
+            spawn_backtraces: config
+                .capture_spawn_backtraces
+                .then(spawn_backtrace::SpawnBacktraces::new),
+            track_user_spans: config.track_user_spans,
+            attribute_history: config.attribute_history,
+            clock: config.clock.clone(),
         };
         (layer, server)
     }
@@ -338,6 +562,12 @@ impl ConsoleLayer {
     /// See also [`Builder::client_buffer_capacity`].
     pub const DEFAULT_CLIENT_BUFFER_CAPACITY: usize = 1024 * 4;
 
+    /// Default number of updates in a row a client may fail to be sent before
+    /// it's disconnected.
+    ///
+    /// See also [`Builder::client_lag_limit`].
+    pub const DEFAULT_CLIENT_LAG_LIMIT: u32 = 3;
+
     /// Default frequency for publishing events to clients.
     ///
     /// Note that methods like [`init`][`crate::init`] and [`spawn`][`crate::spawn`] will take the value
@@ -417,6 +647,104 @@ impl ConsoleLayer {
             .unwrap_or(false)
     }
 
+    /// Returns the group of a newly spawned task.
+    ///
+    /// This is the task span's own `console.group` field, if it has one, or
+    /// else the group of the closest span in the scope the task was spawned
+    /// in that has one.
+    fn task_group<S>(&self, attrs: &span::Attributes<'_>, cx: &Context<'_, S>) -> Option<String>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        if self.group_callsites.is_empty() {
+            return None;
+        }
+
+        if self.group_callsites.contains(attrs.metadata()) {
+            let mut visitor = GroupVisitor::default();
+            attrs.record(&mut visitor);
+            if let Some(group) = visitor.result() {
+                return Some(group);
+            }
+        }
+
+        // Runtimes usually create task spans with no parent, so that tasks
+        // don't appear to be part of the span they were spawned in, so look
+        // at the span that was current when the task was spawned instead.
+        let parent = attrs
+            .parent()
+            .cloned()
+            .or_else(|| cx.current_span().id().cloned())?;
+        cx.span_scope(&parent)?.find_map(|span| {
+            span.extensions()
+                .get::<TaskGroup>()
+                .map(|group| group.0.clone())
+        })
+    }
+
+    /// Returns `true` if a span that isn't a task, resource, or async op
+    /// belongs to the application, rather than to the runtime.
+    fn is_user_span(meta: &'static Metadata<'static>) -> bool {
+        !meta.name().starts_with("runtime.") && !meta.target().starts_with("tokio")
+    }
+
+    /// Records the innermost user span the task being polled on this thread
+    /// is in, after a user span is entered or exited.
+    fn update_current_span<S>(&self, cx: &Context<'_, S>)
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let Some(task_id) = self
+            .current_spans
+            .get()
+            .and_then(|stack| self.first_entered(&stack.borrow(), |id| self.is_id_spawned(id, cx)))
+        else {
+            return;
+        };
+        let Some(task) = cx.span(&task_id) else {
+            return;
+        };
+        // The registry has already exited the span by the time `on_exit` is
+        // called, so this is the span that's current once it's exited.
+        let current = cx
+            .lookup_current()
+            .map(|span| span.metadata())
+            .filter(|&meta| Self::is_user_span(meta));
+        let exts = task.extensions();
+        if let Some(stats) = exts.get::<Arc<stats::TaskStats>>() {
+            stats.set_current_span(current.map(Metadata::name));
+        }
+    }
+
+    /// Returns the task span most recently exited on this thread, if nothing
+    /// else has been recorded on it since, and forgets it.
+    fn take_last_exited(&self) -> Option<span::Id> {
+        self.last_exited.get().and_then(Cell::take)
+    }
+
+    /// Returns the task that was executing on this thread when it started
+    /// unwinding, if it's unwinding from a panic that started in a task.
+    ///
+    /// This must be called before the span being entered or exited is pushed
+    /// onto or popped from the stack.
+    fn unwinding_task<S>(&self, cx: &Context<'_, S>) -> Option<span::Id>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let unwinding_from = self.unwinding_from.get_or_default();
+        if !std::thread::panicking() {
+            unwinding_from.set(None);
+            return None;
+        }
+        let task = unwinding_from.take().unwrap_or_else(|| {
+            self.current_spans.get().and_then(|stack| {
+                self.first_entered(&stack.borrow(), |id| self.is_id_spawned(id, cx))
+            })
+        });
+        unwinding_from.set(Some(task.clone()));
+        task
+    }
+
     fn first_entered<P>(&self, stack: &SpanStack, p: P) -> Option<span::Id>
     where
         P: Fn(&span::Id) -> bool,
@@ -431,18 +759,21 @@ impl ConsoleLayer {
     }
 
     fn send_metadata(&self, dropped: &AtomicUsize, event: Event) -> bool {
-        self.send_stats(dropped, move || (event, ())).is_some()
+        self.send_stats(dropped, EventKind::Metadata, move || (event, ()))
+            .is_some()
     }
 
     fn send_stats<S>(
         &self,
         dropped: &AtomicUsize,
+        kind: EventKind,
         mk_event: impl FnOnce() -> (Event, S),
     ) -> Option<S> {
         use mpsc::error::TrySendError;
 
+        let tx = self.tx.shard();
         // Return whether or not we actually sent the event.
-        let sent = match self.tx.try_reserve() {
+        let sent = match tx.try_reserve() {
             Ok(permit) => {
                 let (event, stats) = mk_event();
                 permit.send(event);
@@ -459,11 +790,15 @@ impl ConsoleLayer {
                 // time is very high, maybe the aggregator task hasn't been
                 // polled yet. so... eek?!
                 dropped.fetch_add(1, Ordering::Release);
+                self.shared.dropped_by_kind[kind as usize].fetch_add(1, Ordering::Release);
+                if let Some(ref recorder) = self.recorder {
+                    recorder.events_dropped();
+                }
                 None
             }
         };
 
-        let capacity = self.tx.capacity();
+        let capacity = tx.capacity();
         if capacity <= self.flush_under_capacity {
             self.shared.flush.trigger();
         }
@@ -471,9 +806,27 @@ impl ConsoleLayer {
         sent
     }
 
-    fn record(&self, event: impl FnOnce() -> record::Event) {
+    /// If this callback is sampled, attributes the time spent in it since
+    /// `started`, scaled up by the sample rate, to the task's overhead.
+    fn record_overhead(&self, stats: &stats::TaskStats, started: Instant) {
+        if self.overhead_sample_rate == 0 {
+            return;
+        }
+
+        let samples = self.overhead_samples.get_or_default();
+        let count = samples.get();
+        samples.set(count.wrapping_add(1));
+        if count % self.overhead_sample_rate == 0 {
+            let rate = u32::try_from(self.overhead_sample_rate).unwrap_or(u32::MAX);
+            stats.record_overhead(started.elapsed().saturating_mul(rate));
+        }
+    }
+
+    fn record(&self, event: impl FnOnce() -> record::RecordEvent) {
         if let Some(ref recorder) = self.recorder {
-            recorder.record(event());
+            if recorder.is_recording() {
+                recorder.record(event());
+            }
         }
     }
 
@@ -509,13 +862,14 @@ impl ConsoleLayer {
             None => return,
         };
 
-        stats.update_attribute(id, &update);
+        let at = self.clock.now();
+        stats.update_attribute(id, &update, at);
 
         if let Some(parent) = stats.parent_id.as_ref().and_then(|parent| ctx.span(parent)) {
             let exts = parent.extensions();
             if let Some(stats) = get_stats(&exts) {
                 if stats.inherit_child_attributes {
-                    stats.update_attribute(id, &update);
+                    stats.update_attribute(id, &update, at);
                 }
             }
         }
@@ -531,6 +885,15 @@ where
             return subscriber::Interest::never();
         }
 
+        if meta.is_span()
+            && meta
+                .fields()
+                .field(GroupVisitor::GROUP_FIELD_NAME)
+                .is_some()
+        {
+            self.group_callsites.insert(meta);
+        }
+
         let dropped = match (meta.name(), meta.target()) {
             ("runtime.spawn", _) | ("task", "tokio::task") => {
                 self.spawn_callsites.insert(meta);
@@ -572,39 +935,86 @@ where
     }
 
     fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        self.take_last_exited();
         let metadata = attrs.metadata();
         if self.is_spawn(metadata) {
-            let at = Instant::now();
-            let mut task_visitor = TaskVisitor::new(metadata.into());
+            let at = self.clock.now();
+            // Building every field of every task is wasted work if nothing
+            // will ever see them, so unless a client is watching or the
+            // events are being recorded, keep just enough to identify it.
+            let mut task_visitor =
+                if self.recorder.is_some() || self.shared.tasks_watched.load(Ordering::Acquire) {
+                    TaskVisitor::new(metadata)
+                } else {
+                    TaskVisitor::without_fields(metadata)
+                };
             attrs.record(&mut task_visitor);
-            let (fields, location) = task_visitor.result();
-            self.record(|| record::Event::Spawn {
+            let TaskVisitorResult {
+                fields,
+                location,
+                kind,
+                name,
+                size_bytes,
+                original_size_bytes,
+            } = task_visitor.result();
+            let size_bytes = size_bytes.or_else(|| {
+                let estimate = self.task_memory_estimator?;
+                estimate(&TaskMemoryInfo {
+                    name: name.as_deref(),
+                    file: location.as_ref().map(|l| &*l.file),
+                    line: location.as_ref().map(|l| l.line),
+                    is_blocking: kind == proto::tasks::task::Kind::Blocking,
+                })
+            });
+            let backtrace = self
+                .spawn_backtraces
+                .as_ref()
+                .and_then(|backtraces| backtraces.capture(at));
+            let group = self.task_group(attrs, &ctx);
+            if let Some(ref group) = group {
+                // Store the group on the task's span, too, so that tasks
+                // spawned by this task are in the same group.
+                ctx.span(id).expect("if `on_new_span` was called, the span must exist; this is a `tracing` bug!").extensions_mut().insert(TaskGroup(group.clone()));
+            }
+            self.record(|| record::RecordEvent::Spawn {
                 id: id.into_u64(),
                 at: self.base_time.to_system_time(at),
-                fields: record::SerializeFields(fields.clone()),
-            });
-            if let Some(stats) = self.send_stats(&self.shared.dropped_tasks, move || {
-                let stats = Arc::new(stats::TaskStats::new(
-                    self.max_poll_duration_nanos,
-                    self.max_scheduled_duration_nanos,
-                    at,
-                ));
-                let event = Event::Spawn {
-                    id: id.clone(),
-                    stats: stats.clone(),
+                fields: record::SerializeFields {
+                    fields: fields.to_proto(metadata),
                     metadata,
-                    fields,
-                    location,
-                };
-                (event, stats)
-            }) {
+                },
+            });
+            if let Some(stats) =
+                self.send_stats(&self.shared.dropped_tasks, EventKind::Spawn, move || {
+                    let stats = Arc::new(stats::TaskStats::new(
+                        self.max_poll_duration_nanos,
+                        self.max_scheduled_duration_nanos,
+                        at,
+                    ));
+                    let event = Event::Spawn {
+                        id: id.clone(),
+                        stats: stats.clone(),
+                        metadata,
+                        fields,
+                        location,
+                        kind,
+                        runtime: runtime::current(),
+                        group,
+                        size_bytes,
+                        original_size_bytes,
+                        backtrace,
+                    };
+                    (event, stats)
+                })
+            {
+                self.record_overhead(&stats, at);
                 ctx.span(id).expect("if `on_new_span` was called, the span must exist; this is a `tracing` bug!").extensions_mut().insert(stats);
             }
             return;
         }
 
         if self.is_resource(metadata) {
-            let at = Instant::now();
+            let at = self.clock.now();
             let mut resource_visitor = ResourceVisitor::default();
             attrs.record(&mut resource_visitor);
             if let Some(result) = resource_visitor.result() {
@@ -618,24 +1028,29 @@ where
                 let parent_id = self.current_spans.get().and_then(|stack| {
                     self.first_entered(&stack.borrow(), |id| self.is_id_resource(id, &ctx))
                 });
-                if let Some(stats) = self.send_stats(&self.shared.dropped_resources, move || {
-                    let stats = Arc::new(stats::ResourceStats::new(
-                        at,
-                        inherit_child_attrs,
-                        parent_id.clone(),
-                    ));
-                    let event = Event::Resource {
-                        id: id.clone(),
-                        parent_id,
-                        metadata,
-                        concrete_type,
-                        kind,
-                        location,
-                        is_internal,
-                        stats: stats.clone(),
-                    };
-                    (event, stats)
-                }) {
+                if let Some(stats) = self.send_stats(
+                    &self.shared.dropped_resources,
+                    EventKind::Resource,
+                    move || {
+                        let stats = Arc::new(stats::ResourceStats::new(
+                            at,
+                            inherit_child_attrs,
+                            parent_id.clone(),
+                            self.attribute_history,
+                        ));
+                        let event = Event::Resource {
+                            id: id.clone(),
+                            parent_id,
+                            metadata,
+                            concrete_type,
+                            kind,
+                            location,
+                            is_internal,
+                            stats: stats.clone(),
+                        };
+                        (event, stats)
+                    },
+                ) {
                     ctx.span(id).expect("if `on_new_span` was called, the span must exist; this is a `tracing` bug!").extensions_mut().insert(stats);
                 }
             }
@@ -643,7 +1058,7 @@ where
         }
 
         if self.is_async_op(metadata) {
-            let at = Instant::now();
+            let at = self.clock.now();
             let mut async_op_visitor = AsyncOpVisitor::default();
             attrs.record(&mut async_op_visitor);
             if let Some((source, inherit_child_attrs)) = async_op_visitor.result() {
@@ -656,8 +1071,10 @@ where
                 });
 
                 if let Some(resource_id) = resource_id {
-                    if let Some(stats) =
-                        self.send_stats(&self.shared.dropped_async_ops, move || {
+                    if let Some(stats) = self.send_stats(
+                        &self.shared.dropped_async_ops,
+                        EventKind::AsyncResourceOp,
+                        move || {
                             let stats = Arc::new(stats::AsyncOpStats::new(
                                 at,
                                 inherit_child_attrs,
@@ -672,44 +1089,60 @@ where
                                 stats: stats.clone(),
                             };
                             (event, stats)
-                        })
-                    {
+                        },
+                    ) {
                         ctx.span(id).expect("if `on_new_span` was called, the span must exist; this is a `tracing` bug!").extensions_mut().insert(stats);
                     }
                 }
             }
+            return;
+        }
+
+        if self.group_callsites.contains(metadata) {
+            let mut visitor = GroupVisitor::default();
+            attrs.record(&mut visitor);
+            if let Some(group) = visitor.result() {
+                ctx.span(id).expect("if `on_new_span` was called, the span must exist; this is a `tracing` bug!").extensions_mut().insert(TaskGroup(group));
+            }
         }
     }
 
     fn on_event(&self, event: &tracing::Event<'_>, ctx: Context<'_, S>) {
+        self.take_last_exited();
         let metadata = event.metadata();
         if self.waker_callsites.contains(metadata) {
-            let at = Instant::now();
+            let at = self.clock.now();
             let mut visitor = WakerVisitor::default();
             event.record(&mut visitor);
+            let site = visitor.take_site();
             // XXX (eliza): ew...
             if let Some((id, mut op)) = visitor.result() {
                 if let Some(span) = ctx.span(&id) {
                     let exts = span.extensions();
                     if let Some(stats) = exts.get::<Arc<stats::TaskStats>>() {
+                        // Are we currently inside the task's span?
+                        let in_task = self
+                            .current_spans
+                            .get()
+                            .map(|spans| spans.borrow().iter().any(|span| span == &id))
+                            .unwrap_or(false);
                         if op.is_wake() {
-                            // Are we currently inside the task's span? If so, the task
-                            // has woken itself.
-
-                            let self_wake = self
-                                .current_spans
-                                .get()
-                                .map(|spans| spans.borrow().iter().any(|span| span == &id))
-                                .unwrap_or(false);
-                            op = op.self_wake(self_wake);
+                            // If so, the task has woken itself.
+                            op = op.self_wake(in_task);
+                            if let Some(site) = site {
+                                stats.record_wake_site(site);
+                            }
+                        } else if in_task && matches!(op, WakeOp::Drop) {
+                            stats.drop_waker_in_poll();
                         }
 
                         stats.record_wake_op(op, at);
-                        self.record(|| record::Event::Waker {
+                        self.record(|| record::RecordEvent::Waker {
                             id: id.into_u64(),
                             at: self.base_time.to_system_time(at),
                             op,
                         });
+                        self.record_overhead(stats, at);
                     }
                 }
             }
@@ -739,10 +1172,13 @@ where
                             let exts = span.extensions();
                             if let Some(stats) = exts.get::<Arc<stats::AsyncOpStats>>() {
                                 stats.set_task_id(&task_id);
+                                if is_ready {
+                                    stats.poll_is_ready();
+                                }
                             }
                         }
 
-                        self.send_stats(&self.shared.dropped_async_ops, || {
+                        self.send_stats(&self.shared.dropped_async_ops, EventKind::PollOp, || {
                             let event = Event::PollOp {
                                 metadata,
                                 op_name,
@@ -790,13 +1226,16 @@ where
     }
 
     fn on_enter(&self, id: &span::Id, cx: Context<'_, S>) {
+        let follows_poll = self.take_last_exited().as_ref() == Some(id);
+        self.unwinding_task(&cx);
         if let Some(span) = cx.span(id) {
-            let now = Instant::now();
+            let now = self.clock.now();
             let exts = span.extensions();
             // if the span we are entering is a task or async op, record the
             // poll stats.
-            if let Some(stats) = exts.get::<Arc<stats::TaskStats>>() {
-                stats.start_poll(now);
+            let task_stats = exts.get::<Arc<stats::TaskStats>>();
+            if let Some(stats) = task_stats {
+                stats.start_poll(now, follows_poll);
             } else if let Some(stats) = exts.get::<Arc<stats::AsyncOpStats>>() {
                 stats.start_poll(now);
             } else if exts.get::<Arc<stats::ResourceStats>>().is_some() {
@@ -804,6 +1243,9 @@ where
                 // to enter it, although we don't care about recording poll
                 // stats.
             } else {
+                if self.track_user_spans && Self::is_user_span(span.metadata()) {
+                    self.update_current_span(&cx);
+                }
                 return;
             };
 
@@ -812,21 +1254,42 @@ where
                 .borrow_mut()
                 .push(id.clone());
 
-            self.record(|| record::Event::Enter {
+            self.record(|| record::RecordEvent::Enter {
                 id: id.into_u64(),
                 at: self.base_time.to_system_time(now),
             });
+
+            if let Some(stats) = task_stats {
+                self.record_overhead(stats, now);
+            }
         }
     }
 
     fn on_exit(&self, id: &span::Id, cx: Context<'_, S>) {
+        self.take_last_exited();
+        let unwinding_task = self.unwinding_task(&cx);
         if let Some(span) = cx.span(id) {
             let exts = span.extensions();
-            let now = Instant::now();
+            let now = self.clock.now();
             // if the span we are entering is a task or async op, record the
             // poll stats.
-            if let Some(stats) = exts.get::<Arc<stats::TaskStats>>() {
-                stats.end_poll(now);
+            let task_stats = exts.get::<Arc<stats::TaskStats>>();
+            if let Some(stats) = task_stats {
+                // A task's span is exited while unwinding if the task panics
+                // while it's being polled (or dropped), but so are the spans
+                // of any tasks that are polled or dropped during the unwind.
+                if unwinding_task.as_ref() == Some(id) {
+                    stats.panic();
+                }
+                self.last_exited.get_or_default().set(Some(id.clone()));
+                if let Some(busy) = stats.end_poll(now) {
+                    self.shared
+                        .busy_nanos
+                        .fetch_add(busy.as_nanos() as u64, Ordering::Relaxed);
+                    if let Some(ref recorder) = self.recorder {
+                        recorder.poll_ended(busy);
+                    }
+                }
             } else if let Some(stats) = exts.get::<Arc<stats::AsyncOpStats>>() {
                 stats.end_poll(now);
             } else if exts.get::<Arc<stats::ResourceStats>>().is_some() {
@@ -834,33 +1297,46 @@ where
                 // to enter it, although we don't care about recording poll
                 // stats.
             } else {
+                if self.track_user_spans && Self::is_user_span(span.metadata()) {
+                    self.update_current_span(&cx);
+                }
                 return;
             };
 
             self.current_spans.get_or_default().borrow_mut().pop(id);
 
-            self.record(|| record::Event::Exit {
+            self.record(|| record::RecordEvent::Exit {
                 id: id.into_u64(),
                 at: self.base_time.to_system_time(now),
             });
+
+            if let Some(stats) = task_stats {
+                self.record_overhead(stats, now);
+            }
         }
     }
 
     fn on_close(&self, id: span::Id, cx: Context<'_, S>) {
+        self.take_last_exited();
         if let Some(span) = cx.span(&id) {
-            let now = Instant::now();
+            let now = self.clock.now();
             let exts = span.extensions();
-            if let Some(stats) = exts.get::<Arc<stats::TaskStats>>() {
+            let task_stats = exts.get::<Arc<stats::TaskStats>>();
+            if let Some(stats) = task_stats {
                 stats.drop_task(now);
             } else if let Some(stats) = exts.get::<Arc<stats::AsyncOpStats>>() {
                 stats.drop_async_op(now);
             } else if let Some(stats) = exts.get::<Arc<stats::ResourceStats>>() {
                 stats.drop_resource(now);
             }
-            self.record(|| record::Event::Close {
+            self.record(|| record::RecordEvent::Close {
                 id: id.into_u64(),
                 at: self.base_time.to_system_time(now),
             });
+
+            if let Some(stats) = task_stats {
+                self.record_overhead(stats, now);
+            }
         }
     }
 }
@@ -932,12 +1408,25 @@ impl Server {
     /// This spawns both the server task and the event aggregation worker
     /// task on the current async runtime.
     ///
+    /// The server binds its own TCP listener, so that it can fall back on
+    /// other ports if its address is in use, and so the `tcp_nodelay` and
+    /// `tcp_keepalive` settings of `builder` aren't applied.
+    ///
+    /// # Errors
+    ///
+    /// If the server's address is already in use, along with every port in
+    /// its [fallback range], or it can't be listened on for another reason,
+    /// this returns a [`BindError`].
+    ///
     /// [`tonic`]: https://docs.rs/tonic/
+    /// [fallback range]: Builder::port_range
     pub async fn serve_with(
         self,
         mut builder: tonic::transport::Server,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
         let addr = self.addr.clone();
+        let listener = self.bind()?;
+        let shutdown = self.shutdown_handle();
         let ServerParts {
             instrument_server,
             aggregator,
@@ -945,18 +1434,27 @@ impl Server {
         let aggregate = spawn_named(aggregator.run(), "console::aggregate");
         let router = builder.add_service(instrument_server);
         let res = match addr {
-            ServerAddr::Tcp(addr) => {
-                let serve = router.serve(addr);
+            ServerAddr::Tcp(_) => {
+                let incoming = TcpIncoming::from_listener(
+                    listener.expect("a TCP address is always bound before serving"),
+                    false,
+                    None,
+                )?;
+                let serve =
+                    router.serve_with_incoming_shutdown(incoming, shutdown.clone().requested());
                 spawn_named(serve, "console::serve").await
             }
             #[cfg(unix)]
             ServerAddr::Unix(path) => {
                 let incoming = UnixListener::bind(path)?;
-                let serve = router.serve_with_incoming(UnixListenerStream::new(incoming));
+                let serve = router.serve_with_incoming_shutdown(
+                    UnixListenerStream::new(incoming),
+                    shutdown.clone().requested(),
+                );
                 spawn_named(serve, "console::serve").await
             }
         };
-        aggregate.abort();
+        shutdown.finish(aggregate).await;
         res?.map_err(Into::into)
     }
 
@@ -1063,6 +1561,8 @@ impl Server {
         builder: tonic::transport::Server,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
         let addr = self.addr.clone();
+        let listener = self.bind()?;
+        let shutdown = self.shutdown_handle();
         let ServerParts {
             instrument_server,
             aggregator,
@@ -1072,21 +1572,211 @@ impl Server {
             .add_service(tonic_web::enable(instrument_server));
         let aggregate = spawn_named(aggregator.run(), "console::aggregate");
         let res = match addr {
-            ServerAddr::Tcp(addr) => {
-                let serve = router.serve(addr);
+            ServerAddr::Tcp(_) => {
+                let incoming = TcpIncoming::from_listener(
+                    listener.expect("a TCP address is always bound before serving"),
+                    false,
+                    None,
+                )?;
+                let serve =
+                    router.serve_with_incoming_shutdown(incoming, shutdown.clone().requested());
                 spawn_named(serve, "console::serve").await
             }
             #[cfg(unix)]
             ServerAddr::Unix(path) => {
                 let incoming = UnixListener::bind(path)?;
-                let serve = router.serve_with_incoming(UnixListenerStream::new(incoming));
+                let serve = router.serve_with_incoming_shutdown(
+                    UnixListenerStream::new(incoming),
+                    shutdown.clone().requested(),
+                );
                 spawn_named(serve, "console::serve").await
             }
         };
-        aggregate.abort();
+        shutdown.finish(aggregate).await;
         res?.map_err(Into::into)
     }
 
+    /// Starts the gRPC service with the given [`tonic`] gRPC transport server
+    /// `builder`, along with a read-only JSON gateway on the same port.
+    ///
+    /// The gateway lets scripts and dashboards poll the current state of the
+    /// instrumented application without speaking gRPC. It serves the
+    /// following endpoints over plain HTTP:
+    ///
+    /// | Endpoint                      | Response                                     |
+    /// |-------------------------------|----------------------------------------------|
+    /// | `/json/state`                 | All tasks, resources and async ops.          |
+    /// | `/json/tasks`                 | All tasks, with their fields and statistics. |
+    /// | `/json/resources`             | All resources, with their attributes.        |
+    /// | `/json/async_ops`             | All async ops, with their attributes.        |
+    /// | `/json/tasks/{id}/histograms` | A task's poll and scheduled time histograms. |
+    ///
+    /// Every request returns a fresh snapshot of the state, including tasks,
+    /// resources and async ops that completed within the retention period.
+    ///
+    /// A task's histograms are looked up by the `id` it has in `/json/tasks`,
+    /// and summarized as a count, percentiles, and the number of durations in
+    /// equally wide buckets between the shortest and longest, in nanoseconds.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # async fn docs() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    /// # let (_, server) = console_subscriber::ConsoleLayer::new();
+    /// server.serve_with_json_gateway(tonic::transport::Server::default()).await
+    /// # }
+    /// ```
+    ///
+    /// With the server running, the list of tasks can then be fetched with:
+    ///
+    /// ```text
+    /// $ curl http://127.0.0.1:6669/json/tasks
+    /// ```
+    #[cfg(feature = "json-gateway")]
+    pub async fn serve_with_json_gateway(
+        self,
+        builder: tonic::transport::Server,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        self.serve_with_routes(builder, axum::Router::new()).await
+    }
+
+    /// Starts the gRPC service with the given [`tonic`] gRPC transport server
+    /// `builder`, along with a web UI and the JSON gateway it reads the state
+    /// from on the same port.
+    ///
+    /// The web UI is a single static page served at `/`, which shows the
+    /// instrumented application's tasks and resources, and the poll and
+    /// scheduled time histograms of the selected task, in a browser. Nothing
+    /// needs to be installed to use it, which makes it handy for inspecting an
+    /// application on a machine without `tokio-console`.
+    ///
+    /// See [`Server::serve_with_json_gateway`] for the JSON endpoints which are
+    /// also served.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # async fn docs() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    /// # let (_, server) = console_subscriber::ConsoleLayer::new();
+    /// server.serve_with_web_ui(tonic::transport::Server::default()).await
+    /// # }
+    /// ```
+    ///
+    /// With the server running, open <http://127.0.0.1:6669/> in a browser.
+    /// Note that some browsers refuse to connect to port 6669, in which case
+    /// the server must be bound to another port.
+    #[cfg(feature = "web-ui")]
+    pub async fn serve_with_web_ui(
+        self,
+        builder: tonic::transport::Server,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        self.serve_with_routes(builder, web_ui::router()).await
+    }
+
+    /// Serves the gRPC service and the JSON gateway, along with `routes`.
+    #[cfg(feature = "json-gateway")]
+    async fn serve_with_routes(
+        self,
+        builder: tonic::transport::Server,
+        routes: axum::Router,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let addr = self.addr.clone();
+        let listener = self.bind()?;
+        let shutdown = self.shutdown_handle();
+        let gateway = gateway::router(self.subscribe.clone(), self.client_buffer);
+        let ServerParts {
+            instrument_server,
+            aggregator,
+        } = self.into_parts();
+        let routes = tonic::service::Routes::new(instrument_server)
+            .into_axum_router()
+            .merge(gateway)
+            .merge(routes);
+        let router = builder.accept_http1(true).add_routes(routes.into());
+        let aggregate = spawn_named(aggregator.run(), "console::aggregate");
+        let res = match addr {
+            ServerAddr::Tcp(_) => {
+                let incoming = TcpIncoming::from_listener(
+                    listener.expect("a TCP address is always bound before serving"),
+                    false,
+                    None,
+                )?;
+                let serve =
+                    router.serve_with_incoming_shutdown(incoming, shutdown.clone().requested());
+                spawn_named(serve, "console::serve").await
+            }
+            #[cfg(unix)]
+            ServerAddr::Unix(path) => {
+                let incoming = UnixListener::bind(path)?;
+                let serve = router.serve_with_incoming_shutdown(
+                    UnixListenerStream::new(incoming),
+                    shutdown.clone().requested(),
+                );
+                spawn_named(serve, "console::serve").await
+            }
+        };
+        shutdown.finish(aggregate).await;
+        res?.map_err(Into::into)
+    }
+
+    /// Listens on the server's TCP address, or on a port from its fallback
+    /// range if that's in use. Unix socket addresses are bound when the
+    /// server starts.
+    fn bind(&self) -> Result<Option<TcpListener>, BindError> {
+        match self.addr {
+            ServerAddr::Tcp(addr) => bind::bind_tcp(addr, self.port_range.as_ref()).map(Some),
+            #[cfg(unix)]
+            ServerAddr::Unix(_) => Ok(None),
+        }
+    }
+
+    /// Starts a stream of updates describing the given `domains`, sent at
+    /// the requested `interval` if there is one.
+    async fn watch(
+        &self,
+        remote_addr: Option<std::net::SocketAddr>,
+        domains: aggregator::Domains,
+        interval: Option<prost_types::Duration>,
+    ) -> Result<tonic::Response<UpdateStream>, tonic::Status> {
+        match remote_addr {
+            Some(addr) => tracing::debug!(client.addr = %addr, ?domains, "starting a new watch"),
+            None => tracing::debug!(client.addr = %"<unknown>", ?domains, "starting a new watch"),
+        }
+        let permit = self.subscribe.reserve().await.map_err(|_| {
+            tonic::Status::internal("cannot start new watch, aggregation task is not running")
+        })?;
+        let interval = match interval.map(Duration::try_from).transpose() {
+            Ok(interval) => interval.map(|interval| interval.max(aggregator::MIN_UPDATE_INTERVAL)),
+            Err(_) => return Err(tonic::Status::invalid_argument("invalid update interval")),
+        };
+        let (tx, rx) = mpsc::channel(self.client_buffer);
+        permit.send(Command::Instrument {
+            watch: Watch(tx),
+            domains,
+            interval,
+        });
+        tracing::debug!("watch started");
+        let stream = update_stream(rx);
+        Ok(tonic::Response::new(stream))
+    }
+
+    /// Returns a [`ShutdownHandle`] which stops this server.
+    ///
+    /// Once the handle's [`shutdown`] method is called, the future returned
+    /// by [`serve`] (or any of the other `serve_with` methods) stops
+    /// accepting connections, lets the aggregator publish a final update to
+    /// connected clients, flushes the recording (if any), and completes.
+    ///
+    /// When the server is run from [`into_parts`], the handle also stops the
+    /// [`Aggregator`], but the gRPC server must be shut down separately.
+    ///
+    /// [`shutdown`]: ShutdownHandle::shutdown
+    /// [`serve`]: Server::serve
+    /// [`into_parts`]: Server::into_parts
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle::new(self.shutdown.clone(), self.recorder.clone())
+    }
+
     /// Returns the parts needed to spawn a gRPC server and the aggregator that
     /// supplies it.
     ///
@@ -1187,39 +1877,58 @@ pub struct ServerParts {
 
 #[tonic::async_trait]
 impl proto::instrument::instrument_server::Instrument for Server {
-    type WatchUpdatesStream =
-        tokio_stream::wrappers::ReceiverStream<Result<proto::instrument::Update, tonic::Status>>;
-    type WatchTaskDetailsStream =
-        tokio_stream::wrappers::ReceiverStream<Result<proto::tasks::TaskDetails, tonic::Status>>;
-    type WatchStateStream =
-        tokio_stream::wrappers::ReceiverStream<Result<proto::instrument::State, tonic::Status>>;
+    type WatchUpdatesStream = UpdateStream;
+    type WatchTaskDetailsStream = WatchStream<proto::tasks::TaskDetails>;
+    type WatchStateStream = WatchStream<proto::instrument::State>;
+    type WatchTasksStream = UpdateStream;
+    type WatchResourcesStream = UpdateStream;
+    type WatchAsyncOpsStream = UpdateStream;
+    type SnapshotStream =
+        tokio_stream::Iter<std::vec::IntoIter<Result<proto::instrument::Update, tonic::Status>>>;
+
     async fn watch_updates(
         &self,
         req: tonic::Request<proto::instrument::InstrumentRequest>,
     ) -> Result<tonic::Response<Self::WatchUpdatesStream>, tonic::Status> {
-        match req.remote_addr() {
-            Some(addr) => tracing::debug!(client.addr = %addr, "starting a new watch"),
-            None => tracing::debug!(client.addr = %"<unknown>", "starting a new watch"),
-        }
-        let permit = self.subscribe.reserve().await.map_err(|_| {
-            tonic::Status::internal("cannot start new watch, aggregation task is not running")
-        })?;
-        let (tx, rx) = mpsc::channel(self.client_buffer);
-        permit.send(Command::Instrument(Watch(tx)));
-        tracing::debug!("watch started");
-        let stream = tokio_stream::wrappers::ReceiverStream::new(rx);
-        Ok(tonic::Response::new(stream))
+        let interval = req.get_ref().update_interval;
+        self.watch(req.remote_addr(), aggregator::Domains::ALL, interval)
+            .await
+    }
+
+    async fn watch_tasks(
+        &self,
+        req: tonic::Request<proto::instrument::TasksRequest>,
+    ) -> Result<tonic::Response<Self::WatchTasksStream>, tonic::Status> {
+        self.watch(req.remote_addr(), aggregator::Domains::TASKS, None)
+            .await
+    }
+
+    async fn watch_resources(
+        &self,
+        req: tonic::Request<proto::instrument::ResourcesRequest>,
+    ) -> Result<tonic::Response<Self::WatchResourcesStream>, tonic::Status> {
+        self.watch(req.remote_addr(), aggregator::Domains::RESOURCES, None)
+            .await
+    }
+
+    async fn watch_async_ops(
+        &self,
+        req: tonic::Request<proto::instrument::AsyncOpsRequest>,
+    ) -> Result<tonic::Response<Self::WatchAsyncOpsStream>, tonic::Status> {
+        self.watch(req.remote_addr(), aggregator::Domains::ASYNC_OPS, None)
+            .await
     }
 
     async fn watch_task_details(
         &self,
         req: tonic::Request<proto::instrument::TaskDetailsRequest>,
     ) -> Result<tonic::Response<Self::WatchTaskDetailsStream>, tonic::Status> {
+        let req = req.into_inner();
         let task_id = req
-            .into_inner()
             .id
             .ok_or_else(|| tonic::Status::invalid_argument("missing task_id"))?
             .id;
+        let histograms = req.histograms.unwrap_or(ALL_HISTOGRAMS);
 
         // `tracing` reserves span ID 0 for niche optimization for `Option<Id>`.
         let id = std::num::NonZeroU64::new(task_id)
@@ -1236,6 +1945,7 @@ impl proto::instrument::instrument_server::Instrument for Server {
             id,
             stream_sender,
             buffer: self.client_buffer,
+            histograms,
         }));
         // If the aggregator drops the sender, the task doesn't exist.
         let rx = stream_recv.await.map_err(|_| {
@@ -1244,61 +1954,94 @@ impl proto::instrument::instrument_server::Instrument for Server {
         })?;
 
         tracing::debug!(id = ?task_id, "task details watch started");
-        let stream = tokio_stream::wrappers::ReceiverStream::new(rx);
+        let stream = watch_stream(rx);
         Ok(tonic::Response::new(stream))
     }
 
     async fn watch_state(
         &self,
-        _req: tonic::Request<proto::instrument::StateRequest>,
+        req: tonic::Request<proto::instrument::StateRequest>,
     ) -> Result<tonic::Response<Self::WatchStateStream>, tonic::Status> {
+        let stream_id = req.into_inner().stream_id;
         let (stream_sender, stream_recv) = mpsc::channel(self.client_buffer);
         self.subscribe
-            .send(Command::WatchState(Watch(stream_sender)))
+            .send(Command::WatchState {
+                stream_id,
+                watch: Watch(stream_sender),
+            })
             .await
             .map_err(|_| {
                 tonic::Status::internal("cannot get state, aggregation task is not running")
             })?;
-        let stream = tokio_stream::wrappers::ReceiverStream::new(stream_recv);
+        let stream = watch_stream(stream_recv);
         Ok(tonic::Response::new(stream))
     }
 
     async fn pause(
         &self,
-        _req: tonic::Request<proto::instrument::PauseRequest>,
+        req: tonic::Request<proto::instrument::PauseRequest>,
     ) -> Result<tonic::Response<proto::instrument::PauseResponse>, tonic::Status> {
-        self.subscribe.send(Command::Pause).await.map_err(|_| {
-            tonic::Status::internal("cannot pause, aggregation task is not running")
-        })?;
+        let stream_id = req.into_inner().stream_id;
+        self.subscribe
+            .send(Command::Pause(stream_id))
+            .await
+            .map_err(|_| {
+                tonic::Status::internal("cannot pause, aggregation task is not running")
+            })?;
         Ok(tonic::Response::new(proto::instrument::PauseResponse {}))
     }
 
     async fn resume(
         &self,
-        _req: tonic::Request<proto::instrument::ResumeRequest>,
+        req: tonic::Request<proto::instrument::ResumeRequest>,
     ) -> Result<tonic::Response<proto::instrument::ResumeResponse>, tonic::Status> {
-        self.subscribe.send(Command::Resume).await.map_err(|_| {
-            tonic::Status::internal("cannot resume, aggregation task is not running")
-        })?;
+        let stream_id = req.into_inner().stream_id;
+        self.subscribe
+            .send(Command::Resume(stream_id))
+            .await
+            .map_err(|_| {
+                tonic::Status::internal("cannot resume, aggregation task is not running")
+            })?;
         Ok(tonic::Response::new(proto::instrument::ResumeResponse {}))
     }
-}
 
-impl WakeOp {
-    /// Returns `true` if `self` is a `Wake` or `WakeByRef` event.
-    fn is_wake(self) -> bool {
-        matches!(self, Self::Wake { .. } | Self::WakeByRef { .. })
+    async fn debug_self(
+        &self,
+        _req: tonic::Request<proto::instrument::DebugSelfRequest>,
+    ) -> Result<tonic::Response<proto::instrument::SelfMetrics>, tonic::Status> {
+        let not_running =
+            || tonic::Status::internal("cannot get metrics, aggregation task is not running");
+        let (tx, rx) = oneshot::channel();
+        self.subscribe
+            .send(Command::DebugSelf(tx))
+            .await
+            .map_err(|_| not_running())?;
+        let metrics = rx.await.map_err(|_| not_running())?;
+        Ok(tonic::Response::new(metrics))
     }
 
-    fn self_wake(self, self_wake: bool) -> Self {
-        match self {
-            Self::Wake { .. } => Self::Wake { self_wake },
-            Self::WakeByRef { .. } => Self::WakeByRef { self_wake },
-            x => x,
-        }
+    async fn snapshot(
+        &self,
+        _req: tonic::Request<proto::instrument::SnapshotRequest>,
+    ) -> Result<tonic::Response<Self::SnapshotStream>, tonic::Status> {
+        let not_running =
+            || tonic::Status::internal("cannot take snapshot, aggregation task is not running");
+        let (tx, rx) = oneshot::channel();
+        self.subscribe
+            .send(Command::Snapshot(tx))
+            .await
+            .map_err(|_| not_running())?;
+        let updates = rx.await.map_err(|_| not_running())?;
+        tracing::debug!(updates = updates.len(), "sending snapshot");
+        let updates = updates.into_iter().map(Ok).collect::<Vec<_>>();
+        Ok(tonic::Response::new(tokio_stream::iter(updates)))
     }
 }
 
+impl EventKind {
+    const COUNT: usize = 5;
+}
+
 #[track_caller]
 pub(crate) fn spawn_named<T>(
     task: impl std::future::Future<Output = T> + Send + 'static,
@@ -1313,3 +2056,39 @@ where
     #[cfg(not(tokio_unstable))]
     tokio::spawn(task)
 }
+
+/// Returns a stream of the messages sent on a [`Watch`].
+fn watch_stream<T: Clone>(rx: mpsc::Receiver<Result<Arc<T>, tonic::Status>>) -> WatchStream<T> {
+    use tokio_stream::StreamExt;
+
+    // The stream's items are dictated by `tonic`, so the size of its error
+    // type can't be helped.
+    #[allow(clippy::result_large_err)]
+    fn take<T: Clone>(message: Result<Arc<T>, tonic::Status>) -> Result<T, tonic::Status> {
+        message.map(unshare)
+    }
+
+    tokio_stream::wrappers::ReceiverStream::new(rx).map(take as fn(_) -> _)
+}
+
+/// Returns a stream of the updates sent on an update stream's [`Watch`].
+fn update_stream(
+    rx: mpsc::Receiver<Result<Arc<aggregator::StreamUpdate>, tonic::Status>>,
+) -> UpdateStream {
+    use tokio_stream::StreamExt;
+
+    #[allow(clippy::result_large_err)]
+    fn take(
+        message: Result<Arc<aggregator::StreamUpdate>, tonic::Status>,
+    ) -> Result<proto::instrument::Update, tonic::Status> {
+        message.map(aggregator::StreamUpdate::take)
+    }
+
+    tokio_stream::wrappers::ReceiverStream::new(rx).map(take as fn(_) -> _)
+}
+
+/// Takes a message sent on a [`Watch`], cloning it only if it is still shared
+/// with other clients.
+fn unshare<T: Clone>(message: Arc<T>) -> T {
+    Arc::try_unwrap(message).unwrap_or_else(|message| (*message).clone())
+}
@@ -0,0 +1,125 @@
+//! Macros for instrumenting resources defined outside of Tokio.
+//!
+//! The console recognizes resources and async operations by the names and
+//! fields of the `tracing` spans that represent them. These macros create
+//! spans with that shape, so that libraries can have their own
+//! synchronization primitives, connection pools, and so on show up in the
+//! console's resources view.
+
+/// Creates a [`tracing::Span`] representing a resource, such as a semaphore,
+/// a connection pool, or a channel.
+///
+/// The span should be created when the resource is constructed and stored
+/// alongside it, so that it is closed when the resource is dropped.
+///
+/// The `concrete_type` and `kind` fields are required. `concrete_type` is the
+/// Rust type of the resource, and `kind` is the category the console groups
+/// it under (e.g. `"Sync"`, `"Timer"`, or `"Net"`). Both may be given as
+/// strings or as `%`/`?` values; a span missing either of them is not
+/// recorded as a resource. The location the macro is invoked at is recorded
+/// as the resource's location.
+///
+/// Any additional fields are recorded on the span as usual. The optional
+/// `is_internal` and `inherits_child_attrs` boolean fields have the same
+/// meaning as for Tokio's own resources: internal resources are hidden by
+/// default, and resources that inherit child attributes show the attributes
+/// of their async operations.
+///
+/// A `target` may be given before the fields, as with `tracing`'s macros.
+/// Note that when a console layer is created with [`spawn`] or [`init`],
+/// the default filter enables resource spans with any target.
+///
+/// Resource attributes are updated with `tracing` events with the target
+/// `runtime::resource::state_update`, emitted while the resource's span is
+/// entered:
+///
+/// ```
+/// let span = console_subscriber::instrument_resource!(
+///     concrete_type = "Pool",
+///     kind = "Sync",
+///     pool.size = 4,
+/// );
+/// let _enter = span.enter();
+/// tracing::trace!(
+///     target: "runtime::resource::state_update",
+///     idle = 2,
+///     idle.op = "sub",
+/// );
+/// ```
+///
+/// Valid values for `.op` are `"add"`, `"sub"`, and `"override"`.
+///
+/// [`spawn`]: crate::spawn
+/// [`init`]: crate::init
+#[macro_export]
+macro_rules! instrument_resource {
+    (target: $target:expr, $($fields:tt)*) => {
+        $crate::__private::tracing::trace_span!(
+            target: $target,
+            "runtime.resource",
+            loc.file = file!(),
+            loc.line = line!(),
+            loc.col = column!(),
+            $($fields)*
+        )
+    };
+    ($($fields:tt)*) => {
+        $crate::instrument_resource!(target: module_path!(), $($fields)*)
+    };
+}
+
+/// Creates a [`tracing::Span`] representing an asynchronous operation on a
+/// resource, such as acquiring a permit or checking out a connection.
+///
+/// The span must be created while the span of the resource the operation
+/// belongs to (see [`instrument_resource!`]) is entered; otherwise, the
+/// console ignores it. The future performing the operation should be
+/// [instrumented] with the returned span, so that each poll of the future is
+/// recorded as a poll of the operation.
+///
+/// The `source` field is required and names the method that created the
+/// operation; like the resource fields, it may be a string or a `%`/`?`
+/// value. Additional fields are recorded as usual, and operation
+/// attributes may be updated with `tracing` events with the target
+/// `runtime::resource::async_op::state_update`, emitted while the
+/// operation's span is entered.
+///
+/// The console attributes an operation to the task polling it when it sees
+/// a `tracing` event with the target `runtime::resource::poll_op`, emitted
+/// while both the resource's span and the operation's span are entered. The
+/// event has a string `op_name` field naming the poll method, and a boolean
+/// `is_ready` field which is `true` if the poll completed the operation.
+///
+/// ```
+/// use tracing::Instrument;
+///
+/// # async fn doc() {
+/// let pool_span = console_subscriber::instrument_resource!(
+///     concrete_type = "Pool",
+///     kind = "Sync",
+/// );
+/// let checkout = pool_span.in_scope(|| {
+///     console_subscriber::instrument_async_op!(source = "Pool::checkout")
+/// });
+/// async {
+///     // wait for a connection...
+/// }
+/// .instrument(checkout)
+/// .await;
+/// # }
+/// ```
+///
+/// [instrumented]: https://docs.rs/tracing/latest/tracing/trait.Instrument.html
+#[macro_export]
+macro_rules! instrument_async_op {
+    (target: $target:expr, $($fields:tt)*) => {
+        $crate::__private::tracing::trace_span!(
+            target: $target,
+            "runtime.resource.async_op",
+            $($fields)*
+        )
+    };
+    ($($fields:tt)*) => {
+        $crate::instrument_async_op!(target: module_path!(), $($fields)*)
+    };
+}
@@ -1,10 +1,48 @@
+//! Recording events to a file, and reading recordings back.
+//!
+//! When a [recording path] is set, the [`ConsoleLayer`] writes every task
+//! event to that file as it happens. The file starts with a header line
+//! naming the version of the format, followed by one JSON object per line
+//! for each event.
+//!
+//! A [`Reader`] parses a recording back into typed [`Event`]s, for tools that
+//! analyze recordings after the fact.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use console_subscriber::record::{Event, Reader};
+//!
+//! # fn main() -> Result<(), console_subscriber::record::ReadError> {
+//! let mut polls = 0;
+//! for event in Reader::open("./console.json")? {
+//!     if let Event::Enter { .. } = event? {
+//!         polls += 1;
+//!     }
+//! }
+//! println!("tasks were polled {} times", polls);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [recording path]: crate::Builder::recording_path
+//! [`ConsoleLayer`]: crate::ConsoleLayer
+
 use console_api as proto;
 use crossbeam_channel::{Receiver, Sender};
 use serde::{
     ser::{SerializeSeq, SerializeStruct},
-    Serialize,
+    Deserialize, Serialize,
+};
+use std::{
+    error, fmt,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant, SystemTime},
 };
-use std::{fs::File, io, path::Path, time::SystemTime};
+use tracing_core::Metadata;
 
 /// This marks the currently understood version of the recording format. This
 /// should be increased whenever the format has a breaking change that we
@@ -15,18 +53,77 @@ use std::{fs::File, io, path::Path, time::SystemTime};
 const DATA_FORMAT_VERSION: u8 = 1;
 
 pub(crate) struct Recorder {
-    tx: Sender<Event>,
-    // TODO(eliza): terminate and flush when dropping...
-    _worker: std::thread::JoinHandle<()>,
+    tx: Sender<Message>,
+    /// If set, events are only recorded for a while after an anomaly.
+    trigger: Option<Trigger>,
+    /// The IO thread, which is joined when the recorder is dropped so that
+    /// the recording is flushed to disk.
+    worker: Option<std::thread::JoinHandle<()>>,
 }
 
-#[derive(Serialize)]
+/// A message sent to the recorder's IO thread.
+enum Message {
+    Event(RecordEvent),
+    /// Flush everything written so far, then notify the sender.
+    Flush(Sender<()>),
+}
+
+/// Configures a recording to only capture the events around an anomaly, rather
+/// than the whole run of the application.
+///
+/// Recording starts when one of the policy's conditions is met, and stops once
+/// the policy's [window] has passed without the conditions being met again.
+/// Tasks spawned before recording started are recorded without their spawn
+/// events.
+///
+/// # Examples
+///
+/// ```
+/// use console_subscriber::TriggerPolicy;
+/// use std::time::Duration;
+///
+/// // Record for a minute after any poll takes longer than 100ms, or any
+/// // events are dropped.
+/// let policy = TriggerPolicy::new()
+///     .on_slow_poll(Duration::from_millis(100))
+///     .on_dropped_events()
+///     .window(Duration::from_secs(60));
+///
+/// let builder = console_subscriber::ConsoleLayer::builder()
+///     .recording_path("./anomalies.json")
+///     .record_on(policy);
+/// ```
+///
+/// [window]: TriggerPolicy::window
+#[derive(Clone, Debug)]
+pub struct TriggerPolicy {
+    /// Start recording when a task's poll takes at least this long.
+    slow_poll: Option<Duration>,
+    /// Start recording when the event buffer is full and events are dropped.
+    dropped_events: bool,
+    /// How long to keep recording after the last anomaly.
+    window: Duration,
+}
+
+/// The state of a triggered recording.
+struct Trigger {
+    policy: TriggerPolicy,
+    /// The time that `recording_until` is measured from.
+    started: Instant,
+    /// Nanoseconds since `started` until which events are recorded, or 0 if
+    /// recording was never triggered.
+    recording_until: AtomicU64,
+}
+
+#[derive(Serialize, Deserialize)]
 struct Header {
     v: u8,
 }
 
+/// An event as it's written to a recording, borrowing the span's metadata to
+/// name its fields.
 #[derive(Serialize)]
-pub(crate) enum Event {
+pub(crate) enum RecordEvent {
     Spawn {
         id: u64,
         at: SystemTime,
@@ -46,20 +143,347 @@ pub(crate) enum Event {
     },
     Waker {
         id: u64,
-        op: super::WakeOp,
+        op: WakeOp,
+        at: SystemTime,
+    },
+}
+
+/// An event read from a recording.
+///
+/// Each event refers to a task by the ID of its span, which is unique among
+/// the tasks that are alive at the same time, but may be reused once a task
+/// has closed.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub enum Event {
+    /// A task was spawned.
+    Spawn {
+        /// The task's span ID.
+        id: u64,
+        /// When the task was spawned.
+        at: SystemTime,
+        /// The fields recorded on the task's span, such as its name and where
+        /// it was spawned.
+        fields: Vec<Field>,
+    },
+    /// A task started being polled.
+    Enter {
+        /// The task's span ID.
+        id: u64,
+        /// When the poll started.
+        at: SystemTime,
+    },
+    /// A task finished being polled.
+    Exit {
+        /// The task's span ID.
+        id: u64,
+        /// When the poll finished.
+        at: SystemTime,
+    },
+    /// A task was dropped.
+    Close {
+        /// The task's span ID.
+        id: u64,
+        /// When the task was dropped.
+        at: SystemTime,
+    },
+    /// One of a task's wakers was used.
+    Waker {
+        /// The task's span ID.
+        id: u64,
+        /// What was done with the waker.
+        op: WakeOp,
+        /// When the waker was used.
         at: SystemTime,
     },
 }
 
-pub(crate) struct SerializeFields(pub(crate) Vec<proto::Field>);
+/// A field recorded on a task's span.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct Field {
+    /// The field's name.
+    pub name: String,
+    /// The field's value.
+    pub value: FieldValue,
+}
+
+/// The value of a [`Field`].
+///
+/// Values that were recorded with their `Debug` implementations are read as
+/// strings.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum FieldValue {
+    /// A boolean.
+    Bool(bool),
+    /// An unsigned integer.
+    U64(u64),
+    /// A signed integer. Only negative integers are read as `I64`.
+    I64(i64),
+    /// A string, or a value's `Debug` output.
+    Str(String),
+}
+
+/// What was done with a task's waker.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WakeOp {
+    /// The waker was consumed by waking the task.
+    Wake {
+        /// Whether the task woke itself while it was being polled.
+        self_wake: bool,
+    },
+    /// The task was woken by reference, without consuming the waker.
+    WakeByRef {
+        /// Whether the task woke itself while it was being polled.
+        self_wake: bool,
+    },
+    /// The waker was cloned.
+    Clone,
+    /// The waker was dropped.
+    Drop,
+}
+
+/// Reads the [`Event`]s in a recording, in the order they were recorded.
+///
+/// The recording's header is read when the reader is created, so a file that
+/// isn't a recording, or was written in a version of the format that this
+/// reader doesn't understand, is rejected before any events are read.
+#[derive(Debug)]
+pub struct Reader<R> {
+    reader: R,
+    version: u8,
+    /// The number of the line that was read last.
+    line: usize,
+    buf: String,
+}
+
+/// An error reading a recording.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ReadError {
+    /// The recording couldn't be read.
+    Io(io::Error),
+    /// The recording doesn't start with a header naming its version.
+    MissingHeader,
+    /// The recording was written in a version of the format that this reader
+    /// doesn't understand.
+    UnsupportedVersion {
+        /// The version named by the recording's header.
+        version: u8,
+    },
+    /// A line of the recording isn't a valid event.
+    Parse {
+        /// The line's number, starting from 1 for the header.
+        line: usize,
+        /// The error from parsing the line.
+        source: serde_json::Error,
+    },
+}
 
-struct SerializeField<'a>(&'a proto::Field);
+pub(crate) struct SerializeFields {
+    pub(crate) fields: Vec<proto::Field>,
+    /// The metadata of the span the fields were recorded on, used to look up
+    /// field names that were recorded as indices.
+    pub(crate) metadata: &'static Metadata<'static>,
+}
+
+struct SerializeField<'a> {
+    field: &'a proto::Field,
+    metadata: &'static Metadata<'static>,
+}
+
+// === impl WakeOp ===
+
+impl WakeOp {
+    /// Returns `true` if `self` is a `Wake` or `WakeByRef` event.
+    pub(crate) fn is_wake(self) -> bool {
+        matches!(self, Self::Wake { .. } | Self::WakeByRef { .. })
+    }
+
+    pub(crate) fn self_wake(self, self_wake: bool) -> Self {
+        match self {
+            Self::Wake { .. } => Self::Wake { self_wake },
+            Self::WakeByRef { .. } => Self::WakeByRef { self_wake },
+            x => x,
+        }
+    }
+}
+
+// === impl Reader ===
+
+impl Reader<BufReader<File>> {
+    /// Opens the recording at `path`, and reads its header.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ReadError> {
+        let file = File::open(path).map_err(ReadError::Io)?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: BufRead> Reader<R> {
+    /// Returns a reader for the recording read from `reader`, after reading
+    /// its header.
+    pub fn new(reader: R) -> Result<Self, ReadError> {
+        let mut this = Self {
+            reader,
+            version: 0,
+            line: 0,
+            buf: String::new(),
+        };
+        let header = this.next_line().ok_or(ReadError::MissingHeader)??;
+        let header: Header = serde_json::from_str(header).map_err(|_| ReadError::MissingHeader)?;
+        if header.v != DATA_FORMAT_VERSION {
+            return Err(ReadError::UnsupportedVersion { version: header.v });
+        }
+        this.version = header.v;
+        Ok(this)
+    }
+
+    /// Returns the version of the format that the recording was written in.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Returns the next line which isn't blank, without its line ending.
+    fn next_line(&mut self) -> Option<Result<&str, ReadError>> {
+        loop {
+            self.buf.clear();
+            match self.reader.read_line(&mut self.buf) {
+                Ok(0) => return None,
+                Ok(_) => self.line += 1,
+                Err(e) => return Some(Err(ReadError::Io(e))),
+            }
+            if !self.buf.trim().is_empty() {
+                return Some(Ok(self.buf.trim_end()));
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = Result<Event, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = match self.next_line()? {
+            Ok(line) => serde_json::from_str(line),
+            Err(e) => return Some(Err(e)),
+        };
+        Some(result.map_err(|source| ReadError::Parse {
+            line: self.line,
+            source,
+        }))
+    }
+}
+
+// === impl ReadError ===
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Io(e) => write!(f, "couldn't read the recording: {}", e),
+            ReadError::MissingHeader => {
+                f.write_str("the recording doesn't start with a version header")
+            }
+            ReadError::UnsupportedVersion { version } => write!(
+                f,
+                "the recording is in version {} of the format, but only version {} is supported",
+                version, DATA_FORMAT_VERSION
+            ),
+            ReadError::Parse { line, source } => {
+                write!(f, "line {} of the recording is invalid: {}", line, source)
+            }
+        }
+    }
+}
+
+impl error::Error for ReadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ReadError::Io(e) => Some(e),
+            ReadError::Parse { source, .. } => Some(source),
+            ReadError::MissingHeader | ReadError::UnsupportedVersion { .. } => None,
+        }
+    }
+}
+
+// === impl TriggerPolicy ===
+
+impl TriggerPolicy {
+    /// The default time to keep recording for after an anomaly.
+    pub const DEFAULT_WINDOW: Duration = Duration::from_secs(30);
+
+    /// Returns a policy that never starts recording, until conditions are
+    /// added to it.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts recording when a single poll of a task takes at least
+    /// `threshold`.
+    pub fn on_slow_poll(self, threshold: Duration) -> Self {
+        Self {
+            slow_poll: Some(threshold),
+            ..self
+        }
+    }
+
+    /// Starts recording when events are dropped because the event buffer is
+    /// full.
+    ///
+    /// See also [`Builder::event_buffer_capacity`].
+    ///
+    /// [`Builder::event_buffer_capacity`]: crate::Builder::event_buffer_capacity
+    pub fn on_dropped_events(self) -> Self {
+        Self {
+            dropped_events: true,
+            ..self
+        }
+    }
+
+    /// Sets how long to keep recording for after the most recent anomaly.
+    ///
+    /// By default, this is [`TriggerPolicy::DEFAULT_WINDOW`].
+    pub fn window(self, window: Duration) -> Self {
+        Self { window, ..self }
+    }
+}
+
+impl Default for TriggerPolicy {
+    fn default() -> Self {
+        Self {
+            slow_poll: None,
+            dropped_events: false,
+            window: Self::DEFAULT_WINDOW,
+        }
+    }
+}
+
+impl Trigger {
+    fn is_recording(&self) -> bool {
+        let until = self.recording_until.load(Ordering::Acquire);
+        until != 0 && self.nanos_since_started() < until
+    }
+
+    /// Keeps recording until the policy's window has passed from now.
+    fn fire(&self) {
+        let until = self
+            .nanos_since_started()
+            .saturating_add(self.policy.window.as_nanos() as u64)
+            // 0 means recording was never triggered.
+            .max(1);
+        self.recording_until.fetch_max(until, Ordering::AcqRel);
+    }
+
+    fn nanos_since_started(&self) -> u64 {
+        self.started.elapsed().as_nanos() as u64
+    }
+}
 
 impl Recorder {
-    pub(crate) fn new(path: &Path) -> io::Result<Self> {
+    pub(crate) fn new(path: &Path, trigger: Option<TriggerPolicy>) -> io::Result<Self> {
         let file = std::fs::File::create(path)?;
         let (tx, rx) = crossbeam_channel::bounded(4096);
-        let _worker = std::thread::Builder::new()
+        let worker = std::thread::Builder::new()
             .name("console/subscriber/recorder/io".into())
             .spawn(move || {
                 if let Err(e) = record_io(file, rx) {
@@ -67,19 +491,72 @@ impl Recorder {
                 }
             })?;
 
-        let recorder = Recorder { tx, _worker };
+        let trigger = trigger.map(|policy| Trigger {
+            policy,
+            started: Instant::now(),
+            recording_until: AtomicU64::new(0),
+        });
+        let recorder = Recorder {
+            tx,
+            trigger,
+            worker: Some(worker),
+        };
 
         Ok(recorder)
     }
 
-    pub(crate) fn record(&self, event: Event) {
-        if self.tx.send(event).is_err() {
+    /// Returns `true` if events should be recorded right now.
+    pub(crate) fn is_recording(&self) -> bool {
+        self.trigger.as_ref().map_or(true, Trigger::is_recording)
+    }
+
+    pub(crate) fn record(&self, event: RecordEvent) {
+        if self.tx.send(Message::Event(event)).is_err() {
             eprintln!("event recorder thread has terminated!");
         }
     }
+
+    /// Blocks until every event recorded so far has been written to the
+    /// file.
+    pub(crate) fn flush(&self) {
+        let (done_tx, done_rx) = crossbeam_channel::bounded(1);
+        if self.tx.send(Message::Flush(done_tx)).is_ok() {
+            let _ = done_rx.recv();
+        }
+    }
+
+    /// Starts recording if `busy` is long enough to trigger it.
+    pub(crate) fn poll_ended(&self, busy: Duration) {
+        if let Some(ref trigger) = self.trigger {
+            if trigger.policy.slow_poll.is_some_and(|slow| busy >= slow) {
+                trigger.fire();
+            }
+        }
+    }
+
+    /// Starts recording if dropping events triggers it.
+    pub(crate) fn events_dropped(&self) {
+        if let Some(ref trigger) = self.trigger {
+            if trigger.policy.dropped_events {
+                trigger.fire();
+            }
+        }
+    }
 }
 
-fn record_io(file: File, rx: Receiver<Event>) -> io::Result<()> {
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        // Close the channel, so that the IO thread writes any remaining
+        // events and flushes the file before exiting.
+        let (closed, _) = crossbeam_channel::bounded(0);
+        drop(std::mem::replace(&mut self.tx, closed));
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn record_io(file: File, rx: Receiver<Message>) -> io::Result<()> {
     use std::io::{BufWriter, Write};
 
     fn write<T: Serialize>(mut file: &mut BufWriter<File>, val: &T) -> io::Result<()> {
@@ -99,16 +576,21 @@ fn record_io(file: File, rx: Receiver<Event>) -> io::Result<()> {
     )?;
 
     // wait to receive an event...
-    while let Ok(event) = rx.recv() {
-        // TODO: what to do if file error?
-        write(&mut file, &event)?;
-
+    while let Ok(message) = rx.recv() {
+        let mut flushed = Vec::new();
         // drain any additional events that are ready now
-        while let Ok(event) = rx.try_recv() {
-            write(&mut file, &event)?;
+        for message in std::iter::once(message).chain(rx.try_iter()) {
+            match message {
+                // TODO: what to do if file error?
+                Message::Event(event) => write(&mut file, &event)?,
+                Message::Flush(done) => flushed.push(done),
+            }
         }
 
         file.flush()?;
+        for done in flushed {
+            let _ = done.send(());
+        }
     }
 
     tracing::debug!("event stream ended; flushing file");
@@ -120,9 +602,12 @@ impl serde::Serialize for SerializeFields {
     where
         S: serde::Serializer,
     {
-        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
-        for element in &self.0 {
-            seq.serialize_element(&SerializeField(element))?;
+        let mut seq = serializer.serialize_seq(Some(self.fields.len()))?;
+        for field in &self.fields {
+            seq.serialize_element(&SerializeField {
+                field,
+                metadata: self.metadata,
+            })?;
         }
         seq.end()
     }
@@ -134,15 +619,19 @@ impl serde::Serialize for SerializeField<'_> {
         S: serde::Serializer,
     {
         let mut ser = serializer.serialize_struct("Field", 2)?;
-        ser.serialize_field(
-            "name",
-            match self.0.name.as_ref().expect("name") {
-                proto::field::Name::StrName(ref n) => n,
-                proto::field::Name::NameIdx(_idx) => todo!("metadata idx"),
-            },
-        )?;
-
-        match self.0.value.as_ref().expect("field value") {
+        let name = match self.field.name.as_ref().expect("name") {
+            proto::field::Name::StrName(ref n) => n.as_str(),
+            proto::field::Name::NameIdx(idx) => self
+                .metadata
+                .fields()
+                .iter()
+                .nth(*idx as usize)
+                .expect("field name index must be in the span's metadata")
+                .name(),
+        };
+        ser.serialize_field("name", name)?;
+
+        match self.field.value.as_ref().expect("field value") {
             proto::field::Value::DebugVal(v) | proto::field::Value::StrVal(v) => {
                 ser.serialize_field("value", v)?;
             }
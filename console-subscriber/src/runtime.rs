@@ -0,0 +1,52 @@
+//! Tracks which runtime the current thread belongs to, so that tasks can be
+//! attributed to a runtime when an application runs more than one.
+
+use std::{
+    cell::{OnceCell, RefCell},
+    sync::Arc,
+    thread,
+};
+
+thread_local! {
+    /// The runtime label explicitly registered for the current thread.
+    static REGISTERED: RefCell<Option<Arc<str>>> = const { RefCell::new(None) };
+
+    /// A runtime label inferred from the current thread's name.
+    ///
+    /// This is cached so that we don't allocate a new label for every task
+    /// spawned from the thread.
+    static INFERRED: OnceCell<Option<Arc<str>>> = const { OnceCell::new() };
+}
+
+/// Registers `label` as the label of the runtime the current thread belongs
+/// to.
+pub(crate) fn register(label: Arc<str>) {
+    REGISTERED.with(|registered| *registered.borrow_mut() = Some(label));
+}
+
+/// Returns the label of the runtime the current thread belongs to.
+///
+/// This is used to label the tasks spawned from the thread, even if they're
+/// spawned onto a different runtime through its `Handle`, as the runtime a
+/// task is spawned onto isn't recorded in its span.
+///
+/// If no label was registered for this thread, the thread's name is used
+/// instead, unless the thread is unnamed or is the main thread. Tokio's
+/// worker threads are all named `tokio-runtime-worker` by default, so this
+/// only tells runtimes apart if they name their threads differently.
+pub(crate) fn current() -> Option<Arc<str>> {
+    if let Some(label) = REGISTERED.with(|registered| registered.borrow().clone()) {
+        return Some(label);
+    }
+
+    INFERRED.with(|inferred| {
+        inferred
+            .get_or_init(|| {
+                thread::current()
+                    .name()
+                    .filter(|&name| name != "main")
+                    .map(Arc::from)
+            })
+            .clone()
+    })
+}
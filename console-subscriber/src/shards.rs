@@ -0,0 +1,138 @@
+//! A channel made up of several shards, so that threads sending events to the
+//! aggregator at the same time don't all contend on the same queue.
+//!
+//! Each thread always sends on the same shard, so the events sent by one
+//! thread are received in the order they were sent. Events sent by different
+//! threads may be received in a different order than they were sent in, which
+//! the aggregator tolerates, since every event it receives between two
+//! updates is applied before the next update is published.
+use std::{
+    cell::Cell,
+    sync::atomic::{AtomicUsize, Ordering},
+    task::{Context, Poll},
+};
+use tokio::sync::mpsc;
+
+/// The sending side of a sharded channel.
+#[derive(Debug)]
+pub(crate) struct Sender<T> {
+    shards: Box<[mpsc::Sender<T>]>,
+}
+
+/// A sending side of a sharded channel which does not keep it open.
+#[derive(Debug)]
+pub(crate) struct WeakSender<T> {
+    shards: Box<[mpsc::WeakSender<T>]>,
+}
+
+/// The receiving side of a sharded channel.
+#[derive(Debug)]
+pub(crate) struct Receiver<T> {
+    shards: Box<[mpsc::Receiver<T>]>,
+    /// The shard to receive from first, so that a busy shard doesn't stop the
+    /// others from being drained.
+    next: usize,
+}
+
+/// Creates a channel with `shards` shards, which can each hold `capacity`
+/// values.
+pub(crate) fn channel<T>(shards: usize, capacity: usize) -> (Sender<T>, Receiver<T>) {
+    let (senders, receivers) = (0..shards.max(1))
+        .map(|_| mpsc::channel(capacity.max(1)))
+        .unzip::<_, _, Vec<_>, Vec<_>>();
+    let tx = Sender {
+        shards: senders.into(),
+    };
+    let rx = Receiver {
+        shards: receivers.into(),
+        next: 0,
+    };
+    (tx, rx)
+}
+
+/// Returns this thread's index, used to pick the shard it sends on.
+///
+/// Indices are handed out in the order that threads first send, so that
+/// threads are spread evenly across the shards.
+fn thread_index() -> usize {
+    static NEXT_INDEX: AtomicUsize = AtomicUsize::new(0);
+    thread_local! {
+        static INDEX: Cell<Option<usize>> = const { Cell::new(None) };
+    }
+
+    INDEX.with(|index| {
+        index.get().unwrap_or_else(|| {
+            let next = NEXT_INDEX.fetch_add(1, Ordering::Relaxed);
+            index.set(Some(next));
+            next
+        })
+    })
+}
+
+// === impl Sender ===
+
+impl<T> Sender<T> {
+    /// Returns the shard that the current thread sends on.
+    pub(crate) fn shard(&self) -> &mpsc::Sender<T> {
+        &self.shards[thread_index() % self.shards.len()]
+    }
+
+    /// Returns the number of values that can be sent before every shard is
+    /// full.
+    pub(crate) fn capacity(&self) -> usize {
+        self.shards.iter().map(mpsc::Sender::capacity).sum()
+    }
+
+    pub(crate) fn downgrade(&self) -> WeakSender<T> {
+        WeakSender {
+            shards: self.shards.iter().map(mpsc::Sender::downgrade).collect(),
+        }
+    }
+}
+
+// === impl WeakSender ===
+
+impl<T> WeakSender<T> {
+    /// Returns the number of values waiting to be received across all shards,
+    /// or 0 if the channel has closed.
+    pub(crate) fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .filter_map(mpsc::WeakSender::upgrade)
+            .map(|tx| tx.max_capacity() - tx.capacity())
+            .sum()
+    }
+}
+
+// === impl Receiver ===
+
+impl<T> Receiver<T> {
+    /// Receives a value from any shard, without waiting.
+    ///
+    /// Returns `None` if every shard is empty, and `Some(None)` if every shard
+    /// has closed.
+    pub(crate) fn recv_now_or_never(&mut self) -> Option<Option<T>> {
+        let waker = futures_task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let len = self.shards.len();
+        let mut closed = 0;
+        for i in 0..len {
+            let idx = (self.next + i) % len;
+            match self.shards[idx].poll_recv(&mut cx) {
+                Poll::Ready(Some(value)) => {
+                    self.next = (idx + 1) % len;
+                    return Some(Some(value));
+                }
+                Poll::Ready(None) => closed += 1,
+                Poll::Pending => {}
+            }
+        }
+
+        if closed == len {
+            Some(None)
+        } else {
+            None
+        }
+    }
+}
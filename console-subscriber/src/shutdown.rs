@@ -0,0 +1,152 @@
+use crate::record::Recorder;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use tokio::{sync::Notify, task::JoinHandle};
+
+/// A handle which stops a [`Server`] and its [`Aggregator`].
+///
+/// When [`shutdown`] is called, the server stops accepting new connections,
+/// the aggregator publishes a final update to any connected clients and
+/// ends their streams, and the server's future completes once they have
+/// disconnected. If events are being recorded, the recording is flushed to
+/// disk before the future completes.
+///
+/// A `ShutdownHandle` is returned by [`Server::shutdown_handle`], and may be
+/// cloned and moved to wherever the application coordinates its shutdown.
+///
+/// # Examples
+///
+/// ```rust
+/// # async fn docs() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+/// let (_layer, server) = console_subscriber::ConsoleLayer::new();
+/// let shutdown = server.shutdown_handle();
+///
+/// tokio::spawn(async move {
+///     let _ = tokio::signal::ctrl_c().await;
+///     shutdown.shutdown();
+/// });
+///
+/// // Returns once the server has shut down.
+/// server.serve().await
+/// # }
+/// ```
+///
+/// [`Server`]: crate::Server
+/// [`Aggregator`]: crate::Aggregator
+/// [`shutdown`]: ShutdownHandle::shutdown
+/// [`Server::shutdown_handle`]: crate::Server::shutdown_handle
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    shutdown: Arc<Shutdown>,
+    recorder: Option<Arc<Recorder>>,
+}
+
+/// Signals the server and aggregator to stop.
+#[derive(Debug, Default)]
+pub(crate) struct Shutdown {
+    notify: Notify,
+    requested: AtomicBool,
+}
+
+// === impl ShutdownHandle ===
+
+impl ShutdownHandle {
+    pub(crate) fn new(shutdown: Arc<Shutdown>, recorder: Option<Arc<Recorder>>) -> Self {
+        Self { shutdown, recorder }
+    }
+
+    /// Starts shutting down the server and aggregator.
+    ///
+    /// This returns immediately; the future returned by [`Server::serve`]
+    /// (or the other `serve_with` methods) completes once the shutdown has
+    /// finished. Calling this more than once has no further effect.
+    ///
+    /// [`Server::serve`]: crate::Server::serve
+    pub fn shutdown(&self) {
+        self.shutdown.trigger();
+    }
+
+    /// Returns `true` if [`shutdown`] has been called.
+    ///
+    /// [`shutdown`]: ShutdownHandle::shutdown
+    pub fn is_shutdown(&self) -> bool {
+        self.shutdown.is_requested()
+    }
+
+    /// Blocks until every event recorded so far has been written to the
+    /// recording file.
+    ///
+    /// This does nothing if events are not being recorded. It is called
+    /// automatically when a server started with one of the `serve` methods
+    /// shuts down; it is only needed when running the server from
+    /// [`Server::into_parts`].
+    ///
+    /// [`Server::into_parts`]: crate::Server::into_parts
+    pub fn flush_recording(&self) {
+        if let Some(ref recorder) = self.recorder {
+            recorder.flush();
+        }
+    }
+
+    /// Completes once [`shutdown`] has been called.
+    ///
+    /// [`shutdown`]: ShutdownHandle::shutdown
+    pub(crate) async fn requested(self) {
+        self.shutdown.requested().await
+    }
+
+    /// Finishes serving once the server has stopped.
+    ///
+    /// If the server was shut down, this waits for the aggregator to publish
+    /// its final update; otherwise, the server failed and the aggregator is
+    /// aborted. Either way, the recording is then flushed.
+    pub(crate) async fn finish(&self, aggregate: JoinHandle<()>) {
+        if self.is_shutdown() {
+            let _ = aggregate.await;
+        } else {
+            aggregate.abort();
+        }
+        if self.recorder.is_some() {
+            let handle = self.clone();
+            let _ = tokio::task::spawn_blocking(move || handle.flush_recording()).await;
+        }
+    }
+}
+
+impl std::fmt::Debug for ShutdownHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShutdownHandle")
+            .field("shutdown", &self.shutdown)
+            .field("recording", &self.recorder.is_some())
+            .finish()
+    }
+}
+
+// === impl Shutdown ===
+
+impl Shutdown {
+    fn trigger(&self) {
+        if !self.requested.swap(true, Ordering::AcqRel) {
+            self.notify.notify_waiters();
+        }
+    }
+
+    pub(crate) fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::Acquire)
+    }
+
+    /// Completes once shutdown has been requested.
+    pub(crate) async fn requested(&self) {
+        loop {
+            // Register interest before checking the flag, so that a
+            // notification sent in between isn't missed.
+            let notified = self.notify.notified();
+            if self.is_requested() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
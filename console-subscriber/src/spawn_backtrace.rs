@@ -0,0 +1,102 @@
+use backtrace::Backtrace;
+use std::{
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering::*},
+    time::Instant,
+};
+
+/// Captures backtraces where tasks are spawned, if enabled with
+/// [`Builder::capture_spawn_backtraces`].
+///
+/// Capturing a backtrace walks the whole stack, so only a limited number are
+/// captured each second. Tasks spawned once the limit is reached have no
+/// backtrace. Backtraces are captured unresolved on the spawning thread, and
+/// only resolved to symbols by the aggregator.
+///
+/// [`Builder::capture_spawn_backtraces`]: crate::Builder::capture_spawn_backtraces
+#[derive(Debug)]
+pub(crate) struct SpawnBacktraces {
+    base: Instant,
+    /// The second, since `base`, of the current rate limiting window.
+    window: AtomicU64,
+    /// How many backtraces have been captured in the current window.
+    captured: AtomicUsize,
+}
+
+/// The function names of the crates whose frames are left out of spawn
+/// backtraces, as they're the same for every task.
+const OMITTED_CRATES: &[&str] = &[
+    "alloc",
+    "backtrace",
+    "console_subscriber",
+    "core",
+    "std",
+    "tokio",
+    "tracing",
+    "tracing_core",
+    "tracing_subscriber",
+];
+
+impl SpawnBacktraces {
+    /// The most backtraces captured each second.
+    const MAX_PER_SECOND: usize = 100;
+
+    /// The most frames kept from each backtrace.
+    const MAX_FRAMES: usize = 16;
+
+    pub(crate) fn new() -> Self {
+        Self {
+            base: Instant::now(),
+            window: AtomicU64::new(0),
+            captured: AtomicUsize::new(0),
+        }
+    }
+
+    /// Captures the current thread's backtrace, unless the limit for this
+    /// second has been reached.
+    pub(crate) fn capture(&self, now: Instant) -> Option<Backtrace> {
+        let second = now.saturating_duration_since(self.base).as_secs();
+        let window = self.window.load(Acquire);
+        if second != window
+            && self
+                .window
+                .compare_exchange(window, second, AcqRel, Acquire)
+                .is_ok()
+        {
+            self.captured.store(0, Release);
+        }
+        if self.captured.fetch_add(1, AcqRel) >= Self::MAX_PER_SECOND {
+            return None;
+        }
+        Some(Backtrace::new_unresolved())
+    }
+}
+
+/// Resolves a backtrace captured by [`SpawnBacktraces::capture`] into the
+/// frames that are sent to clients.
+pub(crate) fn resolve(mut backtrace: Backtrace) -> Vec<String> {
+    backtrace.resolve();
+    backtrace
+        .frames()
+        .iter()
+        .flat_map(|frame| frame.symbols())
+        .filter_map(|symbol| {
+            // The alternate format leaves off the symbol's hash.
+            let name = format!("{:#}", symbol.name()?);
+            if is_omitted(&name) {
+                return None;
+            }
+            Some(match (symbol.filename(), symbol.lineno()) {
+                (Some(file), Some(line)) => format!("{} at {}:{}", name, file.display(), line),
+                _ => name,
+            })
+        })
+        .take(SpawnBacktraces::MAX_FRAMES)
+        .collect()
+}
+
+fn is_omitted(name: &str) -> bool {
+    // Trait impls are named like `<tokio::task::JoinHandle<T> as Future>::poll`.
+    let path = name.trim_start_matches('<');
+    let krate = path.split("::").next().unwrap_or(path);
+    OMITTED_CRATES.contains(&krate)
+}
@@ -4,9 +4,11 @@ use hdrhistogram::{
     self,
     serialization::{Serializer, V2Serializer},
 };
+use std::borrow::Cow;
 use std::cmp;
+use std::collections::{HashMap, VecDeque};
 use std::sync::{
-    atomic::{AtomicBool, AtomicUsize, Ordering::*},
+    atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering::*},
     Arc,
 };
 use std::time::{Duration, Instant, SystemTime};
@@ -41,6 +43,69 @@ pub(crate) trait DroppedAt {
     fn dropped_at(&self) -> Option<Instant>;
 }
 
+/// The most distinct locations a task's wakes are counted for. Wakes from
+/// other locations after this many have been seen are not counted.
+const MAX_WAKE_SITES: usize = 32;
+
+/// The number of a task's most frequent wake locations sent in its details.
+const TOP_WAKE_SITES: usize = 5;
+
+/// The number of publish intervals a task's wake counts are kept for.
+const WAKE_HISTORY_LEN: usize = 60;
+
+/// The most distinct threads a task's polls are counted for. Polls on other
+/// threads after this many have been seen are not counted.
+const MAX_POLL_THREADS: usize = 64;
+
+/// Identifies the thread a task was polled on.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct PollThread {
+    /// An ID assigned to each thread the first time it polls a task.
+    ///
+    /// Tokio's worker threads all share the same name, so this is what tells
+    /// them apart.
+    id: u64,
+    name: Option<Arc<str>>,
+}
+
+/// A location from which a task was woken.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct WakeSite {
+    pub(crate) file: Cow<'static, str>,
+    pub(crate) line: Option<u32>,
+    pub(crate) column: Option<u32>,
+}
+
+/// A task's wake counts over its most recent publish intervals.
+#[derive(Debug, Default)]
+struct WakeHistory {
+    /// The task's total wakes and self-wakes when the current interval began.
+    wakes: u64,
+    self_wakes: u64,
+    /// The wakes and self-wakes in each interval, oldest first.
+    intervals: VecDeque<(u64, u64)>,
+}
+
+/// How the most recent outermost poll of a task began, used to tell whether
+/// the task completed or was cancelled once its span closes.
+///
+/// Tokio doesn't record what a task's polls return, or when a task is
+/// aborted. But `Instrumented` futures enter their span when they're dropped,
+/// so the last time a task's span is entered is always when its future is
+/// dropped. A future that returned `Ready` is dropped straight after that
+/// poll, on the same thread, and has already dropped everything it was
+/// waiting on. A future that's dropped at any other time, or that drops one
+/// of its task's wakers while it's being dropped, never completed.
+#[derive(Debug, Default)]
+struct LastEnter {
+    /// If the span was entered directly after a poll of the task ended on
+    /// the same thread, with nothing else recorded in between, how long that
+    /// poll took.
+    follows_poll: Option<Duration>,
+    /// Whether one of the task's wakers was dropped while it was entered.
+    dropped_waker: bool,
+}
+
 /// Anchors an `Instant` with a `SystemTime` timestamp to allow converting
 /// monotonic `Instant`s into timestamps that can be sent over the wire.
 #[derive(Debug, Clone)]
@@ -54,6 +119,11 @@ pub(crate) struct TimeAnchor {
 pub(crate) struct TaskStats {
     is_dirty: AtomicBool,
     is_dropped: AtomicBool,
+    /// Whether the task was dropped before its future completed.
+    is_cancelled: AtomicBool,
+    /// Whether the task panicked.
+    is_panicked: AtomicBool,
+    last_enter: Mutex<LastEnter>,
     // task stats
     pub(crate) created_at: Instant,
     dropped_at: Mutex<Option<Instant>>,
@@ -63,6 +133,20 @@ pub(crate) struct TaskStats {
     waker_clones: AtomicUsize,
     waker_drops: AtomicUsize,
     self_wakes: AtomicUsize,
+    /// The number of wakes from each location the task was woken from.
+    wake_sites: Mutex<HashMap<WakeSite, u64>>,
+    wake_history: Mutex<WakeHistory>,
+
+    /// The number of polls performed by each thread the task was polled on.
+    poll_threads: Mutex<HashMap<PollThread, u64>>,
+
+    /// Estimated time spent in the layer's callbacks for this task, in
+    /// nanoseconds.
+    overhead_nanos: AtomicU64,
+
+    /// The name of the innermost user span the task is in, if it's being
+    /// polled and user spans are tracked.
+    current_span: Mutex<Option<&'static str>>,
 
     /// Poll durations and other stats.
     poll_stats: PollStats<Histogram>,
@@ -116,8 +200,18 @@ struct PollTimestamps<H> {
     last_poll_started: Option<Instant>,
     last_poll_ended: Option<Instant>,
     busy_time: Duration,
+    /// The portion of `busy_time` spent in polls that returned `Ready`.
+    ready_time: Duration,
+    /// The duration of the most recently completed poll.
+    last_poll_time: Duration,
+    /// Whether the poll currently in progress has been reported to return
+    /// `Ready`.
+    poll_is_ready: bool,
     scheduled_time: Duration,
     poll_histogram: H,
+    /// Poll durations recorded since the last time the task's details were
+    /// published, only allocated while a details stream wants them.
+    recent_poll_histogram: Option<H>,
     scheduled_histogram: H,
 }
 
@@ -162,17 +256,24 @@ impl TaskStats {
         Self {
             is_dirty: AtomicBool::new(true),
             is_dropped: AtomicBool::new(false),
+            is_cancelled: AtomicBool::new(false),
+            is_panicked: AtomicBool::new(false),
+            last_enter: Mutex::new(LastEnter::default()),
             created_at,
             dropped_at: Mutex::new(None),
             poll_stats: PollStats {
                 timestamps: Mutex::new(PollTimestamps {
                     poll_histogram: Histogram::new(poll_duration_max),
+                    recent_poll_histogram: None,
                     scheduled_histogram: Histogram::new(scheduled_duration_max),
                     first_poll: None,
                     last_wake: None,
                     last_poll_started: None,
                     last_poll_ended: None,
                     busy_time: Duration::new(0, 0),
+                    ready_time: Duration::new(0, 0),
+                    last_poll_time: Duration::new(0, 0),
+                    poll_is_ready: false,
                     scheduled_time: Duration::new(0, 0),
                 }),
                 current_polls: AtomicUsize::new(0),
@@ -182,9 +283,20 @@ impl TaskStats {
             waker_clones: AtomicUsize::new(0),
             waker_drops: AtomicUsize::new(0),
             self_wakes: AtomicUsize::new(0),
+            wake_sites: Mutex::new(HashMap::new()),
+            wake_history: Mutex::new(WakeHistory::default()),
+            poll_threads: Mutex::new(HashMap::new()),
+            overhead_nanos: AtomicU64::new(0),
+            current_span: Mutex::new(None),
         }
     }
 
+    /// Adds an estimate of time spent in the layer's callbacks for this task.
+    pub(crate) fn record_overhead(&self, overhead: Duration) {
+        self.overhead_nanos
+            .fetch_add(overhead.as_nanos() as u64, Relaxed);
+    }
+
     pub(crate) fn record_wake_op(&self, op: crate::WakeOp, at: Instant) {
         use crate::WakeOp;
         match op {
@@ -212,6 +324,74 @@ impl TaskStats {
         self.make_dirty();
     }
 
+    /// Counts a wake of the task from `site`.
+    pub(crate) fn record_wake_site(&self, site: WakeSite) {
+        let mut sites = self.wake_sites.lock();
+        if let Some(wakes) = sites.get_mut(&site) {
+            *wakes += 1;
+        } else if sites.len() < MAX_WAKE_SITES {
+            sites.insert(site, 1);
+        }
+    }
+
+    /// Returns the locations the task was most often woken from, most
+    /// frequent first.
+    pub(crate) fn top_wake_sites(&self) -> Vec<proto::tasks::WakeSite> {
+        let mut sites = self
+            .wake_sites
+            .lock()
+            .iter()
+            .map(|(site, &wakes)| (site.clone(), wakes))
+            .collect::<Vec<_>>();
+        sites.sort_by(|(a, a_wakes), (b, b_wakes)| {
+            b_wakes
+                .cmp(a_wakes)
+                .then_with(|| (&a.file, a.line).cmp(&(&b.file, b.line)))
+        });
+        sites
+            .into_iter()
+            .take(TOP_WAKE_SITES)
+            .map(|(site, wakes)| proto::tasks::WakeSite {
+                location: Some(proto::Location {
+                    file: Some(site.file.into_owned()),
+                    module_path: None,
+                    line: site.line,
+                    column: site.column,
+                }),
+                wakes,
+            })
+            .collect()
+    }
+
+    /// Ends the task's current publish interval, recording the number of
+    /// wakes and self-wakes in it.
+    pub(crate) fn end_wake_interval(&self) {
+        if self.is_dropped.load(Acquire) {
+            return;
+        }
+        let wakes = self.wakes.load(Acquire) as u64;
+        let self_wakes = self.self_wakes.load(Acquire) as u64;
+        let mut history = self.wake_history.lock();
+        let interval = (wakes - history.wakes, self_wakes - history.self_wakes);
+        history.wakes = wakes;
+        history.self_wakes = self_wakes;
+        if history.intervals.len() == WAKE_HISTORY_LEN {
+            history.intervals.pop_front();
+        }
+        history.intervals.push_back(interval);
+    }
+
+    /// Returns the task's wake counts in each of its recent publish
+    /// intervals, oldest first.
+    pub(crate) fn wake_history(&self) -> Vec<proto::tasks::WakeInterval> {
+        self.wake_history
+            .lock()
+            .intervals
+            .iter()
+            .map(|&(wakes, self_wakes)| proto::tasks::WakeInterval { wakes, self_wakes })
+            .collect()
+    }
+
     fn wake(&self, at: Instant, self_wake: bool) {
         self.poll_stats.wake(at);
 
@@ -223,16 +403,93 @@ impl TaskStats {
         self.make_dirty();
     }
 
-    pub(crate) fn start_poll(&self, at: Instant) {
-        self.poll_stats.start_poll(at);
+    /// Records the start of a poll. `follows_poll` is whether the task's span
+    /// was entered straight after a poll of the task ended on this thread.
+    pub(crate) fn start_poll(&self, at: Instant, follows_poll: bool) {
+        if self.poll_stats.start_poll(at) {
+            let follows_poll =
+                follows_poll.then(|| self.poll_stats.timestamps.lock().last_poll_time);
+            *self.last_enter.lock() = LastEnter {
+                follows_poll,
+                dropped_waker: false,
+            };
+            let thread = PollThread::current();
+            let mut threads = self.poll_threads.lock();
+            if let Some(polls) = threads.get_mut(&thread) {
+                *polls += 1;
+            } else if threads.len() < MAX_POLL_THREADS {
+                threads.insert(thread, 1);
+            }
+        }
         self.make_dirty();
     }
 
-    pub(crate) fn end_poll(&self, at: Instant) {
-        self.poll_stats.end_poll(at);
+    /// Returns the number of polls performed by each thread the task was
+    /// polled on, most polls first.
+    pub(crate) fn poll_threads(&self) -> Vec<proto::tasks::ThreadPolls> {
+        let mut threads = self
+            .poll_threads
+            .lock()
+            .iter()
+            .map(|(thread, &polls)| proto::tasks::ThreadPolls {
+                thread_id: thread.id,
+                thread_name: thread.name.as_deref().map(String::from),
+                polls,
+            })
+            .collect::<Vec<_>>();
+        threads.sort_by(|a, b| b.polls.cmp(&a.polls).then(a.thread_id.cmp(&b.thread_id)));
+        threads
+    }
+
+    /// Records the innermost user span the task is in, or that it's no
+    /// longer in one.
+    pub(crate) fn set_current_span(&self, span: Option<&'static str>) {
+        let mut current = self.current_span.lock();
+        if *current != span {
+            *current = span;
+            drop(current);
+            self.make_dirty();
+        }
+    }
+
+    /// Records the end of a poll, returning how long the task was busy for if
+    /// this ended the task's outermost poll.
+    pub(crate) fn end_poll(&self, at: Instant) -> Option<Duration> {
+        let busy = self.poll_stats.end_poll(at);
+        self.make_dirty();
+        busy
+    }
+
+    /// Records that one of the task's wakers was dropped while the task was
+    /// being polled (or dropped).
+    pub(crate) fn drop_waker_in_poll(&self) {
+        self.last_enter.lock().dropped_waker = true;
+    }
+
+    /// Records that the task panicked. This has no effect if the task has
+    /// already completed.
+    pub(crate) fn panic(&self) {
+        if self.is_dropped.load(Acquire) {
+            return;
+        }
+        self.is_panicked.store(true, Release);
         self.make_dirty();
     }
 
+    /// Returns how the task terminated, if it has.
+    fn terminated_by(&self) -> proto::tasks::stats::TerminatedBy {
+        use proto::tasks::stats::TerminatedBy;
+        if !self.is_dropped.load(Acquire) {
+            TerminatedBy::NotTerminated
+        } else if self.is_panicked.load(Acquire) {
+            TerminatedBy::Panicked
+        } else if self.is_cancelled.load(Acquire) {
+            TerminatedBy::Aborted
+        } else {
+            TerminatedBy::Completed
+        }
+    }
+
     pub(crate) fn drop_task(&self, dropped_at: Instant) {
         if self.is_dropped.swap(true, AcqRel) {
             // The task was already dropped.
@@ -242,6 +499,19 @@ impl TaskStats {
 
         let _prev = self.dropped_at.lock().replace(dropped_at);
         debug_assert_eq!(_prev, None, "tried to drop a task twice; this is a bug!");
+        let last_enter = self.last_enter.lock();
+        match last_enter.follows_poll {
+            Some(_) if last_enter.dropped_waker => self.is_cancelled.store(true, Release),
+            // Tokio doesn't tell us what a task's polls return, but a task
+            // whose future completed was dropped right after the poll that
+            // returned `Ready`.
+            Some(ready_poll) if !self.is_panicked.load(Acquire) => {
+                self.poll_stats.add_ready_time(ready_poll)
+            }
+            Some(_) => {}
+            None => self.is_cancelled.store(true, Release),
+        }
+        drop(last_enter);
         self.make_dirty();
     }
 
@@ -250,6 +520,45 @@ impl TaskStats {
         proto::tasks::task_details::PollTimesHistogram::Histogram(hist)
     }
 
+    /// Starts or stops recording the task's recent poll durations.
+    ///
+    /// Starting allocates the histogram and begins a new interval, unless it
+    /// was already being recorded. Stopping frees it.
+    pub(crate) fn record_recent_poll_durations(&self, record: bool) {
+        let mut timestamps = self.poll_stats.timestamps.lock();
+        if !record {
+            timestamps.recent_poll_histogram = None;
+        } else if timestamps.recent_poll_histogram.is_none() {
+            let max = timestamps.poll_histogram.max;
+            timestamps.recent_poll_histogram = Some(Histogram::new(max));
+        }
+    }
+
+    /// Returns a histogram of the poll durations recorded since the last call
+    /// to this method, and starts a new interval, or `None` if they aren't
+    /// being recorded.
+    pub(crate) fn take_recent_poll_duration_histogram(
+        &self,
+    ) -> Option<proto::tasks::DurationHistogram> {
+        let mut timestamps = self.poll_stats.timestamps.lock();
+        let recent = timestamps.recent_poll_histogram.as_mut()?;
+        let hist = recent.to_proto();
+        recent.reset();
+        Some(hist)
+    }
+
+    /// Returns the approximate number of bytes allocated for this task's
+    /// histograms.
+    pub(crate) fn histogram_bytes(&self) -> usize {
+        let timestamps = self.poll_stats.timestamps.lock();
+        timestamps.poll_histogram.allocated_bytes()
+            + timestamps
+                .recent_poll_histogram
+                .as_ref()
+                .map_or(0, Histogram::allocated_bytes)
+            + timestamps.scheduled_histogram.allocated_bytes()
+    }
+
     pub(crate) fn scheduled_duration_histogram(&self) -> proto::tasks::DurationHistogram {
         self.poll_stats
             .timestamps
@@ -291,6 +600,15 @@ impl ToProto for TaskStats {
                         Default::default()
                     }),
             ),
+            cancelled: self.is_cancelled.load(Acquire),
+            instrumentation_overhead: match self.overhead_nanos.load(Relaxed) {
+                0 => None,
+                nanos => Duration::from_nanos(nanos).try_into().ok(),
+            },
+            // Filled in by the aggregator, which tracks what tasks wait on.
+            waiting_on: Vec::new(),
+            current_span: self.current_span.lock().map(String::from),
+            terminated_by: self.terminated_by().into(),
         }
     }
 }
@@ -328,7 +646,7 @@ impl AsyncOpStats {
     ) -> Self {
         Self {
             task_id: AtomicCell::new(0),
-            stats: ResourceStats::new(created_at, inherit_child_attributes, parent_id),
+            stats: ResourceStats::new(created_at, inherit_child_attributes, parent_id, 0),
             poll_stats: PollStats::default(),
         }
     }
@@ -361,6 +679,11 @@ impl AsyncOpStats {
         self.make_dirty();
     }
 
+    /// Records that the poll currently in progress will return `Ready`.
+    pub(crate) fn poll_is_ready(&self) {
+        self.poll_stats.poll_is_ready();
+    }
+
     #[inline]
     fn make_dirty(&self) {
         self.stats.make_dirty()
@@ -407,24 +730,27 @@ impl ToProto for AsyncOpStats {
 // === impl ResourceStats ===
 
 impl ResourceStats {
+    /// Returns the stats of a new resource, which keep the last
+    /// `attribute_history` values of each of its attributes.
     pub(crate) fn new(
         created_at: Instant,
         inherit_child_attributes: bool,
         parent_id: Option<Id>,
+        attribute_history: usize,
     ) -> Self {
         Self {
             is_dirty: AtomicBool::new(true),
             is_dropped: AtomicBool::new(false),
             created_at,
             dropped_at: Mutex::new(None),
-            attributes: Default::default(),
+            attributes: Mutex::new(attribute::Attributes::with_history(attribute_history)),
             inherit_child_attributes,
             parent_id,
         }
     }
 
-    pub(crate) fn update_attribute(&self, id: &Id, update: &attribute::Update) {
-        self.attributes.lock().update(id, update);
+    pub(crate) fn update_attribute(&self, id: &Id, update: &attribute::Update, at: Instant) {
+        self.attributes.lock().update(id, update, at);
         self.make_dirty();
     }
 
@@ -478,15 +804,43 @@ impl ToProto for ResourceStats {
     type Output = proto::resources::Stats;
 
     fn to_proto(&self, base_time: &TimeAnchor) -> Self::Output {
-        let attributes = self.attributes.lock().values().cloned().collect();
+        let attributes = self.attributes.lock();
+        let mut attribute_history = attributes.history().collect::<Vec<_>>();
+        attribute_history.sort_by_key(|(at, _)| *at);
+        let attribute_history = attribute_history
+            .into_iter()
+            .map(|(at, attribute)| proto::resources::AttributeSample {
+                at: Some(base_time.to_timestamp(*at)),
+                attribute: Some(attribute.clone()),
+            })
+            .collect();
         proto::resources::Stats {
             created_at: Some(base_time.to_timestamp(self.created_at)),
             dropped_at: self.dropped_at.lock().map(|at| base_time.to_timestamp(at)),
-            attributes,
+            attributes: attributes.values().cloned().collect(),
+            attribute_history,
+            // Filled in by the aggregator, which tracks poll ops.
+            acquired_by: None,
         }
     }
 }
 
+// === impl PollThread ===
+
+impl PollThread {
+    /// Returns the current thread.
+    fn current() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        thread_local! {
+            static CURRENT: PollThread = PollThread {
+                id: NEXT_ID.fetch_add(1, Relaxed),
+                name: std::thread::current().name().map(Arc::from),
+            };
+        }
+        CURRENT.with(Clone::clone)
+    }
+}
+
 // === impl PollStats ===
 
 impl<H: RecordDuration> PollStats<H> {
@@ -495,9 +849,11 @@ impl<H: RecordDuration> PollStats<H> {
         timestamps.last_wake = cmp::max(timestamps.last_wake, Some(at));
     }
 
-    fn start_poll(&self, at: Instant) {
+    /// Records the start of a poll, returning `true` if this started the
+    /// outermost poll.
+    fn start_poll(&self, at: Instant) -> bool {
         if self.current_polls.fetch_add(1, AcqRel) > 0 {
-            return;
+            return false;
         }
 
         // We are starting the first poll
@@ -515,7 +871,7 @@ impl<H: RecordDuration> PollStats<H> {
         // This also ensures that `busy_time` and `scheduled_time` don't overlap.
         let scheduled = match std::cmp::max(timestamps.last_wake, timestamps.last_poll_ended) {
             Some(scheduled) => scheduled,
-            None => return, // Async operations record polls, but not wakes
+            None => return true, // Async operations record polls, but not wakes
         };
 
         // `at < scheduled` is possible when a task switches threads between polls.
@@ -525,12 +881,13 @@ impl<H: RecordDuration> PollStats<H> {
         timestamps.scheduled_histogram.record_duration(elapsed);
 
         timestamps.scheduled_time += elapsed;
+        true
     }
 
-    fn end_poll(&self, at: Instant) {
+    fn end_poll(&self, at: Instant) -> Option<Duration> {
         // Are we ending the last current poll?
         if self.current_polls.fetch_sub(1, AcqRel) > 1 {
-            return;
+            return None;
         }
 
         let mut timestamps = self.timestamps.lock();
@@ -541,7 +898,7 @@ impl<H: RecordDuration> PollStats<H> {
                     "a poll ended, but start timestamp was recorded. \
                      this is probably a `console-subscriber` bug"
                 );
-                return;
+                return None;
             }
         };
 
@@ -554,14 +911,33 @@ impl<H: RecordDuration> PollStats<H> {
                     was before its start timestamp\nstart = {:?}\n  end = {:?}",
                     started, at
                 );
-                return;
+                return None;
             }
         };
 
         // if we have a poll time histogram, add the timestamp
         timestamps.poll_histogram.record_duration(elapsed);
+        if let Some(recent) = &mut timestamps.recent_poll_histogram {
+            recent.record_duration(elapsed);
+        }
 
         timestamps.busy_time += elapsed;
+        timestamps.last_poll_time = elapsed;
+        if std::mem::take(&mut timestamps.poll_is_ready) {
+            timestamps.ready_time += elapsed;
+        }
+        Some(elapsed)
+    }
+
+    fn poll_is_ready(&self) {
+        if self.current_polls.load(Acquire) > 0 {
+            self.timestamps.lock().poll_is_ready = true;
+        }
+    }
+
+    /// Counts a poll that took `poll_time` as having returned `Ready`.
+    fn add_ready_time(&self, poll_time: Duration) {
+        self.timestamps.lock().ready_time += poll_time;
     }
 }
 
@@ -586,6 +962,12 @@ impl<H> ToProto for PollStats<H> {
                 );
                 Default::default()
             })),
+            ready_time: timestamps.ready_time.try_into().ok(),
+            pending_time: timestamps
+                .busy_time
+                .saturating_sub(timestamps.ready_time)
+                .try_into()
+                .ok(),
         }
     }
 }
@@ -630,6 +1012,12 @@ impl Histogram {
         }
     }
 
+    /// Returns the approximate number of bytes allocated for the histogram's
+    /// counts.
+    fn allocated_bytes(&self) -> usize {
+        self.histogram.distinct_values() * std::mem::size_of::<u64>()
+    }
+
     fn to_proto(&self) -> proto::tasks::DurationHistogram {
         let mut serializer = V2Serializer::new();
         let mut raw_histogram = Vec::new();
@@ -643,6 +1031,12 @@ impl Histogram {
             highest_outlier: self.max_outlier,
         }
     }
+
+    fn reset(&mut self) {
+        self.histogram.reset();
+        self.outliers = 0;
+        self.max_outlier = None;
+    }
 }
 
 impl RecordDuration for Histogram {
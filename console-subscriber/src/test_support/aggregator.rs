@@ -0,0 +1,191 @@
+use std::time::Duration;
+
+use console_api::{
+    instrument::{
+        instrument_server::Instrument, DebugSelfRequest, InstrumentRequest, PauseRequest,
+        ResumeRequest, SelfMetrics, TaskDetailsRequest, Update,
+    },
+    tasks::TaskDetails,
+};
+use futures::Stream;
+
+use crate::{
+    aggregator::{Clock, ManualClock},
+    Builder, ConsoleLayer, Server,
+};
+
+/// Runs the console subscriber's aggregator with a clock that the test moves
+/// forward, so that it's deterministic when updates are published and when
+/// retained data expires.
+///
+/// Unlike [`Expectations`], the harness doesn't drive a future itself. The
+/// test installs the returned [`ConsoleLayer`], spawns the aggregator onto its
+/// runtime, and then alternates between doing work and [advancing] the
+/// aggregator's clock.
+///
+/// [`Expectations`]: super::Expectations
+/// [advancing]: AggregatorHarness::advance
+///
+/// # Examples
+///
+/// ```no_run
+/// use console_subscriber::{test_support::AggregatorHarness, ConsoleLayer};
+/// use std::time::Duration;
+/// use tracing_subscriber::prelude::*;
+///
+/// let (layer, mut harness) =
+///     AggregatorHarness::new(ConsoleLayer::builder().retention(Duration::from_secs(60)));
+/// let _subscriber = tracing_subscriber::registry().with(layer).set_default();
+/// # let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+/// runtime.block_on(async {
+///     harness.spawn();
+///     tokio::spawn(async {}).await.unwrap();
+///     let tasks = harness.self_metrics().await.tasks;
+///
+///     // Once the retention period has passed, the completed task is dropped.
+///     harness.advance(Duration::from_secs(61)).await;
+///     assert_eq!(harness.self_metrics().await.tasks, tasks - 1);
+/// });
+/// ```
+pub struct AggregatorHarness {
+    server: Server,
+    clock: ManualClock,
+}
+
+impl AggregatorHarness {
+    /// Builds a [`ConsoleLayer`] from `builder`, along with a harness for the
+    /// aggregator it sends its events to.
+    ///
+    /// The aggregator doesn't run until it's [spawned].
+    ///
+    /// [spawned]: AggregatorHarness::spawn
+    pub fn new(mut builder: Builder) -> (ConsoleLayer, Self) {
+        let clock = ManualClock::new();
+        builder.clock = Clock::Manual(clock.clone());
+        let (layer, server) = builder.build();
+        (layer, Self { server, clock })
+    }
+
+    /// Sets the largest update, in bytes, that the aggregator sends to a new
+    /// stream, so that a test can make it reduce retention to fit an update
+    /// without recording megabytes of data first.
+    ///
+    /// # Panics
+    ///
+    /// If the aggregator has already been spawned.
+    pub fn max_message_size(mut self, max_message_size: usize) -> Self {
+        self.server
+            .aggregator
+            .as_mut()
+            .expect("the max message size must be set before the aggregator is spawned")
+            .max_message_size = max_message_size;
+        self
+    }
+
+    /// Spawns the aggregator onto the current Tokio runtime.
+    ///
+    /// The aggregator stops once the harness is dropped.
+    ///
+    /// # Panics
+    ///
+    /// If the aggregator has already been spawned, or if this isn't called
+    /// from within a Tokio runtime.
+    pub fn spawn(&mut self) -> tokio::task::JoinHandle<()> {
+        let aggregator = self
+            .server
+            .aggregator
+            .take()
+            .expect("the aggregator can only be spawned once");
+        tokio::spawn(aggregator.run())
+    }
+
+    /// Returns the clock that the aggregator reads the time from.
+    pub fn clock(&self) -> &ManualClock {
+        &self.clock
+    }
+
+    /// Moves the aggregator's clock forward by `by`, then waits for the
+    /// aggregator to catch up with it, as with [`self_metrics`].
+    ///
+    /// If the publish interval has passed, this also makes the aggregator
+    /// publish an update to its streams, although the update may only arrive
+    /// after this returns.
+    ///
+    /// [`self_metrics`]: AggregatorHarness::self_metrics
+    pub async fn advance(&self, by: Duration) {
+        self.clock.advance(by);
+        self.self_metrics().await;
+    }
+
+    /// Returns the aggregator's metrics about itself.
+    ///
+    /// The aggregator is asked twice, so that by the time the second answer
+    /// arrives it has finished a whole pass of its loop since this was
+    /// called. Every event recorded before then has been aggregated, and
+    /// data older than the retention period has been dropped.
+    pub async fn self_metrics(&self) -> SelfMetrics {
+        let mut metrics = None;
+        for _ in 0..2 {
+            let response =
+                Instrument::debug_self(&self.server, tonic::Request::new(DebugSelfRequest {}))
+                    .await
+                    .expect("the aggregator should be running");
+            metrics = Some(response.into_inner());
+        }
+        metrics.expect("the aggregator was asked for its metrics")
+    }
+
+    /// Starts a stream of updates, as the console does when it connects.
+    ///
+    /// The first update on the stream describes everything the aggregator
+    /// retains.
+    pub async fn watch_updates(&self) -> impl Stream<Item = Result<Update, tonic::Status>> {
+        Instrument::watch_updates(
+            &self.server,
+            tonic::Request::new(InstrumentRequest::default()),
+        )
+        .await
+        .expect("the aggregator should be running")
+        .into_inner()
+    }
+
+    /// Pauses the update stream with the given ID, or every update stream if
+    /// the ID is 0, and waits for the aggregator to do so.
+    pub async fn pause(&self, stream_id: u64) {
+        Instrument::pause(
+            &self.server,
+            tonic::Request::new(PauseRequest { stream_id }),
+        )
+        .await
+        .expect("the aggregator should be running");
+        self.self_metrics().await;
+    }
+
+    /// Resumes the update stream with the given ID, or every update stream if
+    /// the ID is 0, and waits for the aggregator to do so.
+    ///
+    /// The next update on a resumed stream describes everything the
+    /// aggregator retains.
+    pub async fn resume(&self, stream_id: u64) {
+        Instrument::resume(
+            &self.server,
+            tonic::Request::new(ResumeRequest { stream_id }),
+        )
+        .await
+        .expect("the aggregator should be running");
+        self.self_metrics().await;
+    }
+
+    /// Starts a stream of details about a task, as the console does when the
+    /// task is selected.
+    pub async fn watch_task_details(
+        &self,
+        request: TaskDetailsRequest,
+    ) -> Result<impl Stream<Item = Result<TaskDetails, tonic::Status>>, tonic::Status> {
+        Ok(
+            Instrument::watch_task_details(&self.server, tonic::Request::new(request))
+                .await?
+                .into_inner(),
+        )
+    }
+}
@@ -0,0 +1,237 @@
+use std::{collections::BTreeMap, fmt};
+
+use console_api::async_ops;
+
+use super::{
+    validation::{Expectation, ValidationFailure},
+    MAIN_TASK_NAME,
+};
+
+/// An actual async op
+///
+/// This struct contains the values recorded from the console subscriber
+/// client and represents what is known about an actual async op performed on
+/// a resource on the test's runtime.
+#[derive(Clone, Debug)]
+pub(super) struct ActualAsyncOp {
+    pub(super) id: u64,
+    pub(super) source: String,
+    pub(super) resource_id: Option<u64>,
+    pub(super) task_id: Option<u64>,
+    /// The concrete type of the resource the async op was performed on.
+    pub(super) resource_type: Option<String>,
+    /// The name of the task which polled the async op.
+    pub(super) task_name: Option<String>,
+    pub(super) polls: u64,
+    /// The async op's attributes, with their values formatted as strings.
+    pub(super) attributes: BTreeMap<String, String>,
+}
+
+impl ActualAsyncOp {
+    pub(super) fn new(async_op: &async_ops::AsyncOp) -> Option<Self> {
+        Some(Self {
+            id: async_op.id?.id,
+            source: async_op.source.clone(),
+            resource_id: async_op.resource_id.map(|id| id.id),
+            task_id: None,
+            resource_type: None,
+            task_name: None,
+            polls: 0,
+            attributes: BTreeMap::new(),
+        })
+    }
+
+    pub(super) fn update_from_stats(&mut self, stats: &async_ops::Stats) {
+        self.task_id = stats.task_id.map(|id| id.id);
+        if let Some(poll_stats) = &stats.poll_stats {
+            self.polls = poll_stats.polls;
+        }
+    }
+}
+
+/// An expected async op.
+///
+/// This struct contains the fields that an expected async op will attempt to
+/// match actual async ops on, as well as the expectations that will be used
+/// to validate that the actual async op is as expected.
+///
+/// Async ops are matched by their source, the method which created them. If
+/// several async ops with the same source are recorded, the expectations are
+/// validated against only one of them.
+///
+/// # Examples
+///
+/// ```no_run
+/// use console_subscriber::test_support::{ExpectedAsyncOp, Expectations};
+///
+/// let expected = ExpectedAsyncOp::default()
+///     .match_source("Semaphore::acquire".into())
+///     .expect_resource("Semaphore".into())
+///     .expect_default_task();
+///
+/// Expectations::new().async_op(expected).assert(async {
+///     let semaphore = tokio::sync::Semaphore::new(1);
+///     let _permit = semaphore.acquire().await;
+/// });
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ExpectedAsyncOp {
+    match_source: Option<String>,
+    expect_present: Option<bool>,
+    expect_resource: Option<String>,
+    expect_task: Option<String>,
+    expect_polls: Option<u64>,
+    expect_attributes: BTreeMap<String, String>,
+}
+
+impl Expectation for ExpectedAsyncOp {
+    type Actual = ActualAsyncOp;
+    const NOUN: &'static str = "async op";
+
+    fn matches(&self, actual: &ActualAsyncOp) -> bool {
+        self.match_source.as_ref() == Some(&actual.source)
+    }
+
+    fn validate(&self, actual: &ActualAsyncOp) -> Result<(), ValidationFailure<Self>> {
+        let mut no_expectations = true;
+        if let Some(_expected) = self.expect_present {
+            no_expectations = false;
+        }
+
+        if let Some(expected_resource) = &self.expect_resource {
+            no_expectations = false;
+            if Some(expected_resource) != actual.resource_type.as_ref() {
+                return Err(self.failure(
+                    actual,
+                    format!(
+                        "{self}: expected the resource to be \
+                        {expected_resource:?}, but actual was {actual_resource:?}",
+                        actual_resource = actual.resource_type,
+                    ),
+                ));
+            }
+        }
+
+        if let Some(expected_task) = &self.expect_task {
+            no_expectations = false;
+            if Some(expected_task) != actual.task_name.as_ref() {
+                return Err(self.failure(
+                    actual,
+                    format!(
+                        "{self}: expected the task to be {expected_task:?}, \
+                        but actual was {actual_task:?}",
+                        actual_task = actual.task_name,
+                    ),
+                ));
+            }
+        }
+
+        if let Some(expected_polls) = self.expect_polls {
+            no_expectations = false;
+            if expected_polls != actual.polls {
+                return Err(self.failure(
+                    actual,
+                    format!(
+                        "{self}: expected `polls` to be {expected_polls}, but \
+                        actual was {actual_polls}",
+                        actual_polls = actual.polls,
+                    ),
+                ));
+            }
+        }
+
+        for (name, expected_value) in &self.expect_attributes {
+            no_expectations = false;
+            let actual_value = actual.attributes.get(name);
+            if Some(expected_value) != actual_value {
+                return Err(self.failure(
+                    actual,
+                    format!(
+                        "{self}: expected attribute `{name}` to be \
+                        {expected_value:?}, but actual was {actual_value:?}",
+                    ),
+                ));
+            }
+        }
+
+        if no_expectations {
+            return Err(self.no_expectations_error(actual));
+        }
+
+        Ok(())
+    }
+}
+
+impl ExpectedAsyncOp {
+    /// Matches async ops by source.
+    ///
+    /// To match this expected async op, an actual async op must have been
+    /// created by `source`, such as `"Semaphore::acquire"`.
+    pub fn match_source(mut self, source: String) -> Self {
+        self.match_source = Some(source);
+        self
+    }
+
+    /// Expects that an async op is present.
+    ///
+    /// To validate, an actual async op matching this expected async op must be
+    /// found.
+    pub fn expect_present(mut self) -> Self {
+        self.expect_present = Some(true);
+        self
+    }
+
+    /// Expects that an async op was performed on a specific type of resource.
+    ///
+    /// To validate, the resource of the actual async op must have the concrete
+    /// type `concrete_type`.
+    pub fn expect_resource(mut self, concrete_type: String) -> Self {
+        self.expect_resource = Some(concrete_type);
+        self
+    }
+
+    /// Expects that an async op was polled by a specific task.
+    ///
+    /// To validate, the actual async op must have been polled by a task named
+    /// `name`.
+    pub fn expect_task(mut self, name: String) -> Self {
+        self.expect_task = Some(name);
+        self
+    }
+
+    /// Expects that an async op was polled by the task which runs the test's
+    /// future.
+    pub fn expect_default_task(self) -> Self {
+        self.expect_task(MAIN_TASK_NAME.into())
+    }
+
+    /// Expects that an async op has a specific value for `polls`.
+    ///
+    /// To validate, the actual async op must have a count of polls (on
+    /// `PollStats`) equal to `polls`.
+    pub fn expect_polls(mut self, polls: u64) -> Self {
+        self.expect_polls = Some(polls);
+        self
+    }
+
+    /// Expects that an async op has an attribute with a specific value.
+    ///
+    /// To validate, the actual async op's attribute named `name` must have
+    /// the value `value` when the test's future completes. Values are
+    /// compared as strings, so a numeric attribute with the value 1 is
+    /// expected with `"1"`.
+    pub fn expect_attribute(mut self, name: String, value: String) -> Self {
+        self.expect_attributes.insert(name, value);
+        self
+    }
+}
+
+impl fmt::Display for ExpectedAsyncOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields = match &self.match_source {
+            Some(source) => format!("source={source}"),
+            None => "(no fields to match on)".into(),
+        };
+        write!(f, "AsyncOp {{ {fields} }}")
+    }
+}
@@ -0,0 +1,159 @@
+//! Assertions on the tasks, resources, and async ops recorded by the console
+//! subscriber, for use in tests.
+//!
+//! This module lets applications and libraries check in their own tests that
+//! their tasks behave as expected (for example, that a task never wakes
+//! itself), using the same instrumentation that [`tokio-console`] displays.
+//!
+//! Each test drives a future to completion on a fresh current-thread Tokio
+//! runtime, with a [`ConsoleLayer`] recording everything it does. A console
+//! client receives the recorded updates, and once the future has completed,
+//! each expectation is validated against the first task, resource, or async
+//! op that matches it. If any expectation isn't met, the test panics with a
+//! description of every failure.
+//!
+//! Because the subscriber relies on Tokio's `tracing` instrumentation, tests
+//! using this module must be built with `RUSTFLAGS="--cfg tokio_unstable"`.
+//!
+//! Tests which need to control when the aggregator publishes updates and
+//! when retained data expires can use the [`AggregatorHarness`] instead.
+//!
+//! This module is only available when the `test-support` feature is enabled.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use console_subscriber::test_support::{ExpectedResource, ExpectedTask, Expectations};
+//!
+//! Expectations::new()
+//!     .task(
+//!         ExpectedTask::default()
+//!             .match_default_name()
+//!             .expect_self_wakes(0),
+//!     )
+//!     .resource(
+//!         ExpectedResource::default()
+//!             .match_concrete_type("Sleep".into())
+//!             .expect_present(),
+//!     )
+//!     .assert(async {
+//!         tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+//!     });
+//! ```
+//!
+//! [`tokio-console`]: https://github.com/tokio-rs/console
+//! [`ConsoleLayer`]: crate::ConsoleLayer
+
+use std::future::Future;
+
+mod aggregator;
+mod async_op;
+mod resource;
+mod state;
+mod subscriber;
+mod task;
+mod validation;
+
+pub use crate::aggregator::ManualClock;
+pub use aggregator::AggregatorHarness;
+pub use async_op::ExpectedAsyncOp;
+pub use resource::ExpectedResource;
+pub use subscriber::MAIN_TASK_NAME;
+pub use task::ExpectedTask;
+
+use subscriber::run_test;
+
+/// A set of expectations on what the console subscriber records while a
+/// future runs.
+///
+/// See the [module-level documentation](self) for details.
+#[derive(Clone, Debug, Default)]
+#[must_use = "expectations do nothing until `assert` is called"]
+pub struct Expectations {
+    tasks: Vec<ExpectedTask>,
+    resources: Vec<ExpectedResource>,
+    async_ops: Vec<ExpectedAsyncOp>,
+}
+
+impl Expectations {
+    /// Returns a new, empty set of expectations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an expectation on a task.
+    pub fn task(mut self, expected_task: ExpectedTask) -> Self {
+        self.tasks.push(expected_task);
+        self
+    }
+
+    /// Adds an expectation on a resource.
+    pub fn resource(mut self, expected_resource: ExpectedResource) -> Self {
+        self.resources.push(expected_resource);
+        self
+    }
+
+    /// Adds an expectation on an async op.
+    pub fn async_op(mut self, expected_async_op: ExpectedAsyncOp) -> Self {
+        self.async_ops.push(expected_async_op);
+        self
+    }
+
+    /// Asserts that these expectations are met by what a console subscriber
+    /// records when driving the provided `future` to completion.
+    ///
+    /// The future is run in a task named [`MAIN_TASK_NAME`].
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if any of the expectations are not met or if
+    /// matching tasks, resources, or async ops are not recorded for all of
+    /// them.
+    #[track_caller]
+    pub fn assert<Fut>(self, future: Fut)
+    where
+        Fut: Future + Send + 'static,
+        Fut::Output: Send + 'static,
+    {
+        run_test(self, future)
+    }
+}
+
+/// Assert that an `expected_task` is recorded by a console-subscriber
+/// when driving the provided `future` to completion.
+///
+/// This function is equivalent to calling [`assert_tasks`] with a vector
+/// containing a single task.
+///
+/// # Panics
+///
+/// This function will panic if the expectations on the expected task are not
+/// met or if a matching task is not recorded.
+#[track_caller]
+pub fn assert_task<Fut>(expected_task: ExpectedTask, future: Fut)
+where
+    Fut: Future + Send + 'static,
+    Fut::Output: Send + 'static,
+{
+    Expectations::new().task(expected_task).assert(future)
+}
+
+/// Assert that the `expected_tasks` are recorded by a console-subscriber
+/// when driving the provided `future` to completion.
+///
+/// # Panics
+///
+/// This function will panic if the expectations on any of the expected tasks
+/// are not met or if matching tasks are not recorded for all expected tasks.
+#[track_caller]
+pub fn assert_tasks<Fut>(expected_tasks: Vec<ExpectedTask>, future: Fut)
+where
+    Fut: Future + Send + 'static,
+    Fut::Output: Send + 'static,
+{
+    Expectations {
+        tasks: expected_tasks,
+        ..Expectations::default()
+    }
+    .assert(future)
+}
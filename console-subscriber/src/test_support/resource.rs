@@ -0,0 +1,260 @@
+use std::{collections::BTreeMap, fmt};
+
+use console_api::resources;
+
+use super::validation::{Expectation, ValidationFailure};
+
+/// An actual resource
+///
+/// This struct contains the values recorded from the console subscriber
+/// client and represents what is known about an actual resource created on
+/// the test's runtime.
+#[derive(Clone, Debug)]
+pub(super) struct ActualResource {
+    pub(super) id: u64,
+    pub(super) concrete_type: String,
+    pub(super) kind: String,
+    pub(super) location_file: Option<String>,
+    pub(super) is_internal: bool,
+    pub(super) dropped: bool,
+    /// The resource's attributes, with their values formatted as strings.
+    pub(super) attributes: BTreeMap<String, String>,
+}
+
+impl ActualResource {
+    pub(super) fn new(resource: &resources::Resource) -> Option<Self> {
+        use resources::resource::kind::{Kind, Known};
+
+        let kind = match resource.kind.as_ref()?.kind.as_ref()? {
+            Kind::Known(known) if *known == Known::Timer as i32 => "Timer".to_string(),
+            Kind::Known(_) => "Unknown".to_string(),
+            Kind::Other(other) => other.clone(),
+        };
+        Some(Self {
+            id: resource.id?.id,
+            concrete_type: resource.concrete_type.clone(),
+            kind,
+            location_file: resource
+                .location
+                .as_ref()
+                .and_then(|location| location.file.clone()),
+            is_internal: resource.is_internal,
+            dropped: false,
+            attributes: BTreeMap::new(),
+        })
+    }
+
+    pub(super) fn update_from_stats(&mut self, stats: &resources::Stats) {
+        self.dropped = stats.dropped_at.is_some();
+    }
+}
+
+/// An expected resource.
+///
+/// This struct contains the fields that an expected resource will attempt to
+/// match actual resources on, as well as the expectations that will be used
+/// to validate that the actual resource is as expected.
+///
+/// Resources are matched by their concrete type. If several resources of the
+/// same type are recorded, the expectations are validated against only one
+/// of them.
+///
+/// # Examples
+///
+/// ```no_run
+/// use console_subscriber::test_support::{ExpectedResource, Expectations};
+///
+/// let expected = ExpectedResource::default()
+///     .match_concrete_type("Sleep".into())
+///     .expect_kind("Timer".into());
+///
+/// Expectations::new().resource(expected).assert(async {
+///     tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+/// });
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ExpectedResource {
+    match_concrete_type: Option<String>,
+    expect_present: Option<bool>,
+    expect_kind: Option<String>,
+    expect_internal: Option<bool>,
+    expect_dropped: Option<bool>,
+    expect_file: Option<String>,
+    expect_attributes: BTreeMap<String, String>,
+}
+
+impl Expectation for ExpectedResource {
+    type Actual = ActualResource;
+    const NOUN: &'static str = "resource";
+
+    fn matches(&self, actual: &ActualResource) -> bool {
+        self.match_concrete_type.as_ref() == Some(&actual.concrete_type)
+    }
+
+    fn validate(&self, actual: &ActualResource) -> Result<(), ValidationFailure<Self>> {
+        let mut no_expectations = true;
+        if let Some(_expected) = self.expect_present {
+            no_expectations = false;
+        }
+
+        if let Some(expected_kind) = &self.expect_kind {
+            no_expectations = false;
+            if expected_kind != &actual.kind {
+                return Err(self.failure(
+                    actual,
+                    format!(
+                        "{self}: expected `kind` to be {expected_kind:?}, but \
+                        actual was {actual_kind:?}",
+                        actual_kind = actual.kind,
+                    ),
+                ));
+            }
+        }
+
+        if let Some(expected_internal) = self.expect_internal {
+            no_expectations = false;
+            if expected_internal != actual.is_internal {
+                return Err(self.failure(
+                    actual,
+                    format!(
+                        "{self}: expected `is_internal` to be \
+                        {expected_internal}, but actual was {actual_internal}",
+                        actual_internal = actual.is_internal,
+                    ),
+                ));
+            }
+        }
+
+        if let Some(expected_dropped) = self.expect_dropped {
+            no_expectations = false;
+            if expected_dropped != actual.dropped {
+                return Err(self.failure(
+                    actual,
+                    format!(
+                        "{self}: expected `dropped` to be {expected_dropped}, \
+                        but actual was {actual_dropped}",
+                        actual_dropped = actual.dropped,
+                    ),
+                ));
+            }
+        }
+
+        if let Some(expected_file) = &self.expect_file {
+            no_expectations = false;
+            let matches = actual
+                .location_file
+                .as_ref()
+                .is_some_and(|file| file.ends_with(expected_file.as_str()));
+            if !matches {
+                return Err(self.failure(
+                    actual,
+                    format!(
+                        "{self}: expected the location's file to end with \
+                        {expected_file:?}, but actual was {actual_file:?}",
+                        actual_file = actual.location_file,
+                    ),
+                ));
+            }
+        }
+
+        for (name, expected_value) in &self.expect_attributes {
+            no_expectations = false;
+            let actual_value = actual.attributes.get(name);
+            if Some(expected_value) != actual_value {
+                return Err(self.failure(
+                    actual,
+                    format!(
+                        "{self}: expected attribute `{name}` to be \
+                        {expected_value:?}, but actual was {actual_value:?}",
+                    ),
+                ));
+            }
+        }
+
+        if no_expectations {
+            return Err(self.no_expectations_error(actual));
+        }
+
+        Ok(())
+    }
+}
+
+impl ExpectedResource {
+    /// Matches resources by concrete type.
+    ///
+    /// To match this expected resource, an actual resource must have the
+    /// concrete type `concrete_type`, such as `"Semaphore"` or `"Sleep"`.
+    pub fn match_concrete_type(mut self, concrete_type: String) -> Self {
+        self.match_concrete_type = Some(concrete_type);
+        self
+    }
+
+    /// Expects that a resource is present.
+    ///
+    /// To validate, an actual resource matching this expected resource must be
+    /// found.
+    pub fn expect_present(mut self) -> Self {
+        self.expect_present = Some(true);
+        self
+    }
+
+    /// Expects that a resource has a specific kind.
+    ///
+    /// To validate, the actual resource must have the kind `kind`, such as
+    /// `"Sync"` or `"Timer"`.
+    pub fn expect_kind(mut self, kind: String) -> Self {
+        self.expect_kind = Some(kind);
+        self
+    }
+
+    /// Expects that a resource is, or is not, internal to the library which
+    /// created it.
+    ///
+    /// To validate, the actual resource's `is_internal` flag must equal
+    /// `is_internal`.
+    pub fn expect_internal(mut self, is_internal: bool) -> Self {
+        self.expect_internal = Some(is_internal);
+        self
+    }
+
+    /// Expects that a resource has, or has not, been dropped by the time the
+    /// test's future completes.
+    ///
+    /// To validate, the actual resource must have been dropped if `dropped`
+    /// is `true`, and must not have been if it is `false`.
+    pub fn expect_dropped(mut self, dropped: bool) -> Self {
+        self.expect_dropped = Some(dropped);
+        self
+    }
+
+    /// Expects that a resource was created in a specific file.
+    ///
+    /// To validate, the path of the file in the actual resource's location
+    /// must end with `file`.
+    pub fn expect_file(mut self, file: String) -> Self {
+        self.expect_file = Some(file);
+        self
+    }
+
+    /// Expects that a resource has an attribute with a specific value.
+    ///
+    /// Attributes describe a resource's current state, such as the number of
+    /// permits available in a `Semaphore`. To validate, the actual resource's
+    /// attribute named `name` must have the value `value` when the test's
+    /// future completes. Values are compared as strings, so a numeric
+    /// attribute with the value 2 is expected with `"2"`.
+    pub fn expect_attribute(mut self, name: String, value: String) -> Self {
+        self.expect_attributes.insert(name, value);
+        self
+    }
+}
+
+impl fmt::Display for ExpectedResource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields = match &self.match_concrete_type {
+            Some(concrete_type) => format!("concrete_type={concrete_type}"),
+            None => "(no fields to match on)".into(),
+        };
+        write!(f, "Resource {{ {fields} }}")
+    }
+}
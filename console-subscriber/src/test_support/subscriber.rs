@@ -1,34 +1,73 @@
-use std::{collections::HashMap, fmt, future::Future, thread};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    future::Future,
+    thread,
+};
 
 use console_api::{
-    field::Value,
+    field::{Name, Value},
     instrument::{instrument_client::InstrumentClient, InstrumentRequest},
+    Attribute, Field,
 };
-use console_subscriber::ServerParts;
 use futures::stream::StreamExt;
 use hyper_util::rt::TokioIo;
 use tokio::{io::DuplexStream, task};
 use tonic::transport::{Channel, Endpoint, Server, Uri};
 use tower::service_fn;
 
+use super::async_op::ActualAsyncOp;
+use super::resource::ActualResource;
 use super::state::{TestState, TestStep};
-use super::task::{ActualTask, ExpectedTask, TaskValidationFailure};
+use super::task::{ActualTask, ExpectedTask};
+use super::validation::{validate_all, Expectation, ValidationFailure};
+use super::Expectations;
+use crate::ServerParts;
 
-pub(crate) const MAIN_TASK_NAME: &str = "console-test::main";
+/// The name of the task which runs the future under test.
+pub const MAIN_TASK_NAME: &str = "console-test::main";
 const END_SIGNAL_TASK_NAME: &str = "console-test::signal";
 
 #[derive(Debug)]
 struct TestFailure {
-    failures: Vec<TaskValidationFailure>,
+    tasks: Vec<ValidationFailure<ExpectedTask>>,
+    resources: Vec<ValidationFailure<super::ExpectedResource>>,
+    async_ops: Vec<ValidationFailure<super::ExpectedAsyncOp>>,
+}
+
+/// Everything recorded by the console client while a test ran.
+struct Recorded {
+    tasks: Vec<ActualTask>,
+    resources: Vec<ActualResource>,
+    async_ops: Vec<ActualAsyncOp>,
+}
+
+impl TestFailure {
+    fn is_empty(&self) -> bool {
+        self.tasks.is_empty() && self.resources.is_empty() && self.async_ops.is_empty()
+    }
 }
 
 impl fmt::Display for TestFailure {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "Task validation failed:")?;
-        for failure in &self.failures {
-            writeln!(f, " - {failure}")?;
+        fn section<E: Expectation>(
+            f: &mut fmt::Formatter<'_>,
+            title: &str,
+            failures: &[ValidationFailure<E>],
+        ) -> fmt::Result {
+            if failures.is_empty() {
+                return Ok(());
+            }
+            writeln!(f, "{title} validation failed:")?;
+            for failure in failures {
+                writeln!(f, " - {failure}")?;
+            }
+            Ok(())
         }
-        Ok(())
+
+        section(f, "Task", &self.tasks)?;
+        section(f, "Resource", &self.resources)?;
+        section(f, "Async op", &self.async_ops)
     }
 }
 
@@ -39,10 +78,32 @@ impl fmt::Display for TestFailure {
 /// is then used to record traces as the provided future is driven to
 /// completion on a current thread tokio runtime.
 ///
-/// This function will panic if the expectations on any of the expected tasks
-/// are not met or if matching tasks are not recorded for all expected tasks.
+/// This function will panic if any of the expectations are not met or if
+/// matching tasks, resources, or async ops are not recorded for all of them.
 #[track_caller]
-pub(super) fn run_test<Fut>(expected_tasks: Vec<ExpectedTask>, future: Fut)
+pub(super) fn run_test<Fut>(expectations: Expectations, future: Fut)
+where
+    Fut: Future + Send + 'static,
+    Fut::Output: Send + 'static,
+{
+    let recorded = record_test(future);
+
+    let test_failure = TestFailure {
+        tasks: validate_all(&expectations.tasks, &recorded.tasks),
+        resources: validate_all(&expectations.resources, &recorded.resources),
+        async_ops: validate_all(&expectations.async_ops, &recorded.async_ops),
+    };
+    if !test_failure.is_empty() {
+        panic!("Test failed: {test_failure}")
+    }
+}
+
+/// Records the test
+///
+/// This function drives the provided future to completion with a
+/// `console-subscriber` layer, as described for [`run_test`], and returns
+/// everything the console client recorded, without validating it.
+fn record_test<Fut>(future: Fut) -> Recorded
 where
     Fut: Future + Send + 'static,
     Fut::Output: Send + 'static,
@@ -50,7 +111,7 @@ where
     use tracing_subscriber::prelude::*;
 
     let (client_stream, server_stream) = tokio::io::duplex(1024);
-    let (console_layer, server) = console_subscriber::ConsoleLayer::builder().build();
+    let (console_layer, server) = crate::ConsoleLayer::builder().build();
     let registry = tracing_subscriber::registry().with(console_layer);
 
     let mut test_state = TestState::new();
@@ -84,7 +145,7 @@ where
                     .spawn(console_server(server, server_stream, test_state.clone()))
                     .expect("console-test error: could not spawn 'console-server' task");
 
-                let actual_tasks = task::Builder::new()
+                let recorded = task::Builder::new()
                     .name("console::client")
                     .spawn(console_client(client_stream, test_state.clone()))
                     .expect("console-test error: could not spawn 'console-client' task")
@@ -92,7 +153,7 @@ where
                     .expect("console-test error: failed to await 'console-client' task");
 
                 test_state.advance_to_step(TestStep::UpdatesRecorded);
-                actual_tasks
+                recorded
             })
         })
         .expect("console-test error: console subscriber could not spawn thread");
@@ -127,13 +188,9 @@ where
         });
     });
 
-    let actual_tasks = join_handle
+    join_handle
         .join()
-        .expect("console-test error: failed to join 'console-subscriber' thread");
-
-    if let Err(test_failure) = validate_expected_tasks(expected_tasks, actual_tasks) {
-        panic!("Test failed: {test_failure}")
-    }
+        .expect("console-test error: failed to join 'console-subscriber' thread")
 }
 
 /// Starts the console server.
@@ -150,7 +207,7 @@ where
 /// 1. Advances to: `ServerStarted`
 /// 2. Waits for: `UpdatesRecorded`
 async fn console_server(
-    server: console_subscriber::Server,
+    server: crate::Server,
     server_stream: DuplexStream,
     mut test_state: TestState,
 ) {
@@ -177,20 +234,20 @@ async fn console_server(
     aggregate.abort();
 }
 
-/// Starts the console client and validates the expected tasks.
+/// Starts the console client and records what it receives.
 ///
 /// First we wait until the server has started (test step `ServerStarted`), then
 /// the client is connected to its half of the duplex stream and we start recording
 /// the actual tasks.
 ///
-/// Once recording finishes (see [`record_actual_tasks()`] for details on the test
-/// state condition), the actual tasks returned.
+/// Once recording finishes (see [`record_actual()`] for details on the test
+/// state condition), the actual tasks, resources, and async ops are returned.
 ///
 /// # Test State
 ///
 /// 1. Waits for: `ServerStarted`
 /// 2. Advances to: `ClientConnected`
-async fn console_client(client_stream: DuplexStream, mut test_state: TestState) -> Vec<ActualTask> {
+async fn console_client(client_stream: DuplexStream, mut test_state: TestState) -> Recorded {
     test_state.wait_for_step(TestStep::ServerStarted).await;
 
     let mut client_stream = Some(client_stream);
@@ -207,8 +264,7 @@ async fn console_client(client_stream: DuplexStream, mut test_state: TestState)
                 // We need to return a Result from this async block, which is
                 // why we don't unwrap the `client` here.
                 client.map(TokioIo::new).ok_or_else(|| {
-                    std::io::Error::new(
-                        std::io::ErrorKind::Other,
+                    std::io::Error::other(
                         "console-test error: client already taken. This shouldn't happen.",
                     )
                 })
@@ -218,26 +274,24 @@ async fn console_client(client_stream: DuplexStream, mut test_state: TestState)
         .expect("console-test client error: couldn't create client");
     test_state.advance_to_step(TestStep::ClientConnected);
 
-    record_actual_tasks(channel, test_state).await
+    record_actual(channel, test_state).await
 }
 
-/// Records the actual tasks which are received by the client channel.
+/// Records the actual tasks, resources, and async ops which are received by
+/// the client channel.
 ///
 /// Updates will be received until the test state reaches the `TestFinished` step
 /// (indicating that the test itself has finished running), at which point we wait
-/// for a final update before returning all the actual tasks which were recorded.
+/// for a final update before returning everything which was recorded.
 ///
 /// # Test State
 ///
 /// 1. Waits for: `TestFinished`
-async fn record_actual_tasks(
-    client_channel: Channel,
-    mut test_state: TestState,
-) -> Vec<ActualTask> {
+async fn record_actual(client_channel: Channel, mut test_state: TestState) -> Recorded {
     let mut client = InstrumentClient::new(client_channel);
 
     let mut stream = match client
-        .watch_updates(tonic::Request::new(InstrumentRequest {}))
+        .watch_updates(tonic::Request::new(InstrumentRequest::default()))
         .await
     {
         Ok(stream) => stream.into_inner(),
@@ -245,6 +299,11 @@ async fn record_actual_tasks(
     };
 
     let mut tasks = HashMap::new();
+    let mut resources = HashMap::new();
+    let mut async_ops = HashMap::new();
+    // Field names of each registered callsite, used to resolve field names
+    // which are sent as indices into the callsite's metadata.
+    let mut field_names = HashMap::new();
 
     // The console-subscriber aggregator is a bit of an unknown entity for us,
     // especially with respect to its update loops. We can't guarantee that
@@ -256,26 +315,32 @@ async fn record_actual_tasks(
     while let Some(update) = stream.next().await {
         let update = update.expect("console-test error: update stream error");
 
+        if let Some(new_metadata) = &update.new_metadata {
+            for new_metadata in &new_metadata.metadata {
+                if let (Some(id), Some(metadata)) = (&new_metadata.id, &new_metadata.metadata) {
+                    field_names.insert(id.id, metadata.field_names.clone());
+                }
+            }
+        }
+
         if let Some(task_update) = &update.task_update {
             for new_task in &task_update.new_tasks {
                 let mut actual_task = match new_task.id {
                     Some(id) => ActualTask::new(id.id),
                     None => continue,
                 };
+                actual_task.group.clone_from(&new_task.group);
                 for field in &new_task.fields {
-                    match field.name.as_ref() {
-                        Some(console_api::field::Name::StrName(name)) if name == "task.name" => {
-                            actual_task.name = match field.value.as_ref() {
-                                Some(Value::DebugVal(value)) => Some(value.clone()),
-                                Some(Value::StrVal(value)) => Some(value.clone()),
-                                _ => continue,
-                            };
-                        }
-                        _ => {}
+                    if field_name(field, &field_names) == Some("task.name") {
+                        actual_task.name = match field.value.as_ref() {
+                            Some(Value::DebugVal(value)) => Some(value.clone()),
+                            Some(Value::StrVal(value)) => Some(value.clone()),
+                            _ => continue,
+                        };
                     }
                 }
 
-                if signal_task.matches_actual_task(&actual_task) {
+                if signal_task.matches(&actual_task) {
                     signal_task_read = true;
                 } else {
                     tasks.insert(actual_task.id, actual_task);
@@ -289,52 +354,92 @@ async fn record_actual_tasks(
             }
         }
 
+        if let Some(resource_update) = &update.resource_update {
+            for new_resource in &resource_update.new_resources {
+                if let Some(actual_resource) = ActualResource::new(new_resource) {
+                    resources.insert(actual_resource.id, actual_resource);
+                }
+            }
+
+            for (id, stats) in &resource_update.stats_update {
+                if let Some(resource) = resources.get_mut(id) {
+                    resource.update_from_stats(stats);
+                    resource.attributes = attributes(&stats.attributes, &field_names);
+                }
+            }
+        }
+
+        if let Some(async_op_update) = &update.async_op_update {
+            for new_async_op in &async_op_update.new_async_ops {
+                if let Some(actual_async_op) = ActualAsyncOp::new(new_async_op) {
+                    async_ops.insert(actual_async_op.id, actual_async_op);
+                }
+            }
+
+            for (id, stats) in &async_op_update.stats_update {
+                if let Some(async_op) = async_ops.get_mut(id) {
+                    async_op.update_from_stats(stats);
+                    async_op.attributes = attributes(&stats.attributes, &field_names);
+                }
+            }
+        }
+
         if test_state.is_step(TestStep::TestFinished) && signal_task_read {
             // Once the test finishes running and we've read the signal task, the test ends.
             break;
         }
     }
 
-    tasks.into_values().collect()
-}
+    // Resolve the names of the resources and tasks each async op belongs
+    // to, so that expectations don't need to know their IDs.
+    for async_op in async_ops.values_mut() {
+        async_op.resource_type = async_op
+            .resource_id
+            .and_then(|id| resources.get(&id))
+            .map(|resource| resource.concrete_type.clone());
+        async_op.task_name = async_op
+            .task_id
+            .and_then(|id| tasks.get(&id))
+            .and_then(|task| task.name.clone());
+    }
 
-/// Validate the expected tasks against the actual tasks.
-///
-/// Each expected task is checked in turn.
-///
-/// A matching actual task is searched for. If one is found it, the
-/// expected task is validated against the actual task.
-///
-/// Any validation errors result in failure. If no matches
-fn validate_expected_tasks(
-    expected_tasks: Vec<ExpectedTask>,
-    actual_tasks: Vec<ActualTask>,
-) -> Result<(), TestFailure> {
-    let failures: Vec<_> = expected_tasks
-        .iter()
-        .map(|expected| validate_expected_task(expected, &actual_tasks))
-        .filter_map(Result::err)
-        .collect();
-
-    if failures.is_empty() {
-        Ok(())
-    } else {
-        Err(TestFailure { failures })
+    Recorded {
+        tasks: tasks.into_values().collect(),
+        resources: resources.into_values().collect(),
+        async_ops: async_ops.into_values().collect(),
     }
 }
 
-#[allow(clippy::result_large_err)]
-fn validate_expected_task(
-    expected: &ExpectedTask,
-    actual_tasks: &Vec<ActualTask>,
-) -> Result<(), TaskValidationFailure> {
-    for actual in actual_tasks {
-        if expected.matches_actual_task(actual) {
-            // We only match a single task.
-            // FIXME(hds): We should probably create an error or a warning if multiple tasks match.
-            return expected.validate_actual_task(actual);
-        }
+/// Returns the name of `field`, looking it up in the names of its callsite's
+/// fields if it was sent as an index.
+fn field_name<'a>(field: &'a Field, field_names: &'a HashMap<u64, Vec<String>>) -> Option<&'a str> {
+    match field.name.as_ref()? {
+        Name::StrName(name) => Some(name),
+        Name::NameIdx(idx) => field
+            .metadata_id
+            .and_then(|meta_id| field_names.get(&meta_id.id))
+            .and_then(|names| names.get(*idx as usize))
+            .map(String::as_str),
     }
+}
 
-    expected.no_match_error()
+/// Returns the attributes as a map of names to formatted values.
+fn attributes(
+    attributes: &[Attribute],
+    field_names: &HashMap<u64, Vec<String>>,
+) -> BTreeMap<String, String> {
+    attributes
+        .iter()
+        .filter_map(|attribute| {
+            let field = attribute.field.as_ref()?;
+            let name = field_name(field, field_names)?;
+            let value = match field.value.as_ref()? {
+                Value::DebugVal(value) | Value::StrVal(value) => value.clone(),
+                Value::U64Val(value) => value.to_string(),
+                Value::I64Val(value) => value.to_string(),
+                Value::BoolVal(value) => value.to_string(),
+            };
+            Some((name.to_string(), value))
+        })
+        .collect()
 }
@@ -1,8 +1,11 @@
-use std::{error, fmt};
+use std::fmt;
 
 use console_api::tasks;
 
-use super::MAIN_TASK_NAME;
+use super::{
+    validation::{Expectation, ValidationFailure},
+    MAIN_TASK_NAME,
+};
 
 /// An actual task
 ///
@@ -16,6 +19,8 @@ pub(super) struct ActualTask {
     pub(super) wakes: u64,
     pub(super) self_wakes: u64,
     pub(super) polls: u64,
+    pub(super) group: Option<String>,
+    pub(super) terminated_by: tasks::stats::TerminatedBy,
 }
 
 impl ActualTask {
@@ -26,6 +31,8 @@ impl ActualTask {
             wakes: 0,
             self_wakes: 0,
             polls: 0,
+            group: None,
+            terminated_by: tasks::stats::TerminatedBy::NotTerminated,
         }
     }
 
@@ -35,47 +42,7 @@ impl ActualTask {
         if let Some(poll_stats) = &stats.poll_stats {
             self.polls = poll_stats.polls;
         }
-    }
-}
-
-/// An error in task validation.
-pub(super) struct TaskValidationFailure {
-    /// The expected task whose expectations were not met.
-    expected: ExpectedTask,
-    /// The actual task which failed the validation
-    actual: Option<ActualTask>,
-    /// A textual description of the validation failure
-    failure: String,
-}
-
-impl error::Error for TaskValidationFailure {}
-
-impl fmt::Display for TaskValidationFailure {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.failure)
-    }
-}
-
-impl fmt::Debug for TaskValidationFailure {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match &self.actual {
-            Some(actual) => write!(
-                f,
-                "Task Validation Failed!\n  Expected Task: {expected:?}\
-                \n  Actual Task:   {actual:?}\
-                \n  Failure:       {failure}",
-                expected = self.expected,
-                failure = self.failure,
-            ),
-            None => write!(
-                f,
-                "Task Validation Failed!\n  Expected Task: {expected:?}\
-                \n  Actual Task:   <not found>\
-                \n  Failure:       {failure}",
-                expected = self.expected,
-                failure = self.failure,
-            ),
-        }
+        self.terminated_by = stats.terminated_by();
     }
 }
 
@@ -84,22 +51,41 @@ impl fmt::Debug for TaskValidationFailure {
 /// This struct contains the fields that an expected task will attempt to match
 /// actual tasks on, as well as the expectations that will be used to validate
 /// which the actual task is as expected.
+///
+/// # Examples
+///
+/// ```no_run
+/// use console_subscriber::test_support::{assert_task, ExpectedTask};
+///
+/// // The task running the test's future never wakes itself.
+/// let expected = ExpectedTask::default()
+///     .match_default_name()
+///     .expect_self_wakes(0);
+///
+/// assert_task(expected, async {
+///     tokio::task::yield_now().await;
+/// });
+/// ```
 #[derive(Clone, Debug, Default)]
-pub(crate) struct ExpectedTask {
+pub struct ExpectedTask {
     match_name: Option<String>,
     expect_present: Option<bool>,
     expect_wakes: Option<u64>,
     expect_self_wakes: Option<u64>,
     expect_polls: Option<u64>,
+    expect_group: Option<Option<String>>,
+    expect_terminated_by: Option<tasks::stats::TerminatedBy>,
 }
 
-#[allow(clippy::result_large_err)]
-impl ExpectedTask {
+impl Expectation for ExpectedTask {
+    type Actual = ActualTask;
+    const NOUN: &'static str = "task";
+
     /// Returns whether or not an actual task matches this expected task.
     ///
     /// All matching rules will be run, if they all succeed, then `true` will
     /// be returned, otherwise `false`.
-    pub(super) fn matches_actual_task(&self, actual_task: &ActualTask) -> bool {
+    fn matches(&self, actual_task: &ActualTask) -> bool {
         if let Some(match_name) = &self.match_name {
             if Some(match_name) == actual_task.name.as_ref() {
                 return true;
@@ -109,28 +95,7 @@ impl ExpectedTask {
         false
     }
 
-    /// Returns an error specifying that no match was found for this expected
-    /// task.
-    pub(super) fn no_match_error(&self) -> Result<(), TaskValidationFailure> {
-        Err(TaskValidationFailure {
-            expected: self.clone(),
-            actual: None,
-            failure: format!("{self}: no matching actual task was found"),
-        })
-    }
-
-    /// Validates all expectations against the provided actual task.
-    ///
-    /// No check that the actual task matches is performed. That must have been
-    /// done prior.
-    ///
-    /// If all expectations are met, this method returns `Ok(())`. If any
-    /// expectations are not met, then the first incorrect expectation will
-    /// be returned as an `Err`.
-    pub(super) fn validate_actual_task(
-        &self,
-        actual_task: &ActualTask,
-    ) -> Result<(), TaskValidationFailure> {
+    fn validate(&self, actual_task: &ActualTask) -> Result<(), ValidationFailure<Self>> {
         let mut no_expectations = true;
         if let Some(_expected) = self.expect_present {
             no_expectations = false;
@@ -139,68 +104,88 @@ impl ExpectedTask {
         if let Some(expected_wakes) = self.expect_wakes {
             no_expectations = false;
             if expected_wakes != actual_task.wakes {
-                return Err(TaskValidationFailure {
-                    expected: self.clone(),
-                    actual: Some(actual_task.clone()),
-                    failure: format!(
+                return Err(self.failure(
+                    actual_task,
+                    format!(
                         "{self}: expected `wakes` to be {expected_wakes}, but \
                         actual was {actual_wakes}",
                         actual_wakes = actual_task.wakes,
                     ),
-                });
+                ));
             }
         }
 
         if let Some(expected_self_wakes) = self.expect_self_wakes {
             no_expectations = false;
             if expected_self_wakes != actual_task.self_wakes {
-                return Err(TaskValidationFailure {
-                    expected: self.clone(),
-                    actual: Some(actual_task.clone()),
-                    failure: format!(
+                return Err(self.failure(
+                    actual_task,
+                    format!(
                         "{self}: expected `self_wakes` to be \
                         {expected_self_wakes}, but actual was \
                         {actual_self_wakes}",
                         actual_self_wakes = actual_task.self_wakes,
                     ),
-                });
+                ));
             }
         }
 
         if let Some(expected_polls) = self.expect_polls {
             no_expectations = false;
             if expected_polls != actual_task.polls {
-                return Err(TaskValidationFailure {
-                    expected: self.clone(),
-                    actual: Some(actual_task.clone()),
-                    failure: format!(
+                return Err(self.failure(
+                    actual_task,
+                    format!(
                         "{self}: expected `polls` to be {expected_polls}, but \
                         actual was {actual_polls}",
                         actual_polls = actual_task.polls,
                     ),
-                });
+                ));
+            }
+        }
+
+        if let Some(expected_group) = &self.expect_group {
+            no_expectations = false;
+            if expected_group != &actual_task.group {
+                return Err(self.failure(
+                    actual_task,
+                    format!(
+                        "{self}: expected `group` to be {expected_group:?}, but \
+                        actual was {actual_group:?}",
+                        actual_group = actual_task.group,
+                    ),
+                ));
+            }
+        }
+
+        if let Some(expected_terminated_by) = self.expect_terminated_by {
+            no_expectations = false;
+            if expected_terminated_by != actual_task.terminated_by {
+                return Err(self.failure(
+                    actual_task,
+                    format!(
+                        "{self}: expected `terminated_by` to be \
+                        {expected_terminated_by:?}, but actual was \
+                        {actual_terminated_by:?}",
+                        actual_terminated_by = actual_task.terminated_by,
+                    ),
+                ));
             }
         }
 
         if no_expectations {
-            return Err(TaskValidationFailure {
-                expected: self.clone(),
-                actual: Some(actual_task.clone()),
-                failure: format!(
-                    "{self}: no expectations set, if you want to just expect \
-                    that a matching task is present, use `expect_present()`",
-                ),
-            });
+            return Err(self.no_expectations_error(actual_task));
         }
 
         Ok(())
     }
+}
 
+impl ExpectedTask {
     /// Matches tasks by name.
     ///
     /// To match this expected task, an actual task must have the name `name`.
-    #[allow(dead_code)]
-    pub(crate) fn match_name(mut self, name: String) -> Self {
+    pub fn match_name(mut self, name: String) -> Self {
         self.match_name = Some(name);
         self
     }
@@ -208,13 +193,13 @@ impl ExpectedTask {
     /// Matches tasks by the default task name.
     ///
     /// To match this expected task, an actual task must have the default name
-    /// assigned to the task which runs the future provided to [`assert_task`]
-    /// or [`assert_tasks`].
+    /// assigned to the task which runs the future provided to [`assert_task`],
+    /// [`assert_tasks`], or [`Expectations::assert`].
     ///
-    /// [`assert_task`]: fn@support::assert_task
-    /// [`assert_tasks`]: fn@support::assert_tasks
-    #[allow(dead_code)]
-    pub(crate) fn match_default_name(mut self) -> Self {
+    /// [`assert_task`]: fn@super::assert_task
+    /// [`assert_tasks`]: fn@super::assert_tasks
+    /// [`Expectations::assert`]: super::Expectations::assert
+    pub fn match_default_name(mut self) -> Self {
         self.match_name = Some(MAIN_TASK_NAME.into());
         self
     }
@@ -222,8 +207,7 @@ impl ExpectedTask {
     /// Expects that a task is present.
     ///
     /// To validate, an actual task matching this expected task must be found.
-    #[allow(dead_code)]
-    pub(crate) fn expect_present(mut self) -> Self {
+    pub fn expect_present(mut self) -> Self {
         self.expect_present = Some(true);
         self
     }
@@ -232,8 +216,7 @@ impl ExpectedTask {
     ///
     /// To validate, the actual task matching this expected task must have
     /// a count of wakes equal to `wakes`.
-    #[allow(dead_code)]
-    pub(crate) fn expect_wakes(mut self, wakes: u64) -> Self {
+    pub fn expect_wakes(mut self, wakes: u64) -> Self {
         self.expect_wakes = Some(wakes);
         self
     }
@@ -242,8 +225,7 @@ impl ExpectedTask {
     ///
     /// To validate, the actual task matching this expected task must have
     /// a count of self wakes equal to `self_wakes`.
-    #[allow(dead_code)]
-    pub(crate) fn expect_self_wakes(mut self, self_wakes: u64) -> Self {
+    pub fn expect_self_wakes(mut self, self_wakes: u64) -> Self {
         self.expect_self_wakes = Some(self_wakes);
         self
     }
@@ -252,11 +234,28 @@ impl ExpectedTask {
     ///
     /// To validate, the actual task must have a count of polls (on
     /// `PollStats`) equal to `polls`.
-    #[allow(dead_code)]
-    pub(crate) fn expect_polls(mut self, polls: u64) -> Self {
+    pub fn expect_polls(mut self, polls: u64) -> Self {
         self.expect_polls = Some(polls);
         self
     }
+
+    /// Expects that a task is in a specific group, or in no group if `group`
+    /// is `None`.
+    ///
+    /// To validate, the actual task must have been assigned to `group`.
+    pub fn expect_group(mut self, group: Option<&str>) -> Self {
+        self.expect_group = Some(group.map(String::from));
+        self
+    }
+
+    /// Expects that a task terminated in a specific way.
+    ///
+    /// To validate, the actual task's `terminated_by` must be equal to
+    /// `terminated_by`.
+    pub fn expect_terminated_by(mut self, terminated_by: tasks::stats::TerminatedBy) -> Self {
+        self.expect_terminated_by = Some(terminated_by);
+        self
+    }
 }
 
 impl fmt::Display for ExpectedTask {
@@ -0,0 +1,105 @@
+use std::{error, fmt};
+
+/// An expectation on something recorded by the console subscriber, such as
+/// a task or a resource.
+pub(super) trait Expectation: Clone + fmt::Debug + fmt::Display + Sized {
+    /// What the console subscriber recorded.
+    type Actual: Clone + fmt::Debug;
+
+    /// What is being validated, used in failure messages (e.g. "task").
+    const NOUN: &'static str;
+
+    /// Returns whether or not an actual value matches this expectation.
+    fn matches(&self, actual: &Self::Actual) -> bool;
+
+    /// Validates all expectations against the provided actual value.
+    ///
+    /// No check that the actual value matches is performed. That must have
+    /// been done prior.
+    ///
+    /// If all expectations are met, this method returns `Ok(())`. If any
+    /// expectations are not met, then the first incorrect expectation will
+    /// be returned as an `Err`.
+    fn validate(&self, actual: &Self::Actual) -> Result<(), ValidationFailure<Self>>;
+
+    /// Returns a failure for `actual`, described by `failure`.
+    fn failure(&self, actual: &Self::Actual, failure: String) -> ValidationFailure<Self> {
+        ValidationFailure {
+            expected: self.clone(),
+            actual: Some(actual.clone()),
+            failure,
+        }
+    }
+
+    /// Returns the failure for an expectation which has nothing to validate.
+    fn no_expectations_error(&self, actual: &Self::Actual) -> ValidationFailure<Self> {
+        self.failure(
+            actual,
+            format!(
+                "{self}: no expectations set, if you want to just expect \
+                that a matching {noun} is present, use `expect_present()`",
+                noun = Self::NOUN,
+            ),
+        )
+    }
+}
+
+/// An error in validation.
+pub(super) struct ValidationFailure<E: Expectation> {
+    /// The expectation which was not met.
+    expected: E,
+    /// The actual value which failed the validation
+    actual: Option<E::Actual>,
+    /// A textual description of the validation failure
+    failure: String,
+}
+
+impl<E: Expectation> error::Error for ValidationFailure<E> {}
+
+impl<E: Expectation> fmt::Display for ValidationFailure<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.failure)
+    }
+}
+
+impl<E: Expectation> fmt::Debug for ValidationFailure<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Validation Failed!\n  Expected: {expected:?}\n  Actual:   ",
+            expected = self.expected,
+        )?;
+        match &self.actual {
+            Some(actual) => write!(f, "{actual:?}")?,
+            None => write!(f, "<not found>")?,
+        }
+        write!(f, "\n  Failure:  {failure}", failure = self.failure)
+    }
+}
+
+/// Validates each expectation against the first matching actual value,
+/// returning the failures.
+pub(super) fn validate_all<E: Expectation>(
+    expected: &[E],
+    actual: &[E::Actual],
+) -> Vec<ValidationFailure<E>> {
+    expected
+        .iter()
+        .filter_map(|expected| {
+            // We only match a single actual value.
+            // FIXME(hds): We should probably create an error or a warning if multiple match.
+            let result = match actual.iter().find(|actual| expected.matches(actual)) {
+                Some(actual) => expected.validate(actual),
+                None => Err(ValidationFailure {
+                    expected: expected.clone(),
+                    actual: None,
+                    failure: format!(
+                        "{expected}: no matching actual {noun} was found",
+                        noun = E::NOUN,
+                    ),
+                }),
+            };
+            result.err()
+        })
+        .collect()
+}
@@ -2,18 +2,29 @@
 //! fields from tracing metadata and producing the parts
 //! needed to construct `Event` instances.
 
-use super::{attribute, WakeOp};
+use super::{attribute, intern, stats::WakeSite, WakeOp};
 use console_api as proto;
 use proto::resources::resource;
+use std::sync::Arc;
 use tracing_core::{
     field::{self, Visit},
-    span,
+    span, Metadata,
 };
 
 const LOCATION_FILE: &str = "loc.file";
 const LOCATION_LINE: &str = "loc.line";
 const LOCATION_COLUMN: &str = "loc.col";
 const INHERIT_FIELD_NAME: &str = "inherits_child_attrs";
+const TASK_KIND_FIELD_NAME: &str = "kind";
+const BLOCKING_TASK_KIND: &str = "blocking";
+const BLOCKING_TASK_TARGET: &str = "tokio::task::blocking";
+const TASK_NAME_FIELD_NAME: &str = "task.name";
+const TASK_ID_FIELD_NAME: &str = "task.id";
+/// The values of tasks' `kind` field which are kept when the rest of their
+/// fields are skipped.
+const TASK_KINDS: &[&str] = &["task", "local", BLOCKING_TASK_KIND, "block_on"];
+const SIZE_BYTES_FIELD_NAME: &str = "size.bytes";
+const ORIGINAL_SIZE_BYTES_FIELD_NAME: &str = "original_size.bytes";
 
 /// Used to extract the fields needed to construct
 /// an Event::Resource from the metadata of a tracing span
@@ -53,9 +64,15 @@ pub(crate) struct ResourceVisitorResult {
 
 /// Used to extract all fields from the metadata
 /// of a tracing span
+///
+/// Field names are recorded as indices into the span's metadata, which the
+/// client has already received when the callsite was registered. This avoids
+/// allocating a new `String` for the name of every field of every span.
+/// String values are shared with the other spans from the same callsite while
+/// they're the same, so they aren't copied for every span either.
 pub(crate) struct FieldVisitor {
-    fields: Vec<proto::Field>,
-    meta_id: proto::MetaId,
+    fields: Vec<TaskField>,
+    metadata: &'static Metadata<'static>,
 }
 
 /// Used to extract the fields needed to construct
@@ -80,11 +97,83 @@ pub(crate) struct FieldVisitor {
 /// fields, which are interpreted as a Rust source code location where the task
 /// was spawned, if they are present. Other fields are recorded as arbitrary
 /// key-value pairs.
+///
+/// Tasks whose `kind` field is `"blocking"`, or whose span has the
+/// `tokio::task::blocking` target, are recorded as running on the blocking
+/// pool.
+///
+/// When no client is watching tasks, a visitor created with
+/// [`TaskVisitor::without_fields`] skips recording the other fields, keeping
+/// only the task's ID, name and kind, which is enough for the console to
+/// identify the task if a client subscribes later.
 pub(crate) struct TaskVisitor {
-    field_visitor: FieldVisitor,
+    field_visitor: Option<FieldVisitor>,
+    id: Option<u64>,
+    kind_name: Option<&'static str>,
     line: Option<u32>,
-    file: Option<String>,
+    file: Option<Arc<str>>,
     column: Option<u32>,
+    kind: proto::tasks::task::Kind,
+    name: Option<Arc<str>>,
+    size_bytes: Option<u64>,
+    original_size_bytes: Option<u64>,
+}
+
+pub(crate) struct TaskVisitorResult {
+    pub(crate) fields: TaskFields,
+    pub(crate) location: Option<TaskLocation>,
+    pub(crate) kind: proto::tasks::task::Kind,
+    /// The task's `task.name` field.
+    pub(crate) name: Option<Arc<str>>,
+    /// The size of the task's future, if the runtime reported it.
+    pub(crate) size_bytes: Option<u64>,
+    /// The size of the task's future before it was boxed, if the runtime
+    /// reported it.
+    pub(crate) original_size_bytes: Option<u64>,
+}
+
+/// The fields of a task's span.
+#[derive(Debug)]
+pub(crate) enum TaskFields {
+    /// Every field, as recorded when a client was watching.
+    All(Vec<TaskField>),
+    /// Only the fields needed to identify the task, recorded when no client
+    /// was watching, and converted to `proto::Field`s once one subscribes.
+    Essential {
+        id: Option<u64>,
+        name: Option<Arc<str>>,
+        kind: Option<&'static str>,
+    },
+}
+
+/// One of the fields of a task's span.
+#[derive(Debug)]
+pub(crate) struct TaskField {
+    name: proto::field::Name,
+    value: FieldValue,
+}
+
+/// The value of a field of a task's span.
+///
+/// String values may be shared with the other tasks spawned from the same
+/// callsite, and are only copied when the task is sent to a client.
+#[derive(Debug)]
+enum FieldValue {
+    Debug(Arc<str>),
+    Str(Arc<str>),
+    /// A number or a `bool`, which can be sent as it is.
+    Other(proto::field::Value),
+}
+
+/// Where a task was spawned.
+///
+/// The file name is shared with the other tasks spawned from the same
+/// callsite.
+#[derive(Debug, Clone)]
+pub(crate) struct TaskLocation {
+    pub(crate) file: Arc<str>,
+    pub(crate) line: u32,
+    pub(crate) column: u32,
 }
 
 /// Used to extract the fields needed to construct
@@ -117,10 +206,29 @@ pub(crate) struct AsyncOpVisitor {
 /// Fields:
 /// task.id - the id of the task this waker will wake
 /// op - the operation associated with this waker event
+/// loc.file, loc.line, loc.col - optionally, where the wake happened
 #[derive(Default)]
 pub(crate) struct WakerVisitor {
     id: Option<span::Id>,
     op: Option<WakeOp>,
+    file: Option<String>,
+    line: Option<u32>,
+    column: Option<u32>,
+}
+
+/// Used to extract the group that tasks spawned within a span belong to,
+/// from a tracing span that has the following shape:
+///
+/// tracing::info_span!(
+///     "ingest",
+///     console.group = "ingest",
+/// );
+///
+/// Fields:
+/// console.group - the name of the group
+#[derive(Default)]
+pub(crate) struct GroupVisitor {
+    group: Option<String>,
 }
 
 /// Used to extract the fields needed to construct
@@ -198,7 +306,17 @@ impl ResourceVisitor {
 }
 
 impl Visit for ResourceVisitor {
-    fn record_debug(&mut self, _: &field::Field, _: &dyn std::fmt::Debug) {}
+    // Resources defined outside of Tokio may record their type and kind using
+    // `Display` or `Debug` values (e.g. `concrete_type = %name`), so accept
+    // those as well as strings.
+    fn record_debug(&mut self, field: &field::Field, value: &dyn std::fmt::Debug) {
+        match field.name() {
+            Self::RES_CONCRETE_TYPE_FIELD_NAME | Self::RES_KIND_FIELD_NAME | LOCATION_FILE => {
+                self.record_str(field, &format!("{value:?}"))
+            }
+            _ => {}
+        }
+    }
 
     fn record_str(&mut self, field: &tracing_core::Field, value: &str) {
         match field.name() {
@@ -232,116 +350,263 @@ impl Visit for ResourceVisitor {
             _ => {}
         }
     }
+
+    // Integer literals are recorded as `i64`, so a hand-written span such as
+    // `loc.line = 42` ends up here.
+    fn record_i64(&mut self, field: &tracing_core::Field, value: i64) {
+        if let Ok(value) = u64::try_from(value) {
+            self.record_u64(field, value);
+        }
+    }
 }
 
 impl FieldVisitor {
-    pub(crate) fn new(meta_id: proto::MetaId) -> Self {
+    pub(crate) fn new(metadata: &'static Metadata<'static>) -> Self {
         FieldVisitor {
-            fields: Vec::default(),
-            meta_id,
+            fields: Vec::with_capacity(metadata.fields().len()),
+            metadata,
         }
     }
-    pub(crate) fn result(self) -> Vec<proto::Field> {
+
+    pub(crate) fn result(self) -> Vec<TaskField> {
         self.fields
     }
+
+    fn record(&mut self, field: &field::Field, value: FieldValue) {
+        self.fields.push(TaskField {
+            name: field_name(self.metadata, field),
+            value,
+        });
+    }
 }
 
 impl TaskVisitor {
-    pub(crate) fn new(meta_id: proto::MetaId) -> Self {
+    pub(crate) fn new(metadata: &'static Metadata<'static>) -> Self {
+        let kind = if metadata.target() == BLOCKING_TASK_TARGET {
+            proto::tasks::task::Kind::Blocking
+        } else {
+            proto::tasks::task::Kind::Spawn
+        };
         TaskVisitor {
-            field_visitor: FieldVisitor::new(meta_id),
+            field_visitor: Some(FieldVisitor::new(metadata)),
+            id: None,
+            kind_name: None,
             line: None,
             file: None,
             column: None,
+            kind,
+            name: None,
+            size_bytes: None,
+            original_size_bytes: None,
         }
     }
 
-    pub(crate) fn result(self) -> (Vec<proto::Field>, Option<proto::Location>) {
-        let fields = self.field_visitor.result();
-        let location = if self.file.is_some() && self.line.is_some() && self.column.is_some() {
-            Some(proto::Location {
-                file: self.file,
-                line: self.line,
-                column: self.column,
-                ..Default::default()
-            })
-        } else {
-            None
+    /// Returns a visitor which only records the fields needed to identify
+    /// the task, for when nothing would consume the rest.
+    pub(crate) fn without_fields(metadata: &'static Metadata<'static>) -> Self {
+        TaskVisitor {
+            field_visitor: None,
+            ..Self::new(metadata)
+        }
+    }
+
+    pub(crate) fn result(self) -> TaskVisitorResult {
+        let fields = match self.field_visitor {
+            Some(field_visitor) => TaskFields::All(field_visitor.result()),
+            None => TaskFields::Essential {
+                id: self.id,
+                name: self.name.clone(),
+                kind: self.kind_name,
+            },
+        };
+        let location = match (self.file, self.line, self.column) {
+            (Some(file), Some(line), Some(column)) => Some(TaskLocation { file, line, column }),
+            _ => None,
         };
 
-        (fields, location)
+        TaskVisitorResult {
+            fields,
+            location,
+            kind: self.kind,
+            name: self.name,
+            size_bytes: self.size_bytes,
+            original_size_bytes: self.original_size_bytes,
+        }
     }
 }
 
 impl Visit for TaskVisitor {
     fn record_debug(&mut self, field: &field::Field, value: &dyn std::fmt::Debug) {
-        self.field_visitor.record_debug(field, value);
+        let value = match field.name() {
+            TASK_KIND_FIELD_NAME => {
+                let value = intern::debug_value(field, value);
+                if &*value == BLOCKING_TASK_KIND {
+                    self.kind = proto::tasks::task::Kind::Blocking;
+                }
+                self.kind_name = TASK_KINDS.iter().find(|&&kind| kind == &*value).copied();
+                value
+            }
+            TASK_NAME_FIELD_NAME => {
+                let name = intern::debug_value(field, value);
+                self.name = Some(name.clone());
+                name
+            }
+            _ => {
+                if let Some(ref mut field_visitor) = self.field_visitor {
+                    field_visitor.record_debug(field, value);
+                }
+                return;
+            }
+        };
+        if let Some(ref mut field_visitor) = self.field_visitor {
+            field_visitor.record(field, FieldValue::Debug(value));
+        }
     }
 
     fn record_i64(&mut self, field: &tracing_core::Field, value: i64) {
-        self.field_visitor.record_i64(field, value);
+        if let Some(ref mut field_visitor) = self.field_visitor {
+            field_visitor.record_i64(field, value);
+        }
     }
 
     fn record_u64(&mut self, field: &tracing_core::Field, value: u64) {
         match field.name() {
             LOCATION_LINE => self.line = Some(value as u32),
             LOCATION_COLUMN => self.column = Some(value as u32),
-            _ => self.field_visitor.record_u64(field, value),
+            _ => {
+                match field.name() {
+                    TASK_ID_FIELD_NAME => self.id = Some(value),
+                    SIZE_BYTES_FIELD_NAME => self.size_bytes = Some(value),
+                    ORIGINAL_SIZE_BYTES_FIELD_NAME => self.original_size_bytes = Some(value),
+                    _ => {}
+                }
+                if let Some(ref mut field_visitor) = self.field_visitor {
+                    field_visitor.record_u64(field, value);
+                }
+            }
         }
     }
 
     fn record_bool(&mut self, field: &tracing_core::Field, value: bool) {
-        self.field_visitor.record_bool(field, value);
+        if let Some(ref mut field_visitor) = self.field_visitor {
+            field_visitor.record_bool(field, value);
+        }
     }
 
     fn record_str(&mut self, field: &tracing_core::Field, value: &str) {
         if field.name() == LOCATION_FILE {
-            self.file = Some(value.to_string());
+            self.file = Some(intern::str_value(field, value));
         } else {
-            self.field_visitor.record_str(field, value);
+            if field.name() == TASK_KIND_FIELD_NAME {
+                if value == BLOCKING_TASK_KIND {
+                    self.kind = proto::tasks::task::Kind::Blocking;
+                }
+                self.kind_name = TASK_KINDS.iter().find(|&&kind| kind == value).copied();
+            }
+            if field.name() == TASK_NAME_FIELD_NAME {
+                let name = intern::str_value(field, value);
+                if let Some(ref mut field_visitor) = self.field_visitor {
+                    field_visitor.record(field, FieldValue::Str(name.clone()));
+                }
+                self.name = Some(name);
+            } else if let Some(ref mut field_visitor) = self.field_visitor {
+                field_visitor.record_str(field, value);
+            }
         }
     }
 }
 
 impl Visit for FieldVisitor {
     fn record_debug(&mut self, field: &field::Field, value: &dyn std::fmt::Debug) {
-        self.fields.push(proto::Field {
-            name: Some(field.name().into()),
-            value: Some(value.into()),
-            metadata_id: Some(self.meta_id),
-        });
+        self.record(field, FieldValue::Debug(intern::debug_value(field, value)));
     }
 
     fn record_i64(&mut self, field: &tracing_core::Field, value: i64) {
-        self.fields.push(proto::Field {
-            name: Some(field.name().into()),
-            value: Some(value.into()),
-            metadata_id: Some(self.meta_id),
-        });
+        self.record(field, FieldValue::Other(value.into()));
     }
 
     fn record_u64(&mut self, field: &tracing_core::Field, value: u64) {
-        self.fields.push(proto::Field {
-            name: Some(field.name().into()),
-            value: Some(value.into()),
-            metadata_id: Some(self.meta_id),
-        });
+        self.record(field, FieldValue::Other(value.into()));
     }
 
     fn record_bool(&mut self, field: &tracing_core::Field, value: bool) {
-        self.fields.push(proto::Field {
-            name: Some(field.name().into()),
-            value: Some(value.into()),
-            metadata_id: Some(self.meta_id),
-        });
+        self.record(field, FieldValue::Other(value.into()));
     }
 
     fn record_str(&mut self, field: &tracing_core::Field, value: &str) {
-        self.fields.push(proto::Field {
-            name: Some(field.name().into()),
-            value: Some(value.into()),
-            metadata_id: Some(self.meta_id),
-        });
+        self.record(field, FieldValue::Str(intern::str_value(field, value)));
+    }
+}
+
+impl TaskFields {
+    /// Returns the task's fields, as sent to clients.
+    pub(crate) fn to_proto(&self, metadata: &'static Metadata<'static>) -> Vec<proto::Field> {
+        match self {
+            TaskFields::All(fields) => {
+                let meta_id = proto::MetaId::from(metadata);
+                fields
+                    .iter()
+                    .map(|field| proto::Field {
+                        name: Some(field.name.clone()),
+                        value: Some(match field.value {
+                            FieldValue::Debug(ref value) => {
+                                proto::field::Value::DebugVal(value.to_string())
+                            }
+                            FieldValue::Str(ref value) => {
+                                proto::field::Value::StrVal(value.to_string())
+                            }
+                            FieldValue::Other(ref value) => value.clone(),
+                        }),
+                        metadata_id: Some(meta_id),
+                    })
+                    .collect()
+            }
+            TaskFields::Essential { id, name, kind } => {
+                let meta_id = proto::MetaId::from(metadata);
+                let field = |name: &str, value: proto::field::Value| {
+                    let field = metadata.fields().field(name)?;
+                    Some(proto::Field {
+                        name: Some(field_name(metadata, &field)),
+                        value: Some(value),
+                        metadata_id: Some(meta_id),
+                    })
+                };
+                [
+                    id.and_then(|id| field(TASK_ID_FIELD_NAME, id.into())),
+                    name.as_deref()
+                        .and_then(|name| field(TASK_NAME_FIELD_NAME, name.into())),
+                    kind.and_then(|kind| field(TASK_KIND_FIELD_NAME, kind.into())),
+                ]
+                .into_iter()
+                .flatten()
+                .collect()
+            }
+        }
+    }
+}
+
+impl TaskLocation {
+    pub(crate) fn to_proto(&self) -> proto::Location {
+        proto::Location {
+            file: Some(self.file.to_string()),
+            line: Some(self.line),
+            column: Some(self.column),
+            ..Default::default()
+        }
+    }
+}
+
+/// Returns the wire representation of `field`'s name.
+///
+/// If the field belongs to `metadata`, this is the field's index into the
+/// metadata's field names. Otherwise, the name is sent as a string.
+pub(crate) fn field_name(
+    metadata: &'static Metadata<'static>,
+    field: &field::Field,
+) -> proto::field::Name {
+    match metadata.fields().iter().position(|f| &f == field) {
+        Some(idx) => proto::field::Name::NameIdx(idx as u64),
+        None => field.name().into(),
     }
 }
 
@@ -356,7 +621,11 @@ impl AsyncOpVisitor {
 }
 
 impl Visit for AsyncOpVisitor {
-    fn record_debug(&mut self, _: &field::Field, _: &dyn std::fmt::Debug) {}
+    fn record_debug(&mut self, field: &field::Field, value: &dyn std::fmt::Debug) {
+        if field.name() == Self::ASYNC_OP_SRC_FIELD_NAME {
+            self.source = Some(format!("{value:?}"));
+        }
+    }
 
     fn record_str(&mut self, field: &tracing_core::Field, value: &str) {
         if field.name() == Self::ASYNC_OP_SRC_FIELD_NAME {
@@ -381,6 +650,19 @@ impl WakerVisitor {
     pub(crate) fn result(self) -> Option<(span::Id, WakeOp)> {
         self.id.zip(self.op)
     }
+
+    /// Returns where the wake recorded by this visitor happened, if the event
+    /// has `loc.*` fields.
+    ///
+    /// The event's callsite isn't used instead, as it's in the runtime's waker
+    /// code, which every wake would be credited to.
+    pub(crate) fn take_site(&mut self) -> Option<WakeSite> {
+        Some(WakeSite {
+            file: self.file.take()?.into(),
+            line: self.line,
+            column: self.column,
+        })
+    }
 }
 
 impl Visit for WakerVisitor {
@@ -389,20 +671,57 @@ impl Visit for WakerVisitor {
     }
 
     fn record_u64(&mut self, field: &tracing_core::Field, value: u64) {
-        if field.name() == Self::TASK_ID_FIELD_NAME {
-            self.id = Some(span::Id::from_u64(value));
+        match field.name() {
+            Self::TASK_ID_FIELD_NAME => self.id = Some(span::Id::from_u64(value)),
+            LOCATION_LINE => self.line = Some(value as u32),
+            LOCATION_COLUMN => self.column = Some(value as u32),
+            _ => {}
+        }
+    }
+
+    // Integer literals are recorded as `i64`, so a hand-written event such as
+    // `loc.line = 42` ends up here.
+    fn record_i64(&mut self, field: &tracing_core::Field, value: i64) {
+        if let Ok(value) = u64::try_from(value) {
+            self.record_u64(field, value);
         }
     }
 
     fn record_str(&mut self, field: &tracing_core::Field, value: &str) {
-        if field.name() == "op" {
-            self.op = Some(match value {
-                Self::WAKE => WakeOp::Wake { self_wake: false },
-                Self::WAKE_BY_REF => WakeOp::WakeByRef { self_wake: false },
-                Self::CLONE => WakeOp::Clone,
-                Self::DROP => WakeOp::Drop,
-                _ => return,
-            });
+        match field.name() {
+            "op" => {
+                self.op = Some(match value {
+                    Self::WAKE => WakeOp::Wake { self_wake: false },
+                    Self::WAKE_BY_REF => WakeOp::WakeByRef { self_wake: false },
+                    Self::CLONE => WakeOp::Clone,
+                    Self::DROP => WakeOp::Drop,
+                    _ => return,
+                });
+            }
+            LOCATION_FILE => self.file = Some(value.to_string()),
+            _ => {}
+        }
+    }
+}
+
+impl GroupVisitor {
+    pub(crate) const GROUP_FIELD_NAME: &'static str = "console.group";
+
+    pub(crate) fn result(self) -> Option<String> {
+        self.group
+    }
+}
+
+impl Visit for GroupVisitor {
+    fn record_debug(&mut self, field: &field::Field, value: &dyn std::fmt::Debug) {
+        if field.name() == Self::GROUP_FIELD_NAME {
+            self.group = Some(format!("{:?}", value));
+        }
+    }
+
+    fn record_str(&mut self, field: &tracing_core::Field, value: &str) {
+        if field.name() == Self::GROUP_FIELD_NAME {
+            self.group = Some(value.to_string());
         }
     }
 }
@@ -0,0 +1,18 @@
+//! A web UI for the console, embedded in the subscriber.
+//!
+//! The UI is a single static page which polls the [JSON gateway] for the
+//! current state, so a browser is all that's needed to inspect an application.
+//!
+//! [JSON gateway]: crate::gateway
+use axum::{http::header, response::IntoResponse, routing::get, Router};
+
+const INDEX: &str = include_str!("web_ui/index.html");
+
+/// Returns the routes which serve the web UI.
+pub(crate) fn router() -> Router {
+    Router::new().route("/", get(index))
+}
+
+async fn index() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/html; charset=utf-8")], INDEX)
+}
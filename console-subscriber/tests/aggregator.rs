@@ -0,0 +1,679 @@
+use std::time::Duration;
+
+use console_api::{
+    instrument::{HistogramSelection, TaskDetailsRequest, Update},
+    tasks::TaskDetails,
+};
+use console_subscriber::{test_support::AggregatorHarness, ConsoleLayer};
+use futures::{FutureExt, Stream, StreamExt};
+use tracing_subscriber::prelude::*;
+
+const PUBLISH_INTERVAL: Duration = Duration::from_secs(1);
+const RETENTION: Duration = Duration::from_secs(60);
+
+fn harness() -> (ConsoleLayer, AggregatorHarness) {
+    AggregatorHarness::new(
+        ConsoleLayer::builder()
+            .publish_interval(PUBLISH_INTERVAL)
+            .retention(RETENTION),
+    )
+}
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("runtime should build")
+}
+
+/// Spawns `count` tasks which complete straight away, and waits for them.
+async fn spawn_completed(count: usize) {
+    for _ in 0..count {
+        tokio::spawn(async {}).await.expect("task should complete");
+    }
+}
+
+/// Returns the number of completed tasks in `update`.
+///
+/// The only tasks which complete are those the test spawns, while the
+/// runtime's `block_on` task and the aggregator keep running.
+fn completed_tasks(update: &Update) -> usize {
+    update.task_update.as_ref().map_or(0, |update| {
+        update
+            .new_tasks
+            .iter()
+            .filter_map(|task| update.stats_update.get(&task.id?.id))
+            .filter(|stats| stats.dropped_at.is_some())
+            .count()
+    })
+}
+
+async fn next_update<T>(stream: &mut (impl Stream<Item = Result<T, tonic::Status>> + Unpin)) -> T {
+    tokio::time::timeout(Duration::from_secs(5), stream.next())
+        .await
+        .expect("an update should be sent")
+        .expect("the stream should not end")
+        .expect("the update should not be an error")
+}
+
+#[test]
+fn publishes_when_the_clock_ticks() {
+    let (layer, mut harness) = harness();
+    let _subscriber = tracing_subscriber::registry().with(layer).set_default();
+    runtime().block_on(async {
+        harness.spawn();
+        let mut updates = Box::pin(harness.watch_updates().await);
+        assert_eq!(completed_tasks(&next_update(&mut updates).await), 0);
+
+        spawn_completed(3).await;
+        harness.self_metrics().await;
+        assert!(
+            updates.next().now_or_never().is_none(),
+            "no update should be published before the publish interval passes"
+        );
+
+        harness.advance(PUBLISH_INTERVAL).await;
+        assert_eq!(completed_tasks(&next_update(&mut updates).await), 3);
+    });
+}
+
+#[test]
+fn drops_completed_tasks_after_retention() {
+    let (layer, mut harness) = harness();
+    let _subscriber = tracing_subscriber::registry().with(layer).set_default();
+    runtime().block_on(async {
+        harness.spawn();
+        let before = harness.self_metrics().await.tasks;
+        spawn_completed(3).await;
+        assert_eq!(harness.self_metrics().await.tasks, before + 3);
+
+        harness.advance(RETENTION - Duration::from_secs(1)).await;
+        assert_eq!(
+            harness.self_metrics().await.tasks,
+            before + 3,
+            "tasks should be retained for the retention period"
+        );
+
+        harness.advance(Duration::from_secs(2)).await;
+        assert_eq!(harness.self_metrics().await.tasks, before);
+    });
+}
+
+#[test]
+fn stops_retaining_completed_tasks_while_paused_after_twice_the_retention() {
+    let (layer, mut harness) = harness();
+    let _subscriber = tracing_subscriber::registry().with(layer).set_default();
+    runtime().block_on(async {
+        harness.spawn();
+        let mut updates = Box::pin(harness.watch_updates().await);
+        next_update(&mut updates).await;
+        let before = harness.self_metrics().await.tasks;
+        harness.pause(0).await;
+        spawn_completed(3).await;
+        assert_eq!(harness.self_metrics().await.tasks, before + 3);
+
+        // Time spent paused doesn't count towards retention...
+        harness
+            .advance(RETENTION * 2 - Duration::from_secs(1))
+            .await;
+        assert_eq!(
+            harness.self_metrics().await.tasks,
+            before + 3,
+            "tasks should be retained while paused"
+        );
+
+        // ...but only for up to the retention period, even if the tasks were
+        // never sent.
+        harness.advance(Duration::from_secs(2)).await;
+        assert_eq!(harness.self_metrics().await.tasks, before);
+    });
+}
+
+#[test]
+fn halves_retention_until_the_first_update_fits() {
+    let (layer, harness) = harness();
+    // Updates include the metadata of every callsite hit so far, including
+    // those hit by other tests, so leave plenty of room for it.
+    let mut harness = harness.max_message_size(32 * 1024);
+    let _subscriber = tracing_subscriber::registry().with(layer).set_default();
+    runtime().block_on(async {
+        harness.spawn();
+        let before = harness.self_metrics().await.tasks;
+        spawn_completed(400).await;
+        harness.advance(Duration::from_secs(40)).await;
+        spawn_completed(5).await;
+        harness.advance(Duration::from_secs(1)).await;
+        // Everything is still within the retention period.
+        assert_eq!(harness.self_metrics().await.tasks, before + 405);
+
+        // All of the tasks don't fit in the first update, so retention is
+        // halved to 30 seconds, which drops the tasks that completed 41
+        // seconds ago.
+        let mut updates = Box::pin(harness.watch_updates().await);
+        assert_eq!(completed_tasks(&next_update(&mut updates).await), 5);
+        assert_eq!(harness.self_metrics().await.tasks, before + 5);
+
+        // The reduced retention applies from then on.
+        harness.advance(Duration::from_secs(30)).await;
+        let mut updates = Box::pin(harness.watch_updates().await);
+        assert_eq!(completed_tasks(&next_update(&mut updates).await), 0);
+    });
+}
+
+#[test]
+fn keeps_the_history_of_resource_attributes() {
+    let (layer, mut harness) = AggregatorHarness::new(
+        ConsoleLayer::builder()
+            .publish_interval(PUBLISH_INTERVAL)
+            .attribute_history(3),
+    );
+    let _subscriber = tracing_subscriber::registry().with(layer).set_default();
+    runtime().block_on(async {
+        harness.spawn();
+        let span =
+            console_subscriber::instrument_resource!(concrete_type = "test::Pool", kind = "Sync",);
+        for idle in 1..=5_u64 {
+            let _enter = span.enter();
+            tracing::trace!(
+                target: "runtime::resource::state_update",
+                idle = idle,
+                idle.op = "override",
+            );
+            harness.clock().advance(Duration::from_millis(10));
+        }
+        harness.self_metrics().await;
+
+        let mut updates = Box::pin(harness.watch_updates().await);
+        let update = next_update(&mut updates).await;
+        let resources = update.resource_update.expect("resources should be sent");
+        let resource = resources
+            .new_resources
+            .iter()
+            .find(|resource| resource.concrete_type == "test::Pool")
+            .expect("the resource should be sent");
+        let stats = &resources.stats_update[&resource.id.expect("resources have IDs").id];
+        let history = stats
+            .attribute_history
+            .iter()
+            .map(|sample| {
+                let field = sample
+                    .attribute
+                    .as_ref()
+                    .and_then(|attr| attr.field.as_ref());
+                match field.and_then(|field| field.value.as_ref()) {
+                    Some(console_api::field::Value::U64Val(idle)) => *idle,
+                    value => panic!("unexpected value {:?}", value),
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(history, [3, 4, 5], "only the last 3 values are kept");
+        let times = stats
+            .attribute_history
+            .iter()
+            .map(|sample| sample.at.as_ref().expect("samples have times"))
+            .collect::<Vec<_>>();
+        assert!(times.windows(2).all(|pair| pair[0] != pair[1]));
+    });
+}
+
+#[test]
+fn disconnects_lagging_clients_after_the_lag_limit() {
+    let (layer, mut harness) = AggregatorHarness::new(
+        ConsoleLayer::builder()
+            .publish_interval(PUBLISH_INTERVAL)
+            .client_buffer_capacity(1)
+            .client_lag_limit(3),
+    );
+    let _subscriber = tracing_subscriber::registry().with(layer).set_default();
+    runtime().block_on(async {
+        harness.spawn();
+        let mut updates = Box::pin(harness.watch_updates().await);
+        harness.self_metrics().await;
+
+        // The first update fills the stream's buffer, so the next one can't
+        // be sent.
+        spawn_completed(2).await;
+        harness.advance(PUBLISH_INTERVAL).await;
+        let first = next_update(&mut updates).await;
+        assert!(!first.lagging);
+        assert_eq!(completed_tasks(&first), 0);
+
+        // It's sent with the one after, flagged as lagging, rather than the
+        // client being disconnected.
+        harness.advance(PUBLISH_INTERVAL).await;
+        let update = next_update(&mut updates).await;
+        assert!(update.lagging);
+        assert_eq!(completed_tasks(&update), 2);
+        assert_eq!(update.sequence, first.sequence + 1, "no update was missed");
+
+        // Once three updates in a row can't be sent, the client is
+        // disconnected.
+        for _ in 0..4 {
+            harness.advance(PUBLISH_INTERVAL).await;
+        }
+        next_update(&mut updates).await;
+        let end = tokio::time::timeout(Duration::from_secs(5), updates.next())
+            .await
+            .expect("the stream should end");
+        assert!(end.is_none(), "the stream should end, got {:?}", end);
+    });
+}
+
+#[test]
+fn sends_only_the_selected_histograms() {
+    let (layer, mut harness) = harness();
+    let _subscriber = tracing_subscriber::registry().with(layer).set_default();
+    runtime().block_on(async {
+        harness.spawn();
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let task = tokio::spawn(async move {
+            let _ = stopped.await;
+        });
+        harness.self_metrics().await;
+        let mut updates = Box::pin(harness.watch_updates().await);
+        let update = next_update(&mut updates).await;
+        let tasks = update.task_update.expect("tasks should be sent");
+        let id = tasks
+            .new_tasks
+            .iter()
+            .filter_map(|task| task.id)
+            .find(|id| tasks.stats_update[&id.id].dropped_at.is_none())
+            .expect("a running task should be sent");
+
+        let watch = |histograms| {
+            harness.watch_task_details(TaskDetailsRequest {
+                id: Some(id),
+                histograms,
+            })
+        };
+        let mut scheduled_only = Box::pin(
+            watch(Some(HistogramSelection {
+                scheduled_times: true,
+                ..Default::default()
+            }))
+            .await
+            .expect("the task should be found"),
+        );
+        let mut everything = Box::pin(watch(None).await.expect("the task should be found"));
+
+        let histograms = |details: &TaskDetails| {
+            (
+                details.poll_times_histogram.is_some(),
+                details.scheduled_times_histogram.is_some(),
+                details.recent_poll_times_histogram.is_some(),
+            )
+        };
+        assert_eq!(
+            histograms(&next_update(&mut scheduled_only).await),
+            (false, true, false)
+        );
+        // The recent poll times are only sent once an interval has passed.
+        assert_eq!(
+            histograms(&next_update(&mut everything).await),
+            (true, true, false)
+        );
+
+        harness.advance(PUBLISH_INTERVAL).await;
+        assert_eq!(
+            histograms(&next_update(&mut scheduled_only).await),
+            (false, true, false)
+        );
+        assert_eq!(
+            histograms(&next_update(&mut everything).await),
+            (true, true, true)
+        );
+
+        stop.send(()).expect("the task should be running");
+        task.await.expect("task should complete");
+    });
+}
+
+#[test]
+fn only_records_wake_sites_with_locations() {
+    let (layer, mut harness) = harness();
+    let _subscriber = tracing_subscriber::registry().with(layer).set_default();
+    runtime().block_on(async {
+        harness.spawn();
+        let (wake, woken) = tokio::sync::oneshot::channel::<()>();
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let task = tokio::spawn(async move {
+            let _ = woken.await;
+            let _ = stopped.await;
+        });
+        harness.self_metrics().await;
+        let mut updates = Box::pin(harness.watch_updates().await);
+        let update = next_update(&mut updates).await;
+        let tasks = update.task_update.expect("tasks should be sent");
+        let id = tasks
+            .new_tasks
+            .iter()
+            .filter_map(|task| task.id)
+            .find(|id| tasks.stats_update[&id.id].dropped_at.is_none())
+            .expect("a running task should be sent");
+
+        // Tokio's own waker events don't say where the wake happened, so
+        // they aren't credited to Tokio's waker code.
+        wake.send(()).expect("the task should be running");
+        tokio::task::yield_now().await;
+        tracing::trace!(
+            target: "runtime::waker",
+            op = "waker.wake_by_ref",
+            task.id = id.id,
+            loc.file = "src/app.rs",
+            loc.line = 12,
+            loc.col = 5,
+        );
+        harness.self_metrics().await;
+
+        let mut details = Box::pin(
+            harness
+                .watch_task_details(TaskDetailsRequest {
+                    id: Some(id),
+                    histograms: None,
+                })
+                .await
+                .expect("the task should be found"),
+        );
+        let sites = next_update(&mut details)
+            .await
+            .wake_sites
+            .into_iter()
+            .map(|site| {
+                let location = site.location.expect("wake sites have a location");
+                (location.file, location.line, site.wakes)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(sites, [(Some("src/app.rs".to_string()), Some(12), 1)]);
+
+        stop.send(()).expect("the task should be running");
+        task.await.expect("task should complete");
+    });
+}
+
+#[test]
+fn defers_what_does_not_fit_in_an_update() {
+    let (layer, harness) = harness();
+    let mut harness = harness.max_message_size(32 * 1024);
+    let _subscriber = tracing_subscriber::registry().with(layer).set_default();
+    runtime().block_on(async {
+        harness.spawn();
+        let mut updates = Box::pin(harness.watch_updates().await);
+        assert_eq!(completed_tasks(&next_update(&mut updates).await), 0);
+
+        // Far more tasks complete than fit in one update, so they're spread
+        // over the following ones.
+        spawn_completed(400).await;
+        let mut completed = 0;
+        let mut truncated = 0;
+        loop {
+            harness.advance(PUBLISH_INTERVAL).await;
+            let update = next_update(&mut updates).await;
+            completed += completed_tasks(&update);
+            if !update.truncated {
+                break;
+            }
+            truncated += 1;
+        }
+        assert!(truncated > 0, "some updates should be truncated");
+        assert_eq!(completed, 400, "every task should be sent in the end");
+    });
+}
+
+#[test]
+fn shares_recent_poll_times_between_streams() {
+    let (layer, mut harness) = harness();
+    let _subscriber = tracing_subscriber::registry().with(layer).set_default();
+    runtime().block_on(async {
+        harness.spawn();
+        // The task is polled once for each message, and replies once it has
+        // received it.
+        let (messages, mut received) =
+            tokio::sync::mpsc::channel::<tokio::sync::oneshot::Sender<()>>(1);
+        let task = tokio::spawn(async move {
+            while let Some(reply) = received.recv().await {
+                let _ = reply.send(());
+            }
+        });
+        let poll = || async {
+            let (reply, replied) = tokio::sync::oneshot::channel();
+            messages
+                .send(reply)
+                .await
+                .expect("the task should be running");
+            replied.await.expect("the task should reply");
+        };
+        harness.self_metrics().await;
+        let mut updates = Box::pin(harness.watch_updates().await);
+        let update = next_update(&mut updates).await;
+        let tasks = update.task_update.expect("tasks should be sent");
+        let id = tasks
+            .new_tasks
+            .iter()
+            .filter_map(|task| task.id)
+            .filter(|id| tasks.stats_update[&id.id].dropped_at.is_none())
+            .max_by_key(|id| id.id)
+            .expect("a running task should be sent");
+        let watch = || {
+            harness.watch_task_details(TaskDetailsRequest {
+                id: Some(id),
+                histograms: None,
+            })
+        };
+        let recent_polls = |details: TaskDetails| {
+            let histogram = details
+                .recent_poll_times_histogram
+                .expect("the recent poll times should be sent");
+            hdrhistogram::serialization::Deserializer::new()
+                .deserialize::<u64, _>(&mut &histogram.raw_histogram[..])
+                .expect("the histogram should deserialize")
+                .len()
+        };
+
+        let mut first = Box::pin(watch().await.expect("the task should be found"));
+        next_update(&mut first).await;
+        poll().await;
+        poll().await;
+        harness.self_metrics().await;
+
+        // A stream which starts partway through an interval doesn't start a
+        // new one for the streams already watching.
+        let mut second = Box::pin(watch().await.expect("the task should be found"));
+        next_update(&mut second).await;
+        poll().await;
+        harness.self_metrics().await;
+        harness.advance(PUBLISH_INTERVAL).await;
+        assert_eq!(recent_polls(next_update(&mut first).await), 3);
+        assert_eq!(recent_polls(next_update(&mut second).await), 3);
+
+        // Every stream is sent the same interval.
+        poll().await;
+        harness.self_metrics().await;
+        harness.advance(PUBLISH_INTERVAL).await;
+        assert_eq!(recent_polls(next_update(&mut first).await), 1);
+        assert_eq!(recent_polls(next_update(&mut second).await), 1);
+
+        drop(messages);
+        task.await.expect("task should complete");
+    });
+}
+
+#[test]
+fn numbers_shared_updates_by_stream() {
+    let (layer, mut harness) = harness();
+    let _subscriber = tracing_subscriber::registry().with(layer).set_default();
+    runtime().block_on(async {
+        harness.spawn();
+        let mut first = Box::pin(harness.watch_updates().await);
+        next_update(&mut first).await;
+        harness.advance(PUBLISH_INTERVAL).await;
+        assert_eq!(next_update(&mut first).await.sequence, 1);
+
+        // Both streams are sent the same update, each numbered as that
+        // stream's next one.
+        let mut second = Box::pin(harness.watch_updates().await);
+        next_update(&mut second).await;
+        spawn_completed(2).await;
+        harness.self_metrics().await;
+        harness.advance(PUBLISH_INTERVAL).await;
+        let update = next_update(&mut first).await;
+        assert_eq!(update.sequence, 2);
+        assert_eq!(completed_tasks(&update), 2);
+        let update = next_update(&mut second).await;
+        assert_eq!(update.sequence, 1);
+        assert_eq!(completed_tasks(&update), 2);
+    });
+}
+
+const EVENT_BUFFER: usize = 4096;
+
+/// Records `count` poll ops on a resource from within a task, without
+/// yielding to the aggregator.
+async fn record_poll_ops(count: usize) {
+    tokio::spawn(async move {
+        let resource =
+            console_subscriber::instrument_resource!(concrete_type = "test::Pool", kind = "Sync");
+        let async_op = resource
+            .in_scope(|| console_subscriber::instrument_async_op!(source = "Pool::checkout"));
+        let _resource = resource.enter();
+        let _async_op = async_op.enter();
+        for _ in 0..count {
+            tracing::trace!(
+                target: "runtime::resource::poll_op",
+                op_name = "poll_checkout",
+                is_ready = true,
+            );
+        }
+    })
+    .await
+    .expect("task should complete");
+}
+
+/// Returns the number of poll ops recorded by [`record_poll_ops`] in
+/// `update`.
+fn poll_ops(update: &Update) -> usize {
+    update.resource_update.as_ref().map_or(0, |update| {
+        update
+            .new_poll_ops
+            .iter()
+            .filter(|op| op.name == "poll_checkout")
+            .count()
+    })
+}
+
+#[test]
+fn resuming_a_stream_leaves_poll_ops_for_the_others() {
+    let (layer, mut harness) = harness();
+    let _subscriber = tracing_subscriber::registry().with(layer).set_default();
+    runtime().block_on(async {
+        harness.spawn();
+        let mut paused = Box::pin(harness.watch_updates().await);
+        let stream_id = next_update(&mut paused).await.stream_id;
+        let mut live = Box::pin(harness.watch_updates().await);
+        next_update(&mut live).await;
+        harness.advance(PUBLISH_INTERVAL).await;
+        next_update(&mut paused).await;
+        next_update(&mut live).await;
+
+        harness.pause(stream_id).await;
+        record_poll_ops(3).await;
+        harness.self_metrics().await;
+
+        // The resumed stream is sent the complete state straight away, which
+        // doesn't include the poll ops that the live stream hasn't been sent
+        // yet.
+        harness.resume(stream_id).await;
+        let update = next_update(&mut paused).await;
+        assert_eq!(update.stream_id, stream_id);
+        assert_eq!(poll_ops(&update), 0);
+
+        harness.advance(PUBLISH_INTERVAL).await;
+        assert_eq!(poll_ops(&next_update(&mut live).await), 3);
+        assert_eq!(poll_ops(&next_update(&mut paused).await), 3);
+    });
+}
+
+/// Waits for the aggregator to drain the event buffer, as far as it will
+/// before the next tick.
+async fn drain(harness: &AggregatorHarness) {
+    let mut len = harness.self_metrics().await.event_buffer_len;
+    loop {
+        let drained = harness.self_metrics().await.event_buffer_len;
+        if drained >= len {
+            break;
+        }
+        len = drained;
+    }
+}
+
+#[test]
+fn stretches_the_publish_interval_under_load() {
+    let (layer, mut harness) = AggregatorHarness::new(
+        ConsoleLayer::builder()
+            .publish_interval(PUBLISH_INTERVAL)
+            .retention(RETENTION)
+            .event_buffer_capacity(EVENT_BUFFER)
+            .event_buffer_shards(1)
+            .adaptive_publish(PUBLISH_INTERVAL..=PUBLISH_INTERVAL * 4),
+    );
+    let _subscriber = tracing_subscriber::registry().with(layer).set_default();
+    runtime().block_on(async {
+        harness.spawn();
+        let mut updates = Box::pin(harness.watch_updates().await);
+        next_update(&mut updates).await;
+        // Start from a quiet interval, so that nothing published before the
+        // stream's first tick is merged into the updates below.
+        harness.advance(PUBLISH_INTERVAL).await;
+        next_update(&mut updates).await;
+        let interval = |update: &Update| {
+            let interval = update
+                .runtime_stats
+                .and_then(|stats| stats.interval)
+                .expect("runtime stats should be sent");
+            Duration::try_from(interval).expect("interval should be valid")
+        };
+        let dropped = |update: &Update| {
+            let dropped = update
+                .subscriber_stats
+                .and_then(|stats| stats.dropped_events)
+                .expect("subscriber stats should be sent");
+            let resources = update
+                .resource_update
+                .as_ref()
+                .map_or(0, |update| update.dropped_events);
+            (dropped.poll_ops, dropped.resources, resources)
+        };
+
+        // The event buffer nears capacity twice before the next tick, so the
+        // publish interval is doubled. The update published on that tick
+        // sheds the oldest poll ops beyond the limit.
+        for _ in 0..2 {
+            record_poll_ops(EVENT_BUFFER * 3 / 4).await;
+            drain(&harness).await;
+        }
+        let poll_ops = harness.self_metrics().await.poll_ops;
+        harness.advance(PUBLISH_INTERVAL).await;
+        let update = next_update(&mut updates).await;
+        let resources = update
+            .resource_update
+            .as_ref()
+            .expect("resources should be sent");
+        assert_eq!(resources.new_poll_ops.len(), 1024);
+        let (shed, dropped_resources, dropped_by_resource) = dropped(&update);
+        assert!(
+            shed >= poll_ops - 1024,
+            "{shed} of {poll_ops} poll ops were shed"
+        );
+        assert_eq!((dropped_resources, dropped_by_resource), (0, 0));
+
+        // Without any more pressure, the interval recovers on the next tick,
+        // which is only due once the stretched interval has passed.
+        harness.advance(PUBLISH_INTERVAL).await;
+        harness.advance(PUBLISH_INTERVAL).await;
+        let update = next_update(&mut updates).await;
+        assert_eq!(interval(&update), PUBLISH_INTERVAL * 2);
+        assert_eq!(dropped(&update), (0, 0, 0));
+
+        harness.advance(PUBLISH_INTERVAL).await;
+        assert_eq!(interval(&next_update(&mut updates).await), PUBLISH_INTERVAL);
+    });
+}
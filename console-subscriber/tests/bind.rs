@@ -0,0 +1,63 @@
+use std::{
+    net::{Ipv4Addr, SocketAddr, TcpListener},
+    time::Duration,
+};
+
+use console_subscriber::{BindError, ConsoleLayer};
+
+#[tokio::test]
+async fn address_in_use() {
+    let taken = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("binding should succeed");
+    let addr = taken.local_addr().unwrap();
+
+    let (_layer, server) = ConsoleLayer::builder().server_addr(addr).build();
+    let error = tokio::time::timeout(Duration::from_secs(5), server.serve())
+        .await
+        .expect("the server should fail to start")
+        .expect_err("the address is in use");
+    match error.downcast_ref::<BindError>() {
+        Some(BindError::AddrInUse {
+            addr: in_use,
+            port_range: None,
+        }) => assert_eq!(*in_use, addr),
+        _ => panic!("expected an address in use error, got {:?}", error),
+    }
+}
+
+#[tokio::test]
+async fn falls_back_on_port_range() {
+    let taken = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("binding should succeed");
+    let addr = taken.local_addr().unwrap();
+
+    // Port 0 lets the OS choose any free port as the fallback.
+    let (_layer, server) = ConsoleLayer::builder()
+        .server_addr(addr)
+        .port_range(0..=0)
+        .build();
+    let shutdown = server.shutdown_handle();
+    let serve = tokio::spawn(server.serve());
+
+    let bound = async {
+        loop {
+            match ConsoleLayer::server_addr() {
+                Some(bound) if bound != addr => break bound,
+                _ => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        }
+    };
+    let bound: SocketAddr = tokio::time::timeout(Duration::from_secs(5), bound)
+        .await
+        .expect("the server should listen on a fallback port");
+    assert_eq!(bound.ip(), addr.ip());
+    assert_ne!(bound.port(), 0);
+    tokio::net::TcpStream::connect(bound)
+        .await
+        .expect("the server should accept connections on the fallback port");
+
+    shutdown.shutdown();
+    tokio::time::timeout(Duration::from_secs(5), serve)
+        .await
+        .expect("the server should stop after shutting down")
+        .expect("the server task should not panic")
+        .expect("the server should not fail");
+}
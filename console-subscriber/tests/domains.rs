@@ -0,0 +1,87 @@
+#![cfg(unix)]
+use std::time::Duration;
+
+use console_api::instrument::{instrument_client::InstrumentClient, TasksRequest};
+use futures::StreamExt;
+use hyper_util::rt::TokioIo;
+use tokio::net::UnixStream;
+use tonic::transport::{Endpoint, Uri};
+use tower::service_fn;
+use tracing_subscriber::prelude::*;
+
+#[test]
+fn watch_tasks_only_sends_tasks() {
+    let path = std::env::temp_dir().join(format!("console-domains-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let (layer, server) = console_subscriber::ConsoleLayer::builder()
+        .publish_interval(Duration::from_millis(10))
+        .server_addr(path.as_path())
+        .build();
+    let _subscriber = tracing_subscriber::registry().with(layer).set_default();
+    let shutdown = server.shutdown_handle();
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("runtime should build");
+    runtime.block_on(async {
+        let serve = tokio::spawn(server.serve());
+        tokio::task::Builder::new()
+            .name("domains-task")
+            .spawn(futures::future::pending::<()>())
+            .expect("spawning task failed");
+
+        let socket = path.clone();
+        let connect = async {
+            loop {
+                let socket = socket.clone();
+                // The URI is ignored, as the connector connects to the socket.
+                let channel = Endpoint::try_from("http://[::]:6669")
+                    .expect("endpoint should be valid")
+                    .connect_with_connector(service_fn(move |_: Uri| {
+                        let socket = socket.clone();
+                        async move { UnixStream::connect(socket).await.map(TokioIo::new) }
+                    }))
+                    .await;
+                match channel {
+                    Ok(channel) => break channel,
+                    Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+                }
+            }
+        };
+        let channel = tokio::time::timeout(Duration::from_secs(5), connect)
+            .await
+            .expect("server should start");
+        let mut stream = InstrumentClient::new(channel)
+            .watch_tasks(TasksRequest {})
+            .await
+            .expect("watching tasks should succeed")
+            .into_inner();
+
+        for _ in 0..3 {
+            let update = tokio::time::timeout(Duration::from_secs(5), stream.next())
+                .await
+                .expect("an update should be sent")
+                .expect("the stream should not end")
+                .expect("the update should not be an error");
+            assert!(update.task_update.is_some(), "update: {update:?}");
+            assert!(update.resource_update.is_none(), "update: {update:?}");
+            assert!(update.async_op_update.is_none(), "update: {update:?}");
+        }
+
+        // Shutting down ends the stream and the server.
+        shutdown.shutdown();
+        let drain = async { while stream.next().await.is_some() {} };
+        tokio::time::timeout(Duration::from_secs(5), drain)
+            .await
+            .expect("the stream should end after shutting down");
+        tokio::time::timeout(Duration::from_secs(5), serve)
+            .await
+            .expect("the server should stop after shutting down")
+            .expect("the server task should not panic")
+            .expect("the server should not fail");
+    });
+
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,81 @@
+#![cfg(unix)]
+use std::{path::Path, time::Duration};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::UnixStream,
+};
+use tracing_subscriber::prelude::*;
+
+#[test]
+fn serves_tasks_as_json() {
+    let path =
+        std::env::temp_dir().join(format!("console-json-gateway-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let (layer, server) = console_subscriber::ConsoleLayer::builder()
+        .publish_interval(Duration::from_millis(10))
+        .server_addr(path.as_path())
+        .build();
+    let _subscriber = tracing_subscriber::registry().with(layer).set_default();
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("runtime should build");
+    runtime.block_on(async {
+        tokio::spawn(server.serve_with_json_gateway(tonic::transport::Server::default()));
+        tokio::task::Builder::new()
+            .name("json-task")
+            .spawn(futures::future::pending::<()>())
+            .expect("spawning task failed");
+
+        let find_task = async {
+            loop {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                let Some(tasks) = get_json(&path, "/json/tasks").await else {
+                    continue;
+                };
+                let task = tasks.as_array().and_then(|tasks| {
+                    tasks
+                        .iter()
+                        .find(|task| task["fields"]["task.name"] == "json-task")
+                        .cloned()
+                });
+                if let Some(task) = task {
+                    break task;
+                }
+            }
+        };
+        let task = tokio::time::timeout(Duration::from_secs(5), find_task)
+            .await
+            .expect("task should be served by the JSON gateway");
+        assert_eq!(task["kind"], "spawn");
+        assert!(task["polls"].as_u64().unwrap() >= 1, "task: {task}");
+
+        let state = get_json(&path, "/json/state")
+            .await
+            .expect("state should be served by the JSON gateway");
+        assert!(state["tasks"].is_array(), "state: {state}");
+        assert!(state["resources"].is_array(), "state: {state}");
+        assert!(state["async_ops"].is_array(), "state: {state}");
+    });
+
+    let _ = std::fs::remove_file(&path);
+}
+
+/// Sends a minimal HTTP/1.0 GET request over the Unix socket at `path`,
+/// returning the response body parsed as JSON.
+async fn get_json(path: &Path, uri: &str) -> Option<serde_json::Value> {
+    let mut stream = UnixStream::connect(path).await.ok()?;
+    let request = format!("GET {uri} HTTP/1.0\r\nHost: localhost\r\n\r\n");
+    stream.write_all(request.as_bytes()).await.ok()?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.ok()?;
+
+    let (head, body) = response.split_once("\r\n\r\n")?;
+    if !head.starts_with("HTTP/1.0 200") && !head.starts_with("HTTP/1.1 200") {
+        return None;
+    }
+    serde_json::from_str(body).ok()
+}
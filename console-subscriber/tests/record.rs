@@ -0,0 +1,131 @@
+use std::{io::Cursor, path::PathBuf};
+
+use console_subscriber::{
+    record::{Event, FieldValue, ReadError, Reader, WakeOp},
+    ConsoleLayer,
+};
+use tracing_subscriber::prelude::*;
+
+fn recording_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "console-record-{}-{}.json",
+        name,
+        std::process::id()
+    ))
+}
+
+#[test]
+fn reads_back_recorded_events() {
+    let path = recording_path("events");
+    let (layer, server) = ConsoleLayer::builder().recording_path(&path).build();
+    let _subscriber = tracing_subscriber::registry().with(layer).set_default();
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("runtime should build")
+        .block_on(async {
+            tokio::spawn(async { tokio::task::yield_now().await })
+                .await
+                .expect("task should complete");
+        });
+    server.shutdown_handle().flush_recording();
+
+    let reader = Reader::open(&path).expect("the recording should be readable");
+    assert_eq!(reader.version(), 1);
+    let events = reader
+        .collect::<Result<Vec<_>, _>>()
+        .expect("every event should parse");
+    std::fs::remove_file(&path).expect("the recording should be removed");
+
+    // The runtime's `block_on` task is recorded too.
+    let id = events
+        .iter()
+        .find_map(|event| match event {
+            Event::Spawn { id, fields, .. } => fields
+                .iter()
+                .any(|field| field.name == "kind" && field.value == FieldValue::Str("task".into()))
+                .then_some(*id),
+            _ => None,
+        })
+        .expect("the spawned task should be recorded");
+
+    let task_events = events
+        .iter()
+        .filter(|event| match event {
+            Event::Spawn { id: event_id, .. }
+            | Event::Enter { id: event_id, .. }
+            | Event::Exit { id: event_id, .. }
+            | Event::Close { id: event_id, .. }
+            | Event::Waker { id: event_id, .. } => *event_id == id,
+            _ => false,
+        })
+        .collect::<Vec<_>>();
+    assert!(matches!(task_events.first(), Some(Event::Spawn { .. })));
+    assert!(matches!(task_events.last(), Some(Event::Close { .. })));
+    let count = |kind: fn(&Event) -> bool| task_events.iter().filter(|event| kind(event)).count();
+    let enters = count(|event| matches!(event, Event::Enter { .. }));
+    let exits = count(|event| matches!(event, Event::Exit { .. }));
+    assert!(enters >= 2, "the task yields once, so it's polled twice");
+    assert_eq!(enters, exits);
+    assert!(task_events.iter().any(|event| matches!(
+        event,
+        Event::Waker {
+            op: WakeOp::WakeByRef { .. } | WakeOp::Wake { .. },
+            ..
+        }
+    )));
+}
+
+#[test]
+fn reads_every_kind_of_field_value() {
+    let recording = r#"{"v":1}
+{"Spawn":{"id":1,"at":{"secs_since_epoch":1,"nanos_since_epoch":0},"fields":[{"name":"a","value":true},{"name":"b","value":7},{"name":"c","value":-7},{"name":"d","value":"x"}]}}
+
+{"Close":{"id":1,"at":{"secs_since_epoch":2,"nanos_since_epoch":500}}}
+"#;
+    let events = Reader::new(Cursor::new(recording))
+        .expect("the header should be read")
+        .collect::<Result<Vec<_>, _>>()
+        .expect("every event should parse");
+    assert_eq!(events.len(), 2);
+    let Event::Spawn { fields, .. } = &events[0] else {
+        panic!("expected a spawn event, got {:?}", events[0]);
+    };
+    let values = fields.iter().map(|field| &field.value).collect::<Vec<_>>();
+    assert_eq!(
+        values,
+        [
+            &FieldValue::Bool(true),
+            &FieldValue::U64(7),
+            &FieldValue::I64(-7),
+            &FieldValue::Str("x".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn rejects_unsupported_versions() {
+    let err = Reader::new(Cursor::new("{\"v\":2}\n")).expect_err("version 2 isn't supported");
+    assert!(
+        matches!(err, ReadError::UnsupportedVersion { version: 2 }),
+        "{:?}",
+        err
+    );
+
+    for recording in ["", "\n", "{\"Close\":{\"id\":1}}\n"] {
+        let err = Reader::new(Cursor::new(recording)).expect_err("there's no header");
+        assert!(matches!(err, ReadError::MissingHeader), "{:?}", err);
+    }
+}
+
+#[test]
+fn reports_the_line_of_an_invalid_event() {
+    let mut reader = Reader::new(Cursor::new("{\"v\":1}\n\n{\"Teleport\":{}}\n"))
+        .expect("the header should be read");
+    let err = reader
+        .next()
+        .expect("there's a line after the header")
+        .expect_err("the event is unknown");
+    assert!(matches!(err, ReadError::Parse { line: 3, .. }), "{:?}", err);
+    assert!(reader.next().is_none());
+}
@@ -0,0 +1,166 @@
+use std::fmt;
+
+use tracing::Instrument;
+
+mod support;
+use support::{Expectations, ExpectedAsyncOp, ExpectedResource};
+
+/// A resource defined outside of Tokio, as a third-party library might.
+struct Pool {
+    span: tracing::Span,
+}
+
+impl Pool {
+    fn new() -> Self {
+        let span = console_subscriber::instrument_resource!(
+            concrete_type = "test::Pool",
+            kind = "Sync",
+            pool.size = 4_u64,
+        );
+        Self { span }
+    }
+
+    async fn checkout(&self) {
+        let async_op = self
+            .span
+            .in_scope(|| console_subscriber::instrument_async_op!(source = "Pool::checkout"));
+
+        {
+            // Attribute the op to the polling task, as Tokio's resources do.
+            let _resource = self.span.enter();
+            let _async_op = async_op.enter();
+            tracing::trace!(
+                target: "runtime::resource::poll_op",
+                op_name = "poll_checkout",
+                is_ready = true,
+            );
+        }
+
+        async {}.instrument(async_op).await
+    }
+}
+
+#[test]
+fn third_party_resource() {
+    let expected_resource = ExpectedResource::default()
+        .match_concrete_type("test::Pool".into())
+        .expect_kind("Sync".into())
+        .expect_internal(false)
+        .expect_file("resources.rs".into());
+
+    let future = async {
+        drop(Pool::new());
+    };
+
+    Expectations::new()
+        .resource(expected_resource)
+        .assert(future);
+}
+
+#[test]
+fn third_party_async_op() {
+    let expected_async_op = ExpectedAsyncOp::default()
+        .match_source("Pool::checkout".into())
+        .expect_resource("test::Pool".into())
+        .expect_default_task();
+
+    let future = async {
+        let pool = Pool::new();
+        pool.checkout().await;
+    };
+
+    Expectations::new()
+        .async_op(expected_async_op)
+        .assert(future);
+}
+
+#[test]
+fn display_fields() {
+    struct Kind;
+
+    impl fmt::Display for Kind {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("Pool")
+        }
+    }
+
+    let expected_resource = ExpectedResource::default()
+        .match_concrete_type("test::Displayed".into())
+        .expect_kind("Pool".into());
+
+    let future = async {
+        let _span = console_subscriber::instrument_resource!(
+            target: "my_library::pool",
+            concrete_type = %format_args!("test::{}", "Displayed"),
+            kind = %Kind,
+        );
+    };
+
+    Expectations::new()
+        .resource(expected_resource)
+        .assert(future);
+}
+
+#[test]
+fn semaphore_attributes() {
+    let expected_resource = ExpectedResource::default()
+        .match_concrete_type("Semaphore".into())
+        .expect_kind("Sync".into())
+        .expect_attribute("permits".into(), "2".into());
+    let expected_async_op = ExpectedAsyncOp::default()
+        .match_source("Semaphore::acquire".into())
+        .expect_resource("Semaphore".into())
+        .expect_default_task()
+        .expect_attribute("permits_obtained".into(), "1".into());
+
+    let future = async {
+        let semaphore = tokio::sync::Semaphore::new(3);
+        semaphore
+            .acquire()
+            .await
+            .expect("semaphore should not be closed")
+            .forget();
+    };
+
+    Expectations::new()
+        .resource(expected_resource)
+        .async_op(expected_async_op)
+        .assert(future);
+}
+
+#[test]
+#[should_panic(expected = "Test failed: Resource validation failed:
+ - Resource { concrete_type=Semaphore }: expected attribute `permits` to be \"5\", but actual was Some(\"3\")
+")]
+fn fail_attribute() {
+    let expected_resource = ExpectedResource::default()
+        .match_concrete_type("Semaphore".into())
+        .expect_attribute("permits".into(), "5".into());
+
+    let future = async {
+        let _semaphore = tokio::sync::Semaphore::new(3);
+    };
+
+    Expectations::new()
+        .resource(expected_resource)
+        .assert(future);
+}
+
+#[test]
+#[should_panic(expected = "Test failed: Async op validation failed:
+ - AsyncOp { source=Pool::reserve }: no matching actual async op was found
+")]
+fn fail_no_matching_async_op() {
+    let expected_async_op = ExpectedAsyncOp::default()
+        .match_source("Pool::reserve".into())
+        .expect_present();
+
+    let future = async {
+        let pool = Pool::new();
+        pool.checkout().await;
+    };
+
+    Expectations::new()
+        .async_op(expected_async_op)
+        .assert(future);
+}
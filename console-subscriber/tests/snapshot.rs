@@ -0,0 +1,107 @@
+#![cfg(unix)]
+use std::time::Duration;
+
+use console_api::{
+    field::Value,
+    instrument::{instrument_client::InstrumentClient, SnapshotRequest},
+};
+use futures::StreamExt;
+use hyper_util::rt::TokioIo;
+use tokio::net::UnixStream;
+use tonic::transport::{Endpoint, Uri};
+use tower::service_fn;
+use tracing_subscriber::prelude::*;
+
+#[test]
+fn snapshot_contains_every_task_and_ends() {
+    let path = std::env::temp_dir().join(format!("console-snapshot-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let (layer, server) = console_subscriber::ConsoleLayer::builder()
+        .publish_interval(Duration::from_millis(10))
+        .server_addr(path.as_path())
+        .build();
+    let _subscriber = tracing_subscriber::registry().with(layer).set_default();
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("runtime should build");
+    runtime.block_on(async {
+        tokio::spawn(server.serve());
+        let names = ["snapshot-1", "snapshot-2", "snapshot-3"];
+        for name in names {
+            tokio::task::Builder::new()
+                .name(name)
+                .spawn(futures::future::pending::<()>())
+                .expect("spawning task failed");
+        }
+
+        let socket = path.clone();
+        let connect = async {
+            loop {
+                let socket = socket.clone();
+                // The URI is ignored, as the connector connects to the socket.
+                let channel = Endpoint::try_from("http://[::]:6669")
+                    .expect("endpoint should be valid")
+                    .connect_with_connector(service_fn(move |_: Uri| {
+                        let socket = socket.clone();
+                        async move { UnixStream::connect(socket).await.map(TokioIo::new) }
+                    }))
+                    .await;
+                match channel {
+                    Ok(channel) => break channel,
+                    Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+                }
+            }
+        };
+        let channel = tokio::time::timeout(Duration::from_secs(5), connect)
+            .await
+            .expect("server should start");
+        // Let the aggregator see the tasks being spawned.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let stream = InstrumentClient::new(channel)
+            .snapshot(SnapshotRequest {})
+            .await
+            .expect("taking a snapshot should succeed")
+            .into_inner();
+        let updates = tokio::time::timeout(Duration::from_secs(5), stream.collect::<Vec<_>>())
+            .await
+            .expect("the snapshot stream should end")
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .expect("the snapshot should not be an error");
+
+        assert!(!updates.is_empty());
+        // Every update in a snapshot is taken at the same time.
+        assert!(updates.iter().all(|update| update.now == updates[0].now));
+        assert!(updates[0].new_metadata.is_some(), "updates: {updates:?}");
+
+        let tasks = updates
+            .iter()
+            .filter_map(|update| update.task_update.as_ref())
+            .collect::<Vec<_>>();
+        for name in names {
+            let task = tasks
+                .iter()
+                .flat_map(|tasks| &tasks.new_tasks)
+                .find(|task| {
+                    task.fields.iter().any(|field| match &field.value {
+                        Some(Value::StrVal(value) | Value::DebugVal(value)) => value == name,
+                        _ => false,
+                    })
+                })
+                .unwrap_or_else(|| panic!("task {name} should be in the snapshot"));
+            let id = task.id.as_ref().expect("task should have an id").id;
+            assert!(
+                tasks
+                    .iter()
+                    .any(|tasks| tasks.stats_update.contains_key(&id)),
+                "task {name} should have stats in the snapshot"
+            );
+        }
+    });
+
+    let _ = std::fs::remove_file(&path);
+}
@@ -1,8 +1,10 @@
 use std::time::Duration;
 
 use tokio::time::sleep;
+use tracing::Instrument;
 
 mod support;
+use console_api::tasks::stats::TerminatedBy;
 use support::{assert_tasks, spawn_named, ExpectedTask};
 
 /// This test asserts the behavior that was fixed in #440. Before that fix,
@@ -39,3 +41,146 @@ fn child_polls_dont_count_towards_parent_polls() {
 
     assert_tasks(expected_tasks, future);
 }
+
+#[test]
+fn tasks_inherit_group_from_spawning_span() {
+    let expected_tasks = vec![
+        ExpectedTask::default()
+            .match_name("grouped".into())
+            .expect_group(Some("ingest")),
+        ExpectedTask::default()
+            .match_name("grouped-child".into())
+            .expect_group(Some("ingest")),
+        ExpectedTask::default()
+            .match_name("ungrouped".into())
+            .expect_group(None),
+    ];
+
+    let future = async {
+        let span = tracing::info_span!("ingest", console.group = "ingest");
+        async {
+            spawn_named("grouped", async {
+                spawn_named("grouped-child", async {})
+                    .await
+                    .expect("joining grouped child failed");
+            })
+            .await
+            .expect("joining grouped task failed");
+        }
+        .instrument(span)
+        .await;
+
+        spawn_named("ungrouped", async {})
+            .await
+            .expect("joining ungrouped task failed");
+    };
+
+    assert_tasks(expected_tasks, future);
+}
+
+#[test]
+fn tasks_are_terminated_by_panics() {
+    let expected_tasks = vec![
+        ExpectedTask::default()
+            .match_name("completes".into())
+            .expect_terminated_by(TerminatedBy::Completed),
+        ExpectedTask::default()
+            .match_name("panics".into())
+            .expect_terminated_by(TerminatedBy::Panicked),
+    ];
+
+    let future = async {
+        spawn_named("completes", async {})
+            .await
+            .expect("joining completed task failed");
+
+        let panicked = spawn_named("panics", async {
+            sleep(Duration::ZERO).await;
+            panic!("the task panicked");
+        })
+        .await
+        .expect_err("the task should have panicked");
+        assert!(panicked.is_panic());
+    };
+
+    assert_tasks(expected_tasks, future);
+}
+
+#[test]
+fn tasks_dropped_during_unrelated_panics_are_not_panicked() {
+    let expected_tasks = vec![ExpectedTask::default()
+        .match_name("bystander".into())
+        .expect_terminated_by(TerminatedBy::Aborted)];
+
+    let future = async {
+        // The runtime is dropped on another thread, since runtimes can't be
+        // dropped from within an asynchronous context.
+        let dispatch = tracing::dispatcher::get_default(Clone::clone);
+        let unwound = std::thread::spawn(move || {
+            tracing::dispatcher::with_default(&dispatch, || {
+                std::panic::catch_unwind(|| {
+                    let runtime = tokio::runtime::Builder::new_current_thread()
+                        .enable_all()
+                        .build()
+                        .expect("building runtime failed");
+                    let _guard = runtime.enter();
+                    spawn_named("bystander", async {
+                        sleep(Duration::from_secs(60)).await;
+                    });
+                    runtime.block_on(tokio::task::yield_now());
+                    // The runtime, and the task with it, is dropped while
+                    // unwinding from this panic.
+                    panic!("an unrelated panic");
+                })
+            })
+        })
+        .join()
+        .expect("the thread should have caught the panic");
+        assert!(unwound.is_err());
+    };
+
+    assert_tasks(expected_tasks, future);
+}
+
+#[test]
+fn aborted_tasks_are_cancelled() {
+    let expected_tasks = vec![
+        ExpectedTask::default()
+            .match_name("completes".into())
+            .expect_terminated_by(TerminatedBy::Completed),
+        ExpectedTask::default()
+            .match_name("aborted-while-sleeping".into())
+            .expect_terminated_by(TerminatedBy::Aborted),
+        ExpectedTask::default()
+            .match_name("aborted-before-polled".into())
+            .expect_terminated_by(TerminatedBy::Aborted),
+    ];
+
+    let future = async {
+        spawn_named("completes", async {
+            sleep(Duration::ZERO).await;
+        })
+        .await
+        .expect("joining completed task failed");
+
+        let sleeping = spawn_named("aborted-while-sleeping", async {
+            sleep(Duration::from_secs(60 * 60)).await;
+        });
+        // Let the task start sleeping before it's aborted.
+        tokio::task::yield_now().await;
+        sleeping.abort();
+        let error = sleeping
+            .await
+            .expect_err("the task should have been aborted");
+        assert!(error.is_cancelled());
+
+        let never_polled = spawn_named("aborted-before-polled", std::future::pending::<()>());
+        never_polled.abort();
+        let error = never_polled
+            .await
+            .expect_err("the task should have been aborted");
+        assert!(error.is_cancelled());
+    };
+
+    assert_tasks(expected_tasks, future);
+}
@@ -0,0 +1,113 @@
+#![cfg(unix)]
+use std::time::{Duration, Instant};
+
+use console_api::instrument::{
+    instrument_client::InstrumentClient, InstrumentRequest, TasksRequest, Update,
+};
+use futures::{Stream, StreamExt};
+use hyper_util::rt::TokioIo;
+use tokio::net::UnixStream;
+use tonic::transport::{Channel, Endpoint, Uri};
+use tower::service_fn;
+use tracing_subscriber::prelude::*;
+
+async fn next_update(
+    stream: &mut (impl Stream<Item = Result<Update, tonic::Status>> + Unpin),
+) -> Update {
+    tokio::time::timeout(Duration::from_secs(5), stream.next())
+        .await
+        .expect("an update should be sent")
+        .expect("the stream should not end")
+        .expect("the update should not be an error")
+}
+
+#[test]
+fn streams_are_sent_updates_at_their_own_interval() {
+    let path = std::env::temp_dir().join(format!(
+        "console-update-interval-{}.sock",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+
+    let (layer, server) = console_subscriber::ConsoleLayer::builder()
+        .publish_interval(Duration::from_secs(1))
+        .server_addr(path.as_path())
+        .build();
+    let _subscriber = tracing_subscriber::registry().with(layer).set_default();
+    let shutdown = server.shutdown_handle();
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("runtime should build");
+    runtime.block_on(async {
+        let serve = tokio::spawn(server.serve());
+
+        let socket = path.clone();
+        let connect = async {
+            loop {
+                let socket = socket.clone();
+                // The URI is ignored, as the connector connects to the socket.
+                let channel = Endpoint::try_from("http://[::]:6669")
+                    .expect("endpoint should be valid")
+                    .connect_with_connector(service_fn(move |_: Uri| {
+                        let socket = socket.clone();
+                        async move { UnixStream::connect(socket).await.map(TokioIo::new) }
+                    }))
+                    .await;
+                match channel {
+                    Ok(channel) => break channel,
+                    Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+                }
+            }
+        };
+        let channel: Channel = tokio::time::timeout(Duration::from_secs(5), connect)
+            .await
+            .expect("server should start");
+        let mut client = InstrumentClient::new(channel);
+
+        let mut slow = client
+            .watch_tasks(TasksRequest {})
+            .await
+            .expect("watching tasks should succeed")
+            .into_inner();
+        let request = InstrumentRequest {
+            update_interval: Some(Duration::from_millis(100).try_into().unwrap()),
+        };
+        let mut fast = client
+            .watch_updates(request)
+            .await
+            .expect("watching updates should succeed")
+            .into_inner();
+
+        // Each stream's first update has the complete state.
+        assert_eq!(next_update(&mut slow).await.sequence, 0);
+        assert_eq!(next_update(&mut fast).await.sequence, 0);
+
+        // The fast stream is sent updates much more often than the publish
+        // interval, numbered by the stream.
+        let started = Instant::now();
+        for sequence in 1..=5 {
+            assert_eq!(next_update(&mut fast).await.sequence, sequence);
+        }
+        assert!(
+            started.elapsed() < Duration::from_millis(900),
+            "fast updates took {:?}",
+            started.elapsed()
+        );
+
+        // The slow stream is sent the updates published in between merged
+        // together, so its numbering has no gaps.
+        assert_eq!(next_update(&mut slow).await.sequence, 1);
+        assert_eq!(next_update(&mut slow).await.sequence, 2);
+
+        shutdown.shutdown();
+        tokio::time::timeout(Duration::from_secs(5), serve)
+            .await
+            .expect("the server should stop after shutting down")
+            .expect("the server task should not panic")
+            .expect("the server should not fail");
+    });
+
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,94 @@
+#![cfg(unix)]
+use std::{path::Path, time::Duration};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::UnixStream,
+};
+use tracing_subscriber::prelude::*;
+
+#[test]
+fn serves_web_ui_and_histograms() {
+    let path = std::env::temp_dir().join(format!("console-web-ui-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let (layer, server) = console_subscriber::ConsoleLayer::builder()
+        .publish_interval(Duration::from_millis(10))
+        .server_addr(path.as_path())
+        .build();
+    let _subscriber = tracing_subscriber::registry().with(layer).set_default();
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("runtime should build");
+    runtime.block_on(async {
+        tokio::spawn(server.serve_with_web_ui(tonic::transport::Server::default()));
+        tokio::task::Builder::new()
+            .name("web-ui-task")
+            .spawn(futures::future::pending::<()>())
+            .expect("spawning task failed");
+
+        let find_task = async {
+            loop {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                let Some((_, body)) = get(&path, "/json/tasks").await else {
+                    continue;
+                };
+                let Ok(tasks) = serde_json::from_str::<serde_json::Value>(&body) else {
+                    continue;
+                };
+                let id = tasks.as_array().and_then(|tasks| {
+                    tasks
+                        .iter()
+                        .find(|task| task["fields"]["task.name"] == "web-ui-task")
+                        .and_then(|task| task["id"].as_u64())
+                });
+                if let Some(id) = id {
+                    break id;
+                }
+            }
+        };
+        let id = tokio::time::timeout(Duration::from_secs(5), find_task)
+            .await
+            .expect("task should be served by the JSON gateway");
+
+        let (head, body) = get(&path, "/").await.expect("web UI should be served");
+        assert!(head.contains("200"), "response: {head}");
+        assert!(
+            head.to_lowercase().contains("text/html"),
+            "response: {head}"
+        );
+        assert!(body.contains("/json/state"), "body: {body}");
+
+        let (head, body) = get(&path, &format!("/json/tasks/{id}/histograms"))
+            .await
+            .expect("histograms should be served");
+        assert!(head.contains("200"), "response: {head}");
+        let histograms: serde_json::Value =
+            serde_json::from_str(&body).expect("histograms should be JSON");
+        let poll_times = &histograms["poll_times"];
+        assert!(poll_times["count"].as_u64().unwrap() >= 1, "{histograms}");
+        assert_eq!(poll_times["buckets"].as_array().unwrap().len(), 32);
+
+        let (head, _) = get(&path, "/json/tasks/999999999/histograms")
+            .await
+            .expect("the server should respond");
+        assert!(head.contains("404"), "response: {head}");
+    });
+
+    let _ = std::fs::remove_file(&path);
+}
+
+/// Sends a minimal HTTP/1.0 GET request over the Unix socket at `path`,
+/// returning the response's head and body.
+async fn get(path: &Path, uri: &str) -> Option<(String, String)> {
+    let mut stream = UnixStream::connect(path).await.ok()?;
+    let request = format!("GET {uri} HTTP/1.0\r\nHost: localhost\r\n\r\n");
+    stream.write_all(request.as_bytes()).await.ok()?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.ok()?;
+
+    let (head, body) = response.split_once("\r\n\r\n")?;
+    Some((head.to_string(), body.to_string()))
+}
@@ -0,0 +1,131 @@
+//! Headless lint runs for `tokio-console check`.
+//!
+//! This connects to the target like the TUI does, runs the configured warning
+//! linters over the tasks it sees for a fixed period, and prints a JSON report,
+//! so that async regressions can be caught in CI.
+use crate::{
+    conn::{Connection, Message},
+    state::{tasks::Task, DisplayedList, State},
+};
+use color_eyre::eyre::{eyre, WrapErr};
+use serde::Serialize;
+use std::time::Duration;
+
+/// Options for `tokio-console check`.
+#[derive(Debug)]
+pub(crate) struct CheckOptions {
+    pub(crate) duration: Duration,
+}
+
+/// The report printed by `tokio-console check`.
+#[derive(Debug, Serialize)]
+struct Report {
+    /// `true` if no warnings were triggered.
+    passed: bool,
+    duration_ms: u64,
+    updates: usize,
+    /// The number of tasks seen.
+    tasks: usize,
+    /// Each warning triggered while collecting data, by spawn location.
+    warnings: Vec<WarningRow>,
+    /// The tasks which still had warnings at the end of the run.
+    warning_tasks: Vec<TaskRow>,
+}
+
+#[derive(Debug, Serialize)]
+struct WarningRow {
+    warning: String,
+    location: String,
+    /// The number of distinct tasks the warning was triggered for.
+    tasks: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct TaskRow {
+    id: String,
+    name: Option<String>,
+    location: String,
+    warnings: Vec<String>,
+}
+
+/// Collects updates from `conn` for `options.duration`, then prints a report
+/// of the warnings triggered to stdout.
+///
+/// Returns `false` if any warnings were triggered.
+pub(crate) async fn run(
+    mut conn: Connection,
+    mut state: State,
+    options: CheckOptions,
+) -> color_eyre::Result<bool> {
+    let mut updates = 0;
+    let receive = async {
+        loop {
+            if let Message::Update(update) = conn.next_message().await {
+                state.update(Some(DisplayedList::Tasks), update);
+                updates += 1;
+            }
+        }
+    };
+    // The connection is retried forever, so this only ends at the deadline.
+    let _ = tokio::time::timeout(options.duration, receive).await;
+    if updates == 0 {
+        return Err(eyre!(
+            "no updates were received from the target in {:?}",
+            options.duration
+        ));
+    }
+
+    let report = Report::new(&mut state, &options, updates);
+    let output =
+        serde_json::to_string_pretty(&report).wrap_err("failed to serialize check report")?;
+    println!("{}", output);
+    Ok(report.passed)
+}
+
+impl Report {
+    fn new(state: &mut State, options: &CheckOptions, updates: usize) -> Self {
+        let warnings = state
+            .tasks_state()
+            .warning_history()
+            .records()
+            .into_iter()
+            .map(|(warning, location, record)| WarningRow {
+                warning: warning.to_owned(),
+                location: location.to_owned(),
+                tasks: record.count(),
+            })
+            .collect::<Vec<_>>();
+        let warning_tasks = state
+            .tasks_state()
+            .tasks()
+            .filter_map(|task| TaskRow::new(&task.borrow()))
+            .collect();
+        Self {
+            passed: warnings.is_empty(),
+            duration_ms: options.duration.as_millis() as u64,
+            updates,
+            tasks: state.tasks_state().tasks().count(),
+            warnings,
+            warning_tasks,
+        }
+    }
+}
+
+impl TaskRow {
+    /// Returns the row for `task`, if it has any warnings.
+    fn new(task: &Task) -> Option<Self> {
+        if task.warnings().is_empty() {
+            return None;
+        }
+        Some(Self {
+            id: task.id_str().to_string(),
+            name: task.name().map(String::from),
+            location: task.location().to_string(),
+            warnings: task
+                .warnings()
+                .iter()
+                .map(|warning| warning.format(task))
+                .collect(),
+        })
+    }
+}
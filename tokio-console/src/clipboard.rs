@@ -0,0 +1,35 @@
+//! Copying to the system clipboard, for yanking a summary of the selected
+//! task or resource with `y`.
+//!
+//! The clipboard is only available when tokio-console is built with the
+//! `clipboard` feature, as it needs the platform's clipboard libraries.
+
+#[derive(Default)]
+pub(crate) struct Clipboard {
+    /// The system clipboard, opened the first time something is copied.
+    ///
+    /// On Linux, what was copied is only available while this is open, so
+    /// it's kept open until the console exits.
+    #[cfg(feature = "clipboard")]
+    system: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    /// Copies `text` to the system clipboard, returning a description of the
+    /// error if it couldn't be.
+    #[cfg(feature = "clipboard")]
+    pub(crate) fn copy(&mut self, text: String) -> Result<(), String> {
+        let system = match self.system {
+            Some(ref mut system) => system,
+            None => self
+                .system
+                .insert(arboard::Clipboard::new().map_err(|error| error.to_string())?),
+        };
+        system.set_text(text).map_err(|error| error.to_string())
+    }
+
+    #[cfg(not(feature = "clipboard"))]
+    pub(crate) fn copy(&mut self, _text: String) -> Result<(), String> {
+        Err("tokio-console was built without the `clipboard` feature".to_string())
+    }
+}
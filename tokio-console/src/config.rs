@@ -1,12 +1,15 @@
+use crate::state::budget::LatencyBudget;
+use crate::state::stalls;
 use crate::state::tasks::Task;
-use crate::view::Palette;
+use crate::state::Retention;
+use crate::view::{DurationFormat, HighlightRule, Palette, Theme};
 use crate::warnings;
 use clap::builder::{PossibleValuesParser, TypedValueParser};
 use clap::{ArgAction, ArgGroup, CommandFactory, Parser as Clap, Subcommand, ValueHint};
 use clap_complete::Shell;
 use color_eyre::eyre::WrapErr;
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::fs;
 use std::ops::Not;
@@ -35,9 +38,32 @@ pub struct Config {
     /// specifies the path to a Unix domain socket, as in
     /// `file://localhost/path/to/socket`.
     ///
+    /// A target named in the `[targets]` table of the config file can be
+    /// connected to by its name, prefixed with `@`, as in `@api`. Any options
+    /// configured for that target override the rest of the config file.
+    ///
     /// [default: http://127.0.0.1:6669]
     #[clap(value_hint = ValueHint::Url)]
-    pub(crate) target_addr: Option<Uri>,
+    pub(crate) target_addr: Option<TargetAddr>,
+
+    /// Reach the target through an SSH port forward to this host.
+    ///
+    /// This runs `ssh` to forward a local port to the target address as seen
+    /// from the remote machine, so that a process which only listens on
+    /// localhost there can be inspected. The destination may be anything
+    /// `ssh` accepts, such as `user@host` or a host alias from
+    /// `~/.ssh/config`. The forward is closed when the console exits.
+    #[clap(long = "ssh", value_name = "DESTINATION", value_hint = ValueHint::Hostname)]
+    pub(crate) ssh: Option<String>,
+
+    /// Record the updates received from the target to this file.
+    ///
+    /// The recording can be played back later with `tokio-console replay`.
+    /// It's written in a compact protobuf format, unless the file name ends
+    /// in `.json` or `.jsonl`, in which case each update is written as a
+    /// line of JSON. `tokio-console convert` converts between the two.
+    #[clap(long = "record", value_name = "PATH", value_hint = ValueHint::FilePath)]
+    pub(crate) record: Option<PathBuf>,
 
     /// Log level filter for the console's internal diagnostics.
     ///
@@ -58,17 +84,25 @@ pub struct Config {
     /// Each warning is specified by its name, which is one of:
     ///
     /// * `self-wakes` -- Warns when a task wakes itself more than a certain percentage of its total wakeups.
-    ///                   Default percentage is 50%.
+    ///   Default percentage is 50%.
     ///
     /// * `lost-waker` -- Warns when a task is dropped without being woken.
     ///
     /// * `never-yielded` -- Warns when a task has never yielded.
     ///
     /// * `auto-boxed-future` -- Warnings when the future driving a task was automatically boxed by
-    ///                          the runtime because it was large.
+    ///   the runtime because it was large.
     ///
     /// * `large-future` -- Warnings when the future driving a task occupies a large amount of
-    ///                     stack space.
+    ///   stack space.
+    ///
+    /// * `latency-budget` -- Warns when a task exceeds the latency budget configured for its
+    ///   name in the config file.
+    ///
+    /// * `waker-leak` -- Warns when a task's live waker count keeps growing over successive
+    ///   updates, which suggests its wakers are stored and never dropped.
+    ///
+    /// * `panicked` -- Warns when a task panicked.
     #[clap(long = "warn", short = 'W', value_delimiter = ',', num_args = 1..)]
     #[clap(default_values_t = KnownWarnings::default_enabled_warnings())]
     pub(crate) warnings: Vec<KnownWarnings>,
@@ -80,21 +114,29 @@ pub struct Config {
     /// Each warning is specified by its name, which is one of:
     ///
     /// * `self-wakes` -- Warns when a task wakes itself more than a certain percentage of its total wakeups.
-    ///                  Default percentage is 50%.
+    ///   Default percentage is 50%.
     ///
     /// * `lost-waker` -- Warns when a task is dropped without being woken.
     ///
     /// * `never-yielded` -- Warns when a task has never yielded.
     ///
     /// * `auto-boxed-future` -- Warnings when the future driving a task was automatically boxed by
-    ///                          the runtime because it was large.
+    ///   the runtime because it was large.
     ///
     /// * `large-future` -- Warnings when the future driving a task occupies a large amount of
-    ///                     stack space.
+    ///   stack space.
+    ///
+    /// * `latency-budget` -- Warns when a task exceeds the latency budget configured for its
+    ///   name in the config file.
+    ///
+    /// * `waker-leak` -- Warns when a task's live waker count keeps growing over successive
+    ///   updates, which suggests its wakers are stored and never dropped.
+    ///
+    /// * `panicked` -- Warns when a task panicked.
     ///
     /// If this is set to `all`, all warnings are allowed.
     ///
-    /// [possible values: all, self-wakes, lost-waker, never-yielded, large-future, auto-boxed-future]
+    /// [possible values: all, self-wakes, lost-waker, never-yielded, large-future, auto-boxed-future, latency-budget, waker-leak, panicked]
     #[clap(long = "allow", short = 'A', num_args = 1..)]
     pub(crate) allow_warnings: Option<AllowedWarnings>,
 
@@ -140,6 +182,95 @@ pub struct Config {
     #[clap(long = "retain-for")]
     retain_for: Option<RetainFor>,
 
+    /// How long to continue displaying completed tasks after they have been
+    /// closed.
+    ///
+    /// This accepts the same values as `--retain-for`, and overrides it for
+    /// tasks.
+    #[clap(long = "retain-tasks-for")]
+    retain_tasks_for: Option<RetainFor>,
+
+    /// How long to continue displaying dropped resources after they have been
+    /// closed.
+    ///
+    /// This accepts the same values as `--retain-for`, and overrides it for
+    /// resources.
+    #[clap(long = "retain-resources-for")]
+    retain_resources_for: Option<RetainFor>,
+
+    /// How long to continue displaying dropped async ops after they have been
+    /// closed.
+    ///
+    /// This accepts the same values as `--retain-for`, and overrides it for
+    /// async ops.
+    #[clap(long = "retain-async-ops-for")]
+    retain_async_ops_for: Option<RetainFor>,
+
+    /// The maximum amount of memory, in MiB, to use for keeping previous
+    /// updates from the target.
+    ///
+    /// While paused, the `[` and `]` keys step backwards and forwards through
+    /// these updates. If this is 0, no previous updates are kept.
+    ///
+    /// [default: 16]
+    #[clap(long = "history-budget")]
+    history_budget: Option<usize>,
+
+    /// How long tasks must have been idle for before they are considered
+    /// stalled.
+    ///
+    /// The stalled tasks panel (opened with `S`) lists sets of stalled tasks
+    /// which are each waiting on a resource held by another task in the set,
+    /// which are probably deadlocked.
+    ///
+    /// [default: 10s]
+    #[clap(long = "stall-threshold", value_parser = humantime::parse_duration)]
+    stall_threshold: Option<Duration>,
+
+    /// Don't restore the sort order, column widths, and view from the
+    /// previous session, or save them when exiting.
+    ///
+    /// These are saved to `tokio-console/state.toml` in the user's state
+    /// directory (`$XDG_STATE_HOME` or `$HOME/.local/state` on Linux), or in
+    /// the local data directory on other platforms.
+    #[clap(long = "no-restore-state", action = ArgAction::SetTrue)]
+    no_restore_state: bool,
+
+    /// Ring the terminal bell when a watched task completes or is dropped.
+    ///
+    /// Tasks are watched by pressing `a` in the task list.
+    #[clap(long = "bell", action = ArgAction::SetTrue)]
+    pub(crate) bell: bool,
+
+    /// Latency budgets for tasks, by task name.
+    ///
+    /// These can only be set in the config file.
+    #[clap(skip)]
+    pub(crate) latency_budgets: Option<BTreeMap<String, LatencyBudget>>,
+
+    /// Warnings to show a desktop notification for the first time they're
+    /// detected, if desktop notifications are enabled.
+    ///
+    /// These can only be set in the config file.
+    #[clap(skip)]
+    pub(crate) notify: Option<BTreeSet<KnownWarnings>>,
+
+    /// Path prefixes to replace when looking for the source files tasks were
+    /// spawned in, for targets built on another machine or in a container.
+    ///
+    /// These can only be set in the config file.
+    #[clap(skip)]
+    pub(crate) source_remap: Option<BTreeMap<PathBuf, PathBuf>>,
+
+    /// Named targets, which can be connected to as `@name`.
+    ///
+    /// These can only be set in the config file.
+    #[clap(skip)]
+    targets: Option<BTreeMap<String, TargetConfig>>,
+
+    #[clap(flatten)]
+    pub(crate) connection_options: ConnectionOptions,
+
     /// An optional subcommand.
     ///
     /// If one of these is present, the console CLI will do something other than
@@ -157,6 +288,9 @@ pub(crate) enum KnownWarnings {
     NeverYielded,
     AutoBoxedFuture,
     LargeFuture,
+    LatencyBudget,
+    WakerLeak,
+    Panicked,
 }
 
 impl FromStr for KnownWarnings {
@@ -169,6 +303,9 @@ impl FromStr for KnownWarnings {
             "never-yielded" => Ok(KnownWarnings::NeverYielded),
             "auto-boxed-future" => Ok(KnownWarnings::AutoBoxedFuture),
             "large-future" => Ok(KnownWarnings::LargeFuture),
+            "latency-budget" => Ok(KnownWarnings::LatencyBudget),
+            "waker-leak" => Ok(KnownWarnings::WakerLeak),
+            "panicked" => Ok(KnownWarnings::Panicked),
             _ => Err(format!("unknown warning: {}", s)),
         }
     }
@@ -182,6 +319,9 @@ impl From<&KnownWarnings> for warnings::Linter<Task> {
             KnownWarnings::NeverYielded => warnings::Linter::new(warnings::NeverYielded::default()),
             KnownWarnings::AutoBoxedFuture => warnings::Linter::new(warnings::AutoBoxedFuture),
             KnownWarnings::LargeFuture => warnings::Linter::new(warnings::LargeFuture::default()),
+            KnownWarnings::LatencyBudget => warnings::Linter::new(warnings::LatencyBudgetExceeded),
+            KnownWarnings::WakerLeak => warnings::Linter::new(warnings::WakerLeak::default()),
+            KnownWarnings::Panicked => warnings::Linter::new(warnings::Panicked),
         }
     }
 }
@@ -194,6 +334,9 @@ impl fmt::Display for KnownWarnings {
             KnownWarnings::NeverYielded => write!(f, "never-yielded"),
             KnownWarnings::AutoBoxedFuture => write!(f, "auto-boxed-future"),
             KnownWarnings::LargeFuture => write!(f, "large-future"),
+            KnownWarnings::LatencyBudget => write!(f, "latency-budget"),
+            KnownWarnings::WakerLeak => write!(f, "waker-leak"),
+            KnownWarnings::Panicked => write!(f, "panicked"),
         }
     }
 }
@@ -206,6 +349,9 @@ impl KnownWarnings {
             KnownWarnings::NeverYielded,
             KnownWarnings::AutoBoxedFuture,
             KnownWarnings::LargeFuture,
+            KnownWarnings::LatencyBudget,
+            KnownWarnings::WakerLeak,
+            KnownWarnings::Panicked,
         ]
     }
 }
@@ -277,6 +423,175 @@ pub enum OptionalCmd {
         #[clap(value_enum)]
         shell: Shell,
     },
+
+    /// Print a table of tasks sorted by busy time, then exit
+    ///
+    /// Rather than starting the interactive UI, this connects to the target,
+    /// waits for a few updates, and prints the tasks it has seen to stdout.
+    /// This is useful for scripts and CI jobs.
+    Top {
+        /// The format to print the tasks in.
+        #[clap(long = "format", value_enum, default_value_t = TopFormat::Text)]
+        format: TopFormat,
+
+        /// The maximum number of tasks to print.
+        ///
+        /// If this is not set, all tasks are printed.
+        #[clap(long = "limit", short = 'n')]
+        limit: Option<usize>,
+
+        /// The number of updates to wait for before printing the tasks.
+        #[clap(long = "updates", default_value_t = 2)]
+        updates: usize,
+
+        /// How long to wait for those updates before giving up.
+        #[clap(long = "timeout", default_value = "10s", value_parser = humantime::parse_duration)]
+        timeout: Duration,
+    },
+
+    /// Run the configured warnings against a target, then exit
+    ///
+    /// Rather than starting the interactive UI, this connects to the target,
+    /// collects data for a while, and prints a JSON report of the warnings
+    /// triggered to stdout. The exit status is nonzero if any warnings were
+    /// triggered, so that async regressions can fail a CI job.
+    ///
+    /// The warnings checked are set by `--warn` and `--allow`.
+    Check {
+        /// How long to collect data for before reporting.
+        #[clap(long = "duration", default_value = "10s", value_parser = humantime::parse_duration)]
+        duration: Duration,
+    },
+
+    /// Run the console against built-in synthetic data
+    ///
+    /// No target process is needed: the demo cycles through a set of
+    /// representative scenarios, which is handy for exploring the interface
+    /// and its keybindings before instrumenting an application.
+    Demo,
+
+    /// Play back a recording made with `--record`
+    ///
+    /// The updates in the recording are shown at the pace they were
+    /// received. Task details aren't recorded, so they can't be shown.
+    Replay {
+        /// The recording to play back, in either format.
+        #[clap(value_hint = ValueHint::FilePath)]
+        path: PathBuf,
+    },
+
+    /// Convert a recording made with `--record` between formats, then exit
+    ///
+    /// The format of the input is detected from its contents. The output is
+    /// written as JSON lines if its file name ends in `.json` or `.jsonl`,
+    /// and in the protobuf format otherwise.
+    Convert {
+        /// The recording to convert.
+        #[clap(value_hint = ValueHint::FilePath)]
+        input: PathBuf,
+
+        /// The path to write the converted recording to.
+        #[clap(value_hint = ValueHint::FilePath)]
+        output: PathBuf,
+    },
+
+    /// Compare two recordings made with `--record`, then exit
+    ///
+    /// Tasks are matched between the recordings by name, or by spawn location
+    /// if they're unnamed. For each task name or location in both, the busy
+    /// time (as a share of the tasks' lifetimes), the share of wakes which
+    /// were self-wakes, and the longest poll are compared, and those which
+    /// got significantly worse are printed. The exit status is nonzero if any
+    /// did, so that performance regressions can fail a CI job.
+    Diff {
+        /// The recording from before the change.
+        #[clap(value_hint = ValueHint::FilePath)]
+        before: PathBuf,
+
+        /// The recording from after the change.
+        #[clap(value_hint = ValueHint::FilePath)]
+        after: PathBuf,
+
+        /// How much worse, as a percentage, a metric must get to be reported.
+        #[clap(long = "threshold", default_value_t = 20)]
+        threshold: u32,
+
+        /// The format to print the regressions in.
+        #[clap(long = "format", value_enum, default_value_t = DiffFormat::Text)]
+        format: DiffFormat,
+    },
+
+    /// List the named targets from the config file, then exit
+    ///
+    /// Each target can be connected to by passing its name, prefixed with
+    /// `@`, in place of the target address.
+    Targets,
+
+    /// Print the paths of the config files, log directory and state file,
+    /// then exit
+    ///
+    /// The paths are resolved after applying environment variables and
+    /// command line options, which is useful for finding out why a config
+    /// file isn't being picked up.
+    Paths {
+        /// The format to print the paths in.
+        #[clap(long = "format", value_enum, default_value_t = PathsFormat::Text)]
+        format: PathsFormat,
+    },
+}
+
+/// The target to connect to, given either as its address or as the name of
+/// a target from the config file.
+#[derive(Clone, Debug)]
+pub(crate) enum TargetAddr {
+    Addr(Uri),
+    Named(String),
+}
+
+/// Output formats for the `top` subcommand.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TopFormat {
+    /// A plain-text table.
+    Text,
+    /// A JSON array, with one object per task.
+    Json,
+}
+
+/// Output formats for the `diff` subcommand.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffFormat {
+    /// A plain-text table of the regressions.
+    Text,
+    /// A JSON report.
+    Json,
+}
+
+/// Output formats for the `paths` subcommand.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathsFormat {
+    /// One path per line, labeled with what it's used for.
+    Text,
+    /// A JSON object, with one field per path.
+    Json,
+}
+
+/// The paths that tokio-console reads its configuration from and writes its
+/// logs and state to, as printed by the `paths` subcommand.
+#[derive(Debug, Serialize)]
+struct ResolvedPaths {
+    /// The config files, in the order they're loaded. Options in later files
+    /// take precedence over earlier ones.
+    config_files: Vec<ResolvedConfigFile>,
+    log_directory: PathBuf,
+    /// `None` if restoring state is disabled, or there is no state directory
+    /// on this platform.
+    state_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+struct ResolvedConfigFile {
+    path: PathBuf,
+    exists: bool,
 }
 
 #[derive(Debug, Clone, Copy, Deserialize)]
@@ -341,6 +656,32 @@ pub struct ViewOptions {
     )]
     palette: Option<Palette>,
 
+    /// Which color theme to use.
+    ///
+    /// This can also be changed while the console is running by pressing
+    /// `T`.
+    ///
+    /// [default: dark]
+    #[clap(long = "theme", value_enum)]
+    theme: Option<Theme>,
+
+    /// How to display durations.
+    ///
+    /// By default, durations are displayed in whichever unit suits them best.
+    /// Choosing a fixed unit makes durations easier to compare at a glance,
+    /// and to copy into a spreadsheet. This can also be changed while the
+    /// console is running by pressing `u`.
+    ///
+    /// [default: human]
+    #[clap(long = "duration-format", value_enum)]
+    duration_format: Option<DurationFormat>,
+
+    /// Colors for tasks whose names match glob patterns.
+    ///
+    /// These can only be set in the config file.
+    #[clap(skip)]
+    highlights: Option<Vec<HighlightRule>>,
+
     #[clap(flatten)]
     toggles: ColorToggles,
 }
@@ -360,6 +701,51 @@ pub struct ColorToggles {
     color_terminated: Option<bool>,
 }
 
+/// Options for the connection to the instrumented application.
+#[derive(Clap, Debug, Clone, Default)]
+pub struct ConnectionOptions {
+    /// How long to wait for a connection to the target to be established
+    /// before giving up and trying again.
+    ///
+    /// If this is not set, connection attempts only fail once the operating
+    /// system gives up on them.
+    #[clap(long = "connect-timeout", value_parser = humantime::parse_duration)]
+    pub(crate) connect_timeout: Option<Duration>,
+
+    /// Enable TCP keepalive probes on the connection to the target, sent after
+    /// it has been idle for this long.
+    ///
+    /// This has no effect when connecting over a Unix domain socket.
+    #[clap(long = "tcp-keepalive", value_parser = humantime::parse_duration)]
+    pub(crate) tcp_keepalive: Option<Duration>,
+
+    /// Send HTTP/2 keepalive pings to the target at this interval.
+    ///
+    /// This can detect connections that have been silently dropped by a
+    /// firewall or flaky network, which would otherwise look like a target
+    /// with nothing to report.
+    #[clap(long = "http2-keepalive-interval", value_parser = humantime::parse_duration)]
+    pub(crate) http2_keepalive_interval: Option<Duration>,
+
+    /// How long to wait for a response to an HTTP/2 keepalive ping before
+    /// treating the connection as lost.
+    ///
+    /// This only has an effect if `--http2-keepalive-interval` is set.
+    ///
+    /// [default: 20s]
+    #[clap(long = "http2-keepalive-timeout", value_parser = humantime::parse_duration)]
+    pub(crate) http2_keepalive_timeout: Option<Duration>,
+
+    /// How often to ask the target to send updates.
+    ///
+    /// If this is not set, the target sends updates at its own publish
+    /// interval. Other consoles connected to the same target are still sent
+    /// updates at the interval they asked for. The target won't send updates
+    /// more often than every 100ms.
+    #[clap(long = "update-interval", value_parser = humantime::parse_duration)]
+    pub(crate) update_interval: Option<Duration>,
+}
+
 #[derive(Clone, Debug)]
 struct LogFilter(filter::Targets);
 
@@ -389,6 +775,30 @@ impl FromStr for LogFilter {
     }
 }
 
+/// A named target in the config file, which is either just its address, or a
+/// table with its address and any options to override for it.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+enum TargetConfig {
+    Addr(String),
+    Options(Box<TargetOptions>),
+}
+
+/// Options which can be overridden for a named target.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct TargetOptions {
+    addr: String,
+    /// Warnings to enable in addition to those enabled for every target.
+    #[serde(default)]
+    warnings: Vec<KnownWarnings>,
+    allow_warnings: Option<AllowedWarnings>,
+    duration_format: Option<DurationFormat>,
+    latency_budgets: Option<BTreeMap<String, LatencyBudget>>,
+    source_remap: Option<BTreeMap<PathBuf, PathBuf>>,
+    colors: Option<ColorsConfig>,
+}
+
 /// A struct used to parse the toml config file
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
@@ -399,8 +809,21 @@ struct ConfigFile {
     allow_warnings: Option<AllowedWarnings>,
     log_directory: Option<PathBuf>,
     retention: Option<RetainFor>,
+    task_retention: Option<RetainFor>,
+    resource_retention: Option<RetainFor>,
+    async_op_retention: Option<RetainFor>,
+    history_budget: Option<usize>,
+    stall_threshold: Option<String>,
+    restore_state: Option<bool>,
+    bell: Option<bool>,
+    duration_format: Option<DurationFormat>,
+    latency_budgets: Option<BTreeMap<String, LatencyBudget>>,
+    notify: Option<BTreeSet<KnownWarnings>>,
+    source_remap: Option<BTreeMap<PathBuf, PathBuf>>,
     charset: Option<CharsetConfig>,
     colors: Option<ColorsConfig>,
+    connection: Option<ConnectionConfig>,
+    targets: Option<BTreeMap<String, TargetConfig>>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -416,7 +839,32 @@ struct ColorsConfig {
     enabled: Option<bool>,
     truecolor: Option<bool>,
     palette: Option<Palette>,
+    theme: Option<Theme>,
     enable: Option<ColorToggles>,
+    highlights: Option<Vec<HighlightRule>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct ConnectionConfig {
+    connect_timeout: Option<String>,
+    tcp_keepalive: Option<String>,
+    http2_keepalive_interval: Option<String>,
+    http2_keepalive_timeout: Option<String>,
+    update_interval: Option<String>,
+}
+
+// === impl ConnectionConfig ===
+
+impl ConnectionConfig {
+    /// Returns `true` if any of the connection options are set.
+    fn is_set(&self) -> bool {
+        self.connect_timeout.is_some()
+            || self.tcp_keepalive.is_some()
+            || self.http2_keepalive_interval.is_some()
+            || self.http2_keepalive_timeout.is_some()
+            || self.update_interval.is_some()
+    }
 }
 
 // === impl Config ===
@@ -435,11 +883,104 @@ impl Config {
         let config = <Self as Clap>::parse();
         let config = match base {
             None => config,
-            Some(base) => base.merge_with(config),
+            Some(base) => base
+                .with_target_options(config.target_addr.as_ref())?
+                .merge_with(config),
         };
         Ok(config)
     }
 
+    /// Applies the options configured for the named target that will be
+    /// connected to, which is `target` if one was given on the command line.
+    fn with_target_options(self, target: Option<&TargetAddr>) -> color_eyre::Result<Self> {
+        let Some(TargetAddr::Named(name)) = target.or(self.target_addr.as_ref()) else {
+            return Ok(self);
+        };
+        let target = self.named_target(name)?;
+        let options = Config::try_from(target.to_config_file())?;
+        Ok(self.merge_with(options))
+    }
+
+    /// Returns the target with the given name from the config file.
+    fn named_target(&self, name: &str) -> color_eyre::Result<&TargetConfig> {
+        let targets = self.targets.as_ref();
+        targets
+            .and_then(|targets| targets.get(name))
+            .ok_or_else(|| {
+                let known = targets
+                    .map(|targets| targets.keys().map(String::as_str).collect::<Vec<_>>())
+                    .unwrap_or_default();
+                if known.is_empty() {
+                    color_eyre::eyre::eyre!(
+                        "no target named {:?}, as no targets are configured in console.toml",
+                        name
+                    )
+                } else {
+                    color_eyre::eyre::eyre!(
+                        "no target named {:?} in console.toml; known targets are: {}",
+                        name,
+                        known.join(", ")
+                    )
+                }
+            })
+    }
+
+    /// Returns a table of the named targets in the config file, and their
+    /// addresses.
+    pub fn targets_table(&self) -> String {
+        let Some(targets) = self.targets.as_ref().filter(|targets| !targets.is_empty()) else {
+            return "no targets are configured; add them to the [targets] table in console.toml"
+                .to_string();
+        };
+        let width = targets.keys().map(|name| name.len() + 1).max().unwrap_or(0);
+        targets
+            .iter()
+            .map(|(name, target)| format!("{:width$}  {}", format!("@{}", name), target.addr()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Returns the paths of the config files, log directory and state file,
+    /// formatted as `format`.
+    pub fn paths(&self, format: PathsFormat) -> color_eyre::Result<String> {
+        let paths = ResolvedPaths {
+            config_files: [ConfigPath::Home, ConfigPath::Current]
+                .into_iter()
+                .filter_map(ConfigPath::into_path)
+                .map(|path| ResolvedConfigFile {
+                    exists: path.is_file(),
+                    path,
+                })
+                .collect(),
+            log_directory: self
+                .log_directory
+                .clone()
+                .unwrap_or_else(default_log_directory),
+            state_file: self.state_file(),
+        };
+        match format {
+            PathsFormat::Json => {
+                serde_json::to_string_pretty(&paths).wrap_err("failed to serialize paths as JSON")
+            }
+            PathsFormat::Text => {
+                let mut lines = paths
+                    .config_files
+                    .iter()
+                    .map(|file| {
+                        let status = if file.exists { "found" } else { "not found" };
+                        format!("config file:    {} ({})", file.path.display(), status)
+                    })
+                    .collect::<Vec<_>>();
+                lines.push(format!("log directory:  {}", paths.log_directory.display()));
+                lines.push(match paths.state_file {
+                    Some(path) => format!("state file:     {}", path.display()),
+                    None => "state file:     (disabled)".to_string(),
+                });
+                Ok(lines.join("\n"))
+            }
+        }
+    }
+
     pub fn gen_config_file(self) -> color_eyre::Result<String> {
         let defaults = Self::default().merge_with(self);
         let config: ConfigFile = defaults.into();
@@ -516,16 +1057,52 @@ impl Config {
         Ok(())
     }
 
-    pub(crate) fn retain_for(&self) -> Option<Duration> {
-        self.retain_for.unwrap_or_default().0
+    /// Returns how long to keep each kind of item after it has been dropped.
+    pub(crate) fn retention(&self) -> Retention {
+        let retain_for = |kind: Option<RetainFor>| kind.or(self.retain_for).unwrap_or_default().0;
+        Retention {
+            tasks: retain_for(self.retain_tasks_for),
+            resources: retain_for(self.retain_resources_for),
+            async_ops: retain_for(self.retain_async_ops_for),
+        }
+    }
+
+    /// Returns how long tasks must be idle for to be considered stalled.
+    pub(crate) fn stall_threshold(&self) -> Duration {
+        self.stall_threshold.unwrap_or(stalls::DEFAULT_THRESHOLD)
+    }
+
+    /// Returns the memory budget for previous updates, in bytes.
+    pub(crate) fn history_budget(&self) -> usize {
+        self.history_budget.unwrap_or(DEFAULT_HISTORY_BUDGET_MIB) * 1024 * 1024
+    }
+
+    /// Returns the path of the file to restore and save view preferences
+    /// in, or `None` if they shouldn't be restored.
+    pub(crate) fn state_file(&self) -> Option<PathBuf> {
+        if self.no_restore_state {
+            return None;
+        }
+        let mut path = dirs::state_dir().or_else(dirs::data_local_dir)?;
+        path.push("tokio-console/state.toml");
+        Some(path)
     }
 
     pub(crate) fn target_addr(&self) -> color_eyre::Result<Uri> {
-        let target_addr = self
-            .target_addr
-            .as_ref()
-            .unwrap_or(&default_target_addr())
-            .clone();
+        let target_addr = match self.target_addr.as_ref() {
+            None => default_target_addr(),
+            Some(TargetAddr::Addr(addr)) => addr.clone(),
+            Some(TargetAddr::Named(name)) => {
+                let target = self.named_target(name)?;
+                target.addr().parse::<Uri>().wrap_err_with(|| {
+                    format!(
+                        "failed to parse address {:?} of target {:?} as URI",
+                        target.addr(),
+                        name
+                    )
+                })?
+            }
+        };
         match target_addr.scheme_str() {
             Some("file" | "http" | "https") => {}
             _ => {
@@ -559,16 +1136,32 @@ impl Config {
             self, builder =>
                 subcmd,
                 target_addr,
+                ssh,
+                record,
                 log_filter,
                 log_directory,
                 retain_for,
+                retain_tasks_for,
+                retain_resources_for,
+                retain_async_ops_for,
+                history_budget,
+                stall_threshold,
+                no_restore_state,
+                bell,
                 view_options.no_colors,
                 view_options.lang,
                 view_options.ascii_only,
                 view_options.truecolor,
                 view_options.palette,
+                view_options.theme,
+                view_options.duration_format,
                 view_options.toggles.color_durations,
                 view_options.toggles.color_terminated,
+                connection_options.connect_timeout,
+                connection_options.tcp_keepalive,
+                connection_options.http2_keepalive_interval,
+                connection_options.http2_keepalive_timeout,
+                connection_options.update_interval,
         }
 
         builder
@@ -584,6 +1177,8 @@ impl Config {
         Self {
             log_directory: other.log_directory.or(self.log_directory),
             target_addr: other.target_addr.or(self.target_addr),
+            ssh: other.ssh.or(self.ssh),
+            record: other.record.or(self.record),
             log_filter: other.log_filter.or(self.log_filter),
             warnings: {
                 let mut warns: Vec<KnownWarnings> = other.warnings;
@@ -599,7 +1194,43 @@ impl Config {
                 }
             },
             retain_for: other.retain_for.or(self.retain_for),
+            retain_tasks_for: other.retain_tasks_for.or(self.retain_tasks_for),
+            retain_resources_for: other.retain_resources_for.or(self.retain_resources_for),
+            retain_async_ops_for: other.retain_async_ops_for.or(self.retain_async_ops_for),
+            history_budget: other.history_budget.or(self.history_budget),
+            stall_threshold: other.stall_threshold.or(self.stall_threshold),
+            no_restore_state: other.no_restore_state || self.no_restore_state,
+            bell: other.bell || self.bell,
+            latency_budgets: match (self.latency_budgets, other.latency_budgets) {
+                (Some(mut budgets), Some(other)) => {
+                    budgets.extend(other);
+                    Some(budgets)
+                }
+                (budgets, other) => other.or(budgets),
+            },
+            notify: match (self.notify, other.notify) {
+                (Some(mut notify), Some(other)) => {
+                    notify.extend(other);
+                    Some(notify)
+                }
+                (notify, other) => other.or(notify),
+            },
+            source_remap: match (self.source_remap, other.source_remap) {
+                (Some(mut remap), Some(other)) => {
+                    remap.extend(other);
+                    Some(remap)
+                }
+                (remap, other) => other.or(remap),
+            },
+            targets: match (self.targets, other.targets) {
+                (Some(mut targets), Some(other)) => {
+                    targets.extend(other);
+                    Some(targets)
+                }
+                (targets, other) => other.or(targets),
+            },
             view_options: self.view_options.merge_with(other.view_options),
+            connection_options: self.connection_options.merge_with(other.connection_options),
             subcmd: other.subcmd.or(self.subcmd),
         }
     }
@@ -608,7 +1239,9 @@ impl Config {
 impl Default for Config {
     fn default() -> Self {
         Self {
-            target_addr: Some(default_target_addr()),
+            target_addr: Some(TargetAddr::Addr(default_target_addr())),
+            ssh: None,
+            record: None,
             log_filter: Some(LogFilter(
                 filter::Targets::new().with_default(filter::LevelFilter::OFF),
             )),
@@ -616,12 +1249,26 @@ impl Default for Config {
             allow_warnings: None,
             log_directory: Some(default_log_directory()),
             retain_for: Some(RetainFor::default()),
+            retain_tasks_for: None,
+            retain_resources_for: None,
+            retain_async_ops_for: None,
+            history_budget: Some(DEFAULT_HISTORY_BUDGET_MIB),
+            stall_threshold: Some(stalls::DEFAULT_THRESHOLD),
+            no_restore_state: false,
+            bell: false,
+            latency_budgets: None,
+            notify: None,
+            source_remap: None,
+            targets: None,
             view_options: ViewOptions::default(),
+            connection_options: ConnectionOptions::default(),
             subcmd: None,
         }
     }
 }
 
+const DEFAULT_HISTORY_BUDGET_MIB: usize = 16;
+
 fn default_target_addr() -> Uri {
     "http://127.0.0.1:6669"
         .parse::<Uri>()
@@ -692,6 +1339,18 @@ impl ViewOptions {
         self.toggles
     }
 
+    pub(crate) fn theme(&self) -> Theme {
+        self.theme.unwrap_or_default()
+    }
+
+    pub(crate) fn duration_format(&self) -> DurationFormat {
+        self.duration_format.unwrap_or_default()
+    }
+
+    pub(crate) fn highlights(&self) -> Vec<HighlightRule> {
+        self.highlights.clone().unwrap_or_default()
+    }
+
     fn merge_with(self, command_line: ViewOptions) -> Self {
         Self {
             no_colors: command_line.no_colors || self.no_colors,
@@ -699,6 +1358,16 @@ impl ViewOptions {
             ascii_only: command_line.ascii_only.or(self.ascii_only),
             truecolor: command_line.truecolor.or(self.truecolor),
             palette: command_line.palette.or(self.palette),
+            theme: command_line.theme.or(self.theme),
+            duration_format: command_line.duration_format.or(self.duration_format),
+            // The overriding rules are checked first.
+            highlights: match (command_line.highlights, self.highlights) {
+                (Some(mut highlights), Some(base)) => {
+                    highlights.extend(base);
+                    Some(highlights)
+                }
+                (highlights, base) => highlights.or(base),
+            },
             toggles: ColorToggles {
                 color_durations: command_line
                     .toggles
@@ -721,6 +1390,9 @@ impl Default for ViewOptions {
             ascii_only: Some(false),
             truecolor: Some(true),
             palette: Some(Palette::All),
+            theme: Some(Theme::Dark),
+            duration_format: Some(DurationFormat::Human),
+            highlights: None,
             toggles: ColorToggles {
                 color_durations: Some(true),
                 color_terminated: Some(true),
@@ -772,6 +1444,24 @@ impl ColorToggles {
     }
 }
 
+// === impl ConnectionOptions ===
+
+impl ConnectionOptions {
+    fn merge_with(self, command_line: ConnectionOptions) -> Self {
+        Self {
+            connect_timeout: command_line.connect_timeout.or(self.connect_timeout),
+            tcp_keepalive: command_line.tcp_keepalive.or(self.tcp_keepalive),
+            http2_keepalive_interval: command_line
+                .http2_keepalive_interval
+                .or(self.http2_keepalive_interval),
+            http2_keepalive_timeout: command_line
+                .http2_keepalive_timeout
+                .or(self.http2_keepalive_timeout),
+            update_interval: command_line.update_interval.or(self.update_interval),
+        }
+    }
+}
+
 // === impl ColorToggles ===
 
 impl ConfigFile {
@@ -790,11 +1480,11 @@ impl ConfigFile {
         Ok(config)
     }
 
-    fn target_addr(&self) -> color_eyre::Result<Option<Uri>> {
+    fn target_addr(&self) -> color_eyre::Result<Option<TargetAddr>> {
         let uri = self
             .default_target_addr
             .as_ref()
-            .map(|addr| addr.parse::<Uri>())
+            .map(|addr| addr.parse::<TargetAddr>())
             .transpose()
             .wrap_err_with(|| {
                 format!(
@@ -824,6 +1514,39 @@ impl ConfigFile {
         self.retention
     }
 
+    fn stall_threshold(&self) -> color_eyre::Result<Option<Duration>> {
+        self.stall_threshold
+            .as_ref()
+            .map(|value| humantime::parse_duration(value))
+            .transpose()
+            .wrap_err_with(|| format!("failed to parse stall_threshold {:?}", self.stall_threshold))
+    }
+
+    fn connection_options(&self) -> color_eyre::Result<ConnectionOptions> {
+        let parse = |name: &str, value: Option<&String>| {
+            value
+                .map(|value| humantime::parse_duration(value))
+                .transpose()
+                .wrap_err_with(|| format!("failed to parse connection.{} {:?}", name, value))
+        };
+        let Some(config) = self.connection.as_ref() else {
+            return Ok(ConnectionOptions::default());
+        };
+        Ok(ConnectionOptions {
+            connect_timeout: parse("connect_timeout", config.connect_timeout.as_ref())?,
+            tcp_keepalive: parse("tcp_keepalive", config.tcp_keepalive.as_ref())?,
+            http2_keepalive_interval: parse(
+                "http2_keepalive_interval",
+                config.http2_keepalive_interval.as_ref(),
+            )?,
+            http2_keepalive_timeout: parse(
+                "http2_keepalive_timeout",
+                config.http2_keepalive_timeout.as_ref(),
+            )?,
+            update_interval: parse("update_interval", config.update_interval.as_ref())?,
+        })
+    }
+
     fn no_colors(&self) -> Option<bool> {
         self.colors
             .as_ref()
@@ -845,6 +1568,17 @@ impl ConfigFile {
 
 impl From<Config> for ConfigFile {
     fn from(config: Config) -> Self {
+        let connection = ConnectionConfig {
+            connect_timeout: format_duration(config.connection_options.connect_timeout),
+            tcp_keepalive: format_duration(config.connection_options.tcp_keepalive),
+            http2_keepalive_interval: format_duration(
+                config.connection_options.http2_keepalive_interval,
+            ),
+            http2_keepalive_timeout: format_duration(
+                config.connection_options.http2_keepalive_timeout,
+            ),
+            update_interval: format_duration(config.connection_options.update_interval),
+        };
         Self {
             default_target_addr: config.target_addr.map(|addr| addr.to_string()),
             log: config.log_filter.map(|filter| filter.to_string()),
@@ -852,6 +1586,18 @@ impl From<Config> for ConfigFile {
             warnings: config.warnings,
             allow_warnings: config.allow_warnings,
             retention: config.retain_for,
+            task_retention: config.retain_tasks_for,
+            resource_retention: config.retain_resources_for,
+            async_op_retention: config.retain_async_ops_for,
+            history_budget: config.history_budget,
+            stall_threshold: format_duration(config.stall_threshold),
+            restore_state: Some(!config.no_restore_state),
+            bell: Some(config.bell),
+            duration_format: config.view_options.duration_format,
+            latency_budgets: config.latency_budgets,
+            notify: config.notify,
+            source_remap: config.source_remap,
+            targets: config.targets,
             charset: Some(CharsetConfig {
                 lang: config.view_options.lang,
                 ascii_only: config.view_options.ascii_only,
@@ -860,8 +1606,13 @@ impl From<Config> for ConfigFile {
                 enabled: Some(!config.view_options.no_colors),
                 truecolor: config.view_options.truecolor,
                 palette: config.view_options.palette,
+                theme: config.view_options.theme,
                 enable: Some(config.view_options.toggles),
+                highlights: config.view_options.highlights,
             }),
+            // Leave out the section entirely, rather than writing an empty
+            // one, if none of its options are set.
+            connection: connection.is_set().then_some(connection),
         }
     }
 }
@@ -872,11 +1623,24 @@ impl TryFrom<ConfigFile> for Config {
     fn try_from(mut value: ConfigFile) -> Result<Self, Self::Error> {
         Ok(Config {
             target_addr: value.target_addr()?,
+            ssh: None,
+            record: None,
             log_filter: value.log_filter()?,
             warnings: value.warnings.clone(),
             allow_warnings: value.allow_warnings.clone(),
             log_directory: value.log_directory.take(),
             retain_for: value.retain_for(),
+            retain_tasks_for: value.task_retention,
+            retain_resources_for: value.resource_retention,
+            retain_async_ops_for: value.async_op_retention,
+            history_budget: value.history_budget,
+            stall_threshold: value.stall_threshold()?,
+            no_restore_state: value.restore_state.is_some_and(Not::not),
+            bell: value.bell.unwrap_or(false),
+            latency_budgets: value.latency_budgets.take(),
+            notify: value.notify.take(),
+            source_remap: value.source_remap.take(),
+            targets: value.targets.take(),
             view_options: ViewOptions {
                 no_colors: value.no_colors().unwrap_or(false),
                 lang: value
@@ -886,16 +1650,95 @@ impl TryFrom<ConfigFile> for Config {
                 ascii_only: value.charset.as_ref().and_then(|config| config.ascii_only),
                 truecolor: value.colors.as_ref().and_then(|config| config.truecolor),
                 palette: value.colors.as_ref().and_then(|config| config.palette),
+                theme: value.colors.as_ref().and_then(|config| config.theme),
+                duration_format: value.duration_format,
+                highlights: value
+                    .colors
+                    .as_ref()
+                    .and_then(|config| config.highlights.clone()),
                 toggles: ColorToggles {
                     color_durations: value.color_durations(),
                     color_terminated: value.color_terminated(),
                 },
             },
+            connection_options: value.connection_options()?,
             subcmd: None,
         })
     }
 }
 
+// === impl TargetAddr ===
+
+impl FromStr for TargetAddr {
+    type Err = <Uri as FromStr>::Err;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix('@') {
+            Some(name) => Ok(TargetAddr::Named(name.to_string())),
+            None => s.parse().map(TargetAddr::Addr),
+        }
+    }
+}
+
+impl fmt::Display for TargetAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TargetAddr::Addr(addr) => fmt::Display::fmt(addr, f),
+            TargetAddr::Named(name) => write!(f, "@{}", name),
+        }
+    }
+}
+
+// === impl TargetConfig ===
+
+impl TargetConfig {
+    fn addr(&self) -> &str {
+        match self {
+            TargetConfig::Addr(addr) => addr,
+            TargetConfig::Options(options) => &options.addr,
+        }
+    }
+
+    /// Returns a config file containing only the options overridden for this
+    /// target.
+    fn to_config_file(&self) -> ConfigFile {
+        let options = match self {
+            TargetConfig::Addr(addr) => TargetOptions {
+                addr: addr.clone(),
+                ..Default::default()
+            },
+            TargetConfig::Options(options) => (**options).clone(),
+        };
+        ConfigFile {
+            default_target_addr: None,
+            log: None,
+            warnings: options.warnings,
+            allow_warnings: options.allow_warnings,
+            log_directory: None,
+            retention: None,
+            task_retention: None,
+            resource_retention: None,
+            async_op_retention: None,
+            history_budget: None,
+            stall_threshold: None,
+            restore_state: None,
+            bell: None,
+            duration_format: options.duration_format,
+            latency_budgets: options.latency_budgets,
+            notify: None,
+            source_remap: options.source_remap,
+            charset: None,
+            colors: options.colors,
+            connection: None,
+            targets: None,
+        }
+    }
+}
+
+fn format_duration(duration: Option<Duration>) -> Option<String> {
+    duration.map(|duration| humantime::format_duration(duration).to_string())
+}
+
 #[derive(Debug, Clone, Copy)]
 enum ConfigPath {
     Home,
@@ -983,6 +1826,107 @@ mod tests {
         }
     }
 
+    #[test]
+    fn retention_falls_back_to_retain_for() {
+        let config = Config::try_parse_from([
+            "tokio-console",
+            "--retain-for",
+            "1m",
+            "--retain-resources-for",
+            "none",
+        ])
+        .expect("should parse retention flags");
+        let retention = config.retention();
+        assert_eq!(retention.tasks, Some(Duration::from_secs(60)));
+        assert_eq!(retention.resources, None);
+        assert_eq!(retention.async_ops, Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn paths_apply_command_line_options() {
+        let config = Config::try_parse_from([
+            "tokio-console",
+            "--log-dir",
+            "/var/log/console",
+            "--no-restore-state",
+            "paths",
+            "--format",
+            "json",
+        ])
+        .expect("should parse paths subcommand");
+        let Some(OptionalCmd::Paths { format }) = config.subcmd else {
+            panic!("expected the paths subcommand, got {:?}", config.subcmd);
+        };
+        let paths: serde_json::Value =
+            serde_json::from_str(&config.paths(format).unwrap()).unwrap();
+        assert_eq!(paths["log_directory"], "/var/log/console");
+        assert_eq!(paths["state_file"], serde_json::Value::Null);
+        assert!(paths["config_files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|file| file["path"] == "./console.toml"));
+    }
+
+    #[test]
+    fn named_targets() {
+        let base = || {
+            let file: ConfigFile = toml::from_str(
+                r#"
+                warnings = ["self-wakes"]
+                duration_format = "human"
+
+                [targets]
+                local = "http://127.0.0.1:7000"
+                api = { addr = "http://10.0.0.3:6669", warnings = ["lost-waker"], duration_format = "milliseconds" }
+                "#,
+            )
+            .expect("should parse config file");
+            Config::try_from(file).expect("config file should be valid")
+        };
+
+        let command_line =
+            Config::try_parse_from(["tokio-console", "@api"]).expect("should parse target name");
+        let config = base()
+            .with_target_options(command_line.target_addr.as_ref())
+            .expect("target should exist")
+            .merge_with(command_line);
+        assert_eq!(
+            config.target_addr().unwrap(),
+            "http://10.0.0.3:6669".parse::<Uri>().unwrap()
+        );
+        assert_eq!(
+            config.view_options.duration_format(),
+            DurationFormat::Milliseconds
+        );
+        assert!(config.warnings.contains(&KnownWarnings::SelfWakes));
+        assert!(config.warnings.contains(&KnownWarnings::LostWaker));
+
+        // Options given on the command line still take precedence.
+        let command_line =
+            Config::try_parse_from(["tokio-console", "@api", "--duration-format", "nanos"])
+                .expect("should parse target name");
+        let config = base()
+            .with_target_options(command_line.target_addr.as_ref())
+            .expect("target should exist")
+            .merge_with(command_line);
+        assert_eq!(config.view_options.duration_format(), DurationFormat::Nanos);
+
+        let command_line =
+            Config::try_parse_from(["tokio-console", "@local"]).expect("should parse target name");
+        let config = base().merge_with(command_line);
+        assert_eq!(
+            config.target_addr().unwrap(),
+            "http://127.0.0.1:7000".parse::<Uri>().unwrap()
+        );
+
+        let command_line =
+            Config::try_parse_from(["tokio-console", "@nope"]).expect("should parse target name");
+        assert!(base()
+            .with_target_options(command_line.target_addr.as_ref())
+            .is_err());
+    }
+
     fn git_diff(path: impl AsRef<Path>) -> Result<(), String> {
         let output = process::Command::new("git")
             .arg("diff")
@@ -1,13 +1,22 @@
+use crate::config::ConnectionOptions;
+use crate::demo::Demo;
+use crate::recording;
 use console_api::instrument::StateRequest;
 use console_api::instrument::{
-    instrument_client::InstrumentClient, InstrumentRequest, PauseRequest, ResumeRequest,
-    State as InstrumentState, TaskDetailsRequest, Update,
+    instrument_client::InstrumentClient, HistogramSelection, InstrumentRequest, PauseRequest,
+    ResumeRequest, State as InstrumentState, TaskDetailsRequest, Temporality, Update,
 };
 use console_api::tasks::TaskDetails;
-use futures::stream::StreamExt;
+use console_state::clock;
+use futures::stream::{BoxStream, StreamExt};
 use futures::TryFutureExt;
 use hyper_util::rt::TokioIo;
-use std::{error::Error, time::Duration};
+use prost::Message as _;
+use std::{
+    error::Error,
+    path::Path,
+    time::{Duration, Instant, SystemTime},
+};
 #[cfg(unix)]
 use tokio::net::UnixStream;
 use tonic::{
@@ -18,7 +27,42 @@ use tonic::{
 #[derive(Debug)]
 pub struct Connection {
     target: Uri,
+    options: ConnectionOptions,
     state: State,
+    /// A state update to report before anything else, for targets that don't
+    /// stream their own state.
+    pending_state: Option<InstrumentState>,
+    /// Set if the last connection was lost because the target stopped
+    /// responding to HTTP/2 keepalive pings.
+    keepalive_timed_out: bool,
+    stats: ConnectionStats,
+    clock_skew: ClockSkew,
+}
+
+/// An estimate of how far the target's clock is ahead of the console's, from
+/// the timestamps of the updates it sends.
+///
+/// Everything the console displays is measured against the target's clock,
+/// but anything compared with the console's own clock, such as how long ago
+/// the last update was, is off by the skew between them.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct ClockSkew {
+    /// The smoothed offset of the target's clock, in seconds. This is
+    /// negative if the target's clock is behind.
+    offset: Option<f64>,
+}
+
+/// Counters describing the connection over the whole session, across
+/// reconnections.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ConnectionStats {
+    /// When the console started.
+    pub(crate) started_at: Instant,
+    /// How many times the console connected to the target.
+    pub(crate) connects: u64,
+    /// The total encoded size of the update and state messages received from
+    /// the target.
+    pub(crate) bytes_received: u64,
 }
 
 // clippy doesn't like that the "connected" case is much larger than the
@@ -31,12 +75,47 @@ pub struct Connection {
 enum State {
     Connected {
         client: InstrumentClient<Channel>,
+        /// The ID the target assigned to `update_stream`, which pause and
+        /// resume requests must refer to.
+        stream_id: u64,
+        /// The first update, which was received before the state stream was
+        /// started and hasn't been returned yet.
+        first_update: Option<Update>,
         update_stream: Box<Streaming<Update>>,
-        state_stream: Box<Streaming<InstrumentState>>,
+        /// The target's state stream, or `None` if the target is too old to
+        /// support one.
+        state_stream: Option<Box<Streaming<InstrumentState>>>,
     },
     Disconnected(Duration),
+    /// Playing back synthetic data from `tokio-console demo`, rather than
+    /// connecting to a real target.
+    Demo {
+        demo: Box<Demo>,
+        interval: tokio::time::Interval,
+        paused: bool,
+    },
+    /// Playing back a recording from `tokio-console replay`, rather than
+    /// connecting to a real target.
+    Replay(Box<Replay>),
+}
+
+#[derive(Debug)]
+struct Replay {
+    path: String,
+    reader: recording::Reader,
+    /// The next update to play back, once `next_at` is reached.
+    next: Option<Update>,
+    next_at: tokio::time::Instant,
+    /// When the last update that was played back was received.
+    last_received_at: Option<SystemTime>,
+    updates: u64,
+    paused: bool,
+    finished: bool,
 }
 
+/// A stream of details for a single task.
+pub(crate) type DetailsStream = BoxStream<'static, Result<TaskDetails, tonic::Status>>;
+
 #[allow(clippy::large_enum_variant)]
 pub(crate) enum Message {
     Update(Update),
@@ -44,10 +123,13 @@ pub(crate) enum Message {
 }
 
 macro_rules! with_client {
-    ($me:ident, $client:ident, $block:expr) => ({
+    ($me:ident, $client:ident, $block:expr) => {
+        with_client!($me, $client, _stream_id, $block)
+    };
+    ($me:ident, $client:ident, $stream_id:ident, $block:expr) => ({
         loop {
             match $me.state {
-                State::Connected { client: ref mut $client, .. } => {
+                State::Connected { client: ref mut $client, stream_id: $stream_id, .. } => {
                     match $block {
                         Ok(resp) => break Ok(resp),
                         // If the error is a `h2::Error`, that indicates
@@ -68,6 +150,9 @@ macro_rules! with_client {
                     }
                 }
                 State::Disconnected(_) => $me.connect().await,
+                State::Demo { .. } | State::Replay(_) => {
+                    unreachable!("demo and replay commands are handled without a client")
+                }
             }
         }
     })
@@ -75,13 +160,60 @@ macro_rules! with_client {
 
 impl Connection {
     const BACKOFF: Duration = Duration::from_millis(500);
-    pub fn new(target: Uri) -> Self {
+    pub fn new(target: Uri, options: ConnectionOptions) -> Self {
         Self {
             target,
+            options,
             state: State::Disconnected(Duration::from_secs(0)),
+            pending_state: None,
+            keepalive_timed_out: false,
+            stats: ConnectionStats::new(),
+            clock_skew: ClockSkew::default(),
+        }
+    }
+
+    /// Returns a connection that plays back synthetic data instead of
+    /// connecting to a target.
+    pub fn demo() -> Self {
+        Self {
+            target: Uri::from_static("demo://localhost"),
+            options: ConnectionOptions::default(),
+            keepalive_timed_out: false,
+            pending_state: None,
+            stats: ConnectionStats::new(),
+            clock_skew: ClockSkew::default(),
+            state: State::Demo {
+                demo: Box::new(Demo::new(SystemTime::now())),
+                interval: tokio::time::interval(crate::demo::TICK),
+                paused: false,
+            },
         }
     }
 
+    /// Returns a connection that plays back the recording at `path` instead
+    /// of connecting to a target.
+    pub fn replay(path: &Path) -> std::io::Result<Self> {
+        let reader = recording::Reader::open(path)?;
+        Ok(Self {
+            target: Uri::from_static("replay://localhost"),
+            options: ConnectionOptions::default(),
+            keepalive_timed_out: false,
+            pending_state: None,
+            stats: ConnectionStats::new(),
+            clock_skew: ClockSkew::default(),
+            state: State::Replay(Box::new(Replay {
+                path: path.display().to_string(),
+                reader,
+                next: None,
+                next_at: tokio::time::Instant::now(),
+                last_received_at: None,
+                updates: 0,
+                paused: false,
+                finished: false,
+            })),
+        })
+    }
+
     async fn connect(&mut self) {
         const MAX_BACKOFF: Duration = Duration::from_secs(5);
 
@@ -101,7 +233,7 @@ impl Connection {
                         }
                         let path = self.target.path().to_owned();
                         // Dummy endpoint is ignored by the connector.
-                        let endpoint = Endpoint::from_static("http://localhost");
+                        let endpoint = self.endpoint(Endpoint::from_static("http://localhost"));
                         endpoint
                             .connect_with_connector(tower::service_fn(move |_| {
                                 UnixStream::connect(path.clone()).map_ok(TokioIo::new)
@@ -113,29 +245,42 @@ impl Connection {
                         return Err("unix domain sockets are not supported on this platform".into());
                     }
                     _ => {
-                        let endpoint = Endpoint::from(self.target.clone());
+                        let endpoint = self.endpoint(Endpoint::from(self.target.clone()));
                         endpoint.connect().await?
                     }
                 };
                 let mut client = InstrumentClient::new(channel);
-                let update_request = tonic::Request::new(InstrumentRequest {});
-                let update_stream =
+                let update_request = tonic::Request::new(InstrumentRequest {
+                    update_interval: self
+                        .options
+                        .update_interval
+                        .and_then(|interval| interval.try_into().ok()),
+                });
+                let mut update_stream =
                     Box::new(client.watch_updates(update_request).await?.into_inner());
-                let state_request = tonic::Request::new(StateRequest {});
+                // The first update identifies the stream, so that the state
+                // stream reports on it, rather than on any other clients.
+                let first_update = update_stream
+                    .message()
+                    .await?
+                    .ok_or("update stream closed by server")?;
+                let stream_id = first_update.stream_id;
+                let state_request = tonic::Request::new(StateRequest { stream_id });
                 let state_stream = match client.watch_state(state_request).await {
-                    Ok(stream) => Box::new(stream.into_inner()),
-                    Err(e) => {
-                        if e.code() == tonic::Code::Unimplemented {
-                            tracing::error!(
-                                "The server at {} does not support state streaming. Please update the console-subscriber to v0.5.0 or later version.",
-                                self.target
-                            );
-                        }
-                        return Err(e.into());
+                    Ok(stream) => Some(Box::new(stream.into_inner())),
+                    Err(e) if e.code() == tonic::Code::Unimplemented => {
+                        tracing::warn!(
+                            "The server at {} does not support state streaming, so pausing can't be confirmed. Please update the console-subscriber to v0.5.0 or later version.",
+                            self.target
+                        );
+                        None
                     }
+                    Err(e) => return Err(e.into()),
                 };
                 Ok::<State, Box<dyn Error + Send + Sync>>(State::Connected {
                     client,
+                    stream_id,
+                    first_update: Some(first_update),
                     update_stream,
                     state_stream,
                 })
@@ -143,6 +288,15 @@ impl Connection {
             self.state = match try_connect.await {
                 Ok(connected) => {
                     tracing::debug!("connected successfully!");
+                    self.keepalive_timed_out = false;
+                    self.stats.connects += 1;
+                    // The target may have restarted somewhere else.
+                    self.clock_skew.reset();
+                    // A new update stream always starts out live, even if
+                    // the previous one was paused.
+                    self.pending_state = Some(InstrumentState {
+                        temporality: Temporality::Live as i32,
+                    });
                     connected
                 }
                 Err(error) => {
@@ -155,18 +309,32 @@ impl Connection {
     }
 
     pub async fn next_message(&mut self) -> Message {
+        if let Some(state) = self.pending_state.take() {
+            return Message::State(state);
+        }
         loop {
             match &mut self.state {
                 State::Connected {
+                    first_update,
                     update_stream,
                     state_stream,
                     ..
                 } => {
+                    if let Some(update) = first_update.take() {
+                        self.stats.bytes_received += update.encoded_len() as u64;
+                        self.clock_skew.record(&update, SystemTime::now());
+                        return Message::Update(update);
+                    }
                     tokio::select! { biased; // Always biased to update stream.
                         update = update_stream.next() => match update {
-                            Some(Ok(update)) => return Message::Update(update),
+                            Some(Ok(update)) => {
+                                self.stats.bytes_received += update.encoded_len() as u64;
+                                self.clock_skew.record(&update, SystemTime::now());
+                                return Message::Update(update);
+                            }
                             Some(Err(status)) => {
                                 tracing::warn!(%status, "error from update stream");
+                                self.keepalive_timed_out = is_keepalive_timeout(&status);
                                 self.state = State::Disconnected(Self::BACKOFF);
                             }
                             None => {
@@ -174,10 +342,14 @@ impl Connection {
                                 self.state = State::Disconnected(Self::BACKOFF);
                             }
                         },
-                        state = state_stream.next() => match state {
-                            Some(Ok(state)) => return Message::State(state),
+                        state = next_state(state_stream) => match state {
+                            Some(Ok(state)) => {
+                                self.stats.bytes_received += state.encoded_len() as u64;
+                                return Message::State(state);
+                            }
                             Some(Err(status)) => {
                                 tracing::warn!(%status, "error from state stream");
+                                self.keepalive_timed_out = is_keepalive_timeout(&status);
                                 self.state = State::Disconnected(Self::BACKOFF);
                             }
                             None => {
@@ -188,72 +360,408 @@ impl Connection {
                     }
                 }
                 State::Disconnected(_) => self.connect().await,
+                State::Demo {
+                    demo,
+                    interval,
+                    paused,
+                } => {
+                    if *paused {
+                        // Nothing happens while the demo is paused, until a
+                        // resume command changes the state.
+                        return futures::future::pending().await;
+                    }
+                    interval.tick().await;
+                    return Message::Update(demo.next_update());
+                }
+                State::Replay(replay) => {
+                    if replay.paused || replay.finished {
+                        return futures::future::pending().await;
+                    }
+                    if replay.next.is_none() {
+                        replay.read_next();
+                        continue;
+                    }
+                    // The next update is kept until it's due, so that it
+                    // isn't lost if this future is dropped while waiting.
+                    tokio::time::sleep_until(replay.next_at).await;
+                    if let Some(update) = replay.next.take() {
+                        replay.updates += 1;
+                        self.stats.bytes_received += update.encoded_len() as u64;
+                        return Message::Update(update);
+                    }
+                }
             }
         }
     }
 
+    /// Applies the configured timeouts and keepalive settings to `endpoint`.
+    fn endpoint(&self, mut endpoint: Endpoint) -> Endpoint {
+        let options = &self.options;
+        if let Some(timeout) = options.connect_timeout {
+            endpoint = endpoint.connect_timeout(timeout);
+        }
+        if let Some(interval) = options.http2_keepalive_interval {
+            // Keep pinging even when there are no requests in flight: the
+            // update streams are long-lived, and may be quiet for a while.
+            endpoint = endpoint
+                .http2_keep_alive_interval(interval)
+                .keep_alive_while_idle(true);
+        }
+        if let Some(timeout) = options.http2_keepalive_timeout {
+            endpoint = endpoint.keep_alive_timeout(timeout);
+        }
+        endpoint.tcp_keepalive(options.tcp_keepalive)
+    }
+
     #[tracing::instrument(skip(self))]
     pub async fn watch_details(
         &mut self,
         task_id: u64,
-    ) -> Result<Streaming<TaskDetails>, tonic::Status> {
+        histograms: HistogramSelection,
+    ) -> Result<DetailsStream, tonic::Status> {
+        if let State::Demo { demo, .. } = &self.state {
+            return demo
+                .task_details(task_id)
+                .map(|details| details.map(Ok).boxed())
+                .ok_or_else(|| tonic::Status::not_found("no such task"));
+        }
+        if let State::Replay(_) = self.state {
+            return Err(tonic::Status::unavailable(
+                "task details aren't included in recordings",
+            ));
+        }
+
         with_client!(self, client, {
             let request = tonic::Request::new(TaskDetailsRequest {
                 id: Some(task_id.into()),
+                histograms: Some(histograms),
             });
             client.watch_task_details(request).await
         })
-        .map(|watch| watch.into_inner())
+        .map(|watch| watch.into_inner().boxed())
     }
 
     #[tracing::instrument(skip(self))]
     pub async fn pause(&mut self) {
-        let res = with_client!(self, client, {
-            let request = tonic::Request::new(PauseRequest {});
+        if self.set_playback_paused(true) {
+            return;
+        }
+
+        let res = with_client!(self, client, stream_id, {
+            let request = tonic::Request::new(PauseRequest { stream_id });
             client.pause(request).await
         });
 
-        if let Err(e) = res {
-            tracing::error!(error = %e, "rpc error sending pause command");
+        match res {
+            Ok(_) => self.acknowledge(Temporality::Paused),
+            Err(e) => tracing::error!(error = %e, "rpc error sending pause command"),
         }
     }
 
     #[tracing::instrument(skip(self))]
     pub async fn resume(&mut self) {
-        let res = with_client!(self, client, {
-            let request = tonic::Request::new(ResumeRequest {});
+        if self.set_playback_paused(false) {
+            return;
+        }
+
+        let res = with_client!(self, client, stream_id, {
+            let request = tonic::Request::new(ResumeRequest { stream_id });
             client.resume(request).await
         });
 
-        if let Err(e) = res {
-            tracing::error!(error = %e, "rpc error sending resume command");
+        match res {
+            Ok(_) => self.acknowledge(Temporality::Live),
+            Err(e) => tracing::error!(error = %e, "rpc error sending resume command"),
+        }
+    }
+
+    /// Pauses or resumes the demo or replay, returning `false` if this is a
+    /// connection to a real target.
+    fn set_playback_paused(&mut self, pause: bool) -> bool {
+        match &mut self.state {
+            State::Demo { paused, .. } => *paused = pause,
+            State::Replay(replay) => {
+                replay.paused = pause;
+                if !pause {
+                    // Carry on from where the replay was paused, rather than
+                    // catching up on the time spent paused.
+                    replay.next_at = tokio::time::Instant::now();
+                }
+            }
+            _ => return false,
+        }
+        self.pending_state = Some(InstrumentState {
+            temporality: if pause {
+                Temporality::Paused
+            } else {
+                Temporality::Live
+            } as i32,
+        });
+        true
+    }
+
+    /// Reports that the target accepted a pause or resume request, if it won't
+    /// report its new state itself.
+    ///
+    /// Targets with a state stream are left to confirm the change, so that
+    /// the console only shows the temporality the target actually has.
+    fn acknowledge(&mut self, temporality: Temporality) {
+        if let State::Connected {
+            state_stream: None, ..
+        } = self.state
+        {
+            self.pending_state = Some(InstrumentState {
+                temporality: temporality as i32,
+            });
         }
     }
 
-    pub fn render(&self, styles: &crate::view::Styles) -> ratatui::text::Line {
+    pub(crate) fn stats(&self) -> &ConnectionStats {
+        &self.stats
+    }
+
+    pub fn render(&self, styles: &crate::view::Styles) -> ratatui::text::Line<'_> {
+        use crate::view::Role;
         use ratatui::{
-            style::{Color, Modifier},
+            style::Modifier,
             text::{Line, Span},
         };
         let state = match self.state {
+            State::Demo { ref demo, .. } => {
+                return Line::from(vec![
+                    Span::raw("demo: "),
+                    Span::raw(demo.scenario()),
+                    Span::raw(" "),
+                    Span::styled(
+                        "(SYNTHETIC DATA)",
+                        styles.role(Role::Info).add_modifier(Modifier::BOLD),
+                    ),
+                ])
+            }
+            State::Replay(ref replay) => {
+                let status = if replay.finished {
+                    Span::styled(
+                        "(REPLAY FINISHED)",
+                        styles.role(Role::Caution).add_modifier(Modifier::BOLD),
+                    )
+                } else {
+                    Span::styled(
+                        "(RECORDED DATA)",
+                        styles.role(Role::Info).add_modifier(Modifier::BOLD),
+                    )
+                };
+                return Line::from(vec![
+                    Span::raw("replay: "),
+                    Span::raw(replay.path.clone()),
+                    Span::raw(format!(" ({} updates) ", replay.updates)),
+                    status,
+                ]);
+            }
             State::Connected { .. } => Span::styled(
                 "(CONNECTED)",
-                styles.fg(Color::Green).add_modifier(Modifier::BOLD),
+                styles.role(Role::Good).add_modifier(Modifier::BOLD),
             ),
             State::Disconnected(d) if d == Duration::from_secs(0) => Span::styled(
                 "(CONNECTING)",
-                styles.fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                styles.role(Role::Caution).add_modifier(Modifier::BOLD),
+            ),
+            State::Disconnected(d) if self.keepalive_timed_out => Span::styled(
+                format!("(KEEPALIVE TIMED OUT, RECONNECTING IN {:?})", d),
+                styles.role(Role::Bad).add_modifier(Modifier::BOLD),
             ),
             State::Disconnected(d) => Span::styled(
                 format!("(RECONNECTING IN {:?})", d),
-                styles.fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                styles.role(Role::Caution).add_modifier(Modifier::BOLD),
             ),
         };
-        Line::from(vec![
+        let mut line = Line::from(vec![
             Span::raw("connection: "),
             Span::raw(self.target.to_string()),
             Span::raw(" "),
             state,
-        ])
+        ]);
+        if let Some(offset) = self.clock_skew.large_offset() {
+            let direction = if offset > 0.0 { "AHEAD" } else { "BEHIND" };
+            line.push_span(Span::styled(
+                format!(" (TARGET CLOCK {:.1}s {})", offset.abs(), direction),
+                styles.role(Role::Caution),
+            ));
+        }
+        line
+    }
+}
+
+impl Replay {
+    /// The longest pause between two updates that's kept when playing back a
+    /// recording, so that gaps in it (such as while the recording console was
+    /// disconnected) don't leave the replay stuck.
+    const MAX_DELAY: Duration = Duration::from_secs(5);
+
+    /// Reads the next update from the recording, and schedules it to be
+    /// played back after the same delay it was received after.
+    fn read_next(&mut self) {
+        match self.reader.next() {
+            Some(Ok(recorded)) => {
+                let received_at = recorded
+                    .received_at
+                    .and_then(|time| SystemTime::try_from(time).ok());
+                if let (Some(last), Some(received_at)) = (self.last_received_at, received_at) {
+                    let delay = received_at
+                        .duration_since(last)
+                        .unwrap_or_default()
+                        .min(Self::MAX_DELAY);
+                    self.next_at += delay;
+                }
+                self.last_received_at = received_at.or(self.last_received_at);
+                self.next = Some(recorded.update.unwrap_or_default());
+            }
+            Some(Err(error)) => {
+                tracing::warn!(%error, "error reading recording");
+                self.finished = true;
+            }
+            None => {
+                tracing::info!(updates = self.updates, "replay finished");
+                self.finished = true;
+            }
+        }
+    }
+}
+
+impl ClockSkew {
+    /// How far apart the clocks can be before the skew is warned about.
+    const WARNING_THRESHOLD: f64 = 1.0;
+
+    /// How much each new measurement moves the estimate, so that one slow
+    /// update doesn't make it jump.
+    const SMOOTHING: f64 = 0.2;
+
+    /// Records the skew between the time the target built `update` and the
+    /// time the console received it.
+    ///
+    /// This includes the time the update spent in transit, so it's only an
+    /// estimate, but that's normally far smaller than a skew worth noticing.
+    fn record(&mut self, update: &Update, received_at: SystemTime) {
+        let Some(sent_at) = update.now.and_then(|now| SystemTime::try_from(now).ok()) else {
+            return;
+        };
+        let offset = match sent_at.duration_since(received_at) {
+            Ok(ahead) => ahead.as_secs_f64(),
+            Err(behind) => -behind.duration().as_secs_f64(),
+        };
+        let offset = match self.offset {
+            Some(prev) => prev + (offset - prev) * Self::SMOOTHING,
+            None => offset,
+        };
+        self.offset = Some(offset);
+        clock::set_target_offset(offset);
+    }
+
+    fn reset(&mut self) {
+        self.offset = None;
+        clock::set_target_offset(0.0);
+    }
+
+    /// Returns the estimated offset of the target's clock, in seconds, if
+    /// it's large enough to make durations measured against the console's
+    /// clock misleading.
+    pub(crate) fn large_offset(&self) -> Option<f64> {
+        self.offset
+            .filter(|offset| offset.abs() >= Self::WARNING_THRESHOLD)
+    }
+}
+
+impl ConnectionStats {
+    fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            connects: 0,
+            bytes_received: 0,
+        }
+    }
+
+    /// Returns how many times the console reconnected after losing its
+    /// connection to the target.
+    pub(crate) fn reconnects(&self) -> u64 {
+        self.connects.saturating_sub(1)
+    }
+}
+
+/// Returns the next message from `state_stream`, or never completes if the
+/// target doesn't have one.
+async fn next_state(
+    state_stream: &mut Option<Box<Streaming<InstrumentState>>>,
+) -> Option<Result<InstrumentState, tonic::Status>> {
+    match state_stream {
+        Some(stream) => stream.next().await,
+        None => futures::future::pending().await,
+    }
+}
+
+/// Returns `true` if `status` was caused by the target failing to respond to
+/// an HTTP/2 keepalive ping.
+fn is_keepalive_timeout(status: &tonic::Status) -> bool {
+    // A keepalive timeout is a `hyper::Error` caused by hyper's
+    // `KeepAliveTimedOut` error, rather than by an `h2::Error` from the
+    // connection itself. hyper doesn't export that type, so it can't be
+    // downcast to, but its `Debug` output is the type's name.
+    let mut source = status.source();
+    while let Some(error) = source {
+        if let Some(error) = error.downcast_ref::<hyper::Error>() {
+            return error.source().is_some_and(|cause| {
+                !cause.is::<h2::Error>() && format!("{:?}", cause) == "KeepAliveTimedOut"
+            });
+        }
+        source = error.source();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keepalive_timeout_is_not_matched_by_message() {
+        let status = tonic::Status::unavailable("keep-alive timed out");
+        assert!(!is_keepalive_timeout(&status));
+        let status = tonic::Status::from_error(Box::new(h2::Error::from(h2::Reason::CANCEL)));
+        assert!(!is_keepalive_timeout(&status));
+    }
+
+    #[test]
+    fn clock_skew_is_smoothed() {
+        let received_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let update = |offset_secs: i64| Update {
+            now: Some(
+                if offset_secs >= 0 {
+                    received_at + Duration::from_secs(offset_secs as u64)
+                } else {
+                    received_at - Duration::from_secs(offset_secs.unsigned_abs())
+                }
+                .into(),
+            ),
+            ..Default::default()
+        };
+
+        let mut skew = ClockSkew::default();
+        skew.record(&Update::default(), received_at);
+        assert_eq!(skew.offset, None);
+
+        skew.record(&update(0), received_at);
+        assert_eq!(skew.large_offset(), None);
+
+        // A single late update doesn't trip the warning...
+        skew.record(&update(-2), received_at);
+        assert_eq!(skew.large_offset(), None);
+
+        // ...but a consistent skew does.
+        for _ in 0..10 {
+            skew.record(&update(-2), received_at);
+        }
+        let offset = skew.large_offset().expect("skew should be large");
+        assert!((offset + 2.0).abs() < 0.25, "offset was {offset}");
+
+        skew.reset();
+        assert_eq!(skew.large_offset(), None);
     }
 }
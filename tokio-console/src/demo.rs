@@ -0,0 +1,837 @@
+//! A synthetic data source for `tokio-console demo`.
+//!
+//! The demo generates a deterministic stream of instrumentation updates that
+//! cycles through a handful of representative scenarios (a steady workload, a
+//! spawn storm, and tasks that trigger each of the console's lints), so that
+//! the UI can be explored without instrumenting an application first.
+use console_api as proto;
+use futures::stream::{self, Stream};
+use hdrhistogram::serialization::{Serializer, V2Serializer};
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime},
+};
+
+/// The amount of (synthetic) time that passes between two updates.
+pub(crate) const TICK: Duration = Duration::from_secs(1);
+
+/// The number of ticks that each scenario runs for.
+const SCENARIO_TICKS: u64 = 15;
+
+/// How often the synthetic timer fires.
+const TIMER_PERIOD: Duration = Duration::from_secs(3);
+
+/// The number of worker threads the synthetic runtime claims to have.
+const WORKER_THREADS: u64 = 4;
+
+/// The capacity of the synthetic subscriber's event buffer, which is the real
+/// subscriber's default.
+const EVENT_BUFFER_CAPACITY: u64 = 1024 * 100;
+
+const TASK_META: u64 = 1;
+const SEMAPHORE_META: u64 = 2;
+const TIMER_META: u64 = 3;
+const ASYNC_OP_META: u64 = 4;
+
+const SEED: u64 = 0x5EED_C0DE_7001_CAFE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scenario {
+    Steady,
+    SpawnStorm,
+    SelfWakes,
+    LostWaker,
+    NeverYields,
+}
+
+impl Scenario {
+    const ALL: [Scenario; 5] = [
+        Scenario::Steady,
+        Scenario::SpawnStorm,
+        Scenario::SelfWakes,
+        Scenario::LostWaker,
+        Scenario::NeverYields,
+    ];
+
+    fn at(tick: u64) -> Self {
+        Self::ALL[((tick / SCENARIO_TICKS) % Self::ALL.len() as u64) as usize]
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Scenario::Steady => "steady workload",
+            Scenario::SpawnStorm => "spawn storm",
+            Scenario::SelfWakes => "self-waking task",
+            Scenario::LostWaker => "lost waker",
+            Scenario::NeverYields => "task that never yields",
+        }
+    }
+}
+
+/// Generates synthetic instrumentation updates.
+///
+/// Two `Demo`s created with the same start time produce identical sequences
+/// of updates.
+#[derive(Debug)]
+pub(crate) struct Demo {
+    start: SystemTime,
+    tick: u64,
+    rng: Rng,
+    next_id: u64,
+    tasks: Vec<DemoTask>,
+    /// The mean poll duration of every task that was ever spawned, used to
+    /// generate poll time histograms for the task details view.
+    poll_times: HashMap<u64, Duration>,
+    semaphore: Option<u64>,
+    timer: Option<u64>,
+    acquire: Option<AsyncOpStats>,
+}
+
+#[derive(Debug)]
+struct DemoTask {
+    id: u64,
+    name: String,
+    kind: &'static str,
+    runtime: &'static str,
+    location: (&'static str, u32),
+    behavior: Behavior,
+    /// The size of the task's future, and its size before auto-boxing.
+    sizes: Option<(u64, u64)>,
+    /// The tick at which the task will complete, if it ever does.
+    drop_at: Option<u64>,
+    is_new: bool,
+    stats: PollStats,
+    wakes: u64,
+    self_wakes: u64,
+    waker_clones: u64,
+    waker_drops: u64,
+    last_wake: Option<SystemTime>,
+    scheduled: Duration,
+    created_at: SystemTime,
+    dropped_at: Option<SystemTime>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Behavior {
+    /// Polled a handful of times every tick.
+    Worker {
+        polls: (u64, u64),
+        poll_time: Duration,
+    },
+    /// Wakes itself up over and over again.
+    SelfWaking,
+    /// Polled once, then drops its waker without completing.
+    LostWaker,
+    /// Polled once, and that poll never returns.
+    NeverYields,
+    /// A `spawn_blocking` task, which runs for its whole lifetime.
+    Blocking,
+}
+
+#[derive(Debug, Default)]
+struct PollStats {
+    polls: u64,
+    busy: Duration,
+    first_poll: Option<SystemTime>,
+    last_poll_started: Option<SystemTime>,
+    last_poll_ended: Option<SystemTime>,
+}
+
+#[derive(Debug)]
+struct AsyncOpStats {
+    id: u64,
+    task_id: u64,
+    is_new: bool,
+    stats: PollStats,
+    created_at: SystemTime,
+}
+
+/// A tiny xorshift PRNG, so that the demo is reproducible without pulling
+/// in a dependency.
+#[derive(Debug)]
+struct Rng(u64);
+
+// === impl Demo ===
+
+impl Demo {
+    pub(crate) fn new(start: SystemTime) -> Self {
+        Self {
+            start,
+            tick: 0,
+            rng: Rng(SEED),
+            next_id: 1,
+            tasks: Vec::new(),
+            poll_times: HashMap::new(),
+            semaphore: None,
+            timer: None,
+            acquire: None,
+        }
+    }
+
+    /// Returns a short description of the scenario that is currently playing.
+    pub(crate) fn scenario(&self) -> &'static str {
+        Scenario::at(self.tick.saturating_sub(1)).name()
+    }
+
+    /// Advances the synthetic clock by one [`TICK`] and returns the update
+    /// describing everything that happened in the meantime.
+    pub(crate) fn next_update(&mut self) -> proto::instrument::Update {
+        let now = self.start + TICK * self.tick as u32;
+        let new_metadata = (self.tick == 0).then(metadata);
+        if self.tick == 0 {
+            self.spawn_background(now);
+        }
+        self.run_scenario(now);
+
+        let mut busy = Duration::ZERO;
+        let mut new_tasks = Vec::new();
+        let mut task_stats = HashMap::new();
+        for task in &mut self.tasks {
+            busy += task.advance(self.tick, now, &mut self.rng);
+            if task.is_new {
+                task.is_new = false;
+                new_tasks.push(task.to_proto());
+            }
+            task_stats.insert(task.id, task.stats_to_proto());
+        }
+        self.tasks.retain(|task| task.dropped_at.is_none());
+
+        let (resource_update, async_op_update) = self.resources(now);
+        self.tick += 1;
+        // Pretend that each new task was buffered as a single event.
+        let new_event_count = new_tasks.len() as u64;
+
+        proto::instrument::Update {
+            now: Some(now.into()),
+            task_update: Some(proto::tasks::TaskUpdate {
+                new_tasks,
+                stats_update: task_stats,
+                dropped_events: 0,
+            }),
+            resource_update: Some(resource_update),
+            async_op_update: Some(async_op_update),
+            new_metadata,
+            runtime_stats: Some(proto::instrument::RuntimeStats {
+                busy_time: busy.try_into().ok(),
+                interval: TICK.try_into().ok(),
+                worker_threads: WORKER_THREADS,
+            }),
+            subscriber_stats: Some(proto::instrument::SubscriberStats {
+                max_event_buffer_len: new_event_count,
+                event_buffer_capacity: EVENT_BUFFER_CAPACITY,
+                max_aggregation_time: Duration::from_micros(50 + 10 * new_event_count)
+                    .try_into()
+                    .ok(),
+                ..Default::default()
+            }),
+            stream_id: 0,
+            sequence: self.tick,
+            lagging: false,
+            truncated: false,
+        }
+    }
+
+    /// Returns a stream of task details for the task with the given ID.
+    ///
+    /// The poll times histogram is generated independently of the main update
+    /// stream, from the mean poll duration of the task.
+    pub(crate) fn task_details(
+        &self,
+        task_id: u64,
+    ) -> Option<impl Stream<Item = proto::tasks::TaskDetails> + Send + 'static> {
+        let poll_time = *self.poll_times.get(&task_id)?;
+        let histogram = hdrhistogram::Histogram::<u64>::new_with_max(
+            Duration::from_secs(1).as_nanos() as u64,
+            2,
+        )
+        .expect("histogram bounds are valid");
+        let state = (
+            histogram,
+            Rng(SEED ^ task_id),
+            None::<tokio::time::Interval>,
+        );
+        Some(stream::unfold(
+            state,
+            move |(mut histogram, mut rng, interval)| async move {
+                let mut interval = interval.unwrap_or_else(|| tokio::time::interval(TICK));
+                interval.tick().await;
+
+                let max = histogram.high();
+                for _ in 0..rng.range(20, 100) {
+                    let nanos = rng.jitter(poll_time).as_nanos() as u64;
+                    histogram.saturating_record(nanos.clamp(1, max));
+                }
+                let mut raw_histogram = Vec::new();
+                V2Serializer::new()
+                    .serialize(&histogram, &mut raw_histogram)
+                    .expect("histogram failed to serialize");
+                let details = proto::tasks::TaskDetails {
+                    task_id: Some(task_id.into()),
+                    now: Some(SystemTime::now().into()),
+                    poll_times_histogram: Some(
+                        proto::tasks::task_details::PollTimesHistogram::Histogram(
+                            proto::tasks::DurationHistogram {
+                                raw_histogram,
+                                max_value: max,
+                                high_outliers: 0,
+                                highest_outlier: None,
+                            },
+                        ),
+                    ),
+                    scheduled_times_histogram: None,
+                    recent_poll_times_histogram: None,
+                    wake_sites: Vec::new(),
+                    wake_history: Vec::new(),
+                    thread_polls: Vec::new(),
+                    waiting_on: Vec::new(),
+                };
+                Some((details, (histogram, rng, Some(interval))))
+            },
+        ))
+    }
+
+    fn spawn_background(&mut self, now: SystemTime) {
+        let accept = self.spawn(
+            now,
+            "accept-loop",
+            "io",
+            ("src/server.rs", 42),
+            Behavior::Worker {
+                polls: (2, 6),
+                poll_time: Duration::from_micros(40),
+            },
+        );
+        for i in 0..WORKER_THREADS {
+            let poll_time = Duration::from_micros(150 * (i + 1));
+            self.spawn(
+                now,
+                format!("worker-{}", i),
+                "compute",
+                ("src/worker.rs", 17),
+                Behavior::Worker {
+                    polls: (10, 30),
+                    poll_time,
+                },
+            );
+        }
+        let big = self.spawn(
+            now,
+            "big-future",
+            "compute",
+            ("src/cache.rs", 88),
+            Behavior::Worker {
+                polls: (0, 2),
+                poll_time: Duration::from_micros(500),
+            },
+        );
+        self.tasks[big].sizes = Some((8, 24_576));
+
+        self.semaphore = Some(self.next_id());
+        self.timer = Some(self.next_id());
+        let acquire = AsyncOpStats {
+            id: self.next_id(),
+            task_id: self.tasks[accept].id,
+            is_new: true,
+            stats: PollStats::default(),
+            created_at: now,
+        };
+        self.acquire = Some(acquire);
+    }
+
+    fn run_scenario(&mut self, now: SystemTime) {
+        let scenario = Scenario::at(self.tick);
+        let starting = self.tick % SCENARIO_TICKS == 0;
+        let ends_at = (self.tick / SCENARIO_TICKS + 1) * SCENARIO_TICKS;
+
+        let requests = match scenario {
+            Scenario::SpawnStorm => self.rng.range(20, 60),
+            _ => self.rng.range(1, 4),
+        };
+        for _ in 0..requests {
+            let lifetime = self.rng.range(1, 4);
+            let poll_time = Duration::from_micros(self.rng.range(20, 400));
+            let task = self.spawn(
+                now,
+                "request",
+                "io",
+                ("src/server.rs", 57),
+                Behavior::Worker {
+                    polls: (1, 4),
+                    poll_time,
+                },
+            );
+            self.tasks[task].drop_at = Some(self.tick + lifetime);
+        }
+
+        if self.tick % 3 == 0 {
+            let task = self.spawn(
+                now,
+                "flush-metrics",
+                "compute",
+                ("src/metrics.rs", 23),
+                Behavior::Blocking,
+            );
+            self.tasks[task].kind = "blocking";
+            self.tasks[task].drop_at = Some(self.tick + 1);
+        }
+
+        if !starting {
+            return;
+        }
+
+        let (name, location, behavior) = match scenario {
+            Scenario::Steady | Scenario::SpawnStorm => return,
+            Scenario::SelfWakes => ("yield-loop", ("src/poller.rs", 31), Behavior::SelfWaking),
+            Scenario::LostWaker => (
+                "forgotten-waker",
+                ("src/channel.rs", 112),
+                Behavior::LostWaker,
+            ),
+            Scenario::NeverYields => (
+                "blocking-in-async",
+                ("src/compress.rs", 64),
+                Behavior::NeverYields,
+            ),
+        };
+        let task = self.spawn(now, name, "compute", location, behavior);
+        self.tasks[task].drop_at = Some(ends_at);
+    }
+
+    fn spawn(
+        &mut self,
+        now: SystemTime,
+        name: impl Into<String>,
+        runtime: &'static str,
+        location: (&'static str, u32),
+        behavior: Behavior,
+    ) -> usize {
+        let id = self.next_id();
+        let poll_time = match behavior {
+            Behavior::Worker { poll_time, .. } => poll_time,
+            Behavior::SelfWaking => Duration::from_micros(5),
+            Behavior::LostWaker => Duration::from_micros(80),
+            Behavior::NeverYields | Behavior::Blocking => Duration::from_millis(250),
+        };
+        self.poll_times.insert(id, poll_time);
+        self.tasks.push(DemoTask {
+            id,
+            name: name.into(),
+            kind: "task",
+            runtime,
+            location,
+            behavior,
+            sizes: None,
+            drop_at: None,
+            is_new: true,
+            stats: PollStats::default(),
+            wakes: 0,
+            self_wakes: 0,
+            waker_clones: 1,
+            waker_drops: 0,
+            last_wake: None,
+            scheduled: Duration::ZERO,
+            // Tasks are spawned at some point between two updates.
+            created_at: now - TICK / 2,
+            dropped_at: None,
+        });
+        self.tasks.len() - 1
+    }
+
+    fn next_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn resources(
+        &mut self,
+        now: SystemTime,
+    ) -> (
+        proto::resources::ResourceUpdate,
+        proto::async_ops::AsyncOpUpdate,
+    ) {
+        use proto::resources::resource::{kind, Kind};
+
+        let mut resource_update = proto::resources::ResourceUpdate::default();
+        let mut async_op_update = proto::async_ops::AsyncOpUpdate::default();
+        let (Some(semaphore), Some(timer), Some(acquire)) =
+            (self.semaphore, self.timer, self.acquire.as_mut())
+        else {
+            return (resource_update, async_op_update);
+        };
+
+        if self.tick == 0 {
+            resource_update.new_resources = vec![
+                proto::resources::Resource {
+                    id: Some(semaphore.into()),
+                    metadata: Some(proto::MetaId { id: SEMAPHORE_META }),
+                    concrete_type: "Semaphore".to_string(),
+                    kind: Some(Kind {
+                        kind: Some(kind::Kind::Other("Sync".to_string())),
+                    }),
+                    location: Some(location(("src/server.rs", 30))),
+                    parent_resource_id: None,
+                    is_internal: false,
+                },
+                proto::resources::Resource {
+                    id: Some(timer.into()),
+                    metadata: Some(proto::MetaId { id: TIMER_META }),
+                    concrete_type: "Sleep".to_string(),
+                    kind: Some(Kind {
+                        kind: Some(kind::Kind::Known(kind::Known::Timer as i32)),
+                    }),
+                    location: Some(location(("src/metrics.rs", 12))),
+                    parent_resource_id: None,
+                    is_internal: false,
+                },
+            ];
+        }
+
+        let permits = self.rng.range(0, 64);
+        resource_update.stats_update.insert(
+            semaphore,
+            proto::resources::Stats {
+                created_at: Some(self.start.into()),
+                dropped_at: None,
+                attributes: vec![attribute("permits", permits, None)],
+                acquired_by: None,
+                // As if the subscriber kept attribute history, so that the
+                // resource view plots the permits.
+                attribute_history: vec![proto::resources::AttributeSample {
+                    at: Some(now.into()),
+                    attribute: Some(attribute("permits", permits, None)),
+                }],
+            },
+        );
+        // The timer is an `Interval`'s `Sleep`, which is reset to fire again
+        // each time the interval ticks.
+        let period = TIMER_PERIOD.as_secs() / TICK.as_secs();
+        let reset_at = self.start + TICK * (self.tick / period * period) as u32;
+        let duration = TIMER_PERIOD.as_millis() as u64;
+        resource_update.stats_update.insert(
+            timer,
+            proto::resources::Stats {
+                created_at: Some(self.start.into()),
+                dropped_at: None,
+                attributes: vec![attribute("duration", duration, Some("ms"))],
+                acquired_by: None,
+                // As if the subscriber kept attribute history, so that the
+                // upcoming timer wakes show when the timer was reset.
+                attribute_history: vec![proto::resources::AttributeSample {
+                    at: Some(reset_at.into()),
+                    attribute: Some(attribute("duration", duration, Some("ms"))),
+                }],
+            },
+        );
+
+        if std::mem::take(&mut acquire.is_new) {
+            async_op_update
+                .new_async_ops
+                .push(proto::async_ops::AsyncOp {
+                    id: Some(acquire.id.into()),
+                    metadata: Some(proto::MetaId { id: ASYNC_OP_META }),
+                    source: "Semaphore::acquire".to_string(),
+                    parent_async_op_id: None,
+                    resource_id: Some(semaphore.into()),
+                });
+        }
+        let polls = self.rng.range(1, 5);
+        let poll_time = Duration::from_micros(self.rng.range(5, 50));
+        acquire.stats.record(now, polls, poll_time);
+        async_op_update.stats_update.insert(
+            acquire.id,
+            proto::async_ops::Stats {
+                created_at: Some(acquire.created_at.into()),
+                dropped_at: None,
+                task_id: Some(acquire.task_id.into()),
+                poll_stats: Some(acquire.stats.to_proto()),
+                attributes: vec![attribute("permits.acquired", polls, None)],
+            },
+        );
+
+        (resource_update, async_op_update)
+    }
+}
+
+// === impl DemoTask ===
+
+impl DemoTask {
+    /// Simulates one tick of the task's life, returning how long it spent
+    /// being polled.
+    fn advance(&mut self, tick: u64, now: SystemTime, rng: &mut Rng) -> Duration {
+        let busy_before = self.stats.busy;
+        let first_tick = self.stats.first_poll.is_none();
+        match self.behavior {
+            Behavior::Worker { polls, poll_time } => {
+                let polls = rng.range(polls.0, polls.1 + 1);
+                let poll_time = rng.jitter(poll_time);
+                self.stats.record(now, polls, poll_time);
+                self.wake(polls, 0, rng);
+            }
+            Behavior::SelfWaking => {
+                let polls = rng.range(2_000, 5_000);
+                self.stats.record(now, polls, Duration::from_micros(5));
+                self.wake(polls, polls - polls / 20, rng);
+                // The task woke itself again at the end of its last poll.
+                self.last_wake = self.stats.last_poll_ended;
+            }
+            Behavior::LostWaker if first_tick => {
+                self.stats.record(now, 1, Duration::from_micros(80));
+                self.wake(1, 0, rng);
+                self.waker_drops = self.waker_clones;
+            }
+            Behavior::NeverYields | Behavior::Blocking if first_tick => {
+                self.stats.polls = 1;
+                self.stats.first_poll = Some(self.created_at);
+                self.stats.last_poll_started = Some(self.created_at);
+            }
+            Behavior::NeverYields | Behavior::Blocking => {
+                self.stats.busy = now.duration_since(self.created_at).unwrap_or_default();
+            }
+            Behavior::LostWaker => {}
+        }
+
+        if self.drop_at.is_some_and(|drop_at| tick >= drop_at) {
+            if self.stats.last_poll_started > self.stats.last_poll_ended {
+                self.stats.last_poll_ended = Some(now);
+            }
+            self.waker_drops = self.waker_clones;
+            self.dropped_at = Some(now);
+        }
+
+        self.stats.busy.saturating_sub(busy_before)
+    }
+
+    /// Records `wakes` wakeups, the last of which happened just before the
+    /// task's last poll started.
+    fn wake(&mut self, wakes: u64, self_wakes: u64, rng: &mut Rng) {
+        let Some(last_poll_started) = self.stats.last_poll_started else {
+            return;
+        };
+        if wakes == 0 {
+            return;
+        }
+        self.wakes += wakes;
+        self.self_wakes += self_wakes;
+        self.waker_clones += wakes;
+        self.waker_drops += wakes;
+        self.scheduled += rng.jitter(Duration::from_micros(30)) * wakes as u32;
+        self.last_wake = Some(last_poll_started - Duration::from_micros(50));
+    }
+
+    fn to_proto(&self) -> proto::tasks::Task {
+        let meta = Some(proto::MetaId { id: TASK_META });
+        let field = |name: &str, value: proto::field::Value| proto::Field {
+            name: Some(name.into()),
+            value: Some(value),
+            metadata_id: meta,
+        };
+        let mut fields = vec![
+            field("kind", self.kind.into()),
+            field("task.name", self.name.as_str().into()),
+            field("task.id", self.id.into()),
+        ];
+        if let Some((size, original_size)) = self.sizes {
+            fields.push(field("size.bytes", size.into()));
+            fields.push(field("original_size.bytes", original_size.into()));
+        }
+        let kind = if self.kind == "blocking" {
+            proto::tasks::task::Kind::Blocking
+        } else {
+            proto::tasks::task::Kind::Spawn
+        };
+
+        proto::tasks::Task {
+            id: Some(self.id.into()),
+            metadata: meta,
+            kind: kind as i32,
+            fields,
+            parents: Vec::new(),
+            location: Some(location(self.location)),
+            runtime: Some(self.runtime.to_string()),
+            group: None,
+            size_bytes: self.sizes.map(|(size, _)| size),
+            original_size_bytes: self.sizes.map(|(_, original_size)| original_size),
+            spawn_backtrace: Vec::new(),
+        }
+    }
+
+    fn stats_to_proto(&self) -> proto::tasks::Stats {
+        proto::tasks::Stats {
+            created_at: Some(self.created_at.into()),
+            dropped_at: self.dropped_at.map(Into::into),
+            wakes: self.wakes,
+            waker_clones: self.waker_clones,
+            waker_drops: self.waker_drops,
+            last_wake: self.last_wake.map(Into::into),
+            poll_stats: Some(self.stats.to_proto()),
+            self_wakes: self.self_wakes,
+            scheduled_time: self.scheduled.try_into().ok(),
+            instrumentation_overhead: None,
+            cancelled: false,
+            waiting_on: Vec::new(),
+            current_span: None,
+            terminated_by: if self.dropped_at.is_some() {
+                proto::tasks::stats::TerminatedBy::Completed
+            } else {
+                proto::tasks::stats::TerminatedBy::NotTerminated
+            }
+            .into(),
+        }
+    }
+}
+
+// === impl PollStats ===
+
+impl PollStats {
+    /// Records `polls` polls taking `poll_time` each, the last of which ends
+    /// at `now`.
+    fn record(&mut self, now: SystemTime, polls: u64, poll_time: Duration) {
+        if polls == 0 {
+            return;
+        }
+        let started = now - poll_time;
+        self.polls += polls;
+        self.busy += poll_time * polls as u32;
+        self.first_poll.get_or_insert(started);
+        self.last_poll_started = Some(started);
+        self.last_poll_ended = Some(started + poll_time);
+    }
+
+    fn to_proto(&self) -> proto::PollStats {
+        proto::PollStats {
+            polls: self.polls,
+            first_poll: self.first_poll.map(Into::into),
+            last_poll_started: self.last_poll_started.map(Into::into),
+            last_poll_ended: self.last_poll_ended.map(Into::into),
+            busy_time: self.busy.try_into().ok(),
+            // Synthetic tasks never complete, so every poll returns `Pending`.
+            ready_time: Duration::ZERO.try_into().ok(),
+            pending_time: self.busy.try_into().ok(),
+        }
+    }
+}
+
+// === impl Rng ===
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    /// Returns a number in `low..high`.
+    fn range(&mut self, low: u64, high: u64) -> u64 {
+        low + self.next() % (high - low).max(1)
+    }
+
+    /// Returns a duration within 50% of `mean`.
+    fn jitter(&mut self, mean: Duration) -> Duration {
+        mean / 2 + mean * self.range(0, 100) as u32 / 100
+    }
+}
+
+fn metadata() -> proto::RegisterMetadata {
+    let meta = |id, name: &str, target: &str, field_names: &[&str]| {
+        proto::register_metadata::NewMetadata {
+            id: Some(proto::MetaId { id }),
+            metadata: Some(proto::Metadata {
+                name: name.to_string(),
+                target: target.to_string(),
+                module_path: target.to_string(),
+                location: None,
+                kind: proto::metadata::Kind::Span as i32,
+                level: proto::metadata::Level::Trace as i32,
+                field_names: field_names.iter().map(|name| name.to_string()).collect(),
+            }),
+        }
+    };
+    proto::RegisterMetadata {
+        metadata: vec![
+            meta(
+                TASK_META,
+                "runtime.spawn",
+                "tokio::task",
+                &[
+                    "kind",
+                    "task.name",
+                    "task.id",
+                    "size.bytes",
+                    "original_size.bytes",
+                ],
+            ),
+            meta(
+                SEMAPHORE_META,
+                "runtime.resource",
+                "tokio::sync::semaphore",
+                &["concrete_type", "kind"],
+            ),
+            meta(
+                TIMER_META,
+                "runtime.resource",
+                "tokio::time::sleep",
+                &["concrete_type", "kind"],
+            ),
+            meta(
+                ASYNC_OP_META,
+                "runtime.resource.async_op",
+                "tokio::sync::semaphore",
+                &["source"],
+            ),
+        ],
+    }
+}
+
+fn location((file, line): (&'static str, u32)) -> proto::Location {
+    proto::Location {
+        file: Some(format!("demo/{}", file)),
+        module_path: None,
+        line: Some(line),
+        column: Some(5),
+    }
+}
+
+fn attribute(name: &str, value: u64, unit: Option<&str>) -> proto::Attribute {
+    proto::Attribute {
+        field: Some(proto::Field {
+            name: Some(name.into()),
+            value: Some(value.into()),
+            metadata_id: None,
+        }),
+        unit: unit.map(String::from),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demo_is_deterministic() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut a = Demo::new(start);
+        let mut b = Demo::new(start);
+        for _ in 0..(SCENARIO_TICKS * Scenario::ALL.len() as u64 + 1) {
+            assert_eq!(a.next_update(), b.next_update());
+        }
+    }
+
+    #[test]
+    fn demo_cycles_through_scenarios() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut demo = Demo::new(start);
+        let mut seen = Vec::new();
+        for _ in 0..(SCENARIO_TICKS * Scenario::ALL.len() as u64) {
+            demo.next_update();
+            if seen.last() != Some(&demo.scenario()) {
+                seen.push(demo.scenario());
+            }
+        }
+        let all = Scenario::ALL.iter().map(|s| s.name()).collect::<Vec<_>>();
+        assert_eq!(seen, all);
+    }
+}
@@ -0,0 +1,346 @@
+//! Before/after comparisons for `tokio-console diff`.
+//!
+//! Each recording is replayed into its own [`State`], as fast as it can be
+//! read, and the tasks left at the end are grouped by name, or by spawn
+//! location if they're unnamed. The groups found in both recordings are then
+//! compared, and any metric which got significantly worse is reported.
+use crate::{
+    config::DiffFormat,
+    recording,
+    state::{DisplayedList, State},
+};
+use color_eyre::eyre::{eyre, WrapErr};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// Options for `tokio-console diff`.
+#[derive(Debug)]
+pub(crate) struct DiffOptions {
+    pub(crate) before: PathBuf,
+    pub(crate) after: PathBuf,
+    /// How much worse, as a percentage of its value before, a metric must get
+    /// to be reported.
+    pub(crate) threshold: u32,
+    pub(crate) format: DiffFormat,
+}
+
+/// The metrics of a group of tasks, summed over the tasks in it.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Totals {
+    busy: Duration,
+    total: Duration,
+    wakes: u64,
+    self_wakes: u64,
+    /// The longest poll of any of the tasks, as far as the recording shows.
+    ///
+    /// Updates only include the duration of each task's most recent poll, so
+    /// a task polled more than once between updates may have had longer
+    /// polls than this.
+    max_poll: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Metric {
+    /// The share of the tasks' lifetimes they spent being polled.
+    BusyPercent,
+    /// The share of the tasks' wakes which were self-wakes.
+    SelfWakePercent,
+    /// The longest poll of any of the tasks, in nanoseconds.
+    MaxPollNs,
+}
+
+/// A metric which got significantly worse, as printed by `tokio-console
+/// diff`.
+#[derive(Debug, Serialize)]
+struct Regression {
+    /// The name or spawn location of the tasks.
+    task: String,
+    metric: Metric,
+    before: f64,
+    after: f64,
+}
+
+/// The report printed by `tokio-console diff`.
+#[derive(Debug, Serialize)]
+struct Report {
+    /// `true` if nothing got significantly worse.
+    passed: bool,
+    /// The number of task names and locations found in both recordings.
+    compared: usize,
+    regressions: Vec<Regression>,
+}
+
+/// Compares the recordings in `options`, and prints the regressions between
+/// them to stdout.
+///
+/// Returns `false` if anything got significantly worse.
+pub(crate) fn run(options: DiffOptions) -> color_eyre::Result<bool> {
+    let before = load(&options.before)?;
+    let after = load(&options.after)?;
+    let compared = before.keys().filter(|key| after.contains_key(*key)).count();
+    let regressions = compare(&before, &after, options.threshold);
+    let report = Report {
+        passed: regressions.is_empty(),
+        compared,
+        regressions,
+    };
+
+    let output = match options.format {
+        DiffFormat::Text => render_text(&report),
+        DiffFormat::Json => {
+            serde_json::to_string_pretty(&report).wrap_err("failed to serialize diff as JSON")?
+        }
+    };
+    println!("{}", output);
+    Ok(report.passed)
+}
+
+/// Replays the recording at `path`, and returns the totals of its tasks,
+/// grouped by name or spawn location.
+fn load(path: &Path) -> color_eyre::Result<BTreeMap<String, Totals>> {
+    let reader = recording::Reader::open(path)
+        .wrap_err_with(|| format!("failed to open recording {}", path.display()))?;
+    let mut state = State::default();
+    let mut max_polls = HashMap::new();
+    for recorded in reader {
+        let recorded =
+            recorded.wrap_err_with(|| format!("failed to read recording {}", path.display()))?;
+        let Some(update) = recorded.update else {
+            continue;
+        };
+        let stats = update
+            .task_update
+            .iter()
+            .flat_map(|tasks| &tasks.stats_update);
+        for (&span_id, stats) in stats {
+            if let Some(poll) = stats.poll_stats.as_ref().and_then(last_poll) {
+                let max: &mut Duration = max_polls.entry(span_id).or_default();
+                *max = (*max).max(poll);
+            }
+        }
+        state.update(Some(DisplayedList::Tasks), update);
+    }
+    let now = state
+        .last_updated_at()
+        .ok_or_else(|| eyre!("recording {} has no updates", path.display()))?;
+
+    let mut groups = BTreeMap::<String, Totals>::new();
+    for task in state.tasks_state().tasks() {
+        let task = task.borrow();
+        let key = task.name().unwrap_or_else(|| task.location());
+        let totals = groups.entry(key.to_string()).or_default();
+        totals.busy += task.busy(now);
+        totals.total += task.total(now);
+        totals.wakes += task.wakes();
+        totals.self_wakes += task.self_wakes();
+        if let Some(&poll) = max_polls.get(&task.span_id()) {
+            totals.max_poll = totals.max_poll.max(poll);
+        }
+    }
+    Ok(groups)
+}
+
+/// Returns how long the most recent poll in `stats` took, if it has ended.
+fn last_poll(stats: &console_api::PollStats) -> Option<Duration> {
+    let started = SystemTime::try_from(stats.last_poll_started?).ok()?;
+    let ended = SystemTime::try_from(stats.last_poll_ended?).ok()?;
+    ended.duration_since(started).ok()
+}
+
+/// Returns the metrics of the groups in both `before` and `after` which got
+/// more than `threshold` percent worse, worst first.
+fn compare(
+    before: &BTreeMap<String, Totals>,
+    after: &BTreeMap<String, Totals>,
+    threshold: u32,
+) -> Vec<Regression> {
+    let factor = 1.0 + f64::from(threshold) / 100.0;
+    let mut regressions = Vec::new();
+    for (task, before) in before {
+        let Some(after) = after.get(task) else {
+            continue;
+        };
+        for metric in [
+            Metric::BusyPercent,
+            Metric::SelfWakePercent,
+            Metric::MaxPollNs,
+        ] {
+            let (before, after) = (metric.value(before), metric.value(after));
+            if after - before >= metric.noise() && after > before * factor {
+                regressions.push(Regression {
+                    task: task.clone(),
+                    metric,
+                    before,
+                    after,
+                });
+            }
+        }
+    }
+    regressions.sort_by(|a, b| b.change().total_cmp(&a.change()));
+    regressions
+}
+
+impl Metric {
+    fn value(self, totals: &Totals) -> f64 {
+        let percent = |part: f64, whole: f64| {
+            if whole == 0.0 {
+                0.0
+            } else {
+                part / whole * 100.0
+            }
+        };
+        match self {
+            Metric::BusyPercent => percent(totals.busy.as_secs_f64(), totals.total.as_secs_f64()),
+            Metric::SelfWakePercent => percent(totals.self_wakes as f64, totals.wakes as f64),
+            Metric::MaxPollNs => totals.max_poll.as_nanos() as f64,
+        }
+    }
+
+    /// Returns the smallest increase in this metric that's reported, however
+    /// large it is relative to the value before, so that metrics which were
+    /// close to zero don't regress by jittering.
+    fn noise(self) -> f64 {
+        match self {
+            Metric::BusyPercent | Metric::SelfWakePercent => 1.0,
+            Metric::MaxPollNs => Duration::from_millis(1).as_nanos() as f64,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Metric::BusyPercent => "busy",
+            Metric::SelfWakePercent => "self wakes",
+            Metric::MaxPollNs => "max poll",
+        }
+    }
+
+    fn format(self, value: f64) -> String {
+        match self {
+            Metric::BusyPercent | Metric::SelfWakePercent => format!("{:.1}%", value),
+            Metric::MaxPollNs => format!("{:.2?}", Duration::from_nanos(value as u64)),
+        }
+    }
+}
+
+impl Regression {
+    /// Returns how many times worse the metric got.
+    fn change(&self) -> f64 {
+        if self.before == 0.0 {
+            f64::INFINITY
+        } else {
+            self.after / self.before
+        }
+    }
+}
+
+fn render_text(report: &Report) -> String {
+    if report.regressions.is_empty() {
+        return format!(
+            "no regressions in {} task names and locations",
+            report.compared
+        );
+    }
+    const HEADER: [&str; 5] = ["Task", "Metric", "Before", "After", "Change"];
+
+    let table = report
+        .regressions
+        .iter()
+        .map(|regression| {
+            let change = regression.change();
+            [
+                regression.task.clone(),
+                regression.metric.name().to_string(),
+                regression.metric.format(regression.before),
+                regression.metric.format(regression.after),
+                if change.is_finite() {
+                    format!("{:.1}x", change)
+                } else {
+                    "new".to_string()
+                },
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let mut widths = HEADER.map(str::len);
+    for row in &table {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut output = String::new();
+    let header = HEADER.map(String::from);
+    for row in std::iter::once(&header).chain(&table) {
+        let mut line = String::new();
+        for (i, (cell, width)) in row.iter().zip(widths).enumerate() {
+            let cell = match i {
+                0 | 1 => format!("{:<width$}", cell),
+                _ => format!("{:>width$}", cell),
+            };
+            let _ = write!(line, "{} ", cell);
+        }
+        output.push_str(line.trim_end());
+        output.push('\n');
+    }
+    let _ = write!(
+        output,
+        "{} regressions in {} task names and locations",
+        report.regressions.len(),
+        report.compared
+    );
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn totals(busy_ms: u64, self_wakes: u64, max_poll_ms: u64) -> Totals {
+        Totals {
+            busy: Duration::from_millis(busy_ms),
+            total: Duration::from_secs(1),
+            wakes: 100,
+            self_wakes,
+            max_poll: Duration::from_millis(max_poll_ms),
+        }
+    }
+
+    #[test]
+    fn compare_reports_significant_regressions() {
+        let before = BTreeMap::from([
+            ("accept-loop".to_string(), totals(100, 10, 5)),
+            ("idle".to_string(), totals(1, 0, 0)),
+            ("removed".to_string(), totals(0, 0, 0)),
+        ]);
+        let after = BTreeMap::from([
+            // Busy time and max poll doubled, self-wakes only grew by 10%.
+            ("accept-loop".to_string(), totals(200, 11, 10)),
+            // Grew relative to its value before, but only by noise.
+            ("idle".to_string(), totals(5, 0, 0)),
+            ("added".to_string(), totals(900, 90, 100)),
+        ]);
+
+        let regressions = compare(&before, &after, 20);
+        let found = regressions
+            .iter()
+            .map(|regression| (regression.task.as_str(), regression.metric))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                ("accept-loop", Metric::BusyPercent),
+                ("accept-loop", Metric::MaxPollNs),
+            ]
+        );
+        assert_eq!(regressions[0].before, 10.0);
+        assert_eq!(regressions[0].after, 20.0);
+
+        assert!(compare(&before, &after, 200).is_empty());
+    }
+}
@@ -46,6 +46,26 @@ pub(crate) fn is_space(input: &Event) -> bool {
     )
 }
 
+pub(crate) fn is_step_back(input: &Event) -> bool {
+    matches!(
+        input,
+        Event::Key(KeyEvent {
+            code: KeyCode::Char('['),
+            ..
+        })
+    )
+}
+
+pub(crate) fn is_step_forward(input: &Event) -> bool {
+    matches!(
+        input,
+        Event::Key(KeyEvent {
+            code: KeyCode::Char(']'),
+            ..
+        })
+    )
+}
+
 pub(crate) fn is_help_toggle(event: &Event) -> bool {
     matches!(
         event,
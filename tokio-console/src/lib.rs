@@ -37,6 +37,76 @@
 #[doc = include_str!("../console.example.toml")]
 /// ```
 ///
+/// ### Latency Budgets
+///
+/// The config file can set a latency budget for the tasks with each name, in
+/// a `[latency_budgets]` table. A budget limits a percentile of either the
+/// time spent in each poll of the task (`poll`) or the time it spent
+/// scheduled before each poll (`scheduled`):
+///
+/// ```toml
+/// [latency_budgets]
+/// "connection-handler" = "poll p99 < 10ms"
+/// "flush" = "scheduled p50 < 500us"
+/// ```
+///
+/// In the tasks list, the name of each task with a budget is shown in green
+/// while it is within the budget, in yellow when it is over 80% of it, and in
+/// red once it has exceeded it. Tasks which exceed their budgets also get the
+/// `latency-budget` warning. Until a task's details are viewed, its
+/// percentiles are only estimated from the mean duration of its polls between
+/// updates, which understates the slowest polls, so its name isn't colored
+/// and it isn't warned about.
+///
+/// ### Desktop Notifications
+///
+/// When `tokio-console` is built with the `notifications` feature, it can show
+/// a desktop notification the first time each of the warnings listed in the
+/// `notify` setting is detected, so that problems aren't missed while the
+/// console is running in the background:
+///
+/// ```toml
+/// notify = ["lost-waker", "waker-leak"]
+/// ```
+///
+/// Only enabled warnings are notified about. Without the `notifications`
+/// feature, this setting is ignored.
+///
+/// ### Highlighting Tasks
+///
+/// The config file can color the tasks whose names match glob patterns, so
+/// that important tasks stand out in the tasks list. In a pattern, `*`
+/// matches any number of characters and `?` matches any single character.
+/// Colors can be named (such as `cyan` or `light-red`), or given as an ANSI
+/// 256-color index or an RGB hex code:
+///
+/// ```toml
+/// [[colors.highlights]]
+/// name = "db-*"
+/// color = "cyan"
+///
+/// [[colors.highlights]]
+/// name = "worker-?"
+/// color = "#ff8800"
+/// ```
+///
+/// A task matching more than one pattern is shown in the color of the first.
+/// Colors the terminal's palette can't show are ignored.
+///
+/// ### Source Previews
+///
+/// Pressing `v` in the task details view shows the source around the task's
+/// spawn location, if its file can be found. Spawn locations are the paths
+/// the target was built with, so for targets built in a container or on
+/// another machine, the `[source_remap]` table maps path prefixes there to
+/// local directories. The longest matching prefix is used, and relative paths
+/// are looked up in the current directory:
+///
+/// ```toml
+/// [source_remap]
+/// "/usr/src/app" = "/home/me/code/app"
+/// ```
+///
 /// ### Config File Locations
 ///
 /// Configuration files are read from two locations:
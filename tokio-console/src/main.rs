@@ -1,30 +1,43 @@
-use color_eyre::{eyre::eyre, Help, SectionExt};
-use console_api::tasks::TaskDetails;
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Help, SectionExt,
+};
+use console_api::{instrument::HistogramSelection, recording::RecordedUpdate, tasks::TaskDetails};
+use console_state::{self as state, intern, util, warnings};
 use state::{State, Temporality};
 
 use futures::stream::StreamExt;
 use ratatui::{
     layout::{Constraint, Direction, Layout},
-    style::Color,
     text::{Line, Span},
     widgets::{Paragraph, Wrap},
 };
+use std::{
+    io::{self, Write},
+    time::SystemTime,
+};
 use tokio::sync::{mpsc, watch};
 
 use crate::{
     config::AllowedWarnings,
-    view::{bold, UpdateKind},
+    view::{bold, GlobalInput, Role, UpdateKind},
 };
 
+mod check;
+mod clipboard;
 mod config;
 mod conn;
+mod demo;
+mod diff;
 mod input;
-mod intern;
-mod state;
+#[cfg(feature = "notifications")]
+mod notify;
+mod recording;
+mod source;
+mod ssh;
 mod term;
-mod util;
+mod top;
 mod view;
-mod warnings;
 
 #[tokio::main]
 async fn main() -> color_eyre::Result<()> {
@@ -37,7 +50,7 @@ async fn main() -> color_eyre::Result<()> {
     args.trace_init()?;
     tracing::debug!(?args.target_addr, ?args.view_options);
 
-    match args.subcmd {
+    match &args.subcmd {
         Some(config::OptionalCmd::GenConfig) => {
             // Generate a default config file and exit.
             let toml = args.gen_config_file()?;
@@ -45,18 +58,72 @@ async fn main() -> color_eyre::Result<()> {
             return Ok(());
         }
         Some(config::OptionalCmd::GenCompletion { install, shell }) => {
-            return config::gen_completion(install, shell);
+            return config::gen_completion(*install, *shell);
+        }
+        Some(config::OptionalCmd::Paths { format }) => {
+            println!("{}", args.paths(*format)?);
+            return Ok(());
         }
-        None => {}
+        Some(config::OptionalCmd::Diff {
+            before,
+            after,
+            threshold,
+            format,
+        }) => {
+            let options = diff::DiffOptions {
+                before: before.clone(),
+                after: after.clone(),
+                threshold: *threshold,
+                format: *format,
+            };
+            if !diff::run(options)? {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(config::OptionalCmd::Targets) => {
+            println!("{}", args.targets_table());
+            return Ok(());
+        }
+        Some(config::OptionalCmd::Convert { input, output }) => {
+            let updates = recording::convert(input, output)?;
+            eprintln!("converted {} updates to {}", updates, output.display());
+            return Ok(());
+        }
+        Some(
+            config::OptionalCmd::Top { .. }
+            | config::OptionalCmd::Check { .. }
+            | config::OptionalCmd::Demo
+            | config::OptionalCmd::Replay { .. },
+        )
+        | None => {}
     }
 
-    let target = args.target_addr()?;
-    tracing::info!(?target, "using target addr");
+    // Kept alive for the rest of the session, as dropping it closes the port
+    // forward.
+    let mut tunnel = None;
+    let mut conn = if args.subcmd == Some(config::OptionalCmd::Demo) {
+        tracing::info!("running with synthetic demo data");
+        conn::Connection::demo()
+    } else if let Some(config::OptionalCmd::Replay { path }) = &args.subcmd {
+        tracing::info!(?path, "replaying recording");
+        conn::Connection::replay(path)
+            .wrap_err_with(|| format!("failed to open recording {}", path.display()))?
+    } else {
+        let mut target = args.target_addr()?;
+        if let Some(destination) = args.ssh.as_deref() {
+            let ssh = tunnel.insert(ssh::SshTunnel::open(destination, &target).await?);
+            target = ssh.local_addr().clone();
+        }
+        tracing::info!(?target, "using target addr");
+        conn::Connection::new(target, args.connection_options.clone())
+    };
 
-    let retain_for = args.retain_for();
-    let (mut terminal, _cleanup) = term::init_crossterm()?;
-    terminal.clear()?;
-    let mut conn = conn::Connection::new(target);
+    let retention = args.retention();
+    let history_budget = args.history_budget();
+    let stall_threshold = args.stall_threshold();
+    let state_file = args.state_file();
+    let bell = args.bell;
     // A channel to send the outcome of `View::update_input` to the watch_details_stream task.
     let (update_tx, update_rx) = watch::channel(UpdateKind::Other);
     // A channel to send the task details update stream (no need to keep outdated details in the memory)
@@ -71,11 +138,94 @@ async fn main() -> color_eyre::Result<()> {
         None => args.warnings.iter().collect::<Vec<_>>(),
     };
 
+    #[cfg(feature = "notifications")]
+    let mut notifier = {
+        let notify = args.notify.clone().unwrap_or_default();
+        let linters = warnings
+            .iter()
+            .filter(|lint| notify.contains(**lint))
+            .map(|lint| warnings::Linter::from(*lint))
+            .collect::<Vec<_>>();
+        notify::Notifier::new(&linters)
+    };
+    #[cfg(not(feature = "notifications"))]
+    if args
+        .notify
+        .as_ref()
+        .is_some_and(|notify| !notify.is_empty())
+    {
+        tracing::warn!(
+            "the config file lists warnings to `notify` about, but tokio-console was built \
+            without the `notifications` feature"
+        );
+    }
+
+    // The warnings which aren't enabled are still listed in the lints menu,
+    // so that they can be enabled while the console is running.
+    let disabled_warnings = <config::KnownWarnings as clap::ValueEnum>::value_variants()
+        .iter()
+        .filter(|lint| !warnings.contains(lint))
+        .collect::<Vec<_>>();
+
     let mut state = State::default()
         .with_task_linters(warnings.into_iter().map(|lint| lint.into()))
-        .with_retain_for(retain_for);
+        .with_disabled_task_linters(disabled_warnings.into_iter().map(|lint| lint.into()))
+        .with_latency_budgets(args.latency_budgets.clone().unwrap_or_default())
+        .with_retention(retention)
+        .with_history_budget(history_budget)
+        .with_stall_threshold(stall_threshold);
+
+    if let Some(config::OptionalCmd::Top {
+        format,
+        limit,
+        updates,
+        timeout,
+    }) = args.subcmd
+    {
+        let options = top::TopOptions {
+            format,
+            limit,
+            updates,
+            timeout,
+        };
+        return top::run(conn, state, options).await;
+    }
+
+    if let Some(config::OptionalCmd::Check { duration }) = args.subcmd {
+        let options = check::CheckOptions { duration };
+        if !check::run(conn, state, options).await? {
+            // Exiting skips destructors, so close the port forward first.
+            drop(tunnel);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let mut recorder = args
+        .record
+        .as_deref()
+        .map(|path| {
+            recording::Writer::create(path)
+                .wrap_err_with(|| format!("failed to create recording {}", path.display()))
+        })
+        .transpose()?;
+
+    let (mut terminal, _cleanup) = term::init_crossterm()?;
+    terminal.clear()?;
     let mut input = Box::pin(input::EventStream::new());
-    let mut view = view::View::new(styles);
+    let prefs = state_file
+        .as_deref()
+        .map(view::ViewPrefs::load)
+        .transpose()
+        .unwrap_or_else(|error| {
+            tracing::warn!(%error, "failed to restore view preferences");
+            None
+        })
+        .unwrap_or_default();
+    let sources = source::Sources::new(args.source_remap.clone().unwrap_or_default());
+    let mut view = view::View::new(styles)
+        .with_prefs(&prefs)
+        .with_sources(sources);
 
     loop {
         tokio::select! { biased;
@@ -88,29 +238,39 @@ async fn main() -> color_eyre::Result<()> {
                     continue;
                 }
 
-                if input::should_quit(&input) {
-                    return Ok(());
-                }
-
-                if input::is_space(&input) {
-                    if state.is_paused() {
-                        conn.resume().await;
-                        state.start_unpausing();
-                    } else {
-                        conn.pause().await;
-                        state.start_pausing();
+                // Keys are typed into the command palette, and go to a menu,
+                // while it's open, rather than triggering their usual actions.
+                match view.global_input(&input, &state) {
+                    Some(GlobalInput::Quit) => {
+                        if let Some(path) = state_file.as_deref() {
+                            if let Err(error) = view.prefs().save(path) {
+                                tracing::warn!(%error, "failed to save view preferences");
+                            }
+                        }
+                        return Ok(());
+                    }
+                    Some(GlobalInput::TogglePause) => {
+                        let paused = !state.is_paused();
+                        set_paused(&mut conn, &mut state, paused).await;
+                    }
+                    Some(GlobalInput::StepBack) => {
+                        state.step_back();
                     }
+                    Some(GlobalInput::StepForward) => {
+                        state.step_forward();
+                    }
+                    None => {}
                 }
 
-                let update_kind = view.update_input(input, &state);
+                let update_kind = view.update_input(input, &mut state);
                 // Using the result of update_input to manage the details watcher task
                 let _ = update_tx.send(update_kind);
                 match update_kind {
-                    UpdateKind::SelectTask(task_id) => {
-                        tracing::info!(task_id, "starting details watch");
-                        match conn.watch_details(task_id).await {
+                    UpdateKind::SelectTask(task_id, histograms) => {
+                        tracing::info!(task_id, ?histograms, "starting details watch");
+                        match conn.watch_details(task_id, histograms).await {
                             Ok(stream) => {
-                                tokio::spawn(watch_details_stream(task_id, stream, update_rx.clone(), details_tx.clone()));
+                                tokio::spawn(watch_details_stream(task_id, histograms, stream, update_rx.clone(), details_tx.clone()));
                             },
                             Err(error) => {
                                 tracing::warn!(%error, "error watching task details");
@@ -121,13 +281,32 @@ async fn main() -> color_eyre::Result<()> {
                     UpdateKind::ExitTaskView => {
                         state.unset_task_details();
                     }
+                    UpdateKind::SetPaused(paused) if paused != state.is_paused() => {
+                        set_paused(&mut conn, &mut state, paused).await;
+                    }
                     _ => {}
                 }
             },
             instrument_message = conn.next_message() => {
                 match instrument_message {
                     conn::Message::Update(update) => {
-                        state.update(&view.styles, view.current_view(), update);
+                        if let Some(writer) = recorder.as_mut() {
+                            let recorded = RecordedUpdate {
+                                received_at: Some(SystemTime::now().into()),
+                                update: Some(update.clone()),
+                            };
+                            if let Err(error) = writer.write(&recorded) {
+                                tracing::warn!(%error, "error writing recording, recording stopped");
+                                recorder = None;
+                            }
+                        }
+                        state.update(view.displayed_list(), update);
+                        let ended_watched = state.tasks_state_mut().take_ended_watched();
+                        if bell && !ended_watched.is_empty() {
+                            ring_bell();
+                        }
+                        #[cfg(feature = "notifications")]
+                        notifier.notify_new_warnings(&mut state);
                     },
                     conn::Message::State(state_update) => {
                         state.update_state(state_update);
@@ -157,16 +336,26 @@ async fn main() -> color_eyre::Result<()> {
             let mut header_text = conn.render(&view.styles);
             match state.temporality() {
                 Temporality::Paused => {
-                    header_text.push_span(Span::styled(" PAUSED", view.styles.fg(Color::Red)));
+                    header_text.push_span(Span::styled(" PAUSED", view.styles.role(Role::Bad)));
                 }
                 Temporality::Pausing => {
-                    header_text.push_span(Span::styled(" PAUSING", view.styles.fg(Color::Yellow)));
+                    header_text
+                        .push_span(Span::styled(" PAUSING", view.styles.role(Role::Caution)));
                 }
                 Temporality::Unpausing => {
-                    header_text.push_span(Span::styled(" UNPAUSING", view.styles.fg(Color::Green)));
+                    header_text.push_span(Span::styled(" UNPAUSING", view.styles.role(Role::Good)));
                 }
                 Temporality::Live => {}
             }
+            if let Some(steps_back) = state.steps_back() {
+                header_text.push_span(Span::styled(
+                    format!(" (VIEWING {} UPDATES AGO)", steps_back),
+                    view.styles.role(Role::Caution),
+                ));
+            }
+            for span in view::runtime::saturation(&view.styles, state.runtime_state()) {
+                header_text.push_span(span);
+            }
             let dropped_async_ops_state = state.async_ops_state().dropped_events();
             let dropped_tasks_state = state.tasks_state().dropped_events();
             let dropped_resources_state = state.resources_state().dropped_events();
@@ -182,8 +371,32 @@ async fn main() -> color_eyre::Result<()> {
                     dropped_texts.push(format!("{} resources", dropped_resources_state))
                 }
                 header_text.push_span(Span::styled(
-                    format!(" dropped: {}", dropped_texts.join(", ")),
-                    view.styles.fg(Color::Red),
+                    format!(" dropped: {} (D for details)", dropped_texts.join(", ")),
+                    view.styles.role(Role::Bad),
+                ));
+            }
+            if let Some(missed) = state.recent_missed_updates() {
+                header_text.push_span(Span::styled(
+                    format!(" missed {} updates", missed),
+                    view.styles.role(Role::Caution),
+                ));
+            }
+            if state.is_lagging() {
+                header_text.push_span(Span::styled(
+                    " lagging: some details are left out of updates",
+                    view.styles.role(Role::Caution),
+                ));
+            }
+            if state.is_truncated() {
+                header_text.push_span(Span::styled(
+                    " truncated: some changes will arrive in later updates",
+                    view.styles.role(Role::Caution),
+                ));
+            }
+            if let Some(status) = view.status() {
+                header_text.push_span(Span::styled(
+                    format!(" {}", status),
+                    view.styles.role(Role::Info),
                 ));
             }
             let header = Paragraph::new(header_text).wrap(Wrap { trim: true });
@@ -198,20 +411,34 @@ async fn main() -> color_eyre::Result<()> {
 
             f.render_widget(header, chunks[0]);
             f.render_widget(view_controls, chunks[1]);
-            view.render(f, chunks[2], &mut state);
+            view.render(f, chunks[2], &mut state, conn.stats());
         })?;
     }
 }
 
+/// Pauses or resumes the console.
+async fn set_paused(conn: &mut conn::Connection, state: &mut State, paused: bool) {
+    if paused {
+        conn.pause().await;
+        state.start_pausing();
+    } else {
+        state.step_to_latest();
+        conn.resume().await;
+        state.start_unpausing();
+    }
+}
+
 /// Given the task details stream for the given task id, sends the updates
-/// to the `details_tx` channel until the currently-viewed task changes.
+/// to the `details_tx` channel until the currently-viewed task, or the
+/// histograms shown of it, change.
 ///
 /// This is a separate task from the main program loop mainly because there isn't
 /// always a details stream to poll and we need to react to user inputs to
 /// replace the details stream with another one.
 async fn watch_details_stream(
     task_id: u64,
-    mut details_stream: tonic::Streaming<TaskDetails>,
+    histograms: HistogramSelection,
+    mut details_stream: conn::DetailsStream,
     mut watch_rx: watch::Receiver<UpdateKind>,
     details_tx: mpsc::Sender<TaskDetails>,
 ) {
@@ -235,7 +462,9 @@ async fn watch_details_stream(
                         UpdateKind::ExitTaskView => {
                             break;
                         },
-                        UpdateKind::SelectTask(new_id) if new_id != task_id => {
+                        UpdateKind::SelectTask(new_id, new_histograms)
+                            if new_id != task_id || new_histograms != histograms =>
+                        {
                             break;
                         },
                         _ => {}
@@ -247,3 +476,11 @@ async fn watch_details_stream(
         }
     }
 }
+
+/// Rings the terminal bell.
+fn ring_bell() {
+    let mut stdout = io::stdout();
+    if let Err(error) = stdout.write_all(b"\x07").and_then(|_| stdout.flush()) {
+        tracing::warn!(%error, "failed to ring the terminal bell");
+    }
+}
@@ -0,0 +1,56 @@
+//! Desktop notifications for when warnings are first detected.
+//!
+//! The warnings to notify about are listed in the `notify` setting of the
+//! config file, so that a warning is noticed even while the console isn't
+//! being watched.
+
+use console_state::{tasks::Task, warnings::Linter, State};
+
+pub(crate) struct Notifier {
+    /// The summaries of the warnings to notify about which haven't been
+    /// detected yet.
+    pending: Vec<String>,
+}
+
+impl Notifier {
+    pub(crate) fn new(warnings: &[Linter<Task>]) -> Self {
+        Self {
+            pending: warnings
+                .iter()
+                .map(|warning| warning.summary().to_owned())
+                .collect(),
+        }
+    }
+
+    /// Shows a notification for each warning which was detected for the first
+    /// time since the console started.
+    pub(crate) fn notify_new_warnings(&mut self, state: &mut State) {
+        if self.pending.is_empty() {
+            return;
+        }
+        for linter in &state.tasks_state().linters {
+            let count = linter.count();
+            if count == 0 {
+                continue;
+            }
+            let Some(idx) = self.pending.iter().position(|s| s == linter.summary()) else {
+                continue;
+            };
+            self.pending.swap_remove(idx);
+            let body = format!("{} {}", count, linter.summary());
+            tracing::info!(%body, "showing desktop notification");
+            // Showing a notification may block while talking to the desktop
+            // environment, so don't hold up the UI.
+            tokio::task::spawn_blocking(move || {
+                let shown = notify_rust::Notification::new()
+                    .summary("tokio-console warning")
+                    .body(&body)
+                    .appname("tokio-console")
+                    .show();
+                if let Err(error) = shown {
+                    tracing::warn!(%error, "failed to show desktop notification");
+                }
+            });
+        }
+    }
+}
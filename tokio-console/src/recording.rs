@@ -0,0 +1,330 @@
+//! Recordings of the updates the console receives from a target, written with
+//! `--record` and played back with `tokio-console replay`.
+//!
+//! Recordings can be written in two formats, which hold the same data:
+//!
+//! - The protobuf format is the bytes [`MAGIC`], then a [`VERSION`] byte,
+//!   followed by length-delimited [`RecordedUpdate`] messages. This is the
+//!   default, as it's much more compact.
+//! - The JSON format is a `{"v":1}` header line, followed by one
+//!   [`RecordedUpdate`] per line. This is used for files whose names end in
+//!   `.json` or `.jsonl`, and is easier to inspect with other tools.
+//!
+//! `tokio-console convert` converts recordings between the two formats.
+use color_eyre::eyre::WrapErr;
+use console_api::recording::RecordedUpdate;
+use prost::Message as _;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+/// The bytes which start a recording in the protobuf format.
+pub(crate) const MAGIC: &[u8] = b"tokio-console-recording";
+
+/// The version of the recording formats written by this console.
+pub(crate) const VERSION: u8 = 1;
+
+/// The largest single update that will be read from a recording.
+///
+/// This is far larger than any real update, and guards against trying to
+/// allocate an absurd amount of memory for a corrupt length prefix.
+const MAX_UPDATE_LEN: usize = 64 * 1024 * 1024;
+
+/// The formats recordings can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Format {
+    Protobuf,
+    Json,
+}
+
+/// The header line of a recording in the JSON format.
+#[derive(Debug, Serialize, Deserialize)]
+struct JsonHeader {
+    v: u8,
+}
+
+/// Writes a recording.
+#[derive(Debug)]
+pub(crate) struct Writer<W: Write = BufWriter<File>> {
+    out: W,
+    format: Format,
+}
+
+/// Reads the updates from a recording in either format.
+#[derive(Debug)]
+pub(crate) struct Reader<R: BufRead = BufReader<File>> {
+    input: R,
+    format: Format,
+    /// Reused between updates, to avoid allocating a buffer for each.
+    buf: Vec<u8>,
+}
+
+/// Converts the recording at `input` to the format chosen by the name of
+/// `output`, returning the number of updates converted.
+pub(crate) fn convert(input: &Path, output: &Path) -> color_eyre::Result<usize> {
+    let reader = Reader::open(input)
+        .wrap_err_with(|| format!("failed to open recording {}", input.display()))?;
+    let mut writer = Writer::create(output)
+        .wrap_err_with(|| format!("failed to create recording {}", output.display()))?;
+    let mut updates = 0;
+    for recorded in reader {
+        let recorded =
+            recorded.wrap_err_with(|| format!("failed to read recording {}", input.display()))?;
+        writer
+            .write(&recorded)
+            .wrap_err_with(|| format!("failed to write recording {}", output.display()))?;
+        updates += 1;
+    }
+    Ok(updates)
+}
+
+// === impl Format ===
+
+impl Format {
+    /// Returns the format to write a recording at `path` in, based on its
+    /// extension.
+    pub(crate) fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json" | "jsonl") => Format::Json,
+            _ => Format::Protobuf,
+        }
+    }
+}
+
+// === impl Writer ===
+
+impl Writer {
+    /// Creates a recording at `path`, in the format chosen by its extension.
+    pub(crate) fn create(path: &Path) -> io::Result<Self> {
+        let file = File::create(path)?;
+        Self::new(BufWriter::new(file), Format::for_path(path))
+    }
+}
+
+impl<W: Write> Writer<W> {
+    /// Starts a recording in `format`, writing its header to `out`.
+    pub(crate) fn new(mut out: W, format: Format) -> io::Result<Self> {
+        match format {
+            Format::Protobuf => {
+                out.write_all(MAGIC)?;
+                out.write_all(&[VERSION])?;
+            }
+            Format::Json => {
+                serde_json::to_writer(&mut out, &JsonHeader { v: VERSION })?;
+                out.write_all(b"\n")?;
+            }
+        }
+        Ok(Self { out, format })
+    }
+
+    /// Appends an update to the recording.
+    ///
+    /// The recording is flushed after each update, so that it's complete even
+    /// if the console doesn't exit cleanly.
+    pub(crate) fn write(&mut self, recorded: &RecordedUpdate) -> io::Result<()> {
+        match self.format {
+            Format::Protobuf => {
+                let buf = recorded.encode_length_delimited_to_vec();
+                self.out.write_all(&buf)?;
+            }
+            Format::Json => {
+                serde_json::to_writer(&mut self.out, recorded)?;
+                self.out.write_all(b"\n")?;
+            }
+        }
+        self.out.flush()
+    }
+
+    #[cfg(test)]
+    fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+// === impl Reader ===
+
+impl Reader {
+    /// Opens the recording at `path`, detecting its format.
+    pub(crate) fn open(path: &Path) -> io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: BufRead> Reader<R> {
+    /// Starts reading a recording from `input`, detecting its format from
+    /// its header.
+    pub(crate) fn new(mut input: R) -> io::Result<Self> {
+        let format = if input.fill_buf()?.starts_with(b"{") {
+            let mut line = String::new();
+            input.read_line(&mut line)?;
+            let header: JsonHeader = serde_json::from_str(&line)?;
+            check_version(header.v)?;
+            Format::Json
+        } else {
+            let mut magic = [0; MAGIC.len() + 1];
+            input
+                .read_exact(&mut magic)
+                .ok()
+                .filter(|_| magic.starts_with(MAGIC))
+                .ok_or_else(|| invalid_data("not a tokio-console recording"))?;
+            check_version(magic[MAGIC.len()])?;
+            Format::Protobuf
+        };
+        Ok(Self {
+            input,
+            format,
+            buf: Vec::new(),
+        })
+    }
+
+    fn read_protobuf(&mut self) -> io::Result<Option<RecordedUpdate>> {
+        let Some(len) = self.read_len()? else {
+            return Ok(None);
+        };
+        if len > MAX_UPDATE_LEN {
+            return Err(invalid_data(format!(
+                "recorded update is too large ({} bytes)",
+                len
+            )));
+        }
+        self.buf.resize(len, 0);
+        self.input.read_exact(&mut self.buf)?;
+        RecordedUpdate::decode(&self.buf[..])
+            .map(Some)
+            .map_err(invalid_data)
+    }
+
+    /// Reads the varint length prefix of the next update, or returns `None`
+    /// at the end of the recording.
+    fn read_len(&mut self) -> io::Result<Option<usize>> {
+        let mut len = 0u64;
+        for i in 0..10 {
+            let mut byte = [0];
+            if let Err(error) = self.input.read_exact(&mut byte) {
+                return match error.kind() {
+                    io::ErrorKind::UnexpectedEof if i == 0 => Ok(None),
+                    _ => Err(error),
+                };
+            }
+            len |= u64::from(byte[0] & 0x7f) << (i * 7);
+            if byte[0] & 0x80 == 0 {
+                return Ok(Some(len as usize));
+            }
+        }
+        Err(invalid_data("invalid length prefix"))
+    }
+
+    fn read_json(&mut self) -> io::Result<Option<RecordedUpdate>> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.input.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            if !line.trim().is_empty() {
+                return Ok(Some(serde_json::from_str(&line)?));
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = io::Result<RecordedUpdate>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.format {
+            Format::Protobuf => self.read_protobuf(),
+            Format::Json => self.read_json(),
+        }
+        .transpose()
+    }
+}
+
+fn check_version(version: u8) -> io::Result<()> {
+    if version != VERSION {
+        return Err(invalid_data(format!(
+            "unsupported recording version {} (this console reads version {})",
+            version, VERSION
+        )));
+    }
+    Ok(())
+}
+
+fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::demo::{Demo, TICK};
+    use std::time::SystemTime;
+
+    fn recorded_updates() -> Vec<RecordedUpdate> {
+        let start = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        let mut demo = Demo::new(start);
+        (0..20)
+            .map(|tick| RecordedUpdate {
+                received_at: Some((start + TICK * tick).into()),
+                update: Some(demo.next_update()),
+            })
+            .collect()
+    }
+
+    fn write_all(updates: &[RecordedUpdate], format: Format) -> Vec<u8> {
+        let mut writer = Writer::new(Vec::new(), format).unwrap();
+        for recorded in updates {
+            writer.write(recorded).unwrap();
+        }
+        writer.into_inner()
+    }
+
+    fn read_all(recording: &[u8]) -> (Format, Vec<RecordedUpdate>) {
+        let reader = Reader::new(recording).unwrap();
+        let format = reader.format;
+        (format, reader.collect::<io::Result<_>>().unwrap())
+    }
+
+    #[test]
+    fn round_trips_between_formats() {
+        let updates = recorded_updates();
+
+        let protobuf = write_all(&updates, Format::Protobuf);
+        assert!(protobuf.starts_with(MAGIC));
+        let (format, from_protobuf) = read_all(&protobuf);
+        assert_eq!(format, Format::Protobuf);
+        assert_eq!(from_protobuf, updates);
+
+        let json = write_all(&from_protobuf, Format::Json);
+        assert!(json.starts_with(b"{\"v\":1}\n"));
+        let (format, from_json) = read_all(&json);
+        assert_eq!(format, Format::Json);
+        assert_eq!(from_json, updates);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(Reader::new(&b"hello world"[..]).is_err());
+        assert!(Reader::new(&b""[..]).is_err());
+        assert!(Reader::new(&b"{\"v\":2}\n"[..]).is_err());
+
+        let mut future = MAGIC.to_vec();
+        future.push(VERSION + 1);
+        assert!(Reader::new(&future[..]).is_err());
+    }
+
+    #[test]
+    fn truncated_recording_is_an_error() {
+        let mut protobuf = write_all(&recorded_updates()[..2], Format::Protobuf);
+        protobuf.truncate(protobuf.len() - 1);
+        let mut reader = Reader::new(&protobuf[..]).unwrap();
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_err());
+    }
+}
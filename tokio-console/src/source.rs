@@ -0,0 +1,168 @@
+//! Previews of the source code around tasks' spawn locations, for the
+//! task details view.
+//!
+//! Spawn locations are reported as the paths the target was built with, which
+//! may not exist on the machine the console runs on, such as when the target
+//! was built in a container. The `[source_remap]` table of the config file
+//! maps path prefixes from the build machine to local directories:
+//!
+//! ```toml
+//! [source_remap]
+//! "/usr/src/app" = "/home/me/code/app"
+//! ```
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+pub(crate) use crate::state::SourceLocation;
+
+/// Files larger than this are never read, as they're almost certainly not
+/// source code.
+const MAX_FILE_LEN: u64 = 4 * 1024 * 1024;
+
+/// Reads and caches the source files that spawn locations refer to.
+///
+/// Each file is only read once, however many tasks were spawned in it, and
+/// files which can't be found aren't looked for again.
+#[derive(Debug, Default)]
+pub(crate) struct Sources {
+    /// Path prefixes to replace, longest first, so that the most specific
+    /// remapping applies.
+    remap: Vec<(PathBuf, PathBuf)>,
+    files: HashMap<String, Option<SourceFile>>,
+}
+
+#[derive(Debug, Clone)]
+struct SourceFile {
+    path: PathBuf,
+    lines: Rc<[String]>,
+}
+
+/// The lines around a spawn location.
+#[derive(Debug, Clone)]
+pub(crate) struct Preview {
+    /// Where the file was found on the local filesystem.
+    pub(crate) path: PathBuf,
+    /// The number of the first line in `lines`.
+    pub(crate) first_line: u32,
+    /// The line the task was spawned at.
+    pub(crate) line: u32,
+    pub(crate) lines: Vec<String>,
+}
+
+// === impl Sources ===
+
+impl Sources {
+    pub(crate) fn new(remap: BTreeMap<PathBuf, PathBuf>) -> Self {
+        let mut remap: Vec<_> = remap.into_iter().collect();
+        remap.sort_by_key(|(from, _)| std::cmp::Reverse(from.components().count()));
+        Self {
+            remap,
+            files: HashMap::new(),
+        }
+    }
+
+    /// Returns up to `context` lines either side of `location`, or `None` if
+    /// its file can't be found or doesn't have that line.
+    pub(crate) fn preview(&mut self, location: &SourceLocation, context: u32) -> Option<Preview> {
+        let file = match self.files.get(&location.file) {
+            Some(file) => file.clone(),
+            None => {
+                let file = self.load(&location.file);
+                self.files.insert(location.file.clone(), file.clone());
+                file
+            }
+        }?;
+        let index = location.line.checked_sub(1)? as usize;
+        if index >= file.lines.len() {
+            return None;
+        }
+        let first = index.saturating_sub(context as usize);
+        let last = (index + context as usize + 1).min(file.lines.len());
+        Some(Preview {
+            path: file.path,
+            first_line: first as u32 + 1,
+            line: location.line,
+            lines: file.lines[first..last].to_vec(),
+        })
+    }
+
+    fn load(&self, file: &str) -> Option<SourceFile> {
+        let path = self.candidates(Path::new(file)).find(|path| {
+            fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.len() <= MAX_FILE_LEN)
+        })?;
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(error) => {
+                tracing::debug!(%error, path = %path.display(), "failed to read source file");
+                return None;
+            }
+        };
+        tracing::debug!(file, path = %path.display(), "loaded source file");
+        Some(SourceFile {
+            path,
+            lines: text.lines().map(str::to_owned).collect(),
+        })
+    }
+
+    /// Returns the local paths a file reported by the target might be at:
+    /// each remapped path, then the path itself.
+    fn candidates<'a>(&'a self, file: &'a Path) -> impl Iterator<Item = PathBuf> + 'a {
+        self.remap
+            .iter()
+            .filter_map(move |(from, to)| Some(to.join(file.strip_prefix(from).ok()?)))
+            .chain(std::iter::once(file.to_path_buf()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn previews_remapped_files() {
+        let dir = std::env::temp_dir().join(format!("tokio-console-source-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        let source: String = (1..=10).map(|line| format!("line {}\n", line)).collect();
+        fs::write(dir.join("src/main.rs"), source).unwrap();
+
+        let mut sources = Sources::new(BTreeMap::from([
+            (PathBuf::from("/build"), PathBuf::from("/nonexistent")),
+            (PathBuf::from("/build/app"), dir.clone()),
+        ]));
+        let location = |line| SourceLocation {
+            file: "/build/app/src/main.rs".to_owned(),
+            line,
+        };
+
+        let preview = sources
+            .preview(&location(5), 2)
+            .expect("file should be found");
+        assert_eq!(preview.path, dir.join("src/main.rs"));
+        assert_eq!(preview.first_line, 3);
+        assert_eq!(
+            preview.lines,
+            ["line 3", "line 4", "line 5", "line 6", "line 7"]
+        );
+
+        // The context is cut off at the start and end of the file.
+        let preview = sources.preview(&location(10), 2).unwrap();
+        assert_eq!(preview.first_line, 8);
+        assert_eq!(preview.lines.len(), 3);
+        assert!(sources.preview(&location(11), 2).is_none());
+        assert!(sources.preview(&location(0), 2).is_none());
+
+        // Changes aren't picked up, as the file is only read once.
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(sources.preview(&location(1), 2).is_some());
+
+        let missing = SourceLocation {
+            file: "/build/other/src/lib.rs".to_owned(),
+            line: 1,
+        };
+        assert!(sources.preview(&missing, 2).is_none());
+    }
+}
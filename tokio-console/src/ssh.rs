@@ -0,0 +1,176 @@
+//! Reaching remote targets through an SSH port forward, for `--ssh`.
+//!
+//! The console connects to a local port which `ssh` forwards to the target
+//! address as seen from the remote machine, so that a subscriber which is
+//! only bound to localhost there can still be inspected.
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Help, SectionExt,
+};
+use std::{
+    process::Stdio,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    net::{TcpListener, TcpStream},
+    process::{Child, ChildStderr, Command},
+};
+use tonic::transport::Uri;
+
+/// How long to wait for `ssh` to set up the port forward before giving up.
+///
+/// This includes the time taken to enter a password or passphrase, if `ssh`
+/// prompts for one, so it's generous.
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The most of `ssh`'s output which is kept to report if it fails to set up
+/// the port forward. Everything it prints is logged as well.
+const MAX_REPORTED_OUTPUT: usize = 4 * 1024;
+
+/// A running `ssh` port forward.
+///
+/// The `ssh` process is killed when this is dropped.
+#[derive(Debug)]
+pub(crate) struct SshTunnel {
+    // Held so that the process is killed on drop.
+    _child: Child,
+    local: Uri,
+}
+
+impl SshTunnel {
+    /// Spawns `ssh` to forward a local port to `target` on `destination`, and
+    /// waits until the forward is accepting connections.
+    ///
+    /// `destination` is passed to `ssh` as is, so it may be anything `ssh`
+    /// accepts, such as `user@host` or a host alias from `~/.ssh/config`.
+    pub(crate) async fn open(destination: &str, target: &Uri) -> color_eyre::Result<Self> {
+        let remote = match target.scheme_str() {
+            Some("file") => target.path().to_owned(),
+            scheme => {
+                let host = target
+                    .host()
+                    .ok_or_else(|| eyre!("target address {} has no host", target))?;
+                let port =
+                    target
+                        .port_u16()
+                        .unwrap_or(if scheme == Some("https") { 443 } else { 80 });
+                format!("{}:{}", host, port)
+            }
+        };
+        let port = free_local_port().await?;
+        let forward = format!("127.0.0.1:{}:{}", port, remote);
+        tracing::debug!(%destination, %forward, "starting ssh port forward");
+
+        // Leave stdin attached, so that ssh can still prompt for a password or
+        // passphrase before the console takes over the terminal.
+        let mut child = Command::new("ssh")
+            .args(["-N", "-o", "ExitOnForwardFailure=yes", "-L"])
+            .arg(&forward)
+            // Keep a destination starting with `-` from being taken as an
+            // option.
+            .arg("--")
+            .arg(destination)
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .wrap_err("failed to run `ssh`")
+            .suggestion("make sure an OpenSSH client is installed and on the PATH")?;
+
+        // ssh keeps writing to stderr for as long as it runs, so it has to be
+        // read continually, or ssh blocks once the pipe's buffer fills up.
+        let output = Arc::new(Mutex::new(String::new()));
+        let drain = child
+            .stderr
+            .take()
+            .map(|stderr| tokio::spawn(drain_stderr(stderr, output.clone())));
+        let reported_output = || {
+            output
+                .lock()
+                .map(|output| output.trim().to_owned())
+                .unwrap_or_default()
+                .header("ssh output:")
+        };
+
+        // ssh only listens on the local port once it has connected and the
+        // forward was set up, so wait for either that or for it to exit.
+        let exited = tokio::time::timeout(FORWARD_TIMEOUT, async {
+            loop {
+                if let Some(status) = child.try_wait()? {
+                    return Ok::<_, std::io::Error>(Some(status));
+                }
+                if TcpStream::connect(("127.0.0.1", port)).await.is_ok() {
+                    return Ok(None);
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .map_err(|_| {
+            eyre!(
+                "ssh did not set up the port forward within {:?}",
+                FORWARD_TIMEOUT
+            )
+            .with_section(reported_output)
+            .suggestion("check that the destination is reachable with `ssh` alone")
+        })?
+        .wrap_err("failed to wait for `ssh`")?;
+        if let Some(status) = exited {
+            // Wait for the rest of what ssh printed before it exited.
+            if let Some(drain) = drain {
+                let _ = drain.await;
+            }
+            return Err(eyre!(
+                "ssh exited before the port forward was set up ({status})"
+            ))
+            .with_section(reported_output);
+        }
+
+        let scheme = if target.scheme_str() == Some("https") {
+            "https"
+        } else {
+            "http"
+        };
+        let local = format!("{}://127.0.0.1:{}", scheme, port)
+            .parse()
+            .wrap_err("forwarded address should be a valid URI")?;
+        tracing::info!(%destination, %local, "ssh port forward ready");
+        Ok(Self {
+            _child: child,
+            local,
+        })
+    }
+
+    /// Returns the local address which is forwarded to the target.
+    pub(crate) fn local_addr(&self) -> &Uri {
+        &self.local
+    }
+}
+
+/// Logs each line `ssh` writes to `stderr` until it exits, keeping the start
+/// of its output in `output`.
+async fn drain_stderr(stderr: ChildStderr, output: Arc<Mutex<String>>) {
+    // Read raw lines, so that output which isn't valid UTF-8 doesn't stop
+    // the pipe from being drained.
+    let mut lines = BufReader::new(stderr).split(b'\n');
+    while let Ok(Some(line)) = lines.next_segment().await {
+        let line = String::from_utf8_lossy(&line);
+        tracing::debug!(%line, "ssh output");
+        if let Ok(mut output) = output.lock() {
+            if output.len() + line.len() < MAX_REPORTED_OUTPUT {
+                output.push_str(&line);
+                output.push('\n');
+            }
+        }
+    }
+}
+
+/// Asks the OS for a local port which is not currently in use.
+async fn free_local_port() -> color_eyre::Result<u16> {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .wrap_err("failed to find a free local port for the ssh port forward")?;
+    Ok(listener.local_addr()?.port())
+}
@@ -0,0 +1,210 @@
+//! Non-interactive output for `tokio-console top`.
+//!
+//! This is a plain-text (or JSON) render path for the task list, parallel to
+//! the TUI in [`crate::view`], for use in scripts and CI.
+use crate::{
+    config::TopFormat,
+    conn::{Connection, Message},
+    state::{tasks::Task, DisplayedList, State},
+};
+use color_eyre::eyre::{eyre, WrapErr};
+use serde::Serialize;
+use std::{
+    cmp::Reverse,
+    fmt::Write,
+    time::{Duration, SystemTime},
+};
+
+/// Options for `tokio-console top`.
+#[derive(Debug)]
+pub(crate) struct TopOptions {
+    pub(crate) format: TopFormat,
+    pub(crate) limit: Option<usize>,
+    pub(crate) updates: usize,
+    pub(crate) timeout: Duration,
+}
+
+/// A single task, as printed by `tokio-console top`.
+#[derive(Debug, Serialize)]
+struct TaskRow {
+    /// The task's ID, as assigned by the runtime.
+    id: String,
+    name: Option<String>,
+    state: &'static str,
+    kind: String,
+    runtime: Option<String>,
+    target: String,
+    location: String,
+    total_ns: u64,
+    busy_ns: u64,
+    scheduled_ns: u64,
+    idle_ns: u64,
+    polls: u64,
+    warnings: Vec<String>,
+}
+
+/// Waits for `options.updates` updates from `conn`, then prints the tasks
+/// seen so far to stdout, sorted by busy time.
+pub(crate) async fn run(
+    mut conn: Connection,
+    mut state: State,
+    options: TopOptions,
+) -> color_eyre::Result<()> {
+    let receive = async {
+        let mut received = 0;
+        while received < options.updates {
+            if let Message::Update(update) = conn.next_message().await {
+                state.update(Some(DisplayedList::Tasks), update);
+                received += 1;
+            }
+        }
+    };
+    tokio::time::timeout(options.timeout, receive)
+        .await
+        .map_err(|_| {
+            eyre!(
+                "timed out after {:?} waiting for {} updates",
+                options.timeout,
+                options.updates
+            )
+        })?;
+
+    let now = state.last_updated_at().unwrap_or_else(SystemTime::now);
+    let mut rows = state
+        .tasks_state()
+        .tasks()
+        .map(|task| TaskRow::new(&task.borrow(), now))
+        .collect::<Vec<_>>();
+    rows.sort_by_key(|row| Reverse(row.busy_ns));
+    if let Some(limit) = options.limit {
+        rows.truncate(limit);
+    }
+
+    let output = match options.format {
+        TopFormat::Text => render_text(&rows),
+        TopFormat::Json => {
+            serde_json::to_string_pretty(&rows).wrap_err("failed to serialize tasks as JSON")?
+        }
+    };
+    println!("{}", output);
+    Ok(())
+}
+
+impl TaskRow {
+    fn new(task: &Task, now: SystemTime) -> Self {
+        Self {
+            id: task.id_str().to_string(),
+            name: task.name().map(String::from),
+            state: task.state().as_str(),
+            kind: task.kind().to_string(),
+            runtime: task.runtime().map(String::from),
+            target: task.target().to_string(),
+            location: task.location().to_string(),
+            total_ns: task.total(now).as_nanos() as u64,
+            busy_ns: task.busy(now).as_nanos() as u64,
+            scheduled_ns: task.scheduled(now).as_nanos() as u64,
+            idle_ns: task.idle(now).as_nanos() as u64,
+            polls: task.total_polls(),
+            warnings: task
+                .warnings()
+                .iter()
+                .map(|warning| warning.format(task))
+                .collect(),
+        }
+    }
+}
+
+fn render_text(rows: &[TaskRow]) -> String {
+    const HEADER: [&str; 11] = [
+        "ID", "Name", "State", "Total", "Busy", "Sched", "Idle", "Polls", "Kind", "Runtime",
+        "Location",
+    ];
+    // Columns after this one are left-aligned.
+    const LAST_NUMERIC: usize = 7;
+
+    let table = rows
+        .iter()
+        .map(|row| {
+            [
+                row.id.clone(),
+                row.name.clone().unwrap_or_default(),
+                row.state.to_string(),
+                duration(row.total_ns),
+                duration(row.busy_ns),
+                duration(row.scheduled_ns),
+                duration(row.idle_ns),
+                row.polls.to_string(),
+                row.kind.clone(),
+                row.runtime.clone().unwrap_or_default(),
+                row.location.clone(),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let mut widths = HEADER.map(str::len);
+    for row in &table {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut output = String::new();
+    let header = HEADER.map(String::from);
+    for row in std::iter::once(&header).chain(&table) {
+        let mut line = String::new();
+        for (i, (cell, width)) in row.iter().zip(widths).enumerate() {
+            let cell = match i {
+                0 | 3..=LAST_NUMERIC => format!("{:>width$}", cell),
+                _ => format!("{:<width$}", cell),
+            };
+            let _ = write!(line, "{} ", cell);
+        }
+        output.push_str(line.trim_end());
+        output.push('\n');
+    }
+    output.pop();
+    output
+}
+
+fn duration(nanos: u64) -> String {
+    format!("{:.2?}", Duration::from_nanos(nanos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(id: &str, name: Option<&str>, busy_ns: u64) -> TaskRow {
+        TaskRow {
+            id: id.to_string(),
+            name: name.map(String::from),
+            state: "idle",
+            kind: "task".to_string(),
+            runtime: None,
+            target: "tokio::task".to_string(),
+            location: "src/main.rs:10:5".to_string(),
+            total_ns: 2_000_000_000,
+            busy_ns,
+            scheduled_ns: 0,
+            idle_ns: 2_000_000_000 - busy_ns,
+            polls: 3,
+            warnings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn render_text_aligns_columns() {
+        let rows = [
+            row("1", Some("accept-loop"), 1_500_000),
+            row("12", None, 20_000),
+        ];
+        let text = render_text(&rows);
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3, "{}", text);
+        assert!(lines[0].starts_with("ID Name        State"), "{}", text);
+        assert!(lines[1].starts_with(" 1 accept-loop idle "), "{}", text);
+        assert!(lines[2].starts_with("12             idle "), "{}", text);
+        assert!(lines[1].contains("1.50ms"), "{}", text);
+        assert!(lines[1].ends_with("src/main.rs:10:5"), "{}", text);
+    }
+}
@@ -0,0 +1,250 @@
+use crate::{
+    input,
+    state::{async_ops::AsyncOp, State},
+    view::{
+        self, bold,
+        controls::{controls_paragraph, ControlDisplay, Controls, KeyDisplay},
+        help::{HelpText, Hint},
+        resource::ResourceView,
+        Role,
+    },
+};
+use once_cell::sync::OnceCell;
+use ratatui::{
+    layout::{self, Layout},
+    symbols,
+    text::{Line, Span},
+    widgets::{List, ListItem, Paragraph, Sparkline},
+};
+use std::{cell::RefCell, rc::Rc, time::SystemTime};
+
+pub(crate) struct AsyncOpView {
+    async_op: Rc<RefCell<AsyncOp>>,
+    /// The view of the async op's resource, to return to when this view is
+    /// exited.
+    pub(crate) resource_view: ResourceView,
+}
+
+impl AsyncOpView {
+    pub(super) fn new(async_op: Rc<RefCell<AsyncOp>>, resource_view: ResourceView) -> Self {
+        AsyncOpView {
+            async_op,
+            resource_view,
+        }
+    }
+
+    pub(crate) fn async_op(&self) -> &Rc<RefCell<AsyncOp>> {
+        &self.async_op
+    }
+
+    pub(crate) fn update_input(&mut self, _event: input::Event) {}
+
+    pub(crate) fn render(
+        &mut self,
+        styles: &view::Styles,
+        frame: &mut ratatui::terminal::Frame,
+        area: layout::Rect,
+        state: &mut State,
+    ) {
+        let now = state
+            .last_updated_at()
+            .expect("async op view implies we've received an update");
+        let async_op = &*self.async_op.borrow();
+        let resource = self.resource_view.resource().borrow();
+        let task = async_op
+            .task_id()
+            .and_then(|id| state.tasks_state().task(id))
+            .and_then(|task| task.upgrade());
+
+        let controls = Controls::new(view_controls(), &area, styles);
+        // The overview box shows five lines, and the timing box up to seven,
+        // plus their top and bottom borders.
+        let overview_height = 7;
+        let stacked = area.width < STACK_PANES_BELOW;
+        let stats_height = if stacked { overview_height + 9 } else { 10 };
+
+        let chunks = Layout::default()
+            .direction(layout::Direction::Vertical)
+            .constraints(
+                [
+                    // controls
+                    layout::Constraint::Length(controls.height()),
+                    // async op overview and timing
+                    layout::Constraint::Length(stats_height),
+                    // poll history
+                    layout::Constraint::Length(6),
+                    // attributes
+                    layout::Constraint::Percentage(60),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+        let (controls_area, stats_area, history_area, attributes_area) =
+            (chunks[0], chunks[1], chunks[2], chunks[3]);
+
+        let stats_area = view::split_panes(stats_area, stacked, overview_height);
+        let attributes_area =
+            view::split_panes(attributes_area, stacked, attributes_area.height / 2);
+
+        let task_desc = match task {
+            Some(ref task) => task.borrow().short_desc().to_owned(),
+            None => async_op.task_id_str().to_owned(),
+        };
+        let overview = vec![
+            Line::from(vec![bold("ID: "), Span::raw(async_op.id().to_string())]),
+            Line::from(vec![bold("Parent ID: "), Span::raw(async_op.parent_id())]),
+            Line::from(vec![
+                bold("Resource: "),
+                Span::raw(resource.id_str().to_owned()),
+                Span::raw(" ("),
+                Span::raw(resource.concrete_type().to_owned()),
+                Span::raw(")"),
+            ]),
+            Line::from(vec![bold("Task: "), Span::raw(task_desc)]),
+            Line::from(vec![bold("Source: "), Span::raw(async_op.source())]),
+        ];
+
+        let dur = |dur| styles.time_units(dur, view::DUR_LIST_PRECISION, None);
+        let ago = |time: SystemTime| {
+            let mut spans = vec![dur(now.duration_since(time).unwrap_or_default())];
+            spans.push(Span::raw(" ago"));
+            spans
+        };
+        let mut timing = vec![
+            Line::from(vec![bold("Total Time: "), dur(async_op.total(now))]),
+            Line::from(vec![bold("Busy: "), dur(async_op.busy(now))]),
+            Line::from(vec![bold("Idle: "), dur(async_op.idle(now))]),
+        ];
+        if let Some(pending) = async_op.pending() {
+            timing.push(Line::from(vec![bold("Pending: "), dur(pending)]));
+        }
+        timing.push(Line::from(vec![
+            bold("Polls: "),
+            Span::raw(async_op.total_polls().to_string()),
+        ]));
+        if let Some(started) = async_op.last_poll_started() {
+            let mut line = vec![bold("Last Poll: ")];
+            line.extend(ago(started));
+            if async_op.last_poll_ended().is_none() {
+                line.push(Span::styled(" (in progress)", styles.role(Role::Good)));
+            }
+            timing.push(Line::from(line));
+        }
+        let mut created = vec![bold("Created: ")];
+        created.extend(ago(async_op.created_at()));
+        if let Some(dropped_at) = async_op.dropped_at() {
+            created.push(Span::raw(", dropped "));
+            created.extend(ago(dropped_at));
+        }
+        timing.push(Line::from(created));
+
+        // The number of polls in each interval between stats updates.
+        let samples = async_op.poll_history().collect::<Vec<_>>();
+        let polls = samples
+            .windows(2)
+            .map(|pair| pair[1].polls.saturating_sub(pair[0].polls))
+            .collect::<Vec<_>>();
+        let mut history_title = vec![bold("Polls per update")];
+        if let Some(first) = samples.first() {
+            history_title.push(Span::raw(" (since "));
+            history_title.extend(ago(first.at));
+            history_title.push(Span::raw(")"));
+        }
+        let history_block = styles.border_block().title(Line::from(history_title));
+        let history_inner = history_block.inner(history_area);
+        let shown = &polls[polls.len().saturating_sub(history_inner.width as usize)..];
+        let bar_set = if styles.utf8 {
+            symbols::bar::NINE_LEVELS
+        } else {
+            view::tasks::ASCII_BARS
+        };
+        let history = Sparkline::default()
+            .data(shown)
+            .bar_set(bar_set)
+            .style(styles.role(Role::Histogram))
+            .block(history_block);
+
+        let attributes = view::attributes(styles, async_op.attributes())
+            .into_iter()
+            .map(Line::from)
+            .collect::<Vec<_>>();
+        let changes = async_op
+            .attribute_history()
+            .rev()
+            .map(|change| {
+                let mut line = ago(change.at);
+                line.push(Span::raw(": "));
+                line.extend(
+                    view::attributes(styles, &change.attributes)
+                        .into_iter()
+                        .flatten(),
+                );
+                ListItem::new(Line::from(line))
+            })
+            .collect::<Vec<_>>();
+
+        frame.render_widget(controls.into_widget(), controls_area);
+        frame.render_widget(
+            Paragraph::new(overview).block(styles.border_block().title("Async Op")),
+            stats_area[0],
+        );
+        frame.render_widget(
+            Paragraph::new(timing).block(styles.border_block().title("Timing")),
+            stats_area[1],
+        );
+        frame.render_widget(history, history_area);
+        frame.render_widget(
+            Paragraph::new(attributes).block(styles.border_block().title("Attributes")),
+            attributes_area[0],
+        );
+        frame.render_widget(
+            List::new(changes).block(styles.border_block().title("Attribute Changes")),
+            attributes_area[1],
+        );
+    }
+}
+
+impl HelpText for AsyncOpView {
+    fn render_help_content(&self, styles: &view::Styles) -> Paragraph<'static> {
+        controls_paragraph(view_controls(), styles)
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        vec![
+            Hint::enabled("esc", "return to resource"),
+            Hint::when(
+                self.async_op.borrow().task_id().is_some(),
+                "enter",
+                "view task",
+                "not polled by a task yet",
+            ),
+        ]
+    }
+}
+
+/// Below this width, the overview and timing boxes, and the attributes and
+/// their history, are stacked vertically.
+const STACK_PANES_BELOW: u16 = 100;
+
+fn view_controls() -> &'static [ControlDisplay] {
+    static VIEW_CONTROLS: OnceCell<Vec<ControlDisplay>> = OnceCell::new();
+
+    VIEW_CONTROLS.get_or_init(|| {
+        vec![
+            ControlDisplay {
+                action: "return to resource",
+                keys: &[KeyDisplay {
+                    base: "esc",
+                    utf8: Some("\u{238B} esc"),
+                }],
+            },
+            ControlDisplay {
+                action: "view task",
+                keys: &[KeyDisplay {
+                    base: "enter",
+                    utf8: Some("\u{21B5}"),
+                }],
+            },
+        ]
+    })
+}
@@ -7,14 +7,14 @@ use crate::{
     },
     view::{
         self, bold,
-        table::{TableList, TableListState},
-        DUR_LEN, DUR_TABLE_PRECISION,
+        table::{attributes_text, Breakpoint, Columns, TableList, TableListState},
+        DUR_TABLE_PRECISION,
     },
 };
 
 use ratatui::{
     layout,
-    style::{self, Color, Style},
+    style::{self, Style},
     text::Line,
     widgets::{Cell, Row, Table},
 };
@@ -27,24 +27,31 @@ pub(crate) struct AsyncOpsTableCtx {
     pub(crate) resource_id: Id<Resource>,
 }
 
-impl TableList<9> for AsyncOpsTable {
+impl view::SortBy for SortBy {
+    fn as_column(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl TableList<10> for AsyncOpsTable {
     type Row = AsyncOp;
     type Sort = SortBy;
     type Context = AsyncOpsTableCtx;
 
-    const HEADER: &'static [&'static str; 9] = &[
+    const HEADER: &'static [&'static str; 10] = &[
         "ID",
         "Parent",
         "Task",
         "Source",
         "Total",
         "Busy",
+        "Pending",
         "Idle",
         "Polls",
         "Attributes",
     ];
 
-    const WIDTHS: &'static [usize; 9] = &[
+    const WIDTHS: &'static [usize; 10] = &[
         Self::HEADER[0].len() + 1,
         Self::HEADER[1].len() + 1,
         Self::HEADER[2].len() + 1,
@@ -54,10 +61,44 @@ impl TableList<9> for AsyncOpsTable {
         Self::HEADER[6].len() + 1,
         Self::HEADER[7].len() + 1,
         Self::HEADER[8].len() + 1,
+        Self::HEADER[9].len() + 1,
+    ];
+
+    const BREAKPOINTS: &'static [Breakpoint] = &[
+        // Parent, Pending
+        Breakpoint {
+            below: 120,
+            hide: &[1, 6],
+        },
+        // Busy, Idle
+        Breakpoint {
+            below: 80,
+            hide: &[5, 7],
+        },
     ];
 
+    const COMPACT_BELOW: u16 = 100;
+
+    fn export_row(async_op: &AsyncOp, now: std::time::SystemTime) -> [String; 10] {
+        [
+            async_op.id().to_string(),
+            async_op.parent_id().to_owned(),
+            async_op.task_id_str().to_owned(),
+            async_op.source().to_owned(),
+            format!("{:?}", async_op.total(now)),
+            format!("{:?}", async_op.busy(now)),
+            async_op
+                .pending()
+                .map(|pending| format!("{:?}", pending))
+                .unwrap_or_default(),
+            format!("{:?}", async_op.idle(now)),
+            async_op.total_polls().to_string(),
+            attributes_text(async_op.attributes()),
+        ]
+    }
+
     fn render(
-        table_list_state: &mut TableListState<Self, 9>,
+        table_list_state: &mut TableListState<Self, 10>,
         styles: &view::Styles,
         frame: &mut ratatui::terminal::Frame,
         area: layout::Rect,
@@ -71,6 +112,13 @@ impl TableList<9> for AsyncOpsTable {
             return;
         };
 
+        let columns = Columns::for_width::<Self>(area.width);
+        let styles = &if columns.compact() {
+            styles.compact_durations()
+        } else {
+            styles.clone()
+        };
+
         let AsyncOpsTableCtx {
             initial_render,
             resource_id,
@@ -96,18 +144,16 @@ impl TableList<9> for AsyncOpsTable {
                     }),
             )
         };
-        table_list_state
-            .sort_by
-            .sort(now, &mut table_list_state.sorted_items);
+        table_list_state.sort_items(|sort_by, ops| sort_by.sort(now, ops));
 
         let mut id_width = view::Width::new(Self::WIDTHS[0] as u16);
         let mut parent_width = view::Width::new(Self::WIDTHS[1] as u16);
         let mut task_width = view::Width::new(Self::WIDTHS[2] as u16);
         let mut source_width = view::Width::new(Self::WIDTHS[3] as u16);
-        let mut polls_width = view::Width::new(Self::WIDTHS[7] as u16);
+        let mut polls_width = view::Width::new(Self::WIDTHS[8] as u16);
 
         let dur_cell = |dur: std::time::Duration| -> Cell<'static> {
-            Cell::from(styles.time_units(dur, DUR_TABLE_PRECISION, Some(DUR_LEN)))
+            Cell::from(styles.time_units(dur, DUR_TABLE_PRECISION, Some(styles.duration_width())))
         };
 
         let rows = {
@@ -131,28 +177,29 @@ impl TableList<9> for AsyncOpsTable {
                         .map(|t| t.borrow().short_desc().to_owned());
                     let task_str = task.unwrap_or_else(|| async_op.task_id_str().to_owned());
 
-                    let mut row = Row::new(vec![
-                        Cell::from(id_width.update_str(format!(
-                            "{:>width$}",
-                            async_op.id(),
-                            width = id_width.chars() as usize
-                        ))),
-                        Cell::from(parent_width.update_str(async_op.parent_id()).to_owned()),
-                        Cell::from(task_width.update_str(task_str)),
-                        Cell::from(source_width.update_str(async_op.source()).to_owned()),
-                        dur_cell(async_op.total(now)),
-                        dur_cell(async_op.busy(now)),
-                        dur_cell(async_op.idle(now)),
-                        Cell::from(polls_width.update_str(async_op.total_polls().to_string())),
-                        Cell::from(Line::from(
-                            async_op
-                                .formatted_attributes()
-                                .iter()
-                                .flatten()
-                                .cloned()
-                                .collect::<Vec<_>>(),
-                        )),
-                    ]);
+                    let mut row = Row::new(
+                        columns.select([
+                            Cell::from(id_width.update_str(format!(
+                                "{:>width$}",
+                                async_op.id(),
+                                width = id_width.chars() as usize
+                            ))),
+                            Cell::from(parent_width.update_str(async_op.parent_id()).to_owned()),
+                            Cell::from(task_width.update_str(task_str)),
+                            Cell::from(source_width.update_str(async_op.source()).to_owned()),
+                            dur_cell(async_op.total(now)),
+                            dur_cell(async_op.busy(now)),
+                            async_op.pending().map(dur_cell).unwrap_or_default(),
+                            dur_cell(async_op.idle(now)),
+                            Cell::from(polls_width.update_str(async_op.total_polls().to_string())),
+                            Cell::from(Line::from(
+                                view::attributes(styles, async_op.attributes())
+                                    .into_iter()
+                                    .flatten()
+                                    .collect::<Vec<_>>(),
+                            )),
+                        ]),
+                    );
 
                     if async_op.dropped() {
                         row = row.style(styles.terminated());
@@ -162,24 +209,21 @@ impl TableList<9> for AsyncOpsTable {
                 })
         };
 
-        let header_style = if styles.color(Color::Cyan).is_some() {
-            Style::default()
-        } else {
-            Style::default().add_modifier(style::Modifier::REVERSED)
-        };
-        let header_style = header_style.add_modifier(style::Modifier::BOLD);
+        let header_style = styles.table_header();
 
-        let header = Row::new(Self::HEADER.iter().enumerate().map(|(idx, &value)| {
-            if idx == table_list_state.selected_column {
-                if table_list_state.sort_descending {
-                    Cell::from(styles.ascending(value))
+        let header = Row::new(columns.select(Self::HEADER.iter().enumerate().map(
+            |(idx, &value)| {
+                if idx == table_list_state.selected_column {
+                    if table_list_state.sort_descending {
+                        Cell::from(styles.ascending(value))
+                    } else {
+                        Cell::from(styles.descending(value))
+                    }
                 } else {
-                    Cell::from(styles.descending(value))
+                    Cell::from(value)
                 }
-            } else {
-                Cell::from(value)
-            }
-        }))
+            },
+        )))
         .height(1)
         .style(header_style);
 
@@ -195,17 +239,18 @@ impl TableList<9> for AsyncOpsTable {
         ))]);
 
         let attributes_width = layout::Constraint::Percentage(100);
-        let widths = &[
+        let widths = columns.select([
             id_width.constraint(),
             parent_width.constraint(),
             task_width.constraint(),
             source_width.constraint(),
-            layout::Constraint::Length(DUR_LEN as u16),
-            layout::Constraint::Length(DUR_LEN as u16),
-            layout::Constraint::Length(DUR_LEN as u16),
+            layout::Constraint::Length(styles.duration_width() as u16),
+            layout::Constraint::Length(styles.duration_width() as u16),
+            layout::Constraint::Length(styles.duration_width() as u16),
+            layout::Constraint::Length(styles.duration_width() as u16),
             polls_width.constraint(),
             attributes_width,
-        ];
+        ]);
 
         let table = table
             .header(header)
@@ -0,0 +1,178 @@
+//! The command palette, opened with `:`, which runs commands typed by name
+//! rather than bound to a key.
+use crate::{
+    input,
+    view::{self, bold},
+};
+use ratatui::{
+    text::{Line, Span},
+    widgets::Paragraph,
+};
+use std::str::FromStr;
+
+/// A command typed into the command palette.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Command {
+    /// Show the details of the task with the given ID, or whose name contains
+    /// the given text.
+    Task(String),
+    /// Show the details of the resource with the given ID, or whose type
+    /// contains the given text.
+    Resource(String),
+    /// Sort the current list by the column with the given name.
+    Sort(String),
+    /// Show the tasks list.
+    Tasks,
+    /// Show the resources list.
+    Resources,
+    /// Pause the console.
+    Pause,
+    /// Resume the console.
+    Resume,
+}
+
+/// The text being typed into the command palette.
+#[derive(Debug, Default)]
+pub(crate) struct CommandPalette {
+    input: String,
+}
+
+/// What the command palette did with a key press.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum PaletteInput {
+    /// The command is still being typed.
+    Editing,
+    /// The palette was closed without running a command.
+    Cancelled,
+    /// The typed command should be run.
+    Submitted(String),
+}
+
+const COMMANDS: &str = "task, resource, sort, tasks, resources, pause, resume";
+
+// === impl CommandPalette ===
+
+impl CommandPalette {
+    pub(crate) fn update_input(&mut self, event: &input::Event) -> PaletteInput {
+        use input::KeyCode::*;
+        let input::Event::Key(event) = event else {
+            return PaletteInput::Editing;
+        };
+        match event.code {
+            Esc => PaletteInput::Cancelled,
+            Enter => PaletteInput::Submitted(std::mem::take(&mut self.input)),
+            Backspace => match self.input.pop() {
+                Some(_) => PaletteInput::Editing,
+                // Deleting past the start of the command closes the palette,
+                // as it does in vim.
+                None => PaletteInput::Cancelled,
+            },
+            Char(c) => {
+                self.input.push(c);
+                PaletteInput::Editing
+            }
+            _ => PaletteInput::Editing,
+        }
+    }
+
+    /// Renders the command being typed, in place of the hint bar.
+    pub(crate) fn render(&self, styles: &view::Styles) -> Paragraph<'static> {
+        Paragraph::new(Line::from(vec![
+            bold(":"),
+            Span::raw(self.input.clone()),
+            Span::raw(styles.if_utf8("\u{2588}", "_")),
+        ]))
+    }
+}
+
+// === impl Command ===
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, arg) = match s.split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, arg.trim()),
+            None => (s, ""),
+        };
+        let with_arg = |command: fn(String) -> Command| {
+            if arg.is_empty() {
+                Err(format!("`{}` needs an argument", name))
+            } else {
+                Ok(command(arg.to_owned()))
+            }
+        };
+        let without_arg = |command: Command| {
+            if arg.is_empty() {
+                Ok(command)
+            } else {
+                Err(format!("`{}` doesn't take an argument", name))
+            }
+        };
+        match name {
+            "task" => with_arg(Command::Task),
+            "resource" => with_arg(Command::Resource),
+            "sort" => with_arg(Command::Sort),
+            "tasks" => without_arg(Command::Tasks),
+            "resources" => without_arg(Command::Resources),
+            "pause" => without_arg(Command::Pause),
+            "resume" => without_arg(Command::Resume),
+            "" => Err(format!("no command given (commands: {})", COMMANDS)),
+            _ => Err(format!(
+                "unknown command `{}` (commands: {})",
+                name, COMMANDS
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!("task 1234".parse(), Ok(Command::Task("1234".to_owned())));
+        assert_eq!(
+            "  task  my task ".parse(),
+            Ok(Command::Task("my task".to_owned()))
+        );
+        assert_eq!(
+            "resource tcp".parse(),
+            Ok(Command::Resource("tcp".to_owned()))
+        );
+        assert_eq!(
+            "sort last poll".parse(),
+            Ok(Command::Sort("last poll".to_owned()))
+        );
+        assert_eq!("pause".parse(), Ok(Command::Pause));
+        assert_eq!("resources".parse(), Ok(Command::Resources));
+
+        assert!("task".parse::<Command>().is_err());
+        assert!("pause now".parse::<Command>().is_err());
+        assert!("".parse::<Command>().is_err());
+        assert!("jump 12".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn palette_input() {
+        let key = |code| input::Event::Key(input::KeyEvent::new(code, input::KeyModifiers::NONE));
+        let mut palette = CommandPalette::default();
+        for c in "sortx".chars() {
+            assert_eq!(
+                palette.update_input(&key(input::KeyCode::Char(c))),
+                PaletteInput::Editing
+            );
+        }
+        palette.update_input(&key(input::KeyCode::Backspace));
+        assert_eq!(
+            palette.update_input(&key(input::KeyCode::Enter)),
+            PaletteInput::Submitted("sort".to_owned())
+        );
+        assert_eq!(
+            palette.update_input(&key(input::KeyCode::Backspace)),
+            PaletteInput::Cancelled
+        );
+    }
+}
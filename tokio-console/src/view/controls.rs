@@ -15,6 +15,83 @@ const UNIVERSAL_CONTROLS: &[ControlDisplay] = &[
             utf8: None,
         }],
     },
+    ControlDisplay {
+        action: "step back/forward (when paused)",
+        keys: &[KeyDisplay {
+            base: "[, ]",
+            utf8: None,
+        }],
+    },
+    ControlDisplay {
+        action: "session stats",
+        keys: &[KeyDisplay {
+            base: "s",
+            utf8: None,
+        }],
+    },
+    ControlDisplay {
+        action: "dropped events",
+        keys: &[KeyDisplay {
+            base: "D",
+            utf8: None,
+        }],
+    },
+    ControlDisplay {
+        action: "stalled tasks",
+        keys: &[KeyDisplay {
+            base: "S",
+            utf8: None,
+        }],
+    },
+    ControlDisplay {
+        action: "timer wakes",
+        keys: &[KeyDisplay {
+            base: "W",
+            utf8: None,
+        }],
+    },
+    ControlDisplay {
+        action: "subscriber health",
+        keys: &[KeyDisplay {
+            base: "H",
+            utf8: None,
+        }],
+    },
+    ControlDisplay {
+        action: "lints",
+        keys: &[KeyDisplay {
+            base: "L",
+            utf8: None,
+        }],
+    },
+    ControlDisplay {
+        action: "duration units",
+        keys: &[KeyDisplay {
+            base: "u",
+            utf8: None,
+        }],
+    },
+    ControlDisplay {
+        action: "theme",
+        keys: &[KeyDisplay {
+            base: "T",
+            utf8: None,
+        }],
+    },
+    ControlDisplay {
+        action: "copy selected",
+        keys: &[KeyDisplay {
+            base: "y",
+            utf8: None,
+        }],
+    },
+    ControlDisplay {
+        action: "command palette",
+        keys: &[KeyDisplay {
+            base: ":",
+            utf8: None,
+        }],
+    },
     ControlDisplay {
         action: "quit",
         keys: &[KeyDisplay {
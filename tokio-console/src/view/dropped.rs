@@ -0,0 +1,121 @@
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    symbols,
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Sparkline, Wrap},
+};
+
+use crate::{
+    state::State,
+    view::{self, Role},
+};
+
+/// Renders a popup showing how many events the target dropped in each recent
+/// update, and how to avoid dropping them.
+pub(crate) fn render(
+    styles: &view::Styles,
+    frame: &mut ratatui::terminal::Frame,
+    state: &mut State,
+) {
+    let totals = [
+        ("tasks", state.tasks_state().dropped_events()),
+        ("resources", state.resources_state().dropped_events()),
+        ("async ops", state.async_ops_state().dropped_events()),
+    ];
+    let history = state.dropped_events();
+    let series: [(&str, Vec<u64>); 3] = [
+        ("tasks", history.samples().map(|s| s.tasks).collect()),
+        (
+            "resources",
+            history.samples().map(|s| s.resources).collect(),
+        ),
+        (
+            "async ops",
+            history.samples().map(|s| s.async_ops).collect(),
+        ),
+    ];
+
+    let area = view::help::popup_area(frame.size());
+    let block = styles.border_block().title("Dropped Events");
+    let inner = block.inner(area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(2),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Min(0),
+            ]
+            .as_ref(),
+        )
+        .split(inner);
+
+    let mut summary = vec![view::bold("Total dropped: ")];
+    for (i, (name, total)) in totals.iter().enumerate() {
+        if i > 0 {
+            summary.push(Span::from(", "));
+        }
+        let text = format!("{} {}", total, name);
+        if *total > 0 {
+            summary.push(Span::styled(text, styles.role(Role::Bad)));
+        } else {
+            summary.push(Span::from(text));
+        }
+    }
+    let summary = vec![
+        Line::from(summary),
+        Line::from(format!(
+            "Events were dropped in {} of the last {} updates",
+            history.updates_with_drops(),
+            history.samples().len(),
+        )),
+    ];
+    frame.render_widget(Paragraph::new(summary), chunks[0]);
+
+    // Line the sparklines up after the longest label.
+    const LABEL_WIDTH: u16 = 12;
+    let bar_set = if styles.utf8 {
+        symbols::bar::NINE_LEVELS
+    } else {
+        view::tasks::ASCII_BARS
+    };
+    for ((name, data), area) in series.iter().zip(&chunks[1..4]) {
+        let halves = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(LABEL_WIDTH), Constraint::Min(0)].as_ref())
+            .split(*area);
+        // Only show as many of the most recent updates as will fit.
+        let shown = &data[data.len().saturating_sub(halves[1].width as usize)..];
+        let sparkline = Sparkline::default()
+            .data(shown)
+            .bar_set(bar_set.clone())
+            .style(styles.role(Role::Bad));
+        frame.render_widget(Paragraph::new(view::bold(format!("{}:", name))), halves[0]);
+        frame.render_widget(sparkline, halves[1]);
+    }
+
+    let remediation = vec![
+        Line::from(""),
+        Line::from(
+            "Events are dropped when the instrumented application produces them faster \
+             than its console subscriber can aggregate them, so some data may be missing.",
+        ),
+        Line::from(""),
+        Line::from(vec![
+            Span::from("To avoid this, increase the subscriber's event buffer by setting the "),
+            view::bold("TOKIO_CONSOLE_BUFFER_CAPACITY"),
+            Span::from(" environment variable, or with "),
+            view::bold("Builder::event_buffer_capacity"),
+            Span::from(". Publishing updates more often also drains the buffer sooner."),
+        ]),
+    ];
+    frame.render_widget(
+        Paragraph::new(remediation).wrap(Wrap { trim: true }),
+        chunks[4],
+    );
+}
@@ -7,7 +7,12 @@ use ratatui::{
 
 use crate::{
     state::histogram::DurationHistogram,
-    view::{self, mini_histogram::MiniHistogram, percentiles::Percentiles},
+    view::{
+        self,
+        mini_histogram::{HistogramSettings, MiniHistogram},
+        percentiles::Percentiles,
+        Role,
+    },
 };
 
 // This is calculated so that a legend like the below generally fits:
@@ -38,6 +43,8 @@ pub(crate) struct Durations<'a> {
     histogram_title: &'a str,
     /// Fixed width for percentiles block
     percentiles_width: u16,
+    /// How the histogram divides the durations into bars
+    settings: HistogramSettings,
 }
 
 impl Widget for Durations<'_> {
@@ -80,10 +87,16 @@ impl Widget for Durations<'_> {
         percentiles_widget.render(percentiles_area, buf);
 
         if let Some(histogram_area) = histogram_area {
+            let title = match self.settings.describe() {
+                Some(settings) => format!("{} ({})", self.histogram_title, settings),
+                None => self.histogram_title.to_owned(),
+            };
             let histogram_widget = MiniHistogram::default()
-                .block(self.styles.border_block().title(self.histogram_title))
+                .block(self.styles.border_block().title(title))
                 .histogram(self.histogram)
-                .duration_precision(2);
+                .settings(self.settings)
+                .duration_precision(2)
+                .style(self.styles.role(Role::Histogram));
             histogram_widget.render(histogram_area, buf);
         }
     }
@@ -97,6 +110,7 @@ impl<'a> Durations<'a> {
             percentiles_title: "Percentiles",
             histogram_title: "Histogram",
             percentiles_width: 0,
+            settings: HistogramSettings::default(),
         }
     }
 
@@ -119,4 +133,9 @@ impl<'a> Durations<'a> {
         self.percentiles_width = width;
         self
     }
+
+    pub(crate) fn settings(mut self, settings: HistogramSettings) -> Self {
+        self.settings = settings;
+        self
+    }
 }
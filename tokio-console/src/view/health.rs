@@ -0,0 +1,187 @@
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::Style,
+    symbols,
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Sparkline, Wrap},
+};
+
+use crate::{
+    state::State,
+    view::{self, Role},
+};
+
+/// The event buffer is considered close to full above this percentage of its
+/// capacity.
+const BUFFER_WARN_PERCENT: f64 = 50.0;
+
+/// Renders a popup showing the health the target's subscriber reported in
+/// recent updates, such as how full its event buffer got, which helps to
+/// explain gaps in the data.
+pub(crate) fn render(
+    styles: &view::Styles,
+    frame: &mut ratatui::terminal::Frame,
+    state: &mut State,
+) {
+    let area = view::help::popup_area(frame.size());
+    let block = styles.border_block().title("Subscriber Health");
+    let inner = block.inner(area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+
+    let health = state.subscriber_health();
+    let Some(latest) = health.latest() else {
+        let text = Paragraph::new(
+            "The target hasn't reported its subscriber's health. It may be using an \
+             older version of console-subscriber.",
+        )
+        .wrap(Wrap { trim: true });
+        frame.render_widget(text, inner);
+        return;
+    };
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(4),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Min(0),
+            ]
+            .as_ref(),
+        )
+        .split(inner);
+
+    let updates = health.samples().len();
+    let percent_role = |percent: f64| {
+        if percent >= BUFFER_WARN_PERCENT {
+            styles.role(Role::Caution)
+        } else {
+            Style::default()
+        }
+    };
+    let peak = health.peak_buffer_percent();
+    let dropped = health.dropped();
+    let dropped_counts = [
+        ("spawns", dropped.spawns),
+        ("resources", dropped.resources),
+        ("async ops", dropped.async_ops),
+        ("poll ops", dropped.poll_ops),
+        ("metadata", dropped.metadata),
+    ];
+    let mut dropped_line = vec![view::bold("Dropped: ")];
+    for (i, (name, count)) in dropped_counts.iter().enumerate() {
+        if i > 0 {
+            dropped_line.push(Span::from(", "));
+        }
+        let text = format!("{} {}", count, name);
+        if *count > 0 {
+            dropped_line.push(Span::styled(text, styles.role(Role::Bad)));
+        } else {
+            dropped_line.push(Span::from(text));
+        }
+    }
+    let flushes = health.flushes();
+    let summary = vec![
+        Line::from(vec![
+            view::bold("Event buffer: "),
+            Span::styled(
+                format!(
+                    "up to {} of {} events ({:.0}%)",
+                    latest.max_buffered,
+                    latest.capacity,
+                    latest.buffer_percent()
+                ),
+                percent_role(latest.buffer_percent()),
+            ),
+            Span::from(", peak "),
+            Span::styled(format!("{:.0}%", peak), percent_role(peak)),
+            Span::from(format!(" in the last {} updates", updates)),
+        ]),
+        Line::from(vec![
+            view::bold("Early flushes: "),
+            Span::styled(
+                flushes.to_string(),
+                if flushes > 0 {
+                    styles.role(Role::Caution)
+                } else {
+                    Style::default()
+                },
+            ),
+            Span::from(format!(" in the last {} updates", updates)),
+        ]),
+        Line::from(dropped_line),
+        Line::from(vec![
+            view::bold("Longest aggregation pass: "),
+            styles.time_units(latest.max_aggregation_time, view::DUR_LIST_PRECISION, None),
+            Span::from(", "),
+            styles.time_units(
+                health.max_aggregation_time(),
+                view::DUR_LIST_PRECISION,
+                None,
+            ),
+            Span::from(format!(" in the last {} updates", updates)),
+        ]),
+    ];
+    frame.render_widget(Paragraph::new(summary), chunks[0]);
+
+    // Line the sparklines up after the longest label.
+    const LABEL_WIDTH: u16 = 14;
+    let bar_set = if styles.utf8 {
+        symbols::bar::NINE_LEVELS
+    } else {
+        view::tasks::ASCII_BARS
+    };
+    let series: [(&str, Vec<u64>); 2] = [
+        (
+            "buffered",
+            health.samples().map(|sample| sample.max_buffered).collect(),
+        ),
+        (
+            "aggregation",
+            health
+                .samples()
+                .map(|sample| sample.max_aggregation_time.as_micros() as u64)
+                .collect(),
+        ),
+    ];
+    for ((name, data), area) in series.iter().zip(&chunks[1..3]) {
+        let halves = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(LABEL_WIDTH), Constraint::Min(0)].as_ref())
+            .split(*area);
+        // Only show as many of the most recent updates as will fit.
+        let shown = &data[data.len().saturating_sub(halves[1].width as usize)..];
+        let sparkline = Sparkline::default()
+            .data(shown)
+            .bar_set(bar_set.clone())
+            .style(styles.role(Role::Histogram));
+        frame.render_widget(Paragraph::new(view::bold(format!("{}:", name))), halves[0]);
+        frame.render_widget(sparkline, halves[1]);
+    }
+
+    let explanation = vec![
+        Line::from(""),
+        Line::from(
+            "The subscriber buffers events until its aggregator processes them. If the \
+             buffer fills up, new events are dropped, and the tasks, resources or async ops \
+             they describe may be missing or incomplete.",
+        ),
+        Line::from(""),
+        Line::from(vec![
+            Span::from("If the buffer often gets close to full, increase its capacity with "),
+            view::bold("TOKIO_CONSOLE_BUFFER_CAPACITY"),
+            Span::from(", or split it between more threads with "),
+            view::bold("TOKIO_CONSOLE_BUFFER_SHARDS"),
+            Span::from(
+                ". Long aggregation passes mean the aggregator can't keep up, for example \
+                 because the runtime is too busy to poll it promptly.",
+            ),
+        ]),
+    ];
+    frame.render_widget(
+        Paragraph::new(explanation).wrap(Wrap { trim: true }),
+        chunks[3],
+    );
+}
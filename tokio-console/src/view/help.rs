@@ -1,12 +1,97 @@
 use ratatui::{
     layout::{self, Constraint, Direction, Layout},
+    style::{Modifier, Style},
+    text::{Line, Span},
     widgets::{Clear, Paragraph},
 };
 
-use crate::{state::State, view};
+use crate::{
+    state::State,
+    view::{self, bold},
+};
 
 pub(crate) trait HelpText {
     fn render_help_content(&self, styles: &view::Styles) -> Paragraph<'static>;
+
+    /// Returns hints about the actions the view's current state allows, and
+    /// the ones it doesn't allow yet, to show in the hint bar.
+    fn hints(&self) -> Vec<Hint> {
+        Vec::new()
+    }
+}
+
+/// A hint about an action in the current view, shown in the hint bar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Hint {
+    /// An action which can be taken now.
+    Enabled {
+        key: &'static str,
+        action: &'static str,
+    },
+    /// An action which can't be taken yet, and what is needed first.
+    Disabled {
+        key: &'static str,
+        action: &'static str,
+        reason: &'static str,
+    },
+}
+
+// === impl Hint ===
+
+impl Hint {
+    pub(crate) fn enabled(key: &'static str, action: &'static str) -> Self {
+        Self::Enabled { key, action }
+    }
+
+    pub(crate) fn disabled(key: &'static str, action: &'static str, reason: &'static str) -> Self {
+        Self::Disabled {
+            key,
+            action,
+            reason,
+        }
+    }
+
+    /// Returns an enabled hint if `enabled` is true, or a disabled hint
+    /// explaining `reason` otherwise.
+    pub(crate) fn when(
+        enabled: bool,
+        key: &'static str,
+        action: &'static str,
+        reason: &'static str,
+    ) -> Self {
+        if enabled {
+            Self::enabled(key, action)
+        } else {
+            Self::disabled(key, action, reason)
+        }
+    }
+
+    fn to_spans(&self) -> Vec<Span<'static>> {
+        match *self {
+            Hint::Enabled { key, action } => vec![bold(key), Span::raw(format!(" = {action}"))],
+            Hint::Disabled {
+                key,
+                action,
+                reason,
+            } => vec![Span::styled(
+                format!("{key} = {action} ({reason})"),
+                Style::default().add_modifier(Modifier::DIM),
+            )],
+        }
+    }
+}
+
+/// Renders a single line of `hints`, with the actions which can't be taken
+/// yet dimmed.
+pub(crate) fn hint_bar(hints: &[Hint]) -> Paragraph<'static> {
+    let mut spans = vec![Span::raw("hints: ")];
+    for (i, hint) in hints.iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::raw(", "));
+        }
+        spans.extend(hint.to_spans());
+    }
+    Paragraph::new(Line::from(spans))
 }
 
 /// Simple view for help popup
@@ -28,36 +113,12 @@ impl<'a> HelpView<'a> {
         _area: layout::Rect,
         _state: &mut State,
     ) {
-        let r = frame.size();
         let content = self
             .help_text
             .take()
             .expect("help_text should be initialized");
 
-        let popup_layout = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints(
-                [
-                    Constraint::Percentage(20),
-                    Constraint::Min(15),
-                    Constraint::Percentage(20),
-                ]
-                .as_ref(),
-            )
-            .split(r);
-
-        let popup_area = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints(
-                [
-                    Constraint::Percentage(20),
-                    Constraint::Percentage(60),
-                    Constraint::Percentage(20),
-                ]
-                .as_ref(),
-            )
-            .split(popup_layout[1])[1];
-
+        let popup_area = popup_area(frame.size());
         let display_text = content.block(styles.border_block().title("Help"));
 
         // Clear the help block area and render the popup
@@ -65,3 +126,30 @@ impl<'a> HelpView<'a> {
         frame.render_widget(display_text, popup_area);
     }
 }
+
+/// Returns the area of a popup centered in `r`.
+pub(super) fn popup_area(r: layout::Rect) -> layout::Rect {
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Percentage(20),
+                Constraint::Min(15),
+                Constraint::Percentage(20),
+            ]
+            .as_ref(),
+        )
+        .split(r);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage(20),
+                Constraint::Percentage(60),
+                Constraint::Percentage(20),
+            ]
+            .as_ref(),
+        )
+        .split(popup_layout[1])[1]
+}
@@ -0,0 +1,140 @@
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph},
+};
+
+use crate::{
+    input,
+    state::State,
+    view::{self, help::Hint, Role},
+};
+
+/// A menu, shown over the current view, listing the known warnings and
+/// letting them be enabled or disabled while the console is running.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LintMenu {
+    /// The index of the highlighted lint, in the order they're listed.
+    cursor: usize,
+}
+
+/// What happened to the menu after a key was pressed.
+pub(crate) enum LintMenuInput {
+    Open,
+    Closed,
+    /// The lint with this summary was enabled or disabled.
+    Toggled(String),
+}
+
+struct Entry<'a> {
+    summary: &'a str,
+    count: usize,
+    enabled: bool,
+}
+
+impl LintMenu {
+    pub(crate) fn update_input(
+        &mut self,
+        event: &input::Event,
+        state: &mut State,
+    ) -> LintMenuInput {
+        use input::KeyCode::*;
+        let input::Event::Key(event) = event else {
+            return LintMenuInput::Open;
+        };
+        let entries = entries(state);
+        match event.code {
+            Esc | Char('L') | Char('q') => return LintMenuInput::Closed,
+            Down | Char('j') if !entries.is_empty() => {
+                self.cursor = (self.cursor + 1) % entries.len();
+            }
+            Up | Char('k') if !entries.is_empty() => {
+                self.cursor = (self.cursor + entries.len() - 1) % entries.len();
+            }
+            Enter | Char(' ') => {
+                if let Some(entry) = entries.get(self.cursor) {
+                    return LintMenuInput::Toggled(entry.summary.to_string());
+                }
+            }
+            _ => {}
+        }
+        LintMenuInput::Open
+    }
+
+    pub(crate) fn hints() -> Vec<Hint> {
+        vec![
+            Hint::enabled("enter", "enable/disable"),
+            Hint::enabled("esc", "close"),
+        ]
+    }
+
+    /// Renders the menu in a popup, listing each of the configured lints,
+    /// whether they're enabled, and how many tasks they currently apply to.
+    pub(crate) fn render(
+        &self,
+        styles: &view::Styles,
+        frame: &mut ratatui::terminal::Frame,
+        state: &mut State,
+    ) {
+        let area = view::help::popup_area(frame.size());
+        let block = styles.border_block().title("Lints");
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let mut lines = vec![
+            Line::from("Press enter to enable or disable the highlighted lint."),
+            Line::from(""),
+        ];
+        let entries = entries(state);
+        if entries.is_empty() {
+            lines.push(Line::from("No lints are configured."));
+        }
+        for (i, entry) in entries.iter().enumerate() {
+            let marker = if i == self.cursor {
+                view::TABLE_HIGHLIGHT_SYMBOL
+            } else {
+                "   "
+            };
+            let checkbox = if entry.enabled { "[x] " } else { "[ ] " };
+            let mut line = vec![Span::raw(marker), Span::raw(checkbox)];
+            if entry.enabled && entry.count > 0 {
+                line.push(Span::styled(
+                    entry.count.to_string(),
+                    styles.role(Role::Caution),
+                ));
+                line.push(Span::raw(" "));
+            }
+            line.push(Span::raw(entry.summary));
+            if !entry.enabled {
+                line.push(Span::styled(
+                    " (disabled)",
+                    Style::default().add_modifier(Modifier::DIM),
+                ));
+            }
+            lines.push(Line::from(line));
+        }
+        frame.render_widget(Paragraph::new(lines), inner);
+    }
+}
+
+/// Returns the enabled and disabled lints, ordered by their summaries so
+/// that toggling one doesn't move it.
+fn entries(state: &mut State) -> Vec<Entry<'_>> {
+    let tasks = state.tasks_state();
+    let enabled = tasks.linters.iter().map(|linter| (linter, true));
+    let disabled = tasks
+        .disabled_linters()
+        .iter()
+        .map(|linter| (linter, false));
+    let mut entries = enabled
+        .chain(disabled)
+        .map(|(linter, enabled)| Entry {
+            summary: linter.summary(),
+            count: linter.count(),
+            enabled,
+        })
+        .collect::<Vec<_>>();
+    entries.sort_by_key(|entry| entry.summary);
+    entries
+}
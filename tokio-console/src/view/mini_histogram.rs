@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::{cmp, time::Duration};
 
 use ratatui::{
     layout::Rect,
@@ -29,8 +29,41 @@ pub(crate) struct MiniHistogram<'a> {
     bar_set: symbols::bar::Set,
     /// Duration precision for the labels
     duration_precision: usize,
+    /// How the durations are divided into bars
+    settings: HistogramSettings,
 }
 
+/// How a [`MiniHistogram`] divides the recorded durations into bars.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct HistogramSettings {
+    scale: Scale,
+    /// The number of bars, or `None` for one bar per column.
+    buckets: Option<u16>,
+    zoom: Zoom,
+}
+
+/// The scale of a histogram's x-axis.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Scale {
+    #[default]
+    Linear,
+    Log,
+}
+
+/// The range of percentiles a histogram shows.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Zoom {
+    #[default]
+    All,
+    UpToP99,
+    UpToP90,
+    FromP50,
+    FromP90,
+}
+
+/// The bar counts that can be chosen, besides one bar per column.
+const BUCKET_COUNTS: &[u16] = &[4, 8, 16, 32, 64];
+
 #[derive(Debug, Default)]
 pub(crate) struct HistogramMetadata {
     /// The max recorded value in the histogram. This is the label for the bottom-right in the chart
@@ -53,6 +86,7 @@ impl Default for MiniHistogram<'_> {
             max: None,
             bar_set: symbols::bar::NINE_LEVELS,
             duration_precision: 4,
+            settings: HistogramSettings::default(),
         }
     }
 }
@@ -80,7 +114,7 @@ impl Widget for MiniHistogram<'_> {
             // If we overshoot, there will be empty columns/buckets at the right end of the chart.
             // If we undershoot, the rightmost 1-2 columns/buckets will be hidden.
             // We could get the max bucket value from the previous render though...
-            Some(h) => chart_data(h, inner_area.width - 3),
+            Some(h) => chart_data(h, inner_area.width - 3, self.settings),
             None => return,
         };
 
@@ -131,24 +165,26 @@ impl<'a> MiniHistogram<'a> {
             Some(v) => v,
             None => *data.iter().max().unwrap_or(&1u64),
         };
-        let max_index = std::cmp::min(area.width as usize, data.len());
+        // When there are fewer bars than columns, widen the bars to fill the
+        // chart.
+        let bar_width = match data.len() {
+            0 => 1,
+            len => std::cmp::max(area.width as usize / len, 1),
+        };
+        let max_index = std::cmp::min(area.width as usize, data.len() * bar_width);
         let mut data = data
             .iter()
+            .flat_map(|e| std::iter::repeat(e).take(bar_width))
             .take(max_index)
             .map(|e| {
-                if max != 0 {
-                    let r = e * u64::from(area.height) * 8 / max;
+                match (e * u64::from(area.height) * 8).checked_div(max) {
                     // This is the only difference in the bar rendering logic
                     // between MiniHistogram and Sparkline. At least render a
                     // ONE_EIGHT, if the value is greater than 0, even if it's
                     // relatively very small.
-                    if *e > 0 && r == 0 {
-                        1
-                    } else {
-                        r
-                    }
-                } else {
-                    0
+                    Some(0) if *e > 0 => 1,
+                    Some(r) => r,
+                    None => 0,
                 }
             })
             .collect::<Vec<u64>>();
@@ -183,6 +219,11 @@ impl<'a> MiniHistogram<'a> {
         self
     }
 
+    pub(crate) fn settings(mut self, settings: HistogramSettings) -> MiniHistogram<'a> {
+        self.settings = settings;
+        self
+    }
+
     // The same Sparkline setter methods below
 
     #[allow(dead_code)]
@@ -264,9 +305,13 @@ fn render_legend(
     );
 }
 
-/// From the histogram, build a visual representation by trying to make as
-/// many buckets as the width of the render area.
-fn chart_data(histogram: &DurationHistogram, width: u16) -> (Vec<u64>, HistogramMetadata) {
+/// From the histogram, build a visual representation with as many buckets as
+/// the settings ask for, up to the width of the render area.
+fn chart_data(
+    histogram: &DurationHistogram,
+    width: u16,
+    settings: HistogramSettings,
+) -> (Vec<u64>, HistogramMetadata) {
     let &DurationHistogram {
         ref histogram,
         high_outliers,
@@ -274,38 +319,120 @@ fn chart_data(histogram: &DurationHistogram, width: u16) -> (Vec<u64>, Histogram
         ..
     } = histogram;
 
-    let step_size = ((histogram.max() - histogram.min()) as f64 / width as f64).ceil() as u64 + 1;
-    // `iter_linear` panics if step_size is 0
-    let data = if step_size > 0 {
-        let mut found_first_nonzero = false;
-        let data: Vec<u64> = histogram
-            .iter_linear(step_size)
-            .filter_map(|value| {
-                let count = value.count_since_last_iteration();
-                // Remove the 0s from the leading side of the buckets.
-                // Because HdrHistogram can return empty buckets depending
-                // on its internal state, as it approximates values.
-                if count == 0 && !found_first_nonzero {
-                    None
-                } else {
-                    found_first_nonzero = true;
-                    Some(count)
-                }
-            })
-            .collect();
-        data
-    } else {
-        Vec::new()
+    let (low_quantile, high_quantile) = settings.zoom.quantiles();
+    let low = match low_quantile {
+        0.0 => histogram.min(),
+        quantile => histogram.value_at_quantile(quantile),
     };
+    let high = match high_quantile {
+        1.0 => histogram.max(),
+        quantile => histogram.value_at_quantile(quantile),
+    };
+    let buckets = settings
+        .buckets
+        .map_or(width, |buckets| cmp::min(buckets, width));
+    let buckets = cmp::max(buckets, 1) as usize;
+
+    // The position of a value between `low` and `high`, from 0 to 1.
+    let position = |value: u64| match settings.scale {
+        Scale::Linear => (value - low) as f64 / cmp::max(high - low, 1) as f64,
+        Scale::Log => {
+            let ln = |value: u64| (cmp::max(value, 1) as f64).ln();
+            (ln(value) - ln(low)) / (ln(high) - ln(low)).max(f64::EPSILON)
+        }
+    };
+    let mut data = vec![0; buckets];
+    for value in histogram.iter_recorded() {
+        let value_at = value.value_iterated_to();
+        if value_at < low || value_at > high {
+            continue;
+        }
+        let bucket = (position(value_at) * buckets as f64) as usize;
+        data[cmp::min(bucket, buckets - 1)] += value.count_at_value();
+    }
+
     let max_bucket = data.iter().max().copied().unwrap_or_default();
     (
         data,
         HistogramMetadata {
-            max_value: histogram.max(),
-            min_value: histogram.min(),
+            max_value: high,
+            min_value: low,
             max_bucket,
             high_outliers,
             highest_outlier,
         },
     )
 }
+
+// === impl HistogramSettings ===
+
+impl HistogramSettings {
+    /// Switches between a linear and a logarithmic x-axis.
+    pub(crate) fn toggle_scale(&mut self) {
+        self.scale = match self.scale {
+            Scale::Linear => Scale::Log,
+            Scale::Log => Scale::Linear,
+        };
+    }
+
+    /// Uses the next larger bar count, or one bar per column after the
+    /// largest.
+    pub(crate) fn more_buckets(&mut self) {
+        self.buckets = self
+            .buckets
+            .and_then(|buckets| BUCKET_COUNTS.iter().copied().find(|&count| count > buckets));
+    }
+
+    /// Uses the next smaller bar count.
+    pub(crate) fn fewer_buckets(&mut self) {
+        self.buckets = Some(match self.buckets {
+            None => BUCKET_COUNTS[BUCKET_COUNTS.len() - 1],
+            Some(buckets) => BUCKET_COUNTS
+                .iter()
+                .copied()
+                .rev()
+                .find(|&count| count < buckets)
+                .unwrap_or(BUCKET_COUNTS[0]),
+        });
+    }
+
+    /// Cycles through the percentile ranges the histogram can show.
+    pub(crate) fn next_zoom(&mut self) {
+        self.zoom = match self.zoom {
+            Zoom::All => Zoom::UpToP99,
+            Zoom::UpToP99 => Zoom::UpToP90,
+            Zoom::UpToP90 => Zoom::FromP50,
+            Zoom::FromP50 => Zoom::FromP90,
+            Zoom::FromP90 => Zoom::All,
+        };
+    }
+
+    /// Describes the settings which differ from the defaults, such as
+    /// `log, p0-p99, 16 bars`.
+    pub(crate) fn describe(&self) -> Option<String> {
+        let scale = (self.scale == Scale::Log).then(|| "log".to_owned());
+        let zoom = (self.zoom != Zoom::All).then(|| {
+            let (low, high) = self.zoom.quantiles();
+            format!("p{}-p{}", low * 100.0, high * 100.0)
+        });
+        let buckets = self.buckets.map(|buckets| format!("{} bars", buckets));
+        let parts = [scale, zoom, buckets]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+impl Zoom {
+    /// Returns the lowest and highest quantiles shown.
+    fn quantiles(self) -> (f64, f64) {
+        match self {
+            Zoom::All => (0.0, 1.0),
+            Zoom::UpToP99 => (0.0, 0.99),
+            Zoom::UpToP90 => (0.0, 0.9),
+            Zoom::FromP50 => (0.5, 1.0),
+            Zoom::FromP90 => (0.9, 1.0),
+        }
+    }
+}
@@ -1,28 +1,62 @@
 use crate::view::help::HelpView;
 use crate::view::{
-    help::HelpText, resources::ResourcesTable, table::TableListState, tasks::TasksTable,
+    help::HelpText,
+    resource_groups::ResourceGroupsTable,
+    resources::ResourcesTable,
+    table::{ExportFormat, TableList, TableListState},
+    task::HistogramTab,
+    tasks::TasksTable,
 };
-use crate::{input, state::State};
+use crate::{
+    clipboard::Clipboard,
+    conn::ConnectionStats,
+    input,
+    source::Sources,
+    state::{
+        tasks::{RateWindow, ScheduledMetric},
+        Attribute, DisplayedList, Field, State,
+    },
+};
+use console_api::instrument::HistogramSelection;
 use ratatui::{
     layout,
     style::{self, Style},
     text::Span,
 };
-use std::{borrow::Cow, cmp};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    cmp, mem,
+    rc::{Rc, Weak},
+};
 
+mod async_op;
 mod async_ops;
+mod command;
 mod controls;
+mod dropped;
 mod durations;
+mod health;
 mod help;
+mod lint_menu;
 mod mini_histogram;
 mod percentiles;
+mod prefs;
 mod resource;
+mod resource_groups;
 mod resources;
+pub(crate) mod runtime;
+mod session;
+mod sort_menu;
+mod stalls;
 mod styles;
+mod summary;
 mod table;
 mod task;
 mod tasks;
-pub(crate) use self::styles::{Palette, Styles};
+mod timers;
+pub(crate) use self::prefs::ViewPrefs;
+pub(crate) use self::styles::{DurationFormat, HighlightRule, Palette, Role, Styles, Theme};
 pub(crate) use self::table::SortBy;
 
 // This data is only updated every second, so it doesn't make a ton of
@@ -44,10 +78,44 @@ pub struct View {
     /// details view), we want to leave the task list's state the way we left it
     /// --- e.g., if the user previously selected a particular sorting, we want
     /// it to remain sorted that way when we return to it.
-    tasks_list: TableListState<TasksTable, 12>,
-    resources_list: TableListState<ResourcesTable, 9>,
+    tasks_list: TableListState<TasksTable, 23>,
+    /// Which kinds of tasks the tasks list shows.
+    tasks_kind_filter: tasks::KindFilter,
+    /// Which runtime's tasks the tasks list shows.
+    tasks_runtime_filter: tasks::RuntimeFilter,
+    /// Whether the tasks list is grouped, and which groups are collapsed.
+    tasks_grouping: tasks::Grouping,
+    /// Which value the tasks list shows for each task's scheduled time.
+    tasks_sched_metric: ScheduledMetric,
+    /// The window the tasks list computes poll and wake rates over.
+    tasks_rate_window: RateWindow,
+    resources_list: TableListState<ResourcesTable, 11>,
+    /// Which kind and type of resources the resources list shows, if it was
+    /// opened from the resources summary.
+    resources_filter: Option<resources::TypeFilter>,
+    /// Whether the resources list is shown as a tree, and which resources'
+    /// children are collapsed.
+    resources_tree: resources::Tree,
+    /// The resources summary, grouped by kind and concrete type.
+    resource_groups: TableListState<ResourceGroupsTable, 9>,
     state: ViewState,
     show_help_modal: bool,
+    show_session_stats: bool,
+    show_dropped_events: bool,
+    show_stalls: bool,
+    show_timers: bool,
+    show_subscriber_health: bool,
+    /// The outcome of the most recent table export or command, shown until
+    /// the next key is pressed.
+    status: Option<String>,
+    /// The command palette, while a command is being typed.
+    command_palette: Option<command::CommandPalette>,
+    /// The menu for enabling and disabling lints, while it's open.
+    lint_menu: Option<lint_menu::LintMenu>,
+    /// The source files previewed in the task details view.
+    sources: Sources,
+    /// Where `y` copies the selected task or resource to.
+    clipboard: Clipboard,
     pub(crate) styles: Styles,
 }
 
@@ -56,26 +124,42 @@ pub(crate) enum ViewState {
     TasksList,
     /// The table list of all resources.
     ResourcesList,
+    /// The summary of resources, grouped by kind and concrete type.
+    ResourceGroups,
     /// Inspecting a single task instance.
     TaskInstance(self::task::TaskView),
     /// Inspecting a single resource instance.
     ResourceInstance(self::resource::ResourceView),
+    /// Inspecting a single async op, from its resource's view.
+    AsyncOpInstance(self::async_op::AsyncOpView),
 }
 
 /// The outcome of the update_input method
 #[derive(Debug, Copy, Clone)]
 pub(crate) enum UpdateKind {
-    /// A new task is selected
-    SelectTask(u64),
+    /// A task is selected, showing the given histograms
+    SelectTask(u64, HistogramSelection),
     /// The TaskView is exited
     ExitTaskView,
     #[allow(dead_code)]
     /// A new resource is selected
     SelectResource(u64),
+    /// The console should be paused or resumed
+    SetPaused(bool),
     /// No significant change
     Other,
 }
 
+/// A key handled by `main` before the view sees it, because it acts on the
+/// console as a whole rather than on what's shown.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum GlobalInput {
+    Quit,
+    TogglePause,
+    StepBack,
+    StepForward,
+}
+
 #[derive(Debug, Copy, Clone)]
 pub(crate) struct Width {
     curr: u16,
@@ -100,22 +184,173 @@ impl View {
     pub fn new(styles: Styles) -> Self {
         Self {
             state: ViewState::TasksList,
-            tasks_list: TableListState::<TasksTable, 12>::default(),
-            resources_list: TableListState::<ResourcesTable, 9>::default(),
+            tasks_list: TableListState::<TasksTable, 23>::default(),
+            tasks_kind_filter: tasks::KindFilter::default(),
+            tasks_runtime_filter: tasks::RuntimeFilter::default(),
+            tasks_grouping: tasks::Grouping::default(),
+            tasks_sched_metric: ScheduledMetric::default(),
+            tasks_rate_window: RateWindow::default(),
+            resources_list: TableListState::<ResourcesTable, 11>::default(),
+            resources_filter: None,
+            resources_tree: resources::Tree::default(),
+            resource_groups: TableListState::<ResourceGroupsTable, 9>::default(),
             show_help_modal: false,
+            show_session_stats: false,
+            show_dropped_events: false,
+            show_stalls: false,
+            show_timers: false,
+            show_subscriber_health: false,
+            status: None,
+            command_palette: None,
+            lint_menu: None,
+            sources: Sources::default(),
+            clipboard: Clipboard::default(),
             styles,
         }
     }
 
-    pub(crate) fn update_input(&mut self, event: input::Event, state: &State) -> UpdateKind {
+    /// Restores the view preferences saved by a previous session.
+    pub(crate) fn with_prefs(mut self, prefs: &ViewPrefs) -> Self {
+        if let Some(tasks) = &prefs.tasks {
+            self.tasks_list.restore(tasks);
+        }
+        if let Some(resources) = &prefs.resources {
+            self.resources_list.restore(resources);
+        }
+        self.state = match prefs.view {
+            Some(prefs::ListView::Resources) => ViewState::ResourcesList,
+            Some(prefs::ListView::Tasks) | None => ViewState::TasksList,
+        };
+        self
+    }
+
+    /// Sets where the task details view looks for source files.
+    pub(crate) fn with_sources(mut self, sources: Sources) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    /// Returns the view preferences to save for the next session.
+    pub(crate) fn prefs(&self) -> ViewPrefs {
+        let view = match self.state {
+            ViewState::ResourcesList
+            | ViewState::ResourceGroups
+            | ViewState::ResourceInstance(_)
+            | ViewState::AsyncOpInstance(_) => prefs::ListView::Resources,
+            ViewState::TasksList | ViewState::TaskInstance(_) => prefs::ListView::Tasks,
+        };
+        ViewPrefs {
+            view: Some(view),
+            tasks: Some(self.tasks_list.prefs()),
+            resources: Some(self.resources_list.prefs()),
+        }
+    }
+
+    pub(crate) fn update_input(&mut self, event: input::Event, state: &mut State) -> UpdateKind {
         use ViewState::*;
         let mut update_kind = UpdateKind::Other;
+        self.status = None;
+
+        if let Some(palette) = self.command_palette.as_mut() {
+            match palette.update_input(&event) {
+                command::PaletteInput::Editing => {}
+                command::PaletteInput::Cancelled => self.command_palette = None,
+                command::PaletteInput::Submitted(command) => {
+                    self.command_palette = None;
+                    match command
+                        .parse()
+                        .and_then(|command| self.run_command(command, state))
+                    {
+                        Ok(kind) => update_kind = kind,
+                        Err(error) => self.status = Some(error),
+                    }
+                }
+            }
+            return update_kind;
+        }
+
+        // The lints menu handles every key while it's open.
+        if let Some(menu) = self.lint_menu.as_mut() {
+            match menu.update_input(&event, state) {
+                lint_menu::LintMenuInput::Open => {}
+                lint_menu::LintMenuInput::Closed => self.lint_menu = None,
+                lint_menu::LintMenuInput::Toggled(summary) => {
+                    if let Some(enabled) = state.tasks_state_mut().toggle_linter(&summary) {
+                        let action = if enabled { "Enabled" } else { "Disabled" };
+                        self.status = Some(format!("{action} lint: {summary}"));
+                    }
+                }
+            }
+            return update_kind;
+        }
+
+        if matches!(event, key!(Char('L'))) {
+            self.lint_menu = Some(lint_menu::LintMenu::default());
+            return update_kind;
+        }
+
+        // The sorting menu handles every key while it's open.
+        if self.is_sort_menu_open() {
+            match self.state {
+                TasksList => self.tasks_list.update_input(event),
+                ResourcesList => self.resources_list.update_input(event),
+                ResourceGroups => self.resource_groups.update_input(event),
+                ResourceInstance(ref mut view) => view.update_input(event),
+                TaskInstance(_) | AsyncOpInstance(_) => {}
+            }
+            return update_kind;
+        }
+
+        if matches!(event, key!(Char(':'))) {
+            self.command_palette = Some(command::CommandPalette::default());
+            return update_kind;
+        }
 
         if self.should_toggle_help_modal(&event) {
             self.show_help_modal = !self.show_help_modal;
             return update_kind;
         }
 
+        if self.should_toggle_session_stats(&event) {
+            self.show_session_stats = !self.show_session_stats;
+            return update_kind;
+        }
+
+        if self.should_toggle_dropped_events(&event) {
+            self.show_dropped_events = !self.show_dropped_events;
+            return update_kind;
+        }
+
+        if self.should_toggle_stalls(&event) {
+            self.show_stalls = !self.show_stalls;
+            return update_kind;
+        }
+
+        if self.should_toggle_timers(&event) {
+            self.show_timers = !self.show_timers;
+            return update_kind;
+        }
+
+        if self.should_toggle_subscriber_health(&event) {
+            self.show_subscriber_health = !self.show_subscriber_health;
+            return update_kind;
+        }
+
+        if matches!(event, key!(Char('u'))) {
+            self.styles.cycle_duration_format();
+            return update_kind;
+        }
+
+        if matches!(event, key!(Char('T'))) {
+            self.styles.cycle_theme();
+            return update_kind;
+        }
+
+        if matches!(event, key!(Char('y'))) {
+            self.status = self.copy_selected(state);
+            return update_kind;
+        }
+
         if matches!(event, key!(Char('t'))) {
             self.state = TasksList;
             return update_kind;
@@ -133,13 +368,54 @@ impl View {
                 match event {
                     key!(Enter) => {
                         if let Some(task) = self.tasks_list.selected_item() {
-                            update_kind = UpdateKind::SelectTask(task.borrow().span_id());
+                            update_kind = UpdateKind::SelectTask(
+                                task.borrow().span_id(),
+                                HistogramTab::default().histograms(),
+                            );
                             self.state = TaskInstance(self::task::TaskView::new(
                                 task,
                                 state.task_details_ref(),
                             ));
                         }
                     }
+                    key!(Char('a')) => {
+                        if let Some(task) = self.tasks_list.selected_item() {
+                            let task = task.borrow();
+                            let watched = state.tasks_state_mut().toggle_watched(task.id());
+                            let name = task.name().unwrap_or_else(|| task.id_str());
+                            self.status = Some(if watched {
+                                format!("Watching task {name}")
+                            } else {
+                                format!("Stopped watching task {name}")
+                            });
+                        }
+                    }
+                    key!(Char('P')) => {
+                        if let Some(task) = self.tasks_list.selected_item() {
+                            let id = task.borrow().id();
+                            state.tasks_state_mut().toggle_pinned(id);
+                        }
+                    }
+                    key!(Char('b')) => self.tasks_kind_filter = self.tasks_kind_filter.next(),
+                    key!(Char('R')) => {
+                        self.tasks_runtime_filter =
+                            self.tasks_runtime_filter.next(state.tasks_state())
+                    }
+                    key!(Char('p')) => self.tasks_grouping.toggle(),
+                    key!(Char('m')) => self.tasks_sched_metric = self.tasks_sched_metric.next(),
+                    key!(Char('w')) => self.tasks_rate_window = self.tasks_rate_window.next(),
+                    key!(Char('c')) => {
+                        if let Some(task) = self.tasks_list.selected_item() {
+                            self.tasks_grouping.collapse(&task.borrow());
+                        }
+                    }
+                    key!(Char('C')) => self.tasks_grouping.expand_all(),
+                    key!(Char('e')) => {
+                        self.status = export(&self.tasks_list, "tasks", ExportFormat::Csv, state)
+                    }
+                    key!(Char('E')) => {
+                        self.status = export(&self.tasks_list, "tasks", ExportFormat::Json, state)
+                    }
                     _ => {
                         // otherwise pass on to view
                         self.tasks_list.update_input(event);
@@ -154,12 +430,55 @@ impl View {
                             self.state = ResourceInstance(self::resource::ResourceView::new(res));
                         }
                     }
+                    key!(Char('K')) => self.state = ResourceGroups,
+                    key!(Esc) => self.resources_filter = None,
+                    key!(Char('p')) => self.resources_tree.toggle(),
+                    key!(Char('c')) => {
+                        if let Some(resource) = self.resources_list.selected_item() {
+                            self.resources_tree.toggle_collapsed(&resource.borrow());
+                        }
+                    }
+                    key!(Char('C')) => self.resources_tree.expand_all(),
+                    key!(Char('e')) => {
+                        self.status =
+                            export(&self.resources_list, "resources", ExportFormat::Csv, state)
+                    }
+                    key!(Char('E')) => {
+                        self.status =
+                            export(&self.resources_list, "resources", ExportFormat::Json, state)
+                    }
                     _ => {
                         // otherwise pass on to view
                         self.resources_list.update_input(event);
                     }
                 }
             }
+            ResourceGroups => match event {
+                key!(Esc) | key!(Char('K')) => self.state = ResourcesList,
+                key!(Enter) => {
+                    if let Some(group) = self.resource_groups.selected_item() {
+                        self.resources_filter = Some(resources::TypeFilter::new(&group.borrow()));
+                        self.state = ResourcesList;
+                    }
+                }
+                key!(Char('e')) => {
+                    self.status = export(
+                        &self.resource_groups,
+                        "resource-groups",
+                        ExportFormat::Csv,
+                        state,
+                    )
+                }
+                key!(Char('E')) => {
+                    self.status = export(
+                        &self.resource_groups,
+                        "resource-groups",
+                        ExportFormat::Json,
+                        state,
+                    )
+                }
+                _ => self.resource_groups.update_input(event),
+            },
             ResourceInstance(ref mut view) => {
                 // The escape key changes views, so handle here since we can
                 // mutate the currently selected view.
@@ -170,21 +489,50 @@ impl View {
                     }
                     key!(Enter) => {
                         if let Some(op) = view.async_ops_table.selected_item() {
-                            if let Some(task_id) = op.borrow().task_id() {
-                                let task = self
-                                    .tasks_list
-                                    .sorted_items
-                                    .iter()
-                                    .filter_map(|i| i.upgrade())
-                                    .find(|t| task_id == t.borrow().id());
-
-                                if let Some(task) = task {
-                                    update_kind = UpdateKind::SelectTask(task.borrow().span_id());
-                                    self.state = TaskInstance(self::task::TaskView::new(
-                                        task,
-                                        state.task_details_ref(),
-                                    ));
-                                }
+                            let ResourceInstance(view) = mem::replace(&mut self.state, TasksList)
+                            else {
+                                unreachable!("the resource view is the current view");
+                            };
+                            self.state =
+                                AsyncOpInstance(self::async_op::AsyncOpView::new(op, view));
+                        }
+                    }
+                    _ => {
+                        // otherwise pass on to view
+                        view.update_input(event);
+                    }
+                }
+            }
+            AsyncOpInstance(ref mut view) => {
+                // The escape and enter keys change views, so handle them here
+                // since we can mutate the currently selected view.
+                match event {
+                    key!(Esc) => {
+                        let AsyncOpInstance(view) = mem::replace(&mut self.state, TasksList) else {
+                            unreachable!("the async op view is the current view");
+                        };
+                        self.state = ResourceInstance(view.resource_view);
+                    }
+                    key!(Enter) => {
+                        let task_id = view.async_op().borrow().task_id();
+                        if let Some(task_id) = task_id {
+                            let task = self
+                                .tasks_list
+                                .sorted_items
+                                .iter()
+                                .chain(&self.tasks_list.hidden_items)
+                                .filter_map(|i| i.upgrade())
+                                .find(|t| task_id == t.borrow().id());
+
+                            if let Some(task) = task {
+                                update_kind = UpdateKind::SelectTask(
+                                    task.borrow().span_id(),
+                                    HistogramTab::default().histograms(),
+                                );
+                                self.state = TaskInstance(self::task::TaskView::new(
+                                    task,
+                                    state.task_details_ref(),
+                                ));
                             }
                         }
                     }
@@ -202,6 +550,9 @@ impl View {
                         self.state = TasksList;
                         update_kind = UpdateKind::ExitTaskView;
                     }
+                    key!(Tab) => {
+                        update_kind = view.next_histogram_tab();
+                    }
                     _ => {
                         // otherwise pass on to view
                         view.update_input(event);
@@ -212,57 +563,444 @@ impl View {
         update_kind
     }
 
+    /// Runs a command typed into the command palette, returning an error
+    /// message if it can't be run.
+    fn run_command(
+        &mut self,
+        command: command::Command,
+        state: &State,
+    ) -> Result<UpdateKind, String> {
+        use command::Command;
+        use ViewState::*;
+        match command {
+            Command::Task(query) => {
+                let items = self
+                    .tasks_list
+                    .sorted_items
+                    .iter()
+                    .chain(&self.tasks_list.hidden_items);
+                let task = find_item(items, &query, |task| {
+                    (task.id_str() == query, task.name().unwrap_or_default())
+                })
+                .ok_or_else(|| format!("no task with the ID or name `{}`", query))?;
+                let update_kind = UpdateKind::SelectTask(
+                    task.borrow().span_id(),
+                    HistogramTab::default().histograms(),
+                );
+                self.state =
+                    TaskInstance(self::task::TaskView::new(task, state.task_details_ref()));
+                Ok(update_kind)
+            }
+            Command::Resource(query) => {
+                let items = self
+                    .resources_list
+                    .sorted_items
+                    .iter()
+                    .chain(&self.resources_list.hidden_items);
+                let resource = find_item(items, &query, |resource| {
+                    (resource.id_str() == query, resource.concrete_type())
+                })
+                .ok_or_else(|| format!("no resource with the ID or type `{}`", query))?;
+                let update_kind = UpdateKind::SelectResource(resource.borrow().span_id());
+                self.state = ResourceInstance(self::resource::ResourceView::new(resource));
+                Ok(update_kind)
+            }
+            Command::Sort(column) => {
+                match self.state {
+                    TasksList | TaskInstance(_) => self.tasks_list.sort_by_name(&column)?,
+                    ResourcesList | ResourceInstance(_) | AsyncOpInstance(_) => {
+                        self.resources_list.sort_by_name(&column)?
+                    }
+                    ResourceGroups => self.resource_groups.sort_by_name(&column)?,
+                }
+                Ok(UpdateKind::Other)
+            }
+            Command::Tasks => {
+                self.state = TasksList;
+                Ok(UpdateKind::Other)
+            }
+            Command::Resources => {
+                self.state = ResourcesList;
+                Ok(UpdateKind::Other)
+            }
+            Command::Pause => Ok(UpdateKind::SetPaused(true)),
+            Command::Resume => Ok(UpdateKind::SetPaused(false)),
+        }
+    }
+
     /// The help modal should toggle on the `?` key and should exit on `Esc`
     fn should_toggle_help_modal(&mut self, event: &crossterm::event::Event) -> bool {
         input::is_help_toggle(event) || (self.show_help_modal && input::is_esc(event))
     }
 
+    /// The session stats popup should toggle on the `s` key and should exit on
+    /// `Esc`
+    fn should_toggle_session_stats(&self, event: &input::Event) -> bool {
+        matches!(event, key!(Char('s'))) || (self.show_session_stats && input::is_esc(event))
+    }
+
+    /// The dropped events popup should toggle on the `D` key and should exit on
+    /// `Esc`
+    fn should_toggle_dropped_events(&self, event: &input::Event) -> bool {
+        matches!(event, key!(Char('D'))) || (self.show_dropped_events && input::is_esc(event))
+    }
+
+    /// The stalled tasks popup should toggle on the `S` key and should exit on
+    /// `Esc`
+    fn should_toggle_stalls(&self, event: &input::Event) -> bool {
+        matches!(event, key!(Char('S'))) || (self.show_stalls && input::is_esc(event))
+    }
+
+    /// The upcoming timer wakes popup should toggle on the `W` key and should
+    /// exit on `Esc`
+    fn should_toggle_timers(&self, event: &input::Event) -> bool {
+        matches!(event, key!(Char('W'))) || (self.show_timers && input::is_esc(event))
+    }
+
+    /// The subscriber health popup should toggle on the `H` key and should
+    /// exit on `Esc`
+    fn should_toggle_subscriber_health(&self, event: &input::Event) -> bool {
+        matches!(event, key!(Char('H'))) || (self.show_subscriber_health && input::is_esc(event))
+    }
+
     pub(crate) fn render(
         &mut self,
         frame: &mut ratatui::terminal::Frame,
         area: layout::Rect,
         state: &mut State,
+        conn_stats: &ConnectionStats,
     ) {
+        // The hint bar takes up the first line, above the view.
+        let chunks = layout::Layout::default()
+            .direction(layout::Direction::Vertical)
+            .constraints([layout::Constraint::Length(1), layout::Constraint::Min(0)])
+            .split(area);
+        let (hints_area, view_area) = (chunks[0], chunks[1]);
+        let grouping_hints = match self.state {
+            ViewState::TasksList => self.tasks_grouping.hints(),
+            ViewState::ResourcesList => self.resources_tree.hints(),
+            _ => Vec::new(),
+        };
+
         let help_text: &dyn HelpText = match self.state {
             ViewState::TasksList => {
-                self.tasks_list.render(&self.styles, frame, area, state, ());
+                self.tasks_list.render(
+                    &self.styles,
+                    frame,
+                    view_area,
+                    state,
+                    (
+                        self.tasks_kind_filter,
+                        self.tasks_runtime_filter.clone(),
+                        self.tasks_grouping.clone(),
+                        self.tasks_sched_metric,
+                        self.tasks_rate_window,
+                    ),
+                );
                 &self.tasks_list
             }
             ViewState::ResourcesList => {
-                self.resources_list
-                    .render(&self.styles, frame, area, state, ());
+                self.resources_list.render(
+                    &self.styles,
+                    frame,
+                    view_area,
+                    state,
+                    (self.resources_filter.clone(), self.resources_tree.clone()),
+                );
                 &self.resources_list
             }
+            ViewState::ResourceGroups => {
+                self.resource_groups
+                    .render(&self.styles, frame, view_area, state, ());
+                &self.resource_groups
+            }
             ViewState::TaskInstance(ref mut view) => {
-                let now = state
-                    .last_updated_at()
-                    .expect("task view implies we've received an update");
-                view.render(&self.styles, frame, area, now);
+                view.render(&self.styles, &mut self.sources, frame, view_area, state);
                 view
             }
             ViewState::ResourceInstance(ref mut view) => {
-                view.render(&self.styles, frame, area, state);
+                view.render(&self.styles, frame, view_area, state);
+                view
+            }
+            ViewState::AsyncOpInstance(ref mut view) => {
+                view.render(&self.styles, frame, view_area, state);
                 view
             }
         };
 
+        match &self.command_palette {
+            Some(palette) => frame.render_widget(palette.render(&self.styles), hints_area),
+            None if self.lint_menu.is_some() => {
+                frame.render_widget(help::hint_bar(&lint_menu::LintMenu::hints()), hints_area);
+            }
+            None => {
+                let mut hints = help_text.hints();
+                hints.extend(grouping_hints);
+                frame.render_widget(help::hint_bar(&hints), hints_area);
+            }
+        }
+
         state.retain_active();
 
+        if self.show_session_stats {
+            session::render(&self.styles, frame, conn_stats, state.session_stats());
+        }
+
+        if self.show_dropped_events {
+            dropped::render(&self.styles, frame, state);
+        }
+
+        if self.show_stalls {
+            stalls::render(&self.styles, frame, state);
+        }
+
+        if self.show_timers {
+            timers::render(&self.styles, frame, state);
+        }
+
+        if self.show_subscriber_health {
+            health::render(&self.styles, frame, state);
+        }
+
+        if let Some(menu) = &self.lint_menu {
+            menu.render(&self.styles, frame, state);
+        }
+
         if self.show_help_modal {
             let mut help_view = HelpView::new(help_text.render_help_content(&self.styles));
             help_view.render(&self.styles, frame, area, state);
         }
     }
 
-    pub(crate) fn current_view(&self) -> &ViewState {
-        &self.state
+    /// Returns the list being displayed, whose new items the state should
+    /// show as soon as they arrive.
+    pub(crate) fn displayed_list(&self) -> Option<DisplayedList> {
+        match self.state {
+            ViewState::TasksList => Some(DisplayedList::Tasks),
+            ViewState::ResourcesList => Some(DisplayedList::Resources),
+            ViewState::ResourceInstance(_) => Some(DisplayedList::AsyncOps),
+            _ => None,
+        }
+    }
+
+    /// Returns whether a command is being typed into the command palette, or
+    /// a table's sorting menu or the lints menu is open, in which case every
+    /// key goes to it rather than triggering its usual action.
+    pub(crate) fn captures_input(&self) -> bool {
+        self.command_palette.is_some() || self.lint_menu.is_some() || self.is_sort_menu_open()
+    }
+
+    /// Returns the action of a key which acts on the console as a whole, such
+    /// as quitting or pausing, unless the view [captures
+    /// input](Self::captures_input).
+    pub(crate) fn global_input(&self, event: &input::Event, state: &State) -> Option<GlobalInput> {
+        if self.captures_input() {
+            return None;
+        }
+        if input::should_quit(event) {
+            Some(GlobalInput::Quit)
+        } else if input::is_space(event) {
+            Some(GlobalInput::TogglePause)
+        } else if state.is_paused() && input::is_step_back(event) {
+            Some(GlobalInput::StepBack)
+        } else if state.is_paused() && input::is_step_forward(event) {
+            Some(GlobalInput::StepForward)
+        } else {
+            None
+        }
+    }
+
+    /// Returns whether the sorting menu of the table being shown is open.
+    fn is_sort_menu_open(&self) -> bool {
+        match self.state {
+            ViewState::TasksList => self.tasks_list.is_sort_menu_open(),
+            ViewState::ResourcesList => self.resources_list.is_sort_menu_open(),
+            ViewState::ResourceGroups => self.resource_groups.is_sort_menu_open(),
+            ViewState::ResourceInstance(ref view) => view.async_ops_table.is_sort_menu_open(),
+            ViewState::TaskInstance(_) | ViewState::AsyncOpInstance(_) => false,
+        }
+    }
+
+    /// Returns a message describing the outcome of the most recent export or
+    /// command, if one was just made.
+    pub(crate) fn status(&self) -> Option<&str> {
+        self.status.as_deref()
     }
 }
 
+/// Finds the item matching a command palette `query`, in the order the items
+/// are listed.
+///
+/// `key` returns whether an item's ID is the query, and the name to search
+/// for the query in otherwise. An item whose ID or name is the query is
+/// preferred over one whose name only contains it, ignoring case.
+fn find_item<'a, T: 'a>(
+    items: impl Iterator<Item = &'a Weak<RefCell<T>>>,
+    query: &str,
+    key: impl Fn(&T) -> (bool, &str),
+) -> Option<Rc<RefCell<T>>> {
+    let query = query.to_lowercase();
+    let mut partial = None;
+    for item in items.filter_map(Weak::upgrade) {
+        let (id_matches, name) = {
+            let item = item.borrow();
+            let (id_matches, name) = key(&item);
+            (id_matches, name.to_lowercase())
+        };
+        if id_matches || name == query {
+            return Some(item);
+        }
+        if partial.is_none() && name.contains(&query) {
+            partial = Some(item);
+        }
+    }
+    partial
+}
+
+/// Exports `table` to a file, returning a message describing the outcome.
+impl View {
+    /// Copies a summary of the selected task or resource to the clipboard,
+    /// returning the status to show.
+    fn copy_selected(&mut self, state: &State) -> Option<String> {
+        // Nothing is shown until the first update arrives.
+        let now = state.last_updated_at()?;
+        let (kind, id, text) = match self.state {
+            ViewState::TasksList => {
+                let task = self.tasks_list.selected_item()?;
+                let task = task.borrow();
+                ("task", task.id_str().to_owned(), summary::task(&task, now))
+            }
+            ViewState::TaskInstance(ref view) => {
+                let task = view.task().borrow();
+                ("task", task.id_str().to_owned(), summary::task(&task, now))
+            }
+            ViewState::ResourcesList => {
+                let resource = self.resources_list.selected_item()?;
+                let resource = resource.borrow();
+                let text = summary::resource(&resource, now);
+                ("resource", resource.id_str().to_owned(), text)
+            }
+            ViewState::ResourceInstance(ref view) => {
+                let resource = view.resource().borrow();
+                let text = summary::resource(&resource, now);
+                ("resource", resource.id_str().to_owned(), text)
+            }
+            ViewState::AsyncOpInstance(ref view) => {
+                let resource = view.resource_view.resource().borrow();
+                let text = summary::resource(&resource, now);
+                ("resource", resource.id_str().to_owned(), text)
+            }
+            ViewState::ResourceGroups => return None,
+        };
+        let status = match self.clipboard.copy(text) {
+            Ok(()) => format!("copied {} {} to the clipboard", kind, id),
+            Err(error) => {
+                tracing::warn!(%error, "failed to copy {} {}", kind, id);
+                format!("failed to copy {} {}: {}", kind, id, error)
+            }
+        };
+        Some(status)
+    }
+}
+
+fn export<T: TableList<N>, const N: usize>(
+    table: &TableListState<T, N>,
+    name: &str,
+    format: ExportFormat,
+    state: &State,
+) -> Option<String> {
+    // Nothing is shown until the first update arrives.
+    let now = state.last_updated_at()?;
+    let status = match table.export_to_file(name, format, now) {
+        Ok((path, rows)) => format!("exported {} {} to {}", rows, name, path.display()),
+        Err(error) => {
+            tracing::warn!(%error, "failed to export {}", name);
+            format!("failed to export {}: {}", name, error)
+        }
+    };
+    Some(status)
+}
+
 pub(crate) fn bold<'a>(text: impl Into<Cow<'a, str>>) -> Span<'a> {
     Span::styled(text, Style::default().add_modifier(style::Modifier::BOLD))
 }
 
+/// Formats each of a task's fields as `name=value`.
+pub(crate) fn fields(styles: &Styles, fields: &[Field]) -> Vec<Vec<Span<'static>>> {
+    let key_style = styles.role(Role::Key).add_modifier(style::Modifier::BOLD);
+    let delim_style = styles.role(Role::Key).add_modifier(style::Modifier::DIM);
+    let val_style = styles.role(Role::Value);
+
+    fields
+        .iter()
+        .map(|field| {
+            vec![
+                Span::styled(field.name.to_string(), key_style),
+                Span::styled("=", delim_style),
+                Span::styled(format!("{} ", field.value), val_style),
+            ]
+        })
+        .collect()
+}
+
+/// Formats each of a resource or async op's attributes as `name=value`,
+/// followed by the value's unit.
+pub(crate) fn attributes(styles: &Styles, attributes: &[Attribute]) -> Vec<Vec<Span<'static>>> {
+    let key_style = styles.role(Role::Key).add_modifier(style::Modifier::BOLD);
+    let delim_style = styles.role(Role::Key).add_modifier(style::Modifier::DIM);
+    let val_style = styles.role(Role::Value);
+    let unit_style = styles.role(Role::Key);
+
+    attributes
+        .iter()
+        .map(|attr| {
+            let mut elems = vec![
+                Span::styled(attr.field.name.to_string(), key_style),
+                Span::styled("=", delim_style),
+                Span::styled(format!("{}", attr.field.value), val_style),
+            ];
+            if let Some(unit) = &attr.unit {
+                elems.push(Span::styled(unit.clone(), unit_style))
+            }
+            elems.push(Span::raw(" "));
+            elems
+        })
+        .collect()
+}
+
+/// Splits `area` into two panes, side by side and each half as wide, or, if
+/// `stacked`, one above the other with the first `first_height` lines tall.
+///
+/// Detail views stack their panes when the terminal is too narrow to show
+/// them next to each other.
+pub(crate) fn split_panes(
+    area: layout::Rect,
+    stacked: bool,
+    first_height: u16,
+) -> Rc<[layout::Rect]> {
+    let (direction, constraints) = if stacked {
+        (
+            layout::Direction::Vertical,
+            [
+                layout::Constraint::Length(first_height),
+                layout::Constraint::Min(0),
+            ],
+        )
+    } else {
+        (
+            layout::Direction::Horizontal,
+            [
+                layout::Constraint::Percentage(50),
+                layout::Constraint::Percentage(50),
+            ],
+        )
+    };
+    layout::Layout::default()
+        .direction(direction)
+        .constraints(constraints)
+        .split(area)
+}
+
 impl Width {
     pub(crate) fn new(curr: u16) -> Self {
         Self { curr }
@@ -287,3 +1025,61 @@ impl Width {
         self.curr
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ViewOptions;
+
+    fn key(code: input::KeyCode) -> input::Event {
+        input::Event::Key(input::KeyEvent::new(code, input::KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn lint_menu_captures_quit_and_pause_keys() {
+        let mut view = View::new(Styles::from_config(ViewOptions::default()));
+        let mut state = State::default();
+        let quit = key(input::KeyCode::Char('q'));
+        let space = key(input::KeyCode::Char(' '));
+        assert_eq!(view.global_input(&quit, &state), Some(GlobalInput::Quit));
+        assert_eq!(
+            view.global_input(&space, &state),
+            Some(GlobalInput::TogglePause)
+        );
+
+        view.update_input(key(input::KeyCode::Char('L')), &mut state);
+        assert!(view.captures_input());
+        assert_eq!(view.global_input(&space, &state), None);
+        assert!(!matches!(
+            view.update_input(space, &mut state),
+            UpdateKind::SetPaused(_)
+        ));
+        assert!(view.captures_input());
+
+        // `q` closes the menu, rather than quitting.
+        assert_eq!(view.global_input(&quit, &state), None);
+        view.update_input(quit.clone(), &mut state);
+        assert!(!view.captures_input());
+        assert_eq!(view.global_input(&quit, &state), Some(GlobalInput::Quit));
+    }
+
+    #[test]
+    fn sort_menu_captures_quit_and_pause_keys() {
+        let mut view = View::new(Styles::from_config(ViewOptions::default()));
+        let mut state = State::default();
+        let quit = key(input::KeyCode::Char('q'));
+        let space = key(input::KeyCode::Char(' '));
+
+        view.update_input(key(input::KeyCode::Char('o')), &mut state);
+        assert!(view.captures_input());
+        assert_eq!(view.global_input(&space, &state), None);
+        view.update_input(space, &mut state);
+        assert!(view.captures_input());
+
+        // `q` only closes the menu.
+        assert_eq!(view.global_input(&quit, &state), None);
+        view.update_input(quit.clone(), &mut state);
+        assert!(!view.captures_input());
+        assert_eq!(view.global_input(&quit, &state), Some(GlobalInput::Quit));
+    }
+}
@@ -0,0 +1,121 @@
+use color_eyre::eyre::WrapErr;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// View preferences which are saved when the console exits, and restored
+/// the next time it starts.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct ViewPrefs {
+    /// The list view which was shown last.
+    pub(crate) view: Option<ListView>,
+    pub(crate) tasks: Option<TablePrefs>,
+    pub(crate) resources: Option<TablePrefs>,
+}
+
+/// The views which can be restored on startup.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum ListView {
+    Tasks,
+    Resources,
+}
+
+/// The preferences for a single table.
+///
+/// Columns are saved by their headers rather than their positions, so that
+/// the preferences still apply to the right columns when a later version of
+/// the console adds, removes or reorders them. Columns which no longer exist
+/// are ignored.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct TablePrefs {
+    /// The header of the column the table is sorted by.
+    pub(crate) sort_by: Option<String>,
+    pub(crate) sort_descending: bool,
+    /// The header of the column rows are sorted by when they're equal in the
+    /// sorted column, if there is one.
+    pub(crate) then_by: Option<String>,
+    pub(crate) then_by_descending: bool,
+    /// The widest each column has been, by header, so that columns don't
+    /// start out narrow and then grow as rows are added.
+    pub(crate) widths: BTreeMap<String, u16>,
+}
+
+// === impl ViewPrefs ===
+
+impl ViewPrefs {
+    /// Loads the preferences saved at `path`.
+    ///
+    /// Returns the default preferences if nothing has been saved yet.
+    pub(crate) fn load(path: &Path) -> color_eyre::Result<Self> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self::default())
+            }
+            Err(error) => {
+                return Err(error)
+                    .wrap_err_with(|| format!("failed to read state file {}", path.display()))
+            }
+        };
+        toml::from_str(&text)
+            .wrap_err_with(|| format!("failed to parse state file {}", path.display()))
+    }
+
+    /// Saves these preferences to `path`, creating its directory if needed.
+    pub(crate) fn save(&self, path: &Path) -> color_eyre::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .wrap_err_with(|| format!("failed to create directory {}", dir.display()))?;
+        }
+        let text = toml::to_string_pretty(self)?;
+        fs::write(path, text)
+            .wrap_err_with(|| format!("failed to write state file {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let prefs = ViewPrefs {
+            view: Some(ListView::Resources),
+            tasks: Some(TablePrefs {
+                sort_by: Some("Busy".to_string()),
+                sort_descending: true,
+                then_by: Some("Name".to_string()),
+                then_by_descending: false,
+                widths: [("ID".to_string(), 4), ("Name".to_string(), 20)].into(),
+            }),
+            resources: None,
+        };
+        let text = toml::to_string_pretty(&prefs).unwrap();
+        assert_eq!(toml::from_str::<ViewPrefs>(&text).unwrap(), prefs);
+        assert_eq!(
+            toml::from_str::<ViewPrefs>("").unwrap(),
+            ViewPrefs::default()
+        );
+    }
+
+    #[test]
+    fn ignores_positional_table_prefs() {
+        // Tables used to be saved by column index, which doesn't say which
+        // column was meant once the columns change.
+        let text = r#"
+            view = "tasks"
+
+            [tasks]
+            sort_column = 4
+            sort_descending = true
+            column_widths = [4, 8, 5, 20]
+        "#;
+        let prefs = toml::from_str::<ViewPrefs>(text).unwrap();
+        assert_eq!(prefs.view, Some(ListView::Tasks));
+        let tasks = prefs.tasks.unwrap();
+        assert_eq!(tasks.sort_by, None);
+        assert!(tasks.widths.is_empty());
+    }
+}
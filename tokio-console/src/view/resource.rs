@@ -7,21 +7,22 @@ use crate::{
         async_ops::{self, AsyncOpsTable, AsyncOpsTableCtx},
         bold,
         controls::{controls_paragraph, ControlDisplay, Controls, KeyDisplay},
-        help::HelpText,
-        TableListState,
+        help::{HelpText, Hint},
+        Role, TableListState,
     },
 };
 use once_cell::sync::OnceCell;
 use ratatui::{
     layout::{self, Layout},
+    symbols,
     text::{Line, Span, Text},
-    widgets::Paragraph,
+    widgets::{Paragraph, Sparkline},
 };
 use std::{cell::RefCell, rc::Rc};
 
 pub(crate) struct ResourceView {
     resource: Rc<RefCell<Resource>>,
-    pub(crate) async_ops_table: TableListState<AsyncOpsTable, 9>,
+    pub(crate) async_ops_table: TableListState<AsyncOpsTable, 10>,
     initial_render: bool,
 }
 
@@ -29,11 +30,15 @@ impl ResourceView {
     pub(super) fn new(resource: Rc<RefCell<Resource>>) -> Self {
         ResourceView {
             resource,
-            async_ops_table: TableListState::<AsyncOpsTable, 9>::default(),
+            async_ops_table: TableListState::<AsyncOpsTable, 10>::default(),
             initial_render: true,
         }
     }
 
+    pub(crate) fn resource(&self) -> &Rc<RefCell<Resource>> {
+        &self.resource
+    }
+
     pub(crate) fn update_input(&mut self, event: input::Event) {
         self.async_ops_table.update_input(event)
     }
@@ -47,8 +52,23 @@ impl ResourceView {
     ) {
         let resource = &*self.resource.borrow();
         let controls = Controls::new(view_controls(), &area, styles);
+        // The resource box shows six lines, plus the top and bottom borders.
+        let overview_height = 8;
+        let stacked = area.width < STACK_PANES_BELOW;
+        let stats_height = if stacked {
+            overview_height + resource.attributes().len() as u16 + 2
+        } else {
+            overview_height
+        };
 
-        let (controls_area, stats_area, async_ops_area) = {
+        let attribute_history = resource.attribute_history();
+        let history_height = if attribute_history.is_empty() {
+            0
+        } else {
+            HISTORY_HEIGHT
+        };
+
+        let (controls_area, stats_area, history_area, async_ops_area) = {
             let chunks = Layout::default()
                 .direction(layout::Direction::Vertical)
                 .constraints(
@@ -56,26 +76,19 @@ impl ResourceView {
                         // controls
                         layout::Constraint::Length(controls.height()),
                         // resource stats
-                        layout::Constraint::Length(8),
+                        layout::Constraint::Length(stats_height),
+                        // attribute history
+                        layout::Constraint::Length(history_height),
                         // async ops
                         layout::Constraint::Percentage(60),
                     ]
                     .as_ref(),
                 )
                 .split(area);
-            (chunks[0], chunks[1], chunks[2])
+            (chunks[0], chunks[1], chunks[2], chunks[3])
         };
 
-        let stats_area = Layout::default()
-            .direction(layout::Direction::Horizontal)
-            .constraints(
-                [
-                    layout::Constraint::Percentage(50),
-                    layout::Constraint::Percentage(50),
-                ]
-                .as_ref(),
-            )
-            .split(stats_area);
+        let stats_area = view::split_panes(stats_area, stacked, overview_height);
 
         let overview = vec![
             Line::from(vec![bold("ID: "), Span::raw(resource.id_str())]),
@@ -86,17 +99,15 @@ impl ResourceView {
                 bold("Type: "),
                 Span::raw(resource.concrete_type()),
                 Span::raw(" "),
-                resource.type_visibility().render(styles),
+                view::resources::render_visibility(resource.type_visibility(), styles),
             ]),
             Line::from(vec![bold("Location: "), Span::raw(resource.location())]),
         ];
 
         let mut fields = Text::default();
         fields.extend(
-            resource
-                .formatted_attributes()
-                .iter()
-                .cloned()
+            view::attributes(styles, resource.attributes())
+                .into_iter()
                 .map(Line::from),
         );
 
@@ -107,6 +118,48 @@ impl ResourceView {
         frame.render_widget(controls.into_widget(), controls_area);
         frame.render_widget(resource_widget, stats_area[0]);
         frame.render_widget(fields_widget, stats_area[1]);
+
+        // Plots each numeric attribute's recent values side by side.
+        let history_areas = Layout::default()
+            .direction(layout::Direction::Horizontal)
+            .constraints(
+                attribute_history
+                    .iter()
+                    .map(|_| layout::Constraint::Ratio(1, attribute_history.len() as u32))
+                    .collect::<Vec<_>>(),
+            )
+            .split(history_area);
+        let bar_set = if styles.utf8 {
+            symbols::bar::NINE_LEVELS
+        } else {
+            view::tasks::ASCII_BARS
+        };
+        for ((name, samples), area) in attribute_history.iter().zip(history_areas.iter()) {
+            let values = samples
+                .iter()
+                .map(|sample| sample.value)
+                .collect::<Vec<_>>();
+            let max = values.iter().copied().max().unwrap_or_default();
+            let title = Line::from(vec![
+                bold(name.to_string()),
+                Span::raw(format!(
+                    " (now {}, max {})",
+                    values.last().copied().unwrap_or_default(),
+                    max
+                )),
+            ]);
+            let block = styles.border_block().title(title);
+            let width = block.inner(*area).width as usize;
+            let shown = &values[values.len().saturating_sub(width)..];
+            let sparkline = Sparkline::default()
+                .data(shown)
+                .max(max.max(1))
+                .bar_set(bar_set.clone())
+                .style(styles.role(Role::Histogram))
+                .block(block);
+            frame.render_widget(sparkline, *area);
+        }
+
         let ctx = AsyncOpsTableCtx {
             initial_render: self.initial_render,
             resource_id: resource.id(),
@@ -121,8 +174,33 @@ impl HelpText for ResourceView {
     fn render_help_content(&self, styles: &view::Styles) -> Paragraph<'static> {
         controls_paragraph(view_controls(), styles)
     }
+
+    fn hints(&self) -> Vec<Hint> {
+        if self.async_ops_table.is_sort_menu_open() {
+            return self.async_ops_table.hints();
+        }
+        let view_async_op = if self.async_ops_table.len() == 0 {
+            Hint::disabled("enter", "view async op", "no async ops yet")
+        } else {
+            Hint::when(
+                self.async_ops_table.selected_item().is_some(),
+                "enter",
+                "view async op",
+                "select an async op with j/k first",
+            )
+        };
+        vec![Hint::enabled("esc", "return to task list"), view_async_op]
+    }
 }
 
+/// Below this width, the resource and attributes boxes are stacked
+/// vertically.
+const STACK_PANES_BELOW: u16 = 100;
+
+/// The height of the plots of the resource's attribute history, including
+/// their borders.
+const HISTORY_HEIGHT: u16 = 5;
+
 fn view_controls() -> &'static [ControlDisplay] {
     static VIEW_CONTROLS: OnceCell<Vec<ControlDisplay>> = OnceCell::new();
 
@@ -0,0 +1,245 @@
+use crate::{
+    state::{
+        resources::{GroupSortBy, ResourceGroup},
+        State,
+    },
+    view::{
+        self, bold,
+        controls::{ControlDisplay, Controls, KeyDisplay},
+        resources,
+        table::{self, Breakpoint, Columns, TableList, TableListState},
+    },
+};
+
+use once_cell::sync::OnceCell;
+use ratatui::{
+    layout,
+    style::{self, Style},
+    text::Line,
+    widgets::{Cell, Row, Table},
+};
+
+/// A summary of the resources, grouped by their kind and concrete type.
+#[derive(Debug, Default)]
+pub(crate) struct ResourceGroupsTable {}
+
+impl view::SortBy for GroupSortBy {
+    fn as_column(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl TableList<9> for ResourceGroupsTable {
+    type Row = ResourceGroup;
+    type Sort = GroupSortBy;
+    type Context = ();
+
+    const HEADER: &'static [&'static str; 9] = &[
+        "Kind",
+        "Type",
+        "Live",
+        "Dropped",
+        "Created/s",
+        "Dropped/s",
+        "Ready",
+        "Polls/Ready",
+        "Attributes",
+    ];
+
+    const WIDTHS: &'static [usize; 9] = &[
+        Self::HEADER[0].len() + 1,
+        Self::HEADER[1].len() + 1,
+        Self::HEADER[2].len() + 1,
+        Self::HEADER[3].len() + 1,
+        Self::HEADER[4].len() + 1,
+        Self::HEADER[5].len() + 1,
+        Self::HEADER[6].len() + 1,
+        Self::HEADER[7].len() + 1,
+        Self::HEADER[8].len() + 1,
+    ];
+
+    const BREAKPOINTS: &'static [Breakpoint] = &[
+        // Created/s, Dropped/s
+        Breakpoint {
+            below: 120,
+            hide: &[4, 5],
+        },
+        // Ready, Polls/Ready
+        Breakpoint {
+            below: 80,
+            hide: &[6, 7],
+        },
+    ];
+
+    const EXPORTABLE: bool = true;
+
+    fn view_controls() -> &'static [ControlDisplay] {
+        static VIEW_CONTROLS: OnceCell<Vec<ControlDisplay>> = OnceCell::new();
+
+        VIEW_CONTROLS.get_or_init(|| {
+            let groups_controls = &[ControlDisplay {
+                action: "return to resources",
+                keys: &[
+                    KeyDisplay {
+                        base: "esc",
+                        utf8: Some("\u{238B} esc"),
+                    },
+                    KeyDisplay {
+                        base: "K",
+                        utf8: None,
+                    },
+                ],
+            }];
+            [
+                table::view_controls(),
+                table::export_controls(),
+                groups_controls,
+            ]
+            .concat()
+        })
+    }
+
+    fn export_row(group: &ResourceGroup, _: std::time::SystemTime) -> [String; 9] {
+        [
+            group.kind().to_owned(),
+            group.concrete_type().to_owned(),
+            group.live().to_string(),
+            group.dropped().to_string(),
+            format!("{:.1}", group.created_rate()),
+            format!("{:.1}", group.dropped_rate()),
+            resources::ready_ratio(group.poll_counts()),
+            resources::polls_per_ready(group.poll_counts()),
+            attribute_totals(group),
+        ]
+    }
+
+    fn render(
+        table_list_state: &mut TableListState<Self, 9>,
+        styles: &view::Styles,
+        frame: &mut ratatui::terminal::Frame,
+        area: layout::Rect,
+        state: &mut State,
+        _: Self::Context,
+    ) {
+        if state.last_updated_at().is_none() {
+            // If we have never gotten an update yet, skip...
+            return;
+        }
+
+        let columns = Columns::for_width::<Self>(area.width);
+
+        table_list_state
+            .sorted_items
+            .extend(state.resources_state_mut().take_new_groups());
+        table_list_state.sort_items(|sort_by, groups| sort_by.sort(groups));
+
+        let mut kind_width = table_list_state.column_width(0);
+        let mut type_width = table_list_state.column_width(1);
+
+        let controls = Controls::new(Self::view_controls(), &area, styles);
+        let chunks = layout::Layout::default()
+            .direction(layout::Direction::Vertical)
+            .margin(0)
+            .constraints(
+                [
+                    layout::Constraint::Length(controls.height()),
+                    layout::Constraint::Max(area.height),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+        let controls_area = chunks[0];
+        let groups_area = chunks[1];
+
+        // The table's borders and header take up three lines.
+        let window = table_list_state.visible_rows(groups_area.height.saturating_sub(3) as usize);
+
+        let rows = table_list_state
+            .window_items(&window)
+            .into_iter()
+            .filter_map(|group| {
+                let group = group.upgrade()?;
+                let group = group.borrow();
+                let row = Row::new(columns.select([
+                    Cell::from(kind_width.update_str(group.kind()).to_owned()),
+                    Cell::from(type_width.update_str(group.concrete_type()).to_owned()),
+                    Cell::from(group.live().to_string()),
+                    Cell::from(group.dropped().to_string()),
+                    Cell::from(format!("{:.1}", group.created_rate())),
+                    Cell::from(format!("{:.1}", group.dropped_rate())),
+                    Cell::from(resources::ready_ratio(group.poll_counts())),
+                    Cell::from(resources::polls_per_ready(group.poll_counts())),
+                    Cell::from(Line::from(attribute_totals(&group))),
+                ]));
+                Some(if group.live() == 0 {
+                    row.style(styles.terminated())
+                } else {
+                    row
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let header_style = styles.table_header();
+
+        let header = Row::new(columns.select(Self::HEADER.iter().enumerate().map(
+            |(idx, &value)| {
+                if idx == table_list_state.selected_column {
+                    if table_list_state.sort_descending {
+                        Cell::from(styles.ascending(value))
+                    } else {
+                        Cell::from(styles.descending(value))
+                    }
+                } else {
+                    Cell::from(value)
+                }
+            },
+        )))
+        .height(1)
+        .style(header_style);
+
+        let block = styles.border_block().title(vec![bold(format!(
+            "Resources by kind ({}) ",
+            table_list_state.len()
+        ))]);
+
+        table_list_state.remember_width(0, &kind_width);
+        table_list_state.remember_width(1, &type_width);
+        let widths = columns.select([
+            kind_width.constraint(),
+            type_width.constraint(),
+            layout::Constraint::Length(Self::WIDTHS[2] as u16),
+            layout::Constraint::Length(Self::WIDTHS[3] as u16),
+            layout::Constraint::Length(Self::WIDTHS[4] as u16),
+            layout::Constraint::Length(Self::WIDTHS[5] as u16),
+            layout::Constraint::Length(Self::WIDTHS[6] as u16),
+            layout::Constraint::Length(Self::WIDTHS[7] as u16),
+            layout::Constraint::Percentage(100),
+        ]);
+
+        let table = Table::default()
+            .rows(rows)
+            .header(header)
+            .block(block)
+            .widths(widths)
+            .highlight_symbol(view::TABLE_HIGHLIGHT_SYMBOL)
+            .highlight_style(Style::default().add_modifier(style::Modifier::BOLD));
+
+        table_list_state.render_window(frame, groups_area, table, &window);
+        frame.render_widget(controls.into_widget(), controls_area);
+
+        table_list_state
+            .sorted_items
+            .retain(|g| g.upgrade().is_some());
+    }
+}
+
+/// Formats the totals of the group's numeric attributes, such as
+/// `permits=12`.
+fn attribute_totals(group: &ResourceGroup) -> String {
+    group
+        .attribute_totals()
+        .into_iter()
+        .map(|(name, (total, unit))| format!("{}={}{}", name, total, unit.unwrap_or_default()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
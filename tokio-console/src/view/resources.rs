@@ -1,32 +1,88 @@
 use crate::{
     state::{
-        resources::{Resource, SortBy},
+        resources::{PollCounts, Resource, ResourceGroup, SortBy, TypeVisibility},
+        store::Id,
         State,
     },
     view::{
         self, bold,
-        controls::Controls,
-        table::{view_controls, TableList, TableListState},
-        DUR_LEN, DUR_TABLE_PRECISION,
+        controls::{ControlDisplay, Controls, KeyDisplay},
+        help::Hint,
+        table::{self, attributes_text, Breakpoint, Columns, TableList, TableListState},
+        Role, DUR_TABLE_PRECISION,
     },
 };
+use once_cell::sync::OnceCell;
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
 
 use ratatui::{
     layout,
-    style::{self, Color, Style},
-    text::Line,
+    style::{self, Style},
+    text::{Line, Span},
     widgets::{Cell, Row, Table},
 };
 
 #[derive(Debug, Default)]
 pub(crate) struct ResourcesTable {}
 
-impl TableList<9> for ResourcesTable {
+/// Limits the resources list to the resources with one kind and concrete
+/// type, when it is opened from the resources summary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TypeFilter {
+    kind: String,
+    concrete_type: String,
+}
+
+/// How the resources list nests resources under their parents.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Tree {
+    /// Whether resources are listed under their parents.
+    enabled: bool,
+    /// Resources whose descendants are hidden from the list.
+    collapsed: HashSet<Id<Resource>>,
+}
+
+/// Where a resource is listed in the tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TreeRow {
+    /// The index of the resource in the list the tree was built from.
+    index: usize,
+    /// How many ancestors the resource has in the list.
+    depth: usize,
+    /// Whether any of the resources in the list are its children.
+    has_children: bool,
+    /// Whether one of the resource's ancestors is collapsed.
+    hidden: bool,
+}
+
+impl TypeFilter {
+    pub(crate) fn new(group: &ResourceGroup) -> Self {
+        Self {
+            kind: group.kind().to_owned(),
+            concrete_type: group.concrete_type().to_owned(),
+        }
+    }
+
+    fn matches(&self, resource: &Resource) -> bool {
+        resource.kind() == self.kind && resource.concrete_type() == self.concrete_type
+    }
+}
+
+impl view::SortBy for SortBy {
+    fn as_column(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl TableList<11> for ResourcesTable {
     type Row = Resource;
     type Sort = SortBy;
-    type Context = ();
+    type Context = (Option<TypeFilter>, Tree);
 
-    const HEADER: &'static [&'static str; 9] = &[
+    const HEADER: &'static [&'static str; 11] = &[
         "ID",
         "Parent",
         "Kind",
@@ -34,11 +90,13 @@ impl TableList<9> for ResourcesTable {
         "Target",
         "Type",
         "Vis",
+        "Ready",
+        "Polls/Ready",
         "Location",
         "Attributes",
     ];
 
-    const WIDTHS: &'static [usize; 9] = &[
+    const WIDTHS: &'static [usize; 11] = &[
         Self::HEADER[0].len() + 1,
         Self::HEADER[1].len() + 1,
         Self::HEADER[2].len() + 1,
@@ -48,15 +106,100 @@ impl TableList<9> for ResourcesTable {
         Self::HEADER[6].len() + 1,
         Self::HEADER[7].len() + 1,
         Self::HEADER[8].len() + 1,
+        Self::HEADER[9].len() + 1,
+        Self::HEADER[10].len() + 1,
+    ];
+
+    const BREAKPOINTS: &'static [Breakpoint] = &[
+        // Vis, Polls/Ready
+        Breakpoint {
+            below: 140,
+            hide: &[6, 8],
+        },
+        // Parent, Target, Location
+        Breakpoint {
+            below: 100,
+            hide: &[1, 4, 9],
+        },
     ];
 
+    const COMPACT_BELOW: u16 = 100;
+
+    const EXPORTABLE: bool = true;
+
+    fn view_controls() -> &'static [ControlDisplay] {
+        static VIEW_CONTROLS: OnceCell<Vec<ControlDisplay>> = OnceCell::new();
+
+        VIEW_CONTROLS.get_or_init(|| {
+            let resources_controls = &[
+                ControlDisplay {
+                    action: "nest under parents",
+                    keys: &[KeyDisplay {
+                        base: "p",
+                        utf8: None,
+                    }],
+                },
+                ControlDisplay {
+                    action: "collapse/expand children",
+                    keys: &[KeyDisplay {
+                        base: "c",
+                        utf8: None,
+                    }],
+                },
+                ControlDisplay {
+                    action: "expand all",
+                    keys: &[KeyDisplay {
+                        base: "C",
+                        utf8: None,
+                    }],
+                },
+                ControlDisplay {
+                    action: "summary by kind",
+                    keys: &[KeyDisplay {
+                        base: "K",
+                        utf8: None,
+                    }],
+                },
+                ControlDisplay {
+                    action: "clear type filter",
+                    keys: &[KeyDisplay {
+                        base: "esc",
+                        utf8: Some("\u{238B} esc"),
+                    }],
+                },
+            ];
+            [
+                table::view_controls(),
+                table::export_controls(),
+                resources_controls,
+            ]
+            .concat()
+        })
+    }
+
+    fn export_row(resource: &Resource, now: std::time::SystemTime) -> [String; 11] {
+        [
+            resource.id().to_string(),
+            resource.parent_id().to_owned(),
+            resource.kind().to_owned(),
+            format!("{:?}", resource.total(now)),
+            resource.target().to_owned(),
+            resource.concrete_type().to_owned(),
+            resource.type_visibility().as_str().to_owned(),
+            ready_ratio(resource.poll_counts()),
+            polls_per_ready(resource.poll_counts()),
+            resource.location().to_owned(),
+            attributes_text(resource.attributes()),
+        ]
+    }
+
     fn render(
-        table_list_state: &mut TableListState<Self, 9>,
+        table_list_state: &mut TableListState<Self, 11>,
         styles: &view::Styles,
         frame: &mut ratatui::terminal::Frame,
         area: layout::Rect,
         state: &mut State,
-        _: Self::Context,
+        (filter, tree): Self::Context,
     ) {
         let now = if let Some(now) = state.last_updated_at() {
             now
@@ -65,23 +208,78 @@ impl TableList<9> for ResourcesTable {
             return;
         };
 
+        let columns = Columns::for_width::<Self>(area.width);
+        let styles = &if columns.compact() {
+            styles.compact_durations()
+        } else {
+            styles.clone()
+        };
+
         table_list_state
             .sorted_items
             .extend(state.resources_state_mut().take_new_resources());
-        table_list_state
-            .sort_by
-            .sort(now, &mut table_list_state.sorted_items);
+
+        // Move resources in and out of the hidden items as the filter
+        // changes.
+        if filter.is_some() || !table_list_state.hidden_items.is_empty() {
+            let items = table_list_state
+                .sorted_items
+                .drain(..)
+                .chain(table_list_state.hidden_items.drain(..))
+                .collect::<Vec<_>>();
+            let (shown, hidden) = items.into_iter().partition(|resource| {
+                resource.upgrade().is_some_and(|resource| {
+                    filter
+                        .as_ref()
+                        .map_or(true, |filter| filter.matches(&resource.borrow()))
+                })
+            });
+            table_list_state.sorted_items = shown;
+            table_list_state.hidden_items = hidden;
+        }
+
+        table_list_state.sort_items(|sort_by, resources| sort_by.sort(now, resources));
+        // The depth of each resource in the tree, and whether it has children
+        // to collapse.
+        let tree_rows = if tree.enabled {
+            tree.arrange(table_list_state)
+        } else {
+            HashMap::new()
+        };
 
         let viz_len: u16 = Self::WIDTHS[6] as u16;
 
-        let mut id_width = view::Width::new(Self::WIDTHS[0] as u16);
-        let mut parent_width = view::Width::new(Self::WIDTHS[1] as u16);
+        let mut id_width = table_list_state.column_width(0);
+        let mut parent_width = table_list_state.column_width(1);
+
+        let mut kind_width = table_list_state.column_width(2);
+        let mut target_width = table_list_state.column_width(4);
+        let mut type_width = table_list_state.column_width(5);
+        let mut location_width = table_list_state.column_width(9);
+
+        let controls = Controls::new(Self::view_controls(), &area, styles);
 
-        let mut kind_width = view::Width::new(Self::WIDTHS[2] as u16);
-        let mut target_width = view::Width::new(Self::WIDTHS[4] as u16);
-        let mut type_width = view::Width::new(Self::WIDTHS[5] as u16);
-        let mut location_width = view::Width::new(Self::WIDTHS[7] as u16);
+        let layout = layout::Layout::default()
+            .direction(layout::Direction::Vertical)
+            .margin(0);
 
+        let chunks = layout
+            .constraints(
+                [
+                    layout::Constraint::Length(controls.height()),
+                    layout::Constraint::Max(area.height),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+        let controls_area = chunks[0];
+        let tasks_area = chunks[1];
+
+        // Only format the resources that will be visible. The table's
+        // borders and header take up three lines.
+        let window = table_list_state.visible_rows(tasks_area.height.saturating_sub(3) as usize);
+
+        let highlight_changes = table_list_state.highlight_changes;
         let rows = {
             let id_width = &mut id_width;
             let parent_width = &mut parent_width;
@@ -89,111 +287,115 @@ impl TableList<9> for ResourcesTable {
             let target_width = &mut target_width;
             let type_width = &mut type_width;
             let location_width = &mut location_width;
+            let tree = &tree;
+            let tree_rows = &tree_rows;
 
             table_list_state
-                .sorted_items
-                .iter()
+                .window_items(&window)
+                .into_iter()
                 .filter_map(move |resource| {
                     let resource = resource.upgrade()?;
                     let resource = resource.borrow();
 
-                    let mut row = Row::new(vec![
-                        Cell::from(id_width.update_str(format!(
-                            "{:>width$}",
-                            resource.id(),
-                            width = id_width.chars() as usize
-                        ))),
-                        Cell::from(parent_width.update_str(resource.parent_id()).to_owned()),
-                        Cell::from(kind_width.update_str(resource.kind()).to_owned()),
-                        Cell::from(styles.time_units(
-                            resource.total(now),
-                            DUR_TABLE_PRECISION,
-                            Some(DUR_LEN),
-                        )),
-                        Cell::from(target_width.update_str(resource.target()).to_owned()),
-                        Cell::from(type_width.update_str(resource.concrete_type()).to_owned()),
-                        Cell::from(resource.type_visibility().render(styles)),
-                        Cell::from(location_width.update_str(resource.location()).to_owned()),
-                        Cell::from(
-                            resource
-                                .formatted_attributes()
-                                .iter()
-                                .flatten()
-                                .cloned()
-                                .collect::<Line>(),
-                        ),
-                    ]);
+                    let mut row = Row::new(
+                        columns.select([
+                            Cell::from(id_width.update_str(match tree_rows.get(&resource.id()) {
+                                Some(row) => tree.id_label(&resource, row, styles),
+                                None => format!(
+                                    "{:>width$}",
+                                    resource.id(),
+                                    width = id_width.chars() as usize
+                                ),
+                            })),
+                            Cell::from(parent_width.update_str(resource.parent_id()).to_owned()),
+                            Cell::from(kind_width.update_str(resource.kind()).to_owned()),
+                            Cell::from(styles.time_units(
+                                resource.total(now),
+                                DUR_TABLE_PRECISION,
+                                Some(styles.duration_width()),
+                            )),
+                            Cell::from(target_width.update_str(resource.target()).to_owned()),
+                            Cell::from(type_width.update_str(resource.concrete_type()).to_owned()),
+                            Cell::from(render_visibility(resource.type_visibility(), styles)),
+                            Cell::from(ready_ratio(resource.poll_counts())),
+                            Cell::from(polls_per_ready(resource.poll_counts())),
+                            Cell::from(location_width.update_str(resource.location()).to_owned()),
+                            Cell::from(
+                                view::attributes(styles, resource.attributes())
+                                    .into_iter()
+                                    .flatten()
+                                    .collect::<Line>(),
+                            ),
+                        ]),
+                    );
 
                     if resource.dropped() {
                         row = row.style(styles.terminated());
                     }
+                    if highlight_changes && resource.changed_in_update(now) {
+                        row = row.style(styles.changed());
+                    }
 
                     Some(row)
                 })
         };
 
-        let header_style = if styles.color(Color::Cyan).is_some() {
-            Style::default()
-        } else {
-            Style::default().add_modifier(style::Modifier::REVERSED)
-        };
-        let header_style = header_style.add_modifier(style::Modifier::BOLD);
+        let header_style = styles.table_header();
 
-        let header = Row::new(Self::HEADER.iter().enumerate().map(|(idx, &value)| {
-            if idx == table_list_state.selected_column {
-                if table_list_state.sort_descending {
-                    Cell::from(styles.ascending(value))
+        let header = Row::new(columns.select(Self::HEADER.iter().enumerate().map(
+            |(idx, &value)| {
+                if idx == table_list_state.selected_column {
+                    if table_list_state.sort_descending {
+                        Cell::from(styles.ascending(value))
+                    } else {
+                        Cell::from(styles.descending(value))
+                    }
                 } else {
-                    Cell::from(styles.descending(value))
+                    Cell::from(value)
                 }
-            } else {
-                Cell::from(value)
-            }
-        }))
+            },
+        )))
         .height(1)
         .style(header_style);
 
-        let table = if table_list_state.sort_descending {
-            Table::default().rows(rows)
-        } else {
-            Table::default().rows(rows.rev())
-        };
-
-        let block = styles.border_block().title(vec![bold(format!(
-            "Resources ({}) ",
-            table_list_state.len()
-        ))]);
+        let table = Table::default().rows(rows);
 
-        let controls = Controls::new(view_controls(), &area, styles);
-
-        let layout = layout::Layout::default()
-            .direction(layout::Direction::Vertical)
-            .margin(0);
-
-        let chunks = layout
-            .constraints(
-                [
-                    layout::Constraint::Length(controls.height()),
-                    layout::Constraint::Max(area.height),
-                ]
-                .as_ref(),
-            )
-            .split(area);
-        let controls_area = chunks[0];
-        let tasks_area = chunks[1];
+        let mut title = vec![bold(format!("Resources ({}) ", table_list_state.len()))];
+        if tree.enabled {
+            title.push(Span::from("[tree] "));
+        }
+        if let Some(filter) = &filter {
+            title.push(Span::from(format!(
+                "[{} {}] ",
+                filter.kind, filter.concrete_type
+            )));
+        }
+        let block = styles.border_block().title(title);
 
         let attributes_width = layout::Constraint::Percentage(100);
-        let widths = &[
+        for (column, width) in [
+            (0, &id_width),
+            (1, &parent_width),
+            (2, &kind_width),
+            (4, &target_width),
+            (5, &type_width),
+            (9, &location_width),
+        ] {
+            table_list_state.remember_width(column, width);
+        }
+        let widths = columns.select([
             id_width.constraint(),
             parent_width.constraint(),
             kind_width.constraint(),
-            layout::Constraint::Length(DUR_LEN as u16),
+            layout::Constraint::Length(styles.duration_width() as u16),
             target_width.constraint(),
             type_width.constraint(),
             layout::Constraint::Length(viz_len),
+            layout::Constraint::Length(Self::WIDTHS[7] as u16),
+            layout::Constraint::Length(Self::WIDTHS[8] as u16),
             location_width.constraint(),
             attributes_width,
-        ];
+        ]);
 
         let table = table
             .header(header)
@@ -202,11 +404,263 @@ impl TableList<9> for ResourcesTable {
             .highlight_symbol(view::TABLE_HIGHLIGHT_SYMBOL)
             .highlight_style(Style::default().add_modifier(style::Modifier::BOLD));
 
-        frame.render_stateful_widget(table, tasks_area, &mut table_list_state.table_state);
+        table_list_state.render_window(frame, tasks_area, table, &window);
         frame.render_widget(controls.into_widget(), controls_area);
 
         table_list_state
             .sorted_items
             .retain(|t| t.upgrade().is_some());
+        table_list_state
+            .hidden_items
+            .retain(|t| t.upgrade().is_some());
+    }
+}
+
+impl Tree {
+    pub(crate) fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    /// Hides the descendants of `resource`, or shows them again if they're
+    /// hidden, if the list is shown as a tree.
+    pub(crate) fn toggle_collapsed(&mut self, resource: &Resource) {
+        if self.enabled && !self.collapsed.remove(&resource.id()) {
+            self.collapsed.insert(resource.id());
+        }
+    }
+
+    pub(crate) fn expand_all(&mut self) {
+        self.collapsed.clear();
+    }
+
+    /// Returns hints about the tree actions which are available.
+    pub(crate) fn hints(&self) -> Vec<Hint> {
+        let mut hints = vec![
+            Hint::enabled(
+                "p",
+                if self.enabled {
+                    "list resources"
+                } else {
+                    "nest resources"
+                },
+            ),
+            Hint::when(
+                self.enabled,
+                "c",
+                "collapse/expand children",
+                "press p to nest resources first",
+            ),
+        ];
+        if self.enabled && !self.collapsed.is_empty() {
+            hints.push(Hint::enabled("C", "expand all"));
+        }
+        hints
+    }
+
+    /// Reorders the table's resources so that each is listed under its
+    /// parent, keeping the sort order among siblings, and moves the
+    /// descendants of collapsed resources to the hidden items.
+    ///
+    /// Returns where each of the listed resources is in the tree.
+    fn arrange(
+        &self,
+        table: &mut TableListState<ResourcesTable, 11>,
+    ) -> HashMap<Id<Resource>, TreeRow> {
+        // The tree is built in the order the rows are displayed in, which is
+        // the reverse of the sorted order unless the sort is descending.
+        let mut items = table
+            .sorted_items
+            .drain(..)
+            .filter_map(|item| Some((item.upgrade()?, item)))
+            .collect::<Vec<_>>();
+        if !table.sort_descending {
+            items.reverse();
+        }
+        let nodes = items
+            .iter()
+            .map(|(resource, _)| {
+                let resource = resource.borrow();
+                (resource.id(), resource.parent_resource())
+            })
+            .collect::<Vec<_>>();
+
+        let mut rows = HashMap::with_capacity(nodes.len());
+        for row in tree_order(&nodes, &self.collapsed) {
+            let (_, item) = &items[row.index];
+            if row.hidden {
+                table.hidden_items.push(item.clone());
+            } else {
+                table.sorted_items.push(item.clone());
+                rows.insert(nodes[row.index].0, row);
+            }
+        }
+        if !table.sort_descending {
+            table.sorted_items.reverse();
+        }
+        rows
+    }
+
+    /// Formats a resource's ID indented by its depth in the tree, with a
+    /// marker showing whether its children are collapsed.
+    fn id_label(&self, resource: &Resource, row: &TreeRow, styles: &view::Styles) -> String {
+        let marker = if !row.has_children {
+            "  "
+        } else if self.collapsed.contains(&resource.id()) {
+            styles.if_utf8("\u{25B8} ", "+ ")
+        } else {
+            styles.if_utf8("\u{25BE} ", "- ")
+        };
+        format!("{}{}{}", "  ".repeat(row.depth), marker, resource.id())
+    }
+}
+
+/// Lays out `nodes`, given as their own and their parents' IDs, as a tree,
+/// returning them in depth-first order.
+///
+/// Siblings keep the order they're given in. Nodes whose parents aren't in
+/// `nodes` are roots of the tree, and the descendants of `collapsed` nodes
+/// are marked as hidden.
+fn tree_order<K: Hash + Eq + Copy>(
+    nodes: &[(K, Option<K>)],
+    collapsed: &HashSet<K>,
+) -> Vec<TreeRow> {
+    let indices: HashMap<K, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(index, &(id, _))| (id, index))
+        .collect();
+    let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut roots = Vec::new();
+    for (index, &(_, parent)) in nodes.iter().enumerate() {
+        match parent.and_then(|parent| indices.get(&parent)) {
+            Some(&parent) if parent != index => children.entry(parent).or_default().push(index),
+            _ => roots.push(index),
+        }
+    }
+
+    let mut rows = Vec::with_capacity(nodes.len());
+    let mut visited = vec![false; nodes.len()];
+    // The stack holds the nodes still to visit, last first, along with their
+    // depth and whether they're hidden.
+    let mut stack: Vec<(usize, usize, bool)> = Vec::new();
+    // Nodes which are only reachable through a cycle of parents have no
+    // root, so any left unvisited are treated as roots as well.
+    for root in roots.into_iter().chain(0..nodes.len()) {
+        stack.push((root, 0, false));
+        while let Some((index, depth, hidden)) = stack.pop() {
+            if std::mem::replace(&mut visited[index], true) {
+                continue;
+            }
+            let node_children = children.get(&index).map(Vec::as_slice).unwrap_or_default();
+            rows.push(TreeRow {
+                index,
+                depth,
+                has_children: !node_children.is_empty(),
+                hidden,
+            });
+            let hide_children = hidden || collapsed.contains(&nodes[index].0);
+            stack.extend(
+                node_children
+                    .iter()
+                    .rev()
+                    .map(|&child| (child, depth + 1, hide_children)),
+            );
+        }
+    }
+    rows
+}
+
+/// Formats the percentage of poll ops which returned ready.
+/// Returns the icon, or the abbreviation if UTF-8 is disabled, for whether a
+/// resource's type is public.
+pub(crate) fn render_visibility(
+    visibility: TypeVisibility,
+    styles: &view::Styles,
+) -> Span<'static> {
+    const INT_UTF8: &str = "\u{1F512}";
+    const PUB_UTF8: &str = "\u{2705}";
+    match visibility {
+        TypeVisibility::Internal => {
+            Span::styled(styles.if_utf8(INT_UTF8, "INT"), styles.role(Role::Bad))
+        }
+        TypeVisibility::Public => {
+            Span::styled(styles.if_utf8(PUB_UTF8, "PUB"), styles.role(Role::Good))
+        }
+    }
+}
+
+pub(crate) fn ready_ratio(counts: PollCounts) -> String {
+    counts
+        .ready_ratio()
+        .map_or_else(|| "-".to_owned(), |ratio| format!("{:.0}%", ratio * 100.0))
+}
+
+/// Formats the average number of polls it took for poll ops to return ready.
+pub(crate) fn polls_per_ready(counts: PollCounts) -> String {
+    counts
+        .polls_per_ready()
+        .map_or_else(|| "-".to_owned(), |polls| format!("{:.1}", polls))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tree_order_nests_children_under_parents() {
+        // Given in display order: two roots, with children of the second
+        // listed before it.
+        let nodes = [
+            (1, None),
+            (3, Some(2)),
+            (2, None),
+            (4, Some(3)),
+            (5, Some(2)),
+            (6, Some(99)),
+        ];
+        let order = |collapsed: &[u64]| {
+            tree_order(&nodes, &collapsed.iter().copied().collect())
+                .into_iter()
+                .map(|row| (nodes[row.index].0, row.depth, row.hidden))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            order(&[]),
+            [
+                (1, 0, false),
+                (2, 0, false),
+                (3, 1, false),
+                (4, 2, false),
+                (5, 1, false),
+                // The parent isn't listed, so this is a root.
+                (6, 0, false),
+            ]
+        );
+
+        // Collapsing a resource hides all of its descendants.
+        assert_eq!(
+            order(&[2]),
+            [
+                (1, 0, false),
+                (2, 0, false),
+                (3, 1, true),
+                (4, 2, true),
+                (5, 1, true),
+                (6, 0, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn tree_order_lists_cycles_once() {
+        let nodes = [(1, Some(2)), (2, Some(1)), (3, None)];
+        let rows = tree_order(&nodes, &HashSet::new());
+        let mut listed = rows
+            .iter()
+            .map(|row| nodes[row.index].0)
+            .collect::<Vec<_>>();
+        listed.sort_unstable();
+        assert_eq!(listed, [1, 2, 3]);
     }
 }
@@ -0,0 +1,48 @@
+use crate::{
+    state::runtime::RuntimeState,
+    view::{Role, Styles},
+};
+use ratatui::text::Span;
+
+/// The number of recent saturation samples to chart in the header.
+const SATURATION_CHART_LEN: usize = 20;
+
+const UTF8_BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const ASCII_BARS: [char; 8] = ['_', '.', ',', '-', '~', '=', '*', '#'];
+
+/// Renders the runtime saturation estimate, followed by a small chart of its
+/// recent history.
+///
+/// Returns an empty `Vec` if no saturation estimate has been received yet.
+pub(crate) fn saturation(styles: &Styles, state: &RuntimeState) -> Vec<Span<'static>> {
+    let current = match state.saturation() {
+        Some(current) => current,
+        None => return Vec::new(),
+    };
+
+    let bars = if styles.utf8 { UTF8_BARS } else { ASCII_BARS };
+    let chart = state
+        .recent_saturation(SATURATION_CHART_LEN)
+        .map(|sample| {
+            let idx = (sample * (bars.len() - 1) as f64).round() as usize;
+            bars[idx.min(bars.len() - 1)]
+        })
+        .collect::<String>();
+    let style = styles.role(saturation_role(current));
+
+    vec![
+        Span::raw(" saturation: "),
+        Span::styled(format!("{:>3.0}% ", current * 100.0), style),
+        Span::styled(chart, style),
+    ]
+}
+
+fn saturation_role(saturation: f64) -> Role {
+    if saturation >= 0.8 {
+        Role::Bad
+    } else if saturation >= 0.5 {
+        Role::Caution
+    } else {
+        Role::Good
+    }
+}
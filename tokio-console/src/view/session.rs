@@ -0,0 +1,62 @@
+use ratatui::{
+    text::{Line, Span},
+    widgets::{Clear, Paragraph},
+};
+
+use crate::{conn::ConnectionStats, state::SessionStats, view};
+
+/// Renders a popup with totals for the whole session, since the console
+/// started.
+pub(crate) fn render(
+    styles: &view::Styles,
+    frame: &mut ratatui::terminal::Frame,
+    conn: &ConnectionStats,
+    session: &SessionStats,
+) {
+    let stat = |name: &'static str, value: Span<'static>| {
+        Line::from(vec![view::bold(name), Span::from(": "), value])
+    };
+    let count = |value: u64| Span::from(value.to_string());
+
+    let lines = vec![
+        stat(
+            "Session duration",
+            styles.time_units(conn.started_at.elapsed(), view::DUR_LIST_PRECISION, None),
+        ),
+        stat("Updates received", count(session.updates)),
+        stat("Tasks spawned", count(session.tasks_spawned)),
+        stat("Tasks dropped", count(session.tasks_dropped)),
+        stat("Dropped events", count(session.dropped_events)),
+        stat("Missed updates", count(session.missed_updates)),
+        stat("Reconnects", count(conn.reconnects())),
+        stat(
+            "Bytes received",
+            Span::from(format_bytes(conn.bytes_received)),
+        ),
+    ];
+
+    let area = view::help::popup_area(frame.size());
+    let stats = Paragraph::new(lines).block(styles.border_block().title("Session Stats"));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(stats, area);
+}
+
+/// Formats a number of bytes using the largest binary unit it has at least one
+/// of.
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next;
+    }
+    format!("{:.2} {}", value, unit)
+}
@@ -0,0 +1,112 @@
+use ratatui::{
+    layout,
+    text::{Line, Span},
+    widgets::{Clear, Paragraph},
+};
+
+use crate::view::{
+    self,
+    help::Hint,
+    table::{TableList, TableListState},
+};
+
+/// A menu, shown over a table, for choosing the columns the table is sorted
+/// by and in which direction.
+#[derive(Debug, Clone)]
+pub(crate) struct SortMenu {
+    /// The index of the highlighted column.
+    pub(crate) cursor: usize,
+}
+
+impl SortMenu {
+    pub(crate) fn new(cursor: usize) -> Self {
+        Self { cursor }
+    }
+
+    /// Moves the cursor to the next column, or the previous one, which
+    /// `sortable` returns `true` for, wrapping around the table's `len`
+    /// columns.
+    pub(crate) fn move_cursor(&mut self, len: usize, sortable: impl Fn(usize) -> bool, next: bool) {
+        for step in 1..len {
+            let column = if next {
+                (self.cursor + step) % len
+            } else {
+                (self.cursor + len - step) % len
+            };
+            if sortable(column) {
+                self.cursor = column;
+                return;
+            }
+        }
+    }
+
+    pub(crate) fn hints() -> Vec<Hint> {
+        vec![
+            Hint::enabled("enter", "sort by column"),
+            Hint::enabled("2", "then by column"),
+            Hint::enabled("x", "clear then by"),
+            Hint::enabled("esc", "close"),
+        ]
+    }
+
+    /// Renders the menu in a popup over `table`, listing each of the columns
+    /// it can be sorted by.
+    pub(crate) fn render<T: TableList<N>, const N: usize>(
+        &self,
+        table: &TableListState<T, N>,
+        styles: &view::Styles,
+        frame: &mut ratatui::terminal::Frame,
+    ) {
+        let direction = |value: &str, descending: bool| {
+            // Matches the arrows shown in the tables' headers.
+            if descending {
+                styles.ascending(value)
+            } else {
+                styles.descending(value)
+            }
+        };
+        let mut lines = vec![
+            Line::from("Press enter to sort by a column, or again to invert it."),
+            Line::from("Press 2 to sort rows it ranks equally by another column."),
+            Line::from(""),
+        ];
+        let sortable = (0..N).filter(|&column| T::Sort::try_from(column).is_ok());
+        for column in sortable {
+            let name = T::HEADER[column];
+            let marker = if column == self.cursor {
+                view::TABLE_HIGHLIGHT_SYMBOL
+            } else {
+                "   "
+            };
+            let mut line = vec![Span::raw(marker)];
+            if column == table.selected_column {
+                line.push(direction(name, table.sort_descending));
+                line.push(Span::raw("  (sort by)"));
+            } else if let Some(then_by) = table.then_by.filter(|then_by| then_by.column == column) {
+                line.push(direction(name, then_by.descending));
+                line.push(Span::raw("  (then by)"));
+            } else {
+                line.push(Span::raw(name));
+            }
+            lines.push(Line::from(line));
+        }
+
+        let area = frame.size();
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let width = lines
+            .iter()
+            .map(Line::width)
+            .max()
+            .map_or(0, |width| width as u16 + 4)
+            .min(area.width);
+        let area = layout::Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+        let block = styles.border_block().title("Sort By");
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
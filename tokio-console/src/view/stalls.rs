@@ -0,0 +1,73 @@
+use ratatui::{
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Wrap},
+};
+
+use crate::{
+    state::State,
+    view::{self, Role},
+};
+
+/// Renders a popup listing the sets of stalled tasks which are waiting on
+/// resources held by each other, and so are probably deadlocked.
+pub(crate) fn render(styles: &view::Styles, frame: &mut ratatui::terminal::Frame, state: &State) {
+    let stalls = state.stalls();
+    let threshold = state.stall_threshold();
+
+    let area = view::help::popup_area(frame.size());
+    let block = styles.border_block().title("Stalled Tasks");
+    let inner = block.inner(area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+
+    let mut lines = Vec::new();
+    if stalls.is_empty() {
+        lines.push(Line::from(vec![
+            Span::from("No tasks idle for more than "),
+            styles.time_units(threshold, view::DUR_LIST_PRECISION, None),
+            Span::from(" are waiting on each other."),
+        ]));
+    } else {
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{} probable deadlocks", stalls.len()),
+                styles.role(Role::Bad),
+            ),
+            Span::from(" among tasks idle for more than "),
+            styles.time_units(threshold, view::DUR_LIST_PRECISION, None),
+        ]));
+    }
+
+    for (i, stall) in stalls.iter().enumerate() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(view::bold(format!(
+            "Deadlock {} ({} tasks):",
+            i + 1,
+            stall.links.len()
+        ))));
+        for link in &stall.links {
+            lines.push(Line::from(vec![
+                Span::raw("  "),
+                view::bold(link.task.clone()),
+                Span::from(", idle for "),
+                styles.time_units(link.idle_for, view::DUR_LIST_PRECISION, None),
+            ]));
+            lines.push(Line::from(vec![
+                Span::raw("    waiting on "),
+                Span::from(link.resource.clone()),
+                Span::from(", held by "),
+                view::bold(link.held_by.clone()),
+            ]));
+        }
+    }
+
+    lines.extend([
+        Line::from(""),
+        Line::from(
+            "A resource is held by the task which last acquired it, for as long as its \
+             attributes say it is locked. Only instrumented resources, such as Tokio's \
+             Mutex, RwLock and Semaphore, are included.",
+        ),
+    ]);
+    frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: true }), inner);
+}
@@ -9,14 +9,89 @@ use std::{str::FromStr, time::Duration};
 #[derive(Debug, Clone)]
 pub struct Styles {
     palette: Palette,
+    theme: Theme,
     toggles: config::ColorToggles,
+    duration_format: DurationFormat,
+    highlights: Vec<HighlightRule>,
     pub(crate) utf8: bool,
 }
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
+/// A rule which colors the tasks whose names match a glob pattern, so that
+/// important tasks stand out in large tables.
+///
+/// In the pattern, `*` matches any number of characters and `?` matches any
+/// single character. For example:
+///
+/// ```toml
+/// [[colors.highlights]]
+/// name = "db-*"
+/// color = "cyan"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct HighlightRule {
+    /// The glob pattern task names are matched against.
+    name: String,
+    /// The color matching tasks are shown in.
+    color: HighlightColor,
+}
+
+/// A color named in the config file, such as `cyan`, `light-red`, `208` or
+/// `#ff8800`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+struct HighlightColor(Color);
+
+/// A named set of colors for the console's UI.
+#[derive(clap::ValueEnum, Debug, Default, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    /// Colors for terminals with a dark background.
+    #[default]
+    Dark,
+    /// Colors for terminals with a light background.
+    Light,
+    /// The Solarized color scheme.
+    ///
+    /// This needs the 256-color palette; with fewer colors, the dark theme's
+    /// colors are used instead.
+    Solarized,
+    /// Bright colors, for maximum legibility.
+    HighContrast,
+}
+
+/// What a color is used for, so that each theme can choose it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Role {
+    /// The column a table is sorted by.
+    Header,
+    /// Warning icons.
+    Warning,
+    /// Histogram and chart bars.
+    Histogram,
+    /// Something going well, such as a connected console or a task within
+    /// its latency budget.
+    Good,
+    /// Something worth keeping an eye on, such as a task close to its
+    /// latency budget.
+    Caution,
+    /// Something going badly, such as dropped events or a cancelled task.
+    Bad,
+    /// Informational highlights, such as a reconnection in progress.
+    Info,
+    /// The names of fields and attributes.
+    Key,
+    /// The values of fields and attributes.
+    Value,
+    /// The background of table rows that changed in the last update.
+    Changed,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, Deserialize, Serialize)]
 #[repr(u8)]
 pub enum Palette {
     #[serde(rename = "off")]
+    #[default]
     NoColors,
     /// Use ANSI 8 color palette only.
     #[serde(rename = "8")]
@@ -32,6 +107,21 @@ pub enum Palette {
     All,
 }
 
+/// How durations are displayed.
+#[derive(clap::ValueEnum, Debug, Default, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DurationFormat {
+    /// Adaptive units, such as `1.25ms` or `3m02s`.
+    #[default]
+    Human,
+    /// Seconds, with microsecond precision.
+    Seconds,
+    /// Milliseconds, with microsecond precision.
+    Milliseconds,
+    /// Whole nanoseconds.
+    Nanos,
+}
+
 /// Represents formatted time spans.
 ///
 /// Distinguishing between different units allows appropriate colouring.
@@ -48,6 +138,8 @@ enum FormattedDuration {
     /// picoseconds (`ps`) to seconds (`s`). May contain decimal digits
     /// (e.g. `628.76ms`) or not (e.g. `32ns`)
     Debug(String),
+    /// A duration in a fixed unit chosen by the user, e.g. `0.628760s`
+    Fixed(String),
 }
 
 impl FormattedDuration {
@@ -58,6 +150,7 @@ impl FormattedDuration {
             Self::HoursMinutes(inner) => inner,
             Self::MinutesSeconds(inner) => inner,
             Self::Debug(inner) => inner,
+            Self::Fixed(inner) => inner,
         }
     }
 }
@@ -66,13 +159,82 @@ fn fg_style(color: Color) -> Style {
     Style::default().fg(color)
 }
 
+// === impl Theme ===
+
+impl Theme {
+    fn next(self) -> Self {
+        match self {
+            Self::Dark => Self::Light,
+            Self::Light => Self::Solarized,
+            Self::Solarized => Self::HighContrast,
+            Self::HighContrast => Self::Dark,
+        }
+    }
+
+    /// Returns this theme's color for `role`.
+    fn color(self, role: Role) -> Color {
+        match self {
+            Self::Dark => match role {
+                Role::Header | Role::Info | Role::Histogram => Color::Cyan,
+                Role::Warning => Color::LightYellow,
+                Role::Good => Color::Green,
+                Role::Caution | Role::Value => Color::Yellow,
+                Role::Bad => Color::Red,
+                Role::Key => Color::LightBlue,
+                Role::Changed => Color::DarkGray,
+            },
+            Self::Light => match role {
+                Role::Header | Role::Info | Role::Key | Role::Histogram => Color::Blue,
+                Role::Warning | Role::Caution | Role::Value => Color::Magenta,
+                Role::Good => Color::Green,
+                Role::Bad => Color::Red,
+                Role::Changed => Color::Gray,
+            },
+            // The 256-color approximations of the Solarized accent colors.
+            Self::Solarized => match role {
+                Role::Header | Role::Info => Color::Indexed(37), // cyan
+                Role::Warning => Color::Indexed(166),            // orange
+                Role::Histogram | Role::Key => Color::Indexed(33), // blue
+                Role::Good => Color::Indexed(64),                // green
+                Role::Caution | Role::Value => Color::Indexed(136), // yellow
+                Role::Bad => Color::Indexed(160),                // red
+                Role::Changed => Color::Indexed(235),            // base02
+            },
+            Self::HighContrast => match role {
+                Role::Header | Role::Info => Color::LightCyan,
+                Role::Warning | Role::Caution | Role::Value => Color::LightYellow,
+                Role::Histogram | Role::Key => Color::White,
+                Role::Good => Color::LightGreen,
+                Role::Bad => Color::LightRed,
+                Role::Changed => Color::Blue,
+            },
+        }
+    }
+}
+
+// === impl DurationFormat ===
+
+impl DurationFormat {
+    fn next(self) -> Self {
+        match self {
+            Self::Human => Self::Seconds,
+            Self::Seconds => Self::Milliseconds,
+            Self::Milliseconds => Self::Nanos,
+            Self::Nanos => Self::Human,
+        }
+    }
+}
+
 // === impl Styles ===
 
 impl Styles {
     pub fn from_config(config: config::ViewOptions) -> Self {
         Self {
             palette: config.determine_palette(),
+            theme: config.theme(),
             toggles: config.toggles(),
+            duration_format: config.duration_format(),
+            highlights: config.highlights(),
             utf8: config.is_utf8(),
         }
     }
@@ -156,6 +318,67 @@ impl Styles {
         }
     }
 
+    /// Switches to the next theme.
+    pub(crate) fn cycle_theme(&mut self) {
+        self.theme = self.theme.next();
+    }
+
+    /// Returns the current theme's color for `role`, if the palette can
+    /// display it.
+    ///
+    /// If the palette can't display the theme's color, the dark theme's
+    /// color is used instead.
+    pub(crate) fn role_color(&self, role: Role) -> Option<Color> {
+        self.color(self.theme.color(role))
+            .or_else(|| self.color(Theme::Dark.color(role)))
+    }
+
+    /// Returns a style with the current theme's color for `role` as the
+    /// foreground.
+    pub(crate) fn role(&self, role: Role) -> Style {
+        match self.role_color(role) {
+            Some(color) => Style::default().fg(color),
+            None => Style::default(),
+        }
+    }
+
+    /// Style for table headers.
+    pub(crate) fn table_header(&self) -> Style {
+        let style = if self.role_color(Role::Header).is_some() {
+            Style::default()
+        } else {
+            Style::default().add_modifier(Modifier::REVERSED)
+        };
+        style.add_modifier(Modifier::BOLD)
+    }
+
+    /// Switches to the next duration format.
+    pub(crate) fn cycle_duration_format(&mut self) {
+        self.duration_format = self.duration_format.next();
+    }
+
+    /// Returns the width of duration columns in tables.
+    ///
+    /// Human-readable durations are at most 6 characters wide, but durations
+    /// in a fixed unit need more room.
+    pub(crate) fn duration_width(&self) -> usize {
+        match self.duration_format {
+            DurationFormat::Human => super::DUR_LEN,
+            DurationFormat::Seconds => 11,
+            DurationFormat::Milliseconds => 12,
+            DurationFormat::Nanos => 14,
+        }
+    }
+
+    /// Returns these styles, but with durations in the shortest format, for
+    /// tables which are too narrow for the selected [`DurationFormat`].
+    pub(crate) fn compact_durations(&self) -> Self {
+        Self {
+            duration_format: DurationFormat::Human,
+            ..self.clone()
+        }
+    }
+
     /// Creates a span with a formatted duration inside.
     ///
     /// The formatted duration will be colored depending on the palette
@@ -165,6 +388,9 @@ impl Styles {
     /// added. Otherwise the text in the span will be left-padded to
     /// the specified width (right aligned). Passing `Some(0)` is
     /// equivalent to `None`.
+    ///
+    /// If a fixed [`DurationFormat`] is selected, `prec` is ignored and the
+    /// duration is not colored by its unit.
     pub fn time_units<'a>(&self, dur: Duration, prec: usize, width: Option<usize>) -> Span<'a> {
         let formatted = self.duration_text(dur, width.unwrap_or(0), prec);
 
@@ -208,6 +434,31 @@ impl Styles {
     }
 
     fn duration_text(&self, dur: Duration, width: usize, prec: usize) -> FormattedDuration {
+        match self.duration_format {
+            DurationFormat::Human => {}
+            DurationFormat::Seconds => {
+                return FormattedDuration::Fixed(format!(
+                    "{:>width$.6}s",
+                    dur.as_secs_f64(),
+                    width = width.saturating_sub(1),
+                ))
+            }
+            DurationFormat::Milliseconds => {
+                return FormattedDuration::Fixed(format!(
+                    "{:>width$.3}ms",
+                    dur.as_secs_f64() * 1000.0,
+                    width = width.saturating_sub(2),
+                ))
+            }
+            DurationFormat::Nanos => {
+                return FormattedDuration::Fixed(format!(
+                    "{:>width$}ns",
+                    dur.as_nanos(),
+                    width = width.saturating_sub(2),
+                ))
+            }
+        }
+
         let secs = dur.as_secs();
 
         if secs >= 60 * 60 * 24 * 100 {
@@ -260,31 +511,60 @@ impl Styles {
         Style::default().add_modifier(Modifier::DIM)
     }
 
-    pub fn fg(&self, color: Color) -> Style {
-        if let Some(color) = self.color(color) {
-            Style::default().fg(color)
-        } else {
-            Style::default()
+    /// Style for table rows of tasks that were aborted before they completed.
+    pub fn cancelled(&self) -> Style {
+        let style = self.terminated();
+        match self.role_color(Role::Bad) {
+            Some(color) => style.fg(color),
+            None => style,
+        }
+    }
+
+    /// Style for table rows that changed in the last update.
+    /// The style of a watched task's row, which stands out once the task has
+    /// `ended`.
+    pub fn watched(&self, ended: bool) -> Style {
+        if !ended {
+            return Style::default().add_modifier(Modifier::UNDERLINED);
+        }
+        let style = Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED);
+        match self.role_color(Role::Caution) {
+            Some(color) => style.fg(color),
+            None => style,
+        }
+    }
+
+    pub fn panicked(&self) -> Style {
+        match self.role_color(Role::Bad) {
+            Some(color) => Style::default().fg(color).add_modifier(Modifier::BOLD),
+            None => Style::default().add_modifier(Modifier::BOLD),
+        }
+    }
+
+    pub fn changed(&self) -> Style {
+        match self.role_color(Role::Changed) {
+            Some(color) => Style::default().bg(color),
+            None => Style::default().add_modifier(Modifier::REVERSED),
         }
     }
 
     pub fn warning_wide(&self) -> Span<'static> {
         Span::styled(
             self.if_utf8("\u{26A0} ", "/!\\ "),
-            self.fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+            self.role(Role::Warning).add_modifier(Modifier::BOLD),
         )
     }
 
     pub fn warning_narrow(&self) -> Span<'static> {
         Span::styled(
             self.if_utf8("\u{26A0} ", "! "),
-            self.fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+            self.role(Role::Warning).add_modifier(Modifier::BOLD),
         )
     }
 
     pub fn selected(&self, value: &str) -> Span<'static> {
-        let style = if let Some(cyan) = self.color(Color::Cyan) {
-            Style::default().fg(cyan)
+        let style = if let Some(color) = self.role_color(Role::Header) {
+            Style::default().fg(color)
         } else {
             Style::default().remove_modifier(Modifier::REVERSED)
         };
@@ -332,6 +612,17 @@ impl Styles {
         }
     }
 
+    /// Returns the style of a task named `name`, if it matches a highlight
+    /// rule. If it matches more than one, the first rule is used.
+    pub(crate) fn highlight(&self, name: Option<&str>) -> Option<Style> {
+        let name = name?;
+        let rule = self
+            .highlights
+            .iter()
+            .find(|rule| glob_matches(&rule.name, name))?;
+        self.color(rule.color.0).map(fg_style)
+    }
+
     pub fn border_block(&self) -> ratatui::widgets::Block<'_> {
         if self.utf8 {
             ratatui::widgets::Block::default()
@@ -344,6 +635,56 @@ impl Styles {
     }
 }
 
+// === impl HighlightColor ===
+
+impl TryFrom<String> for HighlightColor {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+            .map(HighlightColor)
+            .map_err(|_| format!("invalid highlight color `{}`", s))
+    }
+}
+
+impl From<HighlightColor> for String {
+    fn from(color: HighlightColor) -> Self {
+        color.0.to_string()
+    }
+}
+
+/// Returns whether `text` matches the glob `pattern`, in which `*` matches
+/// any number of characters and `?` matches any single character.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was, and the position in `text` it matched up to,
+    // so that it can be retried matching one more character.
+    let mut star = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    star = Some((star_p, star_t + 1));
+                    p = star_p + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
 // === impl Palette ===
 
 impl FromStr for Palette {
@@ -362,8 +703,22 @@ impl FromStr for Palette {
     }
 }
 
-impl Default for Palette {
-    fn default() -> Self {
-        Self::NoColors
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_matches("db-*", "db-pool"));
+        assert!(glob_matches("db-*", "db-"));
+        assert!(!glob_matches("db-*", "cache-db-pool"));
+        assert!(glob_matches("*-worker-?", "io-worker-3"));
+        assert!(!glob_matches("*-worker-?", "io-worker-12"));
+        assert!(glob_matches("*conn*", "accept connection"));
+        assert!(glob_matches("a*b*c", "aXbYbZc"));
+        assert!(!glob_matches("a*b*c", "aXbYbZ"));
+        assert!(glob_matches("exact", "exact"));
+        assert!(!glob_matches("exact", "exactly"));
+        assert!(glob_matches("*", ""));
     }
 }
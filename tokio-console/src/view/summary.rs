@@ -0,0 +1,144 @@
+//! Plain text summaries of a task or resource, which `y` copies to the
+//! clipboard for pasting into bug reports and chat.
+
+use crate::state::{resources::Resource, tasks::Task, Attribute, Field};
+use std::{fmt::Write, time::SystemTime};
+
+/// Summarizes `task` as it was at `now`.
+pub(crate) fn task(task: &Task, now: SystemTime) -> String {
+    let mut summary = format!("Task {}", task.id_str());
+    if let Some(name) = task.name() {
+        let _ = write!(summary, " ({})", name);
+    }
+    let _ = write!(
+        summary,
+        "\nKind: {}\nTarget: {}\nLocation: {}\nState: {}",
+        task.kind(),
+        task.target(),
+        task.location(),
+        task.state().as_str(),
+    );
+    let _ = write!(
+        summary,
+        "\nTotal: {:.2?}, busy: {:.2?}, scheduled: {:.2?}, idle: {:.2?}",
+        task.total(now),
+        task.busy(now),
+        task.scheduled(now),
+        task.idle(now),
+    );
+    let _ = write!(
+        summary,
+        "\nPolls: {}, wakes: {}, self wakes: {}",
+        task.total_polls(),
+        task.wakes(),
+        task.self_wakes(),
+    );
+    if !task.fields().is_empty() {
+        let _ = write!(summary, "\nFields: {}", fields(task.fields()));
+    }
+    for warning in task.warnings() {
+        let _ = write!(summary, "\nWarning: {}", warning.format(task));
+    }
+    summary
+}
+
+/// Summarizes `resource` as it was at `now`.
+pub(crate) fn resource(resource: &Resource, now: SystemTime) -> String {
+    let mut summary = format!(
+        "Resource {} ({})\nKind: {}\nTarget: {}\nLocation: {}\nTotal: {:.2?}",
+        resource.id_str(),
+        resource.concrete_type(),
+        resource.kind(),
+        resource.target(),
+        resource.location(),
+        resource.total(now),
+    );
+    if resource.dropped() {
+        summary.push_str(" (dropped)");
+    }
+    if resource.parent_resource().is_some() {
+        let _ = write!(summary, "\nParent: {}", resource.parent());
+    }
+    if !resource.attributes().is_empty() {
+        let _ = write!(
+            summary,
+            "\nAttributes: {}",
+            attributes(resource.attributes())
+        );
+    }
+    let polls = resource.poll_counts();
+    let _ = write!(
+        summary,
+        "\nPolls: {}, ready: {}",
+        polls.polls(),
+        polls.ready()
+    );
+    summary
+}
+
+fn fields(fields: &[Field]) -> String {
+    fields
+        .iter()
+        .map(|field| format!("{}={}", field.name, field.value))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn attributes(attributes: &[Attribute]) -> String {
+    attributes
+        .iter()
+        .map(|attr| {
+            format!(
+                "{}={}{}",
+                attr.field.name,
+                attr.field.value,
+                attr.unit.as_deref().unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::demo::Demo;
+    use crate::state::State;
+    use std::time::Duration;
+
+    #[test]
+    fn summarizes_tasks_and_resources() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut demo = Demo::new(start);
+        let mut state = State::default();
+        for _ in 0..3 {
+            state.update(None, demo.next_update());
+        }
+        let now = state.last_updated_at().expect("an update was applied");
+
+        let task = state
+            .tasks_state_mut()
+            .take_new_tasks()
+            .find_map(|task| task.upgrade())
+            .expect("the demo spawns tasks");
+        let task = task.borrow();
+        let summary = super::task(&task, now);
+        assert!(summary.starts_with(&format!("Task {}", task.id_str())));
+        assert!(summary.contains(&format!("\nLocation: {}\n", task.location())));
+        assert!(summary.contains(&format!("\nPolls: {}, ", task.total_polls())));
+
+        let resource = state
+            .resources_state_mut()
+            .take_new_resources()
+            .find_map(|resource| resource.upgrade())
+            .expect("the demo creates resources");
+        let resource = resource.borrow();
+        let summary = super::resource(&resource, now);
+        assert!(summary.starts_with(&format!(
+            "Resource {} ({})",
+            resource.id_str(),
+            resource.concrete_type()
+        )));
+        assert!(summary.contains(&format!("\nKind: {}\n", resource.kind())));
+    }
+}
@@ -3,17 +3,26 @@ use crate::{
     view::{
         self,
         controls::{controls_paragraph, ControlDisplay, KeyDisplay},
-        help::HelpText,
+        help::{HelpText, Hint},
+        prefs::TablePrefs,
+        sort_menu::SortMenu,
     },
 };
 use ratatui::{
     layout,
-    widgets::{Paragraph, TableState},
+    widgets::{Paragraph, Table, TableState},
 };
-use std::convert::TryFrom;
+use std::{cmp, convert::TryFrom};
 
 use std::cell::RefCell;
 use std::rc::{Rc, Weak};
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    ops::Range,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 pub(crate) trait TableList<const N: usize> {
     type Row;
@@ -23,6 +32,30 @@ pub(crate) trait TableList<const N: usize> {
     const HEADER: &'static [&'static str; N];
     const WIDTHS: &'static [usize; N];
 
+    /// The widths below which the table hides its lower-priority columns.
+    ///
+    /// Every breakpoint wider than the table's area applies, so a narrower
+    /// table hides the columns of each wider breakpoint as well.
+    const BREAKPOINTS: &'static [Breakpoint] = &[];
+
+    /// The width below which the table's durations are always shown in the
+    /// shortest format, regardless of the selected [`DurationFormat`].
+    ///
+    /// [`DurationFormat`]: view::DurationFormat
+    const COMPACT_BELOW: u16 = 0;
+
+    /// Whether the table can be exported to a file, with `e` and `E`.
+    const EXPORTABLE: bool = false;
+
+    /// The controls for this table, shown along with the universal controls.
+    fn view_controls() -> &'static [ControlDisplay] {
+        view_controls()
+    }
+
+    /// Returns the plain text of each of `row`'s columns, in the same order as
+    /// [`Self::HEADER`], for exporting the table.
+    fn export_row(row: &Self::Row, now: SystemTime) -> [String; N];
+
     fn render(
         state: &mut TableListState<Self, N>,
         styles: &view::Styles,
@@ -34,16 +67,72 @@ pub(crate) trait TableList<const N: usize> {
         Self: Sized;
 }
 
+/// A width below which a table hides some of its columns.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Breakpoint {
+    /// The columns are hidden when the table is narrower than this.
+    pub(crate) below: u16,
+    /// The indices of the columns to hide.
+    pub(crate) hide: &'static [usize],
+}
+
+/// Which of a table's columns are shown at the width it's being rendered at.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Columns<const N: usize> {
+    shown: [bool; N],
+    compact: bool,
+}
+
 pub(crate) trait SortBy {
     fn as_column(&self) -> usize;
 }
 
+/// A secondary sort key, which orders the rows that the column a table is
+/// sorted by ranks equally.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) struct ThenBy {
+    /// The index of the column to sort by.
+    pub(crate) column: usize,
+    /// Whether this column is sorted in the opposite direction to the
+    /// default, like [`TableListState::sort_descending`].
+    pub(crate) descending: bool,
+}
+
+/// A file format a table can be exported in.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// The number of rows above and below the visible part of a table which are
+/// also formatted when rendering it.
+///
+/// The table is scrolled to keep the selected row visible before it's
+/// rendered, so this only needs to cover any difference between that and how
+/// `Table` decides to scroll.
+const RENDER_MARGIN: usize = 8;
+
 pub(crate) struct TableListState<T: TableList<N>, const N: usize> {
     pub(crate) sorted_items: Vec<Weak<RefCell<T::Row>>>,
+    /// Items that are hidden by the table's current filter, if it has one.
+    pub(crate) hidden_items: Vec<Weak<RefCell<T::Row>>>,
     pub(crate) sort_by: T::Sort,
     pub(crate) selected_column: usize,
     pub(crate) sort_descending: bool,
+    /// The column to sort rows by when they're equal in the sorted column.
+    pub(crate) then_by: Option<ThenBy>,
+    /// The sorting menu, while it's open.
+    sort_menu: Option<SortMenu>,
+    /// Whether rows that changed in the last update should be highlighted.
+    pub(crate) highlight_changes: bool,
     pub(crate) table_state: TableState,
+    /// The widest each column has been, so that columns don't shrink and grow
+    /// again as the table is scrolled.
+    column_widths: [u16; N],
+    /// The columns shown when the table was last rendered, which are the
+    /// columns it's exported with.
+    columns: Columns<N>,
 
     last_key_event: Option<input::KeyEvent>,
 }
@@ -69,6 +158,11 @@ impl<T: TableList<N>, const N: usize> TableListState<T, N> {
 
     pub(in crate::view) fn key_input(&mut self, event: input::KeyEvent) {
         use input::KeyCode::*;
+        if self.sort_menu.is_some() {
+            self.sort_menu_input(event);
+            return;
+        }
+
         let header_len = T::HEADER.len();
         let code = event.code;
         match code {
@@ -87,6 +181,10 @@ impl<T: TableList<N>, const N: usize> TableListState<T, N> {
                 }
             }
             Char('i') => self.sort_descending = !self.sort_descending,
+            Char('o') => {
+                self.sort_menu = Some(SortMenu::new(self.selected_column));
+            }
+            Char('d') => self.highlight_changes = !self.highlight_changes,
             Down | Char('j') => self.scroll_next(),
             Up | Char('k') => self.scroll_prev(),
             Char('G') => self.scroll_to_last(),
@@ -96,13 +194,116 @@ impl<T: TableList<N>, const N: usize> TableListState<T, N> {
             _ => {} // do nothing for now...
         }
 
-        if let Ok(sort_by) = T::Sort::try_from(self.selected_column) {
-            self.sort_by = sort_by;
-        }
+        self.set_sort_column(self.selected_column);
 
         self.last_key_event = Some(event);
     }
 
+    /// Handles a key pressed while the sorting menu is open.
+    fn sort_menu_input(&mut self, event: input::KeyEvent) {
+        use input::KeyCode::*;
+        let Some(menu) = self.sort_menu.as_mut() else {
+            return;
+        };
+        let sortable = |column: usize| T::Sort::try_from(column).is_ok();
+        let column = menu.cursor;
+        match event.code {
+            Esc | Char('o') | Char('q') => self.sort_menu = None,
+            Down | Char('j') => menu.move_cursor(T::HEADER.len(), sortable, true),
+            Up | Char('k') => menu.move_cursor(T::HEADER.len(), sortable, false),
+            Enter | Char('1') => {
+                if column == self.selected_column {
+                    self.sort_descending = !self.sort_descending;
+                } else {
+                    self.set_sort_column(column);
+                }
+            }
+            Char('2') if column != self.selected_column && sortable(column) => {
+                self.then_by = match self.then_by {
+                    Some(then_by) if then_by.column == column => Some(ThenBy {
+                        column,
+                        descending: !then_by.descending,
+                    }),
+                    _ => Some(ThenBy {
+                        column,
+                        descending: self.sort_descending,
+                    }),
+                };
+            }
+            Char('x') | Backspace | Delete => self.then_by = None,
+            _ => {}
+        }
+    }
+
+    /// Sorts the table by column `column`, if it can be sorted by.
+    ///
+    /// The secondary sort key is cleared if it was the same column.
+    fn set_sort_column(&mut self, column: usize) -> bool {
+        let Ok(sort_by) = T::Sort::try_from(column) else {
+            return false;
+        };
+        self.sort_by = sort_by;
+        self.selected_column = column;
+        if self.then_by.is_some_and(|then_by| then_by.column == column) {
+            self.then_by = None;
+        }
+        true
+    }
+
+    /// Returns whether the sorting menu is open, in which case it handles
+    /// every key.
+    pub(in crate::view) fn is_sort_menu_open(&self) -> bool {
+        self.sort_menu.is_some()
+    }
+
+    /// Sorts the table's rows using `sort` to sort them by a single column,
+    /// first by the secondary sort key, if there is one, and then by the
+    /// column the table is sorted by.
+    ///
+    /// `sort` must be a stable sort, so that rows which are equal in the
+    /// sorted column stay in the order of the secondary sort key.
+    pub(in crate::view) fn sort_items(
+        &mut self,
+        sort: impl Fn(&T::Sort, &mut [Weak<RefCell<T::Row>>]),
+    ) {
+        let then_by = self
+            .then_by
+            .and_then(|then_by| Some((T::Sort::try_from(then_by.column).ok()?, then_by)));
+        if let Some((then_sort_by, then_by)) = then_by {
+            sort(&then_sort_by, &mut self.sorted_items);
+            // The rows are displayed in reverse unless the table is sorted
+            // descending, so the secondary key is reversed here when its
+            // direction differs, so that it's displayed in the right order.
+            if then_by.descending != self.sort_descending {
+                self.sorted_items.reverse();
+            }
+        }
+        sort(&self.sort_by, &mut self.sorted_items);
+    }
+
+    /// Sorts the table by the column named `name`, ignoring case and spaces.
+    pub(in crate::view) fn sort_by_name(&mut self, name: &str) -> Result<(), String> {
+        let normalize = |name: &str| name.replace(' ', "").to_lowercase();
+        let wanted = normalize(name);
+        let column = T::HEADER
+            .iter()
+            .position(|header| normalize(header) == wanted)
+            .ok_or_else(|| {
+                format!(
+                    "no column named `{}` (columns: {})",
+                    name,
+                    T::HEADER.join(", ")
+                )
+            })?;
+        if !self.set_sort_column(column) {
+            return Err(format!(
+                "the {} column can't be sorted by",
+                T::HEADER[column]
+            ));
+        }
+        Ok(())
+    }
+
     pub(in crate::view) fn scroll_with(
         &mut self,
         f: impl Fn(&Vec<Weak<RefCell<T::Row>>>, usize) -> usize,
@@ -172,6 +373,215 @@ impl<T: TableList<N>, const N: usize> TableListState<T, N> {
             .and_then(|weak| weak.upgrade())
     }
 
+    /// Returns the width to start column `column` at, before updating it for
+    /// the rows being rendered.
+    pub(in crate::view) fn column_width(&self, column: usize) -> view::Width {
+        view::Width::new(cmp::max(
+            T::WIDTHS[column] as u16,
+            self.column_widths[column],
+        ))
+    }
+
+    /// Remembers how wide column `column` was when it was last rendered.
+    pub(in crate::view) fn remember_width(&mut self, column: usize, width: &view::Width) {
+        self.column_widths[column] = width.chars();
+    }
+
+    /// Returns this table's preferences, to be saved for the next session.
+    pub(crate) fn prefs(&self) -> TablePrefs {
+        let header = |column: usize| T::HEADER[column].to_string();
+        TablePrefs {
+            sort_by: Some(header(self.selected_column)),
+            sort_descending: self.sort_descending,
+            then_by: self.then_by.map(|then_by| header(then_by.column)),
+            then_by_descending: self.then_by.is_some_and(|then_by| then_by.descending),
+            widths: (0..N)
+                .map(|column| (header(column), self.column_widths[column]))
+                .collect(),
+        }
+    }
+
+    /// Restores preferences saved by a previous session.
+    pub(crate) fn restore(&mut self, prefs: &TablePrefs) {
+        let column = |header: &str| T::HEADER.iter().position(|&name| name == header);
+        let sort_column = prefs.sort_by.as_deref().and_then(column);
+        if let Some((column, sort_by)) =
+            sort_column.and_then(|column| Some((column, T::Sort::try_from(column).ok()?)))
+        {
+            self.sort_by = sort_by;
+            self.selected_column = column;
+            self.sort_descending = prefs.sort_descending;
+        }
+        self.then_by = prefs
+            .then_by
+            .as_deref()
+            .and_then(column)
+            .filter(|&column| column != self.selected_column)
+            .filter(|&column| T::Sort::try_from(column).is_ok())
+            .map(|column| ThenBy {
+                column,
+                descending: prefs.then_by_descending,
+            });
+        for (header, &saved) in &prefs.widths {
+            if let Some(column) = column(header) {
+                self.column_widths[column] = saved;
+            }
+        }
+    }
+
+    /// Scrolls the table so that the selected row is visible in an area with
+    /// room for `height` rows, and returns the range of rows, in the order in
+    /// which they are displayed, that should be formatted to render it.
+    ///
+    /// Only the visible rows and a small margin around them are included, so
+    /// that rendering a table with a very large number of rows stays fast.
+    pub(in crate::view) fn visible_rows(&mut self, height: usize) -> Range<usize> {
+        let len = self.sorted_items.len();
+        let mut offset = self.table_state.offset().min(len.saturating_sub(1));
+        if let Some(selected) = self.table_state.selected() {
+            let selected = selected.min(len.saturating_sub(1));
+            if selected < offset {
+                offset = selected;
+            } else if height > 0 && selected >= offset + height {
+                offset = selected + 1 - height;
+            }
+        }
+        *self.table_state.offset_mut() = offset;
+
+        let start = offset.saturating_sub(RENDER_MARGIN);
+        let end = (offset + height + RENDER_MARGIN).min(len);
+        start..end
+    }
+
+    /// Returns the rows in `window`, which is a range of rows in the order in
+    /// which they are displayed, in that order.
+    pub(in crate::view) fn window_items(
+        &self,
+        window: &Range<usize>,
+    ) -> Vec<&Weak<RefCell<T::Row>>> {
+        if self.sort_descending {
+            self.sorted_items[window.clone()].iter().collect()
+        } else {
+            let len = self.sorted_items.len();
+            self.sorted_items[len - window.end..len - window.start]
+                .iter()
+                .rev()
+                .collect()
+        }
+    }
+
+    /// Renders `table`, which contains only the rows in `window`, as if it
+    /// contained every row of the table.
+    pub(in crate::view) fn render_window(
+        &mut self,
+        frame: &mut ratatui::terminal::Frame,
+        area: layout::Rect,
+        table: Table<'_>,
+        window: &Range<usize>,
+    ) {
+        let mut window_state = TableState::default()
+            .with_offset(self.table_state.offset() - window.start)
+            .with_selected(
+                self.table_state
+                    .selected()
+                    .filter(|selected| window.contains(selected))
+                    .map(|selected| selected - window.start),
+            );
+        frame.render_stateful_widget(table, area, &mut window_state);
+        *self.table_state.offset_mut() = window_state.offset() + window.start;
+    }
+
+    /// Returns the rows currently shown in the table, in the order in which
+    /// they are displayed.
+    fn displayed_items(&self) -> Vec<Rc<RefCell<T::Row>>> {
+        let mut items = self
+            .sorted_items
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect::<Vec<_>>();
+        if !self.sort_descending {
+            items.reverse();
+        }
+        items
+    }
+
+    /// Serializes the rows currently shown in the table, sorted and filtered
+    /// as they are displayed, with only the columns that are shown.
+    pub(in crate::view) fn export(&self, format: ExportFormat, now: SystemTime) -> String {
+        let header = self.columns.select(T::HEADER.iter().copied());
+        let rows = self
+            .displayed_items()
+            .into_iter()
+            .map(|row| self.columns.select(T::export_row(&row.borrow(), now)));
+        match format {
+            ExportFormat::Csv => {
+                let mut out = csv_record(header.iter().copied());
+                for row in rows {
+                    out.push_str(&csv_record(row.iter().map(String::as_str)));
+                }
+                out
+            }
+            ExportFormat::Json => {
+                let rows = rows
+                    .map(|row| {
+                        header
+                            .iter()
+                            .map(|&column| column.to_owned())
+                            .zip(row.into_iter().map(serde_json::Value::String))
+                            .collect::<serde_json::Map<_, _>>()
+                    })
+                    .collect::<Vec<_>>();
+                let mut out = serde_json::to_string_pretty(&rows)
+                    .expect("serializing strings to JSON cannot fail");
+                out.push('\n');
+                out
+            }
+        }
+    }
+
+    /// Writes the rows currently shown in the table to a new file named after
+    /// `name` in the current directory, returning the file's path and the
+    /// number of rows written.
+    ///
+    /// An existing file is never overwritten: if the table is exported more
+    /// than once in the same millisecond, a suffix is added to the name.
+    pub(in crate::view) fn export_to_file(
+        &self,
+        name: &str,
+        format: ExportFormat,
+        now: SystemTime,
+    ) -> io::Result<(PathBuf, usize)> {
+        let extension = match format {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        };
+        let timestamp = now
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let contents = self.export(format, now);
+        for attempt in 0u32.. {
+            let path = if attempt == 0 {
+                PathBuf::from(format!("tokio-console-{name}-{timestamp}.{extension}"))
+            } else {
+                PathBuf::from(format!(
+                    "tokio-console-{name}-{timestamp}-{attempt}.{extension}"
+                ))
+            };
+            let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => file,
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(error) => return Err(error),
+            };
+            file.write_all(contents.as_bytes())?;
+            return Ok((path, self.displayed_items().len()));
+        }
+        Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "every export file name is taken",
+        ))
+    }
+
     pub(in crate::view) fn render(
         &mut self,
         styles: &view::Styles,
@@ -180,7 +590,46 @@ impl<T: TableList<N>, const N: usize> TableListState<T, N> {
         state: &mut state::State,
         ctx: T::Context,
     ) {
-        T::render(self, styles, frame, area, state, ctx)
+        self.columns = Columns::for_width::<T>(area.width);
+        T::render(self, styles, frame, area, state, ctx);
+        if let Some(menu) = &self.sort_menu {
+            menu.render(self, styles, frame);
+        }
+    }
+}
+
+// === impl Columns ===
+
+impl<const N: usize> Columns<N> {
+    /// Returns the columns of table `T` which are shown when it's rendered
+    /// in an area `width` characters wide.
+    pub(in crate::view) fn for_width<T: TableList<N>>(width: u16) -> Self {
+        let mut shown = [true; N];
+        for breakpoint in T::BREAKPOINTS.iter().filter(|b| width < b.below) {
+            for &column in breakpoint.hide {
+                shown[column] = false;
+            }
+        }
+        Self {
+            shown,
+            compact: width < T::COMPACT_BELOW,
+        }
+    }
+
+    /// Returns `true` if durations should be shown in the shortest format.
+    pub(in crate::view) fn compact(&self) -> bool {
+        self.compact
+    }
+
+    /// Keeps only the items for the shown columns, given one item for each of
+    /// the table's columns, such as its header cells, a row's cells, or the
+    /// column widths.
+    pub(in crate::view) fn select<I>(&self, items: impl IntoIterator<Item = I>) -> Vec<I> {
+        items
+            .into_iter()
+            .zip(self.shown)
+            .filter_map(|(item, shown)| shown.then_some(item))
+            .collect()
     }
 }
 
@@ -194,10 +643,16 @@ where
         let selected_column = sort_by.as_column();
         Self {
             sorted_items: Default::default(),
+            hidden_items: Default::default(),
             sort_by,
             table_state: Default::default(),
             selected_column,
             sort_descending: false,
+            then_by: None,
+            sort_menu: None,
+            highlight_changes: false,
+            column_widths: [0; N],
+            columns: Columns::for_width::<T>(u16::MAX),
             last_key_event: None,
         }
     }
@@ -208,10 +663,85 @@ where
     T: TableList<N>,
 {
     fn render_help_content(&self, styles: &view::Styles) -> Paragraph<'static> {
-        controls_paragraph(view_controls(), styles)
+        controls_paragraph(T::view_controls(), styles)
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        if self.sort_menu.is_some() {
+            return SortMenu::hints();
+        }
+        let has_rows = !self.sorted_items.is_empty();
+        let view_details = if has_rows {
+            Hint::when(
+                self.selected_item().is_some(),
+                "enter",
+                "view details",
+                "select a row with j/k first",
+            )
+        } else {
+            Hint::disabled("enter", "view details", "nothing to show yet")
+        };
+        let mut hints = vec![
+            view_details,
+            Hint::enabled("i", "invert sort"),
+            Hint::enabled("o", "sort menu"),
+            Hint::enabled(
+                "d",
+                if self.highlight_changes {
+                    "stop highlighting changes"
+                } else {
+                    "highlight changes"
+                },
+            ),
+        ];
+        if T::EXPORTABLE {
+            hints.push(Hint::when(has_rows, "e", "export", "nothing to export yet"));
+        }
+        hints
     }
 }
 
+/// Returns the text of a task's fields, without styling.
+pub(crate) fn fields_text(fields: &[state::Field]) -> String {
+    fields
+        .iter()
+        .map(|field| format!("{}={}", field.name, field.value))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Returns the text of a resource or async op's attributes, without styling.
+pub(crate) fn attributes_text(attributes: &[state::Attribute]) -> String {
+    attributes
+        .iter()
+        .map(|attr| {
+            format!(
+                "{}={}{}",
+                attr.field.name,
+                attr.field.value,
+                attr.unit.as_deref().unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Formats a single CSV record, quoting any values that need it.
+fn csv_record<'a>(values: impl Iterator<Item = &'a str>) -> String {
+    let mut record = values
+        .map(|value| {
+            if value.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", value.replace('"', "\"\""))
+            } else {
+                value.to_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    record.push_str("\r\n");
+    record
+}
+
 pub(crate) const fn view_controls() -> &'static [ControlDisplay] {
     &[
         ControlDisplay {
@@ -254,6 +784,20 @@ pub(crate) const fn view_controls() -> &'static [ControlDisplay] {
                 utf8: None,
             }],
         },
+        ControlDisplay {
+            action: "sort by several columns",
+            keys: &[KeyDisplay {
+                base: "o",
+                utf8: None,
+            }],
+        },
+        ControlDisplay {
+            action: "highlight changes",
+            keys: &[KeyDisplay {
+                base: "d",
+                utf8: None,
+            }],
+        },
         ControlDisplay {
             action: "scroll to top",
             keys: &[KeyDisplay {
@@ -270,3 +814,286 @@ pub(crate) const fn view_controls() -> &'static [ControlDisplay] {
         },
     ]
 }
+
+/// The controls for exporting a table, shown by the tables which are
+/// [`TableList::EXPORTABLE`].
+pub(crate) const fn export_controls() -> &'static [ControlDisplay] {
+    &[ControlDisplay {
+        action: "export (csv/json)",
+        keys: &[
+            KeyDisplay {
+                base: "e",
+                utf8: None,
+            },
+            KeyDisplay {
+                base: "E",
+                utf8: None,
+            },
+        ],
+    }]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{backend::TestBackend, widgets::Row, Terminal};
+
+    #[test]
+    fn visible_rows_follow_selection() {
+        let mut state = TableListState::<view::tasks::TasksTable, 23> {
+            sorted_items: (0..1000).map(|_| Weak::new()).collect(),
+            ..Default::default()
+        };
+
+        state.table_state.select(Some(500));
+        assert_eq!(state.visible_rows(20), 473..509);
+        assert_eq!(state.table_state.offset(), 481);
+        assert_eq!(state.window_items(&(473..509)).len(), 36);
+
+        state.table_state.select(Some(0));
+        assert_eq!(state.visible_rows(20), 0..28);
+        assert_eq!(state.table_state.offset(), 0);
+    }
+
+    #[test]
+    fn columns_hide_below_breakpoints() {
+        let all = Columns::<23>::for_width::<view::tasks::TasksTable>(200);
+        assert_eq!(all.select(0..23).len(), 23);
+        assert!(!all.compact());
+
+        let narrow = Columns::<23>::for_width::<view::tasks::TasksTable>(70);
+        let shown = narrow.select(0..23);
+        assert!(narrow.compact());
+        // Every breakpoint wider than the table applies.
+        for breakpoint in view::tasks::TasksTable::BREAKPOINTS {
+            for column in breakpoint.hide {
+                assert!(!shown.contains(column), "column {column} should be hidden");
+            }
+        }
+        // The ID and name are always shown.
+        assert!(shown.contains(&2));
+        assert!(shown.contains(&4));
+    }
+
+    /// A table of pairs, which can be sorted by either element.
+    struct PairsTable;
+
+    #[derive(Default)]
+    struct PairsSort(usize);
+
+    impl SortBy for PairsSort {
+        fn as_column(&self) -> usize {
+            self.0
+        }
+    }
+
+    impl TryFrom<usize> for PairsSort {
+        type Error = ();
+        fn try_from(column: usize) -> Result<Self, ()> {
+            (column < 2).then_some(Self(column)).ok_or(())
+        }
+    }
+
+    impl TableList<3> for PairsTable {
+        type Row = (u32, u32);
+        type Sort = PairsSort;
+        type Context = ();
+
+        const HEADER: &'static [&'static str; 3] = &["First", "Second", "Label"];
+        const WIDTHS: &'static [usize; 3] = &[5, 6, 5];
+        const BREAKPOINTS: &'static [Breakpoint] = &[Breakpoint {
+            below: 20,
+            hide: &[2],
+        }];
+
+        fn export_row(row: &Self::Row, _: SystemTime) -> [String; 3] {
+            [row.0.to_string(), row.1.to_string(), String::new()]
+        }
+
+        fn render(
+            table_list_state: &mut TableListState<Self, 3>,
+            styles: &view::Styles,
+            frame: &mut ratatui::terminal::Frame,
+            area: layout::Rect,
+            _: &mut state::State,
+            _: Self::Context,
+        ) {
+            let columns = Columns::for_width::<Self>(area.width);
+            // The table's borders and header take up three lines.
+            let window = table_list_state.visible_rows(area.height.saturating_sub(3) as usize);
+            let rows = table_list_state
+                .window_items(&window)
+                .into_iter()
+                .filter_map(|row| {
+                    let row = *row.upgrade()?.borrow();
+                    Some(Row::new(
+                        columns.select(Self::export_row(&row, SystemTime::UNIX_EPOCH)),
+                    ))
+                });
+            let widths =
+                columns.select(Self::WIDTHS.map(|width| layout::Constraint::Length(width as u16)));
+            let table = Table::default()
+                .rows(rows)
+                .header(Row::new(columns.select(*Self::HEADER)))
+                .block(styles.border_block())
+                .widths(widths);
+            table_list_state.render_window(frame, area, table, &window);
+        }
+    }
+
+    fn key(code: input::KeyCode) -> input::KeyEvent {
+        input::KeyEvent::new(code, input::KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn sorts_by_then_by_column() {
+        let rows = [(1, 2), (0, 5), (1, 1), (0, 3), (1, 4)]
+            .into_iter()
+            .map(|row| Rc::new(RefCell::new(row)))
+            .collect::<Vec<_>>();
+        let mut state = TableListState::<PairsTable, 3> {
+            sorted_items: rows.iter().map(Rc::downgrade).collect(),
+            sort_descending: true,
+            ..Default::default()
+        };
+        let sort = |sort_by: &PairsSort, items: &mut [Weak<RefCell<(u32, u32)>>]| {
+            items.sort_by_key(|item| {
+                let item = item.upgrade().unwrap();
+                let item = item.borrow();
+                if sort_by.0 == 0 {
+                    item.0
+                } else {
+                    item.1
+                }
+            })
+        };
+        let displayed = |state: &TableListState<PairsTable, 3>| {
+            state
+                .displayed_items()
+                .iter()
+                .map(|item| *item.borrow())
+                .collect::<Vec<_>>()
+        };
+
+        // Sort by the second column within the first, using the menu.
+        state.key_input(key(input::KeyCode::Char('o')));
+        assert!(state.is_sort_menu_open());
+        state.key_input(key(input::KeyCode::Char('j')));
+        state.key_input(key(input::KeyCode::Char('2')));
+        state.key_input(key(input::KeyCode::Esc));
+        assert!(!state.is_sort_menu_open());
+        assert_eq!(
+            state.then_by,
+            Some(ThenBy {
+                column: 1,
+                descending: true
+            })
+        );
+        state.sort_items(sort);
+        assert_eq!(displayed(&state), [(0, 3), (0, 5), (1, 1), (1, 2), (1, 4)]);
+
+        // Inverting the primary column keeps the secondary column's direction.
+        state.key_input(key(input::KeyCode::Char('i')));
+        state.sort_items(sort);
+        assert_eq!(displayed(&state), [(1, 1), (1, 2), (1, 4), (0, 3), (0, 5)]);
+
+        // Sorting by the secondary column makes it the only sort key.
+        state.key_input(key(input::KeyCode::Char('l')));
+        assert_eq!(state.then_by, None);
+        let prefs = state.prefs();
+        assert_eq!(prefs.sort_by.as_deref(), Some("Second"));
+        assert_eq!(prefs.then_by, None);
+    }
+
+    #[test]
+    fn restores_prefs_by_column_header() {
+        let mut state = TableListState::<PairsTable, 3>::default();
+        state.restore(&TablePrefs {
+            sort_by: Some("Second".to_string()),
+            sort_descending: true,
+            then_by: Some("First".to_string()),
+            then_by_descending: false,
+            widths: [("Label".to_string(), 12), ("Removed".to_string(), 30)].into(),
+        });
+        assert_eq!(state.selected_column, 1);
+        assert_eq!(state.sort_by.as_column(), 1);
+        assert!(state.sort_descending);
+        assert_eq!(
+            state.then_by,
+            Some(ThenBy {
+                column: 0,
+                descending: false
+            })
+        );
+        assert_eq!(state.column_widths, [0, 0, 12]);
+
+        // A column which no longer exists leaves the sort as it was.
+        let sorted_by = state.prefs();
+        state.restore(&TablePrefs {
+            sort_by: Some("Removed".to_string()),
+            ..sorted_by.clone()
+        });
+        assert_eq!(state.prefs(), sorted_by);
+    }
+
+    #[test]
+    fn exports_only_shown_columns() {
+        let rows = [(1, 2), (3, 4)]
+            .into_iter()
+            .map(|row| Rc::new(RefCell::new(row)))
+            .collect::<Vec<_>>();
+        let mut state = TableListState::<PairsTable, 3> {
+            sorted_items: rows.iter().map(Rc::downgrade).collect(),
+            sort_descending: true,
+            ..Default::default()
+        };
+        let now = SystemTime::now();
+        assert_eq!(
+            state.export(ExportFormat::Csv, now),
+            "First,Second,Label\r\n1,2,\r\n3,4,\r\n"
+        );
+
+        // Columns hidden when the table was last rendered are left out.
+        let styles = view::Styles::from_config(Default::default());
+        let mut terminal = Terminal::new(TestBackend::new(16, 6)).unwrap();
+        let buffer = terminal
+            .draw(|frame| {
+                let area = frame.size();
+                state.render(&styles, frame, area, &mut state::State::default(), ());
+            })
+            .unwrap()
+            .buffer
+            .clone();
+        let shown = (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer.get(x, y).symbol())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        assert!(shown.iter().any(|line| line.contains("First")));
+        assert!(!shown.iter().any(|line| line.contains("Label")));
+        assert_eq!(
+            state.export(ExportFormat::Csv, now),
+            "First,Second\r\n1,2\r\n3,4\r\n"
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&state.export(ExportFormat::Json, now)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([
+                { "First": "1", "Second": "2" },
+                { "First": "3", "Second": "4" },
+            ])
+        );
+    }
+
+    #[test]
+    fn csv_record_quotes_special_characters() {
+        let record = csv_record(["plain", "a, b", "say \"hi\"", "two\nlines", ""].into_iter());
+        assert_eq!(
+            record,
+            "plain,\"a, b\",\"say \"\"hi\"\"\",\"two\nlines\",\r\n"
+        );
+    }
+}
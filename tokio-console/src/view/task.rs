@@ -1,46 +1,96 @@
 use crate::{
     input,
-    state::{tasks::Task, DetailsRef},
+    source::{Preview, Sources},
+    state::{tasks::Task, DetailsRef, State},
     util::Percentage,
     view::{
         self, bold,
         controls::{controls_paragraph, ControlDisplay, Controls, KeyDisplay},
         durations::Durations,
-        help::HelpText,
+        help::{HelpText, Hint},
+        mini_histogram::HistogramSettings,
+        Role, UpdateKind,
     },
 };
+use console_api::instrument::HistogramSelection;
 use ratatui::{
     layout::{self, Layout},
+    symbols,
     text::{Line, Span, Text},
-    widgets::{List, ListItem, Paragraph},
-};
-use std::{
-    cell::RefCell,
-    cmp,
-    rc::Rc,
-    time::{Duration, SystemTime},
+    widgets::{List, ListItem, Paragraph, Sparkline, Wrap},
 };
+use std::{cell::RefCell, cmp, rc::Rc, time::Duration};
 
 pub(crate) struct TaskView {
     task: Rc<RefCell<Task>>,
     details: DetailsRef,
+    /// How the duration histograms are drawn.
+    histogram: HistogramSettings,
+    /// Which histogram the tabbed histogram pane shows.
+    histogram_tab: HistogramTab,
+    /// Whether the source around the task's spawn location is shown.
+    show_source: bool,
+}
+
+/// The histograms that can be shown in the task view's tabbed pane, below the
+/// histogram of all of the task's poll times.
+///
+/// Only the histograms being shown are requested from the target, so the
+/// scheduled times histogram isn't sent until its tab is selected.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HistogramTab {
+    #[default]
+    RecentPolls,
+    Scheduled,
 }
 
 impl TaskView {
     pub(super) fn new(task: Rc<RefCell<Task>>, details: DetailsRef) -> Self {
-        TaskView { task, details }
+        TaskView {
+            task,
+            details,
+            histogram: HistogramSettings::default(),
+            histogram_tab: HistogramTab::default(),
+            show_source: false,
+        }
     }
 
-    pub(crate) fn update_input(&mut self, _event: input::Event) {
-        // TODO :D
+    pub(crate) fn task(&self) -> &Rc<RefCell<Task>> {
+        &self.task
+    }
+
+    /// Switches the tabbed histogram pane to the next histogram, returning
+    /// the update which watches the task's details with the histograms it
+    /// now shows.
+    pub(crate) fn next_histogram_tab(&mut self) -> UpdateKind {
+        self.histogram_tab = self.histogram_tab.next();
+        UpdateKind::SelectTask(
+            self.task.borrow().span_id(),
+            self.histogram_tab.histograms(),
+        )
+    }
+
+    pub(crate) fn update_input(&mut self, event: input::Event) {
+        let input::Event::Key(event) = event else {
+            return;
+        };
+        match event.code {
+            input::KeyCode::Char('l') => self.histogram.toggle_scale(),
+            input::KeyCode::Char('+' | '=') => self.histogram.more_buckets(),
+            input::KeyCode::Char('-') => self.histogram.fewer_buckets(),
+            input::KeyCode::Char('z') => self.histogram.next_zoom(),
+            input::KeyCode::Char('v') => self.show_source = !self.show_source,
+            _ => {}
+        }
     }
 
     pub(crate) fn render(
         &mut self,
         styles: &view::Styles,
+        sources: &mut Sources,
         frame: &mut ratatui::terminal::Frame,
         area: layout::Rect,
-        now: SystemTime,
+        state: &mut State,
     ) {
         // Rows with the following info:
         // - Task main attributes
@@ -48,7 +98,11 @@ impl TaskView {
         // - metrics
         // - logs?
 
+        let now = state
+            .last_updated_at()
+            .expect("task view implies we've received an update");
         let task = &*self.task.borrow();
+        let location_overhead = state.tasks_state().location_overhead(task.location());
         let details_ref = self.details.borrow();
         let details = details_ref
             .as_ref()
@@ -68,82 +122,133 @@ impl TaskView {
             })
             .collect();
 
-        let (
-            controls_area,
-            stats_area,
-            poll_dur_area,
-            scheduled_dur_area,
-            fields_area,
-            warnings_area,
-        ) = if warnings.is_empty() {
-            let chunks = Layout::default()
-                .direction(layout::Direction::Vertical)
-                .constraints(
-                    [
-                        // controls
-                        layout::Constraint::Length(controls.height()),
-                        // task stats
-                        layout::Constraint::Length(10),
-                        // poll duration
-                        layout::Constraint::Length(9),
-                        // scheduled duration
-                        layout::Constraint::Length(9),
-                        // fields
-                        layout::Constraint::Percentage(60),
-                    ]
-                    .as_ref(),
-                )
-                .split(area);
-            (chunks[0], chunks[1], chunks[2], chunks[3], chunks[4], None)
+        let thread_polls = details.map(|d| d.thread_polls()).unwrap_or_default();
+        let shown_threads = cmp::min(thread_polls.len(), TOP_POLL_THREADS);
+        // The task stats box always shows the ID, target, location, and time
+        // totals, plus any optional lines, the threads it was polled on under
+        // a heading, and the top and bottom borders.
+        let stats_height = 7
+            + task.name().is_some() as u16
+            + task.runtime().is_some() as u16
+            + task.current_span().is_some() as u16
+            + task.busy_by_outcome().is_some() as u16
+            + task.overhead().is_some() as u16
+            + task.is_blocking_pool() as u16
+            + if thread_polls.is_empty() {
+                0
+            } else {
+                shown_threads as u16 + 1 + (thread_polls.len() > shown_threads) as u16
+            }
+            + 2;
+        let wake_sites = details.map(|d| d.wake_sites()).unwrap_or_default();
+        let waiting_on = details
+            .map(|d| d.waiting_on())
+            .unwrap_or_default()
+            .iter()
+            .map(|waiting| {
+                let resource = state
+                    .resources_state()
+                    .resource_by_span(waiting.resource_span_id)
+                    .map(|resource| {
+                        let resource = resource.borrow();
+                        format!(
+                            "{} {} (resource {})",
+                            resource.kind(),
+                            resource.concrete_type(),
+                            resource.id_str()
+                        )
+                    })
+                    .unwrap_or_else(|| "unknown resource".to_owned());
+                let mut line = vec![
+                    Span::raw(format!("  {} ", resource)),
+                    Span::from(format!("in {}", waiting.op_name)),
+                ];
+                if let Some(waiting_for) = waiting
+                    .since
+                    .and_then(|since| now.duration_since(since).ok())
+                {
+                    line.push(Span::raw(" for "));
+                    line.push(styles.time_units(waiting_for, view::DUR_LIST_PRECISION, None));
+                }
+                Line::from(line)
+            })
+            .collect::<Vec<_>>();
+        // The waker box shows the current wakers and wakeups, self wakes if
+        // there are any, and the wake sites and what the task is waiting on
+        // under headings, plus the top and bottom borders.
+        let waker_height = 2
+            + (task.self_wakes() > 0) as u16
+            + if wake_sites.is_empty() {
+                0
+            } else {
+                wake_sites.len() as u16 + 1
+            }
+            + if waiting_on.is_empty() {
+                0
+            } else {
+                waiting_on.len() as u16 + 1
+            }
+            + 2;
+        let stacked = area.width < STACK_PANES_BELOW;
+        let stats_area_height = if stacked {
+            stats_height + waker_height
         } else {
-            let chunks = Layout::default()
-                .direction(layout::Direction::Vertical)
-                .constraints(
-                    [
-                        // controls
-                        layout::Constraint::Length(controls.height()),
-                        // warnings (add 2 for top and bottom borders)
-                        layout::Constraint::Length(warnings.len() as u16 + 2),
-                        // task stats
-                        layout::Constraint::Length(10),
-                        // poll duration
-                        layout::Constraint::Length(9),
-                        // scheduled duration
-                        layout::Constraint::Length(9),
-                        // fields
-                        layout::Constraint::Percentage(60),
-                    ]
-                    .as_ref(),
-                )
-                .split(area);
-
-            (
-                chunks[0],
-                chunks[2],
-                chunks[3],
-                chunks[4],
-                chunks[5],
-                Some(chunks[1]),
-            )
+            cmp::max(stats_height, waker_height)
         };
 
-        let stats_area = Layout::default()
-            .direction(layout::Direction::Horizontal)
-            .constraints(
-                [
-                    layout::Constraint::Percentage(50),
-                    layout::Constraint::Percentage(50),
-                ]
-                .as_ref(),
-            )
-            .split(stats_area);
+        let mut constraints = vec![
+            // controls
+            layout::Constraint::Length(controls.height()),
+        ];
+        if !warnings.is_empty() {
+            // warnings (add 2 for top and bottom borders)
+            constraints.push(layout::Constraint::Length(warnings.len() as u16 + 2));
+        }
+        // task stats
+        constraints.push(layout::Constraint::Length(stats_area_height));
+        if self.show_source {
+            // source preview, plus borders
+            constraints.push(layout::Constraint::Length(SOURCE_CONTEXT as u16 * 2 + 3));
+        }
+        let backtrace = task.spawn_backtrace();
+        if !backtrace.is_empty() {
+            // spawn backtrace, plus borders
+            constraints.push(layout::Constraint::Length(backtrace.len() as u16 + 2));
+        }
+        constraints.extend([
+            // wake history (3 lines, plus borders)
+            layout::Constraint::Length(5),
+            // poll duration
+            layout::Constraint::Length(9),
+            // recent poll or scheduled duration
+            layout::Constraint::Length(9),
+            // fields
+            layout::Constraint::Percentage(60),
+        ]);
+        let chunks = Layout::default()
+            .direction(layout::Direction::Vertical)
+            .constraints(constraints)
+            .split(area);
+        let mut chunks = chunks.iter().copied();
+        let mut next_area = || chunks.next().expect("a layout chunk for each constraint");
+        let controls_area = next_area();
+        let warnings_area = (!warnings.is_empty()).then(&mut next_area);
+        let stats_area = next_area();
+        let source_area = self.show_source.then(&mut next_area);
+        let backtrace_area = (!backtrace.is_empty()).then(&mut next_area);
+        let wake_history_area = next_area();
+        let poll_dur_area = next_area();
+        let tabbed_dur_area = next_area();
+        let fields_area = next_area();
+
+        let stats_area = view::split_panes(stats_area, stacked, stats_height);
 
         // Just preallocate capacity for ID, name, target, total, busy, and idle.
         let mut overview = Vec::with_capacity(8);
         overview.push(Line::from(vec![
             bold("ID: "),
             Span::raw(format!("{} ", task.id_str())),
-            task.state().render(styles),
+            view::tasks::render_state(task.state(), styles),
         ]));
 
         if let Some(name) = task.name() {
@@ -152,6 +257,10 @@ impl TaskView {
 
         overview.push(Line::from(vec![bold("Target: "), Span::raw(task.target())]));
 
+        if let Some(runtime) = task.runtime() {
+            overview.push(Line::from(vec![bold("Runtime: "), Span::raw(runtime)]));
+        }
+
         let title = "Location: ";
         let location_max_width = stats_area[0].width as usize - 2 - title.len(); // NOTE: -2 for the border
         let location = if task.location().len() > location_max_width {
@@ -164,6 +273,10 @@ impl TaskView {
 
         overview.push(Line::from(vec![bold(title), Span::raw(location)]));
 
+        if let Some(span) = task.current_span() {
+            overview.push(Line::from(vec![bold("Currently in: "), Span::raw(span)]));
+        }
+
         let total = task.total(now);
 
         let dur_percent = |name: &'static str, amt: Duration| -> Line {
@@ -180,8 +293,64 @@ impl TaskView {
             styles.time_units(total, view::DUR_LIST_PRECISION, None),
         ]));
         overview.push(dur_percent("Busy: ", task.busy(now)));
+        if let Some((ready, pending)) = task.busy_by_outcome() {
+            let percent = pending
+                .as_secs_f64()
+                .percent_of((ready + pending).as_secs_f64());
+            overview.push(Line::from(vec![
+                Span::raw("  "),
+                bold("ready: "),
+                styles.time_units(ready, view::DUR_LIST_PRECISION, None),
+                Span::raw(", "),
+                bold("pending: "),
+                styles.time_units(pending, view::DUR_LIST_PRECISION, None),
+                Span::from(format!(" ({:.2}%)", percent)),
+            ]));
+        }
+        if let Some(queued) = task.queued(now) {
+            overview.push(dur_percent("Queued: ", queued));
+        }
         overview.push(dur_percent("Scheduled: ", task.scheduled(now)));
         overview.push(dur_percent("Idle: ", task.idle(now)));
+        if let Some(overhead) = task.overhead() {
+            let mut line = vec![
+                bold("Overhead (est.): "),
+                styles.time_units(overhead, view::DUR_LIST_PRECISION, None),
+            ];
+            if let Some(location_overhead) = location_overhead {
+                line.push(Span::raw(", "));
+                line.push(styles.time_units(location_overhead, view::DUR_LIST_PRECISION, None));
+                line.push(Span::raw(" for this location"));
+            }
+            overview.push(Line::from(line));
+        }
+
+        if !thread_polls.is_empty() {
+            let polls = thread_polls.iter().map(|&(_, polls)| polls).sum::<u64>();
+            let thread_line = |name: String, thread_polls: u64| {
+                let percent = (thread_polls as f64).percent_of(polls as f64);
+                Line::from(vec![
+                    Span::raw(format!("  {}: ", name)),
+                    Span::from(format!("{} polls ({:.2}%)", thread_polls, percent)),
+                ])
+            };
+            overview.push(Line::from(vec![
+                bold("Polled on: "),
+                Span::from(format!("{} threads", thread_polls.len())),
+            ]));
+            overview.extend(
+                thread_polls[..shown_threads]
+                    .iter()
+                    .map(|(name, polls)| thread_line(name.clone(), *polls)),
+            );
+            let others = &thread_polls[shown_threads..];
+            if !others.is_empty() {
+                overview.push(thread_line(
+                    format!("{} others", others.len()),
+                    others.iter().map(|&(_, polls)| polls).sum(),
+                ));
+            }
+        }
 
         let mut waker_stats = vec![Line::from(vec![
             bold("Current wakers: "),
@@ -219,8 +388,61 @@ impl TaskView {
             ]));
         }
 
+        if !wake_sites.is_empty() {
+            waker_stats.push(Line::from(bold("Woken from:")));
+            waker_stats.extend(wake_sites.iter().map(|(location, wakes)| {
+                Line::from(vec![
+                    Span::raw(format!("  {} ", location)),
+                    Span::from(format!("({} times)", wakes)),
+                ])
+            }));
+        }
+
+        if !waiting_on.is_empty() {
+            waker_stats.push(Line::from(bold("Waiting on:")));
+            waker_stats.extend(waiting_on);
+        }
+
+        let wake_history = details.map(|d| d.wake_history()).unwrap_or_default();
+        let (wakes, self_wakes) = wake_history
+            .iter()
+            .fold((0, 0), |(wakes, self_wakes), &(w, s)| {
+                (wakes + w, self_wakes + s)
+            });
+        let wake_history_block = styles.border_block().title(Line::from(vec![
+            bold("Self Wakes per Update"),
+            Span::raw(format!(
+                " ({} of {} wakes in the last {} updates)",
+                self_wakes,
+                wakes,
+                wake_history.len()
+            )),
+        ]));
+        let wake_history_inner = wake_history_block.inner(wake_history_area);
+        let shown = &wake_history[wake_history
+            .len()
+            .saturating_sub(wake_history_inner.width as usize)..];
+        let self_wake_counts = shown
+            .iter()
+            .map(|&(_, self_wakes)| self_wakes)
+            .collect::<Vec<_>>();
+        let bar_set = if styles.utf8 {
+            symbols::bar::NINE_LEVELS
+        } else {
+            view::tasks::ASCII_BARS
+        };
+        let wake_history_widget = Sparkline::default()
+            .data(&self_wake_counts)
+            .bar_set(bar_set)
+            .style(styles.role(Role::Histogram))
+            .block(wake_history_block);
+
         let mut fields = Text::default();
-        fields.extend(task.formatted_fields().iter().cloned().map(Line::from));
+        fields.extend(
+            view::fields(styles, task.fields())
+                .into_iter()
+                .map(Line::from),
+        );
 
         if let Some(warnings_area) = warnings_area {
             let warnings = List::new(warnings).block(styles.border_block().title("Warnings"));
@@ -241,36 +463,170 @@ impl TaskView {
             .histogram(details.and_then(|d| d.poll_times_histogram()))
             .percentiles_title(poll_percentiles_title)
             .histogram_title("Poll Times Histogram")
-            .percentiles_width(percentiles_width);
-        let scheduled_durations_widget = Durations::new(styles)
-            .histogram(details.and_then(|d| d.scheduled_times_histogram()))
-            .percentiles_title(scheduled_percentiles_title)
-            .histogram_title("Scheduled Times Histogram")
-            .percentiles_width(percentiles_width);
+            .percentiles_width(percentiles_width)
+            .settings(self.histogram);
+        let tabbed_durations_widget = match self.histogram_tab {
+            HistogramTab::RecentPolls => Durations::new(styles)
+                .histogram(details.and_then(|d| d.recent_poll_times_histogram()))
+                .percentiles_title("Recent Poll Times")
+                .histogram_title("Poll Times Since Last Update (tab: scheduled)"),
+            HistogramTab::Scheduled => Durations::new(styles)
+                .histogram(details.and_then(|d| d.scheduled_times_histogram()))
+                .percentiles_title(scheduled_percentiles_title)
+                .histogram_title("Scheduled Times Histogram (tab: recent polls)"),
+        }
+        .percentiles_width(percentiles_width)
+        .settings(self.histogram);
 
         let fields_widget = Paragraph::new(fields).block(styles.border_block().title("Fields"));
 
         frame.render_widget(controls.into_widget(), controls_area);
         frame.render_widget(task_widget, stats_area[0]);
         frame.render_widget(wakers_widget, stats_area[1]);
+        frame.render_widget(wake_history_widget, wake_history_area);
         frame.render_widget(poll_durations_widget, poll_dur_area);
-        frame.render_widget(scheduled_durations_widget, scheduled_dur_area);
+        frame.render_widget(tabbed_durations_widget, tabbed_dur_area);
         frame.render_widget(fields_widget, fields_area);
+
+        if let Some(backtrace_area) = backtrace_area {
+            let frames = backtrace
+                .iter()
+                .map(|frame| Line::from(frame.as_str()))
+                .collect::<Vec<_>>();
+            let backtrace_widget =
+                Paragraph::new(frames).block(styles.border_block().title("Spawned From"));
+            frame.render_widget(backtrace_widget, backtrace_area);
+        }
+
+        if let Some(source_area) = source_area {
+            let preview = task
+                .source_location()
+                .and_then(|location| sources.preview(location, SOURCE_CONTEXT));
+            frame.render_widget(source_widget(styles, preview), source_area);
+        }
     }
 }
 
+impl HistogramTab {
+    fn next(self) -> Self {
+        match self {
+            Self::RecentPolls => Self::Scheduled,
+            Self::Scheduled => Self::RecentPolls,
+        }
+    }
+
+    /// Returns the histograms to request from the target while this tab is
+    /// shown.
+    pub(crate) fn histograms(self) -> HistogramSelection {
+        HistogramSelection {
+            poll_times: true,
+            scheduled_times: self == Self::Scheduled,
+            recent_poll_times: self == Self::RecentPolls,
+        }
+    }
+}
+
+/// Returns the source preview panel, or an explanation of why there is no
+/// preview.
+fn source_widget(styles: &view::Styles, preview: Option<Preview>) -> Paragraph<'_> {
+    let Some(preview) = preview else {
+        return Paragraph::new(vec![
+            Line::from("The source file for this task's spawn location wasn't found."),
+            Line::from(vec![
+                Span::raw("If the target was built elsewhere, map its paths to local ones in the "),
+                bold("[source_remap]"),
+                Span::raw(" table of the config file."),
+            ]),
+        ])
+        .wrap(Wrap { trim: true })
+        .block(styles.border_block().title("Source"));
+    };
+    let last_line = preview.first_line as usize + preview.lines.len() - 1;
+    let number_width = last_line.to_string().len();
+    let lines = preview
+        .lines
+        .into_iter()
+        .zip(preview.first_line..)
+        .map(|(text, number)| {
+            let number_span = Span::styled(
+                format!("{:>width$} ", number, width = number_width),
+                styles.role(Role::Key),
+            );
+            // Tabs would be drawn as a single cell.
+            let text = text.replace('\t', "    ");
+            if number == preview.line {
+                Line::from(vec![number_span, bold(text)])
+            } else {
+                Line::from(vec![number_span, Span::raw(text)])
+            }
+        })
+        .collect::<Vec<_>>();
+    let title = format!("Source ({})", preview.path.display());
+    Paragraph::new(lines).block(styles.border_block().title(title))
+}
+
 impl HelpText for TaskView {
     fn render_help_content(&self, styles: &view::Styles) -> Paragraph<'static> {
         controls_paragraph(view_controls(), styles)
     }
+
+    fn hints(&self) -> Vec<Hint> {
+        vec![Hint::enabled("esc", "return to task list")]
+    }
 }
 
+/// Below this width, the task and waker boxes are stacked vertically.
+const STACK_PANES_BELOW: u16 = 100;
+
+/// The number of threads a task was polled on that are listed individually.
+const TOP_POLL_THREADS: usize = 4;
+
+/// The number of lines of source shown either side of a task's spawn location.
+const SOURCE_CONTEXT: u32 = 3;
+
 const fn view_controls() -> &'static [ControlDisplay] {
-    &[ControlDisplay {
-        action: "return to task list",
-        keys: &[KeyDisplay {
-            base: "esc",
-            utf8: Some("\u{238B} esc"),
-        }],
-    }]
+    &[
+        ControlDisplay {
+            action: "return to task list",
+            keys: &[KeyDisplay {
+                base: "esc",
+                utf8: Some("\u{238B} esc"),
+            }],
+        },
+        ControlDisplay {
+            action: "histogram log/linear",
+            keys: &[KeyDisplay {
+                base: "l",
+                utf8: None,
+            }],
+        },
+        ControlDisplay {
+            action: "histogram bars",
+            keys: &[KeyDisplay {
+                base: "+, -",
+                utf8: None,
+            }],
+        },
+        ControlDisplay {
+            action: "recent polls/scheduled histogram",
+            keys: &[KeyDisplay {
+                base: "tab",
+                utf8: Some("\u{21E5} tab"),
+            }],
+        },
+        ControlDisplay {
+            action: "histogram zoom",
+            keys: &[KeyDisplay {
+                base: "z",
+                utf8: None,
+            }],
+        },
+        ControlDisplay {
+            action: "show source",
+            keys: &[KeyDisplay {
+                base: "v",
+                utf8: None,
+            }],
+        },
+    ]
 }
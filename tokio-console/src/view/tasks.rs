@@ -1,36 +1,115 @@
 use crate::{
+    intern::InternedStr,
     state::{
-        tasks::{SortBy, Task, TaskState},
+        budget::BudgetStatus,
+        tasks::{
+            GroupStats, RateWindow, ScheduledMetric, SortBy, SpawnHistory, Task, TaskState,
+            TasksState,
+        },
         State,
     },
     view::{
         self, bold,
-        controls::Controls,
-        table::{view_controls, TableList, TableListState},
-        DUR_LEN, DUR_TABLE_PRECISION,
+        controls::{ControlDisplay, Controls, KeyDisplay},
+        help::Hint,
+        table::{self, fields_text, Breakpoint, Columns, TableList, TableListState},
+        Role, DUR_TABLE_PRECISION,
     },
 };
+use once_cell::sync::OnceCell;
 use ratatui::{
     layout,
-    style::{self, Color, Style},
+    style::{self, Style},
+    symbols,
     text::{Line, Span, Text},
-    widgets::{self, Cell, ListItem, Row, Table},
+    widgets::{self, Cell, ListItem, Row, Sparkline, Table},
+};
+use std::{
+    collections::{BTreeSet, HashSet},
+    rc::Weak,
 };
 
 #[derive(Debug, Default)]
 pub(crate) struct TasksTable {}
 
-impl TableList<12> for TasksTable {
+/// Which kinds of tasks are shown in the tasks list.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum KindFilter {
+    #[default]
+    All,
+    /// Only tasks that run on the async workers.
+    Async,
+    /// Only tasks that run on the blocking pool.
+    Blocking,
+}
+
+/// Which runtime's tasks are shown in the tasks list, when the application
+/// runs more than one.
+///
+/// Tasks are labelled with the runtime of the thread they were spawned from,
+/// which isn't the runtime they run on if they were spawned through another
+/// runtime's `Handle`.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub(crate) struct RuntimeFilter {
+    /// The label of the runtime whose tasks are shown, or `None` to show the
+    /// tasks of every runtime.
+    runtime: Option<String>,
+}
+
+/// How the tasks list groups tasks by the group the application assigned them
+/// to.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Grouping {
+    /// Whether tasks in the same group are listed together.
+    enabled: bool,
+    /// Groups whose tasks are hidden from the list.
+    collapsed: HashSet<InternedStr>,
+}
+
+impl view::SortBy for SortBy {
+    fn as_column(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl TableList<23> for TasksTable {
     type Row = Task;
     type Sort = SortBy;
-    type Context = ();
+    type Context = (
+        KindFilter,
+        RuntimeFilter,
+        Grouping,
+        ScheduledMetric,
+        RateWindow,
+    );
 
-    const HEADER: &'static [&'static str; 12] = &[
-        "Warn", "ID", "State", "Name", "Total", "Busy", "Sched", "Idle", "Polls", "Kind",
-        "Location", "Fields",
+    const HEADER: &'static [&'static str; 23] = &[
+        "Pin",
+        "Warn",
+        "ID",
+        "State",
+        "Name",
+        "Total",
+        "Busy",
+        "Sched",
+        "Idle",
+        "Polls",
+        "Kind",
+        "Group",
+        "Runtime",
+        "Location",
+        "Last Poll",
+        "Last Wake",
+        "Polls/s",
+        "Wakes/s",
+        "IO Polls",
+        "IO Ready",
+        "Busy%",
+        "Share",
+        "Fields",
     ];
 
-    const WIDTHS: &'static [usize; 12] = &[
+    const WIDTHS: &'static [usize; 23] = &[
         Self::HEADER[0].len() + 1,
         Self::HEADER[1].len() + 1,
         Self::HEADER[2].len() + 1,
@@ -43,17 +122,164 @@ impl TableList<12> for TasksTable {
         Self::HEADER[9].len() + 1,
         Self::HEADER[10].len() + 1,
         Self::HEADER[11].len() + 1,
+        Self::HEADER[12].len() + 1,
+        Self::HEADER[13].len() + 1,
+        Self::HEADER[14].len() + 1,
+        Self::HEADER[15].len() + 1,
+        Self::HEADER[16].len() + 1,
+        Self::HEADER[17].len() + 1,
+        Self::HEADER[18].len() + 1,
+        Self::HEADER[19].len() + 1,
+        Self::HEADER[20].len() + 1,
+        Self::HEADER[21].len() + 1,
+        Self::HEADER[22].len() + 1,
+    ];
+
+    const BREAKPOINTS: &'static [Breakpoint] = &[
+        // IO Polls, IO Ready
+        Breakpoint {
+            below: 180,
+            hide: &[18, 19],
+        },
+        // Group, Runtime, Last Poll, Last Wake, Polls/s, Wakes/s, Busy%, Share
+        Breakpoint {
+            below: 160,
+            hide: &[11, 12, 14, 15, 16, 17, 20, 21],
+        },
+        // Sched, Kind, Location
+        Breakpoint {
+            below: 120,
+            hide: &[7, 10, 13],
+        },
+        // Total, Idle, Polls
+        Breakpoint {
+            below: 80,
+            hide: &[5, 8, 9],
+        },
     ];
 
+    const COMPACT_BELOW: u16 = 120;
+
+    const EXPORTABLE: bool = true;
+
+    fn view_controls() -> &'static [ControlDisplay] {
+        static VIEW_CONTROLS: OnceCell<Vec<ControlDisplay>> = OnceCell::new();
+
+        VIEW_CONTROLS.get_or_init(|| {
+            let tasks_controls = &[
+                ControlDisplay {
+                    action: "filter kind (all/async/blocking)",
+                    keys: &[KeyDisplay {
+                        base: "b",
+                        utf8: None,
+                    }],
+                },
+                ControlDisplay {
+                    action: "filter runtime (of the thread that spawned each task)",
+                    keys: &[KeyDisplay {
+                        base: "R",
+                        utf8: None,
+                    }],
+                },
+                ControlDisplay {
+                    action: "scheduled time (total/mean/p95)",
+                    keys: &[KeyDisplay {
+                        base: "m",
+                        utf8: None,
+                    }],
+                },
+                ControlDisplay {
+                    action: "rate window (1s/10s/1m)",
+                    keys: &[KeyDisplay {
+                        base: "w",
+                        utf8: None,
+                    }],
+                },
+                ControlDisplay {
+                    action: "watch task (highlight when it ends)",
+                    keys: &[KeyDisplay {
+                        base: "a",
+                        utf8: None,
+                    }],
+                },
+                ControlDisplay {
+                    action: "pin task (keep after it ends)",
+                    keys: &[KeyDisplay {
+                        base: "P",
+                        utf8: None,
+                    }],
+                },
+                ControlDisplay {
+                    action: "group tasks",
+                    keys: &[KeyDisplay {
+                        base: "p",
+                        utf8: None,
+                    }],
+                },
+                ControlDisplay {
+                    action: "collapse group / expand all",
+                    keys: &[
+                        KeyDisplay {
+                            base: "c",
+                            utf8: None,
+                        },
+                        KeyDisplay {
+                            base: "C",
+                            utf8: None,
+                        },
+                    ],
+                },
+            ];
+            [
+                table::view_controls(),
+                table::export_controls(),
+                tasks_controls,
+            ]
+            .concat()
+        })
+    }
+
+    fn export_row(task: &Task, now: std::time::SystemTime) -> [String; 23] {
+        [
+            task.is_pinned().to_string(),
+            task.warnings().len().to_string(),
+            task.id_str().to_owned(),
+            task.state().as_str().to_owned(),
+            task.name().unwrap_or("").to_owned(),
+            format!("{:?}", task.total(now)),
+            format!("{:?}", task.busy(now)),
+            format!("{:?}", task.scheduled(now)),
+            format!("{:?}", task.idle(now)),
+            task.total_polls().to_string(),
+            task.kind().to_owned(),
+            task.group().map_or("", AsRef::as_ref).to_owned(),
+            task.runtime().unwrap_or("").to_owned(),
+            task.location().to_owned(),
+            task.last_polled()
+                .and_then(|time| now.duration_since(time).ok())
+                .map_or_else(String::new, |ago| format!("{:?}", ago)),
+            task.since_wake(now)
+                .map_or_else(String::new, |ago| format!("{:?}", ago)),
+            // Exports don't know the selected window, so use the default.
+            format!("{:.1}", task.poll_rate(RateWindow::default(), now)),
+            format!("{:.1}", task.wake_rate(RateWindow::default(), now)),
+            task.io_polls().polls().to_string(),
+            task.io_polls().ready().to_string(),
+            format!("{:.1}", task.busy_percent(now)),
+            format!("{:.1}", task.runtime_share(RateWindow::default())),
+            fields_text(task.fields()),
+        ]
+    }
+
     fn render(
-        table_list_state: &mut TableListState<Self, 12>,
+        table_list_state: &mut TableListState<Self, 23>,
         styles: &view::Styles,
         frame: &mut ratatui::terminal::Frame,
         area: layout::Rect,
         state: &mut State,
-        _: Self::Context,
+        (kind_filter, runtime_filter, grouping, sched_metric, rate_window): Self::Context,
     ) {
-        let state_len: u16 = Self::WIDTHS[2] as u16;
+        let state_len: u16 = Self::WIDTHS[3] as u16;
         let now = if let Some(now) = state.last_updated_at() {
             now
         } else {
@@ -61,53 +287,226 @@ impl TableList<12> for TasksTable {
             return;
         };
 
+        let columns = Columns::for_width::<Self>(area.width);
+        let styles = &if columns.compact() {
+            styles.compact_durations()
+        } else {
+            styles.clone()
+        };
+
         table_list_state
             .sorted_items
             .extend(state.tasks_state_mut().take_new_tasks());
 
+        // Move the tasks that don't match the kind or runtime filter, or whose
+        // group is collapsed, out of the list, and bring back the ones that are
+        // shown again.
+        if kind_filter != KindFilter::All
+            || runtime_filter.runtime.is_some()
+            || grouping.hides_any()
+            || !table_list_state.hidden_items.is_empty()
+        {
+            let items = table_list_state
+                .sorted_items
+                .drain(..)
+                .chain(table_list_state.hidden_items.drain(..))
+                .collect::<Vec<_>>();
+            let (shown, hidden) = items.into_iter().partition(|task| {
+                task.upgrade().is_some_and(|task| {
+                    let task = task.borrow();
+                    kind_filter.matches(&task)
+                        && runtime_filter.matches(&task)
+                        && !grouping.hides(&task)
+                })
+            });
+            table_list_state.sorted_items = shown;
+            table_list_state.hidden_items = hidden;
+        }
+
         table_list_state
-            .sort_by
-            .sort(now, &mut table_list_state.sorted_items);
+            .sort_items(|sort_by, tasks| sort_by.sort(now, sched_metric, rate_window, tasks));
+        if grouping.enabled {
+            // Keep the selected sort order within each group, with ungrouped
+            // tasks last.
+            table_list_state.sorted_items.sort_by_cached_key(|task| {
+                let group = task.upgrade().and_then(|t| t.borrow().group().cloned());
+                (group.is_none(), group)
+            });
+        }
+        // Watched tasks which have ended are pinned to the top of the table.
+        let watched = state.tasks_state().watched().clone();
+        if !watched.is_empty() {
+            let descending = table_list_state.sort_descending;
+            table_list_state.sorted_items.sort_by_cached_key(|task| {
+                let pinned = task.upgrade().is_some_and(|task| {
+                    let task = task.borrow();
+                    task.has_ended() && watched.contains(&task.id())
+                });
+                // The rows are displayed in reverse unless the table is
+                // sorted descending.
+                pinned != descending
+            });
+        }
 
         let dur_cell = |dur: std::time::Duration| -> Cell<'static> {
-            Cell::from(styles.time_units(dur, DUR_TABLE_PRECISION, Some(DUR_LEN)))
+            Cell::from(styles.time_units(dur, DUR_TABLE_PRECISION, Some(styles.duration_width())))
+        };
+        // How long ago something happened, or nothing if it never did.
+        let ago_cell = |time: Option<std::time::SystemTime>| -> Cell<'static> {
+            time.and_then(|time| now.duration_since(time).ok())
+                .map_or_else(|| Cell::from(""), dur_cell)
+        };
+        // Rates are shown with a decimal place only while it's significant.
+        let rate_cell = |rate: f64| -> Cell<'static> {
+            if rate < 100.0 {
+                Cell::from(format!("{:.1}", rate))
+            } else {
+                Cell::from(format!("{:.0}", rate))
+            }
+        };
+        let rate_width = |header: &str| header.len() as u16 + 1;
+        let percent_cell =
+            |percent: f64| -> Cell<'static> { Cell::from(format!("{:.1}%", percent)) };
+        // Tasks which never polled an I/O resource leave the I/O columns
+        // blank, so the ones that did stand out.
+        let io_cell = |count: u64, io_polls: u64| -> Cell<'static> {
+            if io_polls == 0 {
+                Cell::from("")
+            } else {
+                Cell::from(count.to_string())
+            }
         };
 
         // Start out wide enough to display the column headers...
-        let mut warn_width = view::Width::new(Self::WIDTHS[0] as u16);
-        let mut id_width = view::Width::new(Self::WIDTHS[1] as u16);
-        let mut name_width = view::Width::new(Self::WIDTHS[3] as u16);
-        let mut polls_width = view::Width::new(Self::WIDTHS[7] as u16);
-        let mut kind_width = view::Width::new(Self::WIDTHS[8] as u16);
-        let mut location_width = view::Width::new(Self::WIDTHS[9] as u16);
+        let mut warn_width = table_list_state.column_width(1);
+        let mut id_width = table_list_state.column_width(2);
+        let mut name_width = table_list_state.column_width(4);
+        let mut polls_width = table_list_state.column_width(8);
+        let mut kind_width = table_list_state.column_width(9);
+        let mut group_width = table_list_state.column_width(11);
+        let mut runtime_width = table_list_state.column_width(12);
+        let mut location_width = table_list_state.column_width(10);
+
+        let since = |time: std::time::SystemTime| {
+            styles.time_units(
+                now.duration_since(time).unwrap_or_default(),
+                view::DUR_LIST_PRECISION,
+                None,
+            )
+        };
+        let warnings = state
+            .tasks_state()
+            .warning_history()
+            .records()
+            .into_iter()
+            .map(|(summary, location, record)| {
+                ListItem::new(Text::from(Line::from(vec![
+                    styles.warning_wide(),
+                    // TODO(eliza): it would be nice to handle singular vs plural...
+                    Span::from(format!(
+                        "{} {} at {} (first seen ",
+                        record.count(),
+                        summary,
+                        location
+                    )),
+                    since(record.first_seen()),
+                    Span::from(" ago, last seen "),
+                    since(record.last_seen()),
+                    Span::from(" ago)"),
+                ])))
+            })
+            .collect::<Vec<_>>();
+
+        let layout = layout::Layout::default()
+            .direction(layout::Direction::Vertical)
+            .margin(0);
+
+        let groups = if grouping.enabled {
+            state
+                .tasks_state()
+                .groups(now)
+                .iter()
+                .map(|group| grouping.render_group(group, styles))
+                .collect::<Vec<_>>()
+        } else {
+            Vec::new()
+        };
+
+        let controls = Controls::new(Self::view_controls(), &area, styles);
 
+        // Only show the most recently seen warnings, and the first few groups,
+        // so that a long session doesn't push the task list off the screen.
+        const MAX_WARNINGS: usize = 5;
+        const MAX_GROUPS: usize = 5;
+        let mut constraints = vec![
+            layout::Constraint::Length(controls.height()),
+            layout::Constraint::Length(1),
+        ];
+        if !warnings.is_empty() {
+            constraints.push(layout::Constraint::Length(
+                warnings.len().min(MAX_WARNINGS) as u16 + 2,
+            ));
+        }
+        if !groups.is_empty() {
+            constraints.push(layout::Constraint::Length(
+                groups.len().min(MAX_GROUPS) as u16 + 2,
+            ));
+        }
+        constraints.push(layout::Constraint::Max(area.height));
+        let chunks = layout.constraints(constraints).split(area);
+        let (controls_area, spawns_area, tasks_area) =
+            (chunks[0], chunks[1], chunks[chunks.len() - 1]);
+        let mut panels = chunks[2..chunks.len() - 1].iter().copied();
+        let warnings_area = if warnings.is_empty() {
+            None
+        } else {
+            panels.next()
+        };
+        let groups_area = panels.next();
+
+        // Count task states
         let mut num_idle = 0;
         let mut num_running = 0;
+        let mut num_cancelled = 0;
+        let mut num_panicked = 0;
+        for task in table_list_state
+            .sorted_items
+            .iter()
+            .filter_map(Weak::upgrade)
+        {
+            match task.borrow().state() {
+                TaskState::Running => num_running += 1,
+                TaskState::Idle => num_idle += 1,
+                TaskState::Cancelled => num_cancelled += 1,
+                TaskState::Panicked => num_panicked += 1,
+                _ => {}
+            }
+        }
 
+        // Only format the tasks that will be visible, as formatting every
+        // task each frame is slow when there are a very large number of them.
+        // The table's borders and header take up three lines.
+        let window = table_list_state.visible_rows(tasks_area.height.saturating_sub(3) as usize);
+
+        let highlight_changes = table_list_state.highlight_changes;
         let rows = {
             let id_width = &mut id_width;
             let kind_width = &mut kind_width;
+            let group_width = &mut group_width;
+            let runtime_width = &mut runtime_width;
             let location_width = &mut location_width;
             let name_width = &mut name_width;
             let polls_width = &mut polls_width;
             let warn_width = &mut warn_width;
-            let num_running = &mut num_running;
-            let num_idle = &mut num_idle;
 
             table_list_state
-                .sorted_items
-                .iter()
+                .window_items(&window)
+                .into_iter()
                 .filter_map(move |task| {
                     let task = task.upgrade()?;
                     let task = task.borrow();
                     let state = task.state();
 
-                    // Count task states
-                    match state {
-                        TaskState::Running => *num_running += 1,
-                        TaskState::Idle => *num_idle += 1,
-                        _ => {}
-                    };
                     let n_warnings = task.warnings().len();
                     let warnings = if n_warnings > 0 {
                         let n_warnings = n_warnings.to_string();
@@ -120,70 +519,135 @@ impl TableList<12> for TasksTable {
                         Cell::from("")
                     };
 
-                    let mut row = Row::new(vec![
-                        warnings,
-                        Cell::from(id_width.update_str(format!(
-                            "{:>width$}",
-                            task.id_str(),
-                            width = id_width.chars() as usize
-                        ))),
-                        Cell::from(task.state().render(styles)),
-                        Cell::from(name_width.update_str(task.name().unwrap_or("")).to_string()),
-                        dur_cell(task.total(now)),
-                        dur_cell(task.busy(now)),
-                        dur_cell(task.scheduled(now)),
-                        dur_cell(task.idle(now)),
-                        Cell::from(polls_width.update_str(task.total_polls().to_string())),
-                        Cell::from(kind_width.update_str(task.kind()).to_owned()),
-                        Cell::from(location_width.update_str(task.location()).to_owned()),
-                        Cell::from(Line::from(
-                            task.formatted_fields()
-                                .iter()
-                                .flatten()
-                                .cloned()
-                                .collect::<Vec<_>>(),
-                        )),
-                    ]);
-                    if state == TaskState::Completed {
-                        row = row.style(styles.terminated());
+                    // Tasks with a latency budget are colored by whether
+                    // they are within it, once that isn't just estimated.
+                    let name =
+                        Cell::from(name_width.update_str(task.name().unwrap_or("")).to_string());
+                    let name = match task.budget_status() {
+                        Some((status, false)) => name.style(styles.role(budget_role(status))),
+                        _ => name,
+                    };
+
+                    let pinned = if task.is_pinned() {
+                        Cell::from(styles.if_utf8("\u{1F4CC}", "*").to_owned())
+                    } else {
+                        Cell::from("")
+                    };
+
+                    let mut row = Row::new(
+                        columns.select([
+                            pinned,
+                            warnings,
+                            Cell::from(id_width.update_str(format!(
+                                "{:>width$}",
+                                task.id_str(),
+                                width = id_width.chars() as usize
+                            ))),
+                            Cell::from(render_state(task.state(), styles)),
+                            name,
+                            dur_cell(task.total(now)),
+                            dur_cell(task.busy(now)),
+                            task.scheduled_by(sched_metric, now)
+                                .map_or_else(|| Cell::from(""), dur_cell),
+                            dur_cell(task.idle(now)),
+                            Cell::from(polls_width.update_str(task.total_polls().to_string())),
+                            Cell::from(kind_width.update_str(task.kind()).to_owned()),
+                            Cell::from(
+                                group_width
+                                    .update_str(task.group().map_or("", AsRef::as_ref))
+                                    .to_owned(),
+                            ),
+                            Cell::from(
+                                runtime_width
+                                    .update_str(task.runtime().unwrap_or(""))
+                                    .to_owned(),
+                            ),
+                            Cell::from(location_width.update_str(task.location()).to_owned()),
+                            ago_cell(task.last_polled()),
+                            ago_cell(task.last_wake()),
+                            rate_cell(task.poll_rate(rate_window, now)),
+                            rate_cell(task.wake_rate(rate_window, now)),
+                            io_cell(task.io_polls().polls(), task.io_polls().polls()),
+                            io_cell(task.io_polls().ready(), task.io_polls().polls()),
+                            percent_cell(task.busy_percent(now)),
+                            percent_cell(task.runtime_share(rate_window)),
+                            Cell::from(Line::from(
+                                view::fields(styles, task.fields())
+                                    .into_iter()
+                                    .flatten()
+                                    .collect::<Vec<_>>(),
+                            )),
+                        ]),
+                    );
+                    if let Some(highlight) = styles.highlight(task.name()) {
+                        row = row.style(highlight);
+                    }
+                    match state {
+                        TaskState::Completed => row = row.style(styles.terminated()),
+                        TaskState::Cancelled => row = row.style(styles.cancelled()),
+                        TaskState::Panicked => row = row.style(styles.panicked()),
+                        _ => {}
+                    }
+                    if watched.contains(&task.id()) {
+                        row = row.style(styles.watched(task.has_ended()));
+                    }
+                    if highlight_changes && task.changed_in_update(now) {
+                        row = row.style(styles.changed());
                     }
                     Some(row)
                 })
         };
 
-        let header_style = if styles.color(Color::Cyan).is_some() {
-            Style::default()
-        } else {
-            Style::default().add_modifier(style::Modifier::REVERSED)
-        };
-        let header_style = header_style.add_modifier(style::Modifier::BOLD);
+        let header_style = styles.table_header();
 
-        let header = Row::new(Self::HEADER.iter().enumerate().map(|(idx, &value)| {
-            if idx == table_list_state.selected_column {
-                if table_list_state.sort_descending {
-                    Cell::from(styles.ascending(value))
+        let header = Row::new(columns.select(Self::HEADER.iter().enumerate().map(
+            |(idx, &value)| {
+                let value = if idx == SortBy::Scheduled as usize {
+                    sched_metric.header()
+                } else if idx == SortBy::PollRate as usize {
+                    rate_window.headers().0
+                } else if idx == SortBy::WakeRate as usize {
+                    rate_window.headers().1
+                } else if idx == SortBy::RuntimeShare as usize {
+                    rate_window.share_header()
+                } else {
+                    value
+                };
+                if idx == table_list_state.selected_column {
+                    if table_list_state.sort_descending {
+                        Cell::from(styles.ascending(value))
+                    } else {
+                        Cell::from(styles.descending(value))
+                    }
                 } else {
-                    Cell::from(styles.descending(value))
+                    Cell::from(value)
                 }
-            } else {
-                Cell::from(value)
-            }
-        }))
+            },
+        )))
         .height(1)
         .style(header_style);
 
-        let table = if table_list_state.sort_descending {
-            Table::default().rows(rows)
-        } else {
-            Table::default().rows(rows.rev())
-        };
+        let table = Table::default().rows(rows);
 
+        let kind = match kind_filter {
+            KindFilter::All => "Tasks",
+            KindFilter::Async => "Async Tasks",
+            KindFilter::Blocking => "Blocking Tasks",
+        };
+        let title = match runtime_filter.runtime {
+            Some(ref runtime) => format!("{} on {} ({}) ", kind, runtime, table_list_state.len()),
+            None => format!("{} ({}) ", kind, table_list_state.len()),
+        };
         let block = styles.border_block().title(vec![
-            bold(format!("Tasks ({}) ", table_list_state.len())),
-            TaskState::Running.render(styles),
+            bold(title),
+            render_state(TaskState::Running, styles),
             Span::from(format!(" Running ({}) ", num_running)),
-            TaskState::Idle.render(styles),
-            Span::from(format!(" Idle ({})", num_idle)),
+            render_state(TaskState::Idle, styles),
+            Span::from(format!(" Idle ({}) ", num_idle)),
+            render_state(TaskState::Cancelled, styles),
+            Span::from(format!(" Cancelled ({}) ", num_cancelled)),
+            render_state(TaskState::Panicked, styles),
+            Span::from(format!(" Panicked ({})", num_panicked)),
         ]);
 
         /* TODO: use this to adjust the max size of name and kind columns...
@@ -191,55 +655,12 @@ impl TableList<12> for TasksTable {
         let fixed_col_width = id_width.chars()
             + STATE_LEN
             + name_width.chars()
-            + DUR_LEN as u16
-            + DUR_LEN as u16
-            + DUR_LEN as u16
+            + styles.duration_width() as u16
+            + styles.duration_width() as u16
+            + styles.duration_width() as u16
             + POLLS_LEN as u16
             + kind_width.chars();
         */
-        let warnings = state
-            .tasks_state()
-            .warnings()
-            .map(|warning| {
-                ListItem::new(Text::from(Line::from(vec![
-                    styles.warning_wide(),
-                    // TODO(eliza): it would be nice to handle singular vs plural...
-                    Span::from(format!("{} {}", warning.count(), warning.summary())),
-                ])))
-            })
-            .collect::<Vec<_>>();
-
-        let layout = layout::Layout::default()
-            .direction(layout::Direction::Vertical)
-            .margin(0);
-
-        let controls = Controls::new(view_controls(), &area, styles);
-
-        let (controls_area, tasks_area, warnings_area) = if warnings.is_empty() {
-            let chunks = layout
-                .constraints(
-                    [
-                        layout::Constraint::Length(controls.height()),
-                        layout::Constraint::Max(area.height),
-                    ]
-                    .as_ref(),
-                )
-                .split(area);
-            (chunks[0], chunks[1], None)
-        } else {
-            let warnings_height = warnings.len() as u16 + 2;
-            let chunks = layout
-                .constraints(
-                    [
-                        layout::Constraint::Length(controls.height()),
-                        layout::Constraint::Length(warnings_height),
-                        layout::Constraint::Max(area.height),
-                    ]
-                    .as_ref(),
-                )
-                .split(area);
-            (chunks[0], chunks[2], Some(chunks[1]))
-        };
         // Fill all remaining characters in the frame with the task's fields.
         //
         // Ideally we'd use Min(0), and it would fill the rest of the space. But that is broken
@@ -247,20 +668,45 @@ impl TableList<12> for TasksTable {
         //
         // See https://github.com/fdehau/tui-rs/issues/525
         let fields_width = layout::Constraint::Percentage(100);
-        let widths = &[
+        for (column, width) in [
+            (1, &warn_width),
+            (2, &id_width),
+            (4, &name_width),
+            (8, &polls_width),
+            (9, &kind_width),
+            (10, &location_width),
+            (11, &group_width),
+            (12, &runtime_width),
+        ] {
+            table_list_state.remember_width(column, width);
+        }
+        let widths = columns.select([
+            layout::Constraint::Length(Self::WIDTHS[0] as u16),
             warn_width.constraint(),
             id_width.constraint(),
             layout::Constraint::Length(state_len),
             name_width.constraint(),
-            layout::Constraint::Length(DUR_LEN as u16),
-            layout::Constraint::Length(DUR_LEN as u16),
-            layout::Constraint::Length(DUR_LEN as u16),
-            layout::Constraint::Length(DUR_LEN as u16),
+            layout::Constraint::Length(styles.duration_width() as u16),
+            layout::Constraint::Length(styles.duration_width() as u16),
+            layout::Constraint::Length(
+                styles.duration_width().max(sched_metric.header().len()) as u16
+            ),
+            layout::Constraint::Length(styles.duration_width() as u16),
             polls_width.constraint(),
             kind_width.constraint(),
+            group_width.constraint(),
+            runtime_width.constraint(),
             location_width.constraint(),
+            layout::Constraint::Length(styles.duration_width() as u16),
+            layout::Constraint::Length(styles.duration_width() as u16),
+            layout::Constraint::Length(rate_width(rate_window.headers().0)),
+            layout::Constraint::Length(rate_width(rate_window.headers().1)),
+            layout::Constraint::Length(Self::WIDTHS[18] as u16),
+            layout::Constraint::Length(Self::WIDTHS[19] as u16),
+            layout::Constraint::Length(Self::WIDTHS[20] as u16),
+            layout::Constraint::Length(rate_width(rate_window.share_header())),
             fields_width,
-        ];
+        ]);
 
         let table = table
             .header(header)
@@ -269,18 +715,268 @@ impl TableList<12> for TasksTable {
             .highlight_symbol(view::TABLE_HIGHLIGHT_SYMBOL)
             .highlight_style(Style::default().add_modifier(style::Modifier::BOLD));
 
-        frame.render_stateful_widget(table, tasks_area, &mut table_list_state.table_state);
+        table_list_state.render_window(frame, tasks_area, table, &window);
         frame.render_widget(controls.into_widget(), controls_area);
+        render_spawn_history(
+            frame,
+            spawns_area,
+            styles,
+            state.tasks_state().spawn_history(),
+        );
 
         if let Some(area) = warnings_area {
+            let active = state
+                .tasks_state()
+                .warnings()
+                .map(|warning| warning.count())
+                .sum::<usize>();
+            let block = styles.border_block().title(Line::from(vec![bold(format!(
+                "Warnings ({} active, {} seen this session)",
+                active,
+                warnings.len()
+            ))]));
+            frame.render_widget(widgets::List::new(warnings).block(block), area);
+        }
+
+        if let Some(area) = groups_area {
             let block = styles
                 .border_block()
-                .title(Line::from(vec![bold("Warnings")]));
-            frame.render_widget(widgets::List::new(warnings).block(block), area);
+                .title(Line::from(vec![bold(format!("Groups ({})", groups.len()))]));
+            frame.render_widget(widgets::List::new(groups).block(block), area);
         }
 
         table_list_state
             .sorted_items
             .retain(|t| t.upgrade().is_some());
+        table_list_state
+            .hidden_items
+            .retain(|t| t.upgrade().is_some());
+    }
+}
+
+impl Grouping {
+    pub(crate) fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    /// Hides the tasks in `task`'s group, if the list is grouped.
+    pub(crate) fn collapse(&mut self, task: &Task) {
+        if let (true, Some(group)) = (self.enabled, task.group()) {
+            self.collapsed.insert(group.clone());
+        }
+    }
+
+    pub(crate) fn expand_all(&mut self) {
+        self.collapsed.clear();
+    }
+
+    /// Returns hints about the grouping actions which are available.
+    pub(crate) fn hints(&self) -> Vec<Hint> {
+        let mut hints = vec![
+            Hint::enabled(
+                "p",
+                if self.enabled {
+                    "ungroup tasks"
+                } else {
+                    "group tasks"
+                },
+            ),
+            Hint::when(
+                self.enabled,
+                "c",
+                "collapse group",
+                "press p to group tasks first",
+            ),
+        ];
+        if !self.collapsed.is_empty() {
+            hints.push(Hint::enabled("C", "expand all groups"));
+        }
+        hints
+    }
+
+    fn hides_any(&self) -> bool {
+        self.enabled && !self.collapsed.is_empty()
+    }
+
+    fn hides(&self, task: &Task) -> bool {
+        self.enabled
+            && task
+                .group()
+                .is_some_and(|group| self.collapsed.contains(group))
+    }
+
+    fn render_group(&self, group: &GroupStats, styles: &view::Styles) -> ListItem<'static> {
+        let collapsed = self.collapsed.contains(&group.name);
+        let marker = if collapsed {
+            styles.if_utf8("\u{25B8} ", "+ ")
+        } else {
+            styles.if_utf8("\u{25BE} ", "- ")
+        };
+        let mut line = vec![
+            Span::from(marker.to_owned()),
+            bold(group.name.to_string()),
+            Span::from(format!(
+                ": {} tasks ({} running, {} idle), {} polls, busy ",
+                group.tasks, group.running, group.idle, group.polls
+            )),
+            styles.time_units(group.busy, view::DUR_LIST_PRECISION, None),
+        ];
+        if collapsed {
+            line.push(Span::from(" (collapsed)"));
+        }
+        ListItem::new(Line::from(line))
     }
 }
+
+impl KindFilter {
+    /// Returns the next filter to switch to.
+    pub(crate) fn next(self) -> Self {
+        match self {
+            Self::All => Self::Async,
+            Self::Async => Self::Blocking,
+            Self::Blocking => Self::All,
+        }
+    }
+
+    fn matches(self, task: &Task) -> bool {
+        match self {
+            Self::All => true,
+            Self::Async => !task.is_blocking_pool(),
+            Self::Blocking => task.is_blocking_pool(),
+        }
+    }
+}
+
+impl RuntimeFilter {
+    /// Returns the next filter to switch to, which shows the tasks of the
+    /// next runtime any of `tasks` were spawned on, in order of their labels,
+    /// and then the tasks of every runtime again.
+    pub(crate) fn next(&self, tasks: &TasksState) -> Self {
+        let runtimes = tasks
+            .tasks()
+            .filter_map(|task| task.borrow().runtime().map(str::to_owned))
+            .collect::<BTreeSet<_>>();
+        let runtime = match self.runtime {
+            Some(ref current) => runtimes.into_iter().find(|runtime| runtime > current),
+            None => runtimes.into_iter().next(),
+        };
+        Self { runtime }
+    }
+
+    fn matches(&self, task: &Task) -> bool {
+        match self.runtime {
+            Some(ref runtime) => task.runtime() == Some(runtime.as_str()),
+            None => true,
+        }
+    }
+}
+
+/// Renders sparklines of the number of tasks spawned and dropped in each
+/// bucket of the spawn history, side by side.
+/// Returns the icon, or the abbreviation if UTF-8 is disabled, for a task's
+/// state.
+pub(crate) fn render_state(state: TaskState, styles: &view::Styles) -> Span<'static> {
+    const RUNNING_UTF8: &str = "\u{25B6}";
+    const SCHEDULED_UTF8: &str = "\u{23EB}";
+    const IDLE_UTF8: &str = "\u{23F8}";
+    const COMPLETED_UTF8: &str = "\u{23F9}";
+    const CANCELLED_UTF8: &str = "\u{2716}";
+    const PANICKED_UTF8: &str = "\u{2620}";
+    match state {
+        TaskState::Running => Span::styled(
+            styles.if_utf8(RUNNING_UTF8, "BUSY"),
+            styles.role(Role::Good),
+        ),
+        TaskState::Scheduled => Span::raw(styles.if_utf8(SCHEDULED_UTF8, "SCHED")),
+        TaskState::Idle => Span::raw(styles.if_utf8(IDLE_UTF8, "IDLE")),
+        TaskState::Completed => Span::raw(styles.if_utf8(COMPLETED_UTF8, "DONE")),
+        TaskState::Cancelled => Span::styled(
+            styles.if_utf8(CANCELLED_UTF8, "ABRT"),
+            styles.role(Role::Bad),
+        ),
+        TaskState::Panicked => Span::styled(
+            styles.if_utf8(PANICKED_UTF8, "PANC"),
+            styles.role(Role::Bad).add_modifier(style::Modifier::BOLD),
+        ),
+    }
+}
+
+/// Returns the color role to display a task with a latency budget in.
+fn budget_role(status: BudgetStatus) -> Role {
+    match status {
+        BudgetStatus::Met => Role::Good,
+        BudgetStatus::Near => Role::Caution,
+        BudgetStatus::Exceeded => Role::Bad,
+    }
+}
+
+fn render_spawn_history(
+    frame: &mut ratatui::terminal::Frame,
+    area: layout::Rect,
+    styles: &view::Styles,
+    history: &SpawnHistory,
+) {
+    let bucket_secs = SpawnHistory::BUCKET_WIDTH.as_secs();
+    let halves = layout::Layout::default()
+        .direction(layout::Direction::Horizontal)
+        .constraints(
+            [
+                layout::Constraint::Ratio(1, 2),
+                layout::Constraint::Ratio(1, 2),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+
+    let series = [
+        ("spawned", history.spawned().collect::<Vec<_>>(), Role::Good),
+        ("dropped", history.dropped().collect::<Vec<_>>(), Role::Bad),
+    ];
+    for ((name, data, role), area) in series.into_iter().zip(halves.iter()) {
+        let label = Line::from(vec![
+            bold(name),
+            Span::from(format!(
+                "/{}s: {:<4} ",
+                bucket_secs,
+                data.last().copied().unwrap_or(0)
+            )),
+        ]);
+        let chunks = layout::Layout::default()
+            .direction(layout::Direction::Horizontal)
+            .constraints(
+                [
+                    layout::Constraint::Length(label.width() as u16),
+                    layout::Constraint::Min(0),
+                ]
+                .as_ref(),
+            )
+            .split(*area);
+
+        // Only show as many of the most recent buckets as will fit.
+        let shown = &data[data.len().saturating_sub(chunks[1].width as usize)..];
+        let bar_set = if styles.utf8 {
+            symbols::bar::NINE_LEVELS
+        } else {
+            ASCII_BARS
+        };
+        let sparkline = Sparkline::default()
+            .data(shown)
+            .bar_set(bar_set)
+            .style(styles.role(role));
+
+        frame.render_widget(widgets::Paragraph::new(label), chunks[0]);
+        frame.render_widget(sparkline, chunks[1]);
+    }
+}
+
+pub(super) const ASCII_BARS: symbols::bar::Set = symbols::bar::Set {
+    full: "#",
+    seven_eighths: "#",
+    three_quarters: "=",
+    five_eighths: "=",
+    half: "-",
+    three_eighths: "-",
+    one_quarter: "_",
+    one_eighth: "_",
+    empty: " ",
+};
@@ -0,0 +1,60 @@
+use ratatui::{
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Wrap},
+};
+
+use crate::{
+    state::State,
+    view::{self, Role},
+};
+
+/// Renders a popup listing the timers which are due to fire, soonest first,
+/// with the tasks waiting on them.
+pub(crate) fn render(styles: &view::Styles, frame: &mut ratatui::terminal::Frame, state: &State) {
+    let wakes = state.upcoming_wakes();
+
+    let area = view::help::popup_area(frame.size());
+    let block = styles.border_block().title("Upcoming Timer Wakes");
+    let inner = block.inner(area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+
+    let mut lines = Vec::new();
+    if wakes.is_empty() {
+        lines.push(Line::from("No timers are due to fire."));
+    } else {
+        lines.push(Line::from(format!(
+            "{} timers are due to fire:",
+            wakes.len()
+        )));
+    }
+    lines.push(Line::from(""));
+
+    for wake in &wakes {
+        lines.push(Line::from(vec![
+            Span::raw("  in "),
+            styles.time_units(wake.due_in, view::DUR_LIST_PRECISION, None),
+            Span::raw(": "),
+            view::bold(wake.timer.clone()),
+            Span::raw(" at "),
+            Span::from(wake.location.clone()),
+        ]));
+        lines.push(Line::from(match &wake.task {
+            Some(task) => vec![Span::raw("    waking "), view::bold(task.clone())],
+            None => vec![Span::styled(
+                "    not polled by a task yet",
+                styles.role(Role::Caution),
+            )],
+        }));
+    }
+
+    lines.extend([
+        Line::from(""),
+        Line::from(
+            "Deadlines come from the `duration` attribute of timer resources, such as \
+             Tokio's Sleep, which Interval and timeout also use. They are most precise \
+             when the subscriber keeps attribute history.",
+        ),
+    ]);
+    frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: true }), inner);
+}